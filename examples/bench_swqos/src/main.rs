@@ -0,0 +1,362 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use sol_trade_sdk::prelude::*;
+use sol_trade_sdk::swqos::confirmation::ConfirmationService;
+use sol_trade_sdk::swqos::region::cached_region;
+use sol_trade_sdk::swqos::timing::{percentile, recent_submit_timings};
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::message::{v0, VersionedMessage};
+use solana_sdk::transaction::VersionedTransaction;
+use solana_system_interface::instruction::transfer;
+
+const DEFAULT_SAMPLES: usize = 20;
+const DEFAULT_TIP_LAMPORTS: u64 = 100_000;
+const UNIT_LIMIT: u32 = 5_000;
+const UNIT_PRICE_MICRO_LAMPORTS: u64 = 100_000;
+const LANDING_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// One configured swqos client under test
+struct Provider {
+    name: String,
+    region: String,
+    client: Arc<SwqosClient>,
+    /// No other provider under test shares its `SwqosType`, so its recorded timing is its own
+    unique_type: bool,
+}
+
+/// One self-transfer sent through one provider
+#[derive(Debug, Clone, Serialize)]
+struct Sample {
+    provider: String,
+    region: String,
+    round: usize,
+    signature: String,
+    accepted: bool,
+    /// `send_transaction` call duration
+    submit_us: u64,
+    /// Recorded by the built-in clients, `None` for custom clients and shared provider types
+    time_to_first_byte_us: Option<u64>,
+    /// Processed slot read right before sending
+    send_slot: u64,
+    landed_slot: Option<u64>,
+    slots_to_land: Option<u64>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ProviderReport {
+    provider: String,
+    region: String,
+    sent: usize,
+    accepted: usize,
+    landed: usize,
+    submit_us_p50: Option<u64>,
+    submit_us_p90: Option<u64>,
+    submit_us_p99: Option<u64>,
+    slots_to_land_p50: Option<u64>,
+    slots_to_land_p90: Option<u64>,
+    slots_to_land_p99: Option<u64>,
+}
+
+/// Compare end-to-end latency of swqos providers from this machine
+///
+/// Sends `SAMPLES` tiny self-transfers (plus the provider's tip) through every configured
+/// provider, records the submission latency and the slots each transaction took to land, and
+/// prints percentiles per provider and region. With `INTERLEAVE=1` (default) every round sends
+/// through all providers at once against the same slot, otherwise providers take turns.
+/// `OUTPUT=report.csv` writes every sample as CSV, any other path gets a JSON report.
+///
+/// Every sample costs the priority fee and the tip, fund the payer accordingly.
+///
+/// Usage: `SAMPLES=50 OUTPUT=bench.json cargo run --release -p bench_swqos`
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let payer = Arc::new(Keypair::from_base58_string("use_your_payer_keypair_here"));
+    let rpc_url = "https://api.mainnet-beta.solana.com".to_string();
+    let swqos_configs = vec![
+        SwqosConfig::Default(rpc_url.clone()),
+        SwqosConfig::Jito("your_uuid".to_string(), SwqosRegion::Frankfurt, None),
+        SwqosConfig::Jito("your_uuid".to_string(), SwqosRegion::NewYork, None),
+        SwqosConfig::NextBlock("your_api_token".to_string(), SwqosRegion::Frankfurt, None),
+        SwqosConfig::ZeroSlot("your_api_token".to_string(), SwqosRegion::Frankfurt, None),
+    ];
+    let samples = env_or("SAMPLES", DEFAULT_SAMPLES);
+    let tip_lamports = env_or("TIP_LAMPORTS", DEFAULT_TIP_LAMPORTS);
+    let interleave = std::env::var("INTERLEAVE").as_deref() != Ok("0");
+
+    let rpc = Arc::new(SolanaRpcClient::new_with_commitment(
+        rpc_url.clone(),
+        CommitmentConfig::confirmed(),
+    ));
+    let confirmation = ConfirmationService::for_rpc(&rpc);
+    let providers = Arc::new(providers(&rpc_url, swqos_configs));
+    println!(
+        "🚀 Benchmarking {} providers x {} samples ({})",
+        providers.len(),
+        samples,
+        if interleave { "interleaved" } else { "sequential" }
+    );
+
+    let mut results = Vec::with_capacity(providers.len() * samples);
+    for round in 0..samples {
+        let recent_blockhash = rpc.get_latest_blockhash().await?;
+        if interleave {
+            let send_slot = rpc.get_slot_with_commitment(CommitmentConfig::processed()).await?;
+            let handles: Vec<_> = (0..providers.len())
+                .map(|index| {
+                    let providers = providers.clone();
+                    let payer = payer.clone();
+                    let confirmation = confirmation.clone();
+                    tokio::spawn(async move {
+                        let provider = &providers[index];
+                        let tag = (round * providers.len() + index + 1) as u64;
+                        let transaction =
+                            self_transfer(&payer, provider, tag, tip_lamports, recent_blockhash)?;
+                        Ok::<Sample, anyhow::Error>(
+                            send_sample(
+                                provider,
+                                &confirmation,
+                                transaction,
+                                round,
+                                send_slot,
+                                true,
+                            )
+                            .await,
+                        )
+                    })
+                })
+                .collect();
+            for handle in handles {
+                results.push(handle.await??);
+            }
+        } else {
+            for (index, provider) in providers.iter().enumerate() {
+                let tag = (round * providers.len() + index + 1) as u64;
+                let transaction =
+                    self_transfer(&payer, provider, tag, tip_lamports, recent_blockhash)?;
+                let send_slot = rpc.get_slot_with_commitment(CommitmentConfig::processed()).await?;
+                results.push(
+                    send_sample(provider, &confirmation, transaction, round, send_slot, false)
+                        .await,
+                );
+            }
+        }
+        println!("✅ Round {}/{} done", round + 1, samples);
+    }
+
+    let report = report(&providers, &results);
+    print_report(&report);
+    if let Ok(path) = std::env::var("OUTPUT") {
+        if path.ends_with(".csv") {
+            std::fs::write(&path, to_csv(&results))?;
+        } else {
+            let json = serde_json::json!({ "samples": results, "report": report });
+            std::fs::write(&path, serde_json::to_string_pretty(&json)?)?;
+        }
+        println!("📄 Wrote {}", path);
+    }
+    Ok(())
+}
+
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    std::env::var(key).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+}
+
+fn providers(rpc_url: &str, swqos_configs: Vec<SwqosConfig>) -> Vec<Provider> {
+    let mut providers: Vec<Provider> = swqos_configs
+        .into_iter()
+        .map(|config| {
+            let region = match config.provider() {
+                Some((swqos_type, SwqosRegion::Auto, None)) => cached_region(&swqos_type)
+                    .map_or("Auto".to_string(), |region| format!("{:?}", region)),
+                Some((_, region, None)) => format!("{:?}", region),
+                Some((_, _, Some(_))) => "Custom".to_string(),
+                None => "-".to_string(),
+            };
+            let client = SwqosConfig::get_swqos_client(
+                rpc_url.to_string(),
+                CommitmentConfig::confirmed(),
+                config,
+            );
+            Provider {
+                name: format!("{:?}", client.get_swqos_type()),
+                region,
+                client,
+                unique_type: false,
+            }
+        })
+        .collect();
+    let mut seen = HashSet::new();
+    let shared: HashSet<SwqosType> = providers
+        .iter()
+        .map(|provider| provider.client.get_swqos_type())
+        .filter(|swqos_type| !seen.insert(swqos_type.clone()))
+        .collect();
+    for provider in &mut providers {
+        provider.unique_type = !shared.contains(&provider.client.get_swqos_type());
+    }
+    providers
+}
+
+/// Self-transfer of `tag` lamports plus the provider's tip
+///
+/// The amount only keeps signatures distinct across providers and rounds.
+fn self_transfer(
+    payer: &Keypair,
+    provider: &Provider,
+    tag: u64,
+    tip_lamports: u64,
+    recent_blockhash: Hash,
+) -> anyhow::Result<VersionedTransaction> {
+    let mut instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_limit(UNIT_LIMIT),
+        ComputeBudgetInstruction::set_compute_unit_price(UNIT_PRICE_MICRO_LAMPORTS),
+        transfer(&payer.pubkey(), &payer.pubkey(), tag),
+    ];
+    if let Some(tip_account) = provider.client.get_tip_account() {
+        let tip_account = Pubkey::from_str(&tip_account)?;
+        instructions.push(transfer(&payer.pubkey(), &tip_account, tip_lamports));
+    }
+    let message = v0::Message::try_compile(&payer.pubkey(), &instructions, &[], recent_blockhash)?;
+    Ok(VersionedTransaction::try_new(VersionedMessage::V0(message), &[payer])?)
+}
+
+async fn send_sample(
+    provider: &Provider,
+    confirmation: &Arc<ConfirmationService>,
+    transaction: VersionedTransaction,
+    round: usize,
+    send_slot: u64,
+    interleaved: bool,
+) -> Sample {
+    let swqos_type = provider.client.get_swqos_type();
+    let started = Instant::now();
+    let sent = provider.client.send_transaction(TradeType::Buy, &transaction).await;
+    let submit_us = started.elapsed().as_micros() as u64;
+    // With concurrent sends of the same provider type the last recorded timing may be another's
+    let time_to_first_byte_us = if provider.unique_type || !interleaved {
+        recent_submit_timings(&swqos_type, 1)
+            .pop()
+            .and_then(|timing| timing.time_to_first_byte)
+            .map(|ttfb| ttfb.as_micros() as u64)
+    } else {
+        None
+    };
+
+    let mut sample = Sample {
+        provider: provider.name.clone(),
+        region: provider.region.clone(),
+        round,
+        signature: transaction.signatures[0].to_string(),
+        accepted: sent.is_ok(),
+        submit_us,
+        time_to_first_byte_us,
+        send_slot,
+        landed_slot: None,
+        slots_to_land: None,
+        error: None,
+    };
+    let signature = match sent {
+        Ok(signature) => signature,
+        Err(e) => {
+            sample.error = Some(e.to_string());
+            return sample;
+        }
+    };
+    match confirmation.wait_landed_slot(signature, LANDING_TIMEOUT).await {
+        Ok(landed_slot) => {
+            sample.landed_slot = Some(landed_slot);
+            sample.slots_to_land = Some(landed_slot.saturating_sub(send_slot));
+        }
+        Err(e) => sample.error = Some(e.to_string()),
+    }
+    sample
+}
+
+fn report(providers: &[Provider], samples: &[Sample]) -> Vec<ProviderReport> {
+    let mut keys: Vec<(String, String)> = Vec::new();
+    for provider in providers {
+        let key = (provider.name.clone(), provider.region.clone());
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys.into_iter()
+        .map(|(provider, region)| {
+            let samples: Vec<&Sample> = samples
+                .iter()
+                .filter(|sample| sample.provider == provider && sample.region == region)
+                .collect();
+            let accepted: Vec<&&Sample> = samples.iter().filter(|sample| sample.accepted).collect();
+            let submits = || accepted.iter().map(|sample| sample.submit_us);
+            let slots = || samples.iter().filter_map(|sample| sample.slots_to_land);
+            ProviderReport {
+                sent: samples.len(),
+                accepted: accepted.len(),
+                landed: slots().count(),
+                submit_us_p50: percentile(submits(), 50),
+                submit_us_p90: percentile(submits(), 90),
+                submit_us_p99: percentile(submits(), 99),
+                slots_to_land_p50: percentile(slots(), 50),
+                slots_to_land_p90: percentile(slots(), 90),
+                slots_to_land_p99: percentile(slots(), 99),
+                provider,
+                region,
+            }
+        })
+        .collect()
+}
+
+fn print_report(report: &[ProviderReport]) {
+    let ms = |us: Option<u64>| us.map_or("-".to_string(), |us| format!("{:.1}", us as f64 / 1e3));
+    let slots = |slots: Option<u64>| slots.map_or("-".to_string(), |slots| slots.to_string());
+    println!("\n📊 Submit latency (ms, accepted sends) and slots to land");
+    println!(
+        "{:<12} {:<12} {:>5} {:>5} {:>6} {:>8} {:>8} {:>8} {:>6} {:>6} {:>6}",
+        "provider", "region", "sent", "ok", "landed", "p50", "p90", "p99", "s50", "s90", "s99"
+    );
+    for row in report {
+        println!(
+            "{:<12} {:<12} {:>5} {:>5} {:>6} {:>8} {:>8} {:>8} {:>6} {:>6} {:>6}",
+            row.provider,
+            row.region,
+            row.sent,
+            row.accepted,
+            row.landed,
+            ms(row.submit_us_p50),
+            ms(row.submit_us_p90),
+            ms(row.submit_us_p99),
+            slots(row.slots_to_land_p50),
+            slots(row.slots_to_land_p90),
+            slots(row.slots_to_land_p99),
+        );
+    }
+}
+
+fn to_csv(samples: &[Sample]) -> String {
+    let opt = |value: Option<u64>| value.map_or(String::new(), |value| value.to_string());
+    let mut csv = "provider,region,round,signature,accepted,submit_us,time_to_first_byte_us,\
+                   send_slot,landed_slot,slots_to_land,error\n"
+        .to_string();
+    for sample in samples {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},\"{}\"\n",
+            sample.provider,
+            sample.region,
+            sample.round,
+            sample.signature,
+            sample.accepted,
+            sample.submit_us,
+            opt(sample.time_to_first_byte_us),
+            sample.send_slot,
+            opt(sample.landed_slot),
+            opt(sample.slots_to_land),
+            sample.error.as_deref().unwrap_or_default().replace('"', "\"\""),
+        ));
+    }
+    csv
+}
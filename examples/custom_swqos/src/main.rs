@@ -0,0 +1,138 @@
+use sol_trade_sdk::prelude::*;
+use sol_trade_sdk::swqos::common::poll_transaction_confirmation;
+use solana_client::rpc_config::RpcSendTransactionConfig;
+use solana_sdk::transaction::VersionedTransaction;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Submission backend for an internal relay
+///
+/// The relay here speaks JSON-RPC `sendTransaction`; swap the body of `send_transaction` for
+/// your own transport (gRPC, QUIC, ...). Confirmation is done against a regular RPC node, which
+/// keeps the relay free to only accept transactions.
+struct InternalRelayClient {
+    relay: Arc<SolanaRpcClient>,
+    rpc: Arc<SolanaRpcClient>,
+    tip_account: Option<Pubkey>,
+}
+
+impl InternalRelayClient {
+    fn new(relay_url: String, rpc_url: String, tip_account: Option<Pubkey>) -> Self {
+        Self {
+            relay: Arc::new(SolanaRpcClient::new(relay_url)),
+            rpc: Arc::new(SolanaRpcClient::new(rpc_url)),
+            tip_account,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SwqosClientTrait for InternalRelayClient {
+    /// Submit only; an error means the relay rejected the transaction or was unreachable
+    async fn send_transaction(
+        &self,
+        trade_type: TradeType,
+        transaction: &VersionedTransaction,
+    ) -> anyhow::Result<Signature> {
+        let start_time = Instant::now();
+        let signature = self
+            .relay
+            .send_transaction_with_config(
+                transaction,
+                RpcSendTransactionConfig { skip_preflight: true, ..Default::default() },
+            )
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "Relay submission failed: {} | Signature: {}",
+                    e,
+                    transaction.signatures[0]
+                )
+            })?;
+        println!(" relay {} submitted: {:?}", trade_type, start_time.elapsed());
+        Ok(signature)
+    }
+
+    /// Only called when the trade asked to wait for confirmation
+    async fn confirm_transaction(
+        &self,
+        trade_type: TradeType,
+        signature: Signature,
+    ) -> anyhow::Result<()> {
+        let start_time = Instant::now();
+        poll_transaction_confirmation(&self.rpc, signature).await?;
+        println!(" relay {} confirmed: {:?}", trade_type, start_time.elapsed());
+        Ok(())
+    }
+
+    async fn send_transactions(
+        &self,
+        trade_type: TradeType,
+        transactions: &Vec<VersionedTransaction>,
+    ) -> anyhow::Result<()> {
+        for transaction in transactions {
+            self.send_transaction(trade_type, transaction).await?;
+        }
+        Ok(())
+    }
+
+    /// `None` tells the SDK not to add a tip transfer for this backend
+    fn get_tip_account(&self) -> Option<String> {
+        self.tip_account.map(|account| account.to_string())
+    }
+
+    fn get_swqos_type(&self) -> SwqosType {
+        SwqosType::External
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("🔌 Custom SWQOS Example");
+    let payer = Keypair::from_base58_string("use_your_payer_keypair_here");
+    let rpc_url = "https://api.mainnet-beta.solana.com".to_string();
+    let relay_url = "http://relay.internal:8899".to_string();
+
+    // Register the custom backend next to a built-in one
+    let relay: Arc<SwqosClient> = Arc::new(InternalRelayClient::new(
+        relay_url,
+        rpc_url.clone(),
+        Some(Pubkey::from_str_const("96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5")),
+    ));
+    let swqos_configs = vec![
+        SwqosConfig::External(relay),
+        SwqosConfig::Jito("".to_string(), SwqosRegion::Frankfurt, None),
+    ];
+
+    // One buy tip per swqos client, in the same order
+    let mut priority_fee = PriorityFee::default();
+    priority_fee.buy_tips = vec![TipAmount::Lamports(100_000), TipAmount::Lamports(100_000)];
+
+    let trade_config = TradeConfig::new(
+        rpc_url.clone(),
+        swqos_configs,
+        priority_fee,
+        CommitmentConfig::confirmed(),
+    );
+    let solana_trade = SolanaTrade::new(Arc::new(payer), trade_config).await;
+    println!("✅ SolanaTrade client initialized with a custom swqos backend");
+
+    let mint = std::env::args().nth(1).ok_or("Usage: cargo run -p custom_swqos -- <bonk mint>")?;
+    let mint = Pubkey::from_str(&mint)?;
+    let rpc = solana_trade.rpc.clone();
+    let recent_blockhash = rpc.get_latest_blockhash().await?;
+    let params = BonkParams::from_mint_by_rpc(&rpc, &mint).await?;
+    let signature = solana_trade
+        .buy_with_options(
+            DexType::Bonk,
+            mint,
+            100_000,
+            recent_blockhash,
+            Box::new(params),
+            BuyOptions::default().with_slippage_basis_points(500).with_wsol_ata(true, true),
+        )
+        .await?;
+    println!("✅ Buy sent through the fastest backend: {}", signature);
+    Ok(())
+}
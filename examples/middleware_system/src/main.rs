@@ -1,17 +1,9 @@
 use anyhow::Result;
-use sol_trade_sdk::{
-    common::{AnyResult, PriorityFee, TradeConfig},
-    swqos::{SwqosConfig, SwqosRegion},
-    trading::{
-        core::params::PumpSwapParams, factory::DexType, middleware::builtin::LoggingMiddleware,
-        InstructionMiddleware, MiddlewareManager,
-    },
-    SolanaTrade,
-};
-use solana_sdk::{
-    commitment_config::CommitmentConfig, instruction::Instruction, pubkey::Pubkey,
-    signature::Keypair,
+use sol_trade_sdk::prelude::*;
+use sol_trade_sdk::trading::{
+    middleware::builtin::LoggingMiddleware, InstructionMiddleware, MiddlewareManager,
 };
+use solana_sdk::instruction::Instruction;
 use std::{str::FromStr, sync::Arc};
 
 #[tokio::main]
@@ -67,12 +59,12 @@ async fn create_solana_trade_client() -> AnyResult<SolanaTrade> {
 
     let swqos_configs = vec![SwqosConfig::Default(rpc_url.clone())];
 
-    let trade_config = TradeConfig {
+    let trade_config = TradeConfig::new(
         rpc_url,
-        commitment: CommitmentConfig::confirmed(),
-        priority_fee: PriorityFee::default(),
         swqos_configs,
-    };
+        PriorityFee::default(),
+        CommitmentConfig::confirmed(),
+    );
 
     let solana_trade_client = SolanaTrade::new(Arc::new(payer), trade_config).await;
     println!("SolanaTrade client created successfully!");
@@ -88,24 +80,19 @@ async fn test_middleware() -> AnyResult<()> {
     client = client.with_middleware_manager(middleware_manager);
     let mint_pubkey = Pubkey::from_str("pumpCmXqMfrsAkQ5r49WcJnRayYRqmXz6ae8H7H9Dfn")?;
     let buy_sol_cost = 100_000;
-    let slippage_basis_points = Some(100);
+    let slippage_basis_points = 100;
     let recent_blockhash = client.rpc.get_latest_blockhash().await?;
     let pool_address = Pubkey::from_str("539m4mVWt6iduB6W8rDGPMarzNCMesuqY5eUTiiYHAgR")?;
     client
-        .buy(
+        .buy_with_options(
             DexType::PumpSwap,
             mint_pubkey,
             buy_sol_cost,
-            slippage_basis_points,
             recent_blockhash,
-            None,
             Box::new(PumpSwapParams::from_pool_address_by_rpc(&client.rpc, &pool_address).await?),
-            None,
-            true,
-            true,
-            true,
-            true,
-            false,
+            BuyOptions::default()
+                .with_slippage_basis_points(slippage_basis_points)
+                .with_wsol_ata(true, true),
         )
         .await?;
     println!("tip: This transaction will not succeed because we're using a test account. You can modify the code to initialize the payer with your own private key");
@@ -0,0 +1,126 @@
+use sol_trade_sdk::{
+    prelude::*, trading::core::traits::ProtocolParams, BundleLeg, SellLeg, TwoHopMode,
+};
+use std::{str::FromStr, sync::Arc};
+
+/// The entry points that trade several mints, venues or wallets at once
+///
+/// Buys two PumpFun tokens in a batch and as an all-or-nothing bundle, splits a buy across
+/// wallets, swaps one token into the other through SOL and sells both in packed transactions,
+/// the second one across its bonding curve and PumpSwap pool.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let client = create_solana_trade_client().await?;
+    let mint_a = Pubkey::from_str("xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx")?;
+    let mint_b = Pubkey::from_str("xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx")?;
+    let sol_amount = 100_000;
+    let slippage_basis_points = 500;
+    let recent_blockhash = client.rpc.get_latest_blockhash().await?;
+    let params_a = PumpFunParams::from_mint_by_rpc(&client.rpc, &mint_a).await?;
+    let params_b = PumpFunParams::from_mint_by_rpc(&client.rpc, &mint_b).await?;
+
+    // One transaction per mint, sent concurrently; each order succeeds or fails on its own
+    let orders: Vec<(Pubkey, u64, Box<dyn ProtocolParams>)> = vec![
+        (mint_a, sol_amount, Box::new(params_a.clone())),
+        (mint_b, sol_amount, Box::new(params_b.clone())),
+    ];
+    let results = client
+        .buy_batch(
+            DexType::PumpFun,
+            orders,
+            recent_blockhash,
+            BuyOptions::default().with_slippage_basis_points(slippage_basis_points),
+        )
+        .await;
+    for result in results {
+        println!("Batch buy: {:?}", result);
+    }
+
+    // Both buys land or neither does, when a bundle provider such as Jito is configured
+    let report = client
+        .execute_bundle(
+            vec![
+                BundleLeg::buy(DexType::PumpFun, mint_a, sol_amount, Box::new(params_a.clone())),
+                BundleLeg::buy(DexType::PumpFun, mint_b, sol_amount, Box::new(params_b.clone())),
+            ],
+            Some(recent_blockhash),
+            Vec::new(),
+        )
+        .await?;
+    println!("Bundle {:?}, atomic: {}", report.bundle_id, report.is_atomic());
+
+    // Spread a buy over three wallets by weight and gather the tokens in the first one
+    let payers: Vec<Arc<Keypair>> =
+        (0..3).map(|_| Arc::new(Keypair::from_base58_string("use_your_own_keypair"))).collect();
+    let primary = payers[0].pubkey();
+    let split = SplitAcrossPayers::new(payers, vec![50, 30, 20])?.with_consolidation(primary);
+    let report = client
+        .split_buy(
+            DexType::PumpFun,
+            mint_a,
+            sol_amount,
+            Some(slippage_basis_points),
+            recent_blockhash,
+            None,
+            Box::new(params_a.clone()),
+            Vec::new(),
+            split,
+        )
+        .await?;
+    println!("Split buy complete: {}", report.is_complete());
+
+    // Swap A into B through SOL, in one transaction when both legs fit
+    let report = client
+        .swap_two_hop(
+            DexType::PumpFun,
+            mint_a,
+            1_000_000,
+            Box::new(params_a),
+            DexType::PumpFun,
+            mint_b,
+            Box::new(params_b),
+            Some(slippage_basis_points),
+            recent_blockhash,
+            Vec::new(),
+            TwoHopMode::Auto,
+        )
+        .await?;
+    println!("Two-hop swap {:?}, rate {:?}", report.mode, report.effective_rate());
+
+    // Sell the whole balance of both mints, packed into as few transactions as fit
+    let params_a = PumpFunParams::from_mint_by_rpc(&client.rpc, &mint_a).await?;
+    let params_b = PumpFunParams::from_mint_by_rpc(&client.rpc, &mint_b).await?;
+    let results = client
+        .sell_many(
+            vec![
+                SellLeg::new(DexType::PumpFun, mint_a, 0, Box::new(params_a)),
+                SellLeg::new(DexType::PumpFun, mint_b, 0, Box::new(params_b)),
+            ],
+            None,
+            SellOptions::default().with_slippage_basis_points(slippage_basis_points),
+        )
+        .await?;
+    for (mint, result) in results {
+        println!("Sold {}: {:?}", mint, result);
+    }
+
+    // Around migration, sell into the bonding curve and the PumpSwap pool at once
+    let report = client
+        .sell_smart(mint_b, SellAmount::All, Some(slippage_basis_points), recent_blockhash)
+        .await?;
+    println!("Smart sell filled: {}", report.is_filled());
+    Ok(())
+}
+
+async fn create_solana_trade_client() -> AnyResult<SolanaTrade> {
+    let payer = Keypair::from_base58_string("use_your_own_keypair");
+    let rpc_url = "https://api.mainnet-beta.solana.com".to_string();
+    let swqos_configs = vec![SwqosConfig::Default(rpc_url.clone())];
+    let trade_config = TradeConfig::new(
+        rpc_url,
+        swqos_configs,
+        PriorityFee::default(),
+        CommitmentConfig::confirmed(),
+    );
+    Ok(SolanaTrade::new(Arc::new(payer), trade_config).await)
+}
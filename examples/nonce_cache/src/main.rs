@@ -16,13 +16,7 @@ use sol_trade_sdk::{
     common::nonce_cache::NonceCache,
     solana_streamer_sdk::streaming::event_parser::protocols::pumpfun::parser::PUMPFUN_PROGRAM_ID,
 };
-use sol_trade_sdk::{
-    common::{AnyResult, PriorityFee, TradeConfig},
-    swqos::SwqosConfig,
-    trading::{core::params::PumpFunParams, factory::DexType},
-    SolanaTrade,
-};
-use solana_sdk::{commitment_config::CommitmentConfig, signature::Keypair};
+use sol_trade_sdk::prelude::*;
 
 // Global static flag to ensure transaction is executed only once
 static ALREADY_EXECUTED: AtomicBool = AtomicBool::new(false);
@@ -109,12 +103,8 @@ async fn create_solana_trade_client() -> AnyResult<SolanaTrade> {
     // Configure according to your needs
     priority_fee.rpc_unit_limit = 100000;
 
-    let trade_config = TradeConfig {
-        rpc_url,
-        commitment: CommitmentConfig::confirmed(),
-        priority_fee: priority_fee,
-        swqos_configs,
-    };
+    let trade_config =
+        TradeConfig::new(rpc_url, swqos_configs, priority_fee, CommitmentConfig::confirmed());
 
     let solana_trade_client = SolanaTrade::new(Arc::new(payer), trade_config).await;
     println!("SolanaTrade client created successfully!");
@@ -129,7 +119,7 @@ async fn pumpfun_copy_trade_with_grpc(trade_info: PumpFunTradeEvent) -> AnyResul
 
     let client = create_solana_trade_client().await?;
     let mint_pubkey = trade_info.mint;
-    let slippage_basis_points = Some(100);
+    let slippage_basis_points = 100;
 
     // Setup nonce cache
     let nonce_account_str = "use_your_nonce_account_here";
@@ -142,20 +132,15 @@ async fn pumpfun_copy_trade_with_grpc(trade_info: PumpFunTradeEvent) -> AnyResul
     println!("Buying tokens from PumpFun...");
     let buy_sol_amount = 100_000;
     client
-        .buy(
+        .buy_with_options(
             DexType::PumpFun,
             mint_pubkey,
             buy_sol_amount,
-            slippage_basis_points,
             last_nonce,
-            None,
             Box::new(PumpFunParams::from_trade(&trade_info, None)),
-            None,
-            true,
-            false,
-            false,
-            true,
-            false,
+            BuyOptions::default()
+                .with_slippage_basis_points(slippage_basis_points)
+                .with_durable_nonce(true),
         )
         .await?;
 
@@ -0,0 +1,189 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use sol_trade_sdk::prelude::*;
+use sol_trade_sdk::solana_streamer_sdk::match_event;
+use sol_trade_sdk::solana_streamer_sdk::streaming::event_parser::common::filter::EventTypeFilter;
+use sol_trade_sdk::solana_streamer_sdk::streaming::event_parser::common::EventType;
+use sol_trade_sdk::solana_streamer_sdk::streaming::event_parser::protocols::pumpfun::parser::PUMPFUN_PROGRAM_ID;
+use sol_trade_sdk::solana_streamer_sdk::streaming::event_parser::protocols::pumpfun::PumpFunTradeEvent;
+use sol_trade_sdk::solana_streamer_sdk::streaming::event_parser::{Protocol, UnifiedEvent};
+use sol_trade_sdk::solana_streamer_sdk::streaming::yellowstone_grpc::{
+    AccountFilter, TransactionFilter,
+};
+use sol_trade_sdk::solana_streamer_sdk::streaming::YellowstoneGrpc;
+use sol_trade_sdk::{LatencySlippageFillModel, PaperEngine, ResumeSide, ResumedTrade, TradeResult};
+
+const BUY_SOL_AMOUNT: u64 = 100_000;
+const SLIPPAGE_BASIS_POINTS: u64 = 300;
+const PUMPFUN_DECIMALS: u8 = 6;
+
+// Only copy one trade in this example
+static ALREADY_EXECUTED: AtomicBool = AtomicBool::new(false);
+
+/// Paper or live execution behind one interface, the strategy does not know which it runs on
+enum Trader {
+    Paper(PaperEngine),
+    Live(SolanaTrade),
+}
+
+impl Trader {
+    async fn buy(
+        &self,
+        mint: Pubkey,
+        sol_amount: u64,
+        params: PumpFunParams,
+    ) -> AnyResult<TradeResult> {
+        match self {
+            Trader::Paper(engine) => {
+                engine.buy(mint, sol_amount, SLIPPAGE_BASIS_POINTS, &params, PUMPFUN_DECIMALS).await
+            }
+            Trader::Live(client) => {
+                let recent_blockhash = client.rpc.get_latest_blockhash().await?;
+                let signature = client
+                    .buy_with_options(
+                        DexType::PumpFun,
+                        mint,
+                        sol_amount,
+                        recent_blockhash,
+                        Box::new(params),
+                        BuyOptions::default().with_slippage_basis_points(SLIPPAGE_BASIS_POINTS),
+                    )
+                    .await?;
+                let side = ResumeSide::Buy { expected_sol_spent: sol_amount as f64 / 1e9 };
+                landed(client.resume_tracking(signature, mint, side, Some(recent_blockhash)).await?)
+            }
+        }
+    }
+
+    async fn sell(
+        &self,
+        mint: Pubkey,
+        token_amount: u64,
+        params: PumpFunParams,
+    ) -> AnyResult<TradeResult> {
+        match self {
+            Trader::Paper(engine) => {
+                engine.sell(mint, token_amount, SLIPPAGE_BASIS_POINTS, &params).await
+            }
+            Trader::Live(client) => {
+                let recent_blockhash = client.rpc.get_latest_blockhash().await?;
+                let signature = client
+                    .sell_with_options(
+                        DexType::PumpFun,
+                        mint,
+                        token_amount,
+                        recent_blockhash,
+                        Box::new(params),
+                        SellOptions::default().with_slippage_basis_points(SLIPPAGE_BASIS_POINTS),
+                    )
+                    .await?;
+                let side = ResumeSide::Sell {
+                    expected_tokens_sold: token_amount as f64 / 10f64.powi(PUMPFUN_DECIMALS as i32),
+                };
+                landed(client.resume_tracking(signature, mint, side, Some(recent_blockhash)).await?)
+            }
+        }
+    }
+}
+
+fn landed(resumed: ResumedTrade) -> AnyResult<TradeResult> {
+    match resumed {
+        ResumedTrade::Landed(result) => Ok(result),
+        other => Err(anyhow::anyhow!("Trade did not land: {:?}", other)),
+    }
+}
+
+/// TRADE_MODE=live trades for real, anything else runs on a paper portfolio of 1 SOL
+async fn create_trader() -> AnyResult<Trader> {
+    let payer = Keypair::from_base58_string("use_your_payer_keypair_here");
+    if std::env::var("TRADE_MODE").as_deref() != Ok("live") {
+        println!("📝 Paper mode");
+        let engine = PaperEngine::new(payer.pubkey(), 1_000_000_000).with_fill_model(
+            LatencySlippageFillModel { slippage_basis_points: 100, ..Default::default() },
+        );
+        return Ok(Trader::Paper(engine));
+    }
+
+    println!("🔴 Live mode");
+    let rpc_url = "https://api.mainnet-beta.solana.com".to_string();
+    let trade_config = TradeConfig::new(
+        rpc_url.clone(),
+        vec![SwqosConfig::Default(rpc_url)],
+        PriorityFee::default(),
+        CommitmentConfig::confirmed(),
+    );
+    Ok(Trader::Live(SolanaTrade::new(Arc::new(payer), trade_config).await))
+}
+
+/// Copy-trading strategy: buy what was just bought, then sell the whole position
+async fn copy_trade(trader: &Trader, event: PumpFunTradeEvent) -> AnyResult<()> {
+    let mint = event.mint;
+    let buy = trader.buy(mint, BUY_SOL_AMOUNT, PumpFunParams::from_trade(&event, None)).await?;
+    println!(
+        "✅ Bought {} tokens at {} SOL ({})",
+        buy.tokens_received, buy.entry_price, buy.signature
+    );
+
+    let token_amount = (buy.tokens_received * 10f64.powi(buy.token_decimals as i32)) as u64;
+    let sell =
+        trader.sell(mint, token_amount, PumpFunParams::from_trade(&event, Some(true))).await?;
+    println!(
+        "✅ Sold for {} SOL, PnL {:?} SOL ({})",
+        -sell.sol_spent, sell.profit_loss_absolute, sell.signature
+    );
+
+    if let Trader::Paper(engine) = trader {
+        println!("📝 Paper balance: {} lamports", engine.sol_balance());
+    }
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let trader = Arc::new(create_trader().await?);
+
+    let grpc = YellowstoneGrpc::new(
+        "https://solana-yellowstone-grpc.publicnode.com:443".to_string(),
+        None,
+    )?;
+    let transaction_filter = TransactionFilter {
+        account_include: vec![PUMPFUN_PROGRAM_ID.to_string()],
+        account_exclude: vec![],
+        account_required: vec![],
+    };
+    let account_filter = AccountFilter { account: vec![], owner: vec![] };
+    let event_type_filter = EventTypeFilter { include: vec![EventType::PumpFunBuy] };
+
+    let callback = move |event: Box<dyn UnifiedEvent>| {
+        let trader = trader.clone();
+        match_event!(event, {
+            PumpFunTradeEvent => |e: PumpFunTradeEvent| {
+                if !ALREADY_EXECUTED.swap(true, Ordering::SeqCst) {
+                    tokio::spawn(async move {
+                        if let Err(err) = copy_trade(&trader, e).await {
+                            eprintln!("Error in copy trade: {:?}", err);
+                        }
+                        std::process::exit(0);
+                    });
+                }
+            },
+        });
+    };
+
+    grpc.subscribe_events_immediate(
+        vec![Protocol::PumpFun],
+        None,
+        transaction_filter,
+        account_filter,
+        Some(event_type_filter),
+        None,
+        callback,
+    )
+    .await?;
+
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
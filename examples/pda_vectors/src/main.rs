@@ -0,0 +1,27 @@
+use sol_trade_sdk::prelude::*;
+use sol_trade_sdk::utils::pda::{derive_pdas, verify_pdas};
+use std::str::FromStr;
+
+/// Print every PDA derivation of the SDK for a mint and wallet
+///
+/// Usage: `cargo run -p pda_vectors -- <mint> <wallet> <cpmm_amm_config> [--json]`
+///
+/// Compare the output with the derivations of other tooling to check cross-language parity.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.len() < 3 {
+        eprintln!("Usage: pda_vectors <mint> <wallet> <cpmm_amm_config> [--json]");
+        std::process::exit(1);
+    }
+    let mint = Pubkey::from_str(&args[0])?;
+    let wallet = Pubkey::from_str(&args[1])?;
+    let cpmm_amm_config = Pubkey::from_str(&args[2])?;
+
+    if args.iter().any(|arg| arg == "--json") {
+        let derivations = derive_pdas(&mint, &wallet, &cpmm_amm_config);
+        println!("{}", serde_json::to_string_pretty(&derivations)?);
+    } else {
+        verify_pdas(&mint, &wallet, &cpmm_amm_config);
+    }
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use sol_trade_sdk::prelude::*;
+use sol_trade_sdk::{JsonlLedgerStore, Ledger};
+
+const DEFAULT_LEDGER: &str = "ledger.jsonl";
+const DEFAULT_DAYS: i64 = 7;
+const SCAN_LIMIT: usize = 200;
+
+/// Records the latest wallet transactions into a JSONL ledger and prints a daily PnL report
+#[tokio::main]
+async fn main() -> AnyResult<()> {
+    let rpc_url = std::env::var("RPC_URL")
+        .unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string());
+    let wallet = Pubkey::from_str(&std::env::var("WALLET")?)?;
+    let path = std::env::var("LEDGER").unwrap_or_else(|_| DEFAULT_LEDGER.to_string());
+    let days =
+        std::env::var("DAYS").ok().and_then(|days| days.parse().ok()).unwrap_or(DEFAULT_DAYS);
+
+    let rpc = SolanaRpcClient::new(rpc_url);
+    let ledger = Ledger::new(Arc::new(JsonlLedgerStore::open(path)));
+    let recorded = ledger.scan(&rpc, &wallet, SCAN_LIMIT).await?;
+    println!("Recorded {} new transactions", recorded.len());
+
+    let last = Utc::now().date_naive();
+    let first = last - Duration::days(days - 1);
+    for (day, report) in ledger.daily_reports(&wallet, first, last)? {
+        let win_rate = report
+            .win_rate()
+            .map(|rate| format!("{:.0}%", rate * 100.0))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{} trades {:>3}  realized {:>12.6}  fees {:>10.6}  tips {:>10.6}  rent {:>10.6}  net {:>12.6}  win rate {}",
+            day,
+            report.trade_count,
+            report.realized_sol(),
+            report.fees_sol(),
+            report.tips_sol(),
+            report.rent_net_sol(),
+            report.net_sol(),
+            win_rate,
+        );
+        for (protocol, pnl) in &report.by_protocol {
+            println!(
+                "    {:<12} trades {:>3}  realized {:>12.6}",
+                protocol,
+                pnl.trade_count,
+                pnl.realized_lamports as f64 / 1e9,
+            );
+        }
+    }
+    Ok(())
+}
@@ -13,14 +13,7 @@ use sol_trade_sdk::solana_streamer_sdk::streaming::yellowstone_grpc::{
     AccountFilter, TransactionFilter,
 };
 use sol_trade_sdk::solana_streamer_sdk::streaming::YellowstoneGrpc;
-use sol_trade_sdk::{
-    common::{AnyResult, PriorityFee, TradeConfig},
-    swqos::SwqosConfig,
-    trading::{core::params::PumpFunParams, factory::DexType},
-    SolanaTrade,
-};
-use solana_sdk::signer::Signer;
-use solana_sdk::{commitment_config::CommitmentConfig, signature::Keypair};
+use sol_trade_sdk::prelude::*;
 use spl_associated_token_account::get_associated_token_address;
 
 // Global static flag to ensure transaction is executed only once
@@ -108,12 +101,8 @@ async fn create_solana_trade_client() -> AnyResult<SolanaTrade> {
     // Configure according to your needs
     priority_fee.rpc_unit_limit = 100000;
 
-    let trade_config = TradeConfig {
-        rpc_url,
-        commitment: CommitmentConfig::confirmed(),
-        priority_fee: priority_fee,
-        swqos_configs,
-    };
+    let trade_config =
+        TradeConfig::new(rpc_url, swqos_configs, priority_fee, CommitmentConfig::confirmed());
 
     let solana_trade_client = SolanaTrade::new(Arc::new(payer), trade_config).await;
     println!("SolanaTrade client created successfully!");
@@ -128,27 +117,20 @@ async fn pumpfun_copy_trade_with_grpc(trade_info: PumpFunTradeEvent) -> AnyResul
 
     let client = create_solana_trade_client().await?;
     let mint_pubkey = trade_info.mint;
-    let slippage_basis_points = Some(100);
+    let slippage_basis_points = 100;
     let recent_blockhash = client.rpc.get_latest_blockhash().await?;
 
     // Buy tokens
     println!("Buying tokens from PumpFun...");
     let buy_sol_amount = 100_000;
     client
-        .buy(
+        .buy_with_options(
             DexType::PumpFun,
             mint_pubkey,
             buy_sol_amount,
-            slippage_basis_points,
             recent_blockhash,
-            None,
             Box::new(PumpFunParams::from_trade(&trade_info, None)),
-            None,
-            true,
-            false,
-            false,
-            true,
-            false,
+            BuyOptions::default().with_slippage_basis_points(slippage_basis_points),
         )
         .await?;
 
@@ -164,20 +146,13 @@ async fn pumpfun_copy_trade_with_grpc(trade_info: PumpFunTradeEvent) -> AnyResul
 
     println!("Selling {} tokens", amount_token);
     client
-        .sell(
+        .sell_with_options(
             DexType::PumpFun,
             mint_pubkey,
             amount_token,
-            slippage_basis_points,
             recent_blockhash,
-            None,
-            false,
             Box::new(PumpFunParams::from_trade(&trade_info, Some(true))),
-            None,
-            true,
-            false,
-            false,
-            false,
+            SellOptions::default().with_slippage_basis_points(slippage_basis_points),
         )
         .await?;
 
@@ -3,14 +3,7 @@ use sol_trade_sdk::solana_streamer_sdk::streaming::event_parser::common::EventTy
 use sol_trade_sdk::solana_streamer_sdk::streaming::event_parser::protocols::pumpfun::PumpFunTradeEvent;
 use sol_trade_sdk::solana_streamer_sdk::streaming::event_parser::{Protocol, UnifiedEvent};
 use sol_trade_sdk::solana_streamer_sdk::{match_event, streaming::ShredStreamGrpc};
-use sol_trade_sdk::{
-    common::{AnyResult, PriorityFee, TradeConfig},
-    swqos::SwqosConfig,
-    trading::{core::params::PumpFunParams, factory::DexType},
-    SolanaTrade,
-};
-use solana_sdk::signer::Signer;
-use solana_sdk::{commitment_config::CommitmentConfig, signature::Keypair};
+use sol_trade_sdk::prelude::*;
 use spl_associated_token_account::get_associated_token_address;
 use std::mem::take;
 use std::sync::{
@@ -76,12 +69,8 @@ async fn create_solana_trade_client() -> AnyResult<SolanaTrade> {
     // Set RPC unit limit based on your requirements
     priority_fee.rpc_unit_limit = 100000;
 
-    let trade_config = TradeConfig {
-        rpc_url,
-        commitment: CommitmentConfig::confirmed(),
-        priority_fee: priority_fee,
-        swqos_configs,
-    };
+    let trade_config =
+        TradeConfig::new(rpc_url, swqos_configs, priority_fee, CommitmentConfig::confirmed());
 
     let solana_trade_client = SolanaTrade::new(Arc::new(payer), trade_config).await;
     println!("SolanaTrade client created successfully!");
@@ -96,27 +85,22 @@ async fn pumpfun_sniper_trade_with_shreds(trade_info: PumpFunTradeEvent) -> AnyR
 
     let client = create_solana_trade_client().await?;
     let mint_pubkey = trade_info.mint;
-    let slippage_basis_points = Some(300);
+    let slippage_basis_points = 300;
     let recent_blockhash = client.rpc.get_latest_blockhash().await?;
 
     // Buy tokens
     println!("Buying tokens from PumpFun...");
     let buy_sol_amount = 100_000;
     client
-        .buy(
+        .buy_with_options(
             DexType::PumpFun,
             mint_pubkey,
             buy_sol_amount,
-            slippage_basis_points,
             recent_blockhash,
-            None,
             Box::new(PumpFunParams::from_dev_trade(&trade_info, None)),
-            None,
-            true,
-            true,
-            true,
-            true,
-            false,
+            BuyOptions::default()
+                .with_slippage_basis_points(slippage_basis_points)
+                .with_wsol_ata(true, true),
         )
         .await?;
 
@@ -132,20 +116,15 @@ async fn pumpfun_sniper_trade_with_shreds(trade_info: PumpFunTradeEvent) -> AnyR
 
     println!("Selling {} tokens", amount_token);
     client
-        .sell(
+        .sell_with_options(
             DexType::PumpFun,
             mint_pubkey,
             amount_token,
-            slippage_basis_points,
             recent_blockhash,
-            None,
-            false,
             Box::new(PumpFunParams::immediate_sell(trade_info.creator_vault, true)),
-            None,
-            true,
-            true,
-            true,
-            false,
+            SellOptions::default()
+                .with_slippage_basis_points(slippage_basis_points)
+                .with_wsol_ata(true, true),
         )
         .await?;
 
@@ -1,11 +1,4 @@
-use sol_trade_sdk::{
-    common::{AnyResult, PriorityFee, TradeConfig},
-    swqos::SwqosConfig,
-    trading::{core::params::PumpSwapParams, factory::DexType},
-    SolanaTrade,
-};
-use solana_sdk::{commitment_config::CommitmentConfig, signature::Keypair};
-use solana_sdk::{pubkey::Pubkey, signer::Signer};
+use sol_trade_sdk::prelude::*;
 use spl_associated_token_account::get_associated_token_address_with_program_id;
 use std::{str::FromStr, sync::Arc};
 
@@ -14,7 +7,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Testing PumpSwap trading...");
 
     let client = create_solana_trade_client().await?;
-    let slippage_basis_points = Some(100);
+    let slippage_basis_points = 100;
     let recent_blockhash = client.rpc.get_latest_blockhash().await?;
     let pool = Pubkey::from_str("539m4mVWt6iduB6W8rDGPMarzNCMesuqY5eUTiiYHAgR").unwrap();
     let mint_pubkey = Pubkey::from_str("pumpCmXqMfrsAkQ5r49WcJnRayYRqmXz6ae8H7H9Dfn").unwrap();
@@ -23,20 +16,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Buying tokens from PumpSwap...");
     let buy_sol_amount = 100_000;
     client
-        .buy(
+        .buy_with_options(
             DexType::PumpSwap,
             mint_pubkey,
             buy_sol_amount,
-            slippage_basis_points,
             recent_blockhash,
-            None,
             Box::new(PumpSwapParams::from_pool_address_by_rpc(&client.rpc, &pool).await?),
-            None,
-            true,
-            true,
-            true,
-            true,
-            false,
+            BuyOptions::default()
+                .with_slippage_basis_points(slippage_basis_points)
+                .with_wsol_ata(true, true),
         )
         .await?;
 
@@ -45,25 +33,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let rpc = client.rpc.clone();
     let payer = client.payer.pubkey();
-    let program_id = spl_token_2022::ID;
+    let sell_params = PumpSwapParams::from_pool_address_by_rpc(&client.rpc, &pool).await?;
+    // The token account belongs to the token program of the base mint, spl-token or token-2022
+    let program_id = sell_params.base_token_program();
     let account = get_associated_token_address_with_program_id(&payer, &mint_pubkey, &program_id);
     let balance = rpc.get_token_account_balance(&account).await?;
     let amount_token = balance.amount.parse::<u64>().unwrap();
     client
-        .sell(
+        .sell_with_options(
             DexType::PumpSwap,
             mint_pubkey,
             amount_token,
-            slippage_basis_points,
             recent_blockhash,
-            None,
-            false,
-            Box::new(PumpSwapParams::from_pool_address_by_rpc(&client.rpc, &pool).await?),
-            None,
-            true,
-            true,
-            true,
-            false,
+            Box::new(sell_params),
+            SellOptions::default()
+                .with_slippage_basis_points(slippage_basis_points)
+                .with_wsol_ata(true, true),
         )
         .await?;
 
@@ -82,16 +67,12 @@ async fn create_solana_trade_client() -> AnyResult<SolanaTrade> {
     let swqos_configs = vec![SwqosConfig::Default(rpc_url.clone())];
 
     let mut priority_fee = PriorityFee::default();
-    priority_fee.buy_tip_fees = vec![0.001];
+    priority_fee.buy_tips = vec![TipAmount::Sol(0.001)];
     // Configure according to your needs
     priority_fee.rpc_unit_limit = 150000;
 
-    let trade_config = TradeConfig {
-        rpc_url,
-        commitment: CommitmentConfig::confirmed(),
-        priority_fee: priority_fee,
-        swqos_configs,
-    };
+    let trade_config =
+        TradeConfig::new(rpc_url, swqos_configs, priority_fee, CommitmentConfig::confirmed());
 
     let solana_trade_client = SolanaTrade::new(Arc::new(payer), trade_config).await;
     println!("SolanaTrade client created successfully!");
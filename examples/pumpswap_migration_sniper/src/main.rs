@@ -0,0 +1,114 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::Duration;
+
+use sol_trade_sdk::prelude::*;
+use sol_trade_sdk::solana_streamer_sdk::match_event;
+use sol_trade_sdk::solana_streamer_sdk::streaming::event_parser::{
+    common::{filter::EventTypeFilter, EventType},
+    protocols::pumpswap::{parser::PUMPSWAP_PROGRAM_ID, PumpSwapCreatePoolEvent},
+    Protocol, UnifiedEvent,
+};
+use sol_trade_sdk::solana_streamer_sdk::streaming::yellowstone_grpc::{
+    AccountFilter, TransactionFilter,
+};
+use sol_trade_sdk::solana_streamer_sdk::streaming::YellowstoneGrpc;
+use sol_trade_sdk::{constants::WSOL_TOKEN_ACCOUNT, BlockhashCache};
+
+// Only buy the first migration seen
+static ALREADY_EXECUTED: AtomicBool = AtomicBool::new(false);
+
+/// Buys a token in the slot its PumpSwap pool is created, with params taken from the
+/// create-pool event instead of RPC
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let client = Arc::new(create_solana_trade_client().await?);
+    // Keep a fresh blockhash at hand so the buy makes no RPC call either
+    BlockhashCache::get_instance().start(client.rpc.clone(), Duration::from_millis(400));
+
+    println!("Subscribing to GRPC events...");
+    let grpc = YellowstoneGrpc::new(
+        "https://solana-yellowstone-grpc.publicnode.com:443".to_string(),
+        None,
+    )?;
+
+    let transaction_filter = TransactionFilter {
+        account_include: vec![PUMPSWAP_PROGRAM_ID.to_string()],
+        account_exclude: vec![],
+        account_required: vec![],
+    };
+    let account_filter = AccountFilter { account: vec![], owner: vec![] };
+    // Pool creations only
+    let event_type_filter = EventTypeFilter { include: vec![EventType::PumpSwapCreatePool] };
+
+    println!("Waiting for a PumpSwap pool to be created, press Ctrl+C to stop...");
+    grpc.subscribe_events_immediate(
+        vec![Protocol::PumpSwap],
+        None,
+        transaction_filter,
+        account_filter,
+        Some(event_type_filter),
+        None,
+        create_event_callback(client),
+    )
+    .await?;
+
+    tokio::signal::ctrl_c().await?;
+    Ok(())
+}
+
+/// Buy on the first pool quoted in wSOL, which is how PumpFun migrates
+fn create_event_callback(client: Arc<SolanaTrade>) -> impl Fn(Box<dyn UnifiedEvent>) {
+    move |event: Box<dyn UnifiedEvent>| {
+        match_event!(event, {
+            PumpSwapCreatePoolEvent => |e: PumpSwapCreatePoolEvent| {
+                if e.quote_mint != WSOL_TOKEN_ACCOUNT {
+                    return;
+                }
+                if !ALREADY_EXECUTED.swap(true, Ordering::SeqCst) {
+                    let client = client.clone();
+                    tokio::spawn(async move {
+                        if let Err(err) = snipe_migration(&client, &e).await {
+                            eprintln!("Error in snipe: {:?}", err);
+                        }
+                        std::process::exit(0);
+                    });
+                }
+            },
+        });
+    }
+}
+
+async fn create_solana_trade_client() -> AnyResult<SolanaTrade> {
+    let payer = Keypair::from_base58_string("use_your_payer_keypair_here");
+    let rpc_url = "https://api.mainnet-beta.solana.com".to_string();
+
+    let mut priority_fee = PriorityFee::default();
+    priority_fee.rpc_unit_limit = 150000;
+
+    let trade_config = TradeConfig::builder(rpc_url.clone())
+        .with_swqos_config(SwqosConfig::Default(rpc_url))
+        .with_priority_fee(priority_fee)
+        .build()?;
+    Ok(SolanaTrade::new(Arc::new(payer), trade_config).await)
+}
+
+async fn snipe_migration(client: &SolanaTrade, event: &PumpSwapCreatePoolEvent) -> AnyResult<()> {
+    println!("Pool {} created for {}, buying...", event.pool, event.base_mint);
+    let params = PumpSwapParams::from_create_pool_event(event);
+    let buy_sol_amount = 100_000;
+    let signature = client
+        .buy_with_options(
+            DexType::PumpSwap,
+            event.base_mint,
+            buy_sol_amount,
+            None,
+            Box::new(params),
+            BuyOptions::default().with_slippage_basis_points(1000).with_wsol_ata(true, true),
+        )
+        .await?;
+    println!("Bought: {}", signature);
+    Ok(())
+}
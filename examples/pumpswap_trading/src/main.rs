@@ -14,20 +14,13 @@ use sol_trade_sdk::solana_streamer_sdk::streaming::YellowstoneGrpc;
 use sol_trade_sdk::solana_streamer_sdk::{
     match_event, streaming::event_parser::protocols::pumpswap::parser::PUMPSWAP_PROGRAM_ID,
 };
-use sol_trade_sdk::{
-    common::{AnyResult, PriorityFee, TradeConfig},
-    swqos::SwqosConfig,
-    trading::{core::params::PumpSwapParams, factory::DexType},
-    SolanaTrade,
-};
+use sol_trade_sdk::prelude::*;
 use sol_trade_sdk::{
     instruction::utils::pumpswap::accounts,
     solana_streamer_sdk::streaming::event_parser::{
         common::filter::EventTypeFilter, protocols::pumpswap::PumpSwapBuyEvent,
     },
 };
-use solana_sdk::{commitment_config::CommitmentConfig, signature::Keypair};
-use solana_sdk::{pubkey::Pubkey, signer::Signer};
 use spl_associated_token_account::get_associated_token_address_with_program_id;
 
 // Global static flag to ensure transaction is executed only once
@@ -131,12 +124,8 @@ async fn create_solana_trade_client() -> AnyResult<SolanaTrade> {
     // Configure according to your needs
     priority_fee.rpc_unit_limit = 150000;
 
-    let trade_config = TradeConfig {
-        rpc_url,
-        commitment: CommitmentConfig::confirmed(),
-        priority_fee: priority_fee,
-        swqos_configs,
-    };
+    let trade_config =
+        TradeConfig::new(rpc_url, swqos_configs, priority_fee, CommitmentConfig::confirmed());
 
     let solana_trade_client = SolanaTrade::new(Arc::new(payer), trade_config).await;
     println!("SolanaTrade client created successfully!");
@@ -170,27 +159,22 @@ async fn pumpswap_trade_with_grpc(mint_pubkey: Pubkey, params: PumpSwapParams) -
     println!("Testing PumpSwap trading...");
 
     let client = create_solana_trade_client().await?;
-    let slippage_basis_points = Some(500);
+    let slippage_basis_points = 500;
     let recent_blockhash = client.rpc.get_latest_blockhash().await?;
 
     // Buy tokens
     println!("Buying tokens from PumpSwap...");
     let buy_sol_amount = 100_000;
     client
-        .buy(
+        .buy_with_options(
             DexType::PumpSwap,
             mint_pubkey,
             buy_sol_amount,
-            slippage_basis_points,
             recent_blockhash,
-            None,
             Box::new(params.clone()),
-            None,
-            true,
-            true,
-            true,
-            true,
-            false,
+            BuyOptions::default()
+                .with_slippage_basis_points(slippage_basis_points)
+                .with_wsol_ata(true, true),
         )
         .await?;
 
@@ -208,20 +192,15 @@ async fn pumpswap_trade_with_grpc(mint_pubkey: Pubkey, params: PumpSwapParams) -
     let balance = rpc.get_token_account_balance(&account).await?;
     let amount_token = balance.amount.parse::<u64>().unwrap();
     client
-        .sell(
+        .sell_with_options(
             DexType::PumpSwap,
             mint_pubkey,
             amount_token,
-            slippage_basis_points,
             recent_blockhash,
-            None,
-            false,
             Box::new(params.clone()),
-            None,
-            true,
-            true,
-            true,
-            false,
+            SellOptions::default()
+                .with_slippage_basis_points(slippage_basis_points)
+                .with_wsol_ata(true, true),
         )
         .await?;
 
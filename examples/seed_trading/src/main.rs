@@ -1,14 +1,5 @@
-use sol_trade_sdk::{
-    common::{
-        fast_fn::get_associated_token_address_with_program_id_fast_use_seed, AnyResult,
-        PriorityFee, TradeConfig,
-    },
-    swqos::SwqosConfig,
-    trading::{core::params::PumpSwapParams, factory::DexType},
-    SolanaTrade,
-};
-use solana_sdk::{commitment_config::CommitmentConfig, signature::Keypair};
-use solana_sdk::{pubkey::Pubkey, signer::Signer};
+use sol_trade_sdk::common::fast_fn::get_associated_token_address_with_program_id_fast_use_seed;
+use sol_trade_sdk::prelude::*;
 use std::{str::FromStr, sync::Arc};
 
 #[tokio::main]
@@ -16,7 +7,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Testing PumpSwap trading...");
 
     let client = create_solana_trade_client().await?;
-    let slippage_basis_points = Some(100);
+    let slippage_basis_points = 100;
     let recent_blockhash = client.rpc.get_latest_blockhash().await?;
     let pool = Pubkey::from_str("9qKxzRejsV6Bp2zkefXWCbGvg61c3hHei7ShXJ4FythA").unwrap();
     let mint_pubkey = Pubkey::from_str("2zMMhcVQEXDtdE6vsFS7S7D5oUodfJHE8vd1gnBouauv").unwrap();
@@ -25,20 +16,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Buying tokens from PumpSwap...");
     let buy_sol_amount = 100_000;
     client
-        .buy(
+        .buy_with_options(
             DexType::PumpSwap,
             mint_pubkey,
             buy_sol_amount,
-            slippage_basis_points,
             recent_blockhash,
-            None,
             Box::new(PumpSwapParams::from_pool_address_by_rpc(&client.rpc, &pool).await?),
-            None,
-            true,
-            true,
-            true,
-            true,
-            true, // ❗️❗️❗️❗️ open seed optimize
+            BuyOptions::default()
+                .with_slippage_basis_points(slippage_basis_points)
+                .with_wsol_ata(true, true)
+                .with_seed_optimize(true), // ❗️❗️❗️❗️ open seed optimize
         )
         .await?;
 
@@ -60,20 +47,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let balance = rpc.get_token_account_balance(&account).await?;
     let amount_token = balance.amount.parse::<u64>().unwrap();
     client
-        .sell(
+        .sell_with_options(
             DexType::PumpSwap,
             mint_pubkey,
             amount_token,
-            slippage_basis_points,
             recent_blockhash,
-            None,
-            false,
             Box::new(PumpSwapParams::from_pool_address_by_rpc(&client.rpc, &pool).await?),
-            None,
-            true,
-            true,
-            true,
-            true, // ❗️❗️❗️❗️ open seed optimize
+            SellOptions::default()
+                .with_slippage_basis_points(slippage_basis_points)
+                .with_wsol_ata(true, true)
+                .with_seed_optimize(true), // ❗️❗️❗️❗️ open seed optimize
         )
         .await?;
 
@@ -92,16 +75,12 @@ async fn create_solana_trade_client() -> AnyResult<SolanaTrade> {
     let swqos_configs = vec![SwqosConfig::Default(rpc_url.clone())];
 
     let mut priority_fee = PriorityFee::default();
-    priority_fee.buy_tip_fees = vec![0.001];
+    priority_fee.buy_tips = vec![TipAmount::Sol(0.001)];
     // Configure according to your needs
     priority_fee.rpc_unit_limit = 150000;
 
-    let trade_config = TradeConfig {
-        rpc_url,
-        commitment: CommitmentConfig::confirmed(),
-        priority_fee: priority_fee,
-        swqos_configs,
-    };
+    let trade_config =
+        TradeConfig::new(rpc_url, swqos_configs, priority_fee, CommitmentConfig::confirmed());
 
     let solana_trade_client = SolanaTrade::new(Arc::new(payer), trade_config).await;
     println!("SolanaTrade client created successfully!");
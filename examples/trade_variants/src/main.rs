@@ -0,0 +1,266 @@
+use sol_trade_sdk::{detect_venue, prelude::*};
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+/// The trade entry points besides `buy_with_options` / `sell_with_options`
+///
+/// Previews a PumpFun trade with a simulation, the raw instructions and the unsigned
+/// transaction, trades with the result-returning variants, falls back to PumpSwap when the
+/// curve completed, sells whole balances and creates tokens.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let client = create_solana_trade_client().await?;
+    let mint = Pubkey::from_str("xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx")?;
+    let sol_amount = 100_000;
+    let slippage_basis_points = 500;
+    let recent_blockhash = client.rpc.get_latest_blockhash().await?;
+    let params = PumpFunParams::from_mint_by_rpc(&client.rpc, &mint).await?;
+    let buy_options = BuyOptions::default().with_slippage_basis_points(slippage_basis_points);
+    let sell_options = SellOptions::default().with_slippage_basis_points(slippage_basis_points);
+
+    // Preview: simulate the signed transaction, or take the instructions to send yourself
+    let simulation = client
+        .buy_simulate(
+            DexType::PumpFun,
+            mint,
+            sol_amount,
+            recent_blockhash,
+            Box::new(params.clone()),
+            buy_options.clone(),
+        )
+        .await?;
+    println!("Buy simulation: {:?} units, error {:?}", simulation.units_consumed, simulation.err);
+    let instructions = client
+        .build_buy_instructions(
+            DexType::PumpFun,
+            mint,
+            sol_amount,
+            Box::new(params.clone()),
+            buy_options.clone(),
+        )
+        .await?;
+    println!("Buy instructions: {}", instructions.len());
+    let transaction = client
+        .build_buy_transaction(
+            DexType::PumpFun,
+            mint,
+            sol_amount,
+            recent_blockhash,
+            Box::new(params.clone()),
+            buy_options.clone(),
+        )
+        .await?;
+    println!("Unsigned buy: {} signatures to fill in", transaction.signatures.len());
+
+    // Trade and get the analyzed result instead of only the signature
+    let bought = client
+        .buy_with_result(
+            DexType::PumpFun,
+            mint,
+            sol_amount,
+            recent_blockhash,
+            Box::new(params.clone()),
+            buy_options.clone(),
+        )
+        .await?;
+    println!("Bought {} tokens for {} SOL", bought.tokens_received, bought.sol_spent);
+    let token_amount = bought.raw.map_or(0, |raw| raw.token_delta.unsigned_abs());
+
+    let params = PumpFunParams::from_mint_by_rpc(&client.rpc, &mint).await?;
+    let simulation = client
+        .sell_simulate(
+            DexType::PumpFun,
+            mint,
+            token_amount,
+            recent_blockhash,
+            Box::new(params.clone()),
+            sell_options.clone(),
+        )
+        .await?;
+    println!("Sell simulation succeeds: {}", simulation.is_success());
+    let instructions = client
+        .build_sell_instructions(
+            DexType::PumpFun,
+            mint,
+            token_amount,
+            Box::new(params.clone()),
+            sell_options.clone(),
+        )
+        .await?;
+    println!("Sell instructions: {}", instructions.len());
+    let transaction = client
+        .build_sell_transaction(
+            DexType::PumpFun,
+            mint,
+            token_amount,
+            recent_blockhash,
+            Box::new(params.clone()),
+            sell_options.clone(),
+        )
+        .await?;
+    println!("Unsigned sell: {} signatures to fill in", transaction.signatures.len());
+
+    // Sell half, then the rest with the analyzed result
+    let signature = client
+        .sell_by_percent(
+            DexType::PumpFun,
+            mint,
+            token_amount,
+            50,
+            Some(slippage_basis_points),
+            recent_blockhash,
+            None,
+            false,
+            Box::new(params.clone()),
+            Vec::new(),
+            WaitLevel::Confirmed,
+            false,
+            false,
+            false,
+        )
+        .await?;
+    println!("Sold half: {}", signature);
+    let params = PumpFunParams::from_mint_by_rpc(&client.rpc, &mint).await?;
+    let sold = client
+        .sell_with_result(
+            DexType::PumpFun,
+            mint,
+            token_amount - token_amount / 2,
+            recent_blockhash,
+            Box::new(params),
+            sell_options,
+        )
+        .await?;
+    println!("Sold the rest: {:?}", sold.profit_loss_absolute);
+
+    // Positional buy and sell, every option spelled out as an argument
+    let params = PumpFunParams::from_mint_by_rpc(&client.rpc, &mint).await?;
+    let signature = client
+        .buy(
+            DexType::PumpFun,
+            mint,
+            sol_amount,
+            Some(slippage_basis_points),
+            recent_blockhash,
+            None,
+            Box::new(params),
+            Vec::new(),
+            WaitLevel::Confirmed,
+            true,
+            true,
+            true,
+            false,
+        )
+        .await?;
+    println!("Positional buy: {}", signature);
+    let params = PumpFunParams::from_mint_by_rpc(&client.rpc, &mint).await?;
+    let signature = client
+        .sell(
+            DexType::PumpFun,
+            mint,
+            0,
+            Some(slippage_basis_points),
+            recent_blockhash,
+            None,
+            false,
+            Box::new(params),
+            Vec::new(),
+            WaitLevel::Confirmed,
+            true,
+            true,
+            false,
+        )
+        .await?;
+    println!("Positional sell: {}", signature);
+
+    // Positional variants with a one-off priority fee, returning the analyzed result
+    let params = PumpFunParams::from_mint_by_rpc(&client.rpc, &mint).await?;
+    let bought = client
+        .buy_with_priority_fee(
+            DexType::PumpFun,
+            mint,
+            None,
+            sol_amount,
+            Some(slippage_basis_points),
+            recent_blockhash,
+            None,
+            Box::new(params),
+            Vec::new(),
+            Some(PriorityFee::default()),
+        )
+        .await?;
+    let params = PumpFunParams::from_mint_by_rpc(&client.rpc, &mint).await?;
+    client
+        .sell_with_priority_fee(
+            DexType::PumpFun,
+            mint,
+            None,
+            bought.raw.map_or(0, |raw| raw.token_delta.unsigned_abs()),
+            Some(slippage_basis_points),
+            recent_blockhash,
+            None,
+            false,
+            Box::new(params),
+            Vec::new(),
+            Some(PriorityFee::default()),
+        )
+        .await?;
+
+    // Buy on the curve, or on the PumpSwap pool if the curve completed before the buy landed
+    let params = PumpFunParams::from_mint_by_rpc(&client.rpc, &mint).await?;
+    let report = client
+        .buy_with_curve_fallback(
+            mint,
+            sol_amount,
+            recent_blockhash,
+            Box::new(params),
+            buy_options.with_on_curve_complete(CurveCompleteAction::FallbackToPumpSwap {
+                max_extra_slippage_bps: 200,
+                deadline: Duration::from_secs(10),
+            }),
+        )
+        .await?;
+    println!("Curve fallback: {:?}", report.signature());
+
+    // Sell the whole balance and close the token account, wherever the fallback bought
+    let (dex_type, params) = detect_venue(&client.rpc, &mint).await?;
+    let signature = client
+        .sell_all(dex_type, mint, recent_blockhash, params, SellOptions::default(), true)
+        .await?;
+    println!("Sold everything: {}", signature);
+
+    // Sell a token bought elsewhere, e.g. an airdrop, on whatever market it trades
+    let airdrop = Pubkey::from_str("xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx")?;
+    let signature = client.sell_unknown_token(airdrop, SellAmount::All).await?;
+    println!("Sold the airdrop: {}", signature);
+
+    // Create a token without a dev buy
+    let metadata = TokenMetadata::new("My Token", "MTK", "https://ipfs.io/ipfs/<metadata-cid>");
+    let created = client.create_token(metadata, recent_blockhash).await?;
+    println!("Created {} in {}", created.mint, created.signature);
+
+    // Create a token and buy it in the same transaction
+    let metadata = TokenMetadata::new("My Token", "MTK", "https://ipfs.io/ipfs/<metadata-cid>");
+    let created = client
+        .create_and_buy(metadata, sol_amount, Some(slippage_basis_points), recent_blockhash)
+        .await?;
+    println!("Created {} with a dev buy of {} tokens", created.mint, created.dev_token_amount);
+    Ok(())
+}
+
+async fn create_solana_trade_client() -> AnyResult<SolanaTrade> {
+    let payer = Keypair::from_base58_string("use_your_own_keypair");
+    let rpc_url = "https://api.mainnet-beta.solana.com".to_string();
+    let swqos_configs = vec![SwqosConfig::Default(rpc_url.clone())];
+    let trade_config = TradeConfig::new(
+        rpc_url,
+        swqos_configs,
+        PriorityFee::default(),
+        CommitmentConfig::confirmed(),
+    );
+    // `try_new` returns the configuration error instead of panicking
+    let client = SolanaTrade::try_new(Arc::new(payer), trade_config).await?;
+    for error in client.validate_swqos_auth().await {
+        println!("swqos credentials rejected: {}", error);
+    }
+    Ok(client)
+}
@@ -1,9 +1,4 @@
-use sol_trade_sdk::{
-    common::{AnyResult, PriorityFee, TradeConfig},
-    swqos::{SwqosConfig, SwqosRegion},
-    SolanaTrade,
-};
-use solana_sdk::{commitment_config::CommitmentConfig, signature::Keypair};
+use sol_trade_sdk::prelude::*;
 use std::sync::Arc;
 
 #[tokio::main]
@@ -51,10 +46,5 @@ fn create_swqos_configs(rpc_url: &str) -> Vec<SwqosConfig> {
 }
 
 fn create_trade_config(rpc_url: String, swqos_configs: Vec<SwqosConfig>) -> TradeConfig {
-    TradeConfig {
-        rpc_url,
-        commitment: CommitmentConfig::confirmed(),
-        priority_fee: PriorityFee::default(),
-        swqos_configs,
-    }
+    TradeConfig::new(rpc_url, swqos_configs, PriorityFee::default(), CommitmentConfig::confirmed())
 }
@@ -1,8 +1,4 @@
-use sol_trade_sdk::{
-    common::{PriorityFee, TradeConfig},
-    SolanaTrade,
-};
-use solana_sdk::{commitment_config::CommitmentConfig, signature::Keypair};
+use sol_trade_sdk::prelude::*;
 use std::sync::Arc;
 
 #[tokio::main]
@@ -18,11 +14,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let wrap_amount = 1_000_000; // 0.001 SOL in lamports
     println!("Wrapping {} lamports (0.001 SOL) to WSOL...", wrap_amount);
 
-    match solana_trade.wrap_sol_to_wsol(wrap_amount).await {
-        Ok(signature) => {
+    match solana_trade.wrap_sol_to_wsol(wrap_amount, None).await {
+        Ok(update) => {
             println!("✅ Successfully wrapped SOL to WSOL!");
-            println!("Transaction signature: {}", signature);
-            println!("Explorer: https://solscan.io/tx/{}", signature);
+            println!("Transaction signature: {}", update.signature);
+            println!("WSOL balance: {}", update.wsol_balance);
+            println!("Explorer: https://solscan.io/tx/{}", update.signature);
         }
         Err(e) => {
             println!("❌ Failed to wrap SOL to WSOL: {}", e);
@@ -33,8 +30,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("\n⏳ Waiting 3 seconds before unwrapping...");
     tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
 
-    // Example 2: Close WSOL account and unwrap all remaining balance
-    println!("\n🔒 Example 2: Closing WSOL account and unwrapping remaining balance");
+    // Example 2: Unwrap part of the WSOL, keeping the rest wrapped
+    println!("\n📤 Example 2: Unwrapping half of the WSOL");
+    match solana_trade.unwrap_wsol_partial(wrap_amount / 2).await {
+        Ok(update) => {
+            println!("✅ Successfully unwrapped part of the WSOL!");
+            println!("Transaction signature: {}", update.signature);
+            println!("WSOL left: {}", update.wsol_balance);
+        }
+        Err(e) => {
+            println!("❌ Failed to unwrap WSOL: {}", e);
+        }
+    }
+
+    // Example 3: Close WSOL account and unwrap all remaining balance
+    println!("\n🔒 Example 3: Closing WSOL account and unwrapping remaining balance");
     println!("Closing WSOL account and unwrapping all remaining balance to SOL...");
 
     match solana_trade.close_wsol().await {
@@ -57,12 +67,8 @@ async fn create_solana_trade_client() -> Result<SolanaTrade, Box<dyn std::error:
     println!("🚀 Initializing SolanaTrade client...");
     let payer = Keypair::from_base58_string("use_your_payer_keypair_here");
     let rpc_url = "https://api.mainnet-beta.solana.com".to_string();
-    let trade_config = TradeConfig {
-        rpc_url,
-        commitment: CommitmentConfig::confirmed(),
-        priority_fee: PriorityFee::default(),
-        swqos_configs: vec![],
-    };
+    let trade_config =
+        TradeConfig::new(rpc_url, vec![], PriorityFee::default(), CommitmentConfig::confirmed());
     let solana_trade = SolanaTrade::new(Arc::new(payer), trade_config).await;
     println!("✅ SolanaTrade client initialized successfully!");
     Ok(solana_trade)
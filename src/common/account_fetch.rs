@@ -0,0 +1,346 @@
+//! Batched account loading for the `*_by_rpc` params constructors
+//!
+//! Accounts needed together are loaded with one `getMultipleAccounts` call, raced across the
+//! fallback endpoints of `rpc_race`, and token balances are decoded from the raw account data.
+//! A failure names every account that could not be loaded so a caller can retry just those.
+
+use std::fmt;
+
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use spl_token_2022::{extension::StateWithExtensions, state::Account as TokenAccount};
+
+use crate::common::{rpc_race, SolanaRpcClient};
+
+/// Why one account could not be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountFetchFailure {
+    /// The RPC call carrying the account failed
+    Rpc(String),
+    NotFound,
+    /// The account exists but has an unexpected owner or layout
+    Invalid(String),
+}
+
+impl fmt::Display for AccountFetchFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountFetchFailure::Rpc(e) => write!(f, "rpc error: {}", e),
+            AccountFetchFailure::NotFound => write!(f, "not found"),
+            AccountFetchFailure::Invalid(e) => write!(f, "invalid: {}", e),
+        }
+    }
+}
+
+/// One account that could not be loaded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedAccount {
+    /// Role of the account for the protocol, e.g. `"pool"` or `"token0 vault"`
+    pub role: &'static str,
+    pub address: Pubkey,
+    pub failure: AccountFetchFailure,
+}
+
+impl fmt::Display for FailedAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ({})", self.role, self.address, self.failure)
+    }
+}
+
+/// Accounts that could not be loaded; accounts not listed were loaded fine
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Failed to load {}", describe(.failed))]
+pub struct AccountFetchError {
+    pub failed: Vec<FailedAccount>,
+}
+
+impl AccountFetchError {
+    pub fn new(role: &'static str, address: Pubkey, failure: AccountFetchFailure) -> Self {
+        Self { failed: vec![FailedAccount { role, address, failure }] }
+    }
+
+    /// Whether `role` is among the failed accounts
+    pub fn failed_role(&self, role: &str) -> bool {
+        self.failed.iter().any(|failed| failed.role == role)
+    }
+}
+
+fn describe(failed: &[FailedAccount]) -> String {
+    failed.iter().map(|failed| failed.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// Load `accounts` (role and address) in one `getMultipleAccounts` call, in the same order
+pub async fn fetch_accounts(
+    rpc: &SolanaRpcClient,
+    accounts: &[(&'static str, Pubkey)],
+) -> Result<Vec<Account>, AccountFetchError> {
+    let addresses: Vec<Pubkey> = accounts.iter().map(|(_, address)| *address).collect();
+    let fetched =
+        rpc_race::get_multiple_accounts(rpc, &addresses).await.map_err(|e| AccountFetchError {
+            failed: accounts
+                .iter()
+                .map(|(role, address)| FailedAccount {
+                    role: *role,
+                    address: *address,
+                    failure: AccountFetchFailure::Rpc(e.to_string()),
+                })
+                .collect(),
+        })?;
+
+    let mut loaded = Vec::with_capacity(accounts.len());
+    let mut failed = Vec::new();
+    for ((role, address), account) in accounts.iter().zip(fetched) {
+        match account {
+            Some(account) => loaded.push(account),
+            None => failed.push(FailedAccount {
+                role: *role,
+                address: *address,
+                failure: AccountFetchFailure::NotFound,
+            }),
+        }
+    }
+    if failed.is_empty() {
+        Ok(loaded)
+    } else {
+        Err(AccountFetchError { failed })
+    }
+}
+
+/// Load one account, failing when it is not owned by `owner`
+pub async fn fetch_owned_account(
+    rpc: &SolanaRpcClient,
+    role: &'static str,
+    address: &Pubkey,
+    owner: &Pubkey,
+) -> Result<Account, AccountFetchError> {
+    let account = fetch_accounts(rpc, &[(role, *address)]).await?.remove(0);
+    check_owner(role, address, &account, owner)?;
+    Ok(account)
+}
+
+/// Fail when `account` is not owned by `owner`
+pub fn check_owner(
+    role: &'static str,
+    address: &Pubkey,
+    account: &Account,
+    owner: &Pubkey,
+) -> Result<(), AccountFetchError> {
+    if account.owner != *owner {
+        return Err(AccountFetchError::new(
+            role,
+            *address,
+            AccountFetchFailure::Invalid(format!(
+                "owned by {} instead of {}",
+                account.owner, owner
+            )),
+        ));
+    }
+    Ok(())
+}
+
+/// Token amount held by an spl-token or token-2022 account
+pub fn token_account_amount(
+    role: &'static str,
+    address: &Pubkey,
+    account: &Account,
+) -> Result<u64, AccountFetchError> {
+    StateWithExtensions::<TokenAccount>::unpack(&account.data)
+        .map(|state| state.base.amount)
+        .map_err(|e| {
+            AccountFetchError::new(role, *address, AccountFetchFailure::Invalid(e.to_string()))
+        })
+}
+
+/// Amounts of two token accounts loaded in one `getMultipleAccounts` call
+pub async fn fetch_token_amounts(
+    rpc: &SolanaRpcClient,
+    first: (&'static str, Pubkey),
+    second: (&'static str, Pubkey),
+) -> Result<(u64, u64), AccountFetchError> {
+    let accounts = fetch_accounts(rpc, &[first, second]).await?;
+    let first = token_account_amount(first.0, &first.1, &accounts[0]);
+    let second = token_account_amount(second.0, &second.1, &accounts[1]);
+    match (first, second) {
+        (Ok(first), Ok(second)) => Ok((first, second)),
+        (Err(mut e), Err(other)) => {
+            e.failed.extend(other.failed);
+            Err(e)
+        }
+        (Err(e), _) | (_, Err(e)) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::fake_chain::{token_account, FakeChain},
+        constants::{TOKEN_PROGRAM, TOKEN_PROGRAM_2022, USD1_TOKEN_ACCOUNT},
+        params::{BonkParams, PumpSwapParams, RaydiumAmmV4Params, RaydiumCpmmParams},
+    };
+    use solana_rpc_client_api::request::RpcRequest;
+
+    fn mint_account(token_program: Pubkey) -> Account {
+        Account {
+            lamports: 1_461_600,
+            data: vec![0; 82],
+            owner: token_program,
+            ..Default::default()
+        }
+    }
+
+    fn fetch_error(e: anyhow::Error) -> AccountFetchError {
+        e.downcast::<AccountFetchError>().expect("an AccountFetchError")
+    }
+
+    #[tokio::test]
+    async fn vault_balances_take_one_call_without_balance_requests() {
+        let pool_state = Pubkey::new_unique();
+        let (token0_mint, token1_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let token0_vault =
+            crate::instruction::utils::raydium_cpmm::get_vault_pda(&pool_state, &token0_mint)
+                .unwrap();
+        let token1_vault =
+            crate::instruction::utils::raydium_cpmm::get_vault_pda(&pool_state, &token1_mint)
+                .unwrap();
+        let (rpc, requests) = FakeChain::default()
+            .with(token0_vault, token_account(token0_mint, 5_000, TOKEN_PROGRAM))
+            .with(token1_vault, token_account(token1_mint, 7_000, TOKEN_PROGRAM_2022))
+            .client();
+
+        let balances = crate::instruction::utils::raydium_cpmm::get_pool_token_balances(
+            &rpc,
+            &pool_state,
+            &token0_mint,
+            &token1_mint,
+        )
+        .await
+        .unwrap();
+        assert_eq!(balances, (5_000, 7_000));
+        assert_eq!(*requests.lock(), vec![RpcRequest::GetMultipleAccounts]);
+
+        let balances = crate::trading::common::utils::get_multi_token_balances(
+            &rpc,
+            &token1_vault,
+            &token0_vault,
+        )
+        .await
+        .unwrap();
+        assert_eq!(balances, (7_000, 5_000));
+        assert_eq!(requests.lock().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn partial_failures_name_each_account() {
+        let (missing, invalid) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (rpc, requests) = FakeChain::default()
+            .with(
+                invalid,
+                Account { data: vec![1, 2, 3], owner: TOKEN_PROGRAM, ..Default::default() },
+            )
+            .client();
+
+        let e = fetch_token_amounts(&rpc, ("token0 vault", missing), ("token1 vault", invalid))
+            .await
+            .unwrap_err();
+        assert_eq!(e.failed.len(), 2);
+        assert_eq!(
+            e.failed[0],
+            FailedAccount {
+                role: "token0 vault",
+                address: missing,
+                failure: AccountFetchFailure::NotFound
+            }
+        );
+        assert_eq!((e.failed[1].role, e.failed[1].address), ("token1 vault", invalid));
+        assert!(matches!(e.failed[1].failure, AccountFetchFailure::Invalid(_)));
+        assert_eq!(requests.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rpc_errors_name_every_account_of_the_call() {
+        let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (rpc, requests) = FakeChain::default().failing(second).client();
+
+        let e = fetch_accounts(&rpc, &[("pool", first), ("base mint", second)]).await.unwrap_err();
+        assert_eq!(
+            e.failed.iter().map(|failed| (failed.role, failed.address)).collect::<Vec<_>>(),
+            vec![("pool", first), ("base mint", second)]
+        );
+        assert!(e
+            .failed
+            .iter()
+            .all(|failed| matches!(failed.failure, AccountFetchFailure::Rpc(_))));
+        assert!(e.failed_role("base mint"));
+        assert_eq!(requests.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn constructors_stop_after_one_call_when_the_pool_is_missing() {
+        let pool = Pubkey::new_unique();
+
+        let (rpc, requests) = FakeChain::default().client();
+        let e =
+            fetch_error(PumpSwapParams::from_pool_address_by_rpc(&rpc, &pool).await.err().unwrap());
+        assert_eq!(e, AccountFetchError::new("pool", pool, AccountFetchFailure::NotFound));
+        assert_eq!(*requests.lock(), vec![RpcRequest::GetMultipleAccounts]);
+
+        let (rpc, requests) = FakeChain::default().client();
+        let e = fetch_error(
+            RaydiumCpmmParams::from_pool_address_by_rpc(&rpc, &pool).await.err().unwrap(),
+        );
+        assert_eq!(e, AccountFetchError::new("pool", pool, AccountFetchFailure::NotFound));
+        assert_eq!(*requests.lock(), vec![RpcRequest::GetMultipleAccounts]);
+
+        let (rpc, requests) = FakeChain::default().client();
+        let e = fetch_error(
+            RaydiumAmmV4Params::from_amm_address_by_rpc(&rpc, pool).await.err().unwrap(),
+        );
+        assert_eq!(e, AccountFetchError::new("amm", pool, AccountFetchFailure::NotFound));
+        assert_eq!(*requests.lock(), vec![RpcRequest::GetMultipleAccounts]);
+    }
+
+    #[tokio::test]
+    async fn pools_owned_by_another_program_are_invalid() {
+        let pool = Pubkey::new_unique();
+        let (rpc, requests) = FakeChain::default().with(pool, mint_account(TOKEN_PROGRAM)).client();
+
+        let e =
+            fetch_error(PumpSwapParams::from_pool_address_by_rpc(&rpc, &pool).await.err().unwrap());
+        assert_eq!((e.failed[0].role, e.failed[0].address), ("pool", pool));
+        assert!(matches!(e.failed[0].failure, AccountFetchFailure::Invalid(_)));
+        assert_eq!(requests.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn bonk_loads_the_pool_and_both_mints_in_one_call() {
+        let mint = Pubkey::new_unique();
+        let pool =
+            crate::instruction::utils::bonk::get_pool_pda(&mint, &USD1_TOKEN_ACCOUNT).unwrap();
+        let (rpc, requests) =
+            FakeChain::default().with(mint, mint_account(TOKEN_PROGRAM_2022)).client();
+
+        let e = fetch_error(
+            BonkParams::from_mint_and_quote_by_rpc(&rpc, &mint, &USD1_TOKEN_ACCOUNT)
+                .await
+                .err()
+                .unwrap(),
+        );
+        assert_eq!(
+            e.failed,
+            vec![
+                FailedAccount {
+                    role: "pool",
+                    address: pool,
+                    failure: AccountFetchFailure::NotFound
+                },
+                FailedAccount {
+                    role: "quote mint",
+                    address: USD1_TOKEN_ACCOUNT,
+                    failure: AccountFetchFailure::NotFound
+                },
+            ]
+        );
+        assert!(!e.failed_role("base mint"));
+        assert_eq!(*requests.lock(), vec![RpcRequest::GetMultipleAccounts]);
+    }
+}
@@ -10,6 +10,9 @@ use std::{
 
 use crate::common::SolanaRpcClient;
 
+/// Maximum number of addresses an address lookup table can hold
+pub const LOOKUP_TABLE_MAX_ADDRESSES: usize = 256;
+
 /// AddressLookupTableInfo struct, stores address lookup table related information
 #[derive(Clone)]
 pub struct AddressLookupTableInfo {
@@ -17,6 +20,8 @@ pub struct AddressLookupTableInfo {
     pub lookup_table_address: Option<Pubkey>,
     /// Address lookup table content
     pub address_lookup_table: Option<AddressLookupTableAccount>,
+    /// Authority allowed to extend the table, `None` once frozen
+    pub authority: Option<Pubkey>,
 }
 
 /// AddressLookupTableCache singleton for storing and managing address lookup tables
@@ -48,21 +53,41 @@ impl AddressLookupTableCache {
             key: *lookup_table_address,
             addresses: lookup_table.addresses.to_vec(),
         };
-        self.add_or_update_table(lookup_table_address.clone(), Some(address_lookup_table_account));
+        self.add_or_update_table(
+            lookup_table_address.clone(),
+            Some(address_lookup_table_account),
+            lookup_table.meta.authority,
+        );
         Ok(())
     }
 
+    /// Forget a table, e.g. once it is deactivated
+    pub fn remove_address_lookup_table(&self, lookup_table_address: &Pubkey) {
+        self.tables.remove(lookup_table_address);
+    }
+
+    /// Find a cached table that `authority` can still extend
+    pub fn find_table_by_authority(&self, authority: &Pubkey) -> Option<AddressLookupTableAccount> {
+        self.tables
+            .iter()
+            .filter(|entry| entry.authority.as_ref() == Some(authority))
+            .filter_map(|entry| entry.address_lookup_table.clone())
+            .find(|table| table.addresses.len() < LOOKUP_TABLE_MAX_ADDRESSES)
+    }
+
     /// Add or update address lookup table information - lock-free implementation
     fn add_or_update_table(
         &self,
         lookup_table_address: Pubkey,
         address_lookup_table: Option<AddressLookupTableAccount>,
+        authority: Option<Pubkey>,
     ) {
         if let Some(mut entry) = self.tables.get_mut(&lookup_table_address) {
             // Update existing table
             if let Some(table) = address_lookup_table {
                 entry.address_lookup_table = Some(table);
             }
+            entry.authority = authority;
         } else {
             // Add new table
             self.tables.insert(
@@ -70,38 +95,30 @@ impl AddressLookupTableCache {
                 AddressLookupTableInfo {
                     lookup_table_address: Some(lookup_table_address),
                     address_lookup_table,
+                    authority,
                 },
             );
         }
     }
 
     /// Get table content - high-performance lock-free implementation
-    fn get_table_content(&self, lookup_table_address: &Pubkey) -> AddressLookupTableAccount {
-        let result = self
-            .tables
+    ///
+    /// `None` when the table was never loaded or holds no addresses
+    fn get_table_content(
+        &self,
+        lookup_table_address: &Pubkey,
+    ) -> Option<AddressLookupTableAccount> {
+        self.tables
             .get(lookup_table_address)
             .and_then(|entry| entry.address_lookup_table.clone())
-            .unwrap_or_else(|| AddressLookupTableAccount {
-                key: *lookup_table_address,
-                addresses: Vec::new(),
-            });
-
-        if result.addresses.len() == 0 {
-            eprintln!(" ❌ Address lookup table account {} not setup", lookup_table_address);
-            eprintln!(" ❌ Please update the address table account information using 【AddressLookupTableCache】 first");
-            eprintln!(
-                " ❌ The current transaction will not include this address lookup table account"
-            );
-        }
-
-        return result;
+            .filter(|table| !table.addresses.is_empty())
     }
 }
 
-/// Get address lookup table account
-pub async fn get_address_lookup_table_account(
+/// Get address lookup table account, `None` when it is not in the cache
+pub fn get_address_lookup_table_account(
     lookup_table_address: &Pubkey,
-) -> AddressLookupTableAccount {
+) -> Option<AddressLookupTableAccount> {
     let cache = AddressLookupTableCache::get_instance();
     cache.get_table_content(lookup_table_address)
 }
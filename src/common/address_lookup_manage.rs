@@ -0,0 +1,208 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::{
+    address_lookup_table::{
+        instruction::{
+            close_lookup_table, create_lookup_table, deactivate_lookup_table, extend_lookup_table,
+        },
+        state::AddressLookupTable,
+    },
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    transaction::Transaction,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::common::address_lookup_cache::{AddressLookupTableCache, LOOKUP_TABLE_MAX_ADDRESSES};
+use crate::common::SolanaRpcClient;
+use crate::SolanaTrade;
+
+/// Maximum number of addresses one extend instruction may add
+pub const MAX_ADDRESSES_PER_EXTEND: usize = 30;
+/// Slots after deactivation before a table can be closed, the length of the slot hashes sysvar
+pub const LOOKUP_TABLE_DEACTIVATION_COOLDOWN_SLOTS: u64 = 513;
+/// How long to wait for newly added addresses to become usable
+const LOOKUP_TABLE_ACTIVATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Create an address lookup table with `payer` as authority
+///
+/// The empty table is registered in the `AddressLookupTableCache`, add addresses with
+/// `extend_lookup_table` before using it in a trade.
+pub async fn create_lookup_table_with(
+    rpc: Arc<SolanaRpcClient>,
+    payer: &Keypair,
+) -> Result<(Pubkey, Signature)> {
+    let recent_slot = rpc.get_slot_with_commitment(CommitmentConfig::finalized()).await?;
+    let (instruction, table_address) =
+        create_lookup_table(payer.pubkey(), payer.pubkey(), recent_slot);
+    let signature = send_lookup_table_instruction(&rpc, payer, instruction).await?;
+    println!(icon!(" ✅ ", "Created address lookup table {}"), table_address);
+    refresh_cache(rpc, &table_address).await?;
+    Ok((table_address, signature))
+}
+
+/// Add `addresses` to `table`, skipping the ones it already holds
+///
+/// Sends one transaction per 30 addresses. Addresses added in slot N can only be looked up
+/// from slot N + 1, so the call waits for that slot before the refreshed table is registered
+/// in the `AddressLookupTableCache`.
+///
+/// # Returns
+/// Returns the signatures of the extend transactions, empty when nothing was missing
+pub async fn extend_lookup_table_with(
+    rpc: Arc<SolanaRpcClient>,
+    payer: &Keypair,
+    table: Pubkey,
+    addresses: Vec<Pubkey>,
+) -> Result<Vec<Signature>> {
+    let account = rpc.get_account(&table).await?;
+    let existing = AddressLookupTable::deserialize(&account.data)
+        .map_err(|e| anyhow!("Invalid address lookup table {}: {}", table, e))?
+        .addresses
+        .to_vec();
+    let mut missing: Vec<Pubkey> = Vec::with_capacity(addresses.len());
+    for address in addresses {
+        if !existing.contains(&address) && !missing.contains(&address) {
+            missing.push(address);
+        }
+    }
+    if existing.len() + missing.len() > LOOKUP_TABLE_MAX_ADDRESSES {
+        return Err(anyhow!(
+            "Address lookup table {} has room for {} more addresses, {} requested",
+            table,
+            LOOKUP_TABLE_MAX_ADDRESSES - existing.len(),
+            missing.len()
+        ));
+    }
+    if missing.is_empty() {
+        refresh_cache(rpc, &table).await?;
+        return Ok(Vec::new());
+    }
+
+    let mut signatures = Vec::with_capacity(missing.len().div_ceil(MAX_ADDRESSES_PER_EXTEND));
+    for chunk in missing.chunks(MAX_ADDRESSES_PER_EXTEND) {
+        let instruction =
+            extend_lookup_table(table, payer.pubkey(), Some(payer.pubkey()), chunk.to_vec());
+        signatures.push(send_lookup_table_instruction(&rpc, payer, instruction).await?);
+    }
+    wait_for_warm_up(&rpc, &table).await?;
+    println!(icon!(" ✅ ", "Added {} addresses to lookup table {}"), missing.len(), table);
+    refresh_cache(rpc, &table).await?;
+    Ok(signatures)
+}
+
+/// Deactivate `table`, the first step of closing it
+///
+/// The table is dropped from the `AddressLookupTableCache` so new trades stop using it. It can
+/// be closed once `LOOKUP_TABLE_DEACTIVATION_COOLDOWN_SLOTS` slots have passed.
+pub async fn deactivate_lookup_table_with(
+    rpc: &SolanaRpcClient,
+    payer: &Keypair,
+    table: Pubkey,
+) -> Result<Signature> {
+    let instruction = deactivate_lookup_table(table, payer.pubkey());
+    let signature = send_lookup_table_instruction(rpc, payer, instruction).await?;
+    AddressLookupTableCache::get_instance().remove_address_lookup_table(&table);
+    Ok(signature)
+}
+
+/// Close a deactivated `table`, returning its rent to `payer`
+///
+/// Fails before sending when the table is still active or its cooldown has not passed.
+pub async fn close_lookup_table_with(
+    rpc: &SolanaRpcClient,
+    payer: &Keypair,
+    table: Pubkey,
+) -> Result<Signature> {
+    let account = rpc.get_account(&table).await?;
+    let deactivation_slot = AddressLookupTable::deserialize(&account.data)
+        .map_err(|e| anyhow!("Invalid address lookup table {}: {}", table, e))?
+        .meta
+        .deactivation_slot;
+    if deactivation_slot == u64::MAX {
+        return Err(anyhow!("Address lookup table {} is not deactivated", table));
+    }
+    let closable_slot = deactivation_slot.saturating_add(LOOKUP_TABLE_DEACTIVATION_COOLDOWN_SLOTS);
+    let current_slot = rpc.get_slot().await?;
+    if current_slot < closable_slot {
+        return Err(anyhow!(
+            "Address lookup table {} can be closed in {} slots",
+            table,
+            closable_slot - current_slot
+        ));
+    }
+    let instruction = close_lookup_table(table, payer.pubkey(), payer.pubkey());
+    let signature = send_lookup_table_instruction(rpc, payer, instruction).await?;
+    AddressLookupTableCache::get_instance().remove_address_lookup_table(&table);
+    Ok(signature)
+}
+
+/// Wait until the slot after the last extension of `table`
+async fn wait_for_warm_up(rpc: &SolanaRpcClient, table: &Pubkey) -> Result<()> {
+    let extended_slot = rpc.get_slot().await?;
+    let start = Instant::now();
+    while rpc.get_slot().await? <= extended_slot {
+        if start.elapsed() > LOOKUP_TABLE_ACTIVATION_TIMEOUT {
+            return Err(anyhow!("Address lookup table {} was not activated", table));
+        }
+        tokio::time::sleep(Duration::from_millis(400)).await;
+    }
+    Ok(())
+}
+
+async fn refresh_cache(rpc: Arc<SolanaRpcClient>, table: &Pubkey) -> Result<()> {
+    AddressLookupTableCache::get_instance()
+        .set_address_lookup_table(rpc, table)
+        .await
+        .map_err(|e| anyhow!("Failed to load address lookup table {}: {}", table, e))
+}
+
+async fn send_lookup_table_instruction(
+    rpc: &SolanaRpcClient,
+    payer: &Keypair,
+    instruction: Instruction,
+) -> Result<Signature> {
+    let recent_blockhash = rpc.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_blockhash,
+    );
+    Ok(rpc.send_and_confirm_transaction(&transaction).await?)
+}
+
+impl SolanaTrade {
+    /// Create an address lookup table owned by the payer, see `create_lookup_table_with`
+    #[inline]
+    pub async fn create_lookup_table(&self) -> Result<(Pubkey, Signature)> {
+        create_lookup_table_with(self.rpc.clone(), self.payer.as_ref()).await
+    }
+
+    /// Add `addresses` to a table owned by the payer, see `extend_lookup_table_with`
+    #[inline]
+    pub async fn extend_lookup_table(
+        &self,
+        table: Pubkey,
+        addresses: Vec<Pubkey>,
+    ) -> Result<Vec<Signature>> {
+        extend_lookup_table_with(self.rpc.clone(), self.payer.as_ref(), table, addresses).await
+    }
+
+    /// Deactivate a table owned by the payer, see `deactivate_lookup_table_with`
+    #[inline]
+    pub async fn deactivate_lookup_table(&self, table: Pubkey) -> Result<Signature> {
+        deactivate_lookup_table_with(&self.rpc, self.payer.as_ref(), table).await
+    }
+
+    /// Close a deactivated table owned by the payer, see `close_lookup_table_with`
+    #[inline]
+    pub async fn close_lookup_table(&self, table: Pubkey) -> Result<Signature> {
+        close_lookup_table_with(&self.rpc, self.payer.as_ref(), table).await
+    }
+}
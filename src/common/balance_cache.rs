@@ -0,0 +1,45 @@
+use dashmap::DashMap;
+use solana_sdk::pubkey::Pubkey;
+use std::{
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+
+/// How long a fetched token balance is reused by whole-balance sells
+pub const TOKEN_BALANCE_TTL: Duration = Duration::from_secs(2);
+
+/// TokenBalanceCache singleton holding the token balances fetched for whole-balance sells
+///
+/// Keyed by wallet and mint. Entries expire after `TOKEN_BALANCE_TTL` and are dropped as soon
+/// as a trade of the wallet in the mint is sent, so a sell never reuses a balance that trade
+/// may have changed.
+pub struct TokenBalanceCache {
+    balances: DashMap<(Pubkey, Pubkey), (u64, Instant)>,
+}
+
+static TOKEN_BALANCE_CACHE: OnceLock<Arc<TokenBalanceCache>> = OnceLock::new();
+
+impl TokenBalanceCache {
+    /// Get TokenBalanceCache singleton instance
+    pub fn get_instance() -> Arc<TokenBalanceCache> {
+        TOKEN_BALANCE_CACHE
+            .get_or_init(|| Arc::new(TokenBalanceCache { balances: DashMap::new() }))
+            .clone()
+    }
+
+    /// Cached balance of `mint` held by `wallet`, `None` when missing or expired
+    pub fn get(&self, wallet: &Pubkey, mint: &Pubkey) -> Option<u64> {
+        let entry = self.balances.get(&(*wallet, *mint))?;
+        let (amount, fetched_at) = *entry;
+        (fetched_at.elapsed() < TOKEN_BALANCE_TTL).then_some(amount)
+    }
+
+    pub fn insert(&self, wallet: Pubkey, mint: Pubkey, amount: u64) {
+        self.balances.insert((wallet, mint), (amount, Instant::now()));
+    }
+
+    /// Drop the balance of `mint` held by `wallet`, called once a trade of it is sent
+    pub fn invalidate(&self, wallet: &Pubkey, mint: &Pubkey) {
+        self.balances.remove(&(*wallet, *mint));
+    }
+}
@@ -0,0 +1,148 @@
+use parking_lot::Mutex;
+use solana_hash::Hash;
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, OnceLock,
+};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+use crate::common::{rpc_race, SolanaRpcClient};
+
+/// How often the background task fetches the latest blockhash
+pub const DEFAULT_BLOCKHASH_REFRESH_INTERVAL: Duration = Duration::from_millis(400);
+/// Oldest blockhash served, in slots; a blockhash stays valid for 150 blocks
+pub const DEFAULT_BLOCKHASH_MAX_AGE_SLOTS: u64 = 60;
+// Slot time used to age a blockhash between refreshes
+const SLOT_DURATION: Duration = Duration::from_millis(400);
+
+/// A blockhash with the slot it was fetched at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CachedBlockhash {
+    pub blockhash: Hash,
+    pub slot: u64,
+    pub fetched_at: Instant,
+}
+
+impl CachedBlockhash {
+    /// Slots passed since the fetch, estimated from the wall clock
+    pub fn age_slots(&self) -> u64 {
+        (self.fetched_at.elapsed().as_millis() / SLOT_DURATION.as_millis()) as u64
+    }
+}
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum BlockhashCacheError {
+    #[error("Blockhash cache has no blockhash yet: {0}")]
+    NotReady(String),
+    #[error(
+        "Cached blockhash from slot {slot} is {age_slots} slots old, the limit is {max_age_slots}"
+    )]
+    Stale { slot: u64, age_slots: u64, max_age_slots: u64 },
+}
+
+/// BlockhashCache singleton refreshing the latest blockhash in the background
+///
+/// Trades called without a `recent_blockhash` take it from here instead of fetching it on the
+/// hot path. The refresher starts on first use, or explicitly with `start`.
+pub struct BlockhashCache {
+    latest: Mutex<Option<CachedBlockhash>>,
+    max_age_slots: AtomicU64,
+    commitment: Mutex<Option<CommitmentConfig>>,
+    refresher: Mutex<Option<JoinHandle<()>>>,
+}
+
+static BLOCKHASH_CACHE: OnceLock<Arc<BlockhashCache>> = OnceLock::new();
+
+impl BlockhashCache {
+    /// Get BlockhashCache singleton instance
+    pub fn get_instance() -> Arc<BlockhashCache> {
+        BLOCKHASH_CACHE
+            .get_or_init(|| {
+                Arc::new(BlockhashCache {
+                    latest: Mutex::new(None),
+                    max_age_slots: AtomicU64::new(DEFAULT_BLOCKHASH_MAX_AGE_SLOTS),
+                    commitment: Mutex::new(None),
+                    refresher: Mutex::new(None),
+                })
+            })
+            .clone()
+    }
+
+    /// Refuse blockhashes older than `max_age_slots`
+    pub fn set_max_age_slots(&self, max_age_slots: u64) {
+        self.max_age_slots.store(max_age_slots, Ordering::Relaxed);
+    }
+
+    /// Fetch blockhashes at `commitment` instead of the commitment of the RPC client, `None`
+    /// restores the client's
+    pub fn set_commitment(&self, commitment: Option<CommitmentConfig>) {
+        *self.commitment.lock() = commitment;
+    }
+
+    /// Latest cached blockhash, whatever its age
+    pub fn latest(&self) -> Option<CachedBlockhash> {
+        *self.latest.lock()
+    }
+
+    /// Cached blockhash, or an error when there is none or it is too old to land
+    pub fn get(&self) -> Result<Hash, BlockhashCacheError> {
+        let cached = self
+            .latest()
+            .ok_or_else(|| BlockhashCacheError::NotReady("not fetched yet".to_string()))?;
+        let age_slots = cached.age_slots();
+        let max_age_slots = self.max_age_slots.load(Ordering::Relaxed);
+        if age_slots > max_age_slots {
+            return Err(BlockhashCacheError::Stale { slot: cached.slot, age_slots, max_age_slots });
+        }
+        Ok(cached.blockhash)
+    }
+
+    /// Cached blockhash, starting the refresher if needed and fetching once when the cache is
+    /// empty
+    pub async fn get_or_fetch(
+        self: &Arc<Self>,
+        rpc: &Arc<SolanaRpcClient>,
+    ) -> Result<Hash, BlockhashCacheError> {
+        if self.latest().is_none() {
+            self.refresh(rpc).await.map_err(|e| BlockhashCacheError::NotReady(e.to_string()))?;
+        }
+        self.start(rpc.clone(), DEFAULT_BLOCKHASH_REFRESH_INTERVAL);
+        self.get()
+    }
+
+    /// Fetch the latest blockhash and the current slot, raced across the `rpc_race` fallbacks
+    pub async fn refresh(&self, rpc: &SolanaRpcClient) -> Result<(), anyhow::Error> {
+        let commitment = *self.commitment.lock();
+        let (blockhash, slot) = rpc_race::get_latest_blockhash_and_slot(rpc, commitment).await?;
+        let cached = CachedBlockhash { blockhash, slot, fetched_at: Instant::now() };
+        *self.latest.lock() = Some(cached);
+        Ok(())
+    }
+
+    /// Refresh every `interval` in a background task, does nothing when it already runs
+    pub fn start(self: &Arc<Self>, rpc: Arc<SolanaRpcClient>, interval: Duration) {
+        let mut refresher = self.refresher.lock();
+        if refresher.as_ref().is_some_and(|task| !task.is_finished()) {
+            return;
+        }
+        let cache = self.clone();
+        *refresher = Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = cache.refresh(&rpc).await {
+                    log::warn!(icon!("⚠️ ", "Blockhash refresh failed: {}"), e);
+                }
+            }
+        }));
+    }
+
+    /// Stop the background refresh, the cached blockhash ages out afterwards
+    pub fn stop(&self) {
+        if let Some(task) = self.refresher.lock().take() {
+            task.abort();
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use solana_system_interface::instruction::transfer;
+
+/// Trades a commission is charged on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommissionOn {
+    Buy,
+    Sell,
+    Both,
+}
+
+/// Commission paid to `recipient` out of every matching trade
+///
+/// The commission is a system transfer appended to the trade transaction, so it is only paid
+/// when the trade lands. Buys are charged on the SOL spent, sells on the expected SOL output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Commission {
+    pub recipient: Pubkey,
+    /// Share of the trade in basis points
+    pub bps: u16,
+    pub on: CommissionOn,
+    /// Commissions below this amount are not charged
+    pub min_lamports: u64,
+}
+
+impl Commission {
+    pub fn new(recipient: Pubkey, bps: u16, on: CommissionOn) -> Self {
+        Self { recipient, bps, on, min_lamports: 0 }
+    }
+
+    pub fn with_min_lamports(mut self, min_lamports: u64) -> Self {
+        self.min_lamports = min_lamports;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.bps > 10_000 {
+            return Err(anyhow!("Commission of {} bps is above 100%", self.bps));
+        }
+        Ok(())
+    }
+
+    pub fn applies_to(&self, is_buy: bool) -> bool {
+        match self.on {
+            CommissionOn::Buy => is_buy,
+            CommissionOn::Sell => !is_buy,
+            CommissionOn::Both => true,
+        }
+    }
+
+    /// Commission on a trade of `trade_lamports`, `None` when it is not charged
+    pub fn lamports(&self, is_buy: bool, trade_lamports: u64) -> Option<u64> {
+        if !self.applies_to(is_buy) {
+            return None;
+        }
+        let lamports = (trade_lamports as u128 * self.bps as u128 / 10_000) as u64;
+        (lamports > 0 && lamports >= self.min_lamports).then_some(lamports)
+    }
+
+    /// Transfer of `lamports` from `payer` to the recipient
+    pub fn instruction(&self, payer: &Pubkey, lamports: u64) -> Instruction {
+        transfer(payer, &self.recipient, lamports)
+    }
+}
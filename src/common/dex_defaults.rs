@@ -0,0 +1,306 @@
+//! Per-protocol trade defaults configured once on `TradeConfig`
+//!
+//! Slippage is resolved in this order, the first value set wins:
+//!
+//! 1. the slippage of the trade request
+//! 2. `DexDefaults::slippage_bps` of the trade's `DexType` in `TradeConfig::per_dex_defaults`
+//! 3. `TradeConfig::default_slippage_bps`
+//! 4. `DEFAULT_SLIPPAGE`
+//!
+//! The compute unit sizing follows the same order without the request step:
+//! `DexDefaults::cu_profile`, then `SolanaTrade::cu_sizing`. The SOL bounds only apply to buys,
+//! whose SOL amount is known before sending, and have no global fallback.
+
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+
+use crate::{
+    common::labels::TradeLabels, constants::trade::trade::DEFAULT_SLIPPAGE,
+    trading::common::cu_sizing::CuSizing, trading::factory::DexType,
+};
+
+/// Label key of the slippage a trade was sent with, see `AppliedDefaults::label`
+pub const APPLIED_SLIPPAGE_LABEL: &str = "slippage_bps";
+/// Label key of where that slippage came from, a `DefaultSource::as_str`
+pub const APPLIED_SLIPPAGE_SOURCE_LABEL: &str = "slippage_source";
+
+/// Defaults of one protocol, each used when the trade request leaves it out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DexDefaults {
+    pub slippage_bps: Option<u64>,
+    /// Largest buy in lamports, larger buys fail with `TradeAmountOutOfBounds`
+    pub max_sol_per_trade: Option<u64>,
+    /// Smallest buy in lamports, smaller buys fail with `TradeAmountOutOfBounds`
+    pub min_sol_per_trade: Option<u64>,
+    /// Compute unit sizing of the protocol's trades instead of `SolanaTrade::cu_sizing`
+    pub cu_profile: Option<CuSizing>,
+}
+
+impl DexDefaults {
+    pub fn with_slippage_bps(mut self, slippage_bps: u64) -> Self {
+        self.slippage_bps = Some(slippage_bps);
+        self
+    }
+
+    pub fn with_max_sol_per_trade(mut self, lamports: u64) -> Self {
+        self.max_sol_per_trade = Some(lamports);
+        self
+    }
+
+    pub fn with_min_sol_per_trade(mut self, lamports: u64) -> Self {
+        self.min_sol_per_trade = Some(lamports);
+        self
+    }
+
+    pub fn with_cu_profile(mut self, cu_profile: CuSizing) -> Self {
+        self.cu_profile = Some(cu_profile);
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if let Some(slippage_bps) = self.slippage_bps {
+            if slippage_bps > 10_000 {
+                return Err(anyhow!("Slippage of {} bps is above 10000", slippage_bps));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_sol_per_trade, self.max_sol_per_trade) {
+            if min > max {
+                return Err(anyhow!(
+                    "min_sol_per_trade {} lamports is above max_sol_per_trade {} lamports",
+                    min,
+                    max
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check a buy of `sol_amount` lamports against the bounds
+    pub fn check_sol_amount(
+        &self,
+        dex_type: &DexType,
+        sol_amount: u64,
+    ) -> Result<(), TradeAmountOutOfBounds> {
+        let below = self.min_sol_per_trade.is_some_and(|min| sol_amount < min);
+        let above = self.max_sol_per_trade.is_some_and(|max| sol_amount > max);
+        if below || above {
+            return Err(TradeAmountOutOfBounds {
+                dex_type: dex_type.clone(),
+                sol_amount,
+                min_sol_per_trade: self.min_sol_per_trade,
+                max_sol_per_trade: self.max_sol_per_trade,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A buy was rejected before building because its SOL amount is outside the `DexDefaults`
+/// bounds of its protocol
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "{dex_type:?} buy of {sol_amount} lamports is outside the configured bounds \
+     (min {min_sol_per_trade:?}, max {max_sol_per_trade:?})"
+)]
+pub struct TradeAmountOutOfBounds {
+    pub dex_type: DexType,
+    pub sol_amount: u64,
+    pub min_sol_per_trade: Option<u64>,
+    pub max_sol_per_trade: Option<u64>,
+}
+
+/// Where an applied value came from, in resolution order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultSource {
+    Request,
+    DexDefault,
+    GlobalDefault,
+    Crate,
+}
+
+impl DefaultSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DefaultSource::Request => "request",
+            DefaultSource::DexDefault => "dex_default",
+            DefaultSource::GlobalDefault => "global_default",
+            DefaultSource::Crate => "crate",
+        }
+    }
+}
+
+/// Values a trade is sent with after resolving its defaults
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AppliedDefaults {
+    pub slippage_bps: u64,
+    pub slippage_source: DefaultSource,
+    pub cu_sizing: CuSizing,
+}
+
+impl AppliedDefaults {
+    /// Resolve the slippage and compute unit sizing of a trade on `dex_type`
+    pub fn resolve(
+        request_slippage_bps: Option<u64>,
+        dex_type: &DexType,
+        per_dex_defaults: &HashMap<DexType, DexDefaults>,
+        default_slippage_bps: Option<u64>,
+        cu_sizing: CuSizing,
+    ) -> Self {
+        let dex_defaults = per_dex_defaults.get(dex_type);
+        let (slippage_bps, slippage_source) = match (
+            request_slippage_bps,
+            dex_defaults.and_then(|defaults| defaults.slippage_bps),
+            default_slippage_bps,
+        ) {
+            (Some(slippage_bps), _, _) => (slippage_bps, DefaultSource::Request),
+            (None, Some(slippage_bps), _) => (slippage_bps, DefaultSource::DexDefault),
+            (None, None, Some(slippage_bps)) => (slippage_bps, DefaultSource::GlobalDefault),
+            (None, None, None) => (DEFAULT_SLIPPAGE, DefaultSource::Crate),
+        };
+        let cu_sizing = dex_defaults.and_then(|defaults| defaults.cu_profile).unwrap_or(cu_sizing);
+        Self { slippage_bps, slippage_source, cu_sizing }
+    }
+
+    /// `labels` with the applied slippage and its source added
+    pub fn label(&self, labels: Option<TradeLabels>) -> Option<TradeLabels> {
+        let mut labels = labels.unwrap_or_default();
+        labels.insert(APPLIED_SLIPPAGE_LABEL.to_string(), self.slippage_bps.to_string());
+        labels.insert(
+            APPLIED_SLIPPAGE_SOURCE_LABEL.to_string(),
+            self.slippage_source.as_str().to_string(),
+        );
+        Some(labels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const REQUEST: u64 = 300;
+    const DEX_DEFAULT: u64 = 1_500;
+    const GLOBAL_DEFAULT: u64 = 700;
+
+    fn per_dex_defaults(dex_default: bool) -> HashMap<DexType, DexDefaults> {
+        let mut per_dex_defaults =
+            HashMap::from([(DexType::RaydiumAmmV4, DexDefaults::default().with_slippage_bps(100))]);
+        if dex_default {
+            per_dex_defaults
+                .insert(DexType::PumpFun, DexDefaults::default().with_slippage_bps(DEX_DEFAULT));
+        }
+        per_dex_defaults
+    }
+
+    #[test]
+    fn slippage_resolution_matrix() {
+        // (request, dex default, global default) => applied slippage and its source
+        let matrix = [
+            ((true, true, true), (REQUEST, DefaultSource::Request)),
+            ((true, true, false), (REQUEST, DefaultSource::Request)),
+            ((true, false, true), (REQUEST, DefaultSource::Request)),
+            ((true, false, false), (REQUEST, DefaultSource::Request)),
+            ((false, true, true), (DEX_DEFAULT, DefaultSource::DexDefault)),
+            ((false, true, false), (DEX_DEFAULT, DefaultSource::DexDefault)),
+            ((false, false, true), (GLOBAL_DEFAULT, DefaultSource::GlobalDefault)),
+            ((false, false, false), (DEFAULT_SLIPPAGE, DefaultSource::Crate)),
+        ];
+        for ((request, dex_default, global_default), (slippage_bps, source)) in matrix {
+            let applied = AppliedDefaults::resolve(
+                request.then_some(REQUEST),
+                &DexType::PumpFun,
+                &per_dex_defaults(dex_default),
+                global_default.then_some(GLOBAL_DEFAULT),
+                CuSizing::Static,
+            );
+            assert_eq!(
+                (applied.slippage_bps, applied.slippage_source),
+                (slippage_bps, source),
+                "request {}, dex default {}, global default {}",
+                request,
+                dex_default,
+                global_default
+            );
+        }
+    }
+
+    #[test]
+    fn defaults_of_other_protocols_do_not_apply() {
+        let applied = AppliedDefaults::resolve(
+            None,
+            &DexType::Bonk,
+            &per_dex_defaults(true),
+            None,
+            CuSizing::Static,
+        );
+        assert_eq!(
+            (applied.slippage_bps, applied.slippage_source),
+            (DEFAULT_SLIPPAGE, DefaultSource::Crate)
+        );
+    }
+
+    #[test]
+    fn cu_profile_of_the_protocol_replaces_the_client_sizing() {
+        let simulated = CuSizing::Simulated { margin_pct: 20, cache_ttl: Duration::from_secs(30) };
+        let per_dex_defaults = HashMap::from([
+            (DexType::RaydiumClmm, DexDefaults::default().with_cu_profile(simulated)),
+            (DexType::PumpFun, DexDefaults::default().with_slippage_bps(DEX_DEFAULT)),
+        ]);
+        let resolve = |dex_type| {
+            AppliedDefaults::resolve(None, &dex_type, &per_dex_defaults, None, CuSizing::Static)
+                .cu_sizing
+        };
+        assert_eq!(resolve(DexType::RaydiumClmm), simulated);
+        assert_eq!(resolve(DexType::PumpFun), CuSizing::Static);
+        assert_eq!(resolve(DexType::Bonk), CuSizing::Static);
+    }
+
+    #[test]
+    fn sol_bounds_are_inclusive() {
+        let defaults = DexDefaults::default()
+            .with_min_sol_per_trade(10_000_000)
+            .with_max_sol_per_trade(500_000_000);
+        for sol_amount in [10_000_000, 100_000_000, 500_000_000] {
+            assert_eq!(defaults.check_sol_amount(&DexType::Bonk, sol_amount), Ok(()));
+        }
+        for sol_amount in [9_999_999, 500_000_001] {
+            assert_eq!(
+                defaults.check_sol_amount(&DexType::Bonk, sol_amount),
+                Err(TradeAmountOutOfBounds {
+                    dex_type: DexType::Bonk,
+                    sol_amount,
+                    min_sol_per_trade: Some(10_000_000),
+                    max_sol_per_trade: Some(500_000_000),
+                })
+            );
+        }
+        let unbounded = DexDefaults::default();
+        assert_eq!(unbounded.check_sol_amount(&DexType::Bonk, u64::MAX), Ok(()));
+    }
+
+    #[test]
+    fn invalid_defaults_are_rejected() {
+        assert!(DexDefaults::default().with_slippage_bps(10_001).validate().is_err());
+        let inverted = DexDefaults::default().with_min_sol_per_trade(2).with_max_sol_per_trade(1);
+        assert!(inverted.validate().is_err());
+        assert!(DexDefaults::default().with_slippage_bps(10_000).validate().is_ok());
+    }
+
+    #[test]
+    fn applied_slippage_is_echoed_into_labels() {
+        let applied = AppliedDefaults::resolve(
+            None,
+            &DexType::PumpFun,
+            &per_dex_defaults(true),
+            Some(GLOBAL_DEFAULT),
+            CuSizing::Static,
+        );
+        let labels = TradeLabels::from([("strategy".to_string(), "sniper".to_string())]);
+        let labels = applied.label(Some(labels)).unwrap();
+        assert_eq!(labels["strategy"], "sniper");
+        assert_eq!(labels[APPLIED_SLIPPAGE_LABEL], "1500");
+        assert_eq!(labels[APPLIED_SLIPPAGE_SOURCE_LABEL], "dex_default");
+        assert_eq!(applied.label(None).unwrap().len(), 2);
+    }
+}
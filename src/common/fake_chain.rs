@@ -0,0 +1,131 @@
+//! In-memory RPC for tests that count or script the requests of a code path
+
+use std::{
+    collections::{HashMap, VecDeque},
+    str::FromStr,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use solana_rpc_client::{
+    rpc_client::RpcClientConfig,
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_rpc_client_api::{
+    client_error::Result as ClientResult,
+    request::{RpcError, RpcRequest, RpcResponseErrorData},
+};
+use solana_sdk::{
+    account::Account, commitment_config::CommitmentConfig, program_pack::Pack, pubkey::Pubkey,
+};
+use spl_token_2022::state::{Account as TokenAccount, AccountState};
+
+use crate::common::SolanaRpcClient;
+
+/// RPC answering `getMultipleAccounts` from `accounts` and recording every request
+///
+/// Scripted responses of a request kind are returned first, in order; an `Err` becomes an RPC
+/// response error with that message. Other requests without a script fail.
+#[derive(Default)]
+pub struct FakeChain {
+    accounts: HashMap<Pubkey, Account>,
+    /// A `getMultipleAccounts` call asking for one of these fails as a whole
+    failing: Vec<Pubkey>,
+    scripted: Mutex<HashMap<RpcRequest, VecDeque<Result<Value, String>>>>,
+    requests: Arc<Mutex<Vec<RpcRequest>>>,
+}
+
+impl FakeChain {
+    pub fn with(mut self, address: Pubkey, account: Account) -> Self {
+        self.accounts.insert(address, account);
+        self
+    }
+
+    pub fn failing(mut self, address: Pubkey) -> Self {
+        self.failing.push(address);
+        self
+    }
+
+    pub fn script(self, request: RpcRequest, response: Result<Value, String>) -> Self {
+        self.scripted.lock().entry(request).or_default().push_back(response);
+        self
+    }
+
+    /// The client and the requests it sends
+    pub fn client(self) -> (SolanaRpcClient, Arc<Mutex<Vec<RpcRequest>>>) {
+        let requests = self.requests.clone();
+        let config = RpcClientConfig::with_commitment(CommitmentConfig::confirmed());
+        (SolanaRpcClient::new_sender(self, config), requests)
+    }
+
+    fn multiple_accounts(&self, params: &Value) -> ClientResult<Value> {
+        let addresses: Vec<Pubkey> = params[0]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|address| Pubkey::from_str(address.as_str().unwrap()).unwrap())
+            .collect();
+        if addresses.iter().any(|address| self.failing.contains(address)) {
+            return Err(RpcError::ForUser("connection reset".to_string()).into());
+        }
+        let accounts: Vec<Value> = addresses
+            .iter()
+            .map(|address| match self.accounts.get(address) {
+                Some(account) => json!({
+                    "lamports": account.lamports,
+                    "data": [STANDARD.encode(&account.data), "base64"],
+                    "owner": account.owner.to_string(),
+                    "executable": false,
+                    "rentEpoch": 0,
+                    "space": account.data.len(),
+                }),
+                None => Value::Null,
+            })
+            .collect();
+        Ok(json!({ "context": { "slot": 1 }, "value": accounts }))
+    }
+}
+
+#[async_trait]
+impl RpcSender for FakeChain {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        self.requests.lock().push(request);
+        let scripted = self.scripted.lock().get_mut(&request).and_then(VecDeque::pop_front);
+        match scripted {
+            Some(Ok(value)) => Ok(value),
+            Some(Err(message)) => Err(RpcError::RpcResponseError {
+                code: -32602,
+                message,
+                data: RpcResponseErrorData::Empty,
+            }
+            .into()),
+            None if request == RpcRequest::GetMultipleAccounts => self.multiple_accounts(&params),
+            None => Err(RpcError::ForUser(format!("{} is not served", request)).into()),
+        }
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        RpcTransportStats::default()
+    }
+
+    fn url(&self) -> String {
+        "fake-chain".to_string()
+    }
+}
+
+/// Initialized token account of `mint` holding `amount`, owned by `token_program`
+pub fn token_account(mint: Pubkey, amount: u64, token_program: Pubkey) -> Account {
+    let mut data = vec![0; TokenAccount::LEN];
+    let state = TokenAccount {
+        mint,
+        owner: Pubkey::new_unique(),
+        amount,
+        state: AccountState::Initialized,
+        ..Default::default()
+    };
+    state.pack_into_slice(&mut data);
+    Account { lamports: 2_039_280, data, owner: token_program, ..Default::default() }
+}
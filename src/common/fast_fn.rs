@@ -1,4 +1,5 @@
 use clru::CLruCache;
+use dashmap::DashSet;
 use once_cell::sync::Lazy;
 use parking_lot::RwLock;
 use solana_sdk::{
@@ -9,11 +10,71 @@ use spl_associated_token_account::{
     get_associated_token_address_with_program_id, ID as ASSOCIATED_TOKEN_PROGRAM_ID,
 };
 use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 const MAX_PDA_CACHE_SIZE: usize = 10000;
 const MAX_ATA_CACHE_SIZE: usize = 10000;
 const MAX_INSTRUCTION_CACHE_SIZE: usize = 10000;
 
+// --------------------- Cache Stats ---------------------
+
+/// Hit and miss counts of one cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheCounters {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Hit and miss counts of all global caches since process start
+///
+/// A high miss count under concurrency means tasks are computing the same entries at once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub instruction: CacheCounters,
+    pub pda: CacheCounters,
+    pub ata: CacheCounters,
+}
+
+struct AtomicCacheCounters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl AtomicCacheCounters {
+    const fn new() -> Self {
+        Self { hits: AtomicU64::new(0), misses: AtomicU64::new(0) }
+    }
+
+    #[inline]
+    fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> CacheCounters {
+        CacheCounters {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+static INSTRUCTION_CACHE_COUNTERS: AtomicCacheCounters = AtomicCacheCounters::new();
+static PDA_CACHE_COUNTERS: AtomicCacheCounters = AtomicCacheCounters::new();
+static ATA_CACHE_COUNTERS: AtomicCacheCounters = AtomicCacheCounters::new();
+
+pub fn cache_stats() -> CacheStats {
+    CacheStats {
+        instruction: INSTRUCTION_CACHE_COUNTERS.snapshot(),
+        pda: PDA_CACHE_COUNTERS.snapshot(),
+        ata: ATA_CACHE_COUNTERS.snapshot(),
+    }
+}
+
 // --------------------- Instruction Cache ---------------------
 
 /// Instruction cache key for uniquely identifying instruction types and parameters
@@ -46,9 +107,11 @@ where
     {
         let cache = INSTRUCTION_CACHE.read();
         if let Some(cached_instruction) = cache.peek(&cache_key) {
+            INSTRUCTION_CACHE_COUNTERS.hit();
             return cached_instruction.clone();
         }
     }
+    INSTRUCTION_CACHE_COUNTERS.miss();
 
     // Cache miss, compute new instruction
     let instruction = compute_fn();
@@ -64,6 +127,22 @@ where
 
 // --------------------- Associated Token Account ---------------------
 
+/// Whether a seeded account replaces the ATA of `mint`
+///
+/// Never for wSOL or SOL. Token-2022 mints need their account size registered with
+/// `seed::set_token_2022_mint` or `seed::update_token_2022_mint`, otherwise the canonical ATA
+/// is used, which the ATA program sizes on chain.
+fn seed_account_supported(mint: &Pubkey, token_program: &Pubkey) -> bool {
+    if mint.eq(&crate::constants::WSOL_TOKEN_ACCOUNT)
+        || mint.eq(&crate::constants::SOL_TOKEN_ACCOUNT)
+    {
+        return false;
+    }
+    token_program.eq(&spl_token::ID)
+        || (token_program.eq(&spl_token_2022::ID)
+            && super::seed::get_token_2022_account_size(mint).is_some())
+}
+
 pub fn create_associated_token_account_idempotent_fast_use_seed(
     payer: &Pubkey,
     owner: &Pubkey,
@@ -90,6 +169,7 @@ pub fn _create_associated_token_account_idempotent_fast(
     token_program: &Pubkey,
     use_seed: bool,
 ) -> Vec<Instruction> {
+    let use_seed = use_seed && seed_account_supported(mint, token_program);
     // Create cache key
     let cache_key = InstructionCacheKey::CreateAssociatedTokenAccount {
         payer: *payer,
@@ -99,13 +179,7 @@ pub fn _create_associated_token_account_idempotent_fast(
         use_seed,
     };
 
-    // Only use seed if the mint address is not wSOL or SOL
-    // token 2022 测试不成功（TODO）
-    if use_seed
-        && !mint.eq(&crate::constants::WSOL_TOKEN_ACCOUNT)
-        && !mint.eq(&crate::constants::SOL_TOKEN_ACCOUNT)
-        && token_program.eq(&spl_token::ID)
-    {
+    if use_seed {
         // Use cache to get instruction
         get_cached_instructions(cache_key, || {
             super::seed::create_associated_token_account_use_seed(payer, owner, mint, token_program)
@@ -161,9 +235,11 @@ where
     {
         let cache = PDA_CACHE.read();
         if let Some(cached_pda) = cache.peek(&cache_key) {
+            PDA_CACHE_COUNTERS.hit();
             return Some(*cached_pda);
         }
     }
+    PDA_CACHE_COUNTERS.miss();
 
     // Cache miss, compute new PDA
     let pda_result = compute_fn();
@@ -226,6 +302,7 @@ fn _get_associated_token_address_with_program_id_fast(
     token_program_id: &Pubkey,
     use_seed: bool,
 ) -> Pubkey {
+    let use_seed = use_seed && seed_account_supported(token_mint_address, token_program_id);
     let cache_key = AtaCacheKey {
         wallet_address: *wallet_address,
         token_mint_address: *token_mint_address,
@@ -237,18 +314,14 @@ fn _get_associated_token_address_with_program_id_fast(
     {
         let cache = ATA_CACHE.read();
         if let Some(cached_ata) = cache.peek(&cache_key) {
+            ATA_CACHE_COUNTERS.hit();
             return *cached_ata;
         }
     }
+    ATA_CACHE_COUNTERS.miss();
 
     // Cache miss, compute new ATA
-    // Only use seed if the token mint address is not wSOL or SOL
-    // token 2022 测试不成功（TODO）
-    let ata = if use_seed
-        && !token_mint_address.eq(&crate::constants::WSOL_TOKEN_ACCOUNT)
-        && !token_mint_address.eq(&crate::constants::SOL_TOKEN_ACCOUNT)
-        && token_program_id.eq(&spl_token::ID)
-    {
+    let ata = if use_seed {
         super::seed::get_associated_token_address_with_program_id_use_seed(
             wallet_address,
             token_mint_address,
@@ -274,6 +347,19 @@ fn _get_associated_token_address_with_program_id_fast(
 
 // --------------------- Initialize Accounts ---------------------
 
+// Payers whose accounts were already warmed by `fast_init_once`
+static INITIALIZED_PAYERS: Lazy<DashSet<Pubkey>> = Lazy::new(DashSet::new);
+
+/// `fast_init` the first time a payer is seen
+///
+/// The caches are keyed by payer, so trades with per-trade payers stay correct without it;
+/// it only moves the PDA and ATA derivations of a new payer off its first trade.
+pub fn fast_init_once(payer: &Pubkey) {
+    if INITIALIZED_PAYERS.insert(*payer) {
+        fast_init(payer);
+    }
+}
+
 pub fn fast_init(payer: &Pubkey) {
     // Get PumpFun user volume accumulator PDA
     crate::instruction::utils::pumpfun::get_user_volume_accumulator_pda(payer);
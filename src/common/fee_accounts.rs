@@ -0,0 +1,368 @@
+use anyhow::anyhow;
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_rpc_client_api::config::RpcTransactionConfig;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use solana_transaction_status::{option_serializer::OptionSerializer, UiTransactionEncoding};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::common::store::KeyValueStore;
+use crate::common::SolanaRpcClient;
+use crate::constants::trade::trade::{
+    FEE_OBSERVER_DEFAULT_INTERVAL_SECS, FEE_OBSERVER_DEFAULT_SAMPLE_SIZE,
+};
+use crate::instruction::utils::{pumpfun, pumpswap};
+
+const STORE_KEY: &str = "fee_accounts";
+
+/// Fee accounts the builders take from the registry instead of a constant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FeeAccount {
+    PumpFunFeeRecipient,
+    PumpSwapFeeRecipient,
+}
+
+impl FeeAccount {
+    pub const ALL: [FeeAccount; 2] =
+        [FeeAccount::PumpFunFeeRecipient, FeeAccount::PumpSwapFeeRecipient];
+
+    /// Compiled-in address used until a value is learned or pinned
+    pub fn default_address(&self) -> Pubkey {
+        match self {
+            FeeAccount::PumpFunFeeRecipient => pumpfun::global_constants::FEE_RECIPIENT,
+            FeeAccount::PumpSwapFeeRecipient => pumpswap::accounts::FEE_RECIPIENT,
+        }
+    }
+
+    /// Program whose buy and sell instructions carry the account
+    pub fn program(&self) -> Pubkey {
+        match self {
+            FeeAccount::PumpFunFeeRecipient => pumpfun::accounts::PUMPFUN,
+            FeeAccount::PumpSwapFeeRecipient => pumpswap::accounts::AMM_PROGRAM,
+        }
+    }
+
+    /// Position of the account in the buy and sell instructions
+    fn instruction_index(&self) -> usize {
+        match self {
+            FeeAccount::PumpFunFeeRecipient => 1,
+            FeeAccount::PumpSwapFeeRecipient => 9,
+        }
+    }
+
+    /// Whether `data` is a buy or sell instruction of the program
+    fn is_trade_instruction(&self, data: &[u8]) -> bool {
+        let (buy, sell) = match self {
+            FeeAccount::PumpFunFeeRecipient => {
+                (pumpfun::BUY_DISCRIMINATOR, pumpfun::SELL_DISCRIMINATOR)
+            }
+            FeeAccount::PumpSwapFeeRecipient => {
+                (pumpswap::BUY_DISCRIMINATOR, pumpswap::SELL_DISCRIMINATOR)
+            }
+        };
+        data.starts_with(&buy) || data.starts_with(&sell)
+    }
+}
+
+/// Where the address the builders use came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeeAccountSource {
+    Default,
+    /// Replaced by a `FeeAccountObserver` in `HotSwap` mode
+    Learned,
+    /// Set through `FeeAccountRegistry::pin`, never replaced by the observer
+    Pinned,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct FeeAccountEntry {
+    address: Pubkey,
+    source: FeeAccountSource,
+}
+
+/// FeeAccountRegistry singleton holding the fee accounts used by the instruction builders
+///
+/// Entries start at the compiled-in constants. Learned and pinned values are persisted
+/// through the store set with `set_store`, so they survive restarts.
+pub struct FeeAccountRegistry {
+    entries: DashMap<FeeAccount, FeeAccountEntry>,
+    store: RwLock<Option<Arc<dyn KeyValueStore>>>,
+}
+
+static FEE_ACCOUNT_REGISTRY: OnceLock<Arc<FeeAccountRegistry>> = OnceLock::new();
+
+impl FeeAccountRegistry {
+    /// Get FeeAccountRegistry singleton instance
+    pub fn get_instance() -> Arc<FeeAccountRegistry> {
+        FEE_ACCOUNT_REGISTRY
+            .get_or_init(|| {
+                Arc::new(FeeAccountRegistry { entries: DashMap::new(), store: RwLock::new(None) })
+            })
+            .clone()
+    }
+
+    /// Address the builders use for `account`
+    pub fn get(&self, account: FeeAccount) -> Pubkey {
+        self.entries.get(&account).map_or_else(|| account.default_address(), |entry| entry.address)
+    }
+
+    pub fn source(&self, account: FeeAccount) -> FeeAccountSource {
+        self.entries.get(&account).map_or(FeeAccountSource::Default, |entry| entry.source)
+    }
+
+    /// Persist learned and pinned values through `store`, loading the values stored by a
+    /// previous run
+    pub fn set_store(&self, store: Arc<dyn KeyValueStore>) -> Result<(), anyhow::Error> {
+        if let Some(value) = store.get(STORE_KEY) {
+            let entries: HashMap<FeeAccount, FeeAccountEntry> = serde_json::from_str(&value)
+                .map_err(|e| anyhow!("Failed to parse stored fee accounts: {}", e))?;
+            self.entries.clear();
+            self.entries.extend(entries);
+        }
+        *self.store.write() = Some(store);
+        Ok(())
+    }
+
+    /// Use `address` for `account` until `reset`, e.g. while a fee rotation is investigated
+    pub fn pin(&self, account: FeeAccount, address: Pubkey) {
+        self.entries.insert(account, FeeAccountEntry { address, source: FeeAccountSource::Pinned });
+        self.persist();
+    }
+
+    /// Drop a pinned or learned value, going back to the compiled-in constant
+    pub fn reset(&self, account: FeeAccount) {
+        self.entries.remove(&account);
+        self.persist();
+    }
+
+    /// Replace the address of `account` with an observed one, returns false if it is pinned
+    pub fn learn(&self, account: FeeAccount, address: Pubkey) -> bool {
+        if self.source(account) == FeeAccountSource::Pinned {
+            return false;
+        }
+        self.entries
+            .insert(account, FeeAccountEntry { address, source: FeeAccountSource::Learned });
+        self.persist();
+        true
+    }
+
+    fn persist(&self) {
+        let Some(store) = self.store.read().clone() else {
+            return;
+        };
+        let entries: HashMap<FeeAccount, FeeAccountEntry> =
+            self.entries.iter().map(|entry| (*entry.key(), *entry.value())).collect();
+        match serde_json::to_string(&entries) {
+            Ok(value) => {
+                if let Err(e) = store.put(STORE_KEY, value) {
+                    log::error!("Failed to persist fee accounts: {}", e);
+                }
+            }
+            Err(e) => log::error!("Failed to serialize fee accounts: {}", e),
+        }
+    }
+}
+
+/// Address the builders use for `account`
+#[inline]
+pub fn fee_account(account: FeeAccount) -> Pubkey {
+    FeeAccountRegistry::get_instance().get(account)
+}
+
+/// What the observer does when recent transactions do not use the current address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FeeObservationMode {
+    /// Log a warning only
+    #[default]
+    Warn,
+    /// Switch the builders to the most used observed address, unless it is pinned
+    HotSwap,
+}
+
+/// Fee account usage in a sample of recent transactions
+#[derive(Debug, Clone)]
+pub struct FeeAccountObservation {
+    pub account: FeeAccount,
+    /// Address the builders used when the sample was taken
+    pub current: Pubkey,
+    /// Observed addresses and the number of trade instructions using them, most used first
+    pub observed: Vec<(Pubkey, usize)>,
+}
+
+impl FeeAccountObservation {
+    /// Trades were observed and none of them used the current address
+    pub fn is_stale(&self) -> bool {
+        !self.observed.is_empty()
+            && !self.observed.iter().any(|(address, _)| *address == self.current)
+    }
+
+    pub fn most_used(&self) -> Option<Pubkey> {
+        self.observed.first().map(|(address, _)| *address)
+    }
+}
+
+/// Samples recent successful trades of each protocol and compares the fee accounts they use
+/// with the ones in `FeeAccountRegistry`
+///
+/// Only top-level buy and sell instructions are inspected, trades routed through another
+/// program via CPI are skipped.
+pub struct FeeAccountObserver {
+    rpc: Arc<SolanaRpcClient>,
+    mode: FeeObservationMode,
+    interval: Duration,
+    sample_size: usize,
+}
+
+impl FeeAccountObserver {
+    pub fn new(rpc: Arc<SolanaRpcClient>) -> Self {
+        Self {
+            rpc,
+            mode: FeeObservationMode::default(),
+            interval: Duration::from_secs(FEE_OBSERVER_DEFAULT_INTERVAL_SECS),
+            sample_size: FEE_OBSERVER_DEFAULT_SAMPLE_SIZE,
+        }
+    }
+
+    pub fn with_mode(mut self, mode: FeeObservationMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Number of recent signatures fetched per program
+    pub fn with_sample_size(mut self, sample_size: usize) -> Self {
+        self.sample_size = sample_size;
+        self
+    }
+
+    /// Sample every fee account once, warning about or swapping stale addresses
+    pub async fn observe_once(&self) -> Vec<FeeAccountObservation> {
+        let registry = FeeAccountRegistry::get_instance();
+        let mut observations = Vec::with_capacity(FeeAccount::ALL.len());
+        for account in FeeAccount::ALL {
+            let observed = match self.sample(account).await {
+                Ok(observed) => observed,
+                Err(e) => {
+                    log::warn!(
+                        icon!("⚠️ ", "Failed to sample fee accounts of {:?}: {}"),
+                        account,
+                        e
+                    );
+                    continue;
+                }
+            };
+            let observation =
+                FeeAccountObservation { account, current: registry.get(account), observed };
+            if let (true, Some(address)) = (observation.is_stale(), observation.most_used()) {
+                match self.mode {
+                    FeeObservationMode::Warn => log::warn!(
+                        icon!("⚠️ ", "{:?} is {} but recent trades use {}"),
+                        account,
+                        observation.current,
+                        address
+                    ),
+                    FeeObservationMode::HotSwap => {
+                        if registry.learn(account, address) {
+                            log::warn!(
+                                icon!("⚠️ ", "{:?} switched from {} to observed {}"),
+                                account,
+                                observation.current,
+                                address
+                            );
+                        } else {
+                            log::warn!(
+                                icon!("⚠️ ", "{:?} is pinned to {} but recent trades use {}"),
+                                account,
+                                observation.current,
+                                address
+                            );
+                        }
+                    }
+                }
+            }
+            observations.push(observation);
+        }
+        observations
+    }
+
+    /// Run `observe_once` every interval in a background task
+    pub fn start(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            loop {
+                ticker.tick().await;
+                self.observe_once().await;
+            }
+        })
+    }
+
+    /// Addresses at the fee account position of recent successful trade instructions
+    async fn sample(&self, account: FeeAccount) -> Result<Vec<(Pubkey, usize)>, anyhow::Error> {
+        let program = account.program();
+        let config = GetConfirmedSignaturesForAddress2Config {
+            limit: Some(self.sample_size),
+            commitment: Some(CommitmentConfig::confirmed()),
+            ..Default::default()
+        };
+        let signatures = self.rpc.get_signatures_for_address_with_config(&program, config).await?;
+
+        let tx_config = RpcTransactionConfig {
+            commitment: Some(CommitmentConfig::confirmed()),
+            encoding: Some(UiTransactionEncoding::Base64),
+            max_supported_transaction_version: Some(0),
+        };
+        let mut counts: HashMap<Pubkey, usize> = HashMap::new();
+        for status in signatures.into_iter().filter(|status| status.err.is_none()) {
+            let Ok(signature) = status.signature.parse() else {
+                continue;
+            };
+            let Ok(encoded) = self.rpc.get_transaction_with_config(&signature, tx_config).await
+            else {
+                continue;
+            };
+            let Some(transaction) = encoded.transaction.transaction.decode() else {
+                continue;
+            };
+            let mut keys = transaction.message.static_account_keys().to_vec();
+            if let Some(OptionSerializer::Some(loaded)) =
+                encoded.transaction.meta.as_ref().map(|meta| &meta.loaded_addresses)
+            {
+                keys.extend(
+                    loaded
+                        .writable
+                        .iter()
+                        .chain(loaded.readonly.iter())
+                        .filter_map(|key| Pubkey::from_str(key).ok()),
+                );
+            }
+            for ix in transaction.message.instructions() {
+                if keys.get(ix.program_id_index as usize) != Some(&program)
+                    || !account.is_trade_instruction(&ix.data)
+                {
+                    continue;
+                }
+                let address = ix
+                    .accounts
+                    .get(account.instruction_index())
+                    .and_then(|index| keys.get(*index as usize));
+                if let Some(address) = address {
+                    *counts.entry(*address).or_default() += 1;
+                }
+            }
+        }
+
+        let mut observed: Vec<(Pubkey, usize)> = counts.into_iter().collect();
+        observed.sort_by(|a, b| b.1.cmp(&a.1));
+        Ok(observed)
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::anyhow;
+
+use crate::common::dex_defaults::{APPLIED_SLIPPAGE_LABEL, APPLIED_SLIPPAGE_SOURCE_LABEL};
+
+/// Free-form labels of a trade, e.g. `strategy => "copy"`, returned on its `TradeResult`
+pub type TradeLabels = HashMap<String, String>;
+
+pub const MAX_TRADE_LABELS: usize = 16;
+pub const MAX_LABEL_KEY_LEN: usize = 32;
+pub const MAX_LABEL_VALUE_LEN: usize = 64;
+/// Keys the SDK reports itself
+pub const RESERVED_LABEL_KEYS: [&str; 8] = [
+    "signature",
+    "mint",
+    "wallet",
+    "dex",
+    "side",
+    "provider",
+    APPLIED_SLIPPAGE_LABEL,
+    APPLIED_SLIPPAGE_SOURCE_LABEL,
+];
+
+/// Check the label count, key and value lengths and reserved keys
+pub fn validate_labels(labels: &TradeLabels) -> Result<(), anyhow::Error> {
+    if labels.len() > MAX_TRADE_LABELS {
+        return Err(anyhow!("{} trade labels, at most {} allowed", labels.len(), MAX_TRADE_LABELS));
+    }
+    for (key, value) in labels {
+        if key.is_empty() || key.len() > MAX_LABEL_KEY_LEN {
+            return Err(anyhow!("Label key {:?} must be 1 to {} bytes", key, MAX_LABEL_KEY_LEN));
+        }
+        if RESERVED_LABEL_KEYS.contains(&key.as_str()) {
+            return Err(anyhow!("Label key {:?} is reserved", key));
+        }
+        if value.len() > MAX_LABEL_VALUE_LEN {
+            return Err(anyhow!(
+                "Value of label {:?} is {} bytes, at most {} allowed",
+                key,
+                value.len(),
+                MAX_LABEL_VALUE_LEN
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// `memo` followed by the labels under `keys` as a JSON object, e.g. `trade-42 {"strategy":"copy"}`
+///
+/// Keys without a label are left out, `None` when there is neither a memo nor a label to record.
+pub fn memo_with_labels(
+    memo: Option<&str>,
+    labels: Option<&TradeLabels>,
+    keys: &[String],
+) -> Option<String> {
+    let recorded: BTreeMap<&str, &str> = labels
+        .map(|labels| {
+            keys.iter()
+                .filter_map(|key| labels.get_key_value(key))
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect()
+        })
+        .unwrap_or_default();
+    if recorded.is_empty() {
+        return memo.map(str::to_string);
+    }
+    let recorded = serde_json::to_string(&recorded).ok()?;
+    Some(match memo {
+        Some(memo) => format!("{} {}", memo, recorded),
+        None => recorded,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels() -> TradeLabels {
+        TradeLabels::from([
+            ("strategy".to_string(), "copy".to_string()),
+            ("account".to_string(), "main".to_string()),
+            ("trade_id".to_string(), "42".to_string()),
+        ])
+    }
+
+    #[test]
+    fn memo_records_the_selected_labels_sorted_by_key() {
+        let keys = ["strategy".to_string(), "account".to_string(), "missing".to_string()];
+        assert_eq!(
+            memo_with_labels(Some("trade-42"), Some(&labels()), &keys).as_deref(),
+            Some(r#"trade-42 {"account":"main","strategy":"copy"}"#)
+        );
+        assert_eq!(
+            memo_with_labels(None, Some(&labels()), &keys).as_deref(),
+            Some(r#"{"account":"main","strategy":"copy"}"#)
+        );
+    }
+
+    #[test]
+    fn memo_is_unchanged_without_selected_labels() {
+        assert_eq!(
+            memo_with_labels(Some("trade-42"), Some(&labels()), &[]).as_deref(),
+            Some("trade-42")
+        );
+        let keys = ["strategy".to_string()];
+        assert_eq!(memo_with_labels(Some("trade-42"), None, &keys).as_deref(), Some("trade-42"));
+        assert_eq!(memo_with_labels(None, None, &keys), None);
+    }
+
+    #[test]
+    fn labels_over_the_limits_are_rejected() {
+        assert!(validate_labels(&labels()).is_ok());
+        let reserved = TradeLabels::from([("mint".to_string(), "x".to_string())]);
+        assert!(validate_labels(&reserved).is_err());
+        let long_value =
+            TradeLabels::from([("k".to_string(), "v".repeat(MAX_LABEL_VALUE_LEN + 1))]);
+        assert!(validate_labels(&long_value).is_err());
+        let too_many: TradeLabels =
+            (0..=MAX_TRADE_LABELS).map(|i| (format!("k{}", i), String::new())).collect();
+        assert!(validate_labels(&too_many).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::anyhow;
+use dashmap::DashMap;
+use solana_sdk::pubkey::Pubkey;
+use std::sync::{Arc, OnceLock};
+
+use crate::common::SolanaRpcClient;
+use crate::constants::{accounts::WSOL_TOKEN_ACCOUNT, decimals::SOL_DECIMALS};
+
+/// MintDecimalsCache singleton holding the decimals of every mint seen by the SDK
+///
+/// Decimals of a mint never change, so entries are kept for the lifetime of the process.
+pub struct MintDecimalsCache {
+    decimals: DashMap<Pubkey, u8>,
+}
+
+static MINT_DECIMALS_CACHE: OnceLock<Arc<MintDecimalsCache>> = OnceLock::new();
+
+impl MintDecimalsCache {
+    /// Get MintDecimalsCache singleton instance
+    pub fn get_instance() -> Arc<MintDecimalsCache> {
+        MINT_DECIMALS_CACHE
+            .get_or_init(|| {
+                let decimals = DashMap::new();
+                decimals.insert(WSOL_TOKEN_ACCOUNT, SOL_DECIMALS);
+                Arc::new(MintDecimalsCache { decimals })
+            })
+            .clone()
+    }
+
+    pub fn get(&self, mint: &Pubkey) -> Option<u8> {
+        self.decimals.get(mint).map(|entry| *entry)
+    }
+
+    pub fn insert(&self, mint: Pubkey, decimals: u8) {
+        self.decimals.insert(mint, decimals);
+    }
+
+    /// Cached decimals of `mint`, fetched once on a miss. Works for Token and Token-2022 mints.
+    pub async fn fetch(&self, rpc: &SolanaRpcClient, mint: &Pubkey) -> Result<u8, anyhow::Error> {
+        if let Some(decimals) = self.get(mint) {
+            return Ok(decimals);
+        }
+        let decimals = rpc
+            .get_token_supply(mint)
+            .await
+            .map_err(|e| anyhow!("Failed to fetch decimals of mint {}: {}", mint, e))?
+            .decimals;
+        self.insert(*mint, decimals);
+        Ok(decimals)
+    }
+
+    /// Decimals of `mint`, or an error when they are not cached yet
+    pub fn require(&self, mint: &Pubkey) -> Result<u8, anyhow::Error> {
+        self.get(mint).ok_or_else(|| anyhow!("Decimals of mint {} are not cached", mint))
+    }
+
+    /// `1,234.5 (1234500000)` when the decimals of `mint` are cached, the raw amount otherwise
+    pub fn display(&self, mint: &Pubkey, raw: u64) -> String {
+        match self.get(mint) {
+            Some(decimals) => {
+                format!("{} ({})", crate::utils::amount::format_amount(raw, decimals, None), raw)
+            }
+            None => raw.to_string(),
+        }
+    }
+}
@@ -1,9 +1,25 @@
+pub mod account_fetch;
 pub mod address_lookup_cache;
+pub mod address_lookup_manage;
+pub mod balance_cache;
+pub mod blockhash_cache;
 pub mod bonding_curve;
+pub mod commission;
+pub mod dex_defaults;
+#[cfg(test)]
+pub(crate) mod fake_chain;
 pub mod fast_fn;
+pub mod fee_accounts;
 pub mod global;
+pub mod labels;
+pub mod mint_cache;
 pub mod nonce_cache;
+pub mod priority_fee_estimate;
+pub mod rpc_race;
 pub mod seed;
+pub mod signer;
+pub mod spend_budget;
+pub mod store;
 pub mod subscription_handle;
 pub mod types;
 
@@ -98,27 +98,46 @@ impl NonceCache {
     }
 
     /// Fetch nonce information using RPC
+    ///
+    /// Fails when the account is not an initialized nonce account, which durable nonce trades
+    /// could never advance. RPC errors are only logged.
     pub async fn fetch_nonce_info_use_rpc(
         &self,
         rpc: &SolanaRpcClient,
     ) -> Result<(), anyhow::Error> {
-        match rpc.get_account(&self.get_nonce_info().nonce_account.unwrap()).await {
+        let nonce_account = match self.get_nonce_info().nonce_account {
+            Some(nonce_account) => nonce_account,
+            None => return Err(anyhow::anyhow!("Nonce account is not set")),
+        };
+        match rpc.get_account(&nonce_account).await {
             Ok(account) => match account.state() {
-                Ok(Versions::Current(state)) => {
-                    if let State::Initialized(data) = *state {
+                Ok(Versions::Current(state)) => match *state {
+                    State::Initialized(data) => {
                         let blockhash = data.durable_nonce.as_hash();
-                        let old_nonce_info = self.get_nonce_info();
-                        if old_nonce_info.current_nonce != *blockhash {
-                            self.update_nonce_info_partial(
-                                None,
-                                Some(*blockhash),
-                                None,
-                                Some(false),
-                            );
+                        // Compare and update under one lock so concurrent fetches cannot reset
+                        // `used` for a nonce another task already consumed
+                        let mut current = self.nonce_info.lock();
+                        if current.current_nonce != *blockhash {
+                            current.current_nonce = *blockhash;
+                            current.used = false;
                         }
                     }
+                    State::Uninitialized => {
+                        return Err(anyhow::anyhow!(
+                            "Nonce account {} is not initialized",
+                            nonce_account
+                        ));
+                    }
+                },
+                Ok(Versions::Legacy(_)) => {
+                    return Err(anyhow::anyhow!(
+                        "Nonce account {} has the legacy layout, upgrade it before use",
+                        nonce_account
+                    ));
+                }
+                Err(_) => {
+                    return Err(anyhow::anyhow!("{} is not a nonce account", nonce_account));
                 }
-                _ => (),
             },
             Err(e) => {
                 error!("Failed to get nonce account information: {:?}", e);
@@ -0,0 +1,259 @@
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+use crate::common::{PriorityFee, SolanaRpcClient};
+
+/// Percentile of the recent fees used unless configured otherwise
+pub const DEFAULT_FEE_PERCENTILE: u8 = 75;
+/// Highest unit price an estimate sets unless configured otherwise, in micro-lamports
+pub const DEFAULT_MAX_ESTIMATED_UNIT_PRICE: u64 = 1_000_000;
+/// Longest a trade waits for its estimate unless configured otherwise
+pub const DEFAULT_FEE_ESTIMATE_TIMEOUT: Duration = Duration::from_millis(300);
+/// Most accounts `getRecentPrioritizationFees` accepts
+pub const MAX_FEE_ESTIMATE_ACCOUNTS: usize = 128;
+/// A background estimate missing this many refreshes is no longer used
+const STALE_AFTER_REFRESHES: u32 = 3;
+
+/// How trades estimate their compute unit price from `getRecentPrioritizationFees`
+///
+/// The estimate replaces the tip and RPC unit prices of the `PriorityFee`, per-protocol
+/// `protocol_limits` keep their price. When it is not available in time the static prices are
+/// used.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriorityFeeEstimate {
+    /// Percentile of the recent fees, 0 to 100
+    pub percentile: u8,
+    /// Highest unit price the estimate sets, in micro-lamports
+    pub max_unit_price: u64,
+    /// Longest a trade waits for its estimate before keeping the static prices
+    pub timeout: Duration,
+    /// Estimate in the background on this interval instead of once per trade
+    pub refresh_interval: Option<Duration>,
+    /// Accounts the background estimate is made for, empty for the fees of all transactions.
+    /// Estimates made per trade use the accounts the trade writes.
+    pub accounts: Vec<Pubkey>,
+}
+
+impl Default for PriorityFeeEstimate {
+    fn default() -> Self {
+        Self::new(DEFAULT_FEE_PERCENTILE)
+    }
+}
+
+impl PriorityFeeEstimate {
+    pub fn new(percentile: u8) -> Self {
+        Self {
+            percentile,
+            max_unit_price: DEFAULT_MAX_ESTIMATED_UNIT_PRICE,
+            timeout: DEFAULT_FEE_ESTIMATE_TIMEOUT,
+            refresh_interval: None,
+            accounts: Vec::new(),
+        }
+    }
+
+    pub fn with_max_unit_price(mut self, max_unit_price: u64) -> Self {
+        self.max_unit_price = max_unit_price;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_refresh_interval(mut self, refresh_interval: Duration) -> Self {
+        self.refresh_interval = Some(refresh_interval);
+        self
+    }
+
+    pub fn with_accounts(mut self, accounts: Vec<Pubkey>) -> Self {
+        self.accounts = accounts;
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.percentile > 100 {
+            return Err(anyhow!("Fee percentile {} is above 100", self.percentile));
+        }
+        if self.refresh_interval.is_some_and(|interval| interval.is_zero()) {
+            return Err(anyhow!("Fee estimate refresh interval cannot be zero"));
+        }
+        Ok(())
+    }
+}
+
+/// Unit price at `percentile` of the fees paid in recent slots by transactions writing
+/// `accounts`, of all transactions when `accounts` is empty
+///
+/// Only the first `MAX_FEE_ESTIMATE_ACCOUNTS` accounts are sent.
+pub async fn estimate_unit_price(
+    rpc: &SolanaRpcClient,
+    accounts: &[Pubkey],
+    percentile: u8,
+) -> Result<u64> {
+    if percentile > 100 {
+        return Err(anyhow!("Fee percentile {} is above 100", percentile));
+    }
+    let accounts = &accounts[..accounts.len().min(MAX_FEE_ESTIMATE_ACCOUNTS)];
+    let mut prices: Vec<u64> = rpc
+        .get_recent_prioritization_fees(accounts)
+        .await?
+        .iter()
+        .map(|fee| fee.prioritization_fee)
+        .collect();
+    if prices.is_empty() {
+        return Err(anyhow!("No recent prioritization fees"));
+    }
+    prices.sort_unstable();
+    Ok(prices[(prices.len() - 1) * percentile as usize / 100])
+}
+
+impl PriorityFee {
+    /// Default fees with the tip and RPC unit price estimated by `estimate_unit_price`, capped
+    /// at `DEFAULT_MAX_ESTIMATED_UNIT_PRICE`
+    pub async fn auto_estimate(
+        rpc: &SolanaRpcClient,
+        accounts: &[Pubkey],
+        percentile: u8,
+    ) -> Result<PriorityFee> {
+        let unit_price = estimate_unit_price(rpc, accounts, percentile).await?;
+        Ok(PriorityFee::default().with_unit_price(unit_price.min(DEFAULT_MAX_ESTIMATED_UNIT_PRICE)))
+    }
+}
+
+/// PriorityFeeEstimator singleton applying the configured `PriorityFeeEstimate` to trades
+///
+/// Configured by `SolanaTrade::new` from `TradeConfig::priority_fee_estimate`, the setting is
+/// process-wide.
+pub struct PriorityFeeEstimator {
+    estimate: Mutex<Option<PriorityFeeEstimate>>,
+    latest: Mutex<Option<(u64, Instant)>>,
+    refresher: Mutex<Option<JoinHandle<()>>>,
+}
+
+static PRIORITY_FEE_ESTIMATOR: OnceLock<Arc<PriorityFeeEstimator>> = OnceLock::new();
+
+impl PriorityFeeEstimator {
+    /// Get PriorityFeeEstimator singleton instance
+    pub fn get_instance() -> Arc<PriorityFeeEstimator> {
+        PRIORITY_FEE_ESTIMATOR
+            .get_or_init(|| {
+                Arc::new(PriorityFeeEstimator {
+                    estimate: Mutex::new(None),
+                    latest: Mutex::new(None),
+                    refresher: Mutex::new(None),
+                })
+            })
+            .clone()
+    }
+
+    /// Use `estimate` for the following trades, `None` keeps the static prices
+    ///
+    /// Starts the background refresh when the estimate has a refresh interval.
+    pub fn configure(
+        self: &Arc<Self>,
+        rpc: Arc<SolanaRpcClient>,
+        estimate: Option<PriorityFeeEstimate>,
+    ) {
+        if let Some(task) = self.refresher.lock().take() {
+            task.abort();
+        }
+        *self.latest.lock() = None;
+        let refresh_interval = estimate.as_ref().and_then(|estimate| estimate.refresh_interval);
+        *self.estimate.lock() = estimate;
+        if let Some(interval) = refresh_interval {
+            let estimator = self.clone();
+            *self.refresher.lock() = Some(tokio::spawn(async move {
+                let mut ticker = tokio::time::interval(interval);
+                loop {
+                    ticker.tick().await;
+                    if let Err(e) = estimator.refresh(&rpc).await {
+                        log::warn!(icon!("⚠️ ", "Priority fee estimate failed: {}"), e);
+                    }
+                }
+            }));
+        }
+    }
+
+    /// Current setting, `None` when trades use the static prices
+    pub fn estimate(&self) -> Option<PriorityFeeEstimate> {
+        self.estimate.lock().clone()
+    }
+
+    /// Latest background estimate, uncapped, `None` once it missed three refreshes
+    pub fn latest_unit_price(&self) -> Option<u64> {
+        let interval = self.estimate.lock().as_ref()?.refresh_interval?;
+        let (unit_price, fetched_at) = (*self.latest.lock())?;
+        (fetched_at.elapsed() <= interval * STALE_AFTER_REFRESHES).then_some(unit_price)
+    }
+
+    /// Estimate for the configured accounts and keep the result for `latest_unit_price`
+    pub async fn refresh(&self, rpc: &SolanaRpcClient) -> Result<u64> {
+        let Some(estimate) = self.estimate() else {
+            return Err(anyhow!("No priority fee estimate is configured"));
+        };
+        let unit_price = estimate_unit_price(rpc, &estimate.accounts, estimate.percentile).await?;
+        *self.latest.lock() = Some((unit_price, Instant::now()));
+        Ok(unit_price)
+    }
+
+    /// `priority_fee` with the estimated unit price, unchanged when no estimate is configured
+    /// or it did not arrive within the timeout
+    pub(crate) async fn apply(
+        &self,
+        rpc: &SolanaRpcClient,
+        priority_fee: &Arc<PriorityFee>,
+        payer: &Pubkey,
+        instructions: &[Instruction],
+    ) -> Arc<PriorityFee> {
+        let Some(estimate) = self.estimate() else {
+            return priority_fee.clone();
+        };
+        let unit_price = if estimate.refresh_interval.is_some() {
+            self.latest_unit_price()
+        } else {
+            let accounts = writable_accounts(payer, instructions);
+            let estimated = tokio::time::timeout(
+                estimate.timeout,
+                estimate_unit_price(rpc, &accounts, estimate.percentile),
+            )
+            .await
+            .unwrap_or_else(|_| Err(anyhow!("no answer in {:?}", estimate.timeout)));
+            match estimated {
+                Ok(unit_price) => Some(unit_price),
+                Err(e) => {
+                    log::warn!(
+                        icon!("⚠️ ", "Priority fee estimate failed, using static prices: {}"),
+                        e
+                    );
+                    None
+                }
+            }
+        };
+        match unit_price {
+            Some(unit_price) => Arc::new(
+                priority_fee
+                    .as_ref()
+                    .clone()
+                    .with_unit_price(unit_price.min(estimate.max_unit_price)),
+            ),
+            None => priority_fee.clone(),
+        }
+    }
+}
+
+/// Accounts `instructions` write, besides the payer
+fn writable_accounts(payer: &Pubkey, instructions: &[Instruction]) -> Vec<Pubkey> {
+    let mut accounts: Vec<Pubkey> = Vec::new();
+    for meta in instructions.iter().flat_map(|instruction| &instruction.accounts) {
+        if meta.is_writable && meta.pubkey != *payer && !accounts.contains(&meta.pubkey) {
+            accounts.push(meta.pubkey);
+        }
+    }
+    accounts.truncate(MAX_FEE_ESTIMATE_ACCOUNTS);
+    accounts
+}
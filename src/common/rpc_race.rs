@@ -0,0 +1,115 @@
+//! RPC reads raced across the configured endpoints
+//!
+//! Reads on the hot path of building a trade, the account loads of the `*_by_rpc` params
+//! constructors and the blockhash refresh, go to the RPC they were called with and to every
+//! endpoint of `TradeConfig::fallback_rpc_urls` at once. The first successful answer is used,
+//! the others are dropped; an error is returned only when every endpoint failed. Without
+//! fallbacks only the given RPC is asked.
+//!
+//! A node lagging behind can answer first with slightly older state, e.g. reserves a slot old.
+
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use futures::future::{select_ok, BoxFuture, FutureExt};
+use parking_lot::RwLock;
+use solana_hash::Hash;
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey};
+
+use crate::common::SolanaRpcClient;
+
+lazy_static::lazy_static! {
+    static ref FALLBACK_RPCS: RwLock<Vec<Arc<SolanaRpcClient>>> = RwLock::new(Vec::new());
+}
+
+/// Race reads against `urls` besides the RPC each read is called with
+///
+/// Configured by `SolanaTrade::new` from `TradeConfig::fallback_rpc_urls`, the setting is
+/// process-wide.
+pub fn set_fallback_rpc_urls(urls: &[String], commitment: CommitmentConfig) {
+    *FALLBACK_RPCS.write() = urls
+        .iter()
+        .map(|url| Arc::new(SolanaRpcClient::new_with_commitment(url.clone(), commitment)))
+        .collect();
+}
+
+/// Clients of the configured fallback endpoints
+pub fn fallback_rpcs() -> Vec<Arc<SolanaRpcClient>> {
+    FALLBACK_RPCS.read().clone()
+}
+
+/// Result of the first of `calls` that succeeds, the last error when all of them fail
+pub async fn first_ok<T>(
+    calls: Vec<BoxFuture<'_, Result<T, anyhow::Error>>>,
+) -> Result<T, anyhow::Error> {
+    if calls.is_empty() {
+        return Err(anyhow!("No RPC endpoint to ask"));
+    }
+    select_ok(calls).await.map(|(value, _)| value)
+}
+
+/// `getMultipleAccounts` against `rpc` and the fallbacks, first answer wins
+pub async fn get_multiple_accounts(
+    rpc: &SolanaRpcClient,
+    addresses: &[Pubkey],
+) -> Result<Vec<Option<Account>>, anyhow::Error> {
+    let fallbacks = fallback_rpcs();
+    if fallbacks.is_empty() {
+        return Ok(rpc.get_multiple_accounts(addresses).await?);
+    }
+    let calls = std::iter::once(rpc)
+        .chain(fallbacks.iter().map(|client| client.as_ref()))
+        .map(|client| {
+            async move { Ok::<_, anyhow::Error>(client.get_multiple_accounts(addresses).await?) }
+                .boxed()
+        })
+        .collect();
+    first_ok(calls).await
+}
+
+/// `getMultipleAccounts` with the slot the answering endpoint read the accounts at, first
+/// answer wins
+pub async fn get_multiple_accounts_and_slot(
+    rpc: &SolanaRpcClient,
+    addresses: &[Pubkey],
+) -> Result<(Vec<Option<Account>>, u64), anyhow::Error> {
+    let fallbacks = fallback_rpcs();
+    let calls = std::iter::once(rpc)
+        .chain(fallbacks.iter().map(|client| client.as_ref()))
+        .map(|client| {
+            async move {
+                let response = client
+                    .get_multiple_accounts_with_commitment(addresses, client.commitment())
+                    .await?;
+                Ok::<_, anyhow::Error>((response.value, response.context.slot))
+            }
+            .boxed()
+        })
+        .collect();
+    first_ok(calls).await
+}
+
+/// Latest blockhash and the current slot of the same endpoint, first answer wins
+///
+/// `commitment` overrides the one of the clients, e.g. processed for the freshest blockhash.
+pub async fn get_latest_blockhash_and_slot(
+    rpc: &SolanaRpcClient,
+    commitment: Option<CommitmentConfig>,
+) -> Result<(Hash, u64), anyhow::Error> {
+    let fallbacks = fallback_rpcs();
+    let calls = std::iter::once(rpc)
+        .chain(fallbacks.iter().map(|client| client.as_ref()))
+        .map(|client| {
+            async move {
+                let commitment = commitment.unwrap_or_else(|| client.commitment());
+                let (blockhash, slot) = tokio::join!(
+                    client.get_latest_blockhash_with_commitment(commitment),
+                    client.get_slot_with_commitment(commitment)
+                );
+                Ok::<_, anyhow::Error>((blockhash?.0, slot?))
+            }
+            .boxed()
+        })
+        .collect();
+    first_ok(calls).await
+}
@@ -1,25 +1,121 @@
 use crate::common::SolanaRpcClient;
 use anyhow::anyhow;
+use dashmap::DashMap;
 use fnv::FnvHasher;
+use once_cell::sync::Lazy;
 use solana_sdk::{instruction::Instruction, program_pack::Pack, pubkey::Pubkey};
 use solana_system_interface::instruction::create_account_with_seed;
+use spl_token_2022::{
+    extension::{BaseStateWithExtensions, ExtensionType, StateWithExtensions},
+    state::{Account as Token2022Account, Mint},
+};
 use std::hash::Hasher;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use tokio::time::{sleep, Duration};
 
-// Global rent values for token accounts
-pub static mut SPL_TOKEN_RENT: Option<u64> = None;
-pub static mut SPL_TOKEN_2022_RENT: Option<u64> = None;
+// Global rent values for token accounts, 0 until fetched
+static SPL_TOKEN_RENT: AtomicU64 = AtomicU64::new(0);
+static SPL_TOKEN_2022_RENT: AtomicU64 = AtomicU64::new(0);
+
+/// Rent exemption of a token account, `None` until `update_rents` or `set_rent` ran
+pub fn get_rent(is_2022_token: bool) -> Option<u64> {
+    let rent = if is_2022_token { &SPL_TOKEN_2022_RENT } else { &SPL_TOKEN_RENT };
+    match rent.load(Ordering::Acquire) {
+        0 => None,
+        rent => Some(rent),
+    }
+}
+
+/// Set the rent exemption of a token account, e.g. when running without RPC access
+pub fn set_rent(is_2022_token: bool, rent: u64) {
+    let target = if is_2022_token { &SPL_TOKEN_2022_RENT } else { &SPL_TOKEN_RENT };
+    target.store(rent, Ordering::Release);
+}
+
+/// Bytes every account pays rent for on top of its data
+const ACCOUNT_STORAGE_OVERHEAD: u64 = 128;
+
+/// Size and rent of a token-2022 account for one mint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token2022AccountSize {
+    /// Account length including the extensions the mint requires on its token accounts
+    pub len: usize,
+    /// Rent fetched for `len`, derived from the base token-2022 rent when `None`
+    pub rent: Option<u64>,
+}
+
+impl Token2022AccountSize {
+    /// Rent exemption for `len`, `None` until the base token-2022 rent is known
+    pub fn rent(&self) -> Option<u64> {
+        self.rent.or_else(|| {
+            // Rent is linear in the account size, scale the rent of a base account
+            let base = get_rent(true)?;
+            let base_len = ACCOUNT_STORAGE_OVERHEAD + Token2022Account::LEN as u64;
+            Some(base / base_len * (ACCOUNT_STORAGE_OVERHEAD + self.len as u64))
+        })
+    }
+}
+
+// Token-2022 account sizes per mint, seeded accounts use the ATA until a mint is registered
+static TOKEN_2022_ACCOUNT_SIZES: Lazy<DashMap<Pubkey, Token2022AccountSize>> =
+    Lazy::new(DashMap::new);
+
+/// Length of a token account for the token-2022 mint with account data `mint_data`
+///
+/// Covers the extensions the mint requires on its token accounts plus `ImmutableOwner`, which
+/// seeded accounts carry like the ATAs the ATA program creates.
+pub fn token_2022_account_len(mint_data: &[u8]) -> Result<usize, anyhow::Error> {
+    let mint = StateWithExtensions::<Mint>::unpack(mint_data)
+        .map_err(|e| anyhow!("Failed to unpack token-2022 mint: {}", e))?;
+    let mint_extensions = mint
+        .get_extension_types()
+        .map_err(|e| anyhow!("Failed to read token-2022 mint extensions: {}", e))?;
+    let mut account_extensions =
+        ExtensionType::get_required_init_account_extensions(&mint_extensions);
+    if !account_extensions.contains(&ExtensionType::ImmutableOwner) {
+        account_extensions.push(ExtensionType::ImmutableOwner);
+    }
+    ExtensionType::try_calculate_account_len::<Token2022Account>(&account_extensions)
+        .map_err(|e| anyhow!("Failed to size token-2022 account: {}", e))
+}
+
+/// Register the token account size of a token-2022 mint from its account data
+///
+/// The rent is derived from the base token-2022 rent. Seeded account creation for a
+/// token-2022 mint needs it registered, otherwise the canonical ATA is created.
+pub fn set_token_2022_mint(mint: &Pubkey, mint_data: &[u8]) -> Result<usize, anyhow::Error> {
+    let len = token_2022_account_len(mint_data)?;
+    TOKEN_2022_ACCOUNT_SIZES.insert(*mint, Token2022AccountSize { len, rent: None });
+    Ok(len)
+}
+
+/// Load a token-2022 mint and register its token account size and rent
+pub async fn update_token_2022_mint(
+    client: &SolanaRpcClient,
+    mint: &Pubkey,
+) -> Result<Token2022AccountSize, anyhow::Error> {
+    let mint_account = client.get_account(mint).await?;
+    if mint_account.owner != spl_token_2022::id() {
+        return Err(anyhow!("Mint {} is not owned by token-2022", mint));
+    }
+    let len = token_2022_account_len(&mint_account.data)?;
+    let rent = client.get_minimum_balance_for_rent_exemption(len).await?;
+    let size = Token2022AccountSize { len, rent: Some(rent) };
+    TOKEN_2022_ACCOUNT_SIZES.insert(*mint, size);
+    Ok(size)
+}
+
+/// Token account size registered for a token-2022 mint
+pub fn get_token_2022_account_size(mint: &Pubkey) -> Option<Token2022AccountSize> {
+    TOKEN_2022_ACCOUNT_SIZES.get(mint).map(|size| *size)
+}
 
 pub async fn update_rents(client: &SolanaRpcClient) -> Result<(), anyhow::Error> {
     let rent = fetch_rent_for_token_account(client, false).await?;
-    unsafe {
-        SPL_TOKEN_RENT = Some(rent);
-    }
+    set_rent(false, rent);
     let rent = fetch_rent_for_token_account(client, true).await?;
-    unsafe {
-        SPL_TOKEN_2022_RENT = Some(rent);
-    }
+    set_rent(true, rent);
     Ok(())
 }
 
@@ -52,11 +148,16 @@ pub fn create_associated_token_account_use_seed(
     token_program: &Pubkey,
 ) -> Result<Vec<Instruction>, anyhow::Error> {
     let is_2022_token = token_program == &spl_token_2022::id();
-    let rent =
-        if is_2022_token { unsafe { SPL_TOKEN_2022_RENT } } else { unsafe { SPL_TOKEN_RENT } };
-    if rent.is_none() {
+    let (len, rent) = if is_2022_token {
+        let size = get_token_2022_account_size(mint)
+            .ok_or_else(|| anyhow!("Token-2022 mint {} is not registered for seed", mint))?;
+        (size.len as u64, size.rent())
+    } else {
+        (spl_token::state::Account::LEN as u64, get_rent(false))
+    };
+    let Some(rent) = rent else {
         return Err(anyhow!("Rent is required when using seed"));
-    }
+    };
     let mut buf = [0u8; 8];
     let mut hasher = FnvHasher::default();
     hasher.write(mint.as_ref());
@@ -72,19 +173,23 @@ pub fn create_associated_token_account_use_seed(
     let seed = unsafe { std::str::from_utf8_unchecked(&buf) };
     let ata_like = Pubkey::create_with_seed(payer, seed, token_program)?;
 
-    let len = if is_2022_token {
-        spl_token_2022::state::Account::LEN as u64
-    } else {
-        spl_token::state::Account::LEN as u64
-    };
     let create_acc =
-        create_account_with_seed(payer, &ata_like, owner, seed, rent.unwrap(), len, token_program);
+        create_account_with_seed(payer, &ata_like, owner, seed, rent, len, token_program);
 
-    let init_acc = if is_2022_token {
-        spl_token_2022::instruction::initialize_account3(&token_program, &ata_like, mint, owner)?
-    } else {
-        spl_token::instruction::initialize_account3(&token_program, &ata_like, mint, owner)?
-    };
+    if is_2022_token {
+        // The extension is initialized before the account, `len` has room for it
+        let immutable_owner =
+            spl_token_2022::instruction::initialize_immutable_owner(token_program, &ata_like)?;
+        let init_acc = spl_token_2022::instruction::initialize_account3(
+            token_program,
+            &ata_like,
+            mint,
+            owner,
+        )?;
+        return Ok(vec![create_acc, immutable_owner, init_acc]);
+    }
+    let init_acc =
+        spl_token::instruction::initialize_account3(token_program, &ata_like, mint, owner)?;
 
     Ok(vec![create_acc, init_acc])
 }
@@ -112,3 +217,203 @@ pub fn get_associated_token_address_with_program_id_use_seed(
     let ata_like = Pubkey::create_with_seed(wallet_address, seed, token_program)?;
     Ok(ata_like)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::fast_fn::{
+        create_associated_token_account_idempotent_fast_use_seed,
+        get_associated_token_address_with_program_id_fast_use_seed,
+    };
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde_json::json;
+    use solana_rpc_client_api::request::RpcRequest;
+    use solana_system_interface::instruction::SystemInstruction;
+    use spl_token_2022::{
+        extension::{
+            transfer_fee::TransferFeeConfig, transfer_hook::TransferHook, StateWithExtensionsMut,
+        },
+        instruction::TokenInstruction,
+    };
+    use std::collections::HashMap;
+
+    /// Rent exemption of a base token account, 165 bytes
+    const BASE_RENT: u64 = 2_039_280;
+
+    /// Account data of an initialized token-2022 mint with `extensions`
+    fn mint_data(extensions: &[ExtensionType]) -> Vec<u8> {
+        let base = Mint { decimals: 6, is_initialized: true, ..Default::default() };
+        if extensions.is_empty() {
+            let mut data = vec![0; Mint::LEN];
+            base.pack_into_slice(&mut data);
+            return data;
+        }
+        let len = ExtensionType::try_calculate_account_len::<Mint>(extensions).unwrap();
+        let mut data = vec![0; len];
+        let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        for extension in extensions {
+            match extension {
+                ExtensionType::TransferFeeConfig => {
+                    state.init_extension::<TransferFeeConfig>(true).unwrap();
+                }
+                ExtensionType::TransferHook => {
+                    state.init_extension::<TransferHook>(true).unwrap();
+                }
+                other => unreachable!("{:?} is not used by these tests", other),
+            }
+        }
+        state.base = base;
+        state.pack_base();
+        state.init_account_type().unwrap();
+        data
+    }
+
+    #[test]
+    fn plain_mint_accounts_only_carry_immutable_owner() {
+        // 165 base bytes, the account type byte and an empty ImmutableOwner entry
+        assert_eq!(token_2022_account_len(&mint_data(&[])).unwrap(), 170);
+    }
+
+    #[test]
+    fn transfer_fee_and_hook_mints_size_their_account_extensions() {
+        // On top of the 170 bytes: TransferFeeAmount (4 + 8) and TransferHookAccount (4 + 1)
+        let data = mint_data(&[ExtensionType::TransferFeeConfig, ExtensionType::TransferHook]);
+        assert_eq!(token_2022_account_len(&data).unwrap(), 187);
+        // Required memos (MemoTransfer) are switched on by the account owner later, a mint
+        // never requires them, so they add nothing here
+        let data = mint_data(&[ExtensionType::TransferFeeConfig]);
+        assert_eq!(token_2022_account_len(&data).unwrap(), 182);
+    }
+
+    #[test]
+    fn unreadable_mints_are_not_registered() {
+        let mint = Pubkey::new_unique();
+        assert!(set_token_2022_mint(&mint, &[0; 10]).is_err());
+        assert_eq!(get_token_2022_account_size(&mint), None);
+    }
+
+    #[test]
+    fn derived_rent_scales_the_base_rent_to_the_account_len() {
+        set_rent(true, BASE_RENT);
+        let mint = Pubkey::new_unique();
+        let data = mint_data(&[ExtensionType::TransferFeeConfig, ExtensionType::TransferHook]);
+        assert_eq!(set_token_2022_mint(&mint, &data).unwrap(), 187);
+        let size = get_token_2022_account_size(&mint).unwrap();
+        // 6_960 lamports per byte, including the 128 bytes of storage overhead
+        assert_eq!(size.rent(), Some(6_960 * (128 + 187)));
+
+        let payer = Pubkey::new_unique();
+        let instructions =
+            create_associated_token_account_use_seed(&payer, &payer, &mint, &spl_token_2022::ID)
+                .unwrap();
+        let create = bincode::deserialize::<SystemInstruction>(&instructions[0].data).unwrap();
+        assert!(matches!(
+            create,
+            SystemInstruction::CreateAccountWithSeed { lamports, space: 187, .. }
+                if lamports == 6_960 * (128 + 187)
+        ));
+    }
+
+    #[tokio::test]
+    async fn fetched_rent_matches_the_account_len() {
+        let mint = Pubkey::new_unique();
+        let data = mint_data(&[ExtensionType::TransferFeeConfig]);
+        let rpc = SolanaRpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            HashMap::from([
+                (
+                    RpcRequest::GetAccountInfo,
+                    json!({
+                        "context": { "slot": 1 },
+                        "value": {
+                            "lamports": 1_461_600,
+                            "data": [STANDARD.encode(&data), "base64"],
+                            "owner": spl_token_2022::ID.to_string(),
+                            "executable": false,
+                            "rentEpoch": 0,
+                            "space": data.len(),
+                        },
+                    }),
+                ),
+                (RpcRequest::GetMinimumBalanceForRentExemption, json!(2_157_600)),
+            ]),
+        );
+        let size = update_token_2022_mint(&rpc, &mint).await.unwrap();
+        assert_eq!(size, Token2022AccountSize { len: 182, rent: Some(2_157_600) });
+        assert_eq!(get_token_2022_account_size(&mint), Some(size));
+    }
+
+    #[test]
+    fn unregistered_mints_fall_back_to_the_ata() {
+        set_rent(true, BASE_RENT);
+        let (payer, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(create_associated_token_account_use_seed(
+            &payer,
+            &payer,
+            &mint,
+            &spl_token_2022::ID
+        )
+        .is_err());
+        let instructions = create_associated_token_account_idempotent_fast_use_seed(
+            &payer,
+            &payer,
+            &mint,
+            &spl_token_2022::ID,
+            true,
+        );
+        assert_eq!(instructions.len(), 1);
+        assert_eq!(instructions[0].program_id, spl_associated_token_account::ID);
+    }
+
+    #[test]
+    fn seeded_token_2022_accounts_init_immutable_owner_before_the_account() {
+        set_rent(true, BASE_RENT);
+        let (payer, owner, mint) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        set_token_2022_mint(&mint, &mint_data(&[])).unwrap();
+
+        let instructions = create_associated_token_account_idempotent_fast_use_seed(
+            &payer,
+            &owner,
+            &mint,
+            &spl_token_2022::ID,
+            true,
+        );
+        let address = get_associated_token_address_with_program_id_fast_use_seed(
+            &payer,
+            &mint,
+            &spl_token_2022::ID,
+            true,
+        );
+        assert_eq!(
+            instructions.iter().map(|instruction| instruction.program_id).collect::<Vec<_>>(),
+            vec![crate::constants::SYSTEM_PROGRAM, spl_token_2022::ID, spl_token_2022::ID]
+        );
+
+        match bincode::deserialize::<SystemInstruction>(&instructions[0].data).unwrap() {
+            SystemInstruction::CreateAccountWithSeed { base, lamports, space, owner, .. } => {
+                assert_eq!(base, payer);
+                assert_eq!(space, 170);
+                assert_eq!(lamports, 6_960 * (128 + 170));
+                assert_eq!(owner, spl_token_2022::ID);
+            }
+            other => panic!("Expected CreateAccountWithSeed, got {:?}", other),
+        }
+        assert_eq!(instructions[0].accounts[1].pubkey, address);
+
+        assert_eq!(
+            TokenInstruction::unpack(&instructions[1].data).unwrap(),
+            TokenInstruction::InitializeImmutableOwner
+        );
+        assert_eq!(instructions[1].accounts[0].pubkey, address);
+
+        assert_eq!(
+            TokenInstruction::unpack(&instructions[2].data).unwrap(),
+            TokenInstruction::InitializeAccount3 { owner }
+        );
+        assert_eq!(
+            instructions[2].accounts.iter().map(|account| account.pubkey).collect::<Vec<_>>(),
+            vec![address, mint]
+        );
+    }
+}
@@ -0,0 +1,65 @@
+//! Signers of trade transactions
+//!
+//! Trades are signed through `TradeSigner` instead of a `Keypair`, so the payer's key can live in
+//! a hardware wallet or behind a remote signing service. `KeypairSigner` signs with a local
+//! keypair, which is what `SolanaTrade` uses for its own payer.
+
+use std::{fmt, sync::Arc};
+
+use anyhow::Result;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signature::Signature, signer::Signer};
+
+/// Signer of the payer's signature on trade transactions
+///
+/// `sign_message` gets the serialized message and may take a round trip, e.g. to an HSM. The
+/// parallel executor calls it once per distinct message, not once per swqos provider.
+#[async_trait::async_trait]
+pub trait TradeSigner: Send + Sync {
+    fn pubkey(&self) -> Pubkey;
+
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature>;
+}
+
+impl fmt::Debug for dyn TradeSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TradeSigner").field(&self.pubkey()).finish()
+    }
+}
+
+/// Signers are equal when they sign for the same key, which keeps the trade options comparable
+impl PartialEq for dyn TradeSigner {
+    fn eq(&self, other: &Self) -> bool {
+        self.pubkey() == other.pubkey()
+    }
+}
+
+/// `TradeSigner` of a local keypair
+pub struct KeypairSigner {
+    keypair: Arc<Keypair>,
+}
+
+impl KeypairSigner {
+    pub fn new(keypair: Arc<Keypair>) -> Self {
+        Self { keypair }
+    }
+
+    /// `keypair` as a shared `TradeSigner`, e.g. for the `payer` of the trade params
+    pub fn wrap(keypair: Arc<Keypair>) -> Arc<dyn TradeSigner> {
+        Arc::new(Self::new(keypair))
+    }
+
+    pub fn keypair(&self) -> &Arc<Keypair> {
+        &self.keypair
+    }
+}
+
+#[async_trait::async_trait]
+impl TradeSigner for KeypairSigner {
+    fn pubkey(&self) -> Pubkey {
+        Signer::pubkey(self.keypair.as_ref())
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        Ok(self.keypair.try_sign_message(message)?)
+    }
+}
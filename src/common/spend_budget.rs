@@ -0,0 +1,170 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::common::store::{KeyValueStore, MemoryStore};
+
+const SPEND_BUDGET_KEY_PREFIX: &str = "spend_budget:";
+
+/// What to do once the daily budget is used up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BudgetExhaustionAction {
+    /// Reject new trades until the next UTC day
+    BlockTrades,
+    /// Keep trading without tips, only through the Default RPC client
+    DropTips,
+    /// Only log a warning
+    WarnOnly,
+}
+
+/// Daily infrastructure spend limits (tips + priority fees), reset at 00:00 UTC
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpendBudget {
+    pub daily_tip_lamports: u64,
+    pub daily_priority_fee_lamports: u64,
+    pub action_on_exhaustion: BudgetExhaustionAction,
+}
+
+impl SpendBudget {
+    pub fn new(
+        daily_tip_lamports: u64,
+        daily_priority_fee_lamports: u64,
+        action_on_exhaustion: BudgetExhaustionAction,
+    ) -> Self {
+        Self { daily_tip_lamports, daily_priority_fee_lamports, action_on_exhaustion }
+    }
+}
+
+/// Spend accumulated for one UTC day
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DailySpend {
+    pub tip_lamports: u64,
+    pub priority_fee_lamports: u64,
+}
+
+/// Decision taken by the executor before building a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetDecision {
+    Proceed,
+    DropTips,
+    Block,
+}
+
+struct SpendState {
+    day: String,
+    spend: DailySpend,
+}
+
+/// Tracks daily spend against a `SpendBudget`, persisting through a `KeyValueStore`
+pub struct SpendTracker {
+    budget: SpendBudget,
+    store: Arc<dyn KeyValueStore>,
+    state: Mutex<SpendState>,
+}
+
+impl SpendTracker {
+    /// Create a tracker that keeps its state in memory only
+    pub fn new(budget: SpendBudget) -> Self {
+        Self::with_store(budget, Arc::new(MemoryStore::new()))
+    }
+
+    /// Create a tracker that restores and persists today's spend through `store`
+    pub fn with_store(budget: SpendBudget, store: Arc<dyn KeyValueStore>) -> Self {
+        let day = Self::today();
+        let spend = Self::load(store.as_ref(), &day);
+        Self { budget, store, state: Mutex::new(SpendState { day, spend }) }
+    }
+
+    pub fn budget(&self) -> &SpendBudget {
+        &self.budget
+    }
+
+    /// Spend recorded for the current UTC day
+    pub fn current_spend(&self) -> DailySpend {
+        let mut state = self.state.lock();
+        self.roll_day(&mut state);
+        state.spend
+    }
+
+    /// Remaining budget for the current UTC day
+    pub fn remaining(&self) -> DailySpend {
+        let spend = self.current_spend();
+        DailySpend {
+            tip_lamports: self.budget.daily_tip_lamports.saturating_sub(spend.tip_lamports),
+            priority_fee_lamports: self
+                .budget
+                .daily_priority_fee_lamports
+                .saturating_sub(spend.priority_fee_lamports),
+        }
+    }
+
+    /// Whether either daily limit has been reached
+    pub fn is_exhausted(&self) -> bool {
+        let spend = self.current_spend();
+        spend.tip_lamports >= self.budget.daily_tip_lamports
+            || spend.priority_fee_lamports >= self.budget.daily_priority_fee_lamports
+    }
+
+    /// Decide how the next trade may be sent
+    pub fn decide(&self) -> BudgetDecision {
+        if !self.is_exhausted() {
+            return BudgetDecision::Proceed;
+        }
+        let spend = self.current_spend();
+        match self.budget.action_on_exhaustion {
+            BudgetExhaustionAction::BlockTrades => BudgetDecision::Block,
+            BudgetExhaustionAction::DropTips => BudgetDecision::DropTips,
+            BudgetExhaustionAction::WarnOnly => {
+                log::warn!(
+                    icon!("⚠️ ", "Daily spend budget exhausted: tips {}/{} lamports, priority fees {}/{} lamports"),
+                    spend.tip_lamports,
+                    self.budget.daily_tip_lamports,
+                    spend.priority_fee_lamports,
+                    self.budget.daily_priority_fee_lamports
+                );
+                BudgetDecision::Proceed
+            }
+        }
+    }
+
+    /// Record spend of a sent trade
+    pub fn record(&self, tip_lamports: u64, priority_fee_lamports: u64) {
+        let mut state = self.state.lock();
+        self.roll_day(&mut state);
+        state.spend.tip_lamports = state.spend.tip_lamports.saturating_add(tip_lamports);
+        state.spend.priority_fee_lamports =
+            state.spend.priority_fee_lamports.saturating_add(priority_fee_lamports);
+        self.persist(&state);
+    }
+
+    fn roll_day(&self, state: &mut SpendState) {
+        let today = Self::today();
+        if state.day != today {
+            state.spend = Self::load(self.store.as_ref(), &today);
+            state.day = today;
+        }
+    }
+
+    fn persist(&self, state: &SpendState) {
+        let key = format!("{}{}", SPEND_BUDGET_KEY_PREFIX, state.day);
+        match serde_json::to_string(&state.spend) {
+            Ok(value) => {
+                if let Err(e) = self.store.put(&key, value) {
+                    log::error!("Failed to persist spend budget: {}", e);
+                }
+            }
+            Err(e) => log::error!("Failed to serialize spend budget: {}", e),
+        }
+    }
+
+    fn load(store: &dyn KeyValueStore, day: &str) -> DailySpend {
+        store
+            .get(&format!("{}{}", SPEND_BUDGET_KEY_PREFIX, day))
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default()
+    }
+
+    fn today() -> String {
+        chrono::Utc::now().format("%Y-%m-%d").to_string()
+    }
+}
@@ -0,0 +1,84 @@
+use anyhow::anyhow;
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use std::{collections::HashMap, path::PathBuf};
+
+/// Pluggable key/value store for state that must survive restarts
+///
+/// Values are opaque strings (usually JSON), keys are namespaced by the caller,
+/// e.g. `spend_budget:2025-01-31`.
+pub trait KeyValueStore: Send + Sync {
+    /// Get the value stored under `key`
+    fn get(&self, key: &str) -> Option<String>;
+
+    /// Store `value` under `key`, replacing any previous value
+    fn put(&self, key: &str, value: String) -> Result<(), anyhow::Error>;
+}
+
+/// In-memory store, state is lost when the process exits
+#[derive(Default)]
+pub struct MemoryStore {
+    values: DashMap<String, String>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self { values: DashMap::new() }
+    }
+}
+
+impl KeyValueStore for MemoryStore {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.get(key).map(|value| value.clone())
+    }
+
+    fn put(&self, key: &str, value: String) -> Result<(), anyhow::Error> {
+        self.values.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+/// JSON file backed store, the whole file is rewritten on every `put`
+pub struct FileStore {
+    path: PathBuf,
+    values: Mutex<HashMap<String, String>>,
+}
+
+impl FileStore {
+    /// Open the store at `path`, loading existing values if the file exists
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, anyhow::Error> {
+        let path = path.into();
+        let values = if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|e| anyhow!("Failed to read store file {:?}: {}", path, e))?;
+            if content.trim().is_empty() {
+                HashMap::new()
+            } else {
+                serde_json::from_str(&content)
+                    .map_err(|e| anyhow!("Failed to parse store file {:?}: {}", path, e))?
+            }
+        } else {
+            HashMap::new()
+        };
+        Ok(Self { path, values: Mutex::new(values) })
+    }
+}
+
+impl KeyValueStore for FileStore {
+    fn get(&self, key: &str) -> Option<String> {
+        self.values.lock().get(key).cloned()
+    }
+
+    fn put(&self, key: &str, value: String) -> Result<(), anyhow::Error> {
+        let mut values = self.values.lock();
+        values.insert(key.to_string(), value);
+        let content = serde_json::to_string_pretty(&*values)?;
+        // Write to a temporary file first so a crash never leaves a truncated store behind
+        let tmp_path = self.path.with_extension("tmp");
+        std::fs::write(&tmp_path, content)
+            .map_err(|e| anyhow!("Failed to write store file {:?}: {}", tmp_path, e))?;
+        std::fs::rename(&tmp_path, &self.path)
+            .map_err(|e| anyhow!("Failed to replace store file {:?}: {}", self.path, e))?;
+        Ok(())
+    }
+}
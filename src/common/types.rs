@@ -1,22 +1,66 @@
-use std::sync::Arc;
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
 
 use crate::{
+    common::{
+        commission::Commission, dex_defaults::DexDefaults,
+        priority_fee_estimate::PriorityFeeEstimate, spend_budget::SpendBudget,
+    },
     constants::trade::trade::{
-        DEFAULT_BUY_TIP_FEE, DEFAULT_RPC_UNIT_LIMIT, DEFAULT_RPC_UNIT_PRICE, DEFAULT_SELL_TIP_FEE,
-        DEFAULT_TIP_UNIT_LIMIT, DEFAULT_TIP_UNIT_PRICE,
+        DEFAULT_BUY_TIP_FEE, DEFAULT_MAX_TIP_LAMPORTS, DEFAULT_MIN_TIP_LAMPORTS,
+        DEFAULT_RPC_UNIT_LIMIT, DEFAULT_RPC_UNIT_PRICE, DEFAULT_SELL_TIP_FEE,
+        DEFAULT_TIP_UNIT_LIMIT, DEFAULT_TIP_UNIT_PRICE, LEGACY_TIP_LAMPORTS_THRESHOLD,
+        MAX_COMPUTE_UNIT_LIMIT,
+    },
+    swqos::{
+        confirmation::ConfirmationMode, health::DEFAULT_HEALTH_CHECK_INTERVAL, SwqosClient,
+        SwqosConfig, SwqosType, TradeType,
     },
-    swqos::{SwqosClient, SwqosConfig},
+    trading::factory::DexType,
 };
-use serde::Deserialize;
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
 use solana_client::rpc_client::RpcClient;
-use solana_sdk::{commitment_config::CommitmentConfig, signature::Keypair};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, native_token::sol_str_to_lamports, pubkey::Pubkey,
+    signature::Keypair,
+};
 
 #[derive(Debug, Clone)]
 pub struct TradeConfig {
     pub rpc_url: String,
+    /// More RPC endpoints raced against `rpc_url` for account loads and blockhashes, see
+    /// `rpc_race`
+    pub fallback_rpc_urls: Vec<String>,
     pub swqos_configs: Vec<SwqosConfig>,
     pub priority_fee: PriorityFee,
     pub commitment: CommitmentConfig,
+    /// Commitment of the blockhash fetches of the `BlockhashCache`, `commitment` when `None`
+    pub blockhash_commitment: Option<CommitmentConfig>,
+    /// Optional daily limit for tips and priority fees
+    pub spend_budget: Option<SpendBudget>,
+    /// Probe the swqos credentials in `SolanaTrade::new`, see `SolanaTrade::validate_swqos_auth`
+    pub validate_swqos_auth: bool,
+    /// Commission appended to every matching trade, overridable per trade through the options
+    pub commission: Option<Commission>,
+    /// Defaults of each protocol, see `dex_defaults` for the resolution order
+    pub per_dex_defaults: HashMap<DexType, DexDefaults>,
+    /// Slippage of trades that set none and whose protocol has no default slippage
+    pub default_slippage_bps: Option<u64>,
+    /// How the swqos clients and the RPC client confirm submitted transactions
+    pub confirmation_mode: ConfirmationMode,
+    /// Record the results of `SolanaTrade::buy` and `SolanaTrade::sell` in the
+    /// `PositionTracker`
+    pub track_position: bool,
+    /// Interval of the background swqos health checks, `None` disables them
+    pub swqos_health_check_interval: Option<Duration>,
+    /// Leave providers that fail their health checks out of trades, see `swqos::health`
+    pub skip_unhealthy_swqos: bool,
+    /// Estimate the compute unit price of trades from recent prioritization fees instead of
+    /// using the static `priority_fee` prices
+    pub priority_fee_estimate: Option<PriorityFeeEstimate>,
+    /// Account each provider's tips are paid to instead of a random one of its published tip
+    /// accounts, see `swqos::tip_account`
+    pub tip_accounts: HashMap<SwqosType, String>,
 }
 
 impl TradeConfig {
@@ -26,21 +70,507 @@ impl TradeConfig {
         priority_fee: PriorityFee,
         commitment: CommitmentConfig,
     ) -> Self {
-        Self { rpc_url, swqos_configs, priority_fee, commitment }
+        Self {
+            rpc_url,
+            fallback_rpc_urls: Vec::new(),
+            swqos_configs,
+            priority_fee,
+            commitment,
+            blockhash_commitment: None,
+            spend_budget: None,
+            validate_swqos_auth: false,
+            commission: None,
+            per_dex_defaults: HashMap::new(),
+            default_slippage_bps: None,
+            confirmation_mode: ConfirmationMode::Polling,
+            track_position: false,
+            swqos_health_check_interval: Some(DEFAULT_HEALTH_CHECK_INTERVAL),
+            skip_unhealthy_swqos: true,
+            priority_fee_estimate: None,
+            tip_accounts: HashMap::new(),
+        }
+    }
+
+    pub fn with_fallback_rpc_urls(mut self, fallback_rpc_urls: Vec<String>) -> Self {
+        self.fallback_rpc_urls = fallback_rpc_urls;
+        self
+    }
+
+    pub fn with_blockhash_commitment(mut self, blockhash_commitment: CommitmentConfig) -> Self {
+        self.blockhash_commitment = Some(blockhash_commitment);
+        self
+    }
+
+    pub fn with_spend_budget(mut self, spend_budget: SpendBudget) -> Self {
+        self.spend_budget = Some(spend_budget);
+        self
+    }
+
+    pub fn with_swqos_auth_validation(mut self, validate_swqos_auth: bool) -> Self {
+        self.validate_swqos_auth = validate_swqos_auth;
+        self
+    }
+
+    pub fn with_commission(mut self, commission: Commission) -> Self {
+        self.commission = Some(commission);
+        self
+    }
+
+    pub fn with_dex_defaults(mut self, dex_type: DexType, defaults: DexDefaults) -> Self {
+        self.per_dex_defaults.insert(dex_type, defaults);
+        self
+    }
+
+    pub fn with_default_slippage_bps(mut self, slippage_bps: u64) -> Self {
+        self.default_slippage_bps = Some(slippage_bps);
+        self
+    }
+
+    pub fn with_confirmation_mode(mut self, confirmation_mode: ConfirmationMode) -> Self {
+        self.confirmation_mode = confirmation_mode;
+        self
+    }
+
+    pub fn with_position_tracking(mut self, track_position: bool) -> Self {
+        self.track_position = track_position;
+        self
+    }
+
+    pub fn with_swqos_health_check_interval(mut self, interval: Option<Duration>) -> Self {
+        self.swqos_health_check_interval = interval;
+        self
+    }
+
+    pub fn with_skip_unhealthy_swqos(mut self, skip_unhealthy_swqos: bool) -> Self {
+        self.skip_unhealthy_swqos = skip_unhealthy_swqos;
+        self
+    }
+
+    pub fn with_priority_fee_estimate(mut self, estimate: PriorityFeeEstimate) -> Self {
+        self.priority_fee_estimate = Some(estimate);
+        self
+    }
+
+    pub fn with_tip_account(
+        mut self,
+        swqos_type: SwqosType,
+        tip_account: impl Into<String>,
+    ) -> Self {
+        self.tip_accounts.insert(swqos_type, tip_account.into());
+        self
+    }
+
+    /// Start a `TradeConfigBuilder`, which checks the configuration when it is built
+    pub fn builder(rpc_url: impl Into<String>) -> TradeConfigBuilder {
+        TradeConfigBuilder::new(rpc_url)
+    }
+
+    /// Validate the configuration before it is handed to `SolanaTrade::new`
+    pub fn validate(&self) -> Result<(), TradeConfigError> {
+        if self.rpc_url.trim().is_empty() {
+            return Err(TradeConfigError::EmptyRpcUrl);
+        }
+        for swqos_config in &self.swqos_configs {
+            swqos_config
+                .validate_region()
+                .map_err(|e| TradeConfigError::invalid("swqos_configs", e))?;
+        }
+        if let Some(commission) = &self.commission {
+            commission.validate().map_err(|e| TradeConfigError::invalid("commission", e))?;
+        }
+        for (dex_type, defaults) in &self.per_dex_defaults {
+            defaults.validate().map_err(|e| {
+                TradeConfigError::invalid(format!("per_dex_defaults[{:?}]", dex_type), e)
+            })?;
+        }
+        for (swqos_type, tip_account) in &self.tip_accounts {
+            let field = format!("tip_accounts[{:?}]", swqos_type);
+            if matches!(swqos_type, SwqosType::Default) {
+                return Err(TradeConfigError::invalid(field, "plain RPC sends carry no tip"));
+            }
+            Pubkey::from_str(tip_account).map_err(|e| {
+                TradeConfigError::invalid(field, format!("{} is not a pubkey: {}", tip_account, e))
+            })?;
+        }
+        if let Some(estimate) = &self.priority_fee_estimate {
+            estimate
+                .validate()
+                .map_err(|e| TradeConfigError::invalid("priority_fee_estimate", e))?;
+        }
+        if let Some(slippage_bps) = self.default_slippage_bps {
+            if slippage_bps > 10_000 {
+                return Err(TradeConfigError::invalid(
+                    "default_slippage_bps",
+                    format!("{} bps is above 10000", slippage_bps),
+                ));
+            }
+        }
+        self.validate_priority_fee()
+    }
+
+    /// Unit limits within what Solana allows, and one tip per swqos config that is not zero
+    /// where the provider needs a tip
+    fn validate_priority_fee(&self) -> Result<(), TradeConfigError> {
+        let priority_fee = &self.priority_fee;
+
+        let mut unit_limits = vec![
+            ("priority_fee.tip_unit_limit".to_string(), priority_fee.tip_unit_limit),
+            ("priority_fee.rpc_unit_limit".to_string(), priority_fee.rpc_unit_limit),
+        ];
+        for (name, profile) in
+            [("create", &priority_fee.create), ("create_and_buy", &priority_fee.create_and_buy)]
+        {
+            if let Some(unit_limit) = profile.as_ref().and_then(|profile| profile.unit_limit) {
+                unit_limits.push((format!("priority_fee.{}.unit_limit", name), unit_limit));
+            }
+        }
+        for (protocol_name, (unit_limit, _)) in &priority_fee.protocol_limits {
+            unit_limits
+                .push((format!("priority_fee.protocol_limits[{}]", protocol_name), *unit_limit));
+        }
+        for (field, limit) in unit_limits {
+            if limit > MAX_COMPUTE_UNIT_LIMIT {
+                return Err(TradeConfigError::UnitLimitTooHigh { field, limit });
+            }
+        }
+
+        let mut tip_lists = match &priority_fee.tip_strategy {
+            TipStrategy::Fixed => vec![
+                (
+                    if priority_fee.buy_tips.is_empty() { "buy_tip_fees" } else { "buy_tips" },
+                    priority_fee.resolved_buy_tips(),
+                ),
+                (
+                    if priority_fee.sell_tips.is_empty() { "sell_tip_fees" } else { "sell_tips" },
+                    priority_fee.resolved_sell_tips(),
+                ),
+            ],
+            TipStrategy::PerClient(tips) => vec![(
+                "tip_strategy",
+                tips.iter().map(|tip| TipAmount::from_legacy_sol(*tip)).collect(),
+            )],
+            TipStrategy::PercentOfTrade(_) => Vec::new(),
+        };
+        for (name, profile) in [
+            ("create.tips", &priority_fee.create),
+            ("create_and_buy.tips", &priority_fee.create_and_buy),
+        ] {
+            if let Some(profile) = profile.as_ref().filter(|profile| !profile.tips.is_empty()) {
+                tip_lists.push((name, profile.tips.clone()));
+            }
+        }
+        for (name, tips) in &tip_lists {
+            let field = format!("priority_fee.{}", name);
+            if tips.len() < self.swqos_configs.len() {
+                return Err(TradeConfigError::TipCountMismatch {
+                    field,
+                    tips: tips.len(),
+                    swqos_configs: self.swqos_configs.len(),
+                });
+            }
+            for (index, swqos_config) in self.swqos_configs.iter().enumerate() {
+                if let Some((swqos_type, _, _)) = swqos_config.provider() {
+                    if tips[index].to_lamports() == 0 {
+                        return Err(TradeConfigError::ZeroTip { field, index, swqos_type });
+                    }
+                }
+            }
+        }
+
+        priority_fee.validate_tips().map_err(|e| TradeConfigError::invalid("priority_fee", e))
+    }
+}
+
+/// Field of a `TradeConfig` that `TradeConfig::validate` rejected
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TradeConfigError {
+    #[error("rpc_url is empty")]
+    EmptyRpcUrl,
+    /// Fewer tips than swqos configs, tips are matched to the configs by position
+    #[error(
+        "{field} has {tips} tips for {swqos_configs} swqos configs, configure one tip per swqos \
+         config in the same order"
+    )]
+    TipCountMismatch { field: String, tips: usize, swqos_configs: usize },
+    /// A provider that only lands transactions paying it a tip got a tip of zero
+    #[error("{field}[{index}] is zero, but {swqos_type:?} requires a tip")]
+    ZeroTip { field: String, index: usize, swqos_type: SwqosType },
+    #[error("{field} of {limit} compute units is above the maximum of {}", MAX_COMPUTE_UNIT_LIMIT)]
+    UnitLimitTooHigh { field: String, limit: u32 },
+    #[error("{field}: {message}")]
+    Invalid { field: String, message: String },
+}
+
+impl TradeConfigError {
+    fn invalid(field: impl Into<String>, message: impl std::fmt::Display) -> Self {
+        TradeConfigError::Invalid { field: field.into(), message: message.to_string() }
+    }
+}
+
+/// Builds a `TradeConfig`, checking it with `TradeConfig::validate` in `build`
+///
+/// Starts without swqos configs, with the default `PriorityFee` and confirmed commitment; the
+/// other settings default as in `TradeConfig::new`.
+#[derive(Debug, Clone)]
+pub struct TradeConfigBuilder {
+    config: TradeConfig,
+}
+
+impl TradeConfigBuilder {
+    pub fn new(rpc_url: impl Into<String>) -> Self {
+        Self {
+            config: TradeConfig::new(
+                rpc_url.into(),
+                Vec::new(),
+                PriorityFee::default(),
+                CommitmentConfig::confirmed(),
+            ),
+        }
+    }
+
+    pub fn with_swqos_configs(mut self, swqos_configs: Vec<SwqosConfig>) -> Self {
+        self.config.swqos_configs = swqos_configs;
+        self
+    }
+
+    /// Append one swqos config, its tips go at the same position of the tip lists
+    pub fn with_swqos_config(mut self, swqos_config: SwqosConfig) -> Self {
+        self.config.swqos_configs.push(swqos_config);
+        self
+    }
+
+    pub fn with_priority_fee(mut self, priority_fee: PriorityFee) -> Self {
+        self.config.priority_fee = priority_fee;
+        self
+    }
+
+    pub fn with_commitment(mut self, commitment: CommitmentConfig) -> Self {
+        self.config.commitment = commitment;
+        self
+    }
+
+    pub fn with_fallback_rpc_urls(mut self, fallback_rpc_urls: Vec<String>) -> Self {
+        self.config = self.config.with_fallback_rpc_urls(fallback_rpc_urls);
+        self
+    }
+
+    pub fn with_blockhash_commitment(mut self, blockhash_commitment: CommitmentConfig) -> Self {
+        self.config = self.config.with_blockhash_commitment(blockhash_commitment);
+        self
+    }
+
+    pub fn with_spend_budget(mut self, spend_budget: SpendBudget) -> Self {
+        self.config = self.config.with_spend_budget(spend_budget);
+        self
+    }
+
+    pub fn with_swqos_auth_validation(mut self, validate_swqos_auth: bool) -> Self {
+        self.config = self.config.with_swqos_auth_validation(validate_swqos_auth);
+        self
+    }
+
+    pub fn with_commission(mut self, commission: Commission) -> Self {
+        self.config = self.config.with_commission(commission);
+        self
+    }
+
+    pub fn with_dex_defaults(mut self, dex_type: DexType, defaults: DexDefaults) -> Self {
+        self.config = self.config.with_dex_defaults(dex_type, defaults);
+        self
+    }
+
+    pub fn with_default_slippage_bps(mut self, slippage_bps: u64) -> Self {
+        self.config = self.config.with_default_slippage_bps(slippage_bps);
+        self
+    }
+
+    pub fn with_confirmation_mode(mut self, confirmation_mode: ConfirmationMode) -> Self {
+        self.config = self.config.with_confirmation_mode(confirmation_mode);
+        self
+    }
+
+    pub fn with_position_tracking(mut self, track_position: bool) -> Self {
+        self.config = self.config.with_position_tracking(track_position);
+        self
+    }
+
+    pub fn with_swqos_health_check_interval(mut self, interval: Option<Duration>) -> Self {
+        self.config = self.config.with_swqos_health_check_interval(interval);
+        self
+    }
+
+    pub fn with_skip_unhealthy_swqos(mut self, skip_unhealthy_swqos: bool) -> Self {
+        self.config = self.config.with_skip_unhealthy_swqos(skip_unhealthy_swqos);
+        self
+    }
+
+    pub fn with_priority_fee_estimate(mut self, estimate: PriorityFeeEstimate) -> Self {
+        self.config = self.config.with_priority_fee_estimate(estimate);
+        self
+    }
+
+    pub fn with_tip_account(
+        mut self,
+        swqos_type: SwqosType,
+        tip_account: impl Into<String>,
+    ) -> Self {
+        self.config = self.config.with_tip_account(swqos_type, tip_account);
+        self
+    }
+
+    /// The configuration, or the first field `TradeConfig::validate` rejects
+    pub fn build(self) -> Result<TradeConfig, TradeConfigError> {
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+/// Tip amount with an explicit unit
+///
+/// Tips are converted to lamports exactly once, when the tip transfer instruction is built.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum TipAmount {
+    /// Tip amount in SOL (e.g. 0.001)
+    Sol(f64),
+    /// Tip amount in lamports (e.g. 1_000_000)
+    Lamports(u64),
+}
+
+impl TipAmount {
+    /// Convert the tip to lamports
+    pub fn to_lamports(&self) -> u64 {
+        match self {
+            TipAmount::Sol(sol) => sol_str_to_lamports(sol.to_string().as_str()).unwrap_or(0),
+            TipAmount::Lamports(lamports) => *lamports,
+        }
+    }
+
+    /// Interpret a legacy `f64` tip fee (documented as SOL)
+    ///
+    /// Integral values at or above `LEGACY_TIP_LAMPORTS_THRESHOLD` are almost certainly
+    /// lamports entered into the SOL field, so they are auto-corrected.
+    /// `PriorityFee::warn_legacy_tips` reports the corrected values.
+    pub fn from_legacy_sol(value: f64) -> Self {
+        if Self::legacy_looks_like_lamports(value) {
+            return TipAmount::Lamports(value as u64);
+        }
+        TipAmount::Sol(value)
+    }
+
+    fn legacy_looks_like_lamports(value: f64) -> bool {
+        value >= LEGACY_TIP_LAMPORTS_THRESHOLD && value.fract() == 0.0
+    }
+}
+
+/// Sane range for a single tip transfer, in lamports
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct TipBounds {
+    pub min_lamports: u64,
+    pub max_lamports: u64,
+}
+
+impl Default for TipBounds {
+    fn default() -> Self {
+        Self { min_lamports: DEFAULT_MIN_TIP_LAMPORTS, max_lamports: DEFAULT_MAX_TIP_LAMPORTS }
+    }
+}
+
+impl TipBounds {
+    /// Check that the range is not empty
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.min_lamports > self.max_lamports {
+            return Err(anyhow!(
+                "Minimum tip of {} lamports is above the maximum of {} lamports",
+                self.min_lamports,
+                self.max_lamports
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that a tip lies inside the configured range
+    pub fn check(&self, tip: &TipAmount) -> Result<u64, anyhow::Error> {
+        let lamports = tip.to_lamports();
+        if lamports < self.min_lamports {
+            return Err(anyhow!(
+                "Tip {:?} ({} lamports) is below the minimum of {} lamports. If you meant SOL, use TipAmount::Sol; if you meant lamports, use TipAmount::Lamports",
+                tip,
+                lamports,
+                self.min_lamports
+            ));
+        }
+        if lamports > self.max_lamports {
+            return Err(anyhow!(
+                "Tip {:?} ({} lamports) exceeds the maximum of {} lamports. If you meant lamports, use TipAmount::Lamports instead of SOL",
+                tip,
+                lamports,
+                self.max_lamports
+            ));
+        }
+        Ok(lamports)
     }
 }
 
-#[derive(Debug, Deserialize, Clone, PartialEq)]
+/// How the tip sent to each swqos client is sized
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub enum TipStrategy {
+    /// `buy_tips` for buys and `sell_tips` for sells
+    #[default]
+    Fixed,
+    /// One tip per swqos client in SOL, in swqos order, for buys and sells alike
+    PerClient(Vec<f64>),
+    /// Percentage of the SOL the trade spends or receives, e.g. 0.5 tips 0.5%, clamped into
+    /// `tip_bounds`
+    PercentOfTrade(f64),
+}
+
+/// Compute budget and tips for one trade type, unset entries keep the base values
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct TradeTypeFee {
+    #[serde(default)]
+    pub unit_limit: Option<u32>,
+    #[serde(default)]
+    pub unit_price: Option<u64>,
+    // Matches the order of swqos, empty keeps `buy_tips`
+    #[serde(default)]
+    pub tips: Vec<TipAmount>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct PriorityFee {
     pub tip_unit_limit: u32,
     pub tip_unit_price: u64,
     pub rpc_unit_limit: u32,
     pub rpc_unit_price: u64,
     // Matches the order of swqos
+    // Deprecated: values are SOL as f64, prefer `buy_tips`
     pub buy_tip_fees: Vec<f64>,
     // Matches the order of swqos
+    // Deprecated: values are SOL as f64, prefer `sell_tips`
     pub sell_tip_fees: Vec<f64>,
-    
+    // Matches the order of swqos, takes precedence over `buy_tip_fees` when not empty
+    #[serde(default)]
+    pub buy_tips: Vec<TipAmount>,
+    // Matches the order of swqos, takes precedence over `sell_tip_fees` when not empty
+    #[serde(default)]
+    pub sell_tips: Vec<TipAmount>,
+    // Allowed range for every tip, checked before any transaction is built
+    #[serde(default)]
+    pub tip_bounds: TipBounds,
+    // How tips are sized, `Fixed` uses `buy_tips` and `sell_tips`
+    #[serde(default)]
+    pub tip_strategy: TipStrategy,
+    // Overrides for token creation transactions
+    #[serde(default)]
+    pub create: Option<TradeTypeFee>,
+    // Overrides for create-and-buy transactions, usually with a larger tip
+    #[serde(default)]
+    pub create_and_buy: Option<TradeTypeFee>,
+    // (unit_limit, unit_price) keyed by the executor's protocol name, e.g. "RaydiumClmmV2",
+    // replacing the tip and RPC values for that protocol's transactions
+    #[serde(default)]
+    pub protocol_limits: HashMap<String, (u32, u64)>,
+
     // CUSTOM FIELDS: Restored from backup for compatibility with our trading system
     pub unit_limit: u32,
     pub unit_price: u64, 
@@ -60,7 +590,14 @@ impl Default for PriorityFee {
             buy_tip_fees: vec![DEFAULT_BUY_TIP_FEE],
             // Matches the order of swqos
             sell_tip_fees: vec![DEFAULT_SELL_TIP_FEE],
-            
+            buy_tips: vec![],
+            sell_tips: vec![],
+            tip_bounds: TipBounds::default(),
+            tip_strategy: TipStrategy::default(),
+            create: None,
+            create_and_buy: None,
+            protocol_limits: HashMap::new(),
+
             // CUSTOM FIELDS: Default values for compatibility
             unit_limit: DEFAULT_TIP_UNIT_LIMIT, // Use tip unit limit as default
             unit_price: DEFAULT_TIP_UNIT_PRICE, // Use tip unit price as default
@@ -71,6 +608,230 @@ impl Default for PriorityFee {
     }
 }
 
+impl PriorityFee {
+    /// Set buy tips with explicit units, matches the order of swqos
+    pub fn with_buy_tips(mut self, buy_tips: Vec<TipAmount>) -> Self {
+        self.buy_tips = buy_tips;
+        self
+    }
+
+    /// Set sell tips with explicit units, matches the order of swqos
+    pub fn with_sell_tips(mut self, sell_tips: Vec<TipAmount>) -> Self {
+        self.sell_tips = sell_tips;
+        self
+    }
+
+    /// Size tips per `tip_strategy` instead of the fixed buy and sell tips
+    pub fn with_tip_strategy(mut self, tip_strategy: TipStrategy) -> Self {
+        self.tip_strategy = tip_strategy;
+        self
+    }
+
+    #[deprecated(note = "tip units are ambiguous, use `with_buy_tips` with `TipAmount`")]
+    pub fn with_buy_tip_fees(mut self, buy_tip_fees: Vec<f64>) -> Self {
+        self.buy_tips.clear();
+        self.buy_tip_fees = buy_tip_fees;
+        self
+    }
+
+    #[deprecated(note = "tip units are ambiguous, use `with_sell_tips` with `TipAmount`")]
+    pub fn with_sell_tip_fees(mut self, sell_tip_fees: Vec<f64>) -> Self {
+        self.sell_tips.clear();
+        self.sell_tip_fees = sell_tip_fees;
+        self
+    }
+
+    /// Set the tip and RPC unit price, in micro-lamports
+    pub fn with_unit_price(mut self, unit_price: u64) -> Self {
+        self.tip_unit_price = unit_price;
+        self.rpc_unit_price = unit_price;
+        self
+    }
+
+    /// Set the profile used for `TradeType::Create` transactions
+    pub fn with_create_fee(mut self, create: TradeTypeFee) -> Self {
+        self.create = Some(create);
+        self
+    }
+
+    /// Set the profile used for `TradeType::CreateAndBuy` transactions
+    pub fn with_create_and_buy_fee(mut self, create_and_buy: TradeTypeFee) -> Self {
+        self.create_and_buy = Some(create_and_buy);
+        self
+    }
+
+    /// Set the compute unit limit and price used for transactions of `protocol`
+    ///
+    /// `protocol` is the executor's protocol name, e.g. "PumpFun" or "RaydiumClmmV2".
+    pub fn with_protocol_limits(
+        mut self,
+        protocol: impl Into<String>,
+        unit_limit: u32,
+        unit_price: u64,
+    ) -> Self {
+        self.protocol_limits.insert(protocol.into(), (unit_limit, unit_price));
+        self
+    }
+
+    /// Unit price and limit for a transaction of `protocol_name`, falling back to the RPC or tip
+    /// values when the protocol has no entry
+    pub fn unit_price_and_limit(&self, protocol_name: &str, is_rpc: bool) -> (u64, u32) {
+        if let Some((unit_limit, unit_price)) = self.protocol_limits.get(protocol_name) {
+            return (*unit_price, *unit_limit);
+        }
+        if is_rpc {
+            (self.rpc_unit_price, self.rpc_unit_limit)
+        } else {
+            (self.tip_unit_price, self.tip_unit_limit)
+        }
+    }
+
+    /// Apply `f` to every unit limit, the per-protocol ones included
+    pub fn map_unit_limits(&mut self, f: impl Fn(u32) -> u32) {
+        self.tip_unit_limit = f(self.tip_unit_limit);
+        self.rpc_unit_limit = f(self.rpc_unit_limit);
+        for (unit_limit, _) in self.protocol_limits.values_mut() {
+            *unit_limit = f(*unit_limit);
+        }
+    }
+
+    /// Profile configured for a trade type, `None` for buys and sells
+    pub fn trade_type_fee(&self, trade_type: TradeType) -> Option<&TradeTypeFee> {
+        match trade_type {
+            TradeType::Create => self.create.as_ref(),
+            TradeType::CreateAndBuy => self.create_and_buy.as_ref(),
+            TradeType::Buy | TradeType::Sell => None,
+        }
+    }
+
+    /// Fees with the trade type's profile applied to both the tip and the RPC compute budget
+    pub fn for_trade_type(&self, trade_type: TradeType) -> PriorityFee {
+        let mut priority_fee = self.clone();
+        let Some(profile) = self.trade_type_fee(trade_type) else {
+            return priority_fee;
+        };
+        if let Some(unit_limit) = profile.unit_limit {
+            priority_fee.tip_unit_limit = unit_limit;
+            priority_fee.rpc_unit_limit = unit_limit;
+        }
+        if let Some(unit_price) = profile.unit_price {
+            priority_fee.tip_unit_price = unit_price;
+            priority_fee.rpc_unit_price = unit_price;
+        }
+        if !profile.tips.is_empty() {
+            priority_fee.buy_tips = profile.tips.clone();
+            priority_fee.tip_strategy = TipStrategy::Fixed;
+        }
+        priority_fee
+    }
+
+    /// Buy tips in swqos order, falling back to the legacy `buy_tip_fees`
+    pub fn resolved_buy_tips(&self) -> Vec<TipAmount> {
+        if !self.buy_tips.is_empty() {
+            return self.buy_tips.clone();
+        }
+        self.buy_tip_fees.iter().map(|fee| TipAmount::from_legacy_sol(*fee)).collect()
+    }
+
+    /// Sell tips in swqos order, falling back to the legacy `sell_tip_fees`
+    pub fn resolved_sell_tips(&self) -> Vec<TipAmount> {
+        if !self.sell_tips.is_empty() {
+            return self.sell_tips.clone();
+        }
+        self.sell_tip_fees.iter().map(|fee| TipAmount::from_legacy_sol(*fee)).collect()
+    }
+
+    /// Tips in lamports for `client_count` swqos clients, in swqos order, per `tip_strategy`
+    ///
+    /// `trade_lamports` is the SOL the trade spends or receives, only read by
+    /// `TipStrategy::PercentOfTrade`. Configured tips are checked against `tip_bounds`, a
+    /// percentage of the trade is clamped into them.
+    pub fn tip_lamports(
+        &self,
+        is_buy: bool,
+        client_count: usize,
+        trade_lamports: u64,
+    ) -> Result<Vec<u64>, anyhow::Error> {
+        let (tips, name) = match &self.tip_strategy {
+            TipStrategy::Fixed if is_buy => (self.resolved_buy_tips(), "buy_tips"),
+            TipStrategy::Fixed => (self.resolved_sell_tips(), "sell_tips"),
+            TipStrategy::PerClient(tips) => (
+                tips.iter().map(|tip| TipAmount::from_legacy_sol(*tip)).collect(),
+                "TipStrategy::PerClient tips",
+            ),
+            TipStrategy::PercentOfTrade(percent) => {
+                let lamports = (trade_lamports as f64 * percent / 100.0) as u64;
+                let lamports =
+                    lamports.max(self.tip_bounds.min_lamports).min(self.tip_bounds.max_lamports);
+                return Ok(vec![lamports; client_count]);
+            }
+        };
+        if tips.is_empty() || tips.len() < client_count {
+            return Err(anyhow!(
+                "Number of tip clients exceeds the configured tips. Please configure {} to match swqos_clients",
+                name
+            ));
+        }
+        tips.iter().map(|tip| self.tip_bounds.check(tip)).collect()
+    }
+
+    /// Replace every buy tip with the same amount, keeping the number of entries
+    pub fn override_buy_tips(&mut self, tip: TipAmount) {
+        let count = self.resolved_buy_tips().len();
+        self.buy_tips = vec![tip; count];
+        if let TipAmount::Sol(sol) = tip {
+            self.buy_tip_fee = sol;
+            self.buy_tip_fees = vec![sol; count];
+        }
+    }
+
+    /// Log a warning for every legacy `f64` tip that `TipAmount::from_legacy_sol` reads as
+    /// lamports
+    ///
+    /// `SolanaTrade::new` calls it once, trades convert the same tips without warning again.
+    pub fn warn_legacy_tips(&self) {
+        let mut legacy = Vec::new();
+        if self.buy_tips.is_empty() {
+            legacy.extend(self.buy_tip_fees.iter().map(|fee| ("buy_tip_fees", *fee)));
+        }
+        if self.sell_tips.is_empty() {
+            legacy.extend(self.sell_tip_fees.iter().map(|fee| ("sell_tip_fees", *fee)));
+        }
+        if let TipStrategy::PerClient(tips) = &self.tip_strategy {
+            legacy.extend(tips.iter().map(|tip| ("TipStrategy::PerClient", *tip)));
+        }
+        for (field, value) in legacy {
+            if TipAmount::legacy_looks_like_lamports(value) {
+                log::warn!(
+                    icon!("⚠️ ", "{} tip {} is specified in SOL but looks like lamports, using {} lamports. Use TipAmount::Lamports to silence this warning"),
+                    field,
+                    value,
+                    value as u64
+                );
+            }
+        }
+    }
+
+    /// Validate all configured tips against `tip_bounds`
+    pub fn validate_tips(&self) -> Result<(), anyhow::Error> {
+        self.tip_bounds.validate()?;
+        for tip in self.resolved_buy_tips().iter().chain(self.resolved_sell_tips().iter()) {
+            self.tip_bounds.check(tip)?;
+        }
+        if let TipStrategy::PerClient(tips) = &self.tip_strategy {
+            for tip in tips {
+                self.tip_bounds.check(&TipAmount::from_legacy_sol(*tip))?;
+            }
+        }
+        for profile in self.create.iter().chain(self.create_and_buy.iter()) {
+            for tip in &profile.tips {
+                self.tip_bounds.check(tip)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 pub type SolanaRpcClient = solana_client::nonblocking::rpc_client::RpcClient;
 
 pub struct MethodArgs {
@@ -92,3 +853,162 @@ impl MethodArgs {
 }
 
 pub type AnyResult<T> = anyhow::Result<T>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_with(priority_fee: PriorityFee) -> Result<TradeConfig, TradeConfigError> {
+        TradeConfig::builder("http://127.0.0.1:8899").with_priority_fee(priority_fee).build()
+    }
+
+    #[test]
+    fn tips_convert_to_lamports() {
+        assert_eq!(TipAmount::Sol(0.001).to_lamports(), 1_000_000);
+        assert_eq!(TipAmount::Sol(0.0006).to_lamports(), 600_000);
+        assert_eq!(TipAmount::Lamports(1_500).to_lamports(), 1_500);
+    }
+
+    #[test]
+    fn legacy_tips_that_look_like_lamports_are_corrected() {
+        assert_eq!(TipAmount::from_legacy_sol(1_000_000.0), TipAmount::Lamports(1_000_000));
+        assert_eq!(TipAmount::from_legacy_sol(1_000.0), TipAmount::Lamports(1_000));
+        assert_eq!(TipAmount::from_legacy_sol(0.001), TipAmount::Sol(0.001));
+        assert_eq!(TipAmount::from_legacy_sol(1_000.5), TipAmount::Sol(1_000.5));
+    }
+
+    #[test]
+    fn tips_outside_the_bounds_are_rejected() {
+        let bounds = TipBounds::default();
+        assert_eq!(bounds.check(&TipAmount::Sol(0.001)).unwrap(), 1_000_000);
+        assert!(bounds.check(&TipAmount::Lamports(DEFAULT_MIN_TIP_LAMPORTS - 1)).is_err());
+        assert!(bounds.check(&TipAmount::Sol(2.0)).is_err());
+    }
+
+    #[test]
+    fn inverted_tip_bounds_are_rejected() {
+        assert!(TipBounds::default().validate().is_ok());
+        assert!(TipBounds { min_lamports: 10, max_lamports: 10 }.validate().is_ok());
+        assert!(TipBounds { min_lamports: 11, max_lamports: 10 }.validate().is_err());
+    }
+
+    #[test]
+    fn builder_rejects_invalid_tips() {
+        assert!(build_with(PriorityFee::default()).is_ok());
+
+        let below_minimum = PriorityFee::default().with_buy_tips(vec![TipAmount::Lamports(1)]);
+        assert!(matches!(
+            build_with(below_minimum),
+            Err(TradeConfigError::Invalid { field, .. }) if field == "priority_fee"
+        ));
+
+        let inverted_bounds = PriorityFee {
+            tip_bounds: TipBounds { min_lamports: 2_000_000, max_lamports: 1_000 },
+            ..PriorityFee::default()
+        };
+        assert!(matches!(
+            build_with(inverted_bounds),
+            Err(TradeConfigError::Invalid { field, .. }) if field == "priority_fee"
+        ));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn legacy_tip_fees_apply_without_explicit_tips() {
+        let priority_fee = PriorityFee::default().with_buy_tip_fees(vec![0.001, 1_000_000.0]);
+        assert_eq!(
+            priority_fee.resolved_buy_tips(),
+            vec![TipAmount::Sol(0.001), TipAmount::Lamports(1_000_000)]
+        );
+        assert_eq!(priority_fee.tip_lamports(true, 2, 0).unwrap(), vec![1_000_000, 1_000_000]);
+        assert!(build_with(priority_fee).is_ok());
+    }
+
+    #[test]
+    fn explicit_tips_take_precedence_over_legacy_tip_fees() {
+        let priority_fee = PriorityFee::default()
+            .with_buy_tips(vec![TipAmount::Lamports(5_000)])
+            .with_sell_tips(vec![TipAmount::Sol(0.002)]);
+        assert_eq!(priority_fee.buy_tip_fees, vec![DEFAULT_BUY_TIP_FEE]);
+        assert_eq!(priority_fee.resolved_buy_tips(), vec![TipAmount::Lamports(5_000)]);
+        assert_eq!(priority_fee.tip_lamports(false, 1, 0).unwrap(), vec![2_000_000]);
+    }
+
+    #[test]
+    fn priority_fee_without_tip_fields_deserializes() {
+        let mut json = serde_json::to_value(PriorityFee::default()).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        for field in ["buy_tips", "sell_tips", "tip_bounds", "tip_strategy"] {
+            fields.remove(field);
+        }
+        let priority_fee: PriorityFee = serde_json::from_value(json).unwrap();
+        assert_eq!(priority_fee, PriorityFee::default());
+        assert_eq!(priority_fee.resolved_sell_tips(), vec![TipAmount::Sol(DEFAULT_SELL_TIP_FEE)]);
+    }
+
+    fn profiled_fee() -> PriorityFee {
+        PriorityFee::default()
+            .with_buy_tips(vec![TipAmount::Lamports(1_000_000)])
+            .with_create_fee(TradeTypeFee {
+                unit_limit: Some(250_000),
+                unit_price: None,
+                tips: vec![TipAmount::Lamports(2_000_000)],
+            })
+            .with_create_and_buy_fee(TradeTypeFee {
+                unit_limit: Some(400_000),
+                unit_price: Some(900_000),
+                tips: vec![TipAmount::Sol(0.01)],
+            })
+    }
+
+    #[test]
+    fn each_trade_type_selects_its_tip_profile() {
+        let priority_fee = profiled_fee();
+
+        let create = priority_fee.for_trade_type(TradeType::Create);
+        assert_eq!(create.tip_lamports(true, 1, 0).unwrap(), vec![2_000_000]);
+        assert_eq!((create.tip_unit_limit, create.rpc_unit_limit), (250_000, 250_000));
+        assert_eq!(create.tip_unit_price, priority_fee.tip_unit_price);
+        assert_eq!(create.rpc_unit_price, priority_fee.rpc_unit_price);
+
+        let create_and_buy = priority_fee.for_trade_type(TradeType::CreateAndBuy);
+        assert_eq!(create_and_buy.tip_lamports(true, 1, 0).unwrap(), vec![10_000_000]);
+        assert_eq!(
+            (create_and_buy.tip_unit_limit, create_and_buy.rpc_unit_limit),
+            (400_000, 400_000)
+        );
+        assert_eq!(
+            (create_and_buy.tip_unit_price, create_and_buy.rpc_unit_price),
+            (900_000, 900_000)
+        );
+
+        for trade_type in [TradeType::Buy, TradeType::Sell] {
+            assert!(priority_fee.trade_type_fee(trade_type).is_none());
+            assert_eq!(priority_fee.for_trade_type(trade_type), priority_fee);
+        }
+        assert_eq!(
+            priority_fee.for_trade_type(TradeType::Buy).tip_lamports(true, 1, 0).unwrap(),
+            vec![1_000_000]
+        );
+    }
+
+    #[test]
+    fn missing_or_empty_profiles_keep_the_buy_fees() {
+        let priority_fee =
+            PriorityFee::default().with_buy_tips(vec![TipAmount::Lamports(1_000_000)]);
+        assert_eq!(priority_fee.for_trade_type(TradeType::Create), priority_fee);
+        assert_eq!(priority_fee.for_trade_type(TradeType::CreateAndBuy), priority_fee);
+
+        let priority_fee = priority_fee
+            .with_tip_strategy(TipStrategy::PercentOfTrade(1.0))
+            .with_create_and_buy_fee(TradeTypeFee {
+                unit_price: Some(5),
+                ..TradeTypeFee::default()
+            });
+        let create_and_buy = priority_fee.for_trade_type(TradeType::CreateAndBuy);
+        assert_eq!(create_and_buy.tip_unit_price, 5);
+        assert_eq!(create_and_buy.tip_unit_limit, priority_fee.tip_unit_limit);
+        assert_eq!(create_and_buy.buy_tips, priority_fee.buy_tips);
+        assert_eq!(create_and_buy.tip_strategy, TipStrategy::PercentOfTrade(1.0));
+    }
+}
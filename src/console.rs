@@ -0,0 +1,20 @@
+//! Console and log line decoration
+//!
+//! Lines printed or logged by the SDK start with an emoji marker. Building with the
+//! `plain-console` feature drops the markers so the output stays ASCII for log pipelines that
+//! mangle them. Colors are never written, and error messages never carry a marker.
+
+/// Format string `$text` prefixed with `$icon`, or just `$text` with `plain-console`
+#[cfg(not(feature = "plain-console"))]
+macro_rules! icon {
+    ($icon:literal, $text:literal) => {
+        concat!($icon, $text)
+    };
+}
+
+#[cfg(feature = "plain-console")]
+macro_rules! icon {
+    ($icon:literal, $text:literal) => {
+        $text
+    };
+}
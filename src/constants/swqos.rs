@@ -1,6 +1,7 @@
 use solana_program::pubkey;
 use solana_sdk::pubkey::Pubkey;
 
+use crate::swqos::{SwqosRegion, SwqosType};
 
 pub const JITO_TIP_ACCOUNTS: &[Pubkey] = &[
     pubkey!("96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5"),
@@ -109,111 +110,115 @@ pub const ASTRALANE_TIP_ACCOUNTS: &[Pubkey] = &[
     pubkey!("astrawVNP4xDBKT7rAdxrLYiTSTdqtUr63fSMduivXK"),
 ];
 
-// NewYork,
-// Frankfurt,
-// Amsterdam,
-// SLC,
-// Tokyo,
-// London,
-// LosAngeles,
-// Default,
-
-pub const SWQOS_ENDPOINTS_JITO: [&str; 8] = [
-    "https://ny.mainnet.block-engine.jito.wtf", 
-    "https://frankfurt.mainnet.block-engine.jito.wtf",
-    "https://amsterdam.mainnet.block-engine.jito.wtf",
-    "https://slc.mainnet.block-engine.jito.wtf",
-    "https://tokyo.mainnet.block-engine.jito.wtf",
-    "https://london.mainnet.block-engine.jito.wtf",
-    "https://ny.mainnet.block-engine.jito.wtf", 
-    "https://mainnet.block-engine.jito.wtf",
-];
-
-pub const SWQOS_ENDPOINTS_NEXTBLOCK: [&str; 8] = [
-    "http://ny.nextblock.io",
-    "http://frankfurt.nextblock.io",
-    "http://amsterdam.nextblock.io",
-    "http://slc.nextblock.io",
-    "http://tokyo.nextblock.io",  
-    "http://london.nextblock.io", 
-    "http://singapore.nextblock.io",
-    "http://frankfurt.nextblock.io",
-];
-
-pub const SWQOS_ENDPOINTS_ZERO_SLOT: [&str; 8] = [
-    "http://ny.0slot.trade",
-    "http://de.0slot.trade",
-    "http://ams.0slot.trade",
-    "http://ny.0slot.trade",
-    "http://jp.0slot.trade",
-    "http://ams.0slot.trade",
-    "http://la.0slot.trade",
-    "http://de.0slot.trade",
-];
-
-pub const SWQOS_ENDPOINTS_TEMPORAL: [&str; 8] = [
-    "http://ewr1.nozomi.temporal.xyz",
-    "http://fra2.nozomi.temporal.xyz",
-    "http://ams1.nozomi.temporal.xyz",
-    "http://ewr1.nozomi.temporal.xyz",
-    "http://tyo1.nozomi.temporal.xyz",
-    "http://sgp1.nozomi.temporal.xyz",
-    "http://pit1.nozomi.temporal.xyz",
-    "http://fra2.nozomi.temporal.xyz",
-];
-
-pub const SWQOS_ENDPOINTS_BLOX: [&str; 8] = [
-    "https://ny.solana.dex.blxrbdn.com",
-    "https://germany.solana.dex.blxrbdn.com",
-    "https://amsterdam.solana.dex.blxrbdn.com",
-    "https://ny.solana.dex.blxrbdn.com",
-    "https://tokyo.solana.dex.blxrbdn.com",
-    "https://uk.solana.dex.blxrbdn.com",
-    "https://la.solana.dex.blxrbdn.com",
-    "https://germany.solana.dex.blxrbdn.com",
-];
-
-pub const SWQOS_ENDPOINTS_NODE1: [&str; 8] = [
-    "http://ny.node1.me",
-    "http://fra.node1.me",
-    "http://ams.node1.me",
-    "http://ny.node1.me",
-    "http://fra.node1.me",
-    "http://ams.node1.me",
-    "http://ny.node1.me",
-    "http://fra.node1.me",
-];
-
-pub const SWQOS_ENDPOINTS_FLASHBLOCK: [&str; 8] = [
-    "http://ny.flashblock.trade",
-    "http://fra.flashblock.trade",
-    "http://ams.flashblock.trade",
-    "http://slc.flashblock.trade",
-    "http://singapore.flashblock.trade",
-    "http://london.flashblock.trade",
-    "http://ny.flashblock.trade",
-    "http://ny.flashblock.trade",
-];
-
-pub const SWQOS_ENDPOINTS_BLOCKRAZOR: [&str; 8] = [
-    "http://newyork.solana.blockrazor.xyz:443/sendTransaction",
-    "http://frankfurt.solana.blockrazor.xyz:443/sendTransaction",
-    "http://amsterdam.solana.blockrazor.xyz:443/sendTransaction",
-    "http://newyork.solana.blockrazor.xyz:443/sendTransaction",
-    "http://tokyo.solana.blockrazor.xyz:443/sendTransaction",
-    "http://frankfurt.solana.blockrazor.xyz:443/sendTransaction",
-    "http://newyork.solana.blockrazor.xyz:443/sendTransaction",
-    "http://frankfurt.solana.blockrazor.xyz:443/sendTransaction",
-];
-
-pub const SWQOS_ENDPOINTS_ASTRALANE: [&str; 8] = [
-    "http://ny.gateway.astralane.io/iris",
-    "http://fr.gateway.astralane.io/iris",
-    "http://ams.gateway.astralane.io/iris",
-    "http://ny.gateway.astralane.io/iris",
-    "http://jp.gateway.astralane.io/iris",
-    "http://ny.gateway.astralane.io/iris",
-    "http://lax.gateway.astralane.io/iris",
-    "http://lim.gateway.astralane.io/iris",
-];
-
+/// Whether `account` receives tips for one of the built-in swqos providers
+pub fn is_tip_account(account: &Pubkey) -> bool {
+    [
+        JITO_TIP_ACCOUNTS,
+        NEXTBLOCK_TIP_ACCOUNTS,
+        ZEROSLOT_TIP_ACCOUNTS,
+        NOZOMI_TIP_ACCOUNTS,
+        BLOX_TIP_ACCOUNTS,
+        NODE1_TIP_ACCOUNTS,
+        FLASHBLOCK_TIP_ACCOUNTS,
+        BLOCKRAZOR_TIP_ACCOUNTS,
+        ASTRALANE_TIP_ACCOUNTS,
+    ]
+    .iter()
+    .any(|accounts| accounts.contains(account))
+}
+
+/// Endpoint of a provider in a region, `None` where the provider has no endpoint there
+///
+/// Every provider matches every region explicitly, so adding a region fails to compile until
+/// each provider maps it. `SwqosRegion::Auto` is resolved to a concrete region before lookup.
+pub fn swqos_endpoint(swqos_type: &SwqosType, region: &SwqosRegion) -> Option<&'static str> {
+    use SwqosRegion::*;
+    let endpoint = match swqos_type {
+        SwqosType::Jito => match region {
+            NewYork => "https://ny.mainnet.block-engine.jito.wtf",
+            Frankfurt => "https://frankfurt.mainnet.block-engine.jito.wtf",
+            Amsterdam => "https://amsterdam.mainnet.block-engine.jito.wtf",
+            SLC => "https://slc.mainnet.block-engine.jito.wtf",
+            Tokyo => "https://tokyo.mainnet.block-engine.jito.wtf",
+            London => "https://london.mainnet.block-engine.jito.wtf",
+            LosAngeles | Auto => return None,
+            Default => "https://mainnet.block-engine.jito.wtf",
+        },
+        SwqosType::NextBlock => match region {
+            NewYork => "http://ny.nextblock.io",
+            Frankfurt => "http://frankfurt.nextblock.io",
+            Amsterdam => "http://amsterdam.nextblock.io",
+            SLC => "http://slc.nextblock.io",
+            Tokyo => "http://tokyo.nextblock.io",
+            London => "http://london.nextblock.io",
+            LosAngeles => "http://singapore.nextblock.io",
+            Default => "http://frankfurt.nextblock.io",
+            Auto => return None,
+        },
+        SwqosType::ZeroSlot => match region {
+            NewYork => "http://ny.0slot.trade",
+            Frankfurt => "http://de.0slot.trade",
+            Amsterdam => "http://ams.0slot.trade",
+            Tokyo => "http://jp.0slot.trade",
+            LosAngeles => "http://la.0slot.trade",
+            Default => "http://de.0slot.trade",
+            SLC | London | Auto => return None,
+        },
+        SwqosType::Temporal => match region {
+            NewYork => "http://ewr1.nozomi.temporal.xyz",
+            Frankfurt => "http://fra2.nozomi.temporal.xyz",
+            Amsterdam => "http://ams1.nozomi.temporal.xyz",
+            Tokyo => "http://tyo1.nozomi.temporal.xyz",
+            London => "http://sgp1.nozomi.temporal.xyz",
+            LosAngeles => "http://pit1.nozomi.temporal.xyz",
+            Default => "http://fra2.nozomi.temporal.xyz",
+            SLC | Auto => return None,
+        },
+        SwqosType::Bloxroute => match region {
+            NewYork => "https://ny.solana.dex.blxrbdn.com",
+            Frankfurt => "https://germany.solana.dex.blxrbdn.com",
+            Amsterdam => "https://amsterdam.solana.dex.blxrbdn.com",
+            Tokyo => "https://tokyo.solana.dex.blxrbdn.com",
+            London => "https://uk.solana.dex.blxrbdn.com",
+            LosAngeles => "https://la.solana.dex.blxrbdn.com",
+            Default => "https://germany.solana.dex.blxrbdn.com",
+            SLC | Auto => return None,
+        },
+        SwqosType::Node1 => match region {
+            NewYork => "http://ny.node1.me",
+            Frankfurt => "http://fra.node1.me",
+            Amsterdam => "http://ams.node1.me",
+            Default => "http://fra.node1.me",
+            SLC | Tokyo | London | LosAngeles | Auto => return None,
+        },
+        SwqosType::FlashBlock => match region {
+            NewYork => "http://ny.flashblock.trade",
+            Frankfurt => "http://fra.flashblock.trade",
+            Amsterdam => "http://ams.flashblock.trade",
+            SLC => "http://slc.flashblock.trade",
+            Tokyo => "http://singapore.flashblock.trade",
+            London => "http://london.flashblock.trade",
+            Default => "http://ny.flashblock.trade",
+            LosAngeles | Auto => return None,
+        },
+        SwqosType::BlockRazor => match region {
+            NewYork => "http://newyork.solana.blockrazor.xyz:443/sendTransaction",
+            Frankfurt => "http://frankfurt.solana.blockrazor.xyz:443/sendTransaction",
+            Amsterdam => "http://amsterdam.solana.blockrazor.xyz:443/sendTransaction",
+            Tokyo => "http://tokyo.solana.blockrazor.xyz:443/sendTransaction",
+            Default => "http://frankfurt.solana.blockrazor.xyz:443/sendTransaction",
+            SLC | London | LosAngeles | Auto => return None,
+        },
+        SwqosType::Astralane => match region {
+            NewYork => "http://ny.gateway.astralane.io/iris",
+            Frankfurt => "http://fr.gateway.astralane.io/iris",
+            Amsterdam => "http://ams.gateway.astralane.io/iris",
+            Tokyo => "http://jp.gateway.astralane.io/iris",
+            LosAngeles => "http://lax.gateway.astralane.io/iris",
+            Default => "http://lim.gateway.astralane.io/iris",
+            SLC | London | Auto => return None,
+        },
+        SwqosType::External | SwqosType::Default => return None,
+    };
+    Some(endpoint)
+}
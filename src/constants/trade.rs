@@ -6,4 +6,32 @@ pub mod trade {
     pub const DEFAULT_SELL_TIP_FEE: f64 = 0.0001;
     pub const DEFAULT_RPC_UNIT_LIMIT: u32 = 78000;
     pub const DEFAULT_RPC_UNIT_PRICE: u64 = 500000;
+    pub const DEFAULT_MIN_TIP_LAMPORTS: u64 = 1_000; // 0.000001 SOL
+    pub const DEFAULT_MAX_TIP_LAMPORTS: u64 = 1_000_000_000; // 1 SOL
+    // Legacy f64 tips at or above this value are treated as lamports
+    pub const LEGACY_TIP_LAMPORTS_THRESHOLD: f64 = 1_000.0;
+    // getTransaction "not found" retries right after confirmation
+    pub const ANALYSIS_NOT_FOUND_RETRIES: u32 = 5;
+    pub const ANALYSIS_NOT_FOUND_RETRY_DELAY_MS: u64 = 200;
+    // Background analysis attempts once a landed trade was returned as analysis_pending
+    pub const DEFERRED_ANALYSIS_ATTEMPTS: u32 = 10;
+    pub const DEFERRED_ANALYSIS_RETRY_DELAY_MS: u64 = 1_000;
+    // Lamports kept free per split-buy leg for ATA rent and network fees, on top of the tip
+    pub const SPLIT_LEG_FEE_RESERVE_LAMPORTS: u64 = 3_000_000;
+    // Sell leg swap fee kept out of the SOL a two-hop buy leg spends (Bonk charges 1%)
+    pub const TWO_HOP_FEE_RESERVE_BPS: u64 = 100;
+    // Accounts a transaction may lock, lookup table entries included
+    pub const MAX_TX_ACCOUNT_LOCKS: usize = 64;
+    pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+    // Loaded accounts data size limit of trades, high enough for Raydium CLMM swaps
+    pub const DEFAULT_DATA_SIZE_LIMIT: u32 = 512 * 1024;
+    // Compute units reserved for the memo instruction of a trade, spl-memo logs the memo
+    pub const MEMO_COMPUTE_UNITS: u32 = 10_000;
+    // Longest a compute unit simulation may delay a trade before the static limits are used
+    pub const CU_SIMULATION_TIMEOUT_MS: u64 = 400;
+    // Units of the compute budget, nonce and tip instructions a sizing simulation leaves out
+    pub const CU_SIZING_EXTRA_UNITS: u64 = 1_000;
+    // Fee account observation, low frequency to stay well inside RPC rate limits
+    pub const FEE_OBSERVER_DEFAULT_INTERVAL_SECS: u64 = 600;
+    pub const FEE_OBSERVER_DEFAULT_SAMPLE_SIZE: usize = 20;
 }
\ No newline at end of file
@@ -1,13 +1,13 @@
 use crate::{
     constants::trade::trade::DEFAULT_SLIPPAGE,
-    instruction::utils::bonk::{
-        accounts, get_pool_pda, get_vault_pda, BUY_EXECT_IN_DISCRIMINATOR,
-        SELL_EXECT_IN_DISCRIMINATOR,
-    },
+    instruction::data::{BonkBuyExactInArgs, BonkSellExactInArgs, InstructionData},
+    instruction::utils::bonk::{accounts, get_pool_pda, get_vault_pda},
+    params::BonkParams,
     trading::{
-        common::utils::get_token_balance,
+        common::wsol_manager::{EphemeralWsolAccount, WsolHandling},
         core::{
-            params::{BonkParams, BuyParams, SellParams},
+            error::TradeError,
+            params::{BuyParams, SellParams},
             traits::InstructionBuilder,
         },
     },
@@ -15,11 +15,10 @@ use crate::{
         get_buy_token_amount_from_sol_amount, get_sell_sol_amount_from_token_amount,
     },
 };
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
-    signer::Signer,
 };
 
 /// Instruction builder for Bonk protocol
@@ -32,16 +31,22 @@ impl InstructionBuilder for BonkInstructionBuilder {
         // Parameter validation and basic data preparation
         // ========================================
         if params.sol_amount == 0 {
-            return Err(anyhow!("Amount cannot be zero"));
+            return Err(TradeError::invalid_params("Bonk", "amount is zero").into());
         }
         let protocol_params = params
             .protocol_params
             .as_any()
             .downcast_ref::<BonkParams>()
-            .ok_or_else(|| anyhow!("Invalid protocol params for Bonk"))?;
+            .ok_or_else(|| TradeError::params_mismatch("Bonk"))?;
+
+        params.require_exact_sol_in("Bonk")?;
+
+        let quote_mint = protocol_params.quote_mint();
+        let quote_token_program = protocol_params.quote_token_program();
+        let is_wsol_quote = protocol_params.is_wsol_quote();
 
         let pool_state = if protocol_params.pool_state == Pubkey::default() {
-            get_pool_pda(&params.mint, &crate::constants::WSOL_TOKEN_ACCOUNT).unwrap()
+            get_pool_pda(&params.mint, &quote_mint).unwrap()
         } else {
             protocol_params.pool_state
         };
@@ -49,16 +54,22 @@ impl InstructionBuilder for BonkInstructionBuilder {
         // ========================================
         // Trade calculation and account address preparation
         // ========================================
+        // For stable-quoted pools `sol_amount` is the quote amount in quote token base units
         let amount_in: u64 = params.sol_amount;
         let share_fee_rate: u64 = 0;
-        let minimum_amount_out: u64 = get_buy_token_amount_from_sol_amount(
-            amount_in,
-            protocol_params.virtual_base,
-            protocol_params.virtual_quote,
-            protocol_params.real_base,
-            protocol_params.real_quote,
-            params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE) as u128,
-        );
+        let quote_out = |slippage_basis_points: u64| {
+            get_buy_token_amount_from_sol_amount(
+                amount_in,
+                protocol_params.virtual_base,
+                protocol_params.virtual_quote,
+                protocol_params.real_base,
+                protocol_params.real_quote,
+                slippage_basis_points as u128,
+            )
+        };
+        let minimum_amount_out: u64 = params
+            .min_out_floor(quote_out(0))?
+            .unwrap_or_else(|| quote_out(params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE)));
 
         let user_base_token_account =
             crate::common::fast_fn::get_associated_token_address_with_program_id_fast_use_seed(
@@ -70,10 +81,22 @@ impl InstructionBuilder for BonkInstructionBuilder {
         let user_quote_token_account =
             crate::common::fast_fn::get_associated_token_address_with_program_id_fast_use_seed(
                 &params.payer.pubkey(),
-                &crate::constants::WSOL_TOKEN_ACCOUNT,
-                &crate::constants::TOKEN_PROGRAM,
+                &quote_mint,
+                &quote_token_program,
                 params.open_seed_optimize,
             );
+        let wsol = WsolHandling::new(
+            params.create_wsol_ata,
+            params.close_wsol_ata,
+            protocol_params.auto_handle_wsol && is_wsol_quote,
+        );
+        let ephemeral_wsol = EphemeralWsolAccount::for_trade(
+            &params.payer.pubkey(),
+            params.wsol_account,
+            wsol.allows_ephemeral(),
+        );
+        let user_quote_token_account =
+            ephemeral_wsol.map_or(user_quote_token_account, |account| account.address);
 
         let base_vault_account = if protocol_params.base_vault == Pubkey::default() {
             get_vault_pda(&pool_state, &params.mint).unwrap()
@@ -81,7 +104,7 @@ impl InstructionBuilder for BonkInstructionBuilder {
             protocol_params.base_vault
         };
         let quote_vault_account = if protocol_params.quote_vault == Pubkey::default() {
-            get_vault_pda(&pool_state, &crate::constants::WSOL_TOKEN_ACCOUNT).unwrap()
+            get_vault_pda(&pool_state, &quote_mint).unwrap()
         } else {
             protocol_params.quote_vault
         };
@@ -91,8 +114,10 @@ impl InstructionBuilder for BonkInstructionBuilder {
         // ========================================
         let mut instructions = Vec::with_capacity(6);
 
-        // Handle wSOL wrapping if auto_handle_wsol is enabled
-        if protocol_params.auto_handle_wsol {
+        // Wrap the SOL in when create_wsol_ata is set and the pool is quoted in WSOL
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.extend(ephemeral_wsol.open(&params.payer.pubkey(), amount_in)?);
+        } else if wsol.create {
             instructions
                 .extend(crate::trading::common::handle_wsol(&params.payer.pubkey(), amount_in));
         }
@@ -109,11 +134,8 @@ impl InstructionBuilder for BonkInstructionBuilder {
             ),
         );
 
-        let mut data = [0u8; 32];
-        data[..8].copy_from_slice(&BUY_EXECT_IN_DISCRIMINATOR);
-        data[8..16].copy_from_slice(&amount_in.to_le_bytes());
-        data[16..24].copy_from_slice(&minimum_amount_out.to_le_bytes());
-        data[24..32].copy_from_slice(&share_fee_rate.to_le_bytes());
+        let data =
+            BonkBuyExactInArgs { amount_in, minimum_amount_out, share_fee_rate }.encode();
 
         let accounts: [AccountMeta; 18] = [
             AccountMeta::new(params.payer.pubkey(), true), // Payer (signer)
@@ -126,9 +148,9 @@ impl InstructionBuilder for BonkInstructionBuilder {
             AccountMeta::new(base_vault_account, false),                       // Base Vault
             AccountMeta::new(quote_vault_account, false),                      // Quote Vault
             AccountMeta::new_readonly(params.mint, false), // Base Token Mint (readonly)
-            crate::constants::WSOL_TOKEN_ACCOUNT_META,     // Quote Token Mint (readonly)
+            AccountMeta::new_readonly(quote_mint, false),  // Quote Token Mint (readonly)
             AccountMeta::new_readonly(protocol_params.mint_token_program, false), // Base Token Program (readonly)
-            crate::constants::TOKEN_PROGRAM_META, // Quote Token Program (readonly)
+            AccountMeta::new_readonly(quote_token_program, false), // Quote Token Program (readonly)
             accounts::EVENT_AUTHORITY_META,       // Event Authority (readonly)
             accounts::BONK_META,                  // Program (readonly)
             crate::constants::SYSTEM_PROGRAM_META, // System Program (readonly)
@@ -138,8 +160,10 @@ impl InstructionBuilder for BonkInstructionBuilder {
 
         instructions.push(Instruction::new_with_bytes(accounts::BONK, &data, accounts.to_vec()));
 
-        // Close wSOL ATA if auto_handle_wsol is enabled
-        if protocol_params.auto_handle_wsol {
+        // Close wSOL ATA if close_wsol_ata is set
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.push(ephemeral_wsol.close(&params.payer.pubkey()));
+        } else if wsol.close {
             instructions.extend(crate::trading::common::close_wsol(&params.payer.pubkey()));
         }
 
@@ -150,32 +174,23 @@ impl InstructionBuilder for BonkInstructionBuilder {
         // ========================================
         // Parameter validation and basic data preparation
         // ========================================
-        if params.rpc.is_none() {
-            return Err(anyhow!("RPC is not set"));
-        }
-
         let protocol_params = params
             .protocol_params
             .as_any()
             .downcast_ref::<BonkParams>()
-            .ok_or_else(|| anyhow!("Invalid protocol params for Bonk"))?;
-
-        let rpc = params.rpc.as_ref().unwrap().clone();
-
-        let mut amount = params.token_amount;
-        if params.token_amount.is_none() || params.token_amount.unwrap_or(0) == 0 {
-            let balance_u64 =
-                get_token_balance(rpc.as_ref(), &params.payer.pubkey(), &params.mint).await?;
-            amount = Some(balance_u64);
-        }
-        let amount = amount.unwrap_or(0);
+            .ok_or_else(|| TradeError::params_mismatch("Bonk"))?;
 
+        let amount = params.token_amount.unwrap_or(0);
         if amount == 0 {
-            return Err(anyhow!("Amount cannot be zero"));
+            return Err(TradeError::invalid_params("Bonk", "amount is zero").into());
         }
 
+        let quote_mint = protocol_params.quote_mint();
+        let quote_token_program = protocol_params.quote_token_program();
+        let is_wsol_quote = protocol_params.is_wsol_quote();
+
         let pool_state = if protocol_params.pool_state == Pubkey::default() {
-            get_pool_pda(&params.mint, &crate::constants::WSOL_TOKEN_ACCOUNT).unwrap()
+            get_pool_pda(&params.mint, &quote_mint).unwrap()
         } else {
             protocol_params.pool_state
         };
@@ -184,14 +199,19 @@ impl InstructionBuilder for BonkInstructionBuilder {
         // Trade calculation and account address preparation
         // ========================================
         let share_fee_rate: u64 = 0;
-        let minimum_amount_out: u64 = get_sell_sol_amount_from_token_amount(
-            amount,
-            protocol_params.virtual_base,
-            protocol_params.virtual_quote,
-            protocol_params.real_base,
-            protocol_params.real_quote,
-            params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE) as u128,
-        );
+        let quote_out = |slippage_basis_points: u64| {
+            get_sell_sol_amount_from_token_amount(
+                amount,
+                protocol_params.virtual_base,
+                protocol_params.virtual_quote,
+                protocol_params.real_base,
+                protocol_params.real_quote,
+                slippage_basis_points as u128,
+            )
+        };
+        let minimum_amount_out: u64 = params
+            .min_out_floor(quote_out(0))?
+            .unwrap_or_else(|| quote_out(params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE)));
 
         let user_base_token_account =
             crate::common::fast_fn::get_associated_token_address_with_program_id_fast_use_seed(
@@ -203,10 +223,22 @@ impl InstructionBuilder for BonkInstructionBuilder {
         let user_quote_token_account =
             crate::common::fast_fn::get_associated_token_address_with_program_id_fast_use_seed(
                 &params.payer.pubkey(),
-                &crate::constants::WSOL_TOKEN_ACCOUNT,
-                &crate::constants::TOKEN_PROGRAM,
+                &quote_mint,
+                &quote_token_program,
                 params.open_seed_optimize,
             );
+        let wsol = WsolHandling::new(
+            params.create_wsol_ata,
+            params.close_wsol_ata,
+            protocol_params.auto_handle_wsol && is_wsol_quote,
+        );
+        let ephemeral_wsol = EphemeralWsolAccount::for_trade(
+            &params.payer.pubkey(),
+            params.wsol_account,
+            wsol.allows_ephemeral(),
+        );
+        let user_quote_token_account =
+            ephemeral_wsol.map_or(user_quote_token_account, |account| account.address);
 
         let base_vault_account = if protocol_params.base_vault == Pubkey::default() {
             get_vault_pda(&pool_state, &params.mint).unwrap()
@@ -214,7 +246,7 @@ impl InstructionBuilder for BonkInstructionBuilder {
             protocol_params.base_vault
         };
         let quote_vault_account = if protocol_params.quote_vault == Pubkey::default() {
-            get_vault_pda(&pool_state, &crate::constants::WSOL_TOKEN_ACCOUNT).unwrap()
+            get_vault_pda(&pool_state, &quote_mint).unwrap()
         } else {
             protocol_params.quote_vault
         };
@@ -224,16 +256,29 @@ impl InstructionBuilder for BonkInstructionBuilder {
         // ========================================
         let mut instructions = Vec::with_capacity(3);
 
-        // Handle wSOL ATA creation if auto_handle_wsol is enabled
-        if protocol_params.auto_handle_wsol {
-            instructions.extend(crate::trading::common::create_wsol_ata(&params.payer.pubkey()));
+        // Create the wSOL ATA if create_wsol_ata is set, otherwise make sure the
+        // stable quote token account exists to receive the proceeds
+        if is_wsol_quote {
+            if let Some(ephemeral_wsol) = &ephemeral_wsol {
+                instructions.extend(ephemeral_wsol.open(&params.payer.pubkey(), 0)?);
+            } else if wsol.create {
+                instructions
+                    .extend(crate::trading::common::create_wsol_ata(&params.payer.pubkey()));
+            }
+        } else {
+            instructions.extend(
+                crate::common::fast_fn::create_associated_token_account_idempotent_fast_use_seed(
+                    &params.payer.pubkey(),
+                    &params.payer.pubkey(),
+                    &quote_mint,
+                    &quote_token_program,
+                    params.open_seed_optimize,
+                ),
+            );
         }
 
-        let mut data = [0u8; 32];
-        data[..8].copy_from_slice(&SELL_EXECT_IN_DISCRIMINATOR);
-        data[8..16].copy_from_slice(&amount.to_le_bytes());
-        data[16..24].copy_from_slice(&minimum_amount_out.to_le_bytes());
-        data[24..32].copy_from_slice(&share_fee_rate.to_le_bytes());
+        let data = BonkSellExactInArgs { amount_in: amount, minimum_amount_out, share_fee_rate }
+            .encode();
 
         let accounts: [AccountMeta; 18] = [
             AccountMeta::new(params.payer.pubkey(), true), // Payer (signer)
@@ -246,9 +291,9 @@ impl InstructionBuilder for BonkInstructionBuilder {
             AccountMeta::new(base_vault_account, false),                       // Base Vault
             AccountMeta::new(quote_vault_account, false),                      // Quote Vault
             AccountMeta::new_readonly(params.mint, false), // Base Token Mint (readonly)
-            crate::constants::WSOL_TOKEN_ACCOUNT_META,     // Quote Token Mint (readonly)
+            AccountMeta::new_readonly(quote_mint, false),  // Quote Token Mint (readonly)
             AccountMeta::new_readonly(protocol_params.mint_token_program, false), // Base Token Program (readonly)
-            crate::constants::TOKEN_PROGRAM_META, // Quote Token Program (readonly)
+            AccountMeta::new_readonly(quote_token_program, false), // Quote Token Program (readonly)
             accounts::EVENT_AUTHORITY_META,       // Event Authority (readonly)
             accounts::BONK_META,                  // Program (readonly)
             crate::constants::SYSTEM_PROGRAM_META, // System Program (readonly)
@@ -258,11 +303,280 @@ impl InstructionBuilder for BonkInstructionBuilder {
 
         instructions.push(Instruction::new_with_bytes(accounts::BONK, &data, accounts.to_vec()));
 
-        // Close wSOL ATA if auto_handle_wsol is enabled
-        if protocol_params.auto_handle_wsol {
+        // Close wSOL ATA if close_wsol_ata is set
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.push(ephemeral_wsol.close(&params.payer.pubkey()));
+        } else if wsol.close {
             instructions.extend(crate::trading::common::close_wsol(&params.payer.pubkey()));
         }
 
         Ok(instructions)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::common::wsol_manager::{wsol_steps, WSOL_FLAG_CASES};
+    use solana_sdk::pubkey;
+    use spl_associated_token_account::get_associated_token_address;
+
+    const PROGRAM: Pubkey = pubkey!("LanMV9sAd7wArD4vJFi2qDdfnVhFxYSUg6eADduJ3uj");
+    const AUTHORITY: Pubkey = pubkey!("WLHv2UAZm6z4KyaaELi5pjdbJh6RESMva1Rnn8pJVVh");
+    const GLOBAL_CONFIG: Pubkey = pubkey!("6s1xP3hpbAfFoNtUNF8mfHsjr2Bd97JxFJRWLbL6aHuX");
+    const EVENT_AUTHORITY: Pubkey = pubkey!("2DPAtwB8L12vrMRExbLuyGnC7n2J5LNoZQSejeQGpwkr");
+    const SYSTEM_PROGRAM: Pubkey = pubkey!("11111111111111111111111111111111");
+    const TOKEN_PROGRAM: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+    const WSOL: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+    const USD1: Pubkey = pubkey!("USD1ttGY1N17NEEHLmELoaybftRBUSErhqYiQzvEmuB");
+    const MINT: Pubkey = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+
+    /// Pool of `MINT` quoted in `quote_mint`, with the PDAs the program derives for it from the
+    /// seeds "pool" and "pool_vault", computed outside this crate
+    struct GoldenPool {
+        quote_mint: Pubkey,
+        pool_state: Pubkey,
+        base_vault: Pubkey,
+        quote_vault: Pubkey,
+        params: BonkParams,
+    }
+
+    fn golden_wsol_pool() -> GoldenPool {
+        GoldenPool {
+            quote_mint: WSOL,
+            pool_state: pubkey!("7Z9Mi87ZiWwjC6UgCp19o4NEPzY9bSLNUd6WHKwR9PM4"),
+            base_vault: pubkey!("2EwUNFiPp6a812QF7y9L4zwunq4Kd4jrhLg5cYy7sWu4"),
+            quote_vault: pubkey!("CVvGPGeRQhrZ3xN33LNJvcZX1eddcwRrz7YDnXBV8aUv"),
+            params: BonkParams {
+                real_base: 206_900_000_000_000,
+                real_quote: 12_345_678_901,
+                ..pool_params()
+            },
+        }
+    }
+
+    fn golden_usd1_pool() -> GoldenPool {
+        GoldenPool {
+            quote_mint: USD1,
+            pool_state: pubkey!("7jgjXSvPzyqrvQiboZ5A99dgac6U2p7UAbUFAnafx54k"),
+            base_vault: pubkey!("8EG1ahcnEGUPSMpPjDWKX9CTfQH4hF2c7VM8rEkNu4Jk"),
+            quote_vault: pubkey!("56BDLp4k3TB2Qv236SHCEAyerL61rtU5sCRXmPtFTWSg"),
+            params: BonkParams {
+                virtual_quote: 4_285_714_285,
+                real_base: 51_234_567_890_123,
+                real_quote: 1_500_000_000,
+                quote_mint: USD1,
+                quote_token_program: TOKEN_PROGRAM,
+                ..pool_params()
+            },
+        }
+    }
+
+    /// Accounts of a Bonk swap, in the order of the program's `buy_exact_in` and
+    /// `sell_exact_in`
+    fn swap_accounts(pool: &GoldenPool, payer: &Pubkey) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(AUTHORITY, false),
+            AccountMeta::new_readonly(GLOBAL_CONFIG, false),
+            AccountMeta::new_readonly(pool.params.platform_config, false),
+            AccountMeta::new(pool.pool_state, false),
+            AccountMeta::new(get_associated_token_address(payer, &MINT), false),
+            AccountMeta::new(get_associated_token_address(payer, &pool.quote_mint), false),
+            AccountMeta::new(pool.base_vault, false),
+            AccountMeta::new(pool.quote_vault, false),
+            AccountMeta::new_readonly(MINT, false),
+            AccountMeta::new_readonly(pool.quote_mint, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM, false),
+            AccountMeta::new_readonly(EVENT_AUTHORITY, false),
+            AccountMeta::new_readonly(PROGRAM, false),
+            AccountMeta::new_readonly(SYSTEM_PROGRAM, false),
+            AccountMeta::new(pool.params.fee_destination_1, false),
+            AccountMeta::new(pool.params.fee_destination_2, false),
+        ]
+    }
+
+    fn swap(instructions: &[Instruction]) -> &Instruction {
+        instructions.iter().find(|ix| ix.program_id == PROGRAM).unwrap()
+    }
+
+    /// Pool quoted in wSOL
+    fn pool_params() -> BonkParams {
+        BonkParams {
+            virtual_base: 1_073_025_605_596_382,
+            virtual_quote: 30_000_852_951,
+            mint_token_program: crate::constants::TOKEN_PROGRAM,
+            platform_config: Pubkey::new_unique(),
+            fee_destination_1: Pubkey::new_unique(),
+            fee_destination_2: Pubkey::new_unique(),
+            auto_handle_wsol: true,
+            ..Default::default()
+        }
+    }
+
+    fn sell_params(token_amount: Option<u64>) -> SellParams {
+        SellParams::offline(Pubkey::new_unique(), token_amount, Box::new(pool_params()))
+    }
+
+    #[tokio::test]
+    async fn sell_builds_without_rpc() {
+        let params = sell_params(Some(1_000_000));
+        assert!(params.rpc.is_none());
+        let instructions = BonkInstructionBuilder.build_sell_instructions(&params).await.unwrap();
+        let sell =
+            instructions.iter().find_map(|ix| BonkSellExactInArgs::decode(&ix.data)).unwrap();
+        assert_eq!(sell.amount_in, 1_000_000);
+    }
+
+    #[tokio::test]
+    async fn sell_needs_a_resolved_amount() {
+        let params = sell_params(None);
+        assert!(BonkInstructionBuilder.build_sell_instructions(&params).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn wsol_flags_shape_the_wsol_steps() {
+        for &((create, close, auto_handle_wsol), buy_steps, sell_steps) in WSOL_FLAG_CASES {
+            let flags = (create, close, auto_handle_wsol);
+            let mint = Pubkey::new_unique();
+            let protocol_params = BonkParams { auto_handle_wsol, ..pool_params() };
+            let mut buy = BuyParams::offline(mint, 1_000_000, Box::new(protocol_params.clone()));
+            (buy.create_wsol_ata, buy.close_wsol_ata) = (create, close);
+            let mut sell = SellParams::offline(mint, Some(1_000_000), Box::new(protocol_params));
+            (sell.create_wsol_ata, sell.close_wsol_ata) = (create, close);
+
+            let instructions = BonkInstructionBuilder.build_buy_instructions(&buy).await.unwrap();
+            assert_eq!(
+                wsol_steps(&instructions, &buy.payer.pubkey()),
+                buy_steps,
+                "buy {:?}",
+                flags
+            );
+            let instructions = BonkInstructionBuilder.build_sell_instructions(&sell).await.unwrap();
+            assert_eq!(
+                wsol_steps(&instructions, &sell.payer.pubkey()),
+                sell_steps,
+                "sell {:?}",
+                flags
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn min_out_floors_are_encoded_exactly() {
+        const FLOOR: u64 = 1_000;
+        let mint = Pubkey::new_unique();
+        let swap_data = |instructions: Vec<Instruction>| {
+            instructions.into_iter().find(|ix| ix.program_id == accounts::BONK).unwrap().data
+        };
+
+        let mut buy = BuyParams::offline(mint, 1_000_000, Box::new(pool_params()));
+        buy.min_amount_out = Some(FLOOR);
+        let data = swap_data(BonkInstructionBuilder.build_buy_instructions(&buy).await.unwrap());
+        assert!(data.starts_with(
+            &BonkBuyExactInArgs {
+                amount_in: 1_000_000,
+                minimum_amount_out: FLOOR,
+                share_fee_rate: 0
+            }
+            .encode()
+        ));
+        buy.min_amount_out = Some(u64::MAX);
+        assert!(BonkInstructionBuilder.build_buy_instructions(&buy).await.is_err());
+
+        let mut sell = SellParams::offline(mint, Some(1_000_000_000), Box::new(pool_params()));
+        sell.min_sol_out = Some(FLOOR);
+        let data = swap_data(BonkInstructionBuilder.build_sell_instructions(&sell).await.unwrap());
+        assert!(data.starts_with(
+            &BonkSellExactInArgs {
+                amount_in: 1_000_000_000,
+                minimum_amount_out: FLOOR,
+                share_fee_rate: 0
+            }
+            .encode()
+        ));
+        sell.min_sol_out = Some(u64::MAX);
+        assert!(BonkInstructionBuilder.build_sell_instructions(&sell).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn wsol_pool_buy_and_sell_match_the_golden_instructions() {
+        let pool = golden_wsol_pool();
+
+        // 1_000_000 lamports in, 1.25% fees, 1% slippage
+        let buy = BuyParams::offline(MINT, 1_000_000, Box::new(pool.params.clone()));
+        let payer = buy.payer.pubkey();
+        let instructions = BonkInstructionBuilder.build_buy_instructions(&buy).await.unwrap();
+        assert_eq!(wsol_steps(&instructions, &payer), ["create", "transfer", "sync", "close"]);
+        assert_eq!(swap(&instructions).accounts, swap_accounts(&pool, &payer));
+        assert_eq!(
+            swap(&instructions).data,
+            BonkBuyExactInArgs {
+                amount_in: 1_000_000,
+                minimum_amount_out: 19_995_174_643,
+                share_fee_rate: 0
+            }
+            .encode()
+        );
+
+        let sell = SellParams::offline(MINT, Some(1_000_000_000), Box::new(pool.params.clone()));
+        let payer = sell.payer.pubkey();
+        let instructions = BonkInstructionBuilder.build_sell_instructions(&sell).await.unwrap();
+        assert_eq!(wsol_steps(&instructions, &payer), ["create", "close"]);
+        assert_eq!(swap(&instructions).accounts, swap_accounts(&pool, &payer));
+        assert_eq!(
+            swap(&instructions).data,
+            BonkSellExactInArgs {
+                amount_in: 1_000_000_000,
+                minimum_amount_out: 47_799,
+                share_fee_rate: 0
+            }
+            .encode()
+        );
+    }
+
+    #[tokio::test]
+    async fn usd1_pool_buy_and_sell_match_the_golden_instructions() {
+        let pool = golden_usd1_pool();
+
+        // 1 USD1 in, in its 6-decimal base units, and no SOL wrapped whatever the flags say
+        let buy = BuyParams::offline(MINT, 1_000_000, Box::new(pool.params.clone()));
+        let payer = buy.payer.pubkey();
+        let instructions = BonkInstructionBuilder.build_buy_instructions(&buy).await.unwrap();
+        assert!(wsol_steps(&instructions, &payer).is_empty());
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].program_id, spl_associated_token_account::ID);
+        assert_eq!(instructions[0].accounts[3].pubkey, MINT);
+        assert_eq!(swap(&instructions).accounts, swap_accounts(&pool, &payer));
+        assert_eq!(
+            swap(&instructions).data,
+            BonkBuyExactInArgs {
+                amount_in: 1_000_000,
+                minimum_amount_out: 172_624_838_872,
+                share_fee_rate: 0
+            }
+            .encode()
+        );
+
+        // The proceeds land in the payer's USD1 account, created if missing
+        let sell = SellParams::offline(MINT, Some(1_000_000_000), Box::new(pool.params.clone()));
+        let payer = sell.payer.pubkey();
+        let instructions = BonkInstructionBuilder.build_sell_instructions(&sell).await.unwrap();
+        assert!(wsol_steps(&instructions, &payer).is_empty());
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].program_id, spl_associated_token_account::ID);
+        assert_eq!(instructions[0].accounts[1].pubkey, get_associated_token_address(&payer, &USD1));
+        assert_eq!(instructions[0].accounts[3].pubkey, USD1);
+        assert_eq!(swap(&instructions).accounts, swap_accounts(&pool, &payer));
+        assert_eq!(
+            swap(&instructions).data,
+            BonkSellExactInArgs {
+                amount_in: 1_000_000_000,
+                minimum_amount_out: 5_537,
+                share_fee_rate: 0
+            }
+            .encode()
+        );
+    }
+}
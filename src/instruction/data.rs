@@ -0,0 +1,540 @@
+//! Typed instruction data encoders
+//!
+//! Every swap instruction's data is a discriminator followed by borsh-encoded arguments. The
+//! argument structs below own that layout so builders never slice byte offsets by hand.
+
+use crate::instruction::utils::{
+    bonk, jupiter, meteora_dlmm, pumpfun, pumpswap, raydium_amm_v4, raydium_cpmm,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::pubkey::Pubkey;
+
+pub const RAYDIUM_CLMM_PROGRAM: Pubkey =
+    solana_sdk::pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
+pub const RAYDIUM_CLMM_SWAP_DISCRIMINATOR: &[u8] = &[248, 198, 158, 145, 225, 117, 135, 200];
+pub const RAYDIUM_CLMM_SWAP_V2_DISCRIMINATOR: &[u8] = &[43, 4, 237, 11, 26, 201, 30, 98];
+
+/// Instruction arguments with a fixed discriminator
+pub trait InstructionData: BorshSerialize + BorshDeserialize {
+    const DISCRIMINATOR: &'static [u8];
+
+    /// Discriminator followed by the borsh-encoded arguments
+    fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(Self::DISCRIMINATOR.len() + 32);
+        data.extend_from_slice(Self::DISCRIMINATOR);
+        self.serialize(&mut data).expect("writing to a Vec cannot fail");
+        data
+    }
+
+    /// Decode instruction data, `None` when the discriminator does not match
+    ///
+    /// Trailing bytes after the known arguments are ignored.
+    fn decode(data: &[u8]) -> Option<Self> {
+        let mut args = data.strip_prefix(Self::DISCRIMINATOR)?;
+        Self::deserialize(&mut args).ok()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct PumpFunBuyArgs {
+    pub amount: u64,
+    pub max_sol_cost: u64,
+}
+
+impl InstructionData for PumpFunBuyArgs {
+    const DISCRIMINATOR: &'static [u8] = &pumpfun::BUY_DISCRIMINATOR;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct PumpFunSellArgs {
+    pub amount: u64,
+    pub min_sol_output: u64,
+}
+
+impl InstructionData for PumpFunSellArgs {
+    const DISCRIMINATOR: &'static [u8] = &pumpfun::SELL_DISCRIMINATOR;
+}
+
+/// Arguments of the PumpFun `create` instruction
+#[derive(Debug, Clone, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct PumpFunCreateArgs {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub creator: Pubkey,
+}
+
+impl InstructionData for PumpFunCreateArgs {
+    const DISCRIMINATOR: &'static [u8] = &pumpfun::CREATE_DISCRIMINATOR;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct PumpSwapBuyArgs {
+    pub base_amount_out: u64,
+    pub max_quote_amount_in: u64,
+}
+
+impl InstructionData for PumpSwapBuyArgs {
+    const DISCRIMINATOR: &'static [u8] = &pumpswap::BUY_DISCRIMINATOR;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct PumpSwapSellArgs {
+    pub base_amount_in: u64,
+    pub min_quote_amount_out: u64,
+}
+
+impl InstructionData for PumpSwapSellArgs {
+    const DISCRIMINATOR: &'static [u8] = &pumpswap::SELL_DISCRIMINATOR;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct BonkBuyExactInArgs {
+    pub amount_in: u64,
+    pub minimum_amount_out: u64,
+    pub share_fee_rate: u64,
+}
+
+impl InstructionData for BonkBuyExactInArgs {
+    const DISCRIMINATOR: &'static [u8] = &bonk::BUY_EXECT_IN_DISCRIMINATOR;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct BonkSellExactInArgs {
+    pub amount_in: u64,
+    pub minimum_amount_out: u64,
+    pub share_fee_rate: u64,
+}
+
+impl InstructionData for BonkSellExactInArgs {
+    const DISCRIMINATOR: &'static [u8] = &bonk::SELL_EXECT_IN_DISCRIMINATOR;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct RaydiumCpmmSwapBaseInArgs {
+    pub amount_in: u64,
+    pub minimum_amount_out: u64,
+}
+
+impl InstructionData for RaydiumCpmmSwapBaseInArgs {
+    const DISCRIMINATOR: &'static [u8] = raydium_cpmm::SWAP_BASE_IN_DISCRIMINATOR;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct RaydiumCpmmSwapBaseOutArgs {
+    pub max_amount_in: u64,
+    pub amount_out: u64,
+}
+
+impl InstructionData for RaydiumCpmmSwapBaseOutArgs {
+    const DISCRIMINATOR: &'static [u8] = raydium_cpmm::SWAP_BASE_OUT_DISCRIMINATOR;
+}
+
+/// Raydium AMM v4 uses a 1-byte instruction tag instead of an Anchor discriminator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct RaydiumAmmV4SwapBaseInArgs {
+    pub amount_in: u64,
+    pub minimum_amount_out: u64,
+}
+
+impl InstructionData for RaydiumAmmV4SwapBaseInArgs {
+    const DISCRIMINATOR: &'static [u8] = raydium_amm_v4::SWAP_BASE_IN_DISCRIMINATOR;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct RaydiumClmmSwapArgs {
+    pub amount: u64,
+    pub other_amount_threshold: u64,
+    pub sqrt_price_limit_x64: u128,
+    pub is_base_input: bool,
+}
+
+impl InstructionData for RaydiumClmmSwapArgs {
+    const DISCRIMINATOR: &'static [u8] = RAYDIUM_CLMM_SWAP_DISCRIMINATOR;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct RaydiumClmmSwapV2Args {
+    pub amount: u64,
+    pub other_amount_threshold: u64,
+    pub sqrt_price_limit_x64: u128,
+    pub is_base_input: bool,
+}
+
+impl InstructionData for RaydiumClmmSwapV2Args {
+    const DISCRIMINATOR: &'static [u8] = RAYDIUM_CLMM_SWAP_V2_DISCRIMINATOR;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, BorshSerialize, BorshDeserialize)]
+pub struct MeteoraDlmmSwapArgs {
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+}
+
+impl InstructionData for MeteoraDlmmSwapArgs {
+    const DISCRIMINATOR: &'static [u8] = meteora_dlmm::SWAP_DISCRIMINATOR;
+}
+
+/// Jupiter v6 `shared_accounts_route`
+///
+/// `route_plan` is the borsh-encoded `Vec<RoutePlanStep>` of a quote, kept as bytes since its
+/// swap variants are defined by Jupiter and passed through unchanged. The arguments after it
+/// have a fixed size, so `decode` reads them from the end of the data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JupiterSharedAccountsRouteArgs {
+    pub id: u8,
+    pub route_plan: Vec<u8>,
+    pub in_amount: u64,
+    pub quoted_out_amount: u64,
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+impl JupiterSharedAccountsRouteArgs {
+    const TAIL_LEN: usize = 8 + 8 + 2 + 1;
+
+    /// Discriminator followed by the encoded arguments
+    pub fn encode(&self) -> Vec<u8> {
+        let discriminator = jupiter::SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR;
+        let mut data =
+            Vec::with_capacity(discriminator.len() + 1 + self.route_plan.len() + Self::TAIL_LEN);
+        data.extend_from_slice(discriminator);
+        data.push(self.id);
+        data.extend_from_slice(&self.route_plan);
+        data.extend_from_slice(&self.in_amount.to_le_bytes());
+        data.extend_from_slice(&self.quoted_out_amount.to_le_bytes());
+        data.extend_from_slice(&self.slippage_bps.to_le_bytes());
+        data.push(self.platform_fee_bps);
+        data
+    }
+
+    /// Decode instruction data, `None` when the discriminator does not match or the data is
+    /// too short
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let args = data.strip_prefix(jupiter::SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR)?;
+        let (&id, rest) = args.split_first()?;
+        let route_plan_len = rest.len().checked_sub(Self::TAIL_LEN)?;
+        let (route_plan, tail) = rest.split_at(route_plan_len);
+        Some(Self {
+            id,
+            route_plan: route_plan.to_vec(),
+            in_amount: u64::from_le_bytes(tail[0..8].try_into().ok()?),
+            quoted_out_amount: u64::from_le_bytes(tail[8..16].try_into().ok()?),
+            slippage_bps: u16::from_le_bytes(tail[16..18].try_into().ok()?),
+            platform_fee_bps: tail[18],
+        })
+    }
+}
+
+/// A decoded swap instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodedInstruction {
+    PumpFunBuy(PumpFunBuyArgs),
+    PumpFunSell(PumpFunSellArgs),
+    PumpSwapBuy(PumpSwapBuyArgs),
+    PumpSwapSell(PumpSwapSellArgs),
+    BonkBuyExactIn(BonkBuyExactInArgs),
+    BonkSellExactIn(BonkSellExactInArgs),
+    RaydiumCpmmSwapBaseIn(RaydiumCpmmSwapBaseInArgs),
+    RaydiumCpmmSwapBaseOut(RaydiumCpmmSwapBaseOutArgs),
+    RaydiumAmmV4SwapBaseIn(RaydiumAmmV4SwapBaseInArgs),
+    RaydiumClmmSwap(RaydiumClmmSwapArgs),
+    RaydiumClmmSwapV2(RaydiumClmmSwapV2Args),
+    MeteoraDlmmSwap(MeteoraDlmmSwapArgs),
+    /// The fixed-size arguments of a Jupiter route, the route plan is left out
+    JupiterSharedAccountsRoute {
+        in_amount: u64,
+        quoted_out_amount: u64,
+        slippage_bps: u16,
+    },
+}
+
+/// Decode the data of a swap instruction sent to `program_id`
+///
+/// Returns `None` for programs and instructions this SDK does not build.
+pub fn decode_instruction(program_id: &Pubkey, data: &[u8]) -> Option<DecodedInstruction> {
+    use DecodedInstruction::*;
+
+    if *program_id == pumpfun::accounts::PUMPFUN {
+        PumpFunBuyArgs::decode(data)
+            .map(PumpFunBuy)
+            .or_else(|| PumpFunSellArgs::decode(data).map(PumpFunSell))
+    } else if *program_id == pumpswap::accounts::AMM_PROGRAM {
+        PumpSwapBuyArgs::decode(data)
+            .map(PumpSwapBuy)
+            .or_else(|| PumpSwapSellArgs::decode(data).map(PumpSwapSell))
+    } else if *program_id == bonk::accounts::BONK {
+        BonkBuyExactInArgs::decode(data)
+            .map(BonkBuyExactIn)
+            .or_else(|| BonkSellExactInArgs::decode(data).map(BonkSellExactIn))
+    } else if *program_id == raydium_cpmm::accounts::RAYDIUM_CPMM {
+        RaydiumCpmmSwapBaseInArgs::decode(data)
+            .map(RaydiumCpmmSwapBaseIn)
+            .or_else(|| RaydiumCpmmSwapBaseOutArgs::decode(data).map(RaydiumCpmmSwapBaseOut))
+    } else if *program_id == raydium_amm_v4::accounts::RAYDIUM_AMM_V4 {
+        RaydiumAmmV4SwapBaseInArgs::decode(data).map(RaydiumAmmV4SwapBaseIn)
+    } else if *program_id == RAYDIUM_CLMM_PROGRAM {
+        RaydiumClmmSwapArgs::decode(data)
+            .map(RaydiumClmmSwap)
+            .or_else(|| RaydiumClmmSwapV2Args::decode(data).map(RaydiumClmmSwapV2))
+    } else if *program_id == meteora_dlmm::accounts::METEORA_DLMM {
+        MeteoraDlmmSwapArgs::decode(data).map(MeteoraDlmmSwap)
+    } else if *program_id == jupiter::accounts::JUPITER {
+        JupiterSharedAccountsRouteArgs::decode(data).map(|args| JupiterSharedAccountsRoute {
+            in_amount: args.in_amount,
+            quoted_out_amount: args.quoted_out_amount,
+            slippage_bps: args.slippage_bps,
+        })
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Debug;
+
+    // Anchor discriminators, the first 8 bytes of sha256("global:<instruction name>")
+    const BUY: &str = "66063d1201daebea";
+    const SELL: &str = "33e685a4017f83ad";
+    const CREATE: &str = "181ec828051c0777";
+    const BUY_EXACT_IN: &str = "faea0d7bd59c13ec";
+    const SELL_EXACT_IN: &str = "9527de9bd37c981a";
+    const SWAP_BASE_INPUT: &str = "8fbe5adac41e33de";
+    const SWAP_BASE_OUTPUT: &str = "37d96256a34ab4ad";
+    const SWAP: &str = "f8c69e91e17587c8";
+    const SWAP_V2: &str = "2b04ed0b1ac91e62";
+    const SHARED_ACCOUNTS_ROUTE: &str = "c1209b3341d69c81";
+
+    // Argument values whose little-endian bytes are easy to read
+    const A: u64 = 0x1122_3344_5566_7788;
+    const A_LE: &str = "8877665544332211";
+    const B: u64 = 0x99aa_bbcc_ddee_ff00;
+    const B_LE: &str = "00ffeeddccbbaa99";
+    const C: u64 = 10_000;
+    const C_LE: &str = "1027000000000000";
+    const SQRT_PRICE: u128 = 0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10;
+    const SQRT_PRICE_LE: &str = "100f0e0d0c0b0a090807060504030201";
+
+    fn bytes(parts: &[&str]) -> Vec<u8> {
+        hex::decode(parts.concat()).unwrap()
+    }
+
+    fn assert_round_trip<T: InstructionData + Debug + PartialEq>(args: T, expected: &[&str]) {
+        let expected = bytes(expected);
+        assert_eq!(args.encode(), expected, "{:?}", args);
+        assert_eq!(T::decode(&expected), Some(args));
+    }
+
+    #[test]
+    fn pumpfun_args_encode_to_known_bytes() {
+        assert_round_trip(PumpFunBuyArgs { amount: A, max_sol_cost: B }, &[BUY, A_LE, B_LE]);
+        assert_round_trip(PumpFunSellArgs { amount: A, min_sol_output: B }, &[SELL, A_LE, B_LE]);
+        assert_round_trip(
+            PumpFunCreateArgs {
+                name: "Tok".to_string(),
+                symbol: "T".to_string(),
+                uri: "u".to_string(),
+                creator: Pubkey::new_from_array([7; 32]),
+            },
+            &[
+                CREATE,
+                // Borsh strings are a u32 length followed by the UTF-8 bytes
+                "03000000546f6b",
+                "0100000054",
+                "0100000075",
+                &"07".repeat(32),
+            ],
+        );
+    }
+
+    #[test]
+    fn pumpswap_args_encode_to_known_bytes() {
+        assert_round_trip(
+            PumpSwapBuyArgs { base_amount_out: A, max_quote_amount_in: B },
+            &[BUY, A_LE, B_LE],
+        );
+        assert_round_trip(
+            PumpSwapSellArgs { base_amount_in: A, min_quote_amount_out: B },
+            &[SELL, A_LE, B_LE],
+        );
+    }
+
+    #[test]
+    fn bonk_args_encode_to_known_bytes() {
+        assert_round_trip(
+            BonkBuyExactInArgs { amount_in: A, minimum_amount_out: B, share_fee_rate: C },
+            &[BUY_EXACT_IN, A_LE, B_LE, C_LE],
+        );
+        assert_round_trip(
+            BonkSellExactInArgs { amount_in: A, minimum_amount_out: B, share_fee_rate: C },
+            &[SELL_EXACT_IN, A_LE, B_LE, C_LE],
+        );
+    }
+
+    #[test]
+    fn raydium_args_encode_to_known_bytes() {
+        assert_round_trip(
+            RaydiumCpmmSwapBaseInArgs { amount_in: A, minimum_amount_out: B },
+            &[SWAP_BASE_INPUT, A_LE, B_LE],
+        );
+        assert_round_trip(
+            RaydiumCpmmSwapBaseOutArgs { max_amount_in: A, amount_out: B },
+            &[SWAP_BASE_OUTPUT, A_LE, B_LE],
+        );
+        // 17 bytes: the 1-byte tag 9 and two u64s
+        assert_round_trip(
+            RaydiumAmmV4SwapBaseInArgs { amount_in: A, minimum_amount_out: B },
+            &["09", A_LE, B_LE],
+        );
+        assert_round_trip(
+            RaydiumClmmSwapArgs {
+                amount: A,
+                other_amount_threshold: B,
+                sqrt_price_limit_x64: SQRT_PRICE,
+                is_base_input: true,
+            },
+            &[SWAP, A_LE, B_LE, SQRT_PRICE_LE, "01"],
+        );
+        assert_round_trip(
+            RaydiumClmmSwapV2Args {
+                amount: A,
+                other_amount_threshold: B,
+                sqrt_price_limit_x64: 0,
+                is_base_input: false,
+            },
+            &[SWAP_V2, A_LE, B_LE, &"00".repeat(16), "00"],
+        );
+    }
+
+    #[test]
+    fn meteora_dlmm_args_encode_to_known_bytes() {
+        assert_round_trip(
+            MeteoraDlmmSwapArgs { amount_in: A, min_amount_out: B },
+            &[SWAP, A_LE, B_LE],
+        );
+    }
+
+    #[test]
+    fn jupiter_route_keeps_the_route_plan_between_id_and_amounts() {
+        let args = JupiterSharedAccountsRouteArgs {
+            id: 3,
+            route_plan: vec![1, 0, 0, 0, 7, 100, 0, 1],
+            in_amount: A,
+            quoted_out_amount: B,
+            slippage_bps: 0x0132,
+            platform_fee_bps: 5,
+        };
+        let expected =
+            bytes(&[SHARED_ACCOUNTS_ROUTE, "03", "0100000007640001", A_LE, B_LE, "3201", "05"]);
+        assert_eq!(args.encode(), expected);
+        assert_eq!(JupiterSharedAccountsRouteArgs::decode(&expected), Some(args.clone()));
+
+        let empty_plan = JupiterSharedAccountsRouteArgs { route_plan: Vec::new(), ..args };
+        assert_eq!(empty_plan.encode().len(), 8 + 1 + 8 + 8 + 2 + 1);
+        assert_eq!(JupiterSharedAccountsRouteArgs::decode(&empty_plan.encode()), Some(empty_plan));
+        assert_eq!(JupiterSharedAccountsRouteArgs::decode(&expected[..8 + 1 + 18]), None);
+    }
+
+    #[test]
+    fn decode_checks_the_discriminator_and_length() {
+        let buy = bytes(&[BUY, A_LE, B_LE]);
+        assert_eq!(PumpFunSellArgs::decode(&buy), None);
+        assert_eq!(PumpFunBuyArgs::decode(&buy[..buy.len() - 1]), None);
+        assert_eq!(PumpFunBuyArgs::decode(&buy[..7]), None);
+
+        let mut trailing = buy;
+        trailing.extend_from_slice(&[0xff; 4]);
+        assert_eq!(
+            PumpFunBuyArgs::decode(&trailing),
+            Some(PumpFunBuyArgs { amount: A, max_sol_cost: B })
+        );
+
+        // A bool must be 0 or 1
+        let bad_bool = bytes(&[SWAP, A_LE, B_LE, SQRT_PRICE_LE, "02"]);
+        assert_eq!(RaydiumClmmSwapArgs::decode(&bad_bool), None);
+    }
+
+    #[test]
+    fn decode_instruction_picks_the_layout_by_program() {
+        use DecodedInstruction::*;
+
+        // PumpFun and PumpSwap share their discriminators
+        let buy = bytes(&[BUY, A_LE, B_LE]);
+        assert_eq!(
+            decode_instruction(&pumpfun::accounts::PUMPFUN, &buy),
+            Some(PumpFunBuy(PumpFunBuyArgs { amount: A, max_sol_cost: B }))
+        );
+        assert_eq!(
+            decode_instruction(&pumpswap::accounts::AMM_PROGRAM, &buy),
+            Some(PumpSwapBuy(PumpSwapBuyArgs { base_amount_out: A, max_quote_amount_in: B }))
+        );
+
+        // So do Meteora DLMM and Raydium CLMM, whose swap arguments are longer
+        let swap = bytes(&[SWAP, A_LE, B_LE]);
+        assert_eq!(
+            decode_instruction(&meteora_dlmm::accounts::METEORA_DLMM, &swap),
+            Some(MeteoraDlmmSwap(MeteoraDlmmSwapArgs { amount_in: A, min_amount_out: B }))
+        );
+        assert_eq!(decode_instruction(&RAYDIUM_CLMM_PROGRAM, &swap), None);
+
+        let sell = bytes(&[SELL_EXACT_IN, A_LE, B_LE, C_LE]);
+        assert_eq!(
+            decode_instruction(&bonk::accounts::BONK, &sell),
+            Some(BonkSellExactIn(BonkSellExactInArgs {
+                amount_in: A,
+                minimum_amount_out: B,
+                share_fee_rate: C,
+            }))
+        );
+        assert_eq!(
+            decode_instruction(
+                &raydium_cpmm::accounts::RAYDIUM_CPMM,
+                &bytes(&[SWAP_BASE_OUTPUT, A_LE, B_LE])
+            ),
+            Some(RaydiumCpmmSwapBaseOut(RaydiumCpmmSwapBaseOutArgs {
+                max_amount_in: A,
+                amount_out: B
+            }))
+        );
+        assert_eq!(
+            decode_instruction(
+                &raydium_amm_v4::accounts::RAYDIUM_AMM_V4,
+                &bytes(&["09", A_LE, B_LE])
+            ),
+            Some(RaydiumAmmV4SwapBaseIn(RaydiumAmmV4SwapBaseInArgs {
+                amount_in: A,
+                minimum_amount_out: B
+            }))
+        );
+        // Swap base out is not built by this SDK
+        assert_eq!(
+            decode_instruction(
+                &raydium_amm_v4::accounts::RAYDIUM_AMM_V4,
+                &bytes(&["0b", A_LE, B_LE])
+            ),
+            None
+        );
+        assert_eq!(
+            decode_instruction(
+                &jupiter::accounts::JUPITER,
+                &bytes(&[
+                    SHARED_ACCOUNTS_ROUTE,
+                    "03",
+                    "0100000007640001",
+                    A_LE,
+                    B_LE,
+                    "3201",
+                    "05"
+                ])
+            ),
+            Some(JupiterSharedAccountsRoute {
+                in_amount: A,
+                quoted_out_amount: B,
+                slippage_bps: 0x0132
+            })
+        );
+        assert_eq!(decode_instruction(&Pubkey::new_unique(), &buy), None);
+    }
+}
@@ -0,0 +1,454 @@
+use crate::{
+    common::fast_fn::{
+        get_associated_token_address_with_program_id_fast,
+        get_associated_token_address_with_program_id_fast_use_seed,
+    },
+    constants::trade::trade::DEFAULT_SLIPPAGE,
+    instruction::data::JupiterSharedAccountsRouteArgs,
+    instruction::utils::jupiter::{
+        accounts, get_program_authority_pda, SHARED_ACCOUNTS_ROUTE_FIXED_ACCOUNTS,
+    },
+    params::JupiterParams,
+    trading::{
+        common::wsol_manager::{EphemeralWsolAccount, WsolHandling},
+        core::{
+            error::TradeError,
+            params::{BuyParams, SellParams},
+            traits::InstructionBuilder,
+        },
+    },
+};
+use anyhow::{anyhow, Result};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+/// Instruction builder for Jupiter v6 routes
+pub struct JupiterInstructionBuilder;
+
+/// Source and destination of one `shared_accounts_route` call
+struct RouteSide {
+    mint: Pubkey,
+    token_program: Pubkey,
+    user_token_account: Pubkey,
+}
+
+#[async_trait::async_trait]
+impl InstructionBuilder for JupiterInstructionBuilder {
+    async fn build_buy_instructions(&self, params: &BuyParams) -> Result<Vec<Instruction>> {
+        // ========================================
+        // Parameter validation and basic data preparation
+        // ========================================
+        if params.sol_amount == 0 {
+            return Err(TradeError::invalid_params("Jupiter", "amount is zero").into());
+        }
+        let protocol_params = params
+            .protocol_params
+            .as_any()
+            .downcast_ref::<JupiterParams>()
+            .ok_or_else(|| TradeError::params_mismatch("Jupiter"))?;
+
+        params.require_exact_sol_in("Jupiter")?;
+
+        if protocol_params.input_mint != crate::constants::WSOL_TOKEN_ACCOUNT
+            || protocol_params.output_mint != params.mint
+        {
+            return Err(anyhow!("Jupiter route does not swap wSOL to {}", params.mint));
+        }
+        check_quoted_amount(protocol_params, params.sol_amount)?;
+
+        // ========================================
+        // Account address preparation
+        // ========================================
+        let wsol_token_account = get_associated_token_address_with_program_id_fast_use_seed(
+            &params.payer.pubkey(),
+            &crate::constants::WSOL_TOKEN_ACCOUNT,
+            &crate::constants::TOKEN_PROGRAM,
+            params.open_seed_optimize,
+        );
+        let wsol = WsolHandling::new(
+            params.create_wsol_ata,
+            params.close_wsol_ata,
+            protocol_params.auto_handle_wsol,
+        );
+        let ephemeral_wsol = EphemeralWsolAccount::for_trade(
+            &params.payer.pubkey(),
+            params.wsol_account,
+            wsol.allows_ephemeral(),
+        );
+        let wsol_token_account =
+            ephemeral_wsol.map_or(wsol_token_account, |account| account.address);
+        let mint_token_account = get_associated_token_address_with_program_id_fast_use_seed(
+            &params.payer.pubkey(),
+            &params.mint,
+            &protocol_params.mint_token_program,
+            params.open_seed_optimize,
+        );
+
+        // ========================================
+        // Build instructions
+        // ========================================
+        let mut instructions = Vec::with_capacity(6);
+
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.extend(ephemeral_wsol.open(&params.payer.pubkey(), params.sol_amount)?);
+        } else if wsol.create {
+            instructions.extend(crate::trading::common::handle_wsol(
+                &params.payer.pubkey(),
+                params.sol_amount,
+            ));
+        }
+
+        instructions.extend(
+            crate::common::fast_fn::create_associated_token_account_idempotent_fast_use_seed(
+                &params.payer.pubkey(),
+                &params.payer.pubkey(),
+                &params.mint,
+                &protocol_params.mint_token_program,
+                params.open_seed_optimize,
+            ),
+        );
+
+        instructions.push(shared_accounts_route(
+            protocol_params,
+            &params.payer.pubkey(),
+            RouteSide {
+                mint: crate::constants::WSOL_TOKEN_ACCOUNT,
+                token_program: crate::constants::TOKEN_PROGRAM,
+                user_token_account: wsol_token_account,
+            },
+            RouteSide {
+                mint: params.mint,
+                token_program: protocol_params.mint_token_program,
+                user_token_account: mint_token_account,
+            },
+            params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
+        )?);
+
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.push(ephemeral_wsol.close(&params.payer.pubkey()));
+        } else if wsol.close {
+            // Close wSOL ATA account, reclaim rent
+            instructions.extend(crate::trading::common::close_wsol(&params.payer.pubkey()));
+        }
+
+        Ok(instructions)
+    }
+
+    async fn build_sell_instructions(&self, params: &SellParams) -> Result<Vec<Instruction>> {
+        // ========================================
+        // Parameter validation and basic data preparation
+        // ========================================
+        let protocol_params = params
+            .protocol_params
+            .as_any()
+            .downcast_ref::<JupiterParams>()
+            .ok_or_else(|| TradeError::params_mismatch("Jupiter"))?;
+
+        let token_amount = params.token_amount.unwrap_or(0);
+        if token_amount == 0 {
+            return Err(TradeError::invalid_params("Jupiter", "amount is zero").into());
+        }
+        if protocol_params.input_mint != params.mint
+            || protocol_params.output_mint != crate::constants::WSOL_TOKEN_ACCOUNT
+        {
+            return Err(anyhow!("Jupiter route does not swap {} to wSOL", params.mint));
+        }
+        check_quoted_amount(protocol_params, token_amount)?;
+
+        // ========================================
+        // Account address preparation
+        // ========================================
+        let wsol_token_account = get_associated_token_address_with_program_id_fast_use_seed(
+            &params.payer.pubkey(),
+            &crate::constants::WSOL_TOKEN_ACCOUNT,
+            &crate::constants::TOKEN_PROGRAM,
+            params.open_seed_optimize,
+        );
+        let wsol = WsolHandling::new(
+            params.create_wsol_ata,
+            params.close_wsol_ata,
+            protocol_params.auto_handle_wsol,
+        );
+        let ephemeral_wsol = EphemeralWsolAccount::for_trade(
+            &params.payer.pubkey(),
+            params.wsol_account,
+            wsol.allows_ephemeral(),
+        );
+        let wsol_token_account =
+            ephemeral_wsol.map_or(wsol_token_account, |account| account.address);
+        let mint_token_account = get_associated_token_address_with_program_id_fast_use_seed(
+            &params.payer.pubkey(),
+            &params.mint,
+            &protocol_params.mint_token_program,
+            params.open_seed_optimize,
+        );
+
+        // ========================================
+        // Build instructions
+        // ========================================
+        let mut instructions = Vec::with_capacity(3);
+
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.extend(ephemeral_wsol.open(&params.payer.pubkey(), 0)?);
+        } else if wsol.create {
+            instructions.extend(crate::trading::common::create_wsol_ata(&params.payer.pubkey()));
+        }
+
+        instructions.push(shared_accounts_route(
+            protocol_params,
+            &params.payer.pubkey(),
+            RouteSide {
+                mint: params.mint,
+                token_program: protocol_params.mint_token_program,
+                user_token_account: mint_token_account,
+            },
+            RouteSide {
+                mint: crate::constants::WSOL_TOKEN_ACCOUNT,
+                token_program: crate::constants::TOKEN_PROGRAM,
+                user_token_account: wsol_token_account,
+            },
+            params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
+        )?);
+
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.push(ephemeral_wsol.close(&params.payer.pubkey()));
+        } else if wsol.close {
+            // Close wSOL ATA account, reclaim rent
+            instructions.extend(crate::trading::common::close_wsol(&params.payer.pubkey()));
+        }
+
+        Ok(instructions)
+    }
+}
+
+/// The route's percentages would still apply to another amount, but the on-chain slippage
+/// check is against `quoted_out_amount`, which only holds for the quoted input
+fn check_quoted_amount(protocol_params: &JupiterParams, amount: u64) -> Result<()> {
+    if amount != protocol_params.in_amount {
+        return Err(anyhow!(
+            "Jupiter route was quoted for {} but the trade is for {}, fetch a new quote",
+            protocol_params.in_amount,
+            amount
+        ));
+    }
+    Ok(())
+}
+
+fn shared_accounts_route(
+    protocol_params: &JupiterParams,
+    payer: &Pubkey,
+    source: RouteSide,
+    destination: RouteSide,
+    slippage_basis_points: u64,
+) -> Result<Instruction> {
+    let program_authority = get_program_authority_pda(protocol_params.program_authority_id)
+        .ok_or_else(|| anyhow!("Failed to derive Jupiter program authority"))?;
+    let program_source_token_account = get_associated_token_address_with_program_id_fast(
+        &program_authority,
+        &source.mint,
+        &source.token_program,
+    );
+    let program_destination_token_account = get_associated_token_address_with_program_id_fast(
+        &program_authority,
+        &destination.mint,
+        &destination.token_program,
+    );
+    // Optional accounts are passed as the program id when unused
+    let token_2022_program = if source.token_program == crate::constants::TOKEN_PROGRAM_2022
+        || destination.token_program == crate::constants::TOKEN_PROGRAM_2022
+    {
+        crate::constants::TOKEN_PROGRAM_2022_META
+    } else {
+        accounts::JUPITER_META
+    };
+
+    let mut accounts: Vec<AccountMeta> = Vec::with_capacity(
+        SHARED_ACCOUNTS_ROUTE_FIXED_ACCOUNTS + protocol_params.route_accounts.len(),
+    );
+    accounts.extend([
+        crate::constants::TOKEN_PROGRAM_META, // Token Program (readonly)
+        AccountMeta::new_readonly(program_authority, false), // Program Authority (readonly)
+        AccountMeta::new_readonly(*payer, true), // User Transfer Authority (signer)
+        AccountMeta::new(source.user_token_account, false), // Source Token Account
+        AccountMeta::new(program_source_token_account, false), // Program Source Token Account
+        AccountMeta::new(program_destination_token_account, false), // Program Dest Token Account
+        AccountMeta::new(destination.user_token_account, false), // Destination Token Account
+        AccountMeta::new_readonly(source.mint, false), // Source Mint (readonly)
+        AccountMeta::new_readonly(destination.mint, false), // Destination Mint (readonly)
+        accounts::JUPITER_META,               // Platform Fee Account (none)
+        token_2022_program,                   // Token 2022 Program (readonly)
+        accounts::EVENT_AUTHORITY_META,       // Event Authority (readonly)
+        accounts::JUPITER_META,               // Program (readonly)
+    ]);
+    accounts.extend(protocol_params.route_accounts.iter().cloned());
+
+    let data = JupiterSharedAccountsRouteArgs {
+        id: protocol_params.program_authority_id,
+        route_plan: protocol_params.route_plan.clone(),
+        in_amount: protocol_params.in_amount,
+        quoted_out_amount: protocol_params.quoted_out_amount,
+        slippage_bps: slippage_basis_points.min(10_000) as u16,
+        platform_fee_bps: 0,
+    }
+    .encode();
+
+    Ok(Instruction::new_with_bytes(accounts::JUPITER, &data, accounts))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::common::wsol_manager::wsol_steps;
+    use solana_sdk::pubkey;
+    use spl_associated_token_account::get_associated_token_address_with_program_id;
+
+    const TOKEN_PROGRAM: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+    const TOKEN_PROGRAM_2022: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+    const WSOL: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+    const USDC: Pubkey = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+    const PYUSD: Pubkey = pubkey!("2b1kV6DkPAnxd5ixfnxCpjxmKwqjjaYmCZfHsFu24GXo");
+
+    /// Program authority 0, from the seeds "authority" and [0], and its token accounts,
+    /// computed outside this crate
+    const AUTHORITY: Pubkey = pubkey!("GGztQqQ6pCPaJQnNpXBgELr5cs3WwDakRbh1iEMzjgSJ");
+    const AUTHORITY_WSOL: Pubkey = pubkey!("g7dD1FHSemkUQrX1Eak37wzvDjscgBW2pFCENwjLdMX");
+    const AUTHORITY_USDC: Pubkey = pubkey!("DVCeozFGbe6ew3eWTnZByjHeYqTq1cvbrB7JJhkLxaRJ");
+    const AUTHORITY_PYUSD: Pubkey = pubkey!("HKuXaSKudNhhXMuGJHyKJ35cLPKY7UgTKCKaxrz6NWVv");
+    const EVENT_AUTHORITY: Pubkey = pubkey!("D8cy77BBepLMngZx6ZukaTff5hCt1HrWyKk3Hnd9oitf");
+
+    /// One step, 100% from route account 0 to route account 1
+    const ROUTE_PLAN: [u8; 8] = [1, 0, 0, 0, 7, 100, 0, 1];
+    const ROUTE_PLAN_HEX: &str = "0100000007640001";
+    /// sha256("global:shared_accounts_route")[..8]
+    const ROUTE: &str = "c1209b3341d69c81";
+
+    fn route_accounts() -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new_readonly(Pubkey::new_unique(), false),
+            AccountMeta::new(Pubkey::new_unique(), false),
+            AccountMeta::new(Pubkey::new_unique(), false),
+        ]
+    }
+
+    fn params(
+        input_mint: Pubkey,
+        output_mint: Pubkey,
+        mint_token_program: Pubkey,
+        in_amount: u64,
+        quoted_out_amount: u64,
+    ) -> JupiterParams {
+        JupiterParams {
+            input_mint,
+            output_mint,
+            in_amount,
+            quoted_out_amount,
+            route_plan: ROUTE_PLAN.to_vec(),
+            route_accounts: route_accounts(),
+            program_authority_id: 0,
+            mint_token_program,
+            auto_handle_wsol: true,
+        }
+    }
+
+    /// Fixed accounts of `shared_accounts_route`, in the order of the program's IDL
+    fn fixed_accounts(
+        payer: &Pubkey,
+        (source_mint, source_program, program_source): (Pubkey, Pubkey, Pubkey),
+        (destination_mint, destination_program, program_destination): (Pubkey, Pubkey, Pubkey),
+        token_2022_program: Pubkey,
+    ) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new_readonly(TOKEN_PROGRAM, false),
+            AccountMeta::new_readonly(AUTHORITY, false),
+            AccountMeta::new_readonly(*payer, true),
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(payer, &source_mint, &source_program),
+                false,
+            ),
+            AccountMeta::new(program_source, false),
+            AccountMeta::new(program_destination, false),
+            AccountMeta::new(
+                get_associated_token_address_with_program_id(
+                    payer,
+                    &destination_mint,
+                    &destination_program,
+                ),
+                false,
+            ),
+            AccountMeta::new_readonly(source_mint, false),
+            AccountMeta::new_readonly(destination_mint, false),
+            AccountMeta::new_readonly(accounts::JUPITER, false),
+            AccountMeta::new_readonly(token_2022_program, false),
+            AccountMeta::new_readonly(EVENT_AUTHORITY, false),
+            AccountMeta::new_readonly(accounts::JUPITER, false),
+        ]
+    }
+
+    fn route(instructions: &[Instruction]) -> &Instruction {
+        instructions.iter().find(|ix| ix.program_id == accounts::JUPITER).unwrap()
+    }
+
+    #[tokio::test]
+    async fn buy_matches_the_golden_route_instruction() {
+        let protocol_params = params(WSOL, USDC, TOKEN_PROGRAM, 1_000_000, 150_000);
+        let buy = BuyParams::offline(USDC, 1_000_000, Box::new(protocol_params.clone()));
+        let payer = buy.payer.pubkey();
+        let instructions = JupiterInstructionBuilder.build_buy_instructions(&buy).await.unwrap();
+        assert_eq!(wsol_steps(&instructions, &payer), ["create", "transfer", "sync", "close"]);
+
+        let route = route(&instructions);
+        let mut expected = fixed_accounts(
+            &payer,
+            (WSOL, TOKEN_PROGRAM, AUTHORITY_WSOL),
+            (USDC, TOKEN_PROGRAM, AUTHORITY_USDC),
+            accounts::JUPITER,
+        );
+        expected.extend(protocol_params.route_accounts);
+        assert_eq!(route.accounts, expected);
+        // Discriminator, id 0, route plan, 1_000_000 in, 150_000 out, 1% slippage, no fee
+        assert_eq!(
+            hex::encode(&route.data),
+            [ROUTE, "00", ROUTE_PLAN_HEX, "40420f0000000000", "f049020000000000", "6400", "00"]
+                .concat()
+        );
+    }
+
+    #[tokio::test]
+    async fn token_2022_sell_passes_the_token_2022_program() {
+        let protocol_params = params(PYUSD, WSOL, TOKEN_PROGRAM_2022, 2_500_000, 16_000_000);
+        let sell = SellParams::offline(PYUSD, Some(2_500_000), Box::new(protocol_params.clone()));
+        let payer = sell.payer.pubkey();
+        let instructions = JupiterInstructionBuilder.build_sell_instructions(&sell).await.unwrap();
+        assert_eq!(wsol_steps(&instructions, &payer), ["create", "close"]);
+
+        let route = route(&instructions);
+        let mut expected = fixed_accounts(
+            &payer,
+            (PYUSD, TOKEN_PROGRAM_2022, AUTHORITY_PYUSD),
+            (WSOL, TOKEN_PROGRAM, AUTHORITY_WSOL),
+            TOKEN_PROGRAM_2022,
+        );
+        expected.extend(protocol_params.route_accounts);
+        assert_eq!(route.accounts, expected);
+        // 2_500_000 in, 16_000_000 out
+        assert_eq!(
+            hex::encode(&route.data),
+            [ROUTE, "00", ROUTE_PLAN_HEX, "a025260000000000", "0024f40000000000", "6400", "00"]
+                .concat()
+        );
+    }
+
+    #[tokio::test]
+    async fn route_must_match_the_trade() {
+        // A sell quote cannot fill a buy
+        let sell_quote = params(USDC, WSOL, TOKEN_PROGRAM, 1_000_000, 150_000);
+        let buy = BuyParams::offline(USDC, 1_000_000, Box::new(sell_quote.clone()));
+        assert!(JupiterInstructionBuilder.build_buy_instructions(&buy).await.is_err());
+
+        // Nor can a quote for another amount
+        let sell = SellParams::offline(USDC, Some(999_999), Box::new(sell_quote));
+        let error = JupiterInstructionBuilder.build_sell_instructions(&sell).await.unwrap_err();
+        assert!(error.to_string().contains("fetch a new quote"), "{}", error);
+    }
+}
@@ -0,0 +1,493 @@
+use crate::{
+    common::fast_fn::get_associated_token_address_with_program_id_fast_use_seed,
+    constants::trade::trade::DEFAULT_SLIPPAGE,
+    instruction::data::{InstructionData, MeteoraDlmmSwapArgs},
+    instruction::utils::meteora_dlmm::{
+        accounts, bin_array_index, get_bin_array_pda, swap_bin_array_indexes,
+    },
+    params::MeteoraDlmmParams,
+    trading::{
+        common::wsol_manager::{EphemeralWsolAccount, WsolHandling},
+        core::{
+            error::TradeError,
+            params::{BuyParams, SellParams},
+            traits::InstructionBuilder,
+        },
+    },
+    utils::calc::meteora_dlmm::{base_fee_rate, compute_swap_amount},
+};
+use anyhow::{anyhow, Result};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+/// Instruction builder for Meteora DLMM protocol
+pub struct MeteoraDlmmInstructionBuilder;
+
+#[async_trait::async_trait]
+impl InstructionBuilder for MeteoraDlmmInstructionBuilder {
+    async fn build_buy_instructions(&self, params: &BuyParams) -> Result<Vec<Instruction>> {
+        // ========================================
+        // Parameter validation and basic data preparation
+        // ========================================
+        if params.sol_amount == 0 {
+            return Err(TradeError::invalid_params("MeteoraDlmm", "amount is zero").into());
+        }
+        let protocol_params = params
+            .protocol_params
+            .as_any()
+            .downcast_ref::<MeteoraDlmmParams>()
+            .ok_or_else(|| TradeError::params_mismatch("MeteoraDlmm"))?;
+
+        params.require_exact_sol_in("MeteoraDlmm")?;
+
+        // Buying spends wSOL, so X is swapped for Y when wSOL is token X
+        let swap_for_y = protocol_params.token_x_mint == crate::constants::WSOL_TOKEN_ACCOUNT;
+        check_pair(protocol_params, &params.mint, swap_for_y)?;
+
+        // ========================================
+        // Trade calculation and account address preparation
+        // ========================================
+        let amount_in: u64 = params.sol_amount;
+        let min_amount_out = compute_min_amount_out(
+            protocol_params,
+            swap_for_y,
+            amount_in,
+            params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
+        );
+        let mint_token_program = mint_token_program(protocol_params, swap_for_y);
+
+        let wsol_token_account = get_associated_token_address_with_program_id_fast_use_seed(
+            &params.payer.pubkey(),
+            &crate::constants::WSOL_TOKEN_ACCOUNT,
+            &crate::constants::TOKEN_PROGRAM,
+            params.open_seed_optimize,
+        );
+        let wsol = WsolHandling::new(
+            params.create_wsol_ata,
+            params.close_wsol_ata,
+            protocol_params.auto_handle_wsol,
+        );
+        let ephemeral_wsol = EphemeralWsolAccount::for_trade(
+            &params.payer.pubkey(),
+            params.wsol_account,
+            wsol.allows_ephemeral(),
+        );
+        let wsol_token_account =
+            ephemeral_wsol.map_or(wsol_token_account, |account| account.address);
+        let mint_token_account = get_associated_token_address_with_program_id_fast_use_seed(
+            &params.payer.pubkey(),
+            &params.mint,
+            &mint_token_program,
+            params.open_seed_optimize,
+        );
+
+        // ========================================
+        // Build instructions
+        // ========================================
+        let mut instructions = Vec::with_capacity(6);
+
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.extend(ephemeral_wsol.open(&params.payer.pubkey(), amount_in)?);
+        } else if wsol.create {
+            instructions
+                .extend(crate::trading::common::handle_wsol(&params.payer.pubkey(), amount_in));
+        }
+
+        instructions.extend(
+            crate::common::fast_fn::create_associated_token_account_idempotent_fast_use_seed(
+                &params.payer.pubkey(),
+                &params.payer.pubkey(),
+                &params.mint,
+                &mint_token_program,
+                params.open_seed_optimize,
+            ),
+        );
+
+        instructions.push(swap_instruction(
+            protocol_params,
+            &params.payer.pubkey(),
+            swap_for_y,
+            wsol_token_account,
+            mint_token_account,
+            MeteoraDlmmSwapArgs { amount_in, min_amount_out },
+        )?);
+
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.push(ephemeral_wsol.close(&params.payer.pubkey()));
+        } else if wsol.close {
+            // Close wSOL ATA account, reclaim rent
+            instructions.extend(crate::trading::common::close_wsol(&params.payer.pubkey()));
+        }
+
+        Ok(instructions)
+    }
+
+    async fn build_sell_instructions(&self, params: &SellParams) -> Result<Vec<Instruction>> {
+        // ========================================
+        // Parameter validation and basic data preparation
+        // ========================================
+        let protocol_params = params
+            .protocol_params
+            .as_any()
+            .downcast_ref::<MeteoraDlmmParams>()
+            .ok_or_else(|| TradeError::params_mismatch("MeteoraDlmm"))?;
+
+        let amount_in = params.token_amount.unwrap_or(0);
+        if amount_in == 0 {
+            return Err(TradeError::invalid_params("MeteoraDlmm", "amount is zero").into());
+        }
+
+        // Selling spends the mint, so X is swapped for Y when the mint is token X
+        let swap_for_y = protocol_params.token_x_mint == params.mint;
+        check_pair(protocol_params, &params.mint, !swap_for_y)?;
+
+        // ========================================
+        // Trade calculation and account address preparation
+        // ========================================
+        let min_amount_out = compute_min_amount_out(
+            protocol_params,
+            swap_for_y,
+            amount_in,
+            params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
+        );
+        let mint_token_program = mint_token_program(protocol_params, !swap_for_y);
+
+        let wsol_token_account = get_associated_token_address_with_program_id_fast_use_seed(
+            &params.payer.pubkey(),
+            &crate::constants::WSOL_TOKEN_ACCOUNT,
+            &crate::constants::TOKEN_PROGRAM,
+            params.open_seed_optimize,
+        );
+        let wsol = WsolHandling::new(
+            params.create_wsol_ata,
+            params.close_wsol_ata,
+            protocol_params.auto_handle_wsol,
+        );
+        let ephemeral_wsol = EphemeralWsolAccount::for_trade(
+            &params.payer.pubkey(),
+            params.wsol_account,
+            wsol.allows_ephemeral(),
+        );
+        let wsol_token_account =
+            ephemeral_wsol.map_or(wsol_token_account, |account| account.address);
+        let mint_token_account = get_associated_token_address_with_program_id_fast_use_seed(
+            &params.payer.pubkey(),
+            &params.mint,
+            &mint_token_program,
+            params.open_seed_optimize,
+        );
+
+        // ========================================
+        // Build instructions
+        // ========================================
+        let mut instructions = Vec::with_capacity(3);
+
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.extend(ephemeral_wsol.open(&params.payer.pubkey(), 0)?);
+        } else if wsol.create {
+            instructions.extend(crate::trading::common::create_wsol_ata(&params.payer.pubkey()));
+        }
+
+        instructions.push(swap_instruction(
+            protocol_params,
+            &params.payer.pubkey(),
+            swap_for_y,
+            mint_token_account,
+            wsol_token_account,
+            MeteoraDlmmSwapArgs { amount_in, min_amount_out },
+        )?);
+
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.push(ephemeral_wsol.close(&params.payer.pubkey()));
+        } else if wsol.close {
+            // Close wSOL ATA account, reclaim rent
+            instructions.extend(crate::trading::common::close_wsol(&params.payer.pubkey()));
+        }
+
+        Ok(instructions)
+    }
+}
+
+/// Check the pair trades `mint` against wSOL, `wsol_is_x` telling which side wSOL is on
+fn check_pair(protocol_params: &MeteoraDlmmParams, mint: &Pubkey, wsol_is_x: bool) -> Result<()> {
+    let (wsol_side, mint_side) = if wsol_is_x {
+        (protocol_params.token_x_mint, protocol_params.token_y_mint)
+    } else {
+        (protocol_params.token_y_mint, protocol_params.token_x_mint)
+    };
+    if wsol_side != crate::constants::WSOL_TOKEN_ACCOUNT || mint_side != *mint {
+        return Err(anyhow!(
+            "Lb pair {} does not pair {} with wSOL",
+            protocol_params.lb_pair,
+            mint
+        ));
+    }
+    Ok(())
+}
+
+/// Token program of the non-wSOL side
+fn mint_token_program(protocol_params: &MeteoraDlmmParams, wsol_is_x: bool) -> Pubkey {
+    if wsol_is_x {
+        protocol_params.token_y_program
+    } else {
+        protocol_params.token_x_program
+    }
+}
+
+fn compute_min_amount_out(
+    protocol_params: &MeteoraDlmmParams,
+    swap_for_y: bool,
+    amount_in: u64,
+    slippage_basis_points: u64,
+) -> u64 {
+    let output_reserve = if swap_for_y {
+        protocol_params.reserve_y_amount
+    } else {
+        protocol_params.reserve_x_amount
+    };
+    compute_swap_amount(
+        protocol_params.active_id,
+        protocol_params.bin_step,
+        base_fee_rate(
+            protocol_params.base_factor,
+            protocol_params.bin_step,
+            protocol_params.base_fee_power_factor,
+        ),
+        output_reserve,
+        swap_for_y,
+        amount_in,
+        slippage_basis_points,
+    )
+    .min_amount_out
+}
+
+fn swap_instruction(
+    protocol_params: &MeteoraDlmmParams,
+    payer: &Pubkey,
+    swap_for_y: bool,
+    user_token_in: Pubkey,
+    user_token_out: Pubkey,
+    args: MeteoraDlmmSwapArgs,
+) -> Result<Instruction> {
+    let bin_arrays: Vec<Pubkey> = swap_bin_array_indexes(protocol_params.active_id, swap_for_y)
+        .filter_map(|index| get_bin_array_pda(&protocol_params.lb_pair, index))
+        .filter(|bin_array| protocol_params.bin_arrays.contains(bin_array))
+        .collect();
+    if bin_arrays.is_empty() {
+        return Err(anyhow!("No bin array around the active bin {}", protocol_params.active_id));
+    }
+
+    // Optional accounts are passed as the program id when unused
+    let bin_array_bitmap_extension = match protocol_params.bin_array_bitmap_extension {
+        Some(extension) => AccountMeta::new_readonly(extension, false),
+        None if accounts::BIN_ARRAY_BITMAP_RANGE
+            .contains(&bin_array_index(protocol_params.active_id)) =>
+        {
+            accounts::METEORA_DLMM_META
+        }
+        None => return Err(anyhow!("Active bin needs the bin array bitmap extension")),
+    };
+
+    let mut accounts: Vec<AccountMeta> = Vec::with_capacity(15 + bin_arrays.len());
+    accounts.extend([
+        AccountMeta::new(protocol_params.lb_pair, false), // Lb Pair
+        bin_array_bitmap_extension,                       // Bin Array Bitmap Extension (readonly)
+        AccountMeta::new(protocol_params.reserve_x, false), // Reserve X
+        AccountMeta::new(protocol_params.reserve_y, false), // Reserve Y
+        AccountMeta::new(user_token_in, false),           // User Token In
+        AccountMeta::new(user_token_out, false),          // User Token Out
+        AccountMeta::new_readonly(protocol_params.token_x_mint, false), // Token X Mint (readonly)
+        AccountMeta::new_readonly(protocol_params.token_y_mint, false), // Token Y Mint (readonly)
+        AccountMeta::new(protocol_params.oracle, false),  // Oracle
+        accounts::METEORA_DLMM_META,                      // Host Fee In (none)
+        AccountMeta::new_readonly(*payer, true),          // User (signer)
+        AccountMeta::new_readonly(protocol_params.token_x_program, false), // Token X Program (readonly)
+        AccountMeta::new_readonly(protocol_params.token_y_program, false), // Token Y Program (readonly)
+        accounts::EVENT_AUTHORITY_META, // Event Authority (readonly)
+        accounts::METEORA_DLMM_META,    // Program (readonly)
+    ]);
+    accounts.extend(bin_arrays.into_iter().map(|bin_array| AccountMeta::new(bin_array, false)));
+
+    Ok(Instruction::new_with_bytes(accounts::METEORA_DLMM, &args.encode(), accounts))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::common::wsol_manager::wsol_steps;
+    use solana_sdk::pubkey;
+    use spl_associated_token_account::get_associated_token_address;
+
+    const PROGRAM: Pubkey = pubkey!("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo");
+    const EVENT_AUTHORITY: Pubkey = pubkey!("D1ZN9Wj1fRSUQfCjhvnu1hqDMT7hzjzBBpi12nVniYD6");
+    const TOKEN_PROGRAM: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+    const WSOL: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
+    const MINT: Pubkey = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+
+    /// `[0x11; 32]`, with the PDAs the program derives for it from the seeds "bin_array" and
+    /// "bitmap", computed outside this crate
+    const LB_PAIR: Pubkey = pubkey!("29d2S7vB453rNYFdR5Ycwt7y9haRT5fwVwL9zTmBhfV2");
+    const BIN_ARRAYS: [(i64, Pubkey); 6] = [
+        (-3, pubkey!("BmUtujayvMETTkQmJiRHx1teH8dyHdtTEVxUSyPsLV7U")),
+        (-2, pubkey!("Bs4mXFNggAmmiYFefC97P9LvpJKiQfaKnExDuYszJqw6")),
+        (-1, pubkey!("GZMUpKyKXX6BzSweXajWQoh27K8zpb7osP6VfX6nEd8A")),
+        (0, pubkey!("2hB9gtoXtw5WfM4vNTwC3AsmGJV1A47VgKzfHe2exmvG")),
+        (1, pubkey!("AnQZzmAhkKPe9nDRAnmw5fKRvhg88SKzJooWsgvb34Jy")),
+        (512, pubkey!("FdC8MuTMbuap2RrnAYB2R1UxynDu9AGBAToYWnMSbYyy")),
+    ];
+    const BITMAP_EXTENSION: Pubkey = pubkey!("5gdYnBb5N49Asp2P6t429YiUSqRpUGWdH4greZf1NUt9");
+
+    fn bin_array(index: i64) -> Pubkey {
+        BIN_ARRAYS.iter().find(|(i, _)| *i == index).unwrap().1
+    }
+
+    /// wSOL as token X, `MINT` as token Y, one bin below bin 0 with a 0.1% base fee
+    fn pair_params() -> MeteoraDlmmParams {
+        MeteoraDlmmParams {
+            lb_pair: LB_PAIR,
+            token_x_mint: WSOL,
+            token_y_mint: MINT,
+            reserve_x: Pubkey::new_unique(),
+            reserve_y: Pubkey::new_unique(),
+            reserve_x_amount: 500_000_000_000,
+            reserve_y_amount: 80_000_000_000,
+            token_x_program: TOKEN_PROGRAM,
+            token_y_program: TOKEN_PROGRAM,
+            oracle: Pubkey::new_unique(),
+            bin_array_bitmap_extension: None,
+            // Out of order, and the one at index -2 is not initialized
+            bin_arrays: [1, -1, 0, -3].map(bin_array).to_vec(),
+            active_id: -1,
+            bin_step: 10,
+            base_factor: 10_000,
+            base_fee_power_factor: 0,
+            auto_handle_wsol: true,
+        }
+    }
+
+    /// Accounts of a DLMM `swap`, in the order of the program's IDL, then the bin arrays
+    fn swap_accounts(
+        params: &MeteoraDlmmParams,
+        payer: &Pubkey,
+        (user_token_in, user_token_out): (Pubkey, Pubkey),
+        bin_arrays: &[i64],
+    ) -> Vec<AccountMeta> {
+        let mut accounts = vec![
+            AccountMeta::new(LB_PAIR, false),
+            AccountMeta::new_readonly(PROGRAM, false),
+            AccountMeta::new(params.reserve_x, false),
+            AccountMeta::new(params.reserve_y, false),
+            AccountMeta::new(user_token_in, false),
+            AccountMeta::new(user_token_out, false),
+            AccountMeta::new_readonly(WSOL, false),
+            AccountMeta::new_readonly(MINT, false),
+            AccountMeta::new(params.oracle, false),
+            AccountMeta::new_readonly(PROGRAM, false),
+            AccountMeta::new_readonly(*payer, true),
+            AccountMeta::new_readonly(TOKEN_PROGRAM, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM, false),
+            AccountMeta::new_readonly(EVENT_AUTHORITY, false),
+            AccountMeta::new_readonly(PROGRAM, false),
+        ];
+        accounts.extend(bin_arrays.iter().map(|&index| AccountMeta::new(bin_array(index), false)));
+        accounts
+    }
+
+    fn swap(instructions: &[Instruction]) -> &Instruction {
+        instructions.iter().find(|ix| ix.program_id == PROGRAM).unwrap()
+    }
+
+    #[test]
+    fn bin_array_pdas_match_the_program_derivation() {
+        for (index, address) in BIN_ARRAYS {
+            assert_eq!(get_bin_array_pda(&LB_PAIR, index), Some(address), "index {}", index);
+        }
+        assert_eq!(
+            crate::instruction::utils::meteora_dlmm::get_bin_array_bitmap_extension_pda(&LB_PAIR),
+            Some(BITMAP_EXTENSION)
+        );
+        // Bins below 0 round down to the previous array
+        assert_eq!([-71, -70, -1, 0, 69, 70].map(bin_array_index), [-2, -1, -1, 0, 0, 1]);
+    }
+
+    #[tokio::test]
+    async fn buy_matches_the_golden_swap_instruction() {
+        let params = pair_params();
+
+        // 1 SOL in, 0.1% fee, at a price of 1 / 1.001 and 1% slippage
+        let buy = BuyParams::offline(MINT, 1_000_000_000, Box::new(params.clone()));
+        let payer = buy.payer.pubkey();
+        let instructions =
+            MeteoraDlmmInstructionBuilder.build_buy_instructions(&buy).await.unwrap();
+        assert_eq!(wsol_steps(&instructions, &payer), ["create", "transfer", "sync", "close"]);
+        // X for Y walks down from the active array, skipping the missing one
+        let user_tokens = (
+            get_associated_token_address(&payer, &WSOL),
+            get_associated_token_address(&payer, &MINT),
+        );
+        assert_eq!(
+            swap(&instructions).accounts,
+            swap_accounts(&params, &payer, user_tokens, &[-1, -3])
+        );
+        assert_eq!(
+            swap(&instructions).data,
+            MeteoraDlmmSwapArgs { amount_in: 1_000_000_000, min_amount_out: 988_021_978 }.encode()
+        );
+    }
+
+    #[tokio::test]
+    async fn sell_matches_the_golden_swap_instruction() {
+        let params = pair_params();
+
+        let sell = SellParams::offline(MINT, Some(150_000_000), Box::new(params.clone()));
+        let payer = sell.payer.pubkey();
+        let instructions =
+            MeteoraDlmmInstructionBuilder.build_sell_instructions(&sell).await.unwrap();
+        assert_eq!(wsol_steps(&instructions, &payer), ["create", "close"]);
+        // Y for X walks up from the active array
+        let user_tokens = (
+            get_associated_token_address(&payer, &MINT),
+            get_associated_token_address(&payer, &WSOL),
+        );
+        assert_eq!(
+            swap(&instructions).accounts,
+            swap_accounts(&params, &payer, user_tokens, &[-1, 0, 1])
+        );
+        assert_eq!(
+            swap(&instructions).data,
+            MeteoraDlmmSwapArgs { amount_in: 150_000_000, min_amount_out: 148_499_850 }.encode()
+        );
+    }
+
+    #[tokio::test]
+    async fn far_active_bin_needs_the_bitmap_extension() {
+        // First bin of array 512, just past the bitmap of the pair
+        let mut params = MeteoraDlmmParams {
+            active_id: 512 * 70,
+            bin_arrays: vec![bin_array(512)],
+            ..pair_params()
+        };
+        let sell = SellParams::offline(MINT, Some(150_000_000), Box::new(params.clone()));
+        let error = MeteoraDlmmInstructionBuilder.build_sell_instructions(&sell).await.unwrap_err();
+        assert!(error.to_string().contains("bitmap extension"), "{}", error);
+
+        params.bin_array_bitmap_extension = Some(BITMAP_EXTENSION);
+        let sell = SellParams::offline(MINT, Some(150_000_000), Box::new(params));
+        let instructions =
+            MeteoraDlmmInstructionBuilder.build_sell_instructions(&sell).await.unwrap();
+        let swap = swap(&instructions);
+        assert_eq!(swap.accounts[1], AccountMeta::new_readonly(BITMAP_EXTENSION, false));
+        assert_eq!(swap.accounts[15..], [AccountMeta::new(bin_array(512), false)]);
+    }
+
+    #[tokio::test]
+    async fn no_bin_array_in_the_swap_direction_fails() {
+        // Only arrays above the active one, a buy walks down
+        let params =
+            MeteoraDlmmParams { bin_arrays: vec![bin_array(0), bin_array(1)], ..pair_params() };
+        let buy = BuyParams::offline(MINT, 1_000_000_000, Box::new(params));
+        let error = MeteoraDlmmInstructionBuilder.build_buy_instructions(&buy).await.unwrap_err();
+        assert!(error.to_string().contains("No bin array"), "{}", error);
+    }
+}
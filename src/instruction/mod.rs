@@ -1,7 +1,10 @@
 pub mod pumpfun;
 pub mod pumpswap;
 pub mod bonk;
+pub mod data;
 pub mod raydium_cpmm;
 pub mod raydium_clmm;
 pub mod raydium_amm_v4;
+pub mod jupiter;
+pub mod meteora_dlmm;
 pub mod utils;
\ No newline at end of file
@@ -1,23 +1,32 @@
 use crate::{
+    common::global::GlobalAccount,
     constants::trade::trade::DEFAULT_SLIPPAGE,
+    params::PumpFunParams,
     trading::core::{
-        params::{BuyParams, PumpFunParams, SellParams},
+        error::TradeError,
+        params::{BuyAmountSpec, BuyParams, SellParams},
         traits::InstructionBuilder,
     },
 };
 use crate::{
+    instruction::data::{InstructionData, PumpFunBuyArgs, PumpFunCreateArgs, PumpFunSellArgs},
     instruction::utils::pumpfun::{
-        accounts, get_bonding_curve_pda, get_creator, get_user_volume_accumulator_pda,
-        global_constants::{self},
+        accounts, get_bonding_curve_pda, get_creator, get_creator_vault_pda, get_metadata_pda,
+        get_mint_authority_pda, global_constants, PumpFunLayout, PumpFunTradeAccounts,
     },
     utils::calc::{
         common::{calculate_with_slippage_buy, calculate_with_slippage_sell},
-        pumpfun::{get_buy_token_amount_from_sol_amount, get_sell_sol_amount_from_token_amount},
+        pumpfun::{
+            get_buy_sol_amount_from_token_amount, get_buy_token_amount_from_sol_amount,
+            get_sell_sol_amount_from_token_amount,
+        },
     },
 };
 use anyhow::{anyhow, Result};
-use solana_sdk::instruction::AccountMeta;
-use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signer::Signer};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
 
 /// Instruction builder for PumpFun protocol
 pub struct PumpFunInstructionBuilder;
@@ -32,10 +41,10 @@ impl InstructionBuilder for PumpFunInstructionBuilder {
             .protocol_params
             .as_any()
             .downcast_ref::<PumpFunParams>()
-            .ok_or_else(|| anyhow!("Invalid protocol params for PumpFun"))?;
+            .ok_or_else(|| TradeError::params_mismatch("PumpFun"))?;
 
         if params.sol_amount == 0 {
-            return Err(anyhow!("Amount cannot be zero"));
+            return Err(TradeError::invalid_params("PumpFun", "amount is zero").into());
         }
 
         let bonding_curve = &protocol_params.bonding_curve;
@@ -45,18 +54,55 @@ impl InstructionBuilder for PumpFunInstructionBuilder {
         // ========================================
         // Trade calculation and account address preparation
         // ========================================
-        let buy_token_amount = get_buy_token_amount_from_sol_amount(
-            bonding_curve.virtual_token_reserves as u128,
-            bonding_curve.virtual_sol_reserves as u128,
-            bonding_curve.real_token_reserves as u128,
-            creator,
-            params.sol_amount,
-        );
-
-        let max_sol_cost = calculate_with_slippage_buy(
-            params.sol_amount,
-            params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
-        );
+        let (buy_token_amount, max_sol_cost) = match params.amount_spec {
+            BuyAmountSpec::ExactSolIn(_) => {
+                let buy_token_amount = get_buy_token_amount_from_sol_amount(
+                    bonding_curve.virtual_token_reserves as u128,
+                    bonding_curve.virtual_sol_reserves as u128,
+                    bonding_curve.real_token_reserves as u128,
+                    creator,
+                    params.sol_amount,
+                );
+                match params.min_out_floor(buy_token_amount)? {
+                    // The instruction names the tokens out, the floor is bought for at most
+                    // sol_amount
+                    Some(min_amount_out) => (min_amount_out, params.sol_amount),
+                    None => {
+                        let max_sol_cost = calculate_with_slippage_buy(
+                            params.sol_amount,
+                            params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
+                        );
+                        (buy_token_amount, max_sol_cost)
+                    }
+                }
+            }
+            BuyAmountSpec::ExactTokensOut { token_amount, max_sol } => {
+                params.min_out_floor(token_amount)?;
+                let sol_cost = get_buy_sol_amount_from_token_amount(
+                    bonding_curve.virtual_token_reserves as u128,
+                    bonding_curve.virtual_sol_reserves as u128,
+                    bonding_curve.real_token_reserves as u128,
+                    creator,
+                    token_amount,
+                )
+                .ok_or_else(|| {
+                    anyhow!("Bonding curve cannot sell {} tokens of {}", token_amount, params.mint)
+                })?;
+                if sol_cost > max_sol {
+                    return Err(anyhow!(
+                        "Buying {} tokens costs {} lamports, more than max_sol {}",
+                        token_amount,
+                        sol_cost,
+                        max_sol
+                    ));
+                }
+                let max_sol_cost = calculate_with_slippage_buy(
+                    sol_cost,
+                    params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
+                );
+                (token_amount, max_sol_cost.min(max_sol))
+            }
+        };
 
         let bonding_curve_addr = if bonding_curve.account == Pubkey::default() {
             get_bonding_curve_pda(&params.mint).unwrap()
@@ -83,9 +129,6 @@ impl InstructionBuilder for PumpFunInstructionBuilder {
                 params.open_seed_optimize,
             );
 
-        let user_volume_accumulator =
-            get_user_volume_accumulator_pda(&params.payer.pubkey()).unwrap();
-
         // ========================================
         // Build instructions
         // ========================================
@@ -103,35 +146,21 @@ impl InstructionBuilder for PumpFunInstructionBuilder {
             ),
         );
 
-        let mut buy_data = [0u8; 24];
-        buy_data[..8].copy_from_slice(&[102, 6, 61, 18, 1, 218, 235, 234]); // Method ID
-        buy_data[8..16].copy_from_slice(&buy_token_amount.to_le_bytes());
-        buy_data[16..24].copy_from_slice(&max_sol_cost.to_le_bytes());
-
-        let accounts: [AccountMeta; 16] = [
-            global_constants::GLOBAL_ACCOUNT_META,
-            global_constants::FEE_RECIPIENT_META,
-            AccountMeta::new_readonly(params.mint, false),
-            AccountMeta::new(bonding_curve_addr, false),
-            AccountMeta::new(associated_bonding_curve, false),
-            AccountMeta::new(user_token_account, false),
-            AccountMeta::new(params.payer.pubkey(), true),
-            crate::constants::SYSTEM_PROGRAM_META,
-            crate::constants::TOKEN_PROGRAM_META,
-            AccountMeta::new(creator_vault_pda, false),
-            accounts::EVENT_AUTHORITY_META,
-            accounts::PUMPFUN_META,
-            accounts::GLOBAL_VOLUME_ACCUMULATOR_META,
-            AccountMeta::new(user_volume_accumulator, false),
-            accounts::FEE_CONFIG_META,
-            accounts::FEE_PROGRAM_META,
-        ];
+        let buy_data = PumpFunBuyArgs { amount: buy_token_amount, max_sol_cost }.encode();
 
-        instructions.push(Instruction::new_with_bytes(
-            accounts::PUMPFUN,
-            &buy_data,
-            accounts.to_vec(),
-        ));
+        let accounts = protocol_params.layout.buy_accounts_with(
+            &PumpFunTradeAccounts {
+                mint: params.mint,
+                bonding_curve: bonding_curve_addr,
+                associated_bonding_curve,
+                user_token_account,
+                user: params.payer.pubkey(),
+                creator_vault: creator_vault_pda,
+            },
+            protocol_params.include_volume_accumulators,
+        );
+
+        instructions.push(Instruction::new_with_bytes(accounts::PUMPFUN, &buy_data, accounts));
 
         Ok(instructions)
     }
@@ -144,15 +173,15 @@ impl InstructionBuilder for PumpFunInstructionBuilder {
             .protocol_params
             .as_any()
             .downcast_ref::<PumpFunParams>()
-            .ok_or_else(|| anyhow!("Invalid protocol params for PumpFun"))?;
+            .ok_or_else(|| TradeError::params_mismatch("PumpFun"))?;
 
         let token_amount = if let Some(amount) = params.token_amount {
             if amount == 0 {
-                return Err(anyhow!("Amount cannot be zero"));
+                return Err(TradeError::invalid_params("PumpFun", "amount is zero").into());
             }
             amount
         } else {
-            return Err(anyhow!("Amount token is required"));
+            return Err(TradeError::invalid_params("PumpFun", "token amount is not set").into());
         };
 
         let bonding_curve = &protocol_params.bonding_curve;
@@ -169,10 +198,12 @@ impl InstructionBuilder for PumpFunInstructionBuilder {
             token_amount,
         );
 
-        let min_sol_output = calculate_with_slippage_sell(
-            sol_amount,
-            params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
-        );
+        let min_sol_output = params.min_out_floor(sol_amount)?.unwrap_or_else(|| {
+            calculate_with_slippage_sell(
+                sol_amount,
+                params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
+            )
+        });
 
         let bonding_curve_addr = if bonding_curve.account == Pubkey::default() {
             get_bonding_curve_pda(&params.mint).unwrap()
@@ -204,33 +235,18 @@ impl InstructionBuilder for PumpFunInstructionBuilder {
         // ========================================
         let mut instructions = Vec::with_capacity(2);
 
-        let mut sell_data = [0u8; 24];
-        sell_data[..8].copy_from_slice(&[51, 230, 133, 164, 1, 127, 131, 173]); // Method ID
-        sell_data[8..16].copy_from_slice(&token_amount.to_le_bytes());
-        sell_data[16..24].copy_from_slice(&min_sol_output.to_le_bytes());
-
-        let accounts: [AccountMeta; 14] = [
-            global_constants::GLOBAL_ACCOUNT_META,
-            global_constants::FEE_RECIPIENT_META,
-            AccountMeta::new_readonly(params.mint, false),
-            AccountMeta::new(bonding_curve_addr, false),
-            AccountMeta::new(associated_bonding_curve, false),
-            AccountMeta::new(user_token_account, false),
-            AccountMeta::new(params.payer.pubkey(), true),
-            crate::constants::SYSTEM_PROGRAM_META,
-            AccountMeta::new(creator_vault_pda, false),
-            crate::constants::TOKEN_PROGRAM_META,
-            accounts::EVENT_AUTHORITY_META,
-            accounts::PUMPFUN_META,
-            accounts::FEE_CONFIG_META,
-            accounts::FEE_PROGRAM_META,
-        ];
+        let sell_data = PumpFunSellArgs { amount: token_amount, min_sol_output }.encode();
 
-        instructions.push(Instruction::new_with_bytes(
-            accounts::PUMPFUN,
-            &sell_data,
-            accounts.to_vec(),
-        ));
+        let accounts = protocol_params.layout.sell_accounts(&PumpFunTradeAccounts {
+            mint: params.mint,
+            bonding_curve: bonding_curve_addr,
+            associated_bonding_curve,
+            user_token_account,
+            user: params.payer.pubkey(),
+            creator_vault: creator_vault_pda,
+        });
+
+        instructions.push(Instruction::new_with_bytes(accounts::PUMPFUN, &sell_data, accounts));
 
         // Optional: Close token account - COMMENTED OUT TO PREVENT BALANCE ERROR
         // if protocol_params.close_token_account_when_sell.unwrap_or(false) {
@@ -246,3 +262,263 @@ impl InstructionBuilder for PumpFunInstructionBuilder {
         Ok(instructions)
     }
 }
+
+/// PumpFun `create` instruction minting `mint` with `user` as creator
+///
+/// `mint` must sign the transaction together with `user`.
+pub fn build_create_instruction(
+    mint: &Pubkey,
+    user: &Pubkey,
+    name: &str,
+    symbol: &str,
+    uri: &str,
+) -> Result<Instruction> {
+    let bonding_curve =
+        get_bonding_curve_pda(mint).ok_or_else(|| anyhow!("Bonding curve not found"))?;
+    let associated_bonding_curve =
+        crate::common::fast_fn::get_associated_token_address_with_program_id_fast(
+            &bonding_curve,
+            mint,
+            &crate::constants::TOKEN_PROGRAM,
+        );
+    let data = PumpFunCreateArgs {
+        name: name.to_string(),
+        symbol: symbol.to_string(),
+        uri: uri.to_string(),
+        creator: *user,
+    }
+    .encode();
+    let accounts = vec![
+        AccountMeta::new(*mint, true),
+        AccountMeta::new_readonly(get_mint_authority_pda(), false),
+        AccountMeta::new(bonding_curve, false),
+        AccountMeta::new(associated_bonding_curve, false),
+        global_constants::GLOBAL_ACCOUNT_META,
+        AccountMeta::new_readonly(accounts::MPL_TOKEN_METADATA, false),
+        AccountMeta::new(get_metadata_pda(mint), false),
+        AccountMeta::new(*user, true),
+        crate::constants::SYSTEM_PROGRAM_META,
+        crate::constants::TOKEN_PROGRAM_META,
+        AccountMeta::new_readonly(accounts::ASSOCIATED_TOKEN_PROGRAM, false),
+        AccountMeta::new_readonly(solana_sdk::sysvar::rent::ID, false),
+        accounts::EVENT_AUTHORITY_META,
+        accounts::PUMPFUN_META,
+    ];
+    Ok(Instruction::new_with_bytes(accounts::PUMPFUN, &data, accounts))
+}
+
+/// Dev buy of `sol_amount` on a curve created in the same transaction
+///
+/// The curve starts at the initial reserves of the global account, so the token amount is
+/// quoted with `GlobalAccount::get_initial_buy_price`. The volume accumulators are left out
+/// when `include_volume_accumulators` is false, as with `PumpFunParams::with_volume_accumulators`.
+///
+/// # Returns
+/// Returns the instructions and the token amount bought
+pub fn build_dev_buy_instructions(
+    mint: &Pubkey,
+    user: &Pubkey,
+    sol_amount: u64,
+    slippage_basis_points: u64,
+    include_volume_accumulators: bool,
+) -> Result<(Vec<Instruction>, u64)> {
+    if sol_amount == 0 {
+        return Err(TradeError::invalid_params("PumpFun", "amount is zero").into());
+    }
+    let token_amount = GlobalAccount::new().get_initial_buy_price(sol_amount);
+    let max_sol_cost = calculate_with_slippage_buy(sol_amount, slippage_basis_points);
+    let bonding_curve =
+        get_bonding_curve_pda(mint).ok_or_else(|| anyhow!("Bonding curve not found"))?;
+    let creator_vault =
+        get_creator_vault_pda(user).ok_or_else(|| anyhow!("Creator vault not found"))?;
+    let associated_bonding_curve =
+        crate::common::fast_fn::get_associated_token_address_with_program_id_fast(
+            &bonding_curve,
+            mint,
+            &crate::constants::TOKEN_PROGRAM,
+        );
+    let user_token_account =
+        crate::common::fast_fn::get_associated_token_address_with_program_id_fast(
+            user,
+            mint,
+            &crate::constants::TOKEN_PROGRAM,
+        );
+
+    let mut instructions = Vec::with_capacity(2);
+    instructions.extend(crate::common::fast_fn::create_associated_token_account_idempotent_fast(
+        user,
+        user,
+        mint,
+        &crate::constants::TOKEN_PROGRAM,
+    ));
+    let buy_data = PumpFunBuyArgs { amount: token_amount, max_sol_cost }.encode();
+    let accounts = PumpFunLayout::default().buy_accounts_with(
+        &PumpFunTradeAccounts {
+            mint: *mint,
+            bonding_curve,
+            associated_bonding_curve,
+            user_token_account,
+            user: *user,
+            creator_vault,
+        },
+        include_volume_accumulators,
+    );
+    instructions.push(Instruction::new_with_bytes(accounts::PUMPFUN, &buy_data, accounts));
+    Ok((instructions, token_amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey;
+    use spl_associated_token_account::get_associated_token_address;
+
+    const PROGRAM: Pubkey = pubkey!("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P");
+    const GLOBAL: Pubkey = pubkey!("4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf");
+    const FEE_RECIPIENT: Pubkey = pubkey!("62qc2CNXwrYqQScmEdiZFFAnJR262PxWEuNQtxfafNgV");
+    const SYSTEM_PROGRAM: Pubkey = pubkey!("11111111111111111111111111111111");
+    const TOKEN_PROGRAM: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+    const EVENT_AUTHORITY: Pubkey = pubkey!("Ce6TQqeHC9p8KetsN6JsjHK7UTZk7nasjjnr7XxXp9F1");
+    const GLOBAL_VOLUME_ACCUMULATOR: Pubkey =
+        pubkey!("Hq2wp8uJ9jCPsYgNHex8RtqdvMPfVGoYwjvF1ATiwn2Y");
+    const FEE_CONFIG: Pubkey = pubkey!("8Wf5TiAheLUqBrKXeYg2JtAFFMWtKdG2BSFgqUcPVwTt");
+    const FEE_PROGRAM: Pubkey = pubkey!("pfeeUxB6jkeY1Hxd7CsFCAjcbHA9rWtchMGdZ6VojVZ");
+
+    /// Account list of a mainnet PumpFun buy, the accumulators at indexes 12 and 13
+    fn mainnet_buy_accounts(
+        mint: &Pubkey,
+        user: &Pubkey,
+        include_volume_accumulators: bool,
+    ) -> Vec<AccountMeta> {
+        let pda = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &PROGRAM).0;
+        let bonding_curve = pda(&[b"bonding-curve", mint.as_ref()]);
+        let mut metas = vec![
+            AccountMeta::new_readonly(GLOBAL, false),
+            AccountMeta::new(FEE_RECIPIENT, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(bonding_curve, false),
+            AccountMeta::new(get_associated_token_address(&bonding_curve, mint), false),
+            AccountMeta::new(get_associated_token_address(user, mint), false),
+            AccountMeta::new(*user, true),
+            AccountMeta::new_readonly(SYSTEM_PROGRAM, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM, false),
+            AccountMeta::new(pda(&[b"creator-vault", user.as_ref()]), false),
+            AccountMeta::new_readonly(EVENT_AUTHORITY, false),
+            AccountMeta::new_readonly(PROGRAM, false),
+        ];
+        if include_volume_accumulators {
+            metas.extend([
+                AccountMeta::new(GLOBAL_VOLUME_ACCUMULATOR, false),
+                AccountMeta::new(pda(&[b"user_volume_accumulator", user.as_ref()]), false),
+            ]);
+        }
+        metas.extend([
+            AccountMeta::new_readonly(FEE_CONFIG, false),
+            AccountMeta::new_readonly(FEE_PROGRAM, false),
+        ]);
+        metas
+    }
+
+    /// Buy data for 1 SOL at the initial reserves with 1% slippage
+    fn mainnet_buy_data() -> Vec<u8> {
+        let mut data = vec![102, 6, 61, 18, 1, 218, 235, 234];
+        data.extend(34_612_903_225_806u64.to_le_bytes());
+        data.extend(1_010_000_000u64.to_le_bytes());
+        data
+    }
+
+    fn dev_buy(include_volume_accumulators: bool) -> (Pubkey, Pubkey, Instruction) {
+        let mint = Pubkey::new_from_array([1; 32]);
+        let user = Pubkey::new_from_array([2; 32]);
+        let (instructions, token_amount) = build_dev_buy_instructions(
+            &mint,
+            &user,
+            1_000_000_000,
+            100,
+            include_volume_accumulators,
+        )
+        .unwrap();
+        assert_eq!(token_amount, 34_612_903_225_806);
+        (mint, user, instructions.last().unwrap().clone())
+    }
+
+    #[test]
+    fn dev_buy_matches_the_mainnet_buy_with_accumulators() {
+        let (mint, user, buy) = dev_buy(true);
+        assert_eq!(buy.program_id, PROGRAM);
+        assert_eq!(buy.data, mainnet_buy_data());
+        assert_eq!(buy.accounts.len(), 16);
+        assert_eq!(buy.accounts, mainnet_buy_accounts(&mint, &user, true));
+    }
+
+    #[test]
+    fn dev_buy_matches_the_mainnet_buy_without_accumulators() {
+        let (mint, user, buy) = dev_buy(false);
+        assert_eq!(buy.data, mainnet_buy_data());
+        assert_eq!(buy.accounts.len(), 14);
+        assert_eq!(buy.accounts, mainnet_buy_accounts(&mint, &user, false));
+    }
+
+    /// Bonding curve of `mint` at the initial reserves
+    fn curve_params(mint: &Pubkey) -> PumpFunParams {
+        let creator = Pubkey::new_unique();
+        PumpFunParams {
+            bonding_curve: std::sync::Arc::new(
+                crate::common::bonding_curve::BondingCurveAccount::from_dev_trade(
+                    mint, 0, 0, creator,
+                ),
+            ),
+            ..PumpFunParams::immediate_sell(get_creator_vault_pda(&creator).unwrap(), false)
+        }
+    }
+
+    fn sell_params(token_amount: Option<u64>) -> SellParams {
+        let mint = Pubkey::new_unique();
+        SellParams::offline(mint, token_amount, Box::new(curve_params(&mint)))
+    }
+
+    #[tokio::test]
+    async fn sell_builds_without_rpc() {
+        let params = sell_params(Some(1_000_000));
+        assert!(params.rpc.is_none());
+        let instructions =
+            PumpFunInstructionBuilder.build_sell_instructions(&params).await.unwrap();
+        let sell = instructions.iter().find_map(|ix| PumpFunSellArgs::decode(&ix.data)).unwrap();
+        assert_eq!(sell.amount, 1_000_000);
+    }
+
+    #[tokio::test]
+    async fn sell_needs_a_resolved_amount() {
+        let params = sell_params(None);
+        assert!(PumpFunInstructionBuilder.build_sell_instructions(&params).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn min_out_floors_are_encoded_exactly() {
+        const FLOOR: u64 = 1_000;
+        let mint = Pubkey::new_unique();
+        let swap_data = |instructions: Vec<Instruction>| {
+            instructions.into_iter().find(|ix| ix.program_id == PROGRAM).unwrap().data
+        };
+
+        let mut buy = BuyParams::offline(mint, 1_000_000, Box::new(curve_params(&mint)));
+        buy.min_amount_out = Some(FLOOR);
+        let data = swap_data(PumpFunInstructionBuilder.build_buy_instructions(&buy).await.unwrap());
+        assert!(
+            data.starts_with(&PumpFunBuyArgs { amount: FLOOR, max_sol_cost: 1_000_000 }.encode())
+        );
+        buy.min_amount_out = Some(u64::MAX);
+        assert!(PumpFunInstructionBuilder.build_buy_instructions(&buy).await.is_err());
+
+        let mut sell =
+            SellParams::offline(mint, Some(1_000_000_000), Box::new(curve_params(&mint)));
+        sell.min_sol_out = Some(FLOOR);
+        let data =
+            swap_data(PumpFunInstructionBuilder.build_sell_instructions(&sell).await.unwrap());
+        assert!(data.starts_with(
+            &PumpFunSellArgs { amount: 1_000_000_000, min_sol_output: FLOOR }.encode()
+        ));
+        sell.min_sol_out = Some(u64::MAX);
+        assert!(PumpFunInstructionBuilder.build_sell_instructions(&sell).await.is_err());
+    }
+}
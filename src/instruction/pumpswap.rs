@@ -1,23 +1,25 @@
 use crate::{
+    common::fee_accounts::{fee_account, FeeAccount},
     constants::trade::trade::DEFAULT_SLIPPAGE,
+    instruction::data::{InstructionData, PumpSwapBuyArgs, PumpSwapSellArgs},
     instruction::utils::pumpswap::{
-        accounts, fee_recipient_ata, get_user_volume_accumulator_pda, BUY_DISCRIMINATOR,
-        SELL_DISCRIMINATOR,
+        accounts, coin_creator_vault_accounts, fee_recipient_ata, get_user_volume_accumulator_pda,
     },
+    params::PumpSwapParams,
     trading::{
+        common::wsol_manager::{EphemeralWsolAccount, WsolHandling},
         core::{
-            params::{BuyParams, PumpSwapParams, SellParams},
+            error::TradeError,
+            params::{BuyAmountSpec, BuyParams, SellParams},
             traits::InstructionBuilder,
         },
     },
-    utils::calc::pumpswap::{buy_quote_input_internal, sell_base_input_internal},
+    utils::calc::pumpswap::{
+        buy_base_input_internal, buy_quote_input_internal, sell_base_input_internal,
+    },
 };
 use anyhow::{anyhow, Result};
-use solana_sdk::{
-    instruction::Instruction,
-    pubkey::Pubkey,
-    signer::Signer,
-};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
 use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
 use solana_system_interface::instruction::transfer;
 
@@ -32,10 +34,10 @@ impl InstructionBuilder for PumpSwapInstructionBuilder {
             .protocol_params
             .as_any()
             .downcast_ref::<PumpSwapParams>()
-            .ok_or_else(|| anyhow!("Invalid protocol params for PumpSwap"))?;
+            .ok_or_else(|| TradeError::params_mismatch("PumpSwap"))?;
 
         if params.sol_amount == 0 {
-            return Err(anyhow!("Amount cannot be zero"));
+            return Err(TradeError::invalid_params("PumpSwap", "amount is zero").into());
         }
 
         // Build instructions based on whether account information is provided (like backup)
@@ -52,9 +54,15 @@ impl InstructionBuilder for PumpSwapInstructionBuilder {
             pool_base_token_reserves,
             pool_quote_token_reserves,
             protocol_params.creator,
-            protocol_params.auto_handle_wsol,
+            WsolHandling::new(
+                params.create_wsol_ata,
+                params.close_wsol_ata,
+                protocol_params.auto_handle_wsol,
+            ),
             protocol_params.fee_config,
             protocol_params.fee_program,
+            protocol_params.base_token_program(),
+            protocol_params.quote_token_program(),
         )
         .await
     }
@@ -65,7 +73,7 @@ impl InstructionBuilder for PumpSwapInstructionBuilder {
             .protocol_params
             .as_any()
             .downcast_ref::<PumpSwapParams>()
-            .ok_or_else(|| anyhow!("Invalid protocol params for PumpSwap"))?;
+            .ok_or_else(|| TradeError::params_mismatch("PumpSwap"))?;
         // Build instructions based on whether account information is provided (like backup)
         let base_mint = protocol_params.base_mint;
         let quote_mint = protocol_params.quote_mint;
@@ -80,9 +88,15 @@ impl InstructionBuilder for PumpSwapInstructionBuilder {
             pool_base_token_reserves,
             pool_quote_token_reserves,
             protocol_params.creator,
-            protocol_params.auto_handle_wsol,
+            WsolHandling::new(
+                params.create_wsol_ata,
+                params.close_wsol_ata,
+                protocol_params.auto_handle_wsol,
+            ),
             protocol_params.fee_config,
             protocol_params.fee_program,
+            protocol_params.base_token_program(),
+            protocol_params.quote_token_program(),
         )
         .await
     }
@@ -99,20 +113,47 @@ impl PumpSwapInstructionBuilder {
         pool_base_token_reserves: u64,
         pool_quote_token_reserves: u64,
         creator: Pubkey,
-        auto_handle_wsol: bool,
+        wsol: WsolHandling,
         fee_config: Pubkey,
         fee_program: Pubkey,
+        base_token_program: Pubkey,
+        quote_token_program: Pubkey,
     ) -> Result<Vec<Instruction>> {
-        
-        // RPC validation like backup
-        if params.rpc.is_none() {
-            return Err(anyhow!("RPC is not set"));
-        }
         let quote_mint_is_wsol = quote_mint == crate::constants::WSOL_TOKEN_ACCOUNT;
 
         let token_amount;
         let sol_amount;
-        if quote_mint_is_wsol {
+        if let BuyAmountSpec::ExactTokensOut { token_amount: base_amount_out, max_sol } =
+            params.amount_spec
+        {
+            if !quote_mint_is_wsol {
+                return Err(anyhow!(
+                    "PumpSwap exact-out buys need a pool quoted in wSOL, pool {} has wSOL as base",
+                    pool
+                ));
+            }
+            params.min_out_floor(base_amount_out)?;
+            let result = buy_base_input_internal(
+                base_amount_out,
+                params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
+                pool_base_token_reserves,
+                pool_quote_token_reserves,
+                &creator,
+            )
+            .map_err(|e| anyhow!(e))?;
+            if result.ui_quote > max_sol {
+                return Err(anyhow!(
+                    "Buying {} tokens costs {} lamports, more than max_sol {}",
+                    base_amount_out,
+                    result.ui_quote,
+                    max_sol
+                ));
+            }
+            // base_amount_out
+            token_amount = base_amount_out;
+            // max_quote_amount_in, the slippage bound capped at max_sol
+            sol_amount = result.max_quote.min(max_sol);
+        } else if quote_mint_is_wsol {
             let result = buy_quote_input_internal(
                 params.sol_amount,
                 params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
@@ -121,10 +162,20 @@ impl PumpSwapInstructionBuilder {
                 &creator,
             )
             .unwrap();
-            // base_amount_out
-            token_amount = result.base;
-            // max_quote_amount_in
-            sol_amount = result.max_quote;
+            match params.min_out_floor(result.base)? {
+                // The instruction names the tokens out, the floor is bought for at most
+                // sol_amount
+                Some(min_amount_out) => {
+                    token_amount = min_amount_out;
+                    sol_amount = params.sol_amount;
+                }
+                None => {
+                    // base_amount_out
+                    token_amount = result.base;
+                    // max_quote_amount_in
+                    sol_amount = result.max_quote;
+                }
+            }
         } else {
             let result = sell_base_input_internal(
                 params.sol_amount,
@@ -135,39 +186,58 @@ impl PumpSwapInstructionBuilder {
             )
             .unwrap();
             // min_quote_amount_out
-            token_amount = result.min_quote;
+            token_amount = params.min_out_floor(result.ui_quote)?.unwrap_or(result.min_quote);
             // base_amount_in
             sol_amount = params.sol_amount;
         }
 
         // Create user token accounts (derive like backup)
-        let user_base_token_account = spl_associated_token_account::get_associated_token_address(
-            &params.payer.pubkey(),
-            &base_mint,
-        );
-        let user_quote_token_account = spl_associated_token_account::get_associated_token_address(
+        let mut user_base_token_account =
+            spl_associated_token_account::get_associated_token_address_with_program_id(
+                &params.payer.pubkey(),
+                &base_mint,
+                &base_token_program,
+            );
+        let mut user_quote_token_account =
+            spl_associated_token_account::get_associated_token_address_with_program_id(
+                &params.payer.pubkey(),
+                &quote_mint,
+                &quote_token_program,
+            );
+        // Route the wSOL side of the swap through a per-trade account when requested
+        let ephemeral_wsol = EphemeralWsolAccount::for_trade(
             &params.payer.pubkey(),
-            &quote_mint,
+            params.wsol_account,
+            wsol.allows_ephemeral(),
         );
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            if quote_mint_is_wsol {
+                user_quote_token_account = ephemeral_wsol.address;
+            } else {
+                user_base_token_account = ephemeral_wsol.address;
+            }
+        }
 
         // Get pool token accounts (derive like backup) 
         let pool_base_token_account =
             spl_associated_token_account::get_associated_token_address_with_program_id(
                 &pool,
                 &base_mint,
-                &crate::constants::TOKEN_PROGRAM,
+                &base_token_program,
             );
 
         let pool_quote_token_account =
             spl_associated_token_account::get_associated_token_address_with_program_id(
                 &pool,
                 &quote_mint,
-                &crate::constants::TOKEN_PROGRAM,
+                &quote_token_program,
             );
 
         let mut instructions = vec![];
 
-        if auto_handle_wsol {
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.extend(ephemeral_wsol.open(&params.payer.pubkey(), sol_amount)?);
+        } else if wsol.create {
             // Handle wSOL (like backup)
             instructions.push(
                 // Create wSOL ATA account if it doesn't exist
@@ -205,18 +275,24 @@ impl PumpSwapInstructionBuilder {
             );
         }
 
-        // Create user's base token account (use hardcoded token program like backup)
+        // Create user's token account for the non-wSOL side, with the token program of its mint
+        let (mint, mint_token_program) = if quote_mint_is_wsol {
+            (&base_mint, &base_token_program)
+        } else {
+            (&quote_mint, &quote_token_program)
+        };
         instructions.push(create_associated_token_account_idempotent(
             &params.payer.pubkey(),
             &params.payer.pubkey(),
-            if quote_mint_is_wsol { &base_mint } else { &quote_mint },
-            &crate::constants::TOKEN_PROGRAM, // ✅ HARDCODED like backup
+            mint,
+            mint_token_program,
         ));
 
-        // Derive creator vault accounts (like backup)
-        let coin_creator_vault_ata = crate::instruction::utils::pumpswap::coin_creator_vault_ata(creator, quote_mint);
-        let coin_creator_vault_authority = crate::instruction::utils::pumpswap::coin_creator_vault_authority(creator);
-        let fee_recipient_ata = fee_recipient_ata(accounts::FEE_RECIPIENT, quote_mint);
+        // Creator vault accounts, pools without a coin creator use the cached default-creator vault
+        let (coin_creator_vault_ata, coin_creator_vault_authority) =
+            coin_creator_vault_accounts(creator, quote_mint, &quote_token_program);
+        let fee_recipient = fee_account(FeeAccount::PumpSwapFeeRecipient);
+        let fee_recipient_ata = fee_recipient_ata(fee_recipient, quote_mint, &quote_token_program);
 
         // Create buy instruction (like backup)
         let mut accounts = vec![
@@ -229,10 +305,10 @@ impl PumpSwapInstructionBuilder {
             solana_sdk::instruction::AccountMeta::new(user_quote_token_account, false), // user_quote_token_account
             solana_sdk::instruction::AccountMeta::new(pool_base_token_account, false), // pool_base_token_account
             solana_sdk::instruction::AccountMeta::new(pool_quote_token_account, false), // pool_quote_token_account
-            solana_sdk::instruction::AccountMeta::new_readonly(accounts::FEE_RECIPIENT, false), // fee_recipient (readonly)
+            solana_sdk::instruction::AccountMeta::new_readonly(fee_recipient, false), // fee_recipient (readonly)
             solana_sdk::instruction::AccountMeta::new(fee_recipient_ata, false), // fee_recipient_ata
-            solana_sdk::instruction::AccountMeta::new_readonly(crate::constants::TOKEN_PROGRAM, false), // TOKEN_PROGRAM_ID (readonly) - HARDCODED
-            solana_sdk::instruction::AccountMeta::new_readonly(crate::constants::TOKEN_PROGRAM, false), // TOKEN_PROGRAM_ID (readonly, duplicated as in JS) - HARDCODED
+            solana_sdk::instruction::AccountMeta::new_readonly(base_token_program, false), // base_token_program (readonly)
+            solana_sdk::instruction::AccountMeta::new_readonly(quote_token_program, false), // quote_token_program (readonly)
             solana_sdk::instruction::AccountMeta::new_readonly(crate::constants::SYSTEM_PROGRAM, false), // System Program (readonly)
             solana_sdk::instruction::AccountMeta::new_readonly(
                 accounts::ASSOCIATED_TOKEN_PROGRAM,
@@ -240,7 +316,7 @@ impl PumpSwapInstructionBuilder {
             ), // ASSOCIATED_TOKEN_PROGRAM_ID (readonly)
             solana_sdk::instruction::AccountMeta::new_readonly(accounts::EVENT_AUTHORITY, false), // event_authority (readonly)
             solana_sdk::instruction::AccountMeta::new_readonly(accounts::AMM_PROGRAM, false), // PUMP_AMM_PROGRAM_ID (readonly)
-            solana_sdk::instruction::AccountMeta::new(coin_creator_vault_ata, false), // coin_creator_vault_ata - DERIVED 
+            solana_sdk::instruction::AccountMeta::new(coin_creator_vault_ata, false), // coin_creator_vault_ata - DERIVED
             solana_sdk::instruction::AccountMeta::new_readonly(coin_creator_vault_authority, false), // coin_creator_vault_authority (readonly) - DERIVED
         ];
         if quote_mint_is_wsol {
@@ -257,28 +333,23 @@ impl PumpSwapInstructionBuilder {
         accounts.push(solana_sdk::instruction::AccountMeta::new_readonly(fee_program, false));
 
         // Create instruction data
-        let mut data = [0u8; 24];
-        if quote_mint_is_wsol {
-            data[..8].copy_from_slice(&BUY_DISCRIMINATOR);
-            // base_amount_out
-            data[8..16].copy_from_slice(&token_amount.to_le_bytes());
-            // max_quote_amount_in
-            data[16..24].copy_from_slice(&sol_amount.to_le_bytes());
+        let data = if quote_mint_is_wsol {
+            PumpSwapBuyArgs { base_amount_out: token_amount, max_quote_amount_in: sol_amount }
+                .encode()
         } else {
-            data[..8].copy_from_slice(&SELL_DISCRIMINATOR);
-            // base_amount_in
-            data[8..16].copy_from_slice(&sol_amount.to_le_bytes());
-            // min_quote_amount_out
-            data[16..24].copy_from_slice(&token_amount.to_le_bytes());
-        }
+            PumpSwapSellArgs { base_amount_in: sol_amount, min_quote_amount_out: token_amount }
+                .encode()
+        };
 
         instructions.push(Instruction {
             program_id: accounts::AMM_PROGRAM,
             accounts,
-            data: data.to_vec(),
+            data,
         });
         
-        if auto_handle_wsol {
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.push(ephemeral_wsol.close(&params.payer.pubkey()));
+        } else if wsol.close {
             // Close wSOL ATA account, reclaim any leftover SOL after buy
             instructions.push(
                 spl_token::instruction::close_account(
@@ -309,17 +380,14 @@ impl PumpSwapInstructionBuilder {
         pool_base_token_reserves: u64,
         pool_quote_token_reserves: u64,
         creator: Pubkey,
-        auto_handle_wsol: bool,
+        wsol: WsolHandling,
         fee_config: Pubkey,
         fee_program: Pubkey,
+        base_token_program: Pubkey,
+        quote_token_program: Pubkey,
     ) -> Result<Vec<Instruction>> {
-        
-        // RPC validation like backup
-        if params.rpc.is_none() {
-            return Err(anyhow!("RPC is not set"));
-        }
         if params.token_amount.is_none() {
-            return Err(anyhow!("Token amount is not set"));
+            return Err(TradeError::invalid_params("PumpSwap", "amount is zero").into());
         }
 
         let quote_mint_is_wsol = quote_mint == crate::constants::WSOL_TOKEN_ACCOUNT;
@@ -336,7 +404,7 @@ impl PumpSwapInstructionBuilder {
             )
             .unwrap();
             // min_quote_amount_out
-            sol_amount = result.min_quote;
+            sol_amount = params.min_out_floor(result.ui_quote)?.unwrap_or(result.min_quote);
             // base_amount_in
             token_amount = params.token_amount.unwrap();
         } else {
@@ -348,57 +416,84 @@ impl PumpSwapInstructionBuilder {
                 &creator,
             )
             .unwrap();
-            // base_amount_out
-            sol_amount = result.base;
+            // base_amount_out, the floor is bought for at most the tokens sold when set
+            sol_amount = params.min_out_floor(result.base)?.unwrap_or(result.base);
             token_amount = params.token_amount.unwrap();
         }
 
         // Create user token accounts (derive like backup)
-        let user_base_token_account = spl_associated_token_account::get_associated_token_address(
-            &params.payer.pubkey(),
-            &base_mint,
-        );
-        let user_quote_token_account = spl_associated_token_account::get_associated_token_address(
+        let mut user_base_token_account =
+            spl_associated_token_account::get_associated_token_address_with_program_id(
+                &params.payer.pubkey(),
+                &base_mint,
+                &base_token_program,
+            );
+        let mut user_quote_token_account =
+            spl_associated_token_account::get_associated_token_address_with_program_id(
+                &params.payer.pubkey(),
+                &quote_mint,
+                &quote_token_program,
+            );
+        // Route the wSOL side of the swap through a per-trade account when requested
+        let ephemeral_wsol = EphemeralWsolAccount::for_trade(
             &params.payer.pubkey(),
-            &quote_mint,
+            params.wsol_account,
+            wsol.allows_ephemeral(),
         );
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            if quote_mint_is_wsol {
+                user_quote_token_account = ephemeral_wsol.address;
+            } else {
+                user_base_token_account = ephemeral_wsol.address;
+            }
+        }
 
         // Get pool token accounts (derive like backup)
         let pool_base_token_account =
             spl_associated_token_account::get_associated_token_address_with_program_id(
                 &pool,
                 &base_mint,
-                &crate::constants::TOKEN_PROGRAM,
+                &base_token_program,
             );
 
         let pool_quote_token_account =
             spl_associated_token_account::get_associated_token_address_with_program_id(
                 &pool,
                 &quote_mint,
-                &crate::constants::TOKEN_PROGRAM,
+                &quote_token_program,
             );
 
-        // Derive creator vault accounts (like backup)
-        let coin_creator_vault_ata = crate::instruction::utils::pumpswap::coin_creator_vault_ata(creator, quote_mint);
-        let coin_creator_vault_authority = crate::instruction::utils::pumpswap::coin_creator_vault_authority(creator);
-        let fee_recipient_ata = fee_recipient_ata(accounts::FEE_RECIPIENT, quote_mint);
+        // Creator vault accounts, pools without a coin creator use the cached default-creator vault
+        let (coin_creator_vault_ata, coin_creator_vault_authority) =
+            coin_creator_vault_accounts(creator, quote_mint, &quote_token_program);
+        let fee_recipient = fee_account(FeeAccount::PumpSwapFeeRecipient);
+        let fee_recipient_ata = fee_recipient_ata(fee_recipient, quote_mint, &quote_token_program);
 
         let mut instructions = Vec::with_capacity(5);
 
-        // Always create WSOL ATA for sells (like backup)
-        instructions.push(create_associated_token_account_idempotent(
-            &params.payer.pubkey(),
-            &params.payer.pubkey(),
-            &crate::constants::WSOL_TOKEN_ACCOUNT,
-            &crate::constants::TOKEN_PROGRAM,
-        ));
+        // Create the WSOL ATA to receive the proceeds, or the per-trade account replacing it
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.extend(ephemeral_wsol.open(&params.payer.pubkey(), 0)?);
+        } else if wsol.create {
+            instructions.push(create_associated_token_account_idempotent(
+                &params.payer.pubkey(),
+                &params.payer.pubkey(),
+                &crate::constants::WSOL_TOKEN_ACCOUNT,
+                &crate::constants::TOKEN_PROGRAM,
+            ));
+        }
 
-        // Create user's base token account (use hardcoded token program like backup)
+        // Create user's token account for the non-wSOL side, with the token program of its mint
+        let (mint, mint_token_program) = if quote_mint_is_wsol {
+            (&base_mint, &base_token_program)
+        } else {
+            (&quote_mint, &quote_token_program)
+        };
         instructions.push(create_associated_token_account_idempotent(
             &params.payer.pubkey(),
             &params.payer.pubkey(),
-            if quote_mint_is_wsol { &base_mint } else { &quote_mint },
-            &crate::constants::TOKEN_PROGRAM, // ✅ HARDCODED like backup
+            mint,
+            mint_token_program,
         ));
 
         // Create sell instruction (like backup)
@@ -412,10 +507,10 @@ impl PumpSwapInstructionBuilder {
             solana_sdk::instruction::AccountMeta::new(user_quote_token_account, false), // user_quote_token_account
             solana_sdk::instruction::AccountMeta::new(pool_base_token_account, false), // pool_base_token_account
             solana_sdk::instruction::AccountMeta::new(pool_quote_token_account, false), // pool_quote_token_account
-            solana_sdk::instruction::AccountMeta::new_readonly(accounts::FEE_RECIPIENT, false), // fee_recipient (readonly)
+            solana_sdk::instruction::AccountMeta::new_readonly(fee_recipient, false), // fee_recipient (readonly)
             solana_sdk::instruction::AccountMeta::new(fee_recipient_ata, false), // fee_recipient_ata
-            solana_sdk::instruction::AccountMeta::new_readonly(crate::constants::TOKEN_PROGRAM, false), // TOKEN_PROGRAM_ID (readonly) - HARDCODED
-            solana_sdk::instruction::AccountMeta::new_readonly(crate::constants::TOKEN_PROGRAM, false), // TOKEN_PROGRAM_ID (readonly, duplicated as in JS) - HARDCODED
+            solana_sdk::instruction::AccountMeta::new_readonly(base_token_program, false), // base_token_program (readonly)
+            solana_sdk::instruction::AccountMeta::new_readonly(quote_token_program, false), // quote_token_program (readonly)
             solana_sdk::instruction::AccountMeta::new_readonly(crate::constants::SYSTEM_PROGRAM, false), // System Program (readonly)
             solana_sdk::instruction::AccountMeta::new_readonly(
                 accounts::ASSOCIATED_TOKEN_PROGRAM,
@@ -441,28 +536,23 @@ impl PumpSwapInstructionBuilder {
         accounts.push(solana_sdk::instruction::AccountMeta::new_readonly(fee_program, false));
 
         // Create instruction data
-        let mut data = [0u8; 24];
-        if quote_mint_is_wsol {
-            data[..8].copy_from_slice(&SELL_DISCRIMINATOR);
-            // base_amount_in
-            data[8..16].copy_from_slice(&token_amount.to_le_bytes());
-            // min_quote_amount_out
-            data[16..24].copy_from_slice(&sol_amount.to_le_bytes());
+        let data = if quote_mint_is_wsol {
+            PumpSwapSellArgs { base_amount_in: token_amount, min_quote_amount_out: sol_amount }
+                .encode()
         } else {
-            data[..8].copy_from_slice(&BUY_DISCRIMINATOR);
-            // base_amount_out
-            data[8..16].copy_from_slice(&sol_amount.to_le_bytes());
-            // max_quote_amount_in
-            data[16..24].copy_from_slice(&token_amount.to_le_bytes());
-        }
+            PumpSwapBuyArgs { base_amount_out: sol_amount, max_quote_amount_in: token_amount }
+                .encode()
+        };
 
         instructions.push(Instruction {
             program_id: accounts::AMM_PROGRAM,
             accounts,
-            data: data.to_vec(),
+            data,
         });
         
-        if auto_handle_wsol {
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.push(ephemeral_wsol.close(&params.payer.pubkey()));
+        } else if wsol.close {
             // Close wSOL ATA account after sell to convert WSOL back to SOL (like backup)
             instructions.push(
                 spl_token::instruction::close_account(
@@ -482,4 +572,175 @@ impl PumpSwapInstructionBuilder {
         
         Ok(instructions)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::common::wsol_manager::{
+        ephemeral_wsol_addresses, wsol_steps, WsolAccountChoice, WSOL_FLAG_CASES,
+    };
+
+    /// Pool of `mint` quoted in wSOL
+    fn pool_params(mint: Pubkey) -> PumpSwapParams {
+        PumpSwapParams {
+            pool: Pubkey::new_unique(),
+            base_mint: mint,
+            quote_mint: crate::constants::WSOL_TOKEN_ACCOUNT,
+            pool_base_token_reserves: 200_000_000_000_000,
+            pool_quote_token_reserves: 80_000_000_000,
+            base_token_program: crate::constants::TOKEN_PROGRAM,
+            quote_token_program: crate::constants::TOKEN_PROGRAM,
+            creator: Pubkey::new_unique(),
+            auto_handle_wsol: true,
+            fee_config: Pubkey::new_unique(),
+            fee_program: accounts::FEE_PROGRAM,
+        }
+    }
+
+    fn sell_params(token_amount: Option<u64>) -> SellParams {
+        let mint = Pubkey::new_unique();
+        SellParams::offline(mint, token_amount, Box::new(pool_params(mint)))
+    }
+
+    #[tokio::test]
+    async fn sell_builds_without_rpc() {
+        let params = sell_params(Some(1_000_000));
+        assert!(params.rpc.is_none());
+        let instructions =
+            PumpSwapInstructionBuilder.build_sell_instructions(&params).await.unwrap();
+        let sell = instructions.iter().find_map(|ix| PumpSwapSellArgs::decode(&ix.data)).unwrap();
+        assert_eq!(sell.base_amount_in, 1_000_000);
+    }
+
+    #[tokio::test]
+    async fn sell_needs_a_resolved_amount() {
+        let params = sell_params(None);
+        assert!(PumpSwapInstructionBuilder.build_sell_instructions(&params).await.is_err());
+    }
+
+    /// wSOL accounts of `instructions`: the one the swap uses, whether the payer's wSOL ATA
+    /// appears anywhere, and the account closed last
+    fn wsol_accounts(instructions: &[Instruction], payer: &Pubkey) -> (Pubkey, bool, Pubkey) {
+        let wsol_ata = spl_associated_token_account::get_associated_token_address(
+            payer,
+            &crate::constants::WSOL_TOKEN_ACCOUNT,
+        );
+        let swap = instructions.iter().find(|ix| ix.program_id == accounts::AMM_PROGRAM).unwrap();
+        // user_quote_token_account of a pool quoted in wSOL
+        let swap_account = swap.accounts[6].pubkey;
+        let uses_ata =
+            instructions.iter().any(|ix| ix.accounts.iter().any(|meta| meta.pubkey == wsol_ata));
+        let close = instructions.last().unwrap();
+        assert_eq!(close.program_id, crate::constants::TOKEN_PROGRAM);
+        assert_eq!(close.data, [9], "CloseAccount");
+        (swap_account, uses_ata, close.accounts[0].pubkey)
+    }
+
+    #[tokio::test]
+    async fn ephemeral_wsol_leaves_the_wsol_ata_untouched() {
+        crate::common::seed::set_rent(false, 2_039_280);
+        let mint = Pubkey::new_unique();
+        let mut buy = BuyParams::offline(mint, 1_000_000, Box::new(pool_params(mint)));
+        buy.wsol_account = WsolAccountChoice::EphemeralPerTrade;
+        let mut sell = SellParams::offline(mint, Some(1_000_000), Box::new(pool_params(mint)));
+        sell.wsol_account = WsolAccountChoice::EphemeralPerTrade;
+
+        let buy_instructions =
+            PumpSwapInstructionBuilder.build_buy_instructions(&buy).await.unwrap();
+        let sell_instructions =
+            PumpSwapInstructionBuilder.build_sell_instructions(&sell).await.unwrap();
+
+        for (instructions, payer) in
+            [(&buy_instructions, buy.payer.pubkey()), (&sell_instructions, sell.payer.pubkey())]
+        {
+            let (swap_account, uses_ata, closed) = wsol_accounts(instructions, &payer);
+            assert!(!uses_ata);
+            assert!(ephemeral_wsol_addresses(&payer).contains(&swap_account));
+            // Created first and closed last, in the same transaction
+            assert_eq!(instructions[0].program_id, crate::constants::SYSTEM_PROGRAM);
+            assert_eq!(instructions[0].accounts[1].pubkey, swap_account);
+            assert_eq!(closed, swap_account);
+        }
+    }
+
+    #[tokio::test]
+    async fn canonical_wsol_uses_and_closes_the_wsol_ata() {
+        let mint = Pubkey::new_unique();
+        let buy = BuyParams::offline(mint, 1_000_000, Box::new(pool_params(mint)));
+        assert_eq!(buy.wsol_account, WsolAccountChoice::CanonicalAta);
+
+        let instructions = PumpSwapInstructionBuilder.build_buy_instructions(&buy).await.unwrap();
+
+        let payer = buy.payer.pubkey();
+        let (swap_account, uses_ata, closed) = wsol_accounts(&instructions, &payer);
+        assert!(uses_ata);
+        assert_eq!(
+            swap_account,
+            spl_associated_token_account::get_associated_token_address(
+                &payer,
+                &crate::constants::WSOL_TOKEN_ACCOUNT
+            )
+        );
+        assert_eq!(closed, swap_account);
+    }
+
+    #[tokio::test]
+    async fn wsol_flags_shape_the_wsol_steps() {
+        for &((create, close, auto_handle_wsol), buy_steps, sell_steps) in WSOL_FLAG_CASES {
+            let flags = (create, close, auto_handle_wsol);
+            let mint = Pubkey::new_unique();
+            let protocol_params = PumpSwapParams { auto_handle_wsol, ..pool_params(mint) };
+            let mut buy = BuyParams::offline(mint, 1_000_000, Box::new(protocol_params.clone()));
+            (buy.create_wsol_ata, buy.close_wsol_ata) = (create, close);
+            let mut sell = SellParams::offline(mint, Some(1_000_000), Box::new(protocol_params));
+            (sell.create_wsol_ata, sell.close_wsol_ata) = (create, close);
+
+            let instructions =
+                PumpSwapInstructionBuilder.build_buy_instructions(&buy).await.unwrap();
+            assert_eq!(
+                wsol_steps(&instructions, &buy.payer.pubkey()),
+                buy_steps,
+                "buy {:?}",
+                flags
+            );
+            let instructions =
+                PumpSwapInstructionBuilder.build_sell_instructions(&sell).await.unwrap();
+            assert_eq!(
+                wsol_steps(&instructions, &sell.payer.pubkey()),
+                sell_steps,
+                "sell {:?}",
+                flags
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn min_out_floors_are_encoded_exactly() {
+        const FLOOR: u64 = 1_000;
+        let mint = Pubkey::new_unique();
+        let swap_data = |instructions: Vec<Instruction>| {
+            instructions.into_iter().find(|ix| ix.program_id == accounts::AMM_PROGRAM).unwrap().data
+        };
+
+        let mut buy = BuyParams::offline(mint, 1_000_000, Box::new(pool_params(mint)));
+        buy.min_amount_out = Some(FLOOR);
+        let data =
+            swap_data(PumpSwapInstructionBuilder.build_buy_instructions(&buy).await.unwrap());
+        assert!(data.starts_with(
+            &PumpSwapBuyArgs { base_amount_out: FLOOR, max_quote_amount_in: 1_000_000 }.encode()
+        ));
+        buy.min_amount_out = Some(u64::MAX);
+        assert!(PumpSwapInstructionBuilder.build_buy_instructions(&buy).await.is_err());
+
+        let mut sell = SellParams::offline(mint, Some(1_000_000_000), Box::new(pool_params(mint)));
+        sell.min_sol_out = Some(FLOOR);
+        let data =
+            swap_data(PumpSwapInstructionBuilder.build_sell_instructions(&sell).await.unwrap());
+        assert!(data.starts_with(
+            &PumpSwapSellArgs { base_amount_in: 1_000_000_000, min_quote_amount_out: FLOOR }
+                .encode()
+        ));
+        sell.min_sol_out = Some(u64::MAX);
+        assert!(PumpSwapInstructionBuilder.build_sell_instructions(&sell).await.is_err());
+    }
+}
@@ -1,17 +1,20 @@
 use crate::{
     constants::trade::trade::DEFAULT_SLIPPAGE,
-    instruction::utils::raydium_amm_v4::{accounts, SWAP_BASE_IN_DISCRIMINATOR},
-    trading::core::{
-        params::{BuyParams, RaydiumAmmV4Params, SellParams},
-        traits::InstructionBuilder,
+    instruction::data::{InstructionData, RaydiumAmmV4SwapBaseInArgs},
+    instruction::utils::raydium_amm_v4::{accounts, SerumMarket},
+    params::RaydiumAmmV4Params,
+    trading::{
+        common::wsol_manager::{EphemeralWsolAccount, WsolHandling},
+        core::{
+            error::TradeError,
+            params::{BuyParams, SellParams},
+            traits::InstructionBuilder,
+        },
     },
     utils::calc::raydium_amm_v4::compute_swap_amount,
 };
-use anyhow::{anyhow, Result};
-use solana_sdk::{
-    instruction::{AccountMeta, Instruction},
-    signer::Signer,
-};
+use anyhow::Result;
+use solana_sdk::instruction::{AccountMeta, Instruction};
 
 /// Instruction builder for RaydiumCpmm protocol
 pub struct RaydiumAmmV4InstructionBuilder;
@@ -23,13 +26,15 @@ impl InstructionBuilder for RaydiumAmmV4InstructionBuilder {
         // Parameter validation and basic data preparation
         // ========================================
         if params.sol_amount == 0 {
-            return Err(anyhow!("Amount cannot be zero"));
+            return Err(TradeError::invalid_params("RaydiumAmmV4", "amount is zero").into());
         }
         let protocol_params = params
             .protocol_params
             .as_any()
             .downcast_ref::<RaydiumAmmV4Params>()
-            .ok_or_else(|| anyhow!("Invalid protocol params for RaydiumCpmm"))?;
+            .ok_or_else(|| TradeError::params_mismatch("RaydiumAmmV4"))?;
+
+        params.require_exact_sol_in("RaydiumAmmV4")?;
 
         // ========================================
         // Trade calculation and account address preparation
@@ -43,7 +48,8 @@ impl InstructionBuilder for RaydiumAmmV4InstructionBuilder {
             amount_in,
             params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
         );
-        let minimum_amount_out = swap_result.min_amount_out;
+        let minimum_amount_out =
+            params.min_out_floor(swap_result.amount_out)?.unwrap_or(swap_result.min_amount_out);
 
         let user_source_token_account =
             crate::common::fast_fn::get_associated_token_address_with_program_id_fast_use_seed(
@@ -52,6 +58,18 @@ impl InstructionBuilder for RaydiumAmmV4InstructionBuilder {
                 &crate::constants::TOKEN_PROGRAM,
                 params.open_seed_optimize,
             );
+        let wsol = WsolHandling::new(
+            params.create_wsol_ata,
+            params.close_wsol_ata,
+            protocol_params.auto_handle_wsol,
+        );
+        let ephemeral_wsol = EphemeralWsolAccount::for_trade(
+            &params.payer.pubkey(),
+            params.wsol_account,
+            wsol.allows_ephemeral(),
+        );
+        let user_source_token_account =
+            ephemeral_wsol.map_or(user_source_token_account, |account| account.address);
         let user_destination_token_account =
             crate::common::fast_fn::get_associated_token_address_with_program_id_fast_use_seed(
                 &params.payer.pubkey(),
@@ -65,7 +83,9 @@ impl InstructionBuilder for RaydiumAmmV4InstructionBuilder {
         // ========================================
         let mut instructions = Vec::with_capacity(6);
 
-        if protocol_params.auto_handle_wsol {
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.extend(ephemeral_wsol.open(&params.payer.pubkey(), amount_in)?);
+        } else if wsol.create {
             instructions
                 .extend(crate::trading::common::handle_wsol(&params.payer.pubkey(), amount_in));
         }
@@ -82,6 +102,7 @@ impl InstructionBuilder for RaydiumAmmV4InstructionBuilder {
         );
 
         // Create buy instruction with proper account addresses
+        let serum = serum_market(protocol_params);
         let accounts: [AccountMeta; 17] = [
             crate::constants::TOKEN_PROGRAM_META, // Token Program (readonly)
             AccountMeta::new(protocol_params.amm, false), // Amm
@@ -91,21 +112,18 @@ impl InstructionBuilder for RaydiumAmmV4InstructionBuilder {
             AccountMeta::new(protocol_params.token_pc, false), // Pool Pc Token Account
             AccountMeta::new_readonly(protocol_params.serum_dex, false), // Serum Program
             AccountMeta::new(protocol_params.market, false), // Serum Market
-            AccountMeta::new(protocol_params.market, false), // Serum Bids (should be from market data)
-            AccountMeta::new(protocol_params.market, false), // Serum Asks (should be from market data)
-            AccountMeta::new(protocol_params.market, false), // Serum Event Queue (should be from market data)
-            AccountMeta::new(protocol_params.token_coin, false), // Serum Coin Vault Account
-            AccountMeta::new(protocol_params.token_pc, false), // Serum Pc Vault Account
-            AccountMeta::new_readonly(protocol_params.market, false), // Serum Vault Signer
+            AccountMeta::new(serum.bids, false),  // Serum Bids
+            AccountMeta::new(serum.asks, false),  // Serum Asks
+            AccountMeta::new(serum.event_queue, false), // Serum Event Queue
+            AccountMeta::new(serum.coin_vault, false), // Serum Coin Vault Account
+            AccountMeta::new(serum.pc_vault, false), // Serum Pc Vault Account
+            AccountMeta::new_readonly(serum.vault_signer, false), // Serum Vault Signer
             AccountMeta::new(user_source_token_account, false), // User Source Token Account
             AccountMeta::new(user_destination_token_account, false), // User Destination Token Account
             AccountMeta::new(params.payer.pubkey(), true),           // User Source Owner
         ];
         // Create instruction data
-        let mut data = [0u8; 17];
-        data[..1].copy_from_slice(&SWAP_BASE_IN_DISCRIMINATOR);
-        data[1..9].copy_from_slice(&amount_in.to_le_bytes());
-        data[9..17].copy_from_slice(&minimum_amount_out.to_le_bytes());
+        let data = RaydiumAmmV4SwapBaseInArgs { amount_in, minimum_amount_out }.encode();
 
         instructions.push(Instruction::new_with_bytes(
             accounts::RAYDIUM_AMM_V4,
@@ -113,7 +131,9 @@ impl InstructionBuilder for RaydiumAmmV4InstructionBuilder {
             accounts.to_vec(),
         ));
 
-        if protocol_params.auto_handle_wsol {
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.push(ephemeral_wsol.close(&params.payer.pubkey()));
+        } else if wsol.close {
             // Close wSOL ATA account, reclaim rent
             instructions.extend(crate::trading::common::close_wsol(&params.payer.pubkey()));
         }
@@ -129,10 +149,10 @@ impl InstructionBuilder for RaydiumAmmV4InstructionBuilder {
             .protocol_params
             .as_any()
             .downcast_ref::<RaydiumAmmV4Params>()
-            .ok_or_else(|| anyhow!("Invalid protocol params for RaydiumCpmm"))?;
+            .ok_or_else(|| TradeError::params_mismatch("RaydiumAmmV4"))?;
 
         if params.token_amount.is_none() || params.token_amount.unwrap_or(0) == 0 {
-            return Err(anyhow!("Token amount is not set"));
+            return Err(TradeError::invalid_params("RaydiumAmmV4", "amount is zero").into());
         }
 
         // ========================================
@@ -146,7 +166,8 @@ impl InstructionBuilder for RaydiumAmmV4InstructionBuilder {
             params.token_amount.unwrap_or(0),
             params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
         );
-        let minimum_amount_out = swap_result.min_amount_out;
+        let minimum_amount_out =
+            params.min_out_floor(swap_result.amount_out)?.unwrap_or(swap_result.min_amount_out);
 
         let user_source_token_account =
             crate::common::fast_fn::get_associated_token_address_with_program_id_fast_use_seed(
@@ -162,17 +183,32 @@ impl InstructionBuilder for RaydiumAmmV4InstructionBuilder {
                 &crate::constants::TOKEN_PROGRAM,
                 params.open_seed_optimize,
             );
+        let wsol = WsolHandling::new(
+            params.create_wsol_ata,
+            params.close_wsol_ata,
+            protocol_params.auto_handle_wsol,
+        );
+        let ephemeral_wsol = EphemeralWsolAccount::for_trade(
+            &params.payer.pubkey(),
+            params.wsol_account,
+            wsol.allows_ephemeral(),
+        );
+        let user_destination_token_account =
+            ephemeral_wsol.map_or(user_destination_token_account, |account| account.address);
 
         // ========================================
         // Build instructions
         // ========================================
         let mut instructions = Vec::with_capacity(3);
 
-        if protocol_params.auto_handle_wsol {
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.extend(ephemeral_wsol.open(&params.payer.pubkey(), 0)?);
+        } else if wsol.create {
             instructions.extend(crate::trading::common::create_wsol_ata(&params.payer.pubkey()));
         }
 
         // Create sell instruction with proper account addresses
+        let serum = serum_market(protocol_params);
         let accounts: [AccountMeta; 17] = [
             crate::constants::TOKEN_PROGRAM_META, // Token Program (readonly)
             AccountMeta::new(protocol_params.amm, false), // Amm
@@ -182,21 +218,22 @@ impl InstructionBuilder for RaydiumAmmV4InstructionBuilder {
             AccountMeta::new(protocol_params.token_pc, false), // Pool Pc Token Account
             AccountMeta::new_readonly(protocol_params.serum_dex, false), // Serum Program
             AccountMeta::new(protocol_params.market, false), // Serum Market
-            AccountMeta::new(protocol_params.market, false), // Serum Bids (should be from market data)
-            AccountMeta::new(protocol_params.market, false), // Serum Asks (should be from market data)
-            AccountMeta::new(protocol_params.market, false), // Serum Event Queue (should be from market data)
-            AccountMeta::new(protocol_params.token_coin, false), // Serum Coin Vault Account
-            AccountMeta::new(protocol_params.token_pc, false), // Serum Pc Vault Account
-            AccountMeta::new_readonly(protocol_params.market, false), // Serum Vault Signer
+            AccountMeta::new(serum.bids, false),  // Serum Bids
+            AccountMeta::new(serum.asks, false),  // Serum Asks
+            AccountMeta::new(serum.event_queue, false), // Serum Event Queue
+            AccountMeta::new(serum.coin_vault, false), // Serum Coin Vault Account
+            AccountMeta::new(serum.pc_vault, false), // Serum Pc Vault Account
+            AccountMeta::new_readonly(serum.vault_signer, false), // Serum Vault Signer
             AccountMeta::new(user_source_token_account, false), // User Source Token Account
             AccountMeta::new(user_destination_token_account, false), // User Destination Token Account
             AccountMeta::new(params.payer.pubkey(), true),           // User Source Owner
         ];
         // Create instruction data
-        let mut data = [0u8; 17];
-        data[..1].copy_from_slice(&SWAP_BASE_IN_DISCRIMINATOR);
-        data[1..9].copy_from_slice(&params.token_amount.unwrap_or(0).to_le_bytes());
-        data[9..17].copy_from_slice(&minimum_amount_out.to_le_bytes());
+        let data = RaydiumAmmV4SwapBaseInArgs {
+            amount_in: params.token_amount.unwrap_or(0),
+            minimum_amount_out,
+        }
+        .encode();
 
         instructions.push(Instruction::new_with_bytes(
             accounts::RAYDIUM_AMM_V4,
@@ -204,10 +241,219 @@ impl InstructionBuilder for RaydiumAmmV4InstructionBuilder {
             accounts.to_vec(),
         ));
 
-        if protocol_params.auto_handle_wsol {
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.push(ephemeral_wsol.close(&params.payer.pubkey()));
+        } else if wsol.close {
             instructions.extend(crate::trading::common::close_wsol(&params.payer.pubkey()));
         }
 
         Ok(instructions)
     }
 }
+
+/// Serum market accounts of the swap, the market and pool vaults in their place when unset
+fn serum_market(protocol_params: &RaydiumAmmV4Params) -> SerumMarket {
+    if protocol_params.has_market() {
+        SerumMarket {
+            bids: protocol_params.bids,
+            asks: protocol_params.asks,
+            event_queue: protocol_params.event_queue,
+            coin_vault: protocol_params.coin_vault,
+            pc_vault: protocol_params.pc_vault,
+            vault_signer: protocol_params.vault_signer,
+        }
+    } else {
+        SerumMarket {
+            bids: protocol_params.market,
+            asks: protocol_params.market,
+            event_queue: protocol_params.market,
+            coin_vault: protocol_params.token_coin,
+            pc_vault: protocol_params.token_pc,
+            vault_signer: protocol_params.market,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::common::wsol_manager::{wsol_steps, WSOL_FLAG_CASES};
+    use solana_sdk::pubkey::Pubkey;
+
+    fn pool_params(mint: Pubkey) -> RaydiumAmmV4Params {
+        RaydiumAmmV4Params {
+            amm: Pubkey::new_unique(),
+            coin_mint: mint,
+            pc_mint: crate::constants::WSOL_TOKEN_ACCOUNT,
+            token_coin: Pubkey::new_unique(),
+            token_pc: Pubkey::new_unique(),
+            coin_reserve: 200_000_000_000_000,
+            pc_reserve: 80_000_000_000,
+            auto_handle_wsol: true,
+            open_orders: Pubkey::new_unique(),
+            market: Pubkey::new_unique(),
+            serum_dex: Pubkey::new_unique(),
+            target_orders: Pubkey::new_unique(),
+            bids: Pubkey::default(),
+            asks: Pubkey::default(),
+            event_queue: Pubkey::default(),
+            coin_vault: Pubkey::default(),
+            pc_vault: Pubkey::default(),
+            vault_signer: Pubkey::default(),
+        }
+    }
+
+    fn sell_params(token_amount: Option<u64>) -> SellParams {
+        let mint = Pubkey::new_unique();
+        SellParams::offline(mint, token_amount, Box::new(pool_params(mint)))
+    }
+
+    /// Serum slots of the swap instruction: market, bids, asks, event queue, coin vault, pc
+    /// vault and vault signer
+    fn serum_slots(instructions: &[Instruction]) -> Vec<Pubkey> {
+        let swap =
+            instructions.iter().find(|ix| ix.program_id == accounts::RAYDIUM_AMM_V4).unwrap();
+        swap.accounts[7..14].iter().map(|meta| meta.pubkey).collect()
+    }
+
+    #[tokio::test]
+    async fn sell_builds_without_rpc() {
+        let params = sell_params(Some(1_000_000));
+        assert!(params.rpc.is_none());
+        let instructions =
+            RaydiumAmmV4InstructionBuilder.build_sell_instructions(&params).await.unwrap();
+        let swap = instructions
+            .iter()
+            .find_map(|ix| RaydiumAmmV4SwapBaseInArgs::decode(&ix.data))
+            .unwrap();
+        assert_eq!(swap.amount_in, 1_000_000);
+    }
+
+    #[tokio::test]
+    async fn sell_needs_a_resolved_amount() {
+        let params = sell_params(None);
+        assert!(RaydiumAmmV4InstructionBuilder.build_sell_instructions(&params).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn market_accounts_fill_the_serum_slots_of_buy_and_sell() {
+        let mint = Pubkey::new_unique();
+        let market = SerumMarket {
+            bids: Pubkey::new_unique(),
+            asks: Pubkey::new_unique(),
+            event_queue: Pubkey::new_unique(),
+            coin_vault: Pubkey::new_unique(),
+            pc_vault: Pubkey::new_unique(),
+            vault_signer: Pubkey::new_unique(),
+        };
+        let protocol_params = pool_params(mint).with_market(&market);
+        let expected = vec![
+            protocol_params.market,
+            market.bids,
+            market.asks,
+            market.event_queue,
+            market.coin_vault,
+            market.pc_vault,
+            market.vault_signer,
+        ];
+
+        let buy = BuyParams::offline(mint, 1_000_000, Box::new(protocol_params.clone()));
+        let sell = SellParams::offline(mint, Some(1_000_000), Box::new(protocol_params));
+        let builder = RaydiumAmmV4InstructionBuilder;
+        let buy_instructions = builder.build_buy_instructions(&buy).await.unwrap();
+        let sell_instructions = builder.build_sell_instructions(&sell).await.unwrap();
+        assert_eq!(serum_slots(&buy_instructions), expected);
+        assert_eq!(serum_slots(&sell_instructions), expected);
+        let swap =
+            sell_instructions.iter().find(|ix| ix.program_id == accounts::RAYDIUM_AMM_V4).unwrap();
+        assert!(!swap.accounts[13].is_writable, "vault signer");
+    }
+
+    #[tokio::test]
+    async fn unset_market_keeps_the_placeholders() {
+        let mint = Pubkey::new_unique();
+        let protocol_params = pool_params(mint);
+        assert!(!protocol_params.has_market());
+        let params = SellParams::offline(mint, Some(1_000_000), Box::new(protocol_params.clone()));
+        let instructions =
+            RaydiumAmmV4InstructionBuilder.build_sell_instructions(&params).await.unwrap();
+        let market = protocol_params.market;
+        assert_eq!(
+            serum_slots(&instructions),
+            vec![
+                market,
+                market,
+                market,
+                market,
+                protocol_params.token_coin,
+                protocol_params.token_pc,
+                market
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn wsol_flags_shape_the_wsol_steps() {
+        for &((create, close, auto_handle_wsol), buy_steps, sell_steps) in WSOL_FLAG_CASES {
+            let flags = (create, close, auto_handle_wsol);
+            let mint = Pubkey::new_unique();
+            let protocol_params = RaydiumAmmV4Params { auto_handle_wsol, ..pool_params(mint) };
+            let mut buy = BuyParams::offline(mint, 1_000_000, Box::new(protocol_params.clone()));
+            (buy.create_wsol_ata, buy.close_wsol_ata) = (create, close);
+            let mut sell = SellParams::offline(mint, Some(1_000_000), Box::new(protocol_params));
+            (sell.create_wsol_ata, sell.close_wsol_ata) = (create, close);
+
+            let instructions =
+                RaydiumAmmV4InstructionBuilder.build_buy_instructions(&buy).await.unwrap();
+            assert_eq!(
+                wsol_steps(&instructions, &buy.payer.pubkey()),
+                buy_steps,
+                "buy {:?}",
+                flags
+            );
+            let instructions =
+                RaydiumAmmV4InstructionBuilder.build_sell_instructions(&sell).await.unwrap();
+            assert_eq!(
+                wsol_steps(&instructions, &sell.payer.pubkey()),
+                sell_steps,
+                "sell {:?}",
+                flags
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn min_out_floors_are_encoded_exactly() {
+        const FLOOR: u64 = 1_000;
+        let mint = Pubkey::new_unique();
+        let swap_data = |instructions: Vec<Instruction>| {
+            instructions
+                .into_iter()
+                .find(|ix| ix.program_id == accounts::RAYDIUM_AMM_V4)
+                .unwrap()
+                .data
+        };
+
+        let mut buy = BuyParams::offline(mint, 1_000_000, Box::new(pool_params(mint)));
+        buy.min_amount_out = Some(FLOOR);
+        let data =
+            swap_data(RaydiumAmmV4InstructionBuilder.build_buy_instructions(&buy).await.unwrap());
+        assert!(data.starts_with(
+            &RaydiumAmmV4SwapBaseInArgs { amount_in: 1_000_000, minimum_amount_out: FLOOR }
+                .encode()
+        ));
+        buy.min_amount_out = Some(u64::MAX);
+        assert!(RaydiumAmmV4InstructionBuilder.build_buy_instructions(&buy).await.is_err());
+
+        let mut sell = SellParams::offline(mint, Some(1_000_000_000), Box::new(pool_params(mint)));
+        sell.min_sol_out = Some(FLOOR);
+        let data =
+            swap_data(RaydiumAmmV4InstructionBuilder.build_sell_instructions(&sell).await.unwrap());
+        assert!(data.starts_with(
+            &RaydiumAmmV4SwapBaseInArgs { amount_in: 1_000_000_000, minimum_amount_out: FLOOR }
+                .encode()
+        ));
+        sell.min_sol_out = Some(u64::MAX);
+        assert!(RaydiumAmmV4InstructionBuilder.build_sell_instructions(&sell).await.is_err());
+    }
+}
@@ -1,17 +1,29 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use solana_program::instruction::{AccountMeta, Instruction};
-use solana_sdk::{pubkey::Pubkey, signature::{Keypair, Signer}};
+use solana_sdk::pubkey::Pubkey;
 use solana_system_interface::instruction::transfer;
 use spl_associated_token_account::{get_associated_token_address, instruction::create_associated_token_account_idempotent};
 use spl_token;
 
 use crate::{
+    common::signer::TradeSigner,
+    instruction::data::{
+        InstructionData, RaydiumClmmSwapArgs, RaydiumClmmSwapV2Args, RAYDIUM_CLMM_PROGRAM,
+    },
+    instruction::utils::raydium_clmm::order_tick_arrays,
+    params::RaydiumClmmV2Params,
+    trading::common::wsol_manager::{EphemeralWsolAccount, WsolHandling},
     trading::core::{
-        params::{BuyParams, SellParams, RaydiumClmmV2Params},
-        traits::{InstructionBuilder, ProtocolParams},
+        error::TradeError,
+        params::{BuyParams, SellParams},
+        traits::InstructionBuilder,
     },
 };
 
+/// Previous path of `crate::params::RaydiumClmmParams`
+#[deprecated(note = "use sol_trade_sdk::prelude::RaydiumClmmParams")]
+pub type RaydiumClmmParams = crate::params::RaydiumClmmParams;
+
 /// Raydium CLMM V1 instruction builder
 pub struct RaydiumClmmInstructionBuilder;
 
@@ -24,8 +36,10 @@ impl InstructionBuilder for RaydiumClmmInstructionBuilder {
         let clmm_params = params
             .protocol_params
             .as_any()
-            .downcast_ref::<RaydiumClmmParams>()
-            .ok_or(anyhow!("Invalid parameters for Raydium CLMM"))?;
+            .downcast_ref::<crate::params::RaydiumClmmParams>()
+            .ok_or_else(|| TradeError::params_mismatch("RaydiumClmm"))?;
+
+        params.require_exact_sol_in("RaydiumClmm")?;
 
         let instruction = self.build_swap_instruction(
             &params.payer,
@@ -45,8 +59,8 @@ impl InstructionBuilder for RaydiumClmmInstructionBuilder {
         let clmm_params = params
             .protocol_params
             .as_any()
-            .downcast_ref::<RaydiumClmmParams>()
-            .ok_or(anyhow!("Invalid parameters for Raydium CLMM"))?;
+            .downcast_ref::<crate::params::RaydiumClmmParams>()
+            .ok_or_else(|| TradeError::params_mismatch("RaydiumClmm"))?;
 
         let instruction = self.build_swap_instruction(
             &params.payer,
@@ -61,15 +75,14 @@ impl InstructionBuilder for RaydiumClmmInstructionBuilder {
 }
 
 impl RaydiumClmmInstructionBuilder {
-    const PROGRAM_ID: Pubkey = solana_sdk::pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
-    const SWAP_DISCRIMINATOR: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+    const PROGRAM_ID: Pubkey = RAYDIUM_CLMM_PROGRAM;
 
     fn build_swap_instruction(
         &self,
-        payer: &Keypair,
+        payer: &dyn TradeSigner,
         _token_mint: &Pubkey,
         amount: u64,
-        clmm_params: &RaydiumClmmParams,
+        clmm_params: &crate::params::RaydiumClmmParams,
         is_buy: bool,
     ) -> Result<Instruction> {
         // Determine input/output based on trade direction
@@ -110,12 +123,13 @@ impl RaydiumClmmInstructionBuilder {
         }
 
         // Build instruction data
-        let mut data = Vec::new();
-        data.extend_from_slice(&Self::SWAP_DISCRIMINATOR);
-        data.extend_from_slice(&amount.to_le_bytes()); // amount
-        data.extend_from_slice(&clmm_params.other_amount_threshold.to_le_bytes()); // other_amount_threshold
-        data.extend_from_slice(&clmm_params.sqrt_price_limit_x64.to_le_bytes()); // sqrt_price_limit_x64
-        data.push(if clmm_params.is_base_input { 1 } else { 0 }); // is_base_input
+        let data = RaydiumClmmSwapArgs {
+            amount,
+            other_amount_threshold: clmm_params.other_amount_threshold,
+            sqrt_price_limit_x64: clmm_params.sqrt_price_limit_x64,
+            is_base_input: clmm_params.is_base_input,
+        }
+        .encode();
 
         Ok(Instruction {
             program_id: Self::PROGRAM_ID,
@@ -138,38 +152,48 @@ impl InstructionBuilder for RaydiumClmmV2InstructionBuilder {
             .protocol_params
             .as_any()
             .downcast_ref::<RaydiumClmmV2Params>()
-            .ok_or(anyhow!("Invalid parameters for Raydium CLMM V2"))?;
+            .ok_or_else(|| TradeError::params_mismatch("RaydiumClmmV2"))?;
+
+        params.require_exact_sol_in("RaydiumClmmV2")?;
 
         let mut instructions = Vec::new();
         
         // 🔧 CRITICAL FIX: Create ATA initialization instructions and WSOL wrapping for buy
         
-        // Derive user's WSOL ATA address
+        // Derive user's WSOL ATA address, or the per-trade account replacing it
         let wsol_ata = get_associated_token_address(
             &params.payer.pubkey(),
             &spl_token::native_mint::ID
         );
-        
-        // Create WSOL ATA (idempotent) - for spending SOL
-        instructions.push(create_associated_token_account_idempotent(
-            &params.payer.pubkey(),
-            &params.payer.pubkey(),
-            &spl_token::native_mint::ID, // Use native mint, not the hardcoded account
-            &spl_token::ID,
-        ));
-        
-        // Transfer SOL to WSOL ATA for wrapping
-        instructions.push(transfer(
+        let wsol = WsolHandling::new(
+            params.create_wsol_ata,
+            params.close_wsol_ata,
+            clmm_params.auto_handle_wsol,
+        );
+        let ephemeral_wsol = EphemeralWsolAccount::for_trade(
             &params.payer.pubkey(),
-            &wsol_ata,
-            params.sol_amount,
-        ));
-        
-        // Sync native to wrap SOL into WSOL
-        instructions.push(spl_token::instruction::sync_native(
-            &spl_token::ID,
-            &wsol_ata,
-        )?);
+            params.wsol_account,
+            wsol.allows_ephemeral(),
+        );
+        let wsol_ata = ephemeral_wsol.map_or(wsol_ata, |account| account.address);
+
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.extend(ephemeral_wsol.open(&params.payer.pubkey(), params.sol_amount)?);
+        } else if wsol.create {
+            // Create WSOL ATA (idempotent) - for spending SOL
+            instructions.push(create_associated_token_account_idempotent(
+                &params.payer.pubkey(),
+                &params.payer.pubkey(),
+                &spl_token::native_mint::ID, // Use native mint, not the hardcoded account
+                &spl_token::ID,
+            ));
+
+            // Transfer SOL to WSOL ATA for wrapping
+            instructions.push(transfer(&params.payer.pubkey(), &wsol_ata, params.sol_amount));
+
+            // Sync native to wrap SOL into WSOL
+            instructions.push(spl_token::instruction::sync_native(&spl_token::ID, &wsol_ata)?);
+        }
         
         // Create token mint ATA (idempotent)
         instructions.push(create_associated_token_account_idempotent(
@@ -183,6 +207,7 @@ impl InstructionBuilder for RaydiumClmmV2InstructionBuilder {
         let swap_instruction = self.build_swap_instruction(
             &params.payer,
             &params.mint,
+            wsol_ata,
             params.sol_amount,
             clmm_params,
             true, // is_buy
@@ -191,13 +216,15 @@ impl InstructionBuilder for RaydiumClmmV2InstructionBuilder {
         instructions.push(swap_instruction);
         
         // 🔧 WSOL UNWRAPPING: Close WSOL ATA to unwrap any leftover WSOL back to SOL (matches backup)
-        instructions.push(spl_token::instruction::close_account(
-            &spl_token::ID,
-            &wsol_ata,
-            &params.payer.pubkey(), // destination for unwrapped SOL
-            &params.payer.pubkey(), // authority
-            &[],
-        )?);
+        if ephemeral_wsol.is_some() || wsol.close {
+            instructions.push(spl_token::instruction::close_account(
+                &spl_token::ID,
+                &wsol_ata,
+                &params.payer.pubkey(), // destination for unwrapped SOL
+                &params.payer.pubkey(), // authority
+                &[],
+            )?);
+        }
         
         
         Ok(instructions)
@@ -211,25 +238,40 @@ impl InstructionBuilder for RaydiumClmmV2InstructionBuilder {
             .protocol_params
             .as_any()
             .downcast_ref::<RaydiumClmmV2Params>()
-            .ok_or(anyhow!("Invalid parameters for Raydium CLMM V2"))?;
+            .ok_or_else(|| TradeError::params_mismatch("RaydiumClmmV2"))?;
 
         let mut instructions = Vec::new();
         
         // 🔧 CRITICAL FIX: Create ATA initialization instructions for sell
         
-        // Derive user's WSOL ATA address
+        // Derive user's WSOL ATA address, or the per-trade account replacing it
         let wsol_ata = get_associated_token_address(
             &params.payer.pubkey(),
             &spl_token::native_mint::ID
         );
-        
-        // Create WSOL ATA (idempotent) - for receiving SOL
-        instructions.push(create_associated_token_account_idempotent(
-            &params.payer.pubkey(),
+        let wsol = WsolHandling::new(
+            params.create_wsol_ata,
+            params.close_wsol_ata,
+            clmm_params.auto_handle_wsol,
+        );
+        let ephemeral_wsol = EphemeralWsolAccount::for_trade(
             &params.payer.pubkey(),
-            &spl_token::native_mint::ID, // Use native mint, not hardcoded account
-            &spl_token::ID,
-        ));
+            params.wsol_account,
+            wsol.allows_ephemeral(),
+        );
+        let wsol_ata = ephemeral_wsol.map_or(wsol_ata, |account| account.address);
+
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.extend(ephemeral_wsol.open(&params.payer.pubkey(), 0)?);
+        } else if wsol.create {
+            // Create WSOL ATA (idempotent) - for receiving SOL
+            instructions.push(create_associated_token_account_idempotent(
+                &params.payer.pubkey(),
+                &params.payer.pubkey(),
+                &spl_token::native_mint::ID, // Use native mint, not hardcoded account
+                &spl_token::ID,
+            ));
+        }
         
         // Create token mint ATA (idempotent) - for selling tokens
         instructions.push(create_associated_token_account_idempotent(
@@ -243,6 +285,7 @@ impl InstructionBuilder for RaydiumClmmV2InstructionBuilder {
         let swap_instruction = self.build_swap_instruction(
             &params.payer,
             &params.mint,
+            wsol_ata,
             params.token_amount.unwrap_or(0),
             clmm_params,
             false, // is_sell
@@ -251,13 +294,15 @@ impl InstructionBuilder for RaydiumClmmV2InstructionBuilder {
         instructions.push(swap_instruction);
         
         // 🔧 WSOL UNWRAPPING: Close WSOL ATA to unwrap WSOL back to SOL after sell (matches backup)
-        instructions.push(spl_token::instruction::close_account(
-            &spl_token::ID,
-            &wsol_ata,
-            &params.payer.pubkey(), // destination for unwrapped SOL
-            &params.payer.pubkey(), // authority
-            &[],
-        )?);
+        if ephemeral_wsol.is_some() || wsol.close {
+            instructions.push(spl_token::instruction::close_account(
+                &spl_token::ID,
+                &wsol_ata,
+                &params.payer.pubkey(), // destination for unwrapped SOL
+                &params.payer.pubkey(), // authority
+                &[],
+            )?);
+        }
         
         
         Ok(instructions)
@@ -265,22 +310,18 @@ impl InstructionBuilder for RaydiumClmmV2InstructionBuilder {
 }
 
 impl RaydiumClmmV2InstructionBuilder {
-    const PROGRAM_ID: Pubkey = solana_sdk::pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
-    const SWAP_V2_DISCRIMINATOR: [u8; 8] = [43, 4, 237, 11, 26, 201, 30, 98];
+    const PROGRAM_ID: Pubkey = RAYDIUM_CLMM_PROGRAM;
 
     fn build_swap_instruction(
         &self,
-        payer: &Keypair,
+        payer: &dyn TradeSigner,
         token_mint: &Pubkey,
+        wsol_token_account: Pubkey,
         amount: u64,
         clmm_params: &RaydiumClmmV2Params,
         is_buy: bool,
     ) -> Result<Instruction> {
         // 🔧 CRITICAL FIX: Derive our own ATAs (not use original trader's accounts)
-        let wsol_token_account = get_associated_token_address(
-            &payer.pubkey(),
-            &spl_token::native_mint::ID, // Use native mint ID for WSOL
-        );
         let mint_token_account = get_associated_token_address(
             &payer.pubkey(),
             token_mint,
@@ -320,40 +361,28 @@ impl RaydiumClmmV2InstructionBuilder {
             AccountMeta::new_readonly(clmm_params.output_vault_mint, false), // 12: output_vault_mint (already swapped in params for sell)
         ];
 
-        // 🔧 ADJUSTMENT: Add tick arrays with position swap for sell executions (positions 15 & 16)
-        if is_buy {
-            // Buy: Add tick arrays normally
-            for tick_array in &clmm_params.tick_arrays {
-                accounts.push(AccountMeta::new(*tick_array, false));
-            }
-        } else {
-            // Sell: Add tick arrays with positions 15 and 16 swapped
-            for (i, _) in clmm_params.tick_arrays.iter().enumerate() {
-                let account_position = 13 + i;
-                let tick_array = if i == 2 && clmm_params.tick_arrays.len() > 3 {
-                    // Position 15 (i=2): use tick_arrays[3] instead
-                    println!("   Account {} (pos 15): {} -> {}", account_position, clmm_params.tick_arrays[2], clmm_params.tick_arrays[3]);
-                    clmm_params.tick_arrays[3]
-                } else if i == 3 && clmm_params.tick_arrays.len() > 2 {
-                    // Position 16 (i=3): use tick_arrays[2] instead
-                    println!("   Account {} (pos 16): {} -> {}", account_position, clmm_params.tick_arrays[3], clmm_params.tick_arrays[2]);
-                    clmm_params.tick_arrays[2]
-                } else {
-                    // All other positions: use normal order
-                    println!("   Account {} (pos {}): {} (unchanged)", account_position, account_position, clmm_params.tick_arrays[i]);
-                    clmm_params.tick_arrays[i]
-                };
-                accounts.push(AccountMeta::new(tick_array, false));
-            }
+        // Tick arrays in the order the swap crosses them
+        let zero_for_one = clmm_params.input_vault_mint < clmm_params.output_vault_mint;
+        let tick_arrays = order_tick_arrays(
+            &clmm_params.pool_state,
+            &clmm_params.tick_arrays,
+            clmm_params.tick_current,
+            clmm_params.tick_spacing,
+            zero_for_one,
+            clmm_params.sqrt_price_limit_x64,
+        )?;
+        for tick_array in tick_arrays {
+            accounts.push(AccountMeta::new(tick_array, false));
         }
 
         // Build instruction data
-        let mut data = Vec::new();
-        data.extend_from_slice(&Self::SWAP_V2_DISCRIMINATOR);
-        data.extend_from_slice(&amount.to_le_bytes()); // amount
-        data.extend_from_slice(&clmm_params.other_amount_threshold.to_le_bytes()); // other_amount_threshold
-        data.extend_from_slice(&clmm_params.sqrt_price_limit_x64.to_le_bytes()); // sqrt_price_limit_x64
-        data.push(1); // 🔧 FIX: Always true for both buy and sell in CLMM V2 (per copied transaction)
+        let data = RaydiumClmmSwapV2Args {
+            amount,
+            other_amount_threshold: clmm_params.other_amount_threshold,
+            sqrt_price_limit_x64: clmm_params.sqrt_price_limit_x64,
+            is_base_input: true, // 🔧 FIX: Always true for both buy and sell in CLMM V2 (per copied transaction)
+        }
+        .encode();
 
         Ok(Instruction {
             program_id: Self::PROGRAM_ID,
@@ -362,35 +391,3 @@ impl RaydiumClmmV2InstructionBuilder {
         })
     }
 }
-
-/// Raydium CLMM V1 parameters
-#[derive(Clone)]
-pub struct RaydiumClmmParams {
-    /// Core CLMM accounts
-    pub amm_config: Pubkey,
-    pub pool_state: Pubkey,
-    pub input_vault: Pubkey,
-    pub output_vault: Pubkey,
-    pub observation_state: Pubkey,
-    /// Tick arrays for swap execution
-    pub tick_arrays: Vec<Pubkey>,
-    /// Token programs
-    pub token_program: Pubkey,
-    /// User token accounts
-    pub payer_sol_account: Pubkey,
-    pub payer_token_account: Pubkey,
-    /// Instruction parameters
-    pub other_amount_threshold: u64,
-    pub sqrt_price_limit_x64: u128,
-    pub is_base_input: bool,
-}
-
-impl ProtocolParams for RaydiumClmmParams {
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
-
-    fn clone_box(&self) -> Box<dyn ProtocolParams> {
-        Box::new(self.clone())
-    }
-}
@@ -1,21 +1,28 @@
 use crate::{
     common::fast_fn::get_associated_token_address_with_program_id_fast_use_seed,
     constants::trade::trade::DEFAULT_SLIPPAGE,
+    instruction::data::{InstructionData, RaydiumCpmmSwapBaseInArgs, RaydiumCpmmSwapBaseOutArgs},
     instruction::utils::raydium_cpmm::{
         accounts, get_observation_state_pda, get_pool_pda, get_vault_account,
-        SWAP_BASE_IN_DISCRIMINATOR,
     },
-    trading::core::{
-        params::{BuyParams, RaydiumCpmmParams, SellParams},
-        traits::InstructionBuilder,
+    params::RaydiumCpmmParams,
+    trading::{
+        common::wsol_manager::{EphemeralWsolAccount, WsolHandling},
+        core::{
+            error::TradeError,
+            params::{BuyAmountSpec, BuyParams, SellParams},
+            traits::InstructionBuilder,
+        },
+    },
+    utils::calc::{
+        common::calculate_with_slippage_buy,
+        raydium_cpmm::{compute_swap_amount, compute_swap_amount_base_out},
     },
-    utils::calc::raydium_cpmm::compute_swap_amount,
 };
 use anyhow::{anyhow, Result};
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
-    signer::Signer,
 };
 
 /// Instruction builder for RaydiumCpmm protocol
@@ -28,13 +35,13 @@ impl InstructionBuilder for RaydiumCpmmInstructionBuilder {
         // Parameter validation and basic data preparation
         // ========================================
         if params.sol_amount == 0 {
-            return Err(anyhow!("Amount cannot be zero"));
+            return Err(TradeError::invalid_params("RaydiumCpmm", "amount is zero").into());
         }
         let protocol_params = params
             .protocol_params
             .as_any()
             .downcast_ref::<RaydiumCpmmParams>()
-            .ok_or_else(|| anyhow!("Invalid protocol params for RaydiumCpmm"))?;
+            .ok_or_else(|| TradeError::params_mismatch("RaydiumCpmm"))?;
 
         let pool_state = if protocol_params.pool_state == Pubkey::default() {
             get_pool_pda(
@@ -50,29 +57,77 @@ impl InstructionBuilder for RaydiumCpmmInstructionBuilder {
         // ========================================
         // Trade calculation and account address preparation
         // ========================================
-        let is_base_in = protocol_params.base_mint == crate::constants::WSOL_TOKEN_ACCOUNT;
+        // The input mint is paid in, the token is on the other side of the pool
+        let input_mint = protocol_params.input_mint();
+        let input_token_program = protocol_params.input_token_program()?;
+        let is_base_in = protocol_params.is_input_base()?;
         let mint_token_program = if is_base_in {
             protocol_params.quote_token_program
         } else {
             protocol_params.base_token_program
         };
 
-        let amount_in: u64 = params.sol_amount;
-        let result = compute_swap_amount(
-            protocol_params.base_reserve,
-            protocol_params.quote_reserve,
-            is_base_in,
-            amount_in,
-            params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
-        );
-        let minimum_amount_out = result.min_amount_out;
+        let slippage_basis_points = params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE);
+        // The input paid for the swap, wrapped first when it is SOL, and the swap instruction data
+        let (amount_in, data) = match params.amount_spec {
+            BuyAmountSpec::ExactSolIn(_) => {
+                let amount_in: u64 = params.sol_amount;
+                let result = compute_swap_amount(
+                    protocol_params.base_reserve,
+                    protocol_params.quote_reserve,
+                    is_base_in,
+                    amount_in,
+                    slippage_basis_points,
+                );
+                let minimum_amount_out =
+                    params.min_out_floor(result.amount_out)?.unwrap_or(result.min_amount_out);
+                (amount_in, RaydiumCpmmSwapBaseInArgs { amount_in, minimum_amount_out }.encode())
+            }
+            BuyAmountSpec::ExactTokensOut { token_amount, max_sol } => {
+                params.min_out_floor(token_amount)?;
+                let required_in = compute_swap_amount_base_out(
+                    protocol_params.base_reserve,
+                    protocol_params.quote_reserve,
+                    is_base_in,
+                    token_amount,
+                )
+                .ok_or_else(|| {
+                    anyhow!("Pool {} cannot pay out {} tokens", pool_state, token_amount)
+                })?;
+                if required_in > max_sol {
+                    return Err(anyhow!(
+                        "Buying {} tokens costs {} lamports, more than max_sol {}",
+                        token_amount,
+                        required_in,
+                        max_sol
+                    ));
+                }
+                let max_amount_in =
+                    calculate_with_slippage_buy(required_in, slippage_basis_points).min(max_sol);
+                let data =
+                    RaydiumCpmmSwapBaseOutArgs { max_amount_in, amount_out: token_amount }.encode();
+                (max_amount_in, data)
+            }
+        };
 
-        let wsol_token_account = get_associated_token_address_with_program_id_fast_use_seed(
+        let input_token_account = get_associated_token_address_with_program_id_fast_use_seed(
             &params.payer.pubkey(),
-            &crate::constants::WSOL_TOKEN_ACCOUNT,
-            &crate::constants::TOKEN_PROGRAM,
+            &input_mint,
+            &input_token_program,
             params.open_seed_optimize,
         );
+        let wsol = WsolHandling::new(
+            params.create_wsol_ata,
+            params.close_wsol_ata,
+            protocol_params.auto_handle_wsol && protocol_params.is_wsol_input(),
+        );
+        let ephemeral_wsol = EphemeralWsolAccount::for_trade(
+            &params.payer.pubkey(),
+            params.wsol_account,
+            wsol.allows_ephemeral(),
+        );
+        let input_token_account =
+            ephemeral_wsol.map_or(input_token_account, |account| account.address);
         let mint_token_account = get_associated_token_address_with_program_id_fast_use_seed(
             &params.payer.pubkey(),
             &params.mint,
@@ -80,11 +135,11 @@ impl InstructionBuilder for RaydiumCpmmInstructionBuilder {
             params.open_seed_optimize,
         );
 
-        let wsol_vault_account = get_vault_account(
+        let input_vault_account = get_vault_account(
             &pool_state,
-            &crate::constants::WSOL_TOKEN_ACCOUNT,
+            &input_mint,
             protocol_params,
-            true,
+            protocol_params.is_wsol_input(),
         );
         let mint_vault_account =
             get_vault_account(&pool_state, &params.mint, protocol_params, false);
@@ -100,7 +155,9 @@ impl InstructionBuilder for RaydiumCpmmInstructionBuilder {
         // ========================================
         let mut instructions = Vec::with_capacity(6);
 
-        if protocol_params.auto_handle_wsol {
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.extend(ephemeral_wsol.open(&params.payer.pubkey(), amount_in)?);
+        } else if wsol.create {
             instructions
                 .extend(crate::trading::common::handle_wsol(&params.payer.pubkey(), amount_in));
         }
@@ -123,29 +180,25 @@ impl InstructionBuilder for RaydiumCpmmInstructionBuilder {
             accounts::AUTHORITY_META,                      // Authority (readonly)
             AccountMeta::new(protocol_params.amm_config, false), // Amm Config (readonly)
             AccountMeta::new(pool_state, false),           // Pool State
-            AccountMeta::new(wsol_token_account, false),   // Input Token Account
+            AccountMeta::new(input_token_account, false),  // Input Token Account
             AccountMeta::new(mint_token_account, false),   // Output Token Account
-            AccountMeta::new(wsol_vault_account, false),   // Input Vault Account
+            AccountMeta::new(input_vault_account, false),  // Input Vault Account
             AccountMeta::new(mint_vault_account, false),   // Output Vault Account
-            crate::constants::TOKEN_PROGRAM_META,          // Input Token Program (readonly)
+            AccountMeta::new_readonly(input_token_program, false), // Input Token Program (readonly)
             AccountMeta::new_readonly(mint_token_program, false), // Output Token Program (readonly)
-            crate::constants::WSOL_TOKEN_ACCOUNT_META,     // Input token mint (readonly)
+            AccountMeta::new_readonly(input_mint, false),  // Input token mint (readonly)
             AccountMeta::new_readonly(params.mint, false), // Output token mint (readonly)
             AccountMeta::new(observation_state_account, false), // Observation State Account
         ];
-        // Create instruction data
-        let mut data = [0u8; 24];
-        data[..8].copy_from_slice(&SWAP_BASE_IN_DISCRIMINATOR);
-        data[8..16].copy_from_slice(&amount_in.to_le_bytes());
-        data[16..24].copy_from_slice(&minimum_amount_out.to_le_bytes());
-
         instructions.push(Instruction::new_with_bytes(
             accounts::RAYDIUM_CPMM,
             &data,
             accounts.to_vec(),
         ));
 
-        if params.close_wsol_ata {
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.push(ephemeral_wsol.close(&params.payer.pubkey()));
+        } else if wsol.close {
             // Close wSOL ATA account, reclaim rent
             instructions.extend(crate::trading::common::close_wsol(&params.payer.pubkey()));
         }
@@ -161,10 +214,10 @@ impl InstructionBuilder for RaydiumCpmmInstructionBuilder {
             .protocol_params
             .as_any()
             .downcast_ref::<RaydiumCpmmParams>()
-            .ok_or_else(|| anyhow!("Invalid protocol params for RaydiumCpmm"))?;
+            .ok_or_else(|| TradeError::params_mismatch("RaydiumCpmm"))?;
 
         if params.token_amount.is_none() || params.token_amount.unwrap_or(0) == 0 {
-            return Err(anyhow!("Token amount is not set"));
+            return Err(TradeError::invalid_params("RaydiumCpmm", "amount is zero").into());
         }
 
         let pool_state = if protocol_params.pool_state == Pubkey::default() {
@@ -181,28 +234,44 @@ impl InstructionBuilder for RaydiumCpmmInstructionBuilder {
         // ========================================
         // Trade calculation and account address preparation
         // ========================================
-        let is_base_in = protocol_params.base_mint == params.mint;
+        // The token is paid in, the input mint is paid out
+        let input_mint = protocol_params.input_mint();
+        let input_token_program = protocol_params.input_token_program()?;
+        let is_base_in = !protocol_params.is_input_base()?;
         let mint_token_program = if is_base_in {
             protocol_params.base_token_program
         } else {
             protocol_params.quote_token_program
         };
 
-        let minimum_amount_out: u64 = compute_swap_amount(
+        let swap_result = compute_swap_amount(
             protocol_params.base_reserve,
             protocol_params.quote_reserve,
             is_base_in,
             params.token_amount.unwrap_or(0),
             params.slippage_basis_points.unwrap_or(DEFAULT_SLIPPAGE),
-        )
-        .min_amount_out;
+        );
+        let minimum_amount_out: u64 =
+            params.min_out_floor(swap_result.amount_out)?.unwrap_or(swap_result.min_amount_out);
 
-        let wsol_token_account = get_associated_token_address_with_program_id_fast_use_seed(
+        let input_token_account = get_associated_token_address_with_program_id_fast_use_seed(
             &params.payer.pubkey(),
-            &crate::constants::WSOL_TOKEN_ACCOUNT,
-            &crate::constants::TOKEN_PROGRAM,
+            &input_mint,
+            &input_token_program,
             params.open_seed_optimize,
         );
+        let wsol = WsolHandling::new(
+            params.create_wsol_ata,
+            params.close_wsol_ata,
+            protocol_params.auto_handle_wsol && protocol_params.is_wsol_input(),
+        );
+        let ephemeral_wsol = EphemeralWsolAccount::for_trade(
+            &params.payer.pubkey(),
+            params.wsol_account,
+            wsol.allows_ephemeral(),
+        );
+        let input_token_account =
+            ephemeral_wsol.map_or(input_token_account, |account| account.address);
         let mint_token_account = get_associated_token_address_with_program_id_fast_use_seed(
             &params.payer.pubkey(),
             &params.mint,
@@ -210,11 +279,11 @@ impl InstructionBuilder for RaydiumCpmmInstructionBuilder {
             params.open_seed_optimize,
         );
 
-        let wsol_vault_account = get_vault_account(
+        let input_vault_account = get_vault_account(
             &pool_state,
-            &crate::constants::WSOL_TOKEN_ACCOUNT,
+            &input_mint,
             protocol_params,
-            true,
+            protocol_params.is_wsol_input(),
         );
         let mint_vault_account =
             get_vault_account(&pool_state, &params.mint, protocol_params, false);
@@ -230,8 +299,25 @@ impl InstructionBuilder for RaydiumCpmmInstructionBuilder {
         // ========================================
         let mut instructions = Vec::with_capacity(3);
 
-        if protocol_params.auto_handle_wsol {
-            instructions.extend(crate::trading::common::create_wsol_ata(&params.payer.pubkey()));
+        // Create the wSOL ATA if create_wsol_ata is set, otherwise make sure the token
+        // account of a non-SOL input mint exists to receive the proceeds
+        if protocol_params.is_wsol_input() {
+            if let Some(ephemeral_wsol) = &ephemeral_wsol {
+                instructions.extend(ephemeral_wsol.open(&params.payer.pubkey(), 0)?);
+            } else if wsol.create {
+                instructions
+                    .extend(crate::trading::common::create_wsol_ata(&params.payer.pubkey()));
+            }
+        } else {
+            instructions.extend(
+                crate::common::fast_fn::create_associated_token_account_idempotent_fast_use_seed(
+                    &params.payer.pubkey(),
+                    &params.payer.pubkey(),
+                    &input_mint,
+                    &input_token_program,
+                    params.open_seed_optimize,
+                ),
+            );
         }
 
         // Create sell instruction
@@ -241,20 +327,21 @@ impl InstructionBuilder for RaydiumCpmmInstructionBuilder {
             AccountMeta::new(protocol_params.amm_config, false), // Amm Config (readonly)
             AccountMeta::new(pool_state, false),           // Pool State
             AccountMeta::new(mint_token_account, false),   // Input Token Account
-            AccountMeta::new(wsol_token_account, false),   // Output Token Account
+            AccountMeta::new(input_token_account, false),  // Output Token Account
             AccountMeta::new(mint_vault_account, false),   // Input Vault Account
-            AccountMeta::new(wsol_vault_account, false),   // Output Vault Account
+            AccountMeta::new(input_vault_account, false),  // Output Vault Account
             AccountMeta::new_readonly(mint_token_program, false), // Input Token Program (readonly)
-            crate::constants::TOKEN_PROGRAM_META,          // Output Token Program (readonly)
-            AccountMeta::new_readonly(params.mint, false), // Input token mint (readonly)
-            crate::constants::WSOL_TOKEN_ACCOUNT_META,     // Output token mint (readonly)
-            AccountMeta::new(observation_state_account, false), // Observation State Account
+            AccountMeta::new_readonly(input_token_program, false), // Output Token Program (readonly)
+            AccountMeta::new_readonly(params.mint, false),         // Input token mint (readonly)
+            AccountMeta::new_readonly(input_mint, false),          // Output token mint (readonly)
+            AccountMeta::new(observation_state_account, false),    // Observation State Account
         ];
         // Create instruction data
-        let mut data = [0u8; 24];
-        data[..8].copy_from_slice(&SWAP_BASE_IN_DISCRIMINATOR);
-        data[8..16].copy_from_slice(&params.token_amount.unwrap_or(0).to_le_bytes());
-        data[16..24].copy_from_slice(&minimum_amount_out.to_le_bytes());
+        let data = RaydiumCpmmSwapBaseInArgs {
+            amount_in: params.token_amount.unwrap_or(0),
+            minimum_amount_out,
+        }
+        .encode();
 
         instructions.push(Instruction::new_with_bytes(
             accounts::RAYDIUM_CPMM,
@@ -262,7 +349,9 @@ impl InstructionBuilder for RaydiumCpmmInstructionBuilder {
             accounts.to_vec(),
         ));
 
-        if protocol_params.auto_handle_wsol {
+        if let Some(ephemeral_wsol) = &ephemeral_wsol {
+            instructions.push(ephemeral_wsol.close(&params.payer.pubkey()));
+        } else if wsol.close {
             // Close wSOL ATA account, reclaim rent (matches backup logic)
             instructions.extend(crate::trading::common::close_wsol(&params.payer.pubkey()));
         }
@@ -270,3 +359,209 @@ impl InstructionBuilder for RaydiumCpmmInstructionBuilder {
         Ok(instructions)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::USDC_TOKEN_ACCOUNT;
+    use crate::trading::common::wsol_manager::{wsol_steps, WSOL_FLAG_CASES};
+
+    /// Pool of `mint` quoted in wSOL
+    fn pool_params(mint: Pubkey) -> RaydiumCpmmParams {
+        RaydiumCpmmParams {
+            pool_state: Pubkey::new_unique(),
+            amm_config: Pubkey::new_unique(),
+            base_mint: mint,
+            quote_mint: crate::constants::WSOL_TOKEN_ACCOUNT,
+            base_reserve: 200_000_000_000_000,
+            quote_reserve: 80_000_000_000,
+            base_vault: Pubkey::new_unique(),
+            quote_vault: Pubkey::new_unique(),
+            base_token_program: crate::constants::TOKEN_PROGRAM,
+            quote_token_program: crate::constants::TOKEN_PROGRAM,
+            observation_state: Pubkey::new_unique(),
+            input_mint: Pubkey::default(),
+            auto_handle_wsol: true,
+            authority: None,
+            input_vault: None,
+            output_vault: None,
+        }
+    }
+
+    fn sell_params(token_amount: Option<u64>) -> SellParams {
+        let mint = Pubkey::new_unique();
+        SellParams::offline(mint, token_amount, Box::new(pool_params(mint)))
+    }
+
+    #[tokio::test]
+    async fn sell_builds_without_rpc() {
+        let params = sell_params(Some(1_000_000));
+        assert!(params.rpc.is_none());
+        let instructions =
+            RaydiumCpmmInstructionBuilder.build_sell_instructions(&params).await.unwrap();
+        let swap =
+            instructions.iter().find_map(|ix| RaydiumCpmmSwapBaseInArgs::decode(&ix.data)).unwrap();
+        assert_eq!(swap.amount_in, 1_000_000);
+    }
+
+    #[tokio::test]
+    async fn sell_needs_a_resolved_amount() {
+        let params = sell_params(None);
+        assert!(RaydiumCpmmInstructionBuilder.build_sell_instructions(&params).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn wsol_flags_shape_the_wsol_steps() {
+        for &((create, close, auto_handle_wsol), buy_steps, sell_steps) in WSOL_FLAG_CASES {
+            let flags = (create, close, auto_handle_wsol);
+            let mint = Pubkey::new_unique();
+            let protocol_params = RaydiumCpmmParams { auto_handle_wsol, ..pool_params(mint) };
+            let mut buy = BuyParams::offline(mint, 1_000_000, Box::new(protocol_params.clone()));
+            (buy.create_wsol_ata, buy.close_wsol_ata) = (create, close);
+            let mut sell = SellParams::offline(mint, Some(1_000_000), Box::new(protocol_params));
+            (sell.create_wsol_ata, sell.close_wsol_ata) = (create, close);
+
+            let instructions =
+                RaydiumCpmmInstructionBuilder.build_buy_instructions(&buy).await.unwrap();
+            assert_eq!(
+                wsol_steps(&instructions, &buy.payer.pubkey()),
+                buy_steps,
+                "buy {:?}",
+                flags
+            );
+            let instructions =
+                RaydiumCpmmInstructionBuilder.build_sell_instructions(&sell).await.unwrap();
+            assert_eq!(
+                wsol_steps(&instructions, &sell.payer.pubkey()),
+                sell_steps,
+                "sell {:?}",
+                flags
+            );
+        }
+    }
+
+    /// A pool of 1M USDC against 50M of `mint`, USDC on the base side or on the quote side
+    fn usdc_pool(mint: Pubkey, vaults: (Pubkey, Pubkey), usdc_is_base: bool) -> RaydiumCpmmParams {
+        let usdc = (USDC_TOKEN_ACCOUNT, 1_000_000_000_000, vaults.0);
+        let token = (mint, 50_000_000_000_000, vaults.1);
+        let (base, quote) = if usdc_is_base { (usdc, token) } else { (token, usdc) };
+        RaydiumCpmmParams {
+            base_mint: base.0,
+            quote_mint: quote.0,
+            base_reserve: base.1,
+            quote_reserve: quote.1,
+            base_vault: base.2,
+            quote_vault: quote.2,
+            input_mint: USDC_TOKEN_ACCOUNT,
+            ..pool_params(mint)
+        }
+    }
+
+    #[tokio::test]
+    async fn usdc_input_swaps_against_either_side_of_the_pool() {
+        let mint = Pubkey::new_unique();
+        let (usdc_vault, token_vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        for usdc_is_base in [true, false] {
+            let protocol_params = usdc_pool(mint, (usdc_vault, token_vault), usdc_is_base);
+            let buy = BuyParams::offline(mint, 1_000_000_000, Box::new(protocol_params.clone()));
+            let sell =
+                SellParams::offline(mint, Some(2_000_000_000_000), Box::new(protocol_params));
+            let buy_instructions =
+                RaydiumCpmmInstructionBuilder.build_buy_instructions(&buy).await.unwrap();
+            let sell_instructions =
+                RaydiumCpmmInstructionBuilder.build_sell_instructions(&sell).await.unwrap();
+
+            for (instructions, payer, seed, is_buy) in [
+                (&buy_instructions, buy.payer.pubkey(), buy.open_seed_optimize, true),
+                (&sell_instructions, sell.payer.pubkey(), sell.open_seed_optimize, false),
+            ] {
+                // USDC is never wrapped or unwrapped
+                assert!(wsol_steps(instructions, &payer).is_empty());
+                let account = |token_mint: &Pubkey| {
+                    get_associated_token_address_with_program_id_fast_use_seed(
+                        &payer,
+                        token_mint,
+                        &crate::constants::TOKEN_PROGRAM,
+                        seed,
+                    )
+                };
+                let swap = instructions.last().unwrap();
+                assert_eq!(swap.program_id, accounts::RAYDIUM_CPMM);
+                let slots: Vec<Pubkey> =
+                    [4, 5, 6, 7, 10, 11].iter().map(|&index| swap.accounts[index].pubkey).collect();
+                // Input then output account, vault and mint
+                let usdc = [account(&USDC_TOKEN_ACCOUNT), usdc_vault, USDC_TOKEN_ACCOUNT];
+                let token = [account(&mint), token_vault, mint];
+                let (input, output) = if is_buy { (usdc, token) } else { (token, usdc) };
+                assert_eq!(
+                    slots,
+                    vec![input[0], output[0], input[1], output[1], input[2], output[2]],
+                    "usdc is base: {}",
+                    usdc_is_base
+                );
+            }
+
+            // The amounts of the calc tests, the reserves oriented by the input side
+            let args = |instructions: &[Instruction]| {
+                RaydiumCpmmSwapBaseInArgs::decode(&instructions.last().unwrap().data).unwrap()
+            };
+            assert_eq!(
+                args(&buy_instructions),
+                RaydiumCpmmSwapBaseInArgs {
+                    amount_in: 1_000_000_000,
+                    minimum_amount_out: 49_327_046_270
+                }
+            );
+            assert_eq!(
+                args(&sell_instructions),
+                RaydiumCpmmSwapBaseInArgs {
+                    amount_in: 2_000_000_000_000,
+                    minimum_amount_out: 37_985_383_209
+                }
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn input_mint_outside_the_pool_is_rejected() {
+        let mint = Pubkey::new_unique();
+        let protocol_params =
+            RaydiumCpmmParams { input_mint: USDC_TOKEN_ACCOUNT, ..pool_params(mint) };
+        let buy = BuyParams::offline(mint, 1_000_000_000, Box::new(protocol_params));
+        assert!(RaydiumCpmmInstructionBuilder.build_buy_instructions(&buy).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn min_out_floors_are_encoded_exactly() {
+        const FLOOR: u64 = 1_000;
+        let mint = Pubkey::new_unique();
+        let swap_data = |instructions: Vec<Instruction>| {
+            instructions
+                .into_iter()
+                .find(|ix| ix.program_id == accounts::RAYDIUM_CPMM)
+                .unwrap()
+                .data
+        };
+
+        let mut buy = BuyParams::offline(mint, 1_000_000, Box::new(pool_params(mint)));
+        buy.min_amount_out = Some(FLOOR);
+        let data =
+            swap_data(RaydiumCpmmInstructionBuilder.build_buy_instructions(&buy).await.unwrap());
+        assert!(data.starts_with(
+            &RaydiumCpmmSwapBaseInArgs { amount_in: 1_000_000, minimum_amount_out: FLOOR }.encode()
+        ));
+        buy.min_amount_out = Some(u64::MAX);
+        assert!(RaydiumCpmmInstructionBuilder.build_buy_instructions(&buy).await.is_err());
+
+        let mut sell = SellParams::offline(mint, Some(1_000_000_000), Box::new(pool_params(mint)));
+        sell.min_sol_out = Some(FLOOR);
+        let data =
+            swap_data(RaydiumCpmmInstructionBuilder.build_sell_instructions(&sell).await.unwrap());
+        assert!(data.starts_with(
+            &RaydiumCpmmSwapBaseInArgs { amount_in: 1_000_000_000, minimum_amount_out: FLOOR }
+                .encode()
+        ));
+        sell.min_sol_out = Some(u64::MAX);
+        assert!(RaydiumCpmmInstructionBuilder.build_sell_instructions(&sell).await.is_err());
+    }
+}
@@ -1,6 +1,8 @@
+use crate::common::account_fetch::{
+    check_owner, fetch_accounts, fetch_owned_account, AccountFetchError, AccountFetchFailure,
+};
 use crate::common::SolanaRpcClient;
-use anyhow::anyhow;
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{account::Account, pubkey::Pubkey};
 use solana_streamer_sdk::streaming::event_parser::protocols::bonk::{
     pool_state_decode, types::PoolState,
 };
@@ -54,13 +56,84 @@ pub async fn fetch_pool_state(
     rpc: &SolanaRpcClient,
     pool_address: &Pubkey,
 ) -> Result<PoolState, anyhow::Error> {
-    let account = rpc.get_account(pool_address).await?;
-    if account.owner != accounts::BONK {
-        return Err(anyhow!("Account is not owned by Bonk program"));
+    let account = fetch_owned_account(rpc, "pool", pool_address, &accounts::BONK).await?;
+    Ok(decode_pool_account(pool_address, &account)?)
+}
+
+/// Pool state of `pool_address` and the token programs of `base_mint` and `quote_mint`,
+/// loaded in one `getMultipleAccounts` call
+///
+/// The pool address is derived from the mints, so nothing has to be read first. The WSOL
+/// quote mint is not loaded, its program is known.
+pub async fn fetch_pool_state_and_token_programs(
+    rpc: &SolanaRpcClient,
+    pool_address: &Pubkey,
+    base_mint: &Pubkey,
+    quote_mint: &Pubkey,
+) -> Result<(PoolState, Pubkey, Pubkey), anyhow::Error> {
+    let quote_is_wsol = *quote_mint == crate::constants::WSOL_TOKEN_ACCOUNT;
+    let mut to_load = vec![("pool", *pool_address), ("base mint", *base_mint)];
+    if !quote_is_wsol {
+        to_load.push(("quote mint", *quote_mint));
+    }
+    let loaded = fetch_accounts(rpc, &to_load).await?;
+    check_owner("pool", pool_address, &loaded[0], &accounts::BONK)?;
+    let pool_state = decode_pool_account(pool_address, &loaded[0])?;
+    let quote_token_program =
+        if quote_is_wsol { crate::constants::TOKEN_PROGRAM } else { loaded[2].owner };
+    Ok((pool_state, loaded[1].owner, quote_token_program))
+}
+
+/// Quote mints Bonk pools are launched with, in the order `find_pool_by_base_mint` tries them
+pub const QUOTE_MINTS: [Pubkey; 3] = [
+    crate::constants::WSOL_TOKEN_ACCOUNT,
+    crate::constants::USD1_TOKEN_ACCOUNT,
+    crate::constants::USDC_TOKEN_ACCOUNT,
+];
+
+/// Pool of `base_mint` under the first of `QUOTE_MINTS` it exists for, loaded together with the
+/// base mint in one `getMultipleAccounts` call
+///
+/// Returns the pool address, its state, the token program of `base_mint` and the quote mint.
+/// All `QUOTE_MINTS` are spl-token mints, so their program is known.
+pub async fn find_pool_by_base_mint(
+    rpc: &SolanaRpcClient,
+    base_mint: &Pubkey,
+) -> Result<(Pubkey, PoolState, Pubkey, Pubkey), anyhow::Error> {
+    let pools: Vec<(Pubkey, Pubkey)> = QUOTE_MINTS
+        .iter()
+        .filter_map(|quote_mint| {
+            get_pool_pda(base_mint, quote_mint).map(|pool| (pool, *quote_mint))
+        })
+        .collect();
+    let mut to_load: Vec<Pubkey> = pools.iter().map(|(pool, _)| *pool).collect();
+    to_load.push(*base_mint);
+    let mut loaded = crate::common::rpc_race::get_multiple_accounts(rpc, &to_load).await?;
+    let base_mint_account = loaded.pop().flatten().ok_or_else(|| {
+        AccountFetchError::new("base mint", *base_mint, AccountFetchFailure::NotFound)
+    })?;
+    for ((pool_address, quote_mint), account) in pools.iter().zip(loaded) {
+        let Some(account) = account.filter(|account| account.owner == accounts::BONK) else {
+            continue;
+        };
+        let pool_state = decode_pool_account(pool_address, &account)?;
+        return Ok((*pool_address, pool_state, base_mint_account.owner, *quote_mint));
     }
-    let pool_state = pool_state_decode(&account.data[8..])
-        .ok_or_else(|| anyhow!("Failed to decode pool state"))?;
-    Ok(pool_state)
+    Err(anyhow::anyhow!("No Bonk pool of {} quoted in WSOL, USD1 or USDC", base_mint))
+}
+
+/// Decode the pool state of the `pool_address` account
+pub fn decode_pool_account(
+    pool_address: &Pubkey,
+    account: &Account,
+) -> Result<PoolState, AccountFetchError> {
+    account.data.get(8..).and_then(pool_state_decode).ok_or_else(|| {
+        AccountFetchError::new(
+            "pool",
+            *pool_address,
+            AccountFetchFailure::Invalid("failed to decode pool state".to_string()),
+        )
+    })
 }
 
 pub fn get_amount_in_net(
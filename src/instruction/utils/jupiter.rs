@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use crate::instruction::data::JupiterSharedAccountsRouteArgs;
+use anyhow::anyhow;
+use base64::Engine;
+use serde::Deserialize;
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey};
+
+/// Constants used as seeds for deriving PDAs (Program Derived Addresses)
+pub mod seeds {
+    pub const AUTHORITY_SEED: &[u8] = b"authority";
+}
+
+/// Constants related to program accounts and authorities
+pub mod accounts {
+    use solana_sdk::{pubkey, pubkey::Pubkey};
+    pub const JUPITER: Pubkey = pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
+    pub const EVENT_AUTHORITY: Pubkey = pubkey!("D8cy77BBepLMngZx6ZukaTff5hCt1HrWyKk3Hnd9oitf");
+    // META
+    pub const JUPITER_META: solana_sdk::instruction::AccountMeta =
+        solana_sdk::instruction::AccountMeta {
+            pubkey: JUPITER,
+            is_signer: false,
+            is_writable: false,
+        };
+    pub const EVENT_AUTHORITY_META: solana_sdk::instruction::AccountMeta =
+        solana_sdk::instruction::AccountMeta {
+            pubkey: EVENT_AUTHORITY,
+            is_signer: false,
+            is_writable: false,
+        };
+}
+
+pub const SHARED_ACCOUNTS_ROUTE_DISCRIMINATOR: &[u8] = &[193, 32, 155, 51, 65, 214, 156, 129];
+
+/// Public quote API, `/quote` and `/swap-instructions` are appended
+pub const QUOTE_API_URL: &str = "https://lite-api.jup.ag/swap/v1";
+const QUOTE_API_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Number of fixed accounts of `shared_accounts_route`, the route accounts follow them
+pub const SHARED_ACCOUNTS_ROUTE_FIXED_ACCOUNTS: usize = 13;
+
+/// Program authority `id` that owns the shared token accounts of a route
+pub fn get_program_authority_pda(id: u8) -> Option<Pubkey> {
+    let seeds: &[&[u8]; 2] = &[seeds::AUTHORITY_SEED, &[id]];
+    let program_id: &Pubkey = &accounts::JUPITER;
+    let pda: Option<(Pubkey, u8)> = Pubkey::try_find_program_address(seeds, program_id);
+    pda.map(|pubkey| pubkey.0)
+}
+
+/// A route quoted by the quote API, as the parts of its `shared_accounts_route` instruction
+#[derive(Debug, Clone)]
+pub struct QuotedRoute {
+    pub args: JupiterSharedAccountsRouteArgs,
+    pub route_accounts: Vec<AccountMeta>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SwapInstructionsResponse {
+    swap_instruction: ApiInstruction,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiInstruction {
+    program_id: String,
+    accounts: Vec<ApiAccountMeta>,
+    data: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiAccountMeta {
+    pubkey: String,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+/// Quote `amount` of `input_mint` to `output_mint` and fetch its shared accounts route
+///
+/// Two HTTP round trips: `/quote`, then `/swap-instructions` for `user`.
+pub async fn fetch_quoted_route(
+    user: &Pubkey,
+    input_mint: &Pubkey,
+    output_mint: &Pubkey,
+    amount: u64,
+    slippage_bps: u64,
+) -> Result<QuotedRoute, anyhow::Error> {
+    let client = reqwest::Client::builder().timeout(QUOTE_API_TIMEOUT).build()?;
+    let quote: serde_json::Value = client
+        .get(format!("{}/quote", QUOTE_API_URL))
+        .query(&[
+            ("inputMint", input_mint.to_string()),
+            ("outputMint", output_mint.to_string()),
+            ("amount", amount.to_string()),
+            ("slippageBps", slippage_bps.to_string()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let response: SwapInstructionsResponse = client
+        .post(format!("{}/swap-instructions", QUOTE_API_URL))
+        .json(&serde_json::json!({
+            "quoteResponse": quote,
+            "userPublicKey": user.to_string(),
+            "useSharedAccounts": true,
+            "wrapAndUnwrapSol": false,
+        }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    let instruction = response.swap_instruction;
+    if instruction.program_id.parse::<Pubkey>()? != accounts::JUPITER {
+        return Err(anyhow!("Quote API returned an instruction for {}", instruction.program_id));
+    }
+    let data = base64::engine::general_purpose::STANDARD.decode(&instruction.data)?;
+    let args = JupiterSharedAccountsRouteArgs::decode(&data)
+        .ok_or_else(|| anyhow!("Quote API did not return a shared_accounts_route instruction"))?;
+    let route_accounts = instruction
+        .accounts
+        .iter()
+        .skip(SHARED_ACCOUNTS_ROUTE_FIXED_ACCOUNTS)
+        .map(|meta| {
+            Ok(AccountMeta {
+                pubkey: meta.pubkey.parse()?,
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            })
+        })
+        .collect::<Result<Vec<_>, anyhow::Error>>()?;
+    Ok(QuotedRoute { args, route_accounts })
+}
@@ -0,0 +1,162 @@
+use crate::common::account_fetch::{
+    fetch_owned_account, token_account_amount, AccountFetchError, AccountFetchFailure,
+};
+use crate::common::SolanaRpcClient;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+/// Constants used as seeds for deriving PDAs (Program Derived Addresses)
+pub mod seeds {
+    pub const BIN_ARRAY_SEED: &[u8] = b"bin_array";
+    pub const BIN_ARRAY_BITMAP_SEED: &[u8] = b"bitmap";
+}
+
+/// Constants related to program accounts and authorities
+pub mod accounts {
+    use solana_sdk::{pubkey, pubkey::Pubkey};
+    pub const METEORA_DLMM: Pubkey = pubkey!("LBUZKhRxPF3XUpBCjp4YzTKgLccjZhTSDM9YuVaPwxo");
+    pub const EVENT_AUTHORITY: Pubkey = pubkey!("D1ZN9Wj1fRSUQfCjhvnu1hqDMT7hzjzBBpi12nVniYD6");
+    /// Bins held by one bin array
+    pub const MAX_BIN_PER_ARRAY: i32 = 70;
+    /// Bin arrays passed to a swap, starting at the one holding the active bin
+    pub const SWAP_BIN_ARRAYS: i64 = 3;
+    /// Bin array indexes covered by the bitmap of the lb pair, others need the bitmap extension
+    pub const BIN_ARRAY_BITMAP_RANGE: std::ops::RangeInclusive<i64> = -512..=511;
+    // META
+    pub const METEORA_DLMM_META: solana_sdk::instruction::AccountMeta =
+        solana_sdk::instruction::AccountMeta {
+            pubkey: METEORA_DLMM,
+            is_signer: false,
+            is_writable: false,
+        };
+    pub const EVENT_AUTHORITY_META: solana_sdk::instruction::AccountMeta =
+        solana_sdk::instruction::AccountMeta {
+            pubkey: EVENT_AUTHORITY,
+            is_signer: false,
+            is_writable: false,
+        };
+}
+
+pub const SWAP_DISCRIMINATOR: &[u8] = &[248, 198, 158, 145, 225, 117, 135, 200];
+
+/// Fields of an `LbPair` account the swap needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LbPair {
+    pub base_factor: u16,
+    pub base_fee_power_factor: u8,
+    pub active_id: i32,
+    pub bin_step: u16,
+    pub token_x_mint: Pubkey,
+    pub token_y_mint: Pubkey,
+    pub reserve_x: Pubkey,
+    pub reserve_y: Pubkey,
+    pub oracle: Pubkey,
+    pub token_x_program: Pubkey,
+    pub token_y_program: Pubkey,
+}
+
+/// Decode an `LbPair` account without its 8 byte discriminator
+pub fn lb_pair_decode(data: &[u8]) -> Option<LbPair> {
+    let pubkey_at = |offset: usize| -> Option<Pubkey> {
+        Some(Pubkey::new_from_array(data.get(offset..offset + 32)?.try_into().ok()?))
+    };
+    let token_program = |flag: u8| match flag {
+        0 => Some(crate::constants::TOKEN_PROGRAM),
+        1 => Some(crate::constants::TOKEN_PROGRAM_2022),
+        _ => None,
+    };
+    Some(LbPair {
+        base_factor: u16::from_le_bytes(data.get(0..2)?.try_into().ok()?),
+        base_fee_power_factor: *data.get(26)?,
+        active_id: i32::from_le_bytes(data.get(68..72)?.try_into().ok()?),
+        bin_step: u16::from_le_bytes(data.get(72..74)?.try_into().ok()?),
+        token_x_mint: pubkey_at(80)?,
+        token_y_mint: pubkey_at(112)?,
+        reserve_x: pubkey_at(144)?,
+        reserve_y: pubkey_at(176)?,
+        oracle: pubkey_at(544)?,
+        token_x_program: token_program(*data.get(872)?)?,
+        token_y_program: token_program(*data.get(873)?)?,
+    })
+}
+
+pub async fn fetch_lb_pair(
+    rpc: &SolanaRpcClient,
+    lb_pair: &Pubkey,
+) -> Result<LbPair, anyhow::Error> {
+    let account = fetch_owned_account(rpc, "lb pair", lb_pair, &accounts::METEORA_DLMM).await?;
+    let lb_pair_state = account.data.get(8..).and_then(lb_pair_decode).ok_or_else(|| {
+        AccountFetchError::new(
+            "lb pair",
+            *lb_pair,
+            AccountFetchFailure::Invalid("failed to decode lb pair".to_string()),
+        )
+    })?;
+    Ok(lb_pair_state)
+}
+
+/// Index of the bin array holding `bin_id`
+pub fn bin_array_index(bin_id: i32) -> i64 {
+    bin_id.div_euclid(accounts::MAX_BIN_PER_ARRAY) as i64
+}
+
+pub fn get_bin_array_pda(lb_pair: &Pubkey, index: i64) -> Option<Pubkey> {
+    let seeds: &[&[u8]; 3] = &[seeds::BIN_ARRAY_SEED, lb_pair.as_ref(), &index.to_le_bytes()];
+    let program_id: &Pubkey = &accounts::METEORA_DLMM;
+    let pda: Option<(Pubkey, u8)> = Pubkey::try_find_program_address(seeds, program_id);
+    pda.map(|pubkey| pubkey.0)
+}
+
+pub fn get_bin_array_bitmap_extension_pda(lb_pair: &Pubkey) -> Option<Pubkey> {
+    let seeds: &[&[u8]; 2] = &[seeds::BIN_ARRAY_BITMAP_SEED, lb_pair.as_ref()];
+    let program_id: &Pubkey = &accounts::METEORA_DLMM;
+    let pda: Option<(Pubkey, u8)> = Pubkey::try_find_program_address(seeds, program_id);
+    pda.map(|pubkey| pubkey.0)
+}
+
+/// Bin arrays a swap from `active_id` crosses, in the order the program walks them
+///
+/// Selling X for Y moves to lower bins, selling Y for X to higher bins.
+pub fn swap_bin_array_indexes(active_id: i32, swap_for_y: bool) -> impl Iterator<Item = i64> {
+    let start = bin_array_index(active_id);
+    (0..accounts::SWAP_BIN_ARRAYS)
+        .map(move |step| if swap_for_y { start - step } else { start + step })
+}
+
+/// Reserves, bitmap extension and the initialized bin arrays around the active bin, loaded in
+/// one `getMultipleAccounts` call
+///
+/// # Returns
+/// Returns reserve_x_amount, reserve_y_amount, bin_array_bitmap_extension, bin_arrays
+pub async fn fetch_swap_accounts(
+    rpc: &SolanaRpcClient,
+    lb_pair: &Pubkey,
+    lb_pair_state: &LbPair,
+) -> Result<(u64, u64, Option<Pubkey>, Vec<Pubkey>), anyhow::Error> {
+    let bitmap_extension = get_bin_array_bitmap_extension_pda(lb_pair)
+        .ok_or_else(|| anyhow::anyhow!("Failed to derive bin array bitmap extension"))?;
+    let active_index = bin_array_index(lb_pair_state.active_id);
+    let reach = accounts::SWAP_BIN_ARRAYS - 1;
+    let bin_arrays: Vec<Pubkey> = (active_index - reach..=active_index + reach)
+        .filter_map(|index| get_bin_array_pda(lb_pair, index))
+        .collect();
+
+    let mut addresses = vec![lb_pair_state.reserve_x, lb_pair_state.reserve_y, bitmap_extension];
+    addresses.extend(&bin_arrays);
+    let fetched = rpc.get_multiple_accounts(&addresses).await?;
+
+    let reserve_amount =
+        |role: &'static str, address: &Pubkey, account: &Option<Account>| match account {
+            Some(account) => token_account_amount(role, address, account),
+            None => Err(AccountFetchError::new(role, *address, AccountFetchFailure::NotFound)),
+        };
+    let reserve_x_amount = reserve_amount("reserve x", &lb_pair_state.reserve_x, &fetched[0])?;
+    let reserve_y_amount = reserve_amount("reserve y", &lb_pair_state.reserve_y, &fetched[1])?;
+    let bitmap_extension = fetched[2].as_ref().map(|_| bitmap_extension);
+    let bin_arrays = bin_arrays
+        .into_iter()
+        .zip(&fetched[3..])
+        .filter(|(_, account)| account.is_some())
+        .map(|(address, _)| address)
+        .collect();
+    Ok((reserve_x_amount, reserve_y_amount, bitmap_extension, bin_arrays))
+}
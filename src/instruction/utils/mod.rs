@@ -1,5 +1,8 @@
 pub mod bonk;
+pub mod jupiter;
+pub mod meteora_dlmm;
 pub mod pumpfun;
 pub mod pumpswap;
 pub mod raydium_amm_v4;
+pub mod raydium_clmm;
 pub mod raydium_cpmm;
@@ -1,7 +1,14 @@
+use crate::common::fee_accounts::{fee_account, FeeAccount};
 use crate::common::{global::GlobalAccount, SolanaRpcClient};
+use crate::instruction::data::{InstructionData, PumpFunBuyArgs};
+use crate::solana_streamer_sdk::streaming::event_parser::protocols::pumpfun::types::BondingCurve;
 use crate::solana_streamer_sdk::streaming::event_parser::protocols::pumpfun::PumpFunTradeEvent;
 use anyhow::anyhow;
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    transaction::{Transaction, TransactionError},
+};
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::RwLock;
 
@@ -16,6 +23,9 @@ pub mod seeds {
     /// Seed for metadata PDAs
     pub const METADATA_SEED: &[u8] = b"metadata";
 
+    /// Seed for the mint authority PDA
+    pub const MINT_AUTHORITY_SEED: &[u8] = b"mint-authority";
+
     /// Seed for user volume accumulator PDAs
     pub const USER_VOLUME_ACCUMULATOR_SEED: &[u8] = b"user_volume_accumulator";
 
@@ -147,6 +157,10 @@ pub mod accounts {
         };
 }
 
+pub const BUY_DISCRIMINATOR: [u8; 8] = [102, 6, 61, 18, 1, 218, 235, 234];
+pub const SELL_DISCRIMINATOR: [u8; 8] = [51, 230, 133, 164, 1, 127, 131, 173];
+pub const CREATE_DISCRIMINATOR: [u8; 8] = [24, 30, 200, 40, 5, 28, 7, 119];
+
 pub struct Symbol;
 
 impl Symbol {
@@ -199,6 +213,25 @@ pub fn get_creator_vault_pda(creator: &Pubkey) -> Option<Pubkey> {
     )
 }
 
+/// Mint authority PumpFun assigns to the mints it creates
+#[inline]
+pub fn get_mint_authority_pda() -> Pubkey {
+    static MINT_AUTHORITY: std::sync::LazyLock<Pubkey> = std::sync::LazyLock::new(|| {
+        Pubkey::find_program_address(&[seeds::MINT_AUTHORITY_SEED], &accounts::PUMPFUN).0
+    });
+    *MINT_AUTHORITY
+}
+
+/// Metaplex metadata account of `mint`
+#[inline]
+pub fn get_metadata_pda(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[seeds::METADATA_SEED, accounts::MPL_TOKEN_METADATA.as_ref(), mint.as_ref()],
+        &accounts::MPL_TOKEN_METADATA,
+    )
+    .0
+}
+
 #[inline]
 pub fn get_user_volume_accumulator_pda(user: &Pubkey) -> Option<Pubkey> {
     crate::common::fast_fn::get_cached_pda(
@@ -221,16 +254,20 @@ pub async fn fetch_bonding_curve_account(
         get_bonding_curve_pda(mint).ok_or(anyhow!("Bonding curve not found"))?;
 
     let account = rpc.get_account(&bonding_curve_pda).await?;
-    if account.data.is_empty() {
-        return Err(anyhow!("Bonding curve not found"));
-    }
-
-    let bonding_curve = solana_sdk::borsh1::try_from_slice_unchecked::<crate::solana_streamer_sdk::streaming::event_parser::protocols::pumpfun::types::BondingCurve>(&account.data[8..])
-        .map_err(|e| anyhow::anyhow!("Failed to deserialize bonding curve account: {}", e))?;
+    let bonding_curve = decode_bonding_curve(&account.data)?;
 
     Ok((Arc::new(bonding_curve), bonding_curve_pda))
 }
 
+/// Decode the raw data of a bonding curve account
+pub fn decode_bonding_curve(data: &[u8]) -> Result<BondingCurve, anyhow::Error> {
+    if data.is_empty() {
+        return Err(anyhow!("Bonding curve not found"));
+    }
+    solana_sdk::borsh1::try_from_slice_unchecked::<BondingCurve>(&data[8..])
+        .map_err(|e| anyhow::anyhow!("Failed to deserialize bonding curve account: {}", e))
+}
+
 #[inline]
 pub fn get_buy_price(amount: u64, trade_info: &PumpFunTradeEvent) -> u64 {
     if amount == 0 {
@@ -246,3 +283,350 @@ pub fn get_buy_price(amount: u64, trade_info: &PumpFunTradeEvent) -> u64 {
 
     s_u64.min(trade_info.real_token_reserves)
 }
+
+/// Account layout of the PumpFun buy/sell instructions
+///
+/// pump.fun has changed the account lists of its instructions across program upgrades.
+/// `V2` is the layout of the current program and the default. `V1` is the previous layout,
+/// without the volume accumulators and fee config accounts.
+///
+/// Upgrade path: when the program changes its layout again, a new variant is added and becomes
+/// the default, while the older variants stay selectable through `PumpFunParams::with_layout`.
+/// Use [`detect_layout`] to find out at runtime which layout the deployed program accepts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PumpFunLayout {
+    /// Buy: 12 accounts, sell: 12 accounts
+    V1,
+    /// Buy: 16 accounts (volume accumulators + fee config), or 14 without the accumulators, see
+    /// `PumpFunParams::with_volume_accumulators`; sell: 14 accounts (fee config)
+    #[default]
+    V2,
+}
+
+/// Accounts shared by the PumpFun buy and sell instructions
+pub struct PumpFunTradeAccounts {
+    pub mint: Pubkey,
+    pub bonding_curve: Pubkey,
+    pub associated_bonding_curve: Pubkey,
+    pub user_token_account: Pubkey,
+    pub user: Pubkey,
+    pub creator_vault: Pubkey,
+}
+
+impl PumpFunLayout {
+    /// Account metas of the buy instruction for this layout
+    pub fn buy_accounts(&self, a: &PumpFunTradeAccounts) -> Vec<AccountMeta> {
+        self.buy_accounts_with(a, true)
+    }
+
+    /// Account metas of the buy instruction, leaving out the global and user volume
+    /// accumulators when `include_volume_accumulators` is false
+    ///
+    /// The program still accepts the `V2` buy without them, 14 accounts long, but the trade then
+    /// earns no volume rewards. `V1` has no accumulators either way.
+    pub fn buy_accounts_with(
+        &self,
+        a: &PumpFunTradeAccounts,
+        include_volume_accumulators: bool,
+    ) -> Vec<AccountMeta> {
+        let mut metas = Vec::with_capacity(16);
+        metas.extend_from_slice(&[
+            global_constants::GLOBAL_ACCOUNT_META,
+            AccountMeta::new(fee_account(FeeAccount::PumpFunFeeRecipient), false),
+            AccountMeta::new_readonly(a.mint, false),
+            AccountMeta::new(a.bonding_curve, false),
+            AccountMeta::new(a.associated_bonding_curve, false),
+            AccountMeta::new(a.user_token_account, false),
+            AccountMeta::new(a.user, true),
+            crate::constants::SYSTEM_PROGRAM_META,
+            crate::constants::TOKEN_PROGRAM_META,
+            AccountMeta::new(a.creator_vault, false),
+            accounts::EVENT_AUTHORITY_META,
+            accounts::PUMPFUN_META,
+        ]);
+        if *self == PumpFunLayout::V2 {
+            if include_volume_accumulators {
+                let user_volume_accumulator = get_user_volume_accumulator_pda(&a.user).unwrap();
+                metas.extend_from_slice(&[
+                    accounts::GLOBAL_VOLUME_ACCUMULATOR_META,
+                    AccountMeta::new(user_volume_accumulator, false),
+                ]);
+            }
+            metas.extend_from_slice(&[accounts::FEE_CONFIG_META, accounts::FEE_PROGRAM_META]);
+        }
+        metas
+    }
+
+    /// Account metas of the sell instruction for this layout
+    pub fn sell_accounts(&self, a: &PumpFunTradeAccounts) -> Vec<AccountMeta> {
+        let mut metas = Vec::with_capacity(14);
+        metas.extend_from_slice(&[
+            global_constants::GLOBAL_ACCOUNT_META,
+            AccountMeta::new(fee_account(FeeAccount::PumpFunFeeRecipient), false),
+            AccountMeta::new_readonly(a.mint, false),
+            AccountMeta::new(a.bonding_curve, false),
+            AccountMeta::new(a.associated_bonding_curve, false),
+            AccountMeta::new(a.user_token_account, false),
+            AccountMeta::new(a.user, true),
+            crate::constants::SYSTEM_PROGRAM_META,
+            AccountMeta::new(a.creator_vault, false),
+            crate::constants::TOKEN_PROGRAM_META,
+            accounts::EVENT_AUTHORITY_META,
+            accounts::PUMPFUN_META,
+        ]);
+        if *self == PumpFunLayout::V2 {
+            metas.extend_from_slice(&[accounts::FEE_CONFIG_META, accounts::FEE_PROGRAM_META]);
+        }
+        metas
+    }
+}
+
+/// `BondingCurveComplete`: the curve completed and the token migrated
+pub const BONDING_CURVE_COMPLETE_ERROR_CODE: u32 = 6005;
+
+/// Whether a trade was rejected because its bonding curve completed
+///
+/// Matches the custom program error in the transaction error of a failed confirmation or
+/// preflight simulation.
+pub fn is_bonding_curve_complete_error(error: &anyhow::Error) -> bool {
+    let code = format!("custom program error: {:#x}", BONDING_CURVE_COMPLETE_ERROR_CODE);
+    let message = error.to_string();
+    message.contains(&code) || message.contains("BondingCurveComplete")
+}
+
+/// Anchor errors returned when the account list does not match what the program expects
+const LAYOUT_MISMATCH_ERROR_CODES: [u32; 5] = [
+    2006, // ConstraintSeeds
+    3005, // AccountNotEnoughKeys
+    3007, // AccountOwnedByWrongProgram
+    3008, // InvalidProgramId
+    3012, // AccountNotInitialized
+];
+
+/// Errors the buy can only return once the program has validated its accounts
+const POST_VALIDATION_ERROR_CODES: [u32; 3] = [
+    1,    // System program ResultWithNegativeLamports: the user cannot pay for the tokens
+    6002, // TooMuchSolRequired
+    6005, // BondingCurveComplete
+];
+
+/// What a simulated buy says about its account layout
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum LayoutProbe {
+    /// The program validated the account list
+    Accepted,
+    /// The program rejected the account list
+    Rejected,
+    /// The simulation failed before or for a reason unrelated to the account list
+    Inconclusive(TransactionError),
+}
+
+/// Classify the error of a simulated `[create user ATA, buy]` transaction
+fn probe_result(err: Option<TransactionError>) -> LayoutProbe {
+    match err {
+        None => LayoutProbe::Accepted,
+        Some(TransactionError::InstructionError(1, InstructionError::Custom(code)))
+            if POST_VALIDATION_ERROR_CODES.contains(&code) =>
+        {
+            LayoutProbe::Accepted
+        }
+        Some(TransactionError::InstructionError(1, InstructionError::Custom(code)))
+            if LAYOUT_MISMATCH_ERROR_CODES.contains(&code) =>
+        {
+            LayoutProbe::Rejected
+        }
+        Some(TransactionError::InstructionError(1, InstructionError::NotEnoughAccountKeys)) => {
+            LayoutProbe::Rejected
+        }
+        Some(err) => LayoutProbe::Inconclusive(err),
+    }
+}
+
+/// Detect which instruction layout the deployed PumpFun program accepts
+///
+/// Simulates a minimal buy of `mint` for `user` (signature verification disabled, nothing is
+/// sent) with every layout, newest first, and returns the first layout whose account list the
+/// program accepts. A layout only counts as accepted when the simulation succeeds or fails
+/// with an error the program returns after validating the accounts, such as not enough SOL
+/// for the tokens. `user` must therefore be an existing account able to pay the transaction
+/// fee and the token account rent; any other failure, e.g. an unfunded or nonexistent user,
+/// is returned as an error instead of picking a layout.
+pub async fn detect_layout(
+    rpc: &SolanaRpcClient,
+    user: &Pubkey,
+    mint: &Pubkey,
+    creator_vault: &Pubkey,
+) -> Result<PumpFunLayout, anyhow::Error> {
+    let bonding_curve = get_bonding_curve_pda(mint).ok_or(anyhow!("Bonding curve not found"))?;
+    let trade_accounts = PumpFunTradeAccounts {
+        mint: *mint,
+        bonding_curve,
+        associated_bonding_curve:
+            crate::common::fast_fn::get_associated_token_address_with_program_id_fast(
+                &bonding_curve,
+                mint,
+                &crate::constants::TOKEN_PROGRAM,
+            ),
+        user_token_account:
+            crate::common::fast_fn::get_associated_token_address_with_program_id_fast(
+                user,
+                mint,
+                &crate::constants::TOKEN_PROGRAM,
+            ),
+        user: *user,
+        creator_vault: *creator_vault,
+    };
+
+    let data = PumpFunBuyArgs { amount: 1, max_sol_cost: u64::MAX }.encode();
+
+    let config = solana_rpc_client_api::config::RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: true,
+        ..Default::default()
+    };
+
+    for layout in [PumpFunLayout::V2, PumpFunLayout::V1] {
+        let instructions = vec![
+            spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                user,
+                user,
+                mint,
+                &crate::constants::TOKEN_PROGRAM,
+            ),
+            Instruction::new_with_bytes(
+                accounts::PUMPFUN,
+                &data,
+                layout.buy_accounts(&trade_accounts),
+            ),
+        ];
+        let transaction = Transaction::new_with_payer(&instructions, Some(user));
+        let result = rpc.simulate_transaction_with_config(&transaction, config.clone()).await?;
+        match probe_result(result.value.err) {
+            LayoutProbe::Accepted => return Ok(layout),
+            LayoutProbe::Rejected => continue,
+            LayoutProbe::Inconclusive(err) => {
+                return Err(anyhow!(
+                    "Cannot detect the PumpFun layout, the {:?} simulation failed: {}",
+                    layout,
+                    err
+                ))
+            }
+        }
+    }
+
+    Err(anyhow!("No known PumpFun instruction layout is accepted by the program"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{SYSTEM_PROGRAM, TOKEN_PROGRAM};
+
+    fn trade_accounts() -> PumpFunTradeAccounts {
+        PumpFunTradeAccounts {
+            mint: Pubkey::new_unique(),
+            bonding_curve: Pubkey::new_unique(),
+            associated_bonding_curve: Pubkey::new_unique(),
+            user_token_account: Pubkey::new_unique(),
+            user: Pubkey::new_unique(),
+            creator_vault: Pubkey::new_unique(),
+        }
+    }
+
+    /// Leading accounts of the buy, shared by every layout
+    fn buy_head(a: &PumpFunTradeAccounts) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new_readonly(global_constants::GLOBAL_ACCOUNT, false),
+            AccountMeta::new(global_constants::FEE_RECIPIENT, false),
+            AccountMeta::new_readonly(a.mint, false),
+            AccountMeta::new(a.bonding_curve, false),
+            AccountMeta::new(a.associated_bonding_curve, false),
+            AccountMeta::new(a.user_token_account, false),
+            AccountMeta::new(a.user, true),
+            AccountMeta::new_readonly(SYSTEM_PROGRAM, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM, false),
+            AccountMeta::new(a.creator_vault, false),
+            AccountMeta::new_readonly(accounts::EVENT_AUTHORITY, false),
+            AccountMeta::new_readonly(accounts::PUMPFUN, false),
+        ]
+    }
+
+    /// Leading accounts of the sell, the creator vault comes before the token program
+    fn sell_head(a: &PumpFunTradeAccounts) -> Vec<AccountMeta> {
+        let mut metas = buy_head(a);
+        metas.swap(8, 9);
+        metas
+    }
+
+    fn fee_config() -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new_readonly(accounts::FEE_CONFIG, false),
+            AccountMeta::new_readonly(accounts::FEE_PROGRAM, false),
+        ]
+    }
+
+    #[test]
+    fn v1_buy_has_no_accumulators_or_fee_config() {
+        let a = trade_accounts();
+        assert_eq!(PumpFunLayout::V1.buy_accounts(&a), buy_head(&a));
+        assert_eq!(PumpFunLayout::V1.buy_accounts_with(&a, false), buy_head(&a));
+    }
+
+    #[test]
+    fn v2_buy_appends_accumulators_then_fee_config() {
+        let a = trade_accounts();
+        let mut expected = buy_head(&a);
+        expected.extend([
+            AccountMeta::new(accounts::GLOBAL_VOLUME_ACCUMULATOR, false),
+            AccountMeta::new(get_user_volume_accumulator_pda(&a.user).unwrap(), false),
+        ]);
+        expected.extend(fee_config());
+        assert_eq!(PumpFunLayout::V2.buy_accounts(&a), expected);
+        assert_eq!(PumpFunLayout::default().buy_accounts(&a).len(), 16);
+    }
+
+    #[test]
+    fn v2_buy_without_accumulators_keeps_fee_config() {
+        let a = trade_accounts();
+        let mut expected = buy_head(&a);
+        expected.extend(fee_config());
+        assert_eq!(PumpFunLayout::V2.buy_accounts_with(&a, false), expected);
+    }
+
+    #[test]
+    fn sell_layouts_differ_by_fee_config() {
+        let a = trade_accounts();
+        assert_eq!(PumpFunLayout::V1.sell_accounts(&a), sell_head(&a));
+        let mut expected = sell_head(&a);
+        expected.extend(fee_config());
+        assert_eq!(PumpFunLayout::V2.sell_accounts(&a), expected);
+    }
+
+    #[test]
+    fn only_success_or_post_validation_errors_accept_a_layout() {
+        let buy_error = |error| Some(TransactionError::InstructionError(1, error));
+        assert_eq!(probe_result(None), LayoutProbe::Accepted);
+        assert_eq!(probe_result(buy_error(InstructionError::Custom(1))), LayoutProbe::Accepted);
+        assert_eq!(
+            probe_result(buy_error(InstructionError::Custom(BONDING_CURVE_COMPLETE_ERROR_CODE))),
+            LayoutProbe::Accepted
+        );
+        assert_eq!(probe_result(buy_error(InstructionError::Custom(3005))), LayoutProbe::Rejected);
+        assert_eq!(
+            probe_result(buy_error(InstructionError::NotEnoughAccountKeys)),
+            LayoutProbe::Rejected
+        );
+    }
+
+    #[test]
+    fn an_unfunded_or_missing_user_is_inconclusive() {
+        for err in [
+            TransactionError::AccountNotFound,
+            TransactionError::InsufficientFundsForFee,
+            TransactionError::InstructionError(0, InstructionError::Custom(1)),
+            TransactionError::InstructionError(1, InstructionError::Custom(6003)),
+        ] {
+            assert_eq!(probe_result(Some(err.clone())), LayoutProbe::Inconclusive(err));
+        }
+    }
+}
@@ -1,7 +1,11 @@
-use crate::{common::SolanaRpcClient, constants::TOKEN_PROGRAM};
+use crate::common::account_fetch::{
+    fetch_accounts, fetch_owned_account, fetch_token_amounts, token_account_amount,
+    AccountFetchError, AccountFetchFailure,
+};
+use crate::common::SolanaRpcClient;
 use anyhow::anyhow;
 use solana_account_decoder::UiAccountEncoding;
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{account::Account, pubkey::Pubkey};
 use solana_streamer_sdk::streaming::event_parser::protocols::pumpswap::types::{pool_decode, Pool};
 
 /// Constants used as seeds for deriving PDAs (Program Derived Addresses)
@@ -142,23 +146,59 @@ pub(crate) fn coin_creator_vault_authority(coin_creator: Pubkey) -> Pubkey {
     pump_pool_authority
 }
 
-pub(crate) fn coin_creator_vault_ata(coin_creator: Pubkey, quote_mint: Pubkey) -> Pubkey {
+pub(crate) fn coin_creator_vault_ata(
+    coin_creator: Pubkey,
+    quote_mint: Pubkey,
+    quote_token_program: &Pubkey,
+) -> Pubkey {
     let creator_vault_authority = coin_creator_vault_authority(coin_creator);
     let associated_token_creator_vault_authority =
         spl_associated_token_account::get_associated_token_address_with_program_id(
             &creator_vault_authority,
             &quote_mint,
-            &TOKEN_PROGRAM,
+            quote_token_program,
         );
     associated_token_creator_vault_authority
 }
 
-pub(crate) fn fee_recipient_ata(fee_recipient: Pubkey, quote_mint: Pubkey) -> Pubkey {
+/// Creator vault accounts `(coin_creator_vault_ata, coin_creator_vault_authority)` for a pool
+///
+/// The buy/sell instructions take these accounts positionally, so they must be present even for
+/// pools whose `coin_creator` is `Pubkey::default()`. For such pools the program charges no
+/// creator fee and never touches the vault, so the default-creator accounts are derived once
+/// and reused instead of being re-derived on every trade.
+pub(crate) fn coin_creator_vault_accounts(
+    coin_creator: Pubkey,
+    quote_mint: Pubkey,
+    quote_token_program: &Pubkey,
+) -> (Pubkey, Pubkey) {
+    if crate::utils::calc::pumpswap::has_coin_creator(&coin_creator) {
+        return (
+            coin_creator_vault_ata(coin_creator, quote_mint, quote_token_program),
+            coin_creator_vault_authority(coin_creator),
+        );
+    }
+    static DEFAULT_VAULT_AUTHORITY: std::sync::LazyLock<Pubkey> =
+        std::sync::LazyLock::new(|| coin_creator_vault_authority(Pubkey::default()));
+    let authority = *DEFAULT_VAULT_AUTHORITY;
+    let ata = crate::common::fast_fn::get_associated_token_address_with_program_id_fast(
+        &authority,
+        &quote_mint,
+        quote_token_program,
+    );
+    (ata, authority)
+}
+
+pub(crate) fn fee_recipient_ata(
+    fee_recipient: Pubkey,
+    quote_mint: Pubkey,
+    quote_token_program: &Pubkey,
+) -> Pubkey {
     let associated_token_fee_recipient =
         crate::common::fast_fn::get_associated_token_address_with_program_id_fast(
             &fee_recipient,
             &quote_mint,
-            &TOKEN_PROGRAM,
+            quote_token_program,
         );
     associated_token_fee_recipient
 }
@@ -186,12 +226,22 @@ pub async fn fetch_pool(
     rpc: &SolanaRpcClient,
     pool_address: &Pubkey,
 ) -> Result<Pool, anyhow::Error> {
-    let account = rpc.get_account(pool_address).await?;
-    if account.owner != accounts::AMM_PROGRAM {
-        return Err(anyhow!("Account is not owned by PumpSwap program"));
-    }
-    let pool = pool_decode(&account.data[8..]).ok_or_else(|| anyhow!("Failed to decode pool"))?;
-    Ok(pool)
+    let account = fetch_owned_account(rpc, "pool", pool_address, &accounts::AMM_PROGRAM).await?;
+    Ok(decode_pool_account(pool_address, &account)?)
+}
+
+/// Decode the pool of the `pool_address` account
+pub fn decode_pool_account(
+    pool_address: &Pubkey,
+    account: &Account,
+) -> Result<Pool, AccountFetchError> {
+    account.data.get(8..).and_then(pool_decode).ok_or_else(|| {
+        AccountFetchError::new(
+            "pool",
+            *pool_address,
+            AccountFetchFailure::Invalid("failed to decode pool".to_string()),
+        )
+    })
 }
 
 pub async fn find_by_base_mint(
@@ -279,17 +329,49 @@ pub async fn find_by_mint(
     Err(anyhow!("No pool found for mint {}", mint))
 }
 
+/// Base and quote reserves of `pool`, both vaults loaded in one `getMultipleAccounts` call
 pub async fn get_token_balances(
     pool: &Pool,
     rpc: &SolanaRpcClient,
 ) -> Result<(u64, u64), anyhow::Error> {
-    let base_balance = rpc.get_token_account_balance(&pool.pool_base_token_account).await?;
-    let quote_balance = rpc.get_token_account_balance(&pool.pool_quote_token_account).await?;
-
-    let base_amount = base_balance.amount.parse::<u64>().map_err(|e| anyhow!(e))?;
-    let quote_amount = quote_balance.amount.parse::<u64>().map_err(|e| anyhow!(e))?;
+    Ok(fetch_token_amounts(
+        rpc,
+        ("pool base token account", pool.pool_base_token_account),
+        ("pool quote token account", pool.pool_quote_token_account),
+    )
+    .await?)
+}
 
-    Ok((base_amount, quote_amount))
+/// Reserves of `pool` and the token programs owning its mints, the two vaults and two mints
+/// loaded in one `getMultipleAccounts` call
+///
+/// # Returns
+/// Returns base_reserve, quote_reserve, base_token_program, quote_token_program
+pub async fn get_token_balances_and_programs(
+    pool: &Pool,
+    rpc: &SolanaRpcClient,
+) -> Result<(u64, u64, Pubkey, Pubkey), anyhow::Error> {
+    let accounts = fetch_accounts(
+        rpc,
+        &[
+            ("pool base token account", pool.pool_base_token_account),
+            ("pool quote token account", pool.pool_quote_token_account),
+            ("base mint", pool.base_mint),
+            ("quote mint", pool.quote_mint),
+        ],
+    )
+    .await?;
+    let base_reserve = token_account_amount(
+        "pool base token account",
+        &pool.pool_base_token_account,
+        &accounts[0],
+    )?;
+    let quote_reserve = token_account_amount(
+        "pool quote token account",
+        &pool.pool_quote_token_account,
+        &accounts[1],
+    )?;
+    Ok((base_reserve, quote_reserve, accounts[2].owner, accounts[3].owner))
 }
 
 #[inline]
@@ -1,9 +1,12 @@
-use anyhow::anyhow;
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{account::Account, pubkey::Pubkey};
 use solana_streamer_sdk::streaming::event_parser::protocols::raydium_amm_v4::types::{
     amm_info_decode, AmmInfo,
 };
 
+use crate::common::account_fetch::{
+    check_owner, fetch_accounts, fetch_owned_account, token_account_amount, AccountFetchError,
+    AccountFetchFailure,
+};
 use crate::common::SolanaRpcClient;
 
 /// Constants used as seeds for deriving PDAs (Program Derived Addresses)
@@ -36,8 +39,196 @@ pub const SWAP_BASE_IN_DISCRIMINATOR: &[u8] = &[9];
 pub const SWAP_BASE_OUT_DISCRIMINATOR: &[u8] = &[11];
 
 pub async fn fetch_amm_info(rpc: &SolanaRpcClient, amm: Pubkey) -> Result<AmmInfo, anyhow::Error> {
-    let amm_info = rpc.get_account_data(&amm).await?;
-    let amm_info =
-        amm_info_decode(&amm_info).ok_or_else(|| anyhow!("Failed to decode amm info"))?;
-    Ok(amm_info)
+    let account = fetch_owned_account(rpc, "amm", &amm, &accounts::RAYDIUM_AMM_V4).await?;
+    Ok(decode_amm_account(&amm, &account)?)
+}
+
+/// Decode the amm info of the `amm` account
+pub fn decode_amm_account(amm: &Pubkey, account: &Account) -> Result<AmmInfo, AccountFetchError> {
+    amm_info_decode(&account.data).ok_or_else(|| {
+        AccountFetchError::new(
+            "amm",
+            *amm,
+            AccountFetchFailure::Invalid("failed to decode amm info".to_string()),
+        )
+    })
+}
+
+/// Accounts of the OpenBook (serum) market an amm trades against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerumMarket {
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub event_queue: Pubkey,
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+    /// PDA of the market and its vault signer nonce under the serum program
+    pub vault_signer: Pubkey,
+}
+
+// Offsets in the market account, after the 5 byte "serum" head padding
+const VAULT_SIGNER_NONCE_OFFSET: usize = 45;
+const COIN_VAULT_OFFSET: usize = 117;
+const PC_VAULT_OFFSET: usize = 165;
+const EVENT_QUEUE_OFFSET: usize = 253;
+const BIDS_OFFSET: usize = 285;
+const ASKS_OFFSET: usize = 317;
+
+/// Decode the accounts of `SerumMarket` from the raw account data of `market`
+pub fn decode_market(market: &Pubkey, serum_dex: &Pubkey, data: &[u8]) -> Option<SerumMarket> {
+    let pubkey_at = |offset: usize| -> Option<Pubkey> {
+        Some(Pubkey::new_from_array(data.get(offset..offset + 32)?.try_into().ok()?))
+    };
+    let vault_signer_nonce = u64::from_le_bytes(
+        data.get(VAULT_SIGNER_NONCE_OFFSET..VAULT_SIGNER_NONCE_OFFSET + 8)?.try_into().ok()?,
+    );
+    Some(SerumMarket {
+        bids: pubkey_at(BIDS_OFFSET)?,
+        asks: pubkey_at(ASKS_OFFSET)?,
+        event_queue: pubkey_at(EVENT_QUEUE_OFFSET)?,
+        coin_vault: pubkey_at(COIN_VAULT_OFFSET)?,
+        pc_vault: pubkey_at(PC_VAULT_OFFSET)?,
+        vault_signer: get_vault_signer(market, vault_signer_nonce, serum_dex)?,
+    })
+}
+
+/// Vault signer of a market, `None` when the nonce does not give a valid PDA
+pub fn get_vault_signer(
+    market: &Pubkey,
+    vault_signer_nonce: u64,
+    serum_dex: &Pubkey,
+) -> Option<Pubkey> {
+    Pubkey::create_program_address(&[market.as_ref(), &vault_signer_nonce.to_le_bytes()], serum_dex)
+        .ok()
+}
+
+/// Load and decode the market account, checking it is owned by `serum_dex`
+pub async fn fetch_market(
+    rpc: &SolanaRpcClient,
+    market: &Pubkey,
+    serum_dex: &Pubkey,
+) -> Result<SerumMarket, anyhow::Error> {
+    let account = fetch_owned_account(rpc, "market", market, serum_dex).await?;
+    Ok(decode_market_account(market, serum_dex, &account.data)?)
+}
+
+/// Reserves of the two vaults of `amm_info` and its serum market, loaded in one
+/// `getMultipleAccounts` call
+///
+/// # Returns
+/// Returns coin_reserve, pc_reserve, market
+pub async fn fetch_reserves_and_market(
+    rpc: &SolanaRpcClient,
+    amm_info: &AmmInfo,
+) -> Result<(u64, u64, SerumMarket), anyhow::Error> {
+    let loaded = fetch_accounts(
+        rpc,
+        &[
+            ("coin vault", amm_info.token_coin),
+            ("pc vault", amm_info.token_pc),
+            ("market", amm_info.market),
+        ],
+    )
+    .await?;
+    let coin_reserve = token_account_amount("coin vault", &amm_info.token_coin, &loaded[0])?;
+    let pc_reserve = token_account_amount("pc vault", &amm_info.token_pc, &loaded[1])?;
+    check_owner("market", &amm_info.market, &loaded[2], &amm_info.serum_dex)?;
+    let market = decode_market_account(&amm_info.market, &amm_info.serum_dex, &loaded[2].data)?;
+    Ok((coin_reserve, pc_reserve, market))
+}
+
+fn decode_market_account(
+    market: &Pubkey,
+    serum_dex: &Pubkey,
+    data: &[u8],
+) -> Result<SerumMarket, AccountFetchError> {
+    decode_market(market, serum_dex, data).ok_or_else(|| {
+        AccountFetchError::new(
+            "market",
+            *market,
+            AccountFetchFailure::Invalid("failed to decode serum market".to_string()),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde_json::Value;
+
+    use super::*;
+    use crate::common::fake_chain::FakeChain;
+
+    /// An OpenBook market account with its decoded accounts and vault signer, computed outside
+    /// this crate, and a nonce whose address falls on the curve
+    const MARKET_FIXTURE: &str = include_str!("../../../tests/fixtures/serum_market.json");
+
+    fn pubkey(value: &Value) -> Pubkey {
+        Pubkey::from_str(value.as_str().unwrap()).unwrap()
+    }
+
+    /// Market address, serum program, account data and the fixture
+    fn market_fixture() -> (Pubkey, Pubkey, Vec<u8>, Value) {
+        let fixture: Value = serde_json::from_str(MARKET_FIXTURE).unwrap();
+        let data = STANDARD.decode(fixture["data"].as_str().unwrap()).unwrap();
+        (pubkey(&fixture["market"]), pubkey(&fixture["owner"]), data, fixture)
+    }
+
+    #[test]
+    fn market_decodes_with_the_derived_vault_signer() {
+        let (market, serum_dex, data, fixture) = market_fixture();
+        let decoded = &fixture["decoded"];
+        assert_eq!(
+            decode_market(&market, &serum_dex, &data),
+            Some(SerumMarket {
+                bids: pubkey(&decoded["bids"]),
+                asks: pubkey(&decoded["asks"]),
+                event_queue: pubkey(&decoded["event_queue"]),
+                coin_vault: pubkey(&decoded["coin_vault"]),
+                pc_vault: pubkey(&decoded["pc_vault"]),
+                vault_signer: pubkey(&decoded["vault_signer"]),
+            })
+        );
+        let nonce = decoded["vault_signer_nonce"].as_u64().unwrap();
+        assert_eq!(
+            get_vault_signer(&market, nonce, &serum_dex),
+            Some(pubkey(&decoded["vault_signer"]))
+        );
+        // The signer is derived from the market address, not read from the account
+        assert_ne!(
+            decode_market(&Pubkey::new_unique(), &serum_dex, &data).map(|m| m.vault_signer),
+            Some(pubkey(&decoded["vault_signer"]))
+        );
+    }
+
+    #[test]
+    fn market_with_an_invalid_nonce_or_short_data_does_not_decode() {
+        let (market, serum_dex, mut data, fixture) = market_fixture();
+        assert_eq!(decode_market(&market, &serum_dex, &data[..ASKS_OFFSET + 31]), None);
+
+        let nonce = fixture["on_curve_nonce"].as_u64().unwrap();
+        assert_eq!(get_vault_signer(&market, nonce, &serum_dex), None);
+        data[VAULT_SIGNER_NONCE_OFFSET..VAULT_SIGNER_NONCE_OFFSET + 8]
+            .copy_from_slice(&nonce.to_le_bytes());
+        assert_eq!(decode_market(&market, &serum_dex, &data), None);
+    }
+
+    #[tokio::test]
+    async fn fetch_market_checks_the_owner() {
+        let (market, serum_dex, data, fixture) = market_fixture();
+        let account = Account { lamports: 3_591_360, data, owner: serum_dex, ..Default::default() };
+
+        let (rpc, requests) = FakeChain::default().with(market, account.clone()).client();
+        let loaded = fetch_market(&rpc, &market, &serum_dex).await.unwrap();
+        assert_eq!(loaded.vault_signer, pubkey(&fixture["decoded"]["vault_signer"]));
+        assert_eq!(requests.lock().len(), 1);
+
+        let (rpc, _) = FakeChain::default().with(market, account).client();
+        let error = fetch_market(&rpc, &market, &Pubkey::new_unique()).await.unwrap_err();
+        let error = error.downcast_ref::<AccountFetchError>().unwrap();
+        assert_eq!((error.failed[0].role, error.failed[0].address), ("market", market));
+        assert!(matches!(error.failed[0].failure, AccountFetchFailure::Invalid(_)));
+    }
 }
@@ -0,0 +1,432 @@
+use std::collections::HashSet;
+
+use anyhow::anyhow;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::common::account_fetch::{
+    fetch_accounts, fetch_owned_account, AccountFetchError, AccountFetchFailure,
+};
+use crate::common::SolanaRpcClient;
+use crate::instruction::data::RAYDIUM_CLMM_PROGRAM;
+
+/// Constants used as seeds for deriving PDAs (Program Derived Addresses)
+pub mod seeds {
+    pub const TICK_ARRAY_SEED: &[u8] = b"tick_array";
+    pub const TICK_ARRAY_BITMAP_EXTENSION_SEED: &[u8] = b"pool_tick_array_bitmap_extension";
+}
+
+/// Constants related to program accounts
+pub mod accounts {
+    use solana_sdk::{pubkey, pubkey::Pubkey};
+    /// spl-memo v2, required by `swap_v2`
+    pub const MEMO_PROGRAM: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+}
+
+/// Tick arrays passed to a swap, the current one and the next ones in swap direction
+pub const SWAP_TICK_ARRAY_COUNT: i32 = 3;
+
+/// Ticks stored in one tick array account, in units of the tick spacing
+pub const TICK_ARRAY_SIZE: i32 = 60;
+pub const MIN_TICK: i32 = -443636;
+pub const MAX_TICK: i32 = 443636;
+
+/// The tick arrays passed to a swap end before the price limit of the swap
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "InsufficientTickArrays: {provided} tick arrays reach start index {furthest_start_index}, \
+     the price limit needs {required_start_index}"
+)]
+pub struct InsufficientTickArrays {
+    pub provided: usize,
+    /// Start index of the tick array holding the price limit
+    pub required_start_index: i32,
+    /// Start index of the last tick array in swap direction
+    pub furthest_start_index: i32,
+}
+
+/// Fields of a CLMM pool state account needed to trade on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClmmPoolState {
+    pub amm_config: Pubkey,
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    pub token_vault_0: Pubkey,
+    pub token_vault_1: Pubkey,
+    pub observation_key: Pubkey,
+    pub tick_spacing: u16,
+    pub liquidity: u128,
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+}
+
+// Offsets in the pool state account, after the 8 byte discriminator and the bump
+const AMM_CONFIG_OFFSET: usize = 9;
+const TOKEN_MINT_0_OFFSET: usize = 73;
+const TOKEN_MINT_1_OFFSET: usize = 105;
+const TOKEN_VAULT_0_OFFSET: usize = 137;
+const TOKEN_VAULT_1_OFFSET: usize = 169;
+const OBSERVATION_KEY_OFFSET: usize = 201;
+const TICK_SPACING_OFFSET: usize = 235;
+const LIQUIDITY_OFFSET: usize = 237;
+const SQRT_PRICE_X64_OFFSET: usize = 253;
+const TICK_CURRENT_OFFSET: usize = 269;
+
+/// Decode the fields of `ClmmPoolState` from raw pool state account data
+pub fn decode_pool_state(data: &[u8]) -> Option<ClmmPoolState> {
+    let pubkey_at = |offset: usize| -> Option<Pubkey> {
+        Some(Pubkey::new_from_array(data.get(offset..offset + 32)?.try_into().ok()?))
+    };
+    let u128_at = |offset: usize| -> Option<u128> {
+        Some(u128::from_le_bytes(data.get(offset..offset + 16)?.try_into().ok()?))
+    };
+    Some(ClmmPoolState {
+        amm_config: pubkey_at(AMM_CONFIG_OFFSET)?,
+        token_mint_0: pubkey_at(TOKEN_MINT_0_OFFSET)?,
+        token_mint_1: pubkey_at(TOKEN_MINT_1_OFFSET)?,
+        token_vault_0: pubkey_at(TOKEN_VAULT_0_OFFSET)?,
+        token_vault_1: pubkey_at(TOKEN_VAULT_1_OFFSET)?,
+        observation_key: pubkey_at(OBSERVATION_KEY_OFFSET)?,
+        tick_spacing: u16::from_le_bytes(
+            data.get(TICK_SPACING_OFFSET..TICK_SPACING_OFFSET + 2)?.try_into().ok()?,
+        ),
+        liquidity: u128_at(LIQUIDITY_OFFSET)?,
+        sqrt_price_x64: u128_at(SQRT_PRICE_X64_OFFSET)?,
+        tick_current: i32::from_le_bytes(
+            data.get(TICK_CURRENT_OFFSET..TICK_CURRENT_OFFSET + 4)?.try_into().ok()?,
+        ),
+    })
+}
+
+/// Load and decode the pool state account, checking it is owned by the CLMM program
+pub async fn fetch_pool_state(
+    rpc: &SolanaRpcClient,
+    pool_address: &Pubkey,
+) -> Result<ClmmPoolState, anyhow::Error> {
+    let account = fetch_owned_account(rpc, "pool", pool_address, &RAYDIUM_CLMM_PROGRAM).await?;
+    let pool_state = decode_pool_state(&account.data).ok_or_else(|| {
+        AccountFetchError::new(
+            "pool",
+            *pool_address,
+            AccountFetchFailure::Invalid("failed to decode pool state".to_string()),
+        )
+    })?;
+    Ok(pool_state)
+}
+
+/// Token programs owning `token_mint_0` and `token_mint_1` of the pool, in that order
+pub async fn fetch_mint_token_programs(
+    rpc: &SolanaRpcClient,
+    pool_state: &ClmmPoolState,
+) -> Result<(Pubkey, Pubkey), anyhow::Error> {
+    let mints = fetch_accounts(
+        rpc,
+        &[("token_mint_0", pool_state.token_mint_0), ("token_mint_1", pool_state.token_mint_1)],
+    )
+    .await?;
+    Ok((mints[0].owner, mints[1].owner))
+}
+
+/// The `SWAP_TICK_ARRAY_COUNT` tick arrays a swap starting at `tick_current` crosses first
+///
+/// The array holding `tick_current` comes first, followed by its neighbours towards lower
+/// ticks when `zero_for_one` (the price moves down) and towards higher ticks otherwise.
+/// Arrays past the tick range are left out.
+pub fn swap_tick_arrays(
+    pool_state: &Pubkey,
+    tick_current: i32,
+    tick_spacing: u16,
+    zero_for_one: bool,
+) -> Vec<Pubkey> {
+    let step = TICK_ARRAY_SIZE * tick_spacing as i32;
+    let first_start = tick_array_start_index(MIN_TICK, tick_spacing);
+    let last_start = tick_array_start_index(MAX_TICK, tick_spacing);
+    let start_index = tick_array_start_index(tick_current, tick_spacing);
+    (0..SWAP_TICK_ARRAY_COUNT)
+        .map(|i| if zero_for_one { start_index - i * step } else { start_index + i * step })
+        .filter(|start_index| (first_start..=last_start).contains(start_index))
+        .map(|start_index| get_tick_array_pda(pool_state, start_index))
+        .collect()
+}
+
+pub fn get_tick_array_pda(pool_state: &Pubkey, start_index: i32) -> Pubkey {
+    let seeds: &[&[u8]; 3] =
+        &[seeds::TICK_ARRAY_SEED, pool_state.as_ref(), &start_index.to_be_bytes()];
+    Pubkey::find_program_address(seeds, &RAYDIUM_CLMM_PROGRAM).0
+}
+
+pub fn get_tick_array_bitmap_extension_pda(pool_state: &Pubkey) -> Pubkey {
+    let seeds: &[&[u8]; 2] = &[seeds::TICK_ARRAY_BITMAP_EXTENSION_SEED, pool_state.as_ref()];
+    Pubkey::find_program_address(seeds, &RAYDIUM_CLMM_PROGRAM).0
+}
+
+/// Start index of the tick array containing `tick`
+pub fn tick_array_start_index(tick: i32, tick_spacing: u16) -> i32 {
+    let ticks_in_array = TICK_ARRAY_SIZE * tick_spacing as i32;
+    tick.div_euclid(ticks_in_array) * ticks_in_array
+}
+
+/// Tick of a Q64.64 sqrt price, rounded down
+pub fn tick_at_sqrt_price(sqrt_price_x64: u128) -> i32 {
+    let sqrt_price = sqrt_price_x64 as f64 / (1u128 << 64) as f64;
+    let tick = (2.0 * sqrt_price.ln() / 1.0001f64.ln()).floor() as i32;
+    tick.clamp(MIN_TICK, MAX_TICK)
+}
+
+/// Order `tick_arrays` the way a swap crosses them
+///
+/// A swap of token0 for token1 (`zero_for_one`) moves the price down and crosses the arrays in
+/// descending start index from the one holding `tick_current`, the other direction in ascending
+/// order. The bitmap extension account, if passed, stays in front. Arrays on the wrong side of
+/// the current tick are an error, and with a `sqrt_price_limit_x64` set the arrays must reach
+/// the array holding the limit, otherwise `InsufficientTickArrays` is returned.
+pub fn order_tick_arrays(
+    pool_state: &Pubkey,
+    tick_arrays: &[Pubkey],
+    tick_current: i32,
+    tick_spacing: u16,
+    zero_for_one: bool,
+    sqrt_price_limit_x64: u128,
+) -> Result<Vec<Pubkey>, anyhow::Error> {
+    if tick_spacing == 0 {
+        return Err(anyhow!("tick_spacing is not set"));
+    }
+    let bitmap_extension = get_tick_array_bitmap_extension_pda(pool_state);
+    let mut remaining: HashSet<Pubkey> =
+        tick_arrays.iter().filter(|account| **account != bitmap_extension).copied().collect();
+    if remaining.is_empty() {
+        return Err(anyhow!("No tick arrays provided"));
+    }
+
+    let mut ordered = Vec::with_capacity(tick_arrays.len());
+    if tick_arrays.contains(&bitmap_extension) {
+        ordered.push(bitmap_extension);
+    }
+    let step = TICK_ARRAY_SIZE * tick_spacing as i32;
+    let first_start = tick_array_start_index(MIN_TICK, tick_spacing);
+    let last_start = tick_array_start_index(MAX_TICK, tick_spacing);
+    let mut start_index = tick_array_start_index(tick_current, tick_spacing);
+    let mut furthest_start_index = start_index;
+    while !remaining.is_empty() && (first_start..=last_start).contains(&start_index) {
+        let tick_array = get_tick_array_pda(pool_state, start_index);
+        if remaining.remove(&tick_array) {
+            ordered.push(tick_array);
+            furthest_start_index = start_index;
+        }
+        start_index += if zero_for_one { -step } else { step };
+    }
+    if let Some(tick_array) = remaining.iter().next() {
+        return Err(anyhow!(
+            "Tick array {} is not a tick array of pool {} in swap direction from tick {}",
+            tick_array,
+            pool_state,
+            tick_current
+        ));
+    }
+
+    if sqrt_price_limit_x64 > 0 {
+        let required_start_index =
+            tick_array_start_index(tick_at_sqrt_price(sqrt_price_limit_x64), tick_spacing);
+        let covered = if zero_for_one {
+            furthest_start_index <= required_start_index
+        } else {
+            furthest_start_index >= required_start_index
+        };
+        if !covered {
+            return Err(InsufficientTickArrays {
+                provided: tick_arrays.len(),
+                required_start_index,
+                furthest_start_index,
+            }
+            .into());
+        }
+    }
+    Ok(ordered)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde_json::Value;
+    use solana_sdk::account::Account;
+
+    use super::*;
+    use crate::{
+        common::fake_chain::FakeChain,
+        constants::{TOKEN_PROGRAM, TOKEN_PROGRAM_2022},
+        params::RaydiumClmmV2Params,
+    };
+
+    /// A buy crossing three tick arrays and sells crossing four, with the arrays passed out of
+    /// order, computed outside this crate from the CLMM program's tick array seeds
+    const FIXTURES: &str = include_str!("../../../tests/fixtures/clmm_tick_arrays.json");
+
+    /// A pool state account laid out like the CLMM program's `PoolState`, with its decoded
+    /// fields and the tick arrays of both swap directions, computed outside this crate
+    const POOL_FIXTURE: &str = include_str!("../../../tests/fixtures/clmm_pool_state.json");
+
+    fn pubkeys(value: &Value) -> Vec<Pubkey> {
+        value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|pubkey| Pubkey::from_str(pubkey.as_str().unwrap()).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn tick_arrays_are_ordered_like_the_committed_fixtures() {
+        let fixtures: Value = serde_json::from_str(FIXTURES).unwrap();
+        let pool_state = Pubkey::from_str(fixtures["pool_state"].as_str().unwrap()).unwrap();
+        let bitmap_extension = get_tick_array_bitmap_extension_pda(&pool_state);
+        for case in fixtures["cases"].as_array().unwrap() {
+            let name = case["name"].as_str().unwrap();
+            let tick_current = case["tick_current"].as_i64().unwrap() as i32;
+            let tick_spacing = case["tick_spacing"].as_u64().unwrap() as u16;
+            let zero_for_one = case["zero_for_one"].as_bool().unwrap();
+            let sqrt_price_limit_x64: u128 =
+                case["sqrt_price_limit_x64"].as_str().unwrap().parse().unwrap();
+            let ordered = order_tick_arrays(
+                &pool_state,
+                &pubkeys(&case["provided"]),
+                tick_current,
+                tick_spacing,
+                zero_for_one,
+                sqrt_price_limit_x64,
+            );
+
+            if let Some(expected) = case.get("error") {
+                let expected = InsufficientTickArrays {
+                    provided: expected["provided"].as_u64().unwrap() as usize,
+                    required_start_index: expected["required_start_index"].as_i64().unwrap() as i32,
+                    furthest_start_index: expected["furthest_start_index"].as_i64().unwrap() as i32,
+                };
+                let error = ordered.expect_err(name);
+                assert_eq!(
+                    error.downcast_ref::<InsufficientTickArrays>(),
+                    Some(&expected),
+                    "{}",
+                    name
+                );
+                continue;
+            }
+            let expected = pubkeys(&case["expected"]);
+            assert_eq!(ordered.expect(name), expected, "{}", name);
+
+            // The arrays derived for the swap are the first ones of the expected order
+            let derived = swap_tick_arrays(&pool_state, tick_current, tick_spacing, zero_for_one);
+            let expected_arrays: Vec<Pubkey> =
+                expected.into_iter().filter(|account| *account != bitmap_extension).collect();
+            assert_eq!(derived, expected_arrays[..SWAP_TICK_ARRAY_COUNT as usize], "{}", name);
+        }
+    }
+
+    #[test]
+    fn tick_arrays_of_another_pool_or_direction_are_rejected() {
+        let pool_state = Pubkey::new_unique();
+        let tick_arrays = swap_tick_arrays(&pool_state, 12_345, 60, true);
+        assert!(order_tick_arrays(&pool_state, &tick_arrays, 12_345, 60, false, 0).is_err());
+        let other_pool = swap_tick_arrays(&Pubkey::new_unique(), 12_345, 60, true);
+        assert!(order_tick_arrays(&pool_state, &other_pool, 12_345, 60, true, 0).is_err());
+        assert!(order_tick_arrays(&pool_state, &[], 12_345, 60, true, 0).is_err());
+        assert!(order_tick_arrays(&pool_state, &tick_arrays, 12_345, 0, true, 0).is_err());
+    }
+
+    fn pubkey(value: &Value) -> Pubkey {
+        Pubkey::from_str(value.as_str().unwrap()).unwrap()
+    }
+
+    fn pool_fixture() -> (Pubkey, Account, Value) {
+        let fixture: Value = serde_json::from_str(POOL_FIXTURE).unwrap();
+        let account = Account {
+            lamports: 11_637_120,
+            data: STANDARD.decode(fixture["data"].as_str().unwrap()).unwrap(),
+            owner: pubkey(&fixture["owner"]),
+            ..Default::default()
+        };
+        (pubkey(&fixture["pool_state"]), account, fixture)
+    }
+
+    #[test]
+    fn pool_state_decodes_like_the_fixture() {
+        let (_, account, fixture) = pool_fixture();
+        let decoded = &fixture["decoded"];
+        assert_eq!(
+            decode_pool_state(&account.data),
+            Some(ClmmPoolState {
+                amm_config: pubkey(&decoded["amm_config"]),
+                token_mint_0: pubkey(&decoded["token_mint_0"]),
+                token_mint_1: pubkey(&decoded["token_mint_1"]),
+                token_vault_0: pubkey(&decoded["token_vault_0"]),
+                token_vault_1: pubkey(&decoded["token_vault_1"]),
+                observation_key: pubkey(&decoded["observation_key"]),
+                tick_spacing: decoded["tick_spacing"].as_u64().unwrap() as u16,
+                liquidity: decoded["liquidity"].as_str().unwrap().parse().unwrap(),
+                sqrt_price_x64: decoded["sqrt_price_x64"].as_str().unwrap().parse().unwrap(),
+                tick_current: decoded["tick_current"].as_i64().unwrap() as i32,
+            })
+        );
+        assert_eq!(decode_pool_state(&account.data[..TICK_CURRENT_OFFSET + 3]), None);
+    }
+
+    #[tokio::test]
+    async fn rpc_constructor_derives_the_accounts_of_both_directions() {
+        let (pool_state, account, fixture) = pool_fixture();
+        let decoded = &fixture["decoded"];
+        let (mint_0, mint_1) = (pubkey(&decoded["token_mint_0"]), pubkey(&decoded["token_mint_1"]));
+        let (vault_0, vault_1) =
+            (pubkey(&decoded["token_vault_0"]), pubkey(&decoded["token_vault_1"]));
+        let mint = |token_program| Account {
+            lamports: 1_461_600,
+            data: vec![0; 82],
+            owner: token_program,
+            ..Default::default()
+        };
+
+        for (input_mint, direction) in [(mint_0, "zero_for_one"), (mint_1, "one_for_zero")] {
+            let (rpc, requests) = FakeChain::default()
+                .with(pool_state, account.clone())
+                .with(mint_0, mint(TOKEN_PROGRAM))
+                .with(mint_1, mint(TOKEN_PROGRAM_2022))
+                .client();
+            let params =
+                RaydiumClmmV2Params::from_pool_address_by_rpc(&rpc, &pool_state, &input_mint)
+                    .await
+                    .unwrap();
+            assert_eq!(requests.lock().len(), 2);
+
+            let zero_for_one = input_mint == mint_0;
+            let input = (params.input_vault, params.input_vault_mint, params.input_token_program);
+            let output =
+                (params.output_vault, params.output_vault_mint, params.output_token_program);
+            let side_0 = (vault_0, mint_0, TOKEN_PROGRAM);
+            let side_1 = (vault_1, mint_1, TOKEN_PROGRAM_2022);
+            if zero_for_one {
+                assert_eq!((input, output), (side_0, side_1));
+            } else {
+                assert_eq!((input, output), (side_1, side_0));
+            }
+            assert_eq!(params.amm_config, pubkey(&decoded["amm_config"]));
+            assert_eq!(params.observation_state, pubkey(&decoded["observation_key"]));
+            assert_eq!(
+                params.tick_arrays,
+                pubkeys(&fixture["tick_arrays"][direction]),
+                "{}",
+                direction
+            );
+            assert_eq!((params.tick_spacing, params.tick_current), (10, -18_215));
+        }
+    }
+
+    #[tokio::test]
+    async fn rpc_constructor_rejects_a_mint_outside_the_pool() {
+        let (pool_state, account, _) = pool_fixture();
+        let (rpc, requests) = FakeChain::default().with(pool_state, account).client();
+        let input_mint = Pubkey::new_unique();
+        let result =
+            RaydiumClmmV2Params::from_pool_address_by_rpc(&rpc, &pool_state, &input_mint).await;
+        assert!(result.is_err());
+        assert_eq!(requests.lock().len(), 1);
+    }
+}
@@ -1,6 +1,9 @@
-use crate::{common::SolanaRpcClient, trading::core::params::RaydiumCpmmParams};
+use crate::common::account_fetch::{
+    fetch_owned_account, fetch_token_amounts, AccountFetchError, AccountFetchFailure,
+};
+use crate::{common::SolanaRpcClient, params::RaydiumCpmmParams};
 use anyhow::anyhow;
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{account::Account, pubkey::Pubkey};
 use solana_streamer_sdk::streaming::event_parser::protocols::raydium_cpmm::types::{
     pool_state_decode, PoolState,
 };
@@ -38,13 +41,22 @@ pub async fn fetch_pool_state(
     rpc: &SolanaRpcClient,
     pool_address: &Pubkey,
 ) -> Result<PoolState, anyhow::Error> {
-    let account = rpc.get_account(pool_address).await?;
-    if account.owner != accounts::RAYDIUM_CPMM {
-        return Err(anyhow!("Account is not owned by Raydium Cpmm program"));
-    }
-    let pool_state = pool_state_decode(&account.data[8..])
-        .ok_or_else(|| anyhow!("Failed to decode pool state"))?;
-    Ok(pool_state)
+    let account = fetch_owned_account(rpc, "pool", pool_address, &accounts::RAYDIUM_CPMM).await?;
+    Ok(decode_pool_account(pool_address, &account)?)
+}
+
+/// Decode the pool state of the `pool_address` account
+pub fn decode_pool_account(
+    pool_address: &Pubkey,
+    account: &Account,
+) -> Result<PoolState, AccountFetchError> {
+    account.data.get(8..).and_then(pool_state_decode).ok_or_else(|| {
+        AccountFetchError::new(
+            "pool",
+            *pool_address,
+            AccountFetchFailure::Invalid("failed to decode pool state".to_string()),
+        )
+    })
 }
 
 pub fn get_pool_pda(amm_config: &Pubkey, mint1: &Pubkey, mint2: &Pubkey) -> Option<Pubkey> {
@@ -69,7 +81,8 @@ pub fn get_observation_state_pda(pool_state: &Pubkey) -> Option<Pubkey> {
     pda.map(|pubkey| pubkey.0)
 }
 
-/// Get the balances of two tokens in the pool
+/// Get the balances of two tokens in the pool, both vaults loaded in one `getMultipleAccounts`
+/// call
 ///
 /// # Returns
 /// Returns token0_balance, token1_balance
@@ -79,23 +92,12 @@ pub async fn get_pool_token_balances(
     token0_mint: &Pubkey,
     token1_mint: &Pubkey,
 ) -> Result<(u64, u64), anyhow::Error> {
-    let token0_vault = get_vault_pda(pool_state, token0_mint).unwrap();
-    let token0_balance = rpc.get_token_account_balance(&token0_vault).await?;
-    let token1_vault = get_vault_pda(pool_state, token1_mint).unwrap();
-    let token1_balance = rpc.get_token_account_balance(&token1_vault).await?;
-
-    // Parse balance string to u64
-    let token0_amount = token0_balance
-        .amount
-        .parse::<u64>()
-        .map_err(|e| anyhow!("Failed to parse token0 balance: {}", e))?;
-
-    let token1_amount = token1_balance
-        .amount
-        .parse::<u64>()
-        .map_err(|e| anyhow!("Failed to parse token1 balance: {}", e))?;
-
-    Ok((token0_amount, token1_amount))
+    let token0_vault = get_vault_pda(pool_state, token0_mint)
+        .ok_or_else(|| anyhow!("Failed to derive token0 vault"))?;
+    let token1_vault = get_vault_pda(pool_state, token1_mint)
+        .ok_or_else(|| anyhow!("Failed to derive token1 vault"))?;
+    Ok(fetch_token_amounts(rpc, ("token0 vault", token0_vault), ("token1 vault", token1_vault))
+        .await?)
 }
 
 /// Calculate token price (token1/token0)
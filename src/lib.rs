@@ -1,6 +1,12 @@
+#[macro_use]
+mod console;
+
 pub mod common;
 pub mod constants;
 pub mod instruction;
+pub mod market;
+pub mod params;
+pub mod prelude;
 pub mod protos;
 pub mod swqos;
 pub mod trading;
@@ -9,31 +15,148 @@ use solana_sdk::signer::Signer;
 pub use solana_streamer_sdk;
 
 // Re-export TradeResult for external use
-pub use crate::trading::core::trade_result::TradeResult;
-
-use crate::constants::trade::trade::DEFAULT_SLIPPAGE;
-use crate::swqos::SwqosConfig;
-use crate::trading::core::params::BonkParams;
-use crate::trading::core::params::PumpFunParams;
-use crate::trading::core::params::PumpSwapParams;
-use crate::trading::core::params::RaydiumAmmV4Params;
-use crate::trading::core::params::RaydiumCpmmParams;
-use crate::trading::core::params::RaydiumClmmV2Params;
-use crate::instruction::raydium_clmm::RaydiumClmmParams;
+pub use crate::trading::core::simulation::SimulationResult;
+pub use crate::trading::core::trade_result::{AnalysisCallback, RawTradeAmounts, TradeResult};
+
+// Flattened public paths, e.g. `sol_trade_sdk::DexType`
+pub use crate::common::account_fetch::{AccountFetchError, AccountFetchFailure, FailedAccount};
+pub use crate::common::blockhash_cache::{BlockhashCache, BlockhashCacheError};
+pub use crate::common::commission::{Commission, CommissionOn};
+pub use crate::common::dex_defaults::{
+    AppliedDefaults, DefaultSource, DexDefaults, TradeAmountOutOfBounds,
+};
+pub use crate::common::fee_accounts::{
+    FeeAccount, FeeAccountObservation, FeeAccountObserver, FeeAccountRegistry, FeeAccountSource,
+    FeeObservationMode,
+};
+pub use crate::common::labels::TradeLabels;
+pub use crate::common::priority_fee_estimate::{PriorityFeeEstimate, PriorityFeeEstimator};
+pub use crate::common::signer::{KeypairSigner, TradeSigner};
+pub use crate::common::{
+    AnyResult, PriorityFee, TipAmount, TipStrategy, TradeConfig, TradeConfigError, TradeTypeFee,
+};
+pub use crate::swqos::{
+    auth::SwqosAuthError,
+    confirmation::{ConfirmationMode, TradeStages, WaitLevel},
+    health::SwqosHealth,
+    metrics::{LabeledSwqosStats, SwqosStats},
+    region::{RegionLatency, RegionProbe},
+    timing::{SubmitTiming, SwqosLatencyStats},
+    tip_account::TipAccountOverride,
+    RegionUnavailable, SwqosClient, SwqosClientTrait, SwqosConfig, SwqosRegion, SwqosType,
+    TradeType,
+};
+pub use crate::trading::factory::DexType;
+pub use crate::trading::core::curve_fallback::{
+    BondingCurveCompleted, CurveCompleteAction, CurveFallbackReport,
+};
+pub use crate::trading::core::batch_sell::SellLeg;
+pub use crate::trading::core::bundle::{BundleLeg, BundleReport, MAX_BUNDLE_TRANSACTIONS};
+pub use crate::trading::core::create::{CreatedToken, TokenMetadata};
+pub use crate::trading::core::error::{RelayError, TradeError};
+pub use crate::trading::core::options::{BuyOptions, SellOptions};
+pub use crate::trading::core::params::BuyAmountSpec;
+pub use crate::trading::core::parallel::{BlockhashRetry, QuorumNotMet, SuccessPolicy};
+pub use crate::trading::core::resume::{ResumeSide, ResumedTrade};
+pub use crate::trading::core::sequence::{ReleasedResults, SequenceBuffer};
+pub use crate::trading::core::smart_sell::{
+    optimal_split, SellVenue, SmartSellLeg, SmartSellReport,
+};
+pub use crate::trading::core::two_hop::{TwoHopMode, TwoHopReport};
+pub use crate::trading::core::venue::{detect_venue, SellAmount};
+pub use crate::trading::common::account_check::{CreatedAccountCheck, CreatedAccountIssue};
+pub use crate::trading::common::account_cleanup::{ClosableTokenAccount, TokenAccountCleanup};
+pub use crate::trading::common::cu_sizing::{clear_cu_sizing_cache, CuSizing};
+pub use crate::trading::common::swap_hooks::SwapHooks;
+pub use crate::trading::common::transaction_builder::{
+    estimate_transaction_size, TransactionTooLarge,
+};
+pub use crate::trading::common::token_safety::{transfer_restrictions, TransferRestriction};
+pub use crate::trading::common::wsol_manager::{
+    EphemeralWsolAccount, WsolAccountChoice, WsolBalanceUpdate, EPHEMERAL_WSOL_SLOTS,
+};
+pub use crate::trading::core::split::{
+    split_across_payers, SplitAcrossPayers, SplitBuyLeg, SplitBuyReport,
+};
+pub use crate::trading::guard::{GuardedSell, SellGuard};
+pub use crate::trading::ledger::{
+    utc_day, JsonlLedgerStore, Ledger, LedgerEntry, LedgerEntryKind, LedgerStore,
+    MemoryLedgerStore, PnlReport, ProtocolPnl,
+};
+pub use crate::trading::paper::{
+    FillModel, LatencySlippageFillModel, PaperEngine, PaperFill, PaperOrder, PaperPosition,
+};
+pub use crate::trading::position::{Position, PositionTracker, POSITION_SEQUENCE_TIMEOUT};
+pub use crate::trading::price::{get_price, get_prices, PriceInfo};
+pub use crate::trading::trigger::{
+    ParamsSource, TriggerCondition, TriggerEngine, TriggerEvent, TriggerOrder, TriggerSide,
+};
+pub use crate::trading::watcher::{
+    ExitRule, PositionWatcher, WatchEvent, WatchStatus, WatchedPosition,
+};
+
+use crate::constants::trade::trade::{
+    DEFAULT_DATA_SIZE_LIMIT, SPLIT_LEG_FEE_RESERVE_LAMPORTS, TWO_HOP_FEE_RESERVE_BPS,
+};
+use crate::constants::WSOL_TOKEN_ACCOUNT;
+use crate::instruction::utils::pumpfun::is_bonding_curve_complete_error;
+use crate::trading::common::account_check::check_created_accounts;
+use crate::trading::common::get_address_lookup_table_accounts;
+use crate::trading::common::wsol_manager::ephemeral_wsol_rent;
+use crate::trading::core::executor::{
+    append_commission, append_sell_commission, apply_spend_budget, record_spend,
+};
+use crate::trading::core::batch_sell::{leg_error, pack_sells, BuiltSellLeg, SELL_MANY_PROTOCOL};
+use crate::trading::core::bundle::{bundle_execute, BuiltBundleLeg, RAW_BUNDLE_LEG_PROTOCOL};
+use crate::trading::core::parallel::{
+    buy_parallel_execute, co_signed_parallel_execute, packed_sell_parallel_execute,
+    sell_tip_trade_lamports,
+};
+use crate::trading::common::{
+    get_sell_token_account_with_retry, get_sell_token_balance, BalanceRetry,
+};
+use crate::trading::core::resume::resume_tracking;
+use crate::trading::core::two_hop::{
+    check_single_transaction_fit, merge_two_hop_instructions, two_hop_priority_fee,
+};
+use crate::params::BonkParams;
+use crate::params::JupiterParams;
+use crate::params::MeteoraDlmmParams;
+use crate::params::PumpFunParams;
+use crate::params::PumpSwapParams;
+use crate::params::RaydiumAmmV4Params;
+use crate::params::RaydiumCpmmParams;
+use crate::params::RaydiumClmmParams;
+use crate::params::RaydiumClmmV2Params;
 use crate::trading::core::traits::ProtocolParams;
-use crate::trading::factory::DexType;
 use crate::trading::BuyParams;
 use crate::trading::MiddlewareManager;
 use crate::trading::SellParams;
 use crate::trading::TradeFactory;
-use common::{PriorityFee, SolanaRpcClient, TradeConfig};
+use common::commission::Commission;
+use common::labels::{memo_with_labels, validate_labels};
+use common::spend_budget::{DailySpend, SpendTracker};
+use common::store::KeyValueStore;
+use common::SolanaRpcClient;
+use futures::future::join_all;
 use parking_lot::Mutex;
 use rustls::crypto::{ring::default_provider, CryptoProvider};
 use solana_sdk::hash::Hash;
-use solana_sdk::{pubkey::Pubkey, signature::Keypair, signature::Signature};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, instruction::Instruction, pubkey::Pubkey,
+    signature::Keypair, signature::Signature, transaction::VersionedTransaction,
+};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use swqos::SwqosClient;
+use std::time::Instant;
 
+/// Trading client
+///
+/// `SolanaTrade` is `Send + Sync` and cheap to clone; clones share the RPC client, swqos
+/// clients, middleware and spend tracker, so one instance can be cloned into many tokio tasks.
+/// The global caches it relies on (PDA, ATA and instruction caches, nonce cache, address
+/// lookup table cache, token account rent) are safe for concurrent use: none of them holds a
+/// lock across an `.await` or while taking another lock. See the `concurrency_stress` example.
 pub struct SolanaTrade {
     pub payer: Arc<Keypair>,
     pub rpc: Arc<SolanaRpcClient>,
@@ -41,10 +164,57 @@ pub struct SolanaTrade {
     pub swqos_clients: Vec<Arc<SwqosClient>>,
     pub priority_fee: Arc<PriorityFee>,
     pub middleware_manager: Option<Arc<MiddlewareManager>>,
+    pub spend_tracker: Option<Arc<SpendTracker>>,
+    pub analysis_callback: Option<AnalysisCallback>,
+    pub clamp_sell_to_balance: bool,
+    pub commission: Option<Commission>,
+    /// Fraction digits of `format_amount`, all significant digits when `None`
+    pub amount_precision: Option<u8>,
+    /// How created token accounts that the swap does not use are reported, see `account_check`
+    pub created_account_check: CreatedAccountCheck,
+    /// Account trades wrap and unwrap SOL in, see `WsolAccountChoice`
+    pub wsol_account: WsolAccountChoice,
+    /// How trades choose their compute unit limit, see `CuSizing`
+    pub cu_sizing: CuSizing,
+    /// Defaults of each protocol, see `dex_defaults` for the resolution order
+    pub per_dex_defaults: Arc<HashMap<DexType, DexDefaults>>,
+    pub default_slippage_bps: Option<u64>,
+    /// Record buy and sell results in the `PositionTracker`, see `TradeConfig::track_position`
+    pub track_position: bool,
 }
 
 static INSTANCE: Mutex<Option<Arc<SolanaTrade>>> = Mutex::new(None);
 
+// Types users share across tokio tasks must stay Send + Sync
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SolanaTrade>();
+    assert_send_sync::<TradeConfig>();
+    assert_send_sync::<PriorityFee>();
+    assert_send_sync::<TradeResult>();
+    assert_send_sync::<BuyParams>();
+    assert_send_sync::<SellParams>();
+    assert_send_sync::<crate::trading::core::params::BuyWithTipParams>();
+    assert_send_sync::<crate::trading::core::params::SellWithTipParams>();
+    assert_send_sync::<PumpFunParams>();
+    assert_send_sync::<PumpSwapParams>();
+    assert_send_sync::<BonkParams>();
+    assert_send_sync::<RaydiumCpmmParams>();
+    assert_send_sync::<RaydiumClmmParams>();
+    assert_send_sync::<RaydiumClmmV2Params>();
+    assert_send_sync::<RaydiumAmmV4Params>();
+    assert_send_sync::<MiddlewareManager>();
+    assert_send_sync::<SpendTracker>();
+    assert_send_sync::<TriggerEngine>();
+    assert_send_sync::<common::store::MemoryStore>();
+    assert_send_sync::<common::store::FileStore>();
+    assert_send_sync::<common::nonce_cache::NonceCache>();
+    assert_send_sync::<trading::position::PositionTracker>();
+    assert_send_sync::<common::address_lookup_cache::AddressLookupTableCache>();
+    assert_send_sync::<common::mint_cache::MintDecimalsCache>();
+    assert_send_sync::<swqos::confirmation::ConfirmationService>();
+};
+
 impl Clone for SolanaTrade {
     fn clone(&self) -> Self {
         Self {
@@ -54,14 +224,97 @@ impl Clone for SolanaTrade {
             swqos_clients: self.swqos_clients.clone(),
             priority_fee: self.priority_fee.clone(),
             middleware_manager: self.middleware_manager.clone(),
+            spend_tracker: self.spend_tracker.clone(),
+            analysis_callback: self.analysis_callback.clone(),
+            clamp_sell_to_balance: self.clamp_sell_to_balance,
+            commission: self.commission,
+            amount_precision: self.amount_precision,
+            created_account_check: self.created_account_check,
+            wsol_account: self.wsol_account,
+            cu_sizing: self.cu_sizing,
+            per_dex_defaults: self.per_dex_defaults.clone(),
+            default_slippage_bps: self.default_slippage_bps,
+            track_position: self.track_position,
         }
     }
 }
 
+/// Signature of the transaction a `TradeResult` describes
+fn result_signature(trade_result: &TradeResult) -> Result<Signature, anyhow::Error> {
+    trade_result.signature.parse().map_err(|e| anyhow::anyhow!("Failed to parse signature: {}", e))
+}
+
+/// Record an analyzed `TradeResult` in the `PositionTracker`, pending results are recorded
+/// when their analysis is delivered
+fn record_position(trade_result: &TradeResult) {
+    if trade_result.analysis_pending {
+        return;
+    }
+    if let Err(e) = PositionTracker::get_instance().record(trade_result) {
+        log::warn!(icon!("⚠️ ", "Position of {} not updated: {}"), trade_result.token_mint, e);
+    }
+}
+
+/// Reject account flags `dex_type` cannot honor and warn about the ones it ignores
+///
+/// `create_mint_ata` is `None` for sells.
+fn check_account_flags(
+    dex_type: &DexType,
+    wsol_account: WsolAccountChoice,
+    create_wsol_ata: bool,
+    close_wsol_ata: bool,
+    create_mint_ata: Option<bool>,
+) -> Result<(), anyhow::Error> {
+    if dex_type.handles_wsol()
+        && wsol_account == WsolAccountChoice::EphemeralPerTrade
+        && !(create_wsol_ata && close_wsol_ata)
+    {
+        return Err(anyhow::anyhow!(
+            "WsolAccountChoice::EphemeralPerTrade creates and closes its account in the trade, \
+             create_wsol_ata and close_wsol_ata must both be set"
+        ));
+    }
+    if dex_type.uses_wsol() && !dex_type.handles_wsol() && (create_wsol_ata || close_wsol_ata) {
+        log::warn!(
+            icon!("⚠️ ", "{:?} does not wrap SOL, create_wsol_ata and close_wsol_ata are ignored"),
+            dex_type
+        );
+    }
+    if create_mint_ata == Some(false) {
+        log::warn!(
+            icon!("⚠️ ", "{:?} always creates the token account, create_mint_ata is ignored"),
+            dex_type
+        );
+    }
+    Ok(())
+}
+
 impl SolanaTrade {
+    /// Create a client, logging an invalid `trade_config` instead of rejecting it
+    ///
+    /// Trades the invalid setting affects fail before sending. Use `try_new` to get the error.
     #[inline]
     pub async fn new(payer: Arc<Keypair>, trade_config: TradeConfig) -> Self {
-        crate::common::fast_fn::fast_init(&payer.try_pubkey().unwrap());
+        if let Err(e) = trade_config.validate() {
+            log::error!(
+                icon!(" ❌ ", "Invalid trade config: {}, SolanaTrade::try_new rejects it up front"),
+                e
+            );
+        }
+        Self::init(payer, trade_config).await
+    }
+
+    /// Create a client, returning the `TradeConfigError` of an invalid `trade_config`
+    pub async fn try_new(
+        payer: Arc<Keypair>,
+        trade_config: TradeConfig,
+    ) -> Result<Self, TradeConfigError> {
+        trade_config.validate()?;
+        Ok(Self::init(payer, trade_config).await)
+    }
+
+    async fn init(payer: Arc<Keypair>, trade_config: TradeConfig) -> Self {
+        crate::common::fast_fn::fast_init_once(&payer.try_pubkey().unwrap());
 
         if CryptoProvider::get_default().is_none() {
             let _ = default_provider()
@@ -69,19 +322,48 @@ impl SolanaTrade {
                 .map_err(|e| anyhow::anyhow!("Failed to install crypto provider: {:?}", e));
         }
 
+        // Trades convert legacy tips silently, report the corrected ones once here
+        trade_config.priority_fee.warn_legacy_tips();
+
+        // Probe the providers configured with `SwqosRegion::Auto` before their clients are built
+        join_all(trade_config.swqos_configs.iter().filter_map(|config| match config.provider() {
+            Some((swqos_type, SwqosRegion::Auto, None)) => {
+                Some(async move { swqos::region::resolve_auto_region(&swqos_type).await })
+            }
+            _ => None,
+        }))
+        .await;
+
         let rpc_url = trade_config.rpc_url.clone();
         let swqos_configs = trade_config.swqos_configs.clone();
         let priority_fee = Arc::new(trade_config.priority_fee.clone());
         let commitment = trade_config.commitment.clone();
+        let spend_tracker =
+            trade_config.spend_budget.clone().map(|budget| Arc::new(SpendTracker::new(budget)));
         let mut swqos_clients: Vec<Arc<SwqosClient>> = vec![];
 
         for swqos in swqos_configs {
-            let swqos_client =
+            let mut swqos_client =
                 SwqosConfig::get_swqos_client(rpc_url.clone(), commitment.clone(), swqos.clone());
+            if let Some(tip_account) = trade_config.tip_accounts.get(&swqos_client.get_swqos_type())
+            {
+                swqos_client = TipAccountOverride::wrap(swqos_client, tip_account.clone());
+            }
             swqos_clients.push(swqos_client);
         }
 
+        if trade_config.validate_swqos_auth {
+            for error in swqos::auth::probe_clients(&swqos_clients).await {
+                eprintln!(icon!(" ❌ ", "{}"), error);
+            }
+        }
+
         let rpc = Arc::new(SolanaRpcClient::new_with_commitment(rpc_url.clone(), commitment));
+        common::rpc_race::set_fallback_rpc_urls(&trade_config.fallback_rpc_urls, commitment);
+        BlockhashCache::get_instance().set_commitment(trade_config.blockhash_commitment);
+        // Every client confirms through the service of the shared RPC URL
+        swqos::confirmation::ConfirmationService::for_rpc(&rpc)
+            .set_mode(trade_config.confirmation_mode);
         common::seed::update_rents(&rpc).await.unwrap();
         common::seed::start_rent_updater(rpc.clone());
 
@@ -91,6 +373,15 @@ impl SolanaTrade {
             SwqosConfig::Default(rpc_url),
         );
 
+        common::priority_fee_estimate::PriorityFeeEstimator::get_instance()
+            .configure(rpc.clone(), trade_config.priority_fee_estimate.clone());
+        swqos::health::set_skip_unhealthy(trade_config.skip_unhealthy_swqos);
+        if let Some(interval) = trade_config.swqos_health_check_interval {
+            let mut clients = swqos_clients.clone();
+            clients.push(rpc_client.clone());
+            swqos::health::start_health_monitor(clients, interval);
+        }
+
         let instance = Self {
             payer,
             rpc,
@@ -98,6 +389,17 @@ impl SolanaTrade {
             swqos_clients,
             priority_fee,
             middleware_manager: None,
+            spend_tracker,
+            analysis_callback: None,
+            clamp_sell_to_balance: false,
+            commission: trade_config.commission,
+            amount_precision: None,
+            created_account_check: CreatedAccountCheck::Warn,
+            wsol_account: WsolAccountChoice::CanonicalAta,
+            cu_sizing: CuSizing::Static,
+            per_dex_defaults: Arc::new(trade_config.per_dex_defaults),
+            default_slippage_bps: trade_config.default_slippage_bps,
+            track_position: trade_config.track_position,
         };
 
         let mut current = INSTANCE.lock();
@@ -111,6 +413,148 @@ impl SolanaTrade {
         self
     }
 
+    /// Persist the daily spend budget through `store` so spend survives restarts
+    ///
+    /// Has no effect when `TradeConfig::spend_budget` is not set.
+    pub fn with_spend_store(mut self, store: Arc<dyn KeyValueStore>) -> Self {
+        if let Some(spend_tracker) = &self.spend_tracker {
+            let budget = spend_tracker.budget().clone();
+            self.spend_tracker = Some(Arc::new(SpendTracker::with_store(budget, store)));
+        }
+        self
+    }
+
+    /// Receive deferred analysis results
+    ///
+    /// When a trade lands but `getTransaction` does not return it yet, the trade methods return a
+    /// result flagged `analysis_pending` and keep analyzing in the background; the final result
+    /// is passed to `callback`.
+    pub fn with_analysis_callback(mut self, callback: AnalysisCallback) -> Self {
+        self.analysis_callback = Some(callback);
+        self
+    }
+
+    /// Reduce sell amounts above the token balance to the balance
+    ///
+    /// The balance is read with one RPC call before building. A zero balance fails the sell
+    /// with "Nothing to sell"; selling the whole balance is not affected.
+    pub fn with_sell_clamp_to_balance(mut self, clamp_to_balance: bool) -> Self {
+        self.clamp_sell_to_balance = clamp_to_balance;
+        self
+    }
+
+    /// Warn about, reject or ignore trades that create token accounts the swap does not use
+    ///
+    /// Catches a seeded account created next to the ATA the swap references, or the same wSOL
+    /// account created twice. Warns by default.
+    pub fn with_created_account_check(mut self, check: CreatedAccountCheck) -> Self {
+        self.created_account_check = check;
+        self
+    }
+
+    /// Wrap trade SOL in a per-trade account instead of the wSOL ATA
+    ///
+    /// With `WsolAccountChoice::EphemeralPerTrade` wSOL held long-term in the ATA is never
+    /// unwrapped by a trade. Each trade pays the token account rent up front and gets it back when
+    /// the account is closed at the end of the same transaction.
+    pub fn with_wsol_account_choice(mut self, choice: WsolAccountChoice) -> Self {
+        self.wsol_account = choice;
+        self
+    }
+
+    /// Size the compute unit limit of each trade from a simulation instead of `PriorityFee`
+    ///
+    /// `CuSizing::Simulated { margin_pct, cache_ttl }` simulates the first trade of each protocol,
+    /// mint and side and reuses the consumed units for `cache_ttl`. Trades whose simulation fails
+    /// or is slow keep the static limits.
+    pub fn with_cu_sizing(mut self, cu_sizing: CuSizing) -> Self {
+        self.cu_sizing = cu_sizing;
+        self
+    }
+
+    /// Round `format_amount` output to `precision` fraction digits
+    pub fn with_amount_precision(mut self, precision: u8) -> Self {
+        self.amount_precision = Some(precision);
+        self
+    }
+
+    /// Slippage and compute unit sizing of a trade on `dex_type` sent with
+    /// `slippage_basis_points`, resolved as described in `dex_defaults`
+    pub fn applied_defaults(
+        &self,
+        dex_type: &DexType,
+        slippage_basis_points: Option<u64>,
+    ) -> AppliedDefaults {
+        AppliedDefaults::resolve(
+            slippage_basis_points,
+            dex_type,
+            &self.per_dex_defaults,
+            self.default_slippage_bps,
+            self.cu_sizing,
+        )
+    }
+
+    /// Reject a buy of `sol_amount` lamports outside the `DexDefaults` bounds of `dex_type`
+    pub fn check_buy_amount(
+        &self,
+        dex_type: &DexType,
+        sol_amount: u64,
+    ) -> Result<(), TradeAmountOutOfBounds> {
+        match self.per_dex_defaults.get(dex_type) {
+            Some(defaults) => defaults.check_sol_amount(dex_type, sol_amount),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the daily spend tracker, if a spend budget is configured
+    pub fn get_spend_tracker(&self) -> Option<&Arc<SpendTracker>> {
+        self.spend_tracker.as_ref()
+    }
+
+    /// Get tips and priority fees spent today, if a spend budget is configured
+    pub fn get_current_spend(&self) -> Option<DailySpend> {
+        self.spend_tracker.as_ref().map(|tracker| tracker.current_spend())
+    }
+
+    /// Get rolling p50 / p95 submission and confirmation latency of each swqos provider
+    pub fn get_swqos_latency_stats(&self) -> Vec<SwqosLatencyStats> {
+        swqos::timing::latency_stats()
+    }
+
+    /// Get submission, failure and confirmation counts and submit latency of each swqos
+    /// provider since start or the last `reset_stats`
+    ///
+    /// The counters are process-wide, shared by every `SolanaTrade` instance.
+    pub fn get_swqos_stats(&self) -> Vec<SwqosStats> {
+        swqos::metrics::swqos_stats()
+    }
+
+    /// Get the counters of `get_swqos_stats` split by the labels trades were sent with
+    ///
+    /// Reserved label keys are ignored. Only `swqos::metrics::MAX_LABELED_SERIES` label sets
+    /// are counted separately, later ones are counted together in a series flagged `overflow`.
+    pub fn get_labeled_swqos_stats(&self) -> Vec<LabeledSwqosStats> {
+        swqos::metrics::labeled_swqos_stats()
+    }
+
+    /// Zero the counters returned by `get_swqos_stats` and `get_labeled_swqos_stats`
+    pub fn reset_stats(&self) {
+        swqos::metrics::reset_stats()
+    }
+
+    /// Get the health of each configured swqos provider and the RPC client, see
+    /// `swqos::health`
+    pub fn get_swqos_health(&self) -> Vec<SwqosHealth> {
+        let clients: Vec<Arc<SwqosClient>> =
+            self.swqos_clients.iter().chain(&self.rpc_client).cloned().collect();
+        swqos::health::health_snapshot(&clients)
+    }
+
+    /// Get the latest region probe of each provider configured with `SwqosRegion::Auto`
+    pub fn get_swqos_region_probes(&self) -> Vec<RegionProbe> {
+        swqos::region::region_probes()
+    }
+
     /// Get the RPC client instance
     pub fn get_rpc(&self) -> &Arc<SolanaRpcClient> {
         &self.rpc
@@ -133,13 +577,17 @@ impl SolanaTrade {
     /// * `mint` - The public key of the token mint to buy
     /// * `sol_amount` - Amount of SOL to spend on the purchase (in lamports)
     /// * `slippage_basis_points` - Optional slippage tolerance in basis points (e.g., 100 = 1%)
-    /// * `recent_blockhash` - Recent blockhash for transaction validity
+    /// * `recent_blockhash` - Recent blockhash for transaction validity, `None` takes it from the
+    ///   `BlockhashCache`
     /// * `custom_priority_fee` - Optional custom priority fee for priority processing
     /// * `extension_params` - Optional protocol-specific parameters (uses defaults if None)
-    /// * `lookup_table_key` - Optional address lookup table key for transaction optimization
-    /// * `wait_transaction_confirmed` - Whether to wait for the transaction to be confirmed
-    /// * `create_wsol_ata` - Whether to create wSOL ATA account
-    /// * `close_wsol_ata` - Whether to close wSOL ATA account
+    /// * `lookup_table_keys` - Address lookup tables for transaction optimization, may be empty
+    /// * `wait_level` - Stage to wait for (`WaitLevel`), `true`/`false` map to `Confirmed`/`Submitted`
+    /// * `create_wsol_ata` - Whether to create the wSOL ATA and wrap the SOL in, off spends wSOL
+    ///   the ATA already holds
+    /// * `close_wsol_ata` - Whether to close the wSOL ATA after the swap, off keeps its wSOL
+    /// * `create_mint_ata` - Whether to create the token account, ignored with a warning as every
+    ///   protocol creates it
     /// * `open_seed_optimize` - Whether to open seed optimize
     ///
     /// # Returns
@@ -159,50 +607,575 @@ impl SolanaTrade {
         mint: Pubkey,
         sol_amount: u64,
         slippage_basis_points: Option<u64>,
-        recent_blockhash: Hash,
+        recent_blockhash: impl Into<Option<Hash>>,
         custom_priority_fee: Option<PriorityFee>,
         extension_params: Box<dyn ProtocolParams>,
-        lookup_table_key: Option<Pubkey>,
-        wait_transaction_confirmed: bool,
+        lookup_table_keys: Vec<Pubkey>,
+        wait_level: impl Into<WaitLevel>,
         create_wsol_ata: bool,
         close_wsol_ata: bool,
         create_mint_ata: bool,
         open_seed_optimize: bool,
     ) -> Result<Signature, anyhow::Error> {
-        if slippage_basis_points.is_none() {
+        let options = BuyOptions {
+            slippage_basis_points,
+            custom_priority_fee,
+            lookup_table_keys,
+            wait_level: wait_level.into(),
+            create_wsol_ata,
+            close_wsol_ata,
+            create_mint_ata,
+            open_seed_optimize,
+            commission: None,
+            labels: None,
+            success_policy: SuccessPolicy::FirstAccepted,
+            on_curve_complete: CurveCompleteAction::Fail,
+            swap_hooks: SwapHooks::default(),
+            use_durable_nonce: false,
+            blockhash_retry: BlockhashRetry::default(),
+            memo: None,
+            memo_labels: Vec::new(),
+            deadline: None,
+            payer: None,
+            exact_tokens_out: None,
+            min_amount_out: None,
+            data_size_limit: Some(DEFAULT_DATA_SIZE_LIMIT),
+        };
+        self.buy_with_options(
+            dex_type,
+            mint,
+            sol_amount,
+            recent_blockhash,
+            extension_params,
+            options,
+        )
+        .await
+    }
+
+    /// Execute a sell order for a specified token
+    ///
+    /// # Arguments
+    ///
+    /// * `dex_type` - The trading protocol to use (PumpFun, PumpSwap, or Bonk)
+    /// * `mint` - The public key of the token mint to sell
+    /// * `token_amount` - Amount of tokens to sell (in smallest token units)
+    /// * `slippage_basis_points` - Optional slippage tolerance in basis points (e.g., 100 = 1%)
+    /// * `recent_blockhash` - Recent blockhash for transaction validity, `None` takes it from the
+    ///   `BlockhashCache`
+    /// * `custom_priority_fee` - Optional custom priority fee for priority processing
+    /// * `with_tip` - Optional boolean to indicate if the transaction should be sent with tip
+    /// * `extension_params` - Optional protocol-specific parameters (uses defaults if None)
+    /// * `lookup_table_keys` - Address lookup tables for transaction optimization, may be empty
+    /// * `wait_level` - Stage to wait for (`WaitLevel`), `true`/`false` map to `Confirmed`/`Submitted`
+    /// * `create_wsol_ata` - Whether to create the wSOL ATA receiving the proceeds
+    /// * `close_wsol_ata` - Whether to close the wSOL ATA after the swap, off keeps the proceeds
+    ///   as wSOL
+    /// * `open_seed_optimize` - Whether to open seed optimize
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` if the sell order is successfully executed, or an error if the transaction fails.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    /// - Invalid protocol parameters are provided
+    /// - The transaction fails to execute
+    /// - Network or RPC errors occur
+    /// - Insufficient token balance for the sale
+    /// - Token account doesn't exist or is not properly initialized
+    pub async fn sell(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        token_amount: u64,
+        slippage_basis_points: Option<u64>,
+        recent_blockhash: impl Into<Option<Hash>>,
+        custom_priority_fee: Option<PriorityFee>,
+        with_tip: bool,
+        extension_params: Box<dyn ProtocolParams>,
+        lookup_table_keys: Vec<Pubkey>,
+        wait_level: impl Into<WaitLevel>,
+        create_wsol_ata: bool,
+        close_wsol_ata: bool,
+        open_seed_optimize: bool,
+    ) -> Result<Signature, anyhow::Error> {
+        let options = SellOptions {
+            slippage_basis_points,
+            custom_priority_fee,
+            with_tip,
+            lookup_table_keys,
+            wait_level: wait_level.into(),
+            create_wsol_ata,
+            close_wsol_ata,
+            open_seed_optimize,
+            commission: None,
+            labels: None,
+            success_policy: SuccessPolicy::FirstAccepted,
+            swap_hooks: SwapHooks::default(),
+            use_durable_nonce: false,
+            blockhash_retry: BlockhashRetry::default(),
+            memo: None,
+            memo_labels: Vec::new(),
+            deadline: None,
+            payer: None,
+            expected_min_balance: None,
+            min_sol_out: None,
+            data_size_limit: Some(DEFAULT_DATA_SIZE_LIMIT),
+        };
+        self.sell_with_options(
+            dex_type,
+            mint,
+            token_amount,
+            recent_blockhash,
+            extension_params,
+            options,
+        )
+        .await
+    }
+
+    /// `buy` with the optional settings in a `BuyOptions`
+    ///
+    /// Prefer this over `buy`: settings added later become `BuyOptions` fields with a default
+    /// instead of extra positional parameters.
+    ///
+    /// A PumpFun buy rejected because the bonding curve completed fails with
+    /// `BondingCurveCompleted`, or falls back to PumpSwap as set by `on_curve_complete`.
+    pub async fn buy_with_options(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        sol_amount: u64,
+        recent_blockhash: impl Into<Option<Hash>>,
+        extension_params: Box<dyn ProtocolParams>,
+        options: BuyOptions,
+    ) -> Result<Signature, anyhow::Error> {
+        let trade_result = self
+            .buy_with_result(
+                dex_type,
+                mint,
+                sol_amount,
+                recent_blockhash,
+                extension_params,
+                options,
+            )
+            .await?;
+        result_signature(&trade_result)
+    }
+
+    /// `buy_with_options` returning the `TradeResult` the trade was analyzed into
+    ///
+    /// The result is the one the executor built from the confirmed transaction, no extra RPC
+    /// call is made. With `WaitLevel::Submitted` or `Processed`, or when the transaction was not
+    /// visible yet, it is flagged `analysis_pending` and the analyzed result goes to the analysis
+    /// callback.
+    /// After a PumpSwap fallback it is the result of the pool buy.
+    pub async fn buy_with_result(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        sol_amount: u64,
+        recent_blockhash: impl Into<Option<Hash>>,
+        extension_params: Box<dyn ProtocolParams>,
+        options: BuyOptions,
+    ) -> Result<TradeResult, anyhow::Error> {
+        let recent_blockhash = self.resolve_blockhash(recent_blockhash.into()).await?;
+        if dex_type == DexType::PumpFun && options.on_curve_complete != CurveCompleteAction::Fail {
+            let (report, trade_result) = self
+                .curve_fallback(mint, sol_amount, recent_blockhash, extension_params, options)
+                .await?;
+            return match trade_result {
+                Some(trade_result) => Ok(trade_result),
+                None => Err(report.into_result().err().unwrap_or_else(|| {
+                    anyhow::anyhow!("Curve fallback of {} returned no result", mint)
+                })),
+            };
+        }
+        self.buy_once(dex_type, mint, sol_amount, recent_blockhash, extension_params, options).await
+    }
+
+    /// Build the exact transaction `buy_with_options` would send and run it through
+    /// `simulateTransaction` instead
+    ///
+    /// Swap hooks, middlewares, commission, lookup tables, compute budget and the tip of the
+    /// first swqos client are all applied, nothing is sent and no spend is recorded. A PumpFun
+    /// `on_curve_complete` fallback is not simulated.
+    pub async fn buy_simulate(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        sol_amount: u64,
+        recent_blockhash: Hash,
+        extension_params: Box<dyn ProtocolParams>,
+        options: BuyOptions,
+    ) -> Result<SimulationResult, anyhow::Error> {
+        let buy_params = self.buy_params(
+            &dex_type,
+            mint,
+            sol_amount,
+            recent_blockhash,
+            extension_params,
+            options,
+        )?;
+        let executor = TradeFactory::create_executor(dex_type);
+        executor.simulate_buy(buy_params, self.middleware_manager.clone()).await
+    }
+
+    /// Instructions of a buy for assembling a custom transaction, nothing is sent
+    ///
+    /// The protocol instructions come with swap hooks, middlewares and commission applied, but
+    /// without compute budget, nonce or tip instructions; add those as needed before signing
+    /// with the payer.
+    pub async fn build_buy_instructions(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        sol_amount: u64,
+        extension_params: Box<dyn ProtocolParams>,
+        options: BuyOptions,
+    ) -> Result<Vec<Instruction>, anyhow::Error> {
+        // No transaction is compiled, the blockhash is never read
+        let buy_params = self.buy_params(
+            &dex_type,
+            mint,
+            sol_amount,
+            Hash::default(),
+            extension_params,
+            options,
+        )?;
+        let executor = TradeFactory::create_executor(dex_type);
+        executor.buy_instructions(buy_params, self.middleware_manager.clone()).await
+    }
+
+    /// Unsigned transaction of a buy, for signing elsewhere, nothing is sent
+    ///
+    /// The transaction is the one `buy_simulate` would simulate: compute budget, lookup tables,
+    /// the durable nonce when the options ask for it and the tip of the first swqos client in
+    /// submission order. Its signatures are default placeholders for the signers to fill in.
+    pub async fn build_buy_transaction(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        sol_amount: u64,
+        recent_blockhash: Hash,
+        extension_params: Box<dyn ProtocolParams>,
+        options: BuyOptions,
+    ) -> Result<VersionedTransaction, anyhow::Error> {
+        let buy_params = self.buy_params(
+            &dex_type,
+            mint,
+            sol_amount,
+            recent_blockhash,
+            extension_params,
+            options,
+        )?;
+        let executor = TradeFactory::create_executor(dex_type);
+        executor.buy_transaction(buy_params, self.middleware_manager.clone()).await
+    }
+
+    /// Buy several mints of one protocol at once
+    ///
+    /// Each order `(mint, sol_amount, params)` is built and sent as its own transaction, with its
+    /// own tip, through `buy_with_options`; all orders run concurrently and each transaction is
+    /// submitted to every swqos client. `options`, including its slippage, is shared by all
+    /// orders. Results are in the order of `orders`; an order that fails to build or send does
+    /// not affect the others.
+    pub async fn buy_batch(
+        &self,
+        dex_type: DexType,
+        orders: Vec<(Pubkey, u64, Box<dyn ProtocolParams>)>,
+        recent_blockhash: Hash,
+        options: BuyOptions,
+    ) -> Vec<Result<Signature, anyhow::Error>> {
+        let buys = orders.into_iter().map(|(mint, sol_amount, extension_params)| {
+            self.buy_with_options(
+                dex_type.clone(),
+                mint,
+                sol_amount,
+                recent_blockhash,
+                extension_params,
+                options.clone(),
+            )
+        });
+        join_all(buys).await
+    }
+
+    /// PumpFun buy that re-quotes and buys on the canonical PumpSwap pool once when the bonding
+    /// curve completed before it landed, see `CurveCompleteAction::FallbackToPumpSwap`
+    ///
+    /// Errors other than the curve completion are returned as is. The pool buy spends wSOL the
+    /// payer already holds when it covers `sol_amount`, and wraps `sol_amount` otherwise.
+    pub async fn buy_with_curve_fallback(
+        &self,
+        mint: Pubkey,
+        sol_amount: u64,
+        recent_blockhash: Hash,
+        extension_params: Box<dyn ProtocolParams>,
+        options: BuyOptions,
+    ) -> Result<CurveFallbackReport, anyhow::Error> {
+        let (report, _) = self
+            .curve_fallback(mint, sol_amount, recent_blockhash, extension_params, options)
+            .await?;
+        Ok(report)
+    }
+
+    /// `buy_with_curve_fallback` with the `TradeResult` of the buy that landed
+    async fn curve_fallback(
+        &self,
+        mint: Pubkey,
+        sol_amount: u64,
+        recent_blockhash: Hash,
+        extension_params: Box<dyn ProtocolParams>,
+        options: BuyOptions,
+    ) -> Result<(CurveFallbackReport, Option<TradeResult>), anyhow::Error> {
+        let started = Instant::now();
+        let completed = match self
+            .buy_once(
+                DexType::PumpFun,
+                mint,
+                sol_amount,
+                recent_blockhash,
+                extension_params,
+                options.clone(),
+            )
+            .await
+        {
+            Ok(trade_result) => {
+                let curve_attempt = Ok(result_signature(&trade_result)?);
+                let report = CurveFallbackReport { mint, curve_attempt, pool_attempt: None };
+                return Ok((report, Some(trade_result)));
+            }
+            Err(e) => e.downcast::<BondingCurveCompleted>()?,
+        };
+        let mut report =
+            CurveFallbackReport { mint, curve_attempt: Err(completed), pool_attempt: None };
+        let CurveCompleteAction::FallbackToPumpSwap { max_extra_slippage_bps, deadline } =
+            options.on_curve_complete
+        else {
+            return Ok((report, None));
+        };
+
+        let pool = async {
+            let pool = crate::instruction::utils::pumpswap::find_pool(&self.rpc, &mint).await?;
+            PumpSwapParams::from_pool_address_by_rpc(&self.rpc, &pool).await
+        };
+        let wallet =
+            options.payer.as_ref().map_or_else(|| self.payer.pubkey(), |payer| payer.pubkey());
+        let (pool, wsol_balance) = tokio::join!(
+            pool,
+            get_sell_token_balance(&self.rpc, &wallet, &WSOL_TOKEN_ACCOUNT, false)
+        );
+        let pool = match pool {
+            Ok(pool) => pool,
+            Err(e) => {
+                report.pool_attempt = Some(Err(format!("Failed to quote PumpSwap pool: {}", e)));
+                return Ok((report, None));
+            }
+        };
+        if started.elapsed() >= deadline {
             println!(
-                "slippage_basis_points is none, use default slippage basis points: {}",
-                DEFAULT_SLIPPAGE
+                icon!(
+                    "⚠️ ",
+                    "Bonding curve of {} completed, deadline passed before PumpSwap fallback"
+                ),
+                mint
             );
+            return Ok((report, None));
         }
+
+        let slippage_basis_points =
+            self.applied_defaults(&DexType::PumpSwap, options.slippage_basis_points).slippage_bps
+                + max_extra_slippage_bps;
+        // The failed curve buy is atomic, wSOL left in the account comes from earlier trades
+        let reuse_wsol = self.wsol_account == WsolAccountChoice::CanonicalAta
+            && wsol_balance.unwrap_or(0) >= sol_amount;
+        let pool_options = BuyOptions {
+            slippage_basis_points: Some(slippage_basis_points),
+            create_wsol_ata: !reuse_wsol,
+            on_curve_complete: CurveCompleteAction::Fail,
+            ..options
+        };
+        println!(
+            icon!("⚠️ ", "Bonding curve of {} completed, buying on PumpSwap pool instead"),
+            mint
+        );
+        let (pool_attempt, trade_result) = match self
+            .buy_once(
+                DexType::PumpSwap,
+                mint,
+                sol_amount,
+                recent_blockhash,
+                Box::new(pool),
+                pool_options,
+            )
+            .await
+        {
+            Ok(trade_result) => {
+                (result_signature(&trade_result).map_err(|e| e.to_string()), Some(trade_result))
+            }
+            Err(e) => (Err(e.to_string()), None),
+        };
+        report.pool_attempt = Some(pool_attempt);
+        Ok((report, trade_result))
+    }
+
+    /// Payer of a trade: the per-trade one from the options, or the configured one
+    fn trade_payer(&self, payer: Option<Arc<dyn TradeSigner>>) -> Arc<dyn TradeSigner> {
+        let payer = payer.unwrap_or_else(|| KeypairSigner::wrap(self.payer.clone()));
+        crate::common::fast_fn::fast_init_once(&payer.pubkey());
+        payer
+    }
+
+    /// `recent_blockhash`, or the `BlockhashCache` one when `None`
+    ///
+    /// The cache starts refreshing in the background on the first call without a blockhash, and
+    /// fails rather than returning a blockhash too old to land.
+    async fn resolve_blockhash(
+        &self,
+        recent_blockhash: Option<Hash>,
+    ) -> Result<Hash, anyhow::Error> {
+        match recent_blockhash {
+            Some(recent_blockhash) => Ok(recent_blockhash),
+            None => Ok(BlockhashCache::get_instance().get_or_fetch(&self.rpc).await?),
+        }
+    }
+
+    async fn buy_once(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        sol_amount: u64,
+        recent_blockhash: Hash,
+        extension_params: Box<dyn ProtocolParams>,
+        options: BuyOptions,
+    ) -> Result<TradeResult, anyhow::Error> {
+        let buy_params = self.buy_params(
+            &dex_type,
+            mint,
+            sol_amount,
+            recent_blockhash,
+            extension_params,
+            options,
+        )?;
         let executor = TradeFactory::create_executor(dex_type.clone());
+
+        // Call executor.buy (not buy_with_tip)
+        let trade_result =
+            executor.buy(buy_params, self.middleware_manager.clone()).await.map_err(|e| {
+                if dex_type == DexType::PumpFun && is_bonding_curve_complete_error(&e) {
+                    BondingCurveCompleted { mint, detail: e.to_string() }.into()
+                } else {
+                    e
+                }
+            })?;
+        if self.track_position {
+            record_position(&trade_result);
+        }
+        Ok(trade_result)
+    }
+
+    /// Analysis callback of sent trades, also recording deferred results in the
+    /// `PositionTracker` when `track_position` is set
+    fn trade_analysis_callback(&self) -> Option<AnalysisCallback> {
+        if !self.track_position {
+            return self.analysis_callback.clone();
+        }
+        let callback = self.analysis_callback.clone();
+        Some(Arc::new(move |signature: Signature, result: Result<TradeResult, anyhow::Error>| {
+            if let Ok(trade_result) = &result {
+                record_position(trade_result);
+            }
+            if let Some(callback) = &callback {
+                callback(signature, result);
+            }
+        }))
+    }
+
+    /// Validated `BuyParams` of a buy, shared by `buy_once` and `buy_simulate`
+    fn buy_params(
+        &self,
+        dex_type: &DexType,
+        mint: Pubkey,
+        sol_amount: u64,
+        recent_blockhash: Hash,
+        extension_params: Box<dyn ProtocolParams>,
+        options: BuyOptions,
+    ) -> Result<BuyParams, anyhow::Error> {
+        if let Some(labels) = &options.labels {
+            validate_labels(labels)?;
+        }
+        let memo = memo_with_labels(
+            options.memo.as_deref(),
+            options.labels.as_ref(),
+            &options.memo_labels,
+        );
+        self.check_buy_amount(dex_type, sol_amount)?;
+        if options.min_amount_out.is_some() && !dex_type.supports_min_out() {
+            return Err(anyhow::anyhow!("{:?} does not support min_amount_out", dex_type));
+        }
+        check_account_flags(
+            dex_type,
+            self.wsol_account,
+            options.create_wsol_ata,
+            options.close_wsol_ata,
+            Some(options.create_mint_ata),
+        )?;
+        let applied = self.applied_defaults(dex_type, options.slippage_basis_points);
+        if applied.slippage_source != DefaultSource::Request {
+            println!(
+                "slippage_basis_points is none, use {} slippage basis points: {}",
+                applied.slippage_source.as_str(),
+                applied.slippage_bps
+            );
+        }
         let protocol_params = extension_params;
 
         let mut buy_params = BuyParams {
             rpc: Some(self.rpc.clone()),
-            payer: self.payer.clone(),
+            payer: self.trade_payer(options.payer),
             mint: mint,
             sol_amount: sol_amount,
-            slippage_basis_points: slippage_basis_points,
+            amount_spec: match options.exact_tokens_out {
+                Some(token_amount) => {
+                    BuyAmountSpec::ExactTokensOut { token_amount, max_sol: sol_amount }
+                }
+                None => BuyAmountSpec::ExactSolIn(sol_amount),
+            },
+            trade_type: TradeType::Buy,
+            slippage_basis_points: Some(applied.slippage_bps),
+            min_amount_out: options.min_amount_out,
             priority_fee: self.priority_fee.clone(),
-            lookup_table_key,
+            lookup_table_keys: options.lookup_table_keys,
             recent_blockhash,
-            data_size_limit: 512 * 1024,
-            wait_transaction_confirmed: wait_transaction_confirmed,
+            data_size_limit: options.data_size_limit,
+            wait_level: options.wait_level,
             protocol_params: protocol_params.clone(),
-            open_seed_optimize,
-            create_wsol_ata,
-            close_wsol_ata,
-            create_mint_ata,
+            open_seed_optimize: options.open_seed_optimize,
+            create_wsol_ata: options.create_wsol_ata,
+            close_wsol_ata: options.close_wsol_ata,
+            create_mint_ata: options.create_mint_ata,
             swqos_clients: self.swqos_clients.clone(),
             middleware_manager: self.middleware_manager.clone(),
+            spend_tracker: self.spend_tracker.clone(),
+            analysis_callback: self.trade_analysis_callback(),
+            commission: options.commission.or(self.commission),
+            labels: applied.label(options.labels),
+            success_policy: options.success_policy,
+            created_account_check: self.created_account_check,
+            wsol_account: self.wsol_account,
+            cu_sizing: applied.cu_sizing,
+            swap_hooks: options.swap_hooks,
+            use_durable_nonce: options.use_durable_nonce,
+            blockhash_retry: options.blockhash_retry,
+            memo,
+            deadline: options.deadline,
         };
-        if custom_priority_fee.is_some() {
-            buy_params.priority_fee = Arc::new(custom_priority_fee.unwrap());
+        if let Some(custom_priority_fee) = options.custom_priority_fee {
+            buy_params.priority_fee = Arc::new(custom_priority_fee);
         }
 
         // Validate protocol params
-        let is_valid_params = match dex_type {
+        let is_valid_params = match *dex_type {
             DexType::PumpFun => protocol_params.as_any().downcast_ref::<PumpFunParams>().is_some(),
             DexType::PumpSwap => {
                 protocol_params.as_any().downcast_ref::<PumpSwapParams>().is_some()
@@ -220,100 +1193,354 @@ impl SolanaTrade {
             DexType::RaydiumAmmV4 => {
                 protocol_params.as_any().downcast_ref::<RaydiumAmmV4Params>().is_some()
             }
+            DexType::MeteoraDlmm => {
+                protocol_params.as_any().downcast_ref::<MeteoraDlmmParams>().is_some()
+            }
+            DexType::Jupiter => protocol_params.as_any().downcast_ref::<JupiterParams>().is_some(),
         };
 
         if !is_valid_params {
-            return Err(anyhow::anyhow!("Invalid protocol params for Trade"));
+            return Err(TradeError::params_mismatch(format!("{:?}", dex_type)).into());
         }
 
-        // Call executor.buy (not buy_with_tip) and extract signature from TradeResult
-        let trade_result = executor.buy(buy_params, self.middleware_manager.clone()).await?;
-        Ok(trade_result.signature.parse().map_err(|e| anyhow::anyhow!("Failed to parse signature: {}", e))?)
+        Ok(buy_params)
     }
 
-    /// Execute a sell order for a specified token
-    ///
-    /// # Arguments
+    /// `sell` with the optional settings in a `SellOptions`, see `buy_with_options`
+    pub async fn sell_with_options(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        token_amount: u64,
+        recent_blockhash: impl Into<Option<Hash>>,
+        extension_params: Box<dyn ProtocolParams>,
+        options: SellOptions,
+    ) -> Result<Signature, anyhow::Error> {
+        let trade_result = self
+            .sell_with_result(
+                dex_type,
+                mint,
+                token_amount,
+                recent_blockhash,
+                extension_params,
+                options,
+            )
+            .await?;
+        result_signature(&trade_result)
+    }
+
+    /// `sell_with_options` returning the `TradeResult` the trade was analyzed into, see
+    /// `buy_with_result`
+    pub async fn sell_with_result(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        token_amount: u64,
+        recent_blockhash: impl Into<Option<Hash>>,
+        extension_params: Box<dyn ProtocolParams>,
+        options: SellOptions,
+    ) -> Result<TradeResult, anyhow::Error> {
+        let recent_blockhash = self.resolve_blockhash(recent_blockhash.into()).await?;
+        let with_tip = options.with_tip;
+        let sell_params = self.sell_params(
+            &dex_type,
+            mint,
+            token_amount,
+            recent_blockhash,
+            extension_params,
+            options,
+        )?;
+        let executor = TradeFactory::create_executor(dex_type.clone());
+
+        // Execute sell based on tip preference
+        let trade_result = if with_tip {
+            // Convert to SellWithTipParams for tip execution
+            let sell_with_tip_params = sell_params.with_tip(self.swqos_clients.clone());
+            executor.sell_with_tip(sell_with_tip_params, self.middleware_manager.clone()).await?
+        } else {
+            executor.sell(sell_params, self.middleware_manager.clone()).await?
+        };
+        if self.track_position {
+            record_position(&trade_result);
+        }
+        Ok(trade_result)
+    }
+
+    /// Build the exact transaction `sell_with_options` would send and run it through
+    /// `simulateTransaction` instead, see `buy_simulate`
+    pub async fn sell_simulate(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        token_amount: u64,
+        recent_blockhash: Hash,
+        extension_params: Box<dyn ProtocolParams>,
+        options: SellOptions,
+    ) -> Result<SimulationResult, anyhow::Error> {
+        let sell_params = self.sell_params(
+            &dex_type,
+            mint,
+            token_amount,
+            recent_blockhash,
+            extension_params,
+            options,
+        )?;
+        let executor = TradeFactory::create_executor(dex_type);
+        executor.simulate_sell(sell_params, self.middleware_manager.clone()).await
+    }
+
+    /// Instructions of a sell for assembling a custom transaction, see `build_buy_instructions`
     ///
-    /// * `dex_type` - The trading protocol to use (PumpFun, PumpSwap, or Bonk)
-    /// * `mint` - The public key of the token mint to sell
-    /// * `token_amount` - Amount of tokens to sell (in smallest token units)
-    /// * `slippage_basis_points` - Optional slippage tolerance in basis points (e.g., 100 = 1%)
-    /// * `recent_blockhash` - Recent blockhash for transaction validity
-    /// * `custom_priority_fee` - Optional custom priority fee for priority processing
-    /// * `with_tip` - Optional boolean to indicate if the transaction should be sent with tip
-    /// * `extension_params` - Optional protocol-specific parameters (uses defaults if None)
-    /// * `lookup_table_key` - Optional address lookup table key for transaction optimization
-    /// * `wait_transaction_confirmed` - Whether to wait for the transaction to be confirmed
-    /// * `create_wsol_ata` - Whether to create wSOL ATA account
-    /// * `close_wsol_ata` - Whether to close wSOL ATA account
-    /// * `open_seed_optimize` - Whether to open seed optimize
+    /// A `token_amount` of 0 sells the whole balance, read over RPC like `sell` does.
+    pub async fn build_sell_instructions(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        token_amount: u64,
+        extension_params: Box<dyn ProtocolParams>,
+        options: SellOptions,
+    ) -> Result<Vec<Instruction>, anyhow::Error> {
+        // No transaction is compiled, the blockhash is never read
+        let sell_params = self.sell_params(
+            &dex_type,
+            mint,
+            token_amount,
+            Hash::default(),
+            extension_params,
+            options,
+        )?;
+        let executor = TradeFactory::create_executor(dex_type);
+        executor.sell_instructions(sell_params, self.middleware_manager.clone()).await
+    }
+
+    /// Unsigned transaction of a sell, see `build_buy_transaction`
+    pub async fn build_sell_transaction(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        token_amount: u64,
+        recent_blockhash: Hash,
+        extension_params: Box<dyn ProtocolParams>,
+        options: SellOptions,
+    ) -> Result<VersionedTransaction, anyhow::Error> {
+        let sell_params = self.sell_params(
+            &dex_type,
+            mint,
+            token_amount,
+            recent_blockhash,
+            extension_params,
+            options,
+        )?;
+        let executor = TradeFactory::create_executor(dex_type);
+        executor.sell_transaction(sell_params, self.middleware_manager.clone()).await
+    }
+
+    /// Sell several mints with as few transactions as possible, e.g. to exit many small
+    /// positions at once
     ///
-    /// # Returns
+    /// Each leg is built like `build_sell_instructions` with `options`, which all legs share.
+    /// Legs are packed in order into transactions up to the account lock, packet size and
+    /// compute unit limits, using the lookup tables of `options`; the legs of one transaction
+    /// share the wSOL ATA, which is closed once at the end. The transactions are submitted
+    /// concurrently. A `recent_blockhash` of `None` takes the `BlockhashCache` one.
     ///
-    /// Returns `Ok(())` if the sell order is successfully executed, or an error if the transaction fails.
+    /// Returns the signature of each mint's transaction, or why the mint was not sold. Legs
+    /// packed together share their signature and fail together.
     ///
     /// # Errors
     ///
-    /// This function will return an error if:
-    /// - Invalid protocol parameters are provided
-    /// - The transaction fails to execute
-    /// - Network or RPC errors occur
-    /// - Insufficient token balance for the sale
-    /// - Token account doesn't exist or is not properly initialized
-    pub async fn sell(
+    /// Fails when a mint appears in more than one leg or no blockhash is available.
+    pub async fn sell_many(
         &self,
-        dex_type: DexType,
+        legs: Vec<SellLeg>,
+        recent_blockhash: Option<Hash>,
+        options: SellOptions,
+    ) -> Result<HashMap<Pubkey, Result<Signature, anyhow::Error>>, anyhow::Error> {
+        let mut mints = HashSet::with_capacity(legs.len());
+        if let Some(leg) = legs.iter().find(|leg| !mints.insert(leg.mint)) {
+            return Err(anyhow::anyhow!("Mint {} is sold by more than one leg", leg.mint));
+        }
+        let recent_blockhash = self.resolve_blockhash(recent_blockhash).await?;
+
+        let builds = legs.into_iter().map(|leg| {
+            let options = options.clone();
+            async move {
+                let mint = leg.mint;
+                let built = async {
+                    let params = self.sell_params(
+                        &leg.dex_type,
+                        mint,
+                        leg.token_amount,
+                        recent_blockhash,
+                        leg.params,
+                        options,
+                    )?;
+                    let executor = TradeFactory::create_executor(leg.dex_type);
+                    let instructions = executor
+                        .sell_instructions(params.clone(), self.middleware_manager.clone())
+                        .await?;
+                    let protocol_name = executor.protocol_name();
+                    Ok::<_, anyhow::Error>((
+                        params,
+                        BuiltSellLeg { mint, protocol_name, instructions },
+                    ))
+                };
+                (mint, built.await)
+            }
+        });
+        let mut results = HashMap::new();
+        let (mut leg_params, mut built_legs) = (Vec::new(), Vec::new());
+        for (mint, built) in join_all(builds).await {
+            match built {
+                Ok((params, built_leg)) => {
+                    leg_params.push(params);
+                    built_legs.push(built_leg);
+                }
+                Err(e) => {
+                    results.insert(mint, Err(e));
+                }
+            }
+        }
+        let Some(first) = leg_params.first() else {
+            return Ok(results);
+        };
+
+        let lookup_tables = get_address_lookup_table_accounts(&first.lookup_table_keys)?;
+        let (packed, unfit) = pack_sells(
+            &first.payer.pubkey(),
+            &built_legs,
+            &lookup_tables,
+            &first.priority_fee,
+            first.data_size_limit.unwrap_or(0),
+        );
+        for (index, e) in unfit {
+            results.insert(built_legs[index].mint, Err(e));
+        }
+        let swqos_clients = apply_spend_budget(&self.spend_tracker, first.swqos_clients.clone())?;
+        let sends = packed.into_iter().map(|pack| {
+            let (indexes, instructions) = (pack.legs, pack.instructions);
+            let swqos_clients = swqos_clients.clone();
+            let mut params = leg_params[indexes[0]].clone();
+            params.swqos_clients = swqos_clients.clone();
+            params.priority_fee = Arc::new(pack.priority_fee);
+            let trade_lamports: u64 = indexes
+                .iter()
+                .map(|index| sell_tip_trade_lamports(&leg_params[*index]).unwrap_or(0))
+                .sum();
+            let priority_fee = params.priority_fee.clone();
+            let with_tip = params.with_tip;
+            async move {
+                let sent = packed_sell_parallel_execute(params, instructions, trade_lamports).await;
+                if sent.is_ok() {
+                    record_spend(
+                        &self.spend_tracker,
+                        &swqos_clients,
+                        &priority_fee,
+                        SELL_MANY_PROTOCOL,
+                        false,
+                        trade_lamports,
+                        None,
+                        with_tip,
+                    );
+                }
+                (indexes, sent)
+            }
+        });
+        for (indexes, sent) in join_all(sends).await {
+            for index in indexes {
+                let result = match &sent {
+                    Ok((signature, _)) => Ok(*signature),
+                    Err(e) => Err(leg_error(e)),
+                };
+                results.insert(built_legs[index].mint, result);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Validated `SellParams` of a sell, shared by `sell_with_result` and `sell_simulate`
+    fn sell_params(
+        &self,
+        dex_type: &DexType,
         mint: Pubkey,
         token_amount: u64,
-        slippage_basis_points: Option<u64>,
         recent_blockhash: Hash,
-        custom_priority_fee: Option<PriorityFee>,
-        with_tip: bool,
         extension_params: Box<dyn ProtocolParams>,
-        lookup_table_key: Option<Pubkey>,
-        wait_transaction_confirmed: bool,
-        create_wsol_ata: bool,
-        close_wsol_ata: bool,
-        open_seed_optimize: bool,
-    ) -> Result<Signature, anyhow::Error> {
-        if slippage_basis_points.is_none() {
+        options: SellOptions,
+    ) -> Result<SellParams, anyhow::Error> {
+        if let Some(labels) = &options.labels {
+            validate_labels(labels)?;
+        }
+        let memo = memo_with_labels(
+            options.memo.as_deref(),
+            options.labels.as_ref(),
+            &options.memo_labels,
+        );
+        if options.min_sol_out.is_some() && !dex_type.supports_min_out() {
+            return Err(anyhow::anyhow!("{:?} does not support min_sol_out", dex_type));
+        }
+        check_account_flags(
+            dex_type,
+            self.wsol_account,
+            options.create_wsol_ata,
+            options.close_wsol_ata,
+            None,
+        )?;
+        let applied = self.applied_defaults(dex_type, options.slippage_basis_points);
+        if applied.slippage_source != DefaultSource::Request {
             println!(
-                "slippage_basis_points is none, use default slippage basis points: {}",
-                DEFAULT_SLIPPAGE
+                "slippage_basis_points is none, use {} slippage basis points: {}",
+                applied.slippage_source.as_str(),
+                applied.slippage_bps
             );
         }
-        let executor = TradeFactory::create_executor(dex_type.clone());
         let protocol_params = extension_params;
 
         let mut sell_params = SellParams {
             rpc: Some(self.rpc.clone()),
-            payer: self.payer.clone(),
+            payer: self.trade_payer(options.payer),
             mint: mint,
             token_amount: Some(token_amount),
-            slippage_basis_points: slippage_basis_points,
+            slippage_basis_points: Some(applied.slippage_bps),
+            min_sol_out: options.min_sol_out,
             priority_fee: self.priority_fee.clone(),
-            lookup_table_key,
+            lookup_table_keys: options.lookup_table_keys,
             recent_blockhash,
-            wait_transaction_confirmed: wait_transaction_confirmed,
+            data_size_limit: options.data_size_limit,
+            wait_level: options.wait_level,
             protocol_params: protocol_params.clone(),
-            with_tip: with_tip,
-            open_seed_optimize,
-            swqos_clients: if !with_tip {
+            with_tip: options.with_tip,
+            open_seed_optimize: options.open_seed_optimize,
+            swqos_clients: if !options.with_tip {
                 self.rpc_client.clone()
             } else {
                 self.swqos_clients.clone()
             },
             middleware_manager: self.middleware_manager.clone(),
-            create_wsol_ata,
-            close_wsol_ata,
+            create_wsol_ata: options.create_wsol_ata,
+            close_wsol_ata: options.close_wsol_ata,
+            spend_tracker: self.spend_tracker.clone(),
+            analysis_callback: self.trade_analysis_callback(),
+            clamp_to_balance: self.clamp_sell_to_balance,
+            expected_min_balance: options.expected_min_balance,
+            commission: options.commission.or(self.commission),
+            labels: applied.label(options.labels),
+            success_policy: options.success_policy,
+            created_account_check: self.created_account_check,
+            wsol_account: self.wsol_account,
+            cu_sizing: applied.cu_sizing,
+            swap_hooks: options.swap_hooks,
+            use_durable_nonce: options.use_durable_nonce,
+            blockhash_retry: options.blockhash_retry,
+            memo,
+            deadline: options.deadline,
         };
-        if custom_priority_fee.is_some() {
-            sell_params.priority_fee = Arc::new(custom_priority_fee.unwrap());
+        if let Some(custom_priority_fee) = options.custom_priority_fee {
+            sell_params.priority_fee = Arc::new(custom_priority_fee);
         }
 
         // Validate protocol params
-        let is_valid_params = match dex_type {
+        let is_valid_params = match *dex_type {
             DexType::PumpFun => protocol_params.as_any().downcast_ref::<PumpFunParams>().is_some(),
             DexType::PumpSwap => {
                 protocol_params.as_any().downcast_ref::<PumpSwapParams>().is_some()
@@ -331,21 +1558,17 @@ impl SolanaTrade {
             DexType::RaydiumAmmV4 => {
                 protocol_params.as_any().downcast_ref::<RaydiumAmmV4Params>().is_some()
             }
+            DexType::MeteoraDlmm => {
+                protocol_params.as_any().downcast_ref::<MeteoraDlmmParams>().is_some()
+            }
+            DexType::Jupiter => protocol_params.as_any().downcast_ref::<JupiterParams>().is_some(),
         };
 
         if !is_valid_params {
-            return Err(anyhow::anyhow!("Invalid protocol params for Trade"));
+            return Err(TradeError::params_mismatch(format!("{:?}", dex_type)).into());
         }
 
-        // Execute sell based on tip preference and extract signature from TradeResult
-        let trade_result = if with_tip {
-            // Convert to SellWithTipParams for tip execution
-            let sell_with_tip_params = sell_params.with_tip(self.swqos_clients.clone());
-            executor.sell_with_tip(sell_with_tip_params, self.middleware_manager.clone()).await?
-        } else {
-            executor.sell(sell_params, self.middleware_manager.clone()).await?
-        };
-        Ok(trade_result.signature.parse().map_err(|e| anyhow::anyhow!("Failed to parse signature: {}", e))?)
+        Ok(sell_params)
     }
 
     /// Execute a sell order for a percentage of the specified token amount
@@ -364,8 +1587,8 @@ impl SolanaTrade {
     /// * `custom_priority_fee` - Optional custom priority fee for priority processing
     /// * `with_tip` - Whether to use tip for priority processing
     /// * `extension_params` - Optional protocol-specific parameters (uses defaults if None)
-    /// * `lookup_table_key` - Optional lookup table key for address lookup optimization
-    /// * `wait_transaction_confirmed` - Whether to wait for the transaction to be confirmed
+    /// * `lookup_table_keys` - Lookup tables for address lookup optimization, may be empty
+    /// * `wait_level` - Stage to wait for (`WaitLevel`), `true`/`false` map to `Confirmed`/`Submitted`
     ///
     /// # Returns
     ///
@@ -391,8 +1614,8 @@ impl SolanaTrade {
         custom_priority_fee: Option<PriorityFee>,
         with_tip: bool,
         extension_params: Box<dyn ProtocolParams>,
-        lookup_table_key: Option<Pubkey>,
-        wait_transaction_confirmed: bool,
+        lookup_table_keys: Vec<Pubkey>,
+        wait_level: impl Into<WaitLevel>,
         create_wsol_ata: bool,
         close_wsol_ata: bool,
         open_seed_optimize: bool,
@@ -410,8 +1633,8 @@ impl SolanaTrade {
             custom_priority_fee,
             with_tip,
             extension_params,
-            lookup_table_key,
-            wait_transaction_confirmed,
+            lookup_table_keys,
+            wait_level,
             create_wsol_ata,
             close_wsol_ata,
             open_seed_optimize,
@@ -419,6 +1642,898 @@ impl SolanaTrade {
         .await
     }
 
+    /// Sell the whole balance of the trading wallet's token account for `mint`
+    ///
+    /// The account is looked up among the spl-token and token-2022 ATAs, or the seed-derived
+    /// accounts with `open_seed_optimize`, and its full balance becomes `token_amount`. With
+    /// `close_token_account` the emptied account is closed in the sell transaction, returning
+    /// its rent to the wallet. A token account the RPC does not know yet, e.g. right after the
+    /// buy, is looked up again per `BalanceRetry::default`.
+    ///
+    /// # Errors
+    ///
+    /// Fails without sending when the wallet holds none of the token, or on any error of
+    /// `sell_with_options`.
+    pub async fn sell_all(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        recent_blockhash: impl Into<Option<Hash>>,
+        extension_params: Box<dyn ProtocolParams>,
+        options: SellOptions,
+        close_token_account: bool,
+    ) -> Result<Signature, anyhow::Error> {
+        let owner =
+            options.payer.as_ref().map_or_else(|| self.payer.pubkey(), |payer| payer.pubkey());
+        let account = get_sell_token_account_with_retry(
+            &self.rpc,
+            &owner,
+            &mint,
+            options.open_seed_optimize,
+            BalanceRetry::default(),
+        )
+        .await?
+        .filter(|account| account.amount > 0)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Nothing to sell: wallet {} holds no {}", owner, mint)
+            })?;
+        let options = if close_token_account {
+            options.with_post_swap_instruction(spl_token_2022::instruction::close_account(
+                &account.token_program,
+                &account.address,
+                &owner,
+                &owner,
+                &[&owner],
+            )?)
+        } else {
+            options
+        };
+        self.sell_with_options(
+            dex_type,
+            mint,
+            account.amount,
+            recent_blockhash,
+            extension_params,
+            options,
+        )
+        .await
+    }
+
+    /// Execute a buy split across several wallets
+    ///
+    /// Each payer buys its weighted share of `sol_amount` in its own transaction, with its own
+    /// ATAs and tip, and all legs are submitted concurrently. Payers that cannot cover their
+    /// share plus tip and fees are skipped. A leg failing does not fail the others; check
+    /// `SplitBuyReport::failed_legs` for legs that did not fill. When the split has a
+    /// consolidation wallet, tokens of the filled legs are transferred to it afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error only for invalid protocol parameters; per-leg failures are reported in
+    /// the returned report.
+    pub async fn split_buy(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        sol_amount: u64,
+        slippage_basis_points: Option<u64>,
+        recent_blockhash: Hash,
+        custom_priority_fee: Option<PriorityFee>,
+        extension_params: Box<dyn ProtocolParams>,
+        lookup_table_keys: Vec<Pubkey>,
+        split: SplitAcrossPayers,
+    ) -> Result<SplitBuyReport, anyhow::Error> {
+        // Validate protocol params
+        let is_valid_params = match dex_type {
+            DexType::PumpFun => extension_params.as_any().downcast_ref::<PumpFunParams>().is_some(),
+            DexType::PumpSwap => {
+                extension_params.as_any().downcast_ref::<PumpSwapParams>().is_some()
+            }
+            DexType::Bonk => extension_params.as_any().downcast_ref::<BonkParams>().is_some(),
+            DexType::RaydiumCpmm => {
+                extension_params.as_any().downcast_ref::<RaydiumCpmmParams>().is_some()
+            }
+            DexType::RaydiumClmm => {
+                extension_params.as_any().downcast_ref::<RaydiumClmmParams>().is_some()
+            }
+            DexType::RaydiumClmmV2 => {
+                extension_params.as_any().downcast_ref::<RaydiumClmmV2Params>().is_some()
+            }
+            DexType::RaydiumAmmV4 => {
+                extension_params.as_any().downcast_ref::<RaydiumAmmV4Params>().is_some()
+            }
+            DexType::MeteoraDlmm => {
+                extension_params.as_any().downcast_ref::<MeteoraDlmmParams>().is_some()
+            }
+            DexType::Jupiter => extension_params.as_any().downcast_ref::<JupiterParams>().is_some(),
+        };
+
+        if !is_valid_params {
+            return Err(TradeError::params_mismatch(format!("{:?}", dex_type)).into());
+        }
+
+        let priority_fee = custom_priority_fee.map(Arc::new).unwrap_or(self.priority_fee.clone());
+        let max_tip =
+            priority_fee.resolved_buy_tips().iter().map(|tip| tip.to_lamports()).max().unwrap_or(0);
+        let amounts = split.leg_amounts(sol_amount);
+        let applied = self.applied_defaults(&dex_type, slippage_basis_points);
+
+        let legs = split.payers.iter().zip(split.weights.iter()).zip(amounts).map(
+            |((payer, weight), leg_amount)| {
+                let priority_fee = priority_fee.clone();
+                let protocol_params = extension_params.clone();
+                let dex_type = dex_type.clone();
+                let lookup_table_keys = lookup_table_keys.clone();
+                async move {
+                    let mut leg = SplitBuyLeg::new(payer, *weight, leg_amount);
+                    if let Err(e) = self.check_buy_amount(&dex_type, leg_amount) {
+                        leg.error = Some(e.to_string());
+                        return leg;
+                    }
+                    let commission = self
+                        .commission
+                        .and_then(|commission| commission.lamports(true, leg_amount))
+                        .unwrap_or(0);
+                    // A per-trade wSOL account holds its rent until closed in the same transaction
+                    let wsol_rent = match self.wsol_account {
+                        WsolAccountChoice::EphemeralPerTrade => ephemeral_wsol_rent().unwrap_or(0),
+                        WsolAccountChoice::CanonicalAta => 0,
+                    };
+                    let required = leg_amount
+                        + max_tip
+                        + commission
+                        + wsol_rent
+                        + SPLIT_LEG_FEE_RESERVE_LAMPORTS;
+                    match self.get_sol_balance(&payer.pubkey()).await {
+                        Ok(balance) if balance < required => {
+                            leg.error = Some(
+                                TradeError::InsufficientBalance {
+                                    needed: required,
+                                    available: balance,
+                                }
+                                .to_string(),
+                            );
+                            return leg;
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            leg.error = Some(format!("Failed to check balance: {}", e));
+                            return leg;
+                        }
+                    }
+
+                    let buy_params = BuyParams {
+                        rpc: Some(self.rpc.clone()),
+                        payer: KeypairSigner::wrap(payer.clone()),
+                        mint,
+                        sol_amount: leg_amount,
+                        amount_spec: BuyAmountSpec::ExactSolIn(leg_amount),
+                        trade_type: TradeType::Buy,
+                        slippage_basis_points: Some(applied.slippage_bps),
+                        min_amount_out: None,
+                        priority_fee,
+                        lookup_table_keys,
+                        recent_blockhash,
+                        data_size_limit: Some(DEFAULT_DATA_SIZE_LIMIT),
+                        wait_level: WaitLevel::Confirmed,
+                        protocol_params,
+                        open_seed_optimize: false,
+                        create_wsol_ata: true,
+                        close_wsol_ata: true,
+                        create_mint_ata: true,
+                        swqos_clients: self.swqos_clients.clone(),
+                        middleware_manager: self.middleware_manager.clone(),
+                        spend_tracker: self.spend_tracker.clone(),
+                        analysis_callback: self.analysis_callback.clone(),
+                        commission: self.commission,
+                        labels: applied.label(None),
+                        success_policy: SuccessPolicy::FirstAccepted,
+                        created_account_check: self.created_account_check,
+                        wsol_account: self.wsol_account,
+                        cu_sizing: applied.cu_sizing,
+                        swap_hooks: SwapHooks::default(),
+                        use_durable_nonce: false,
+                        blockhash_retry: BlockhashRetry::default(),
+                        memo: None,
+                        deadline: None,
+                    };
+                    let executor = TradeFactory::create_executor(dex_type);
+                    match executor.buy(buy_params, self.middleware_manager.clone()).await {
+                        Ok(trade_result) => leg.trade_result = Some(trade_result),
+                        Err(e) => leg.error = Some(e.to_string()),
+                    }
+                    leg
+                }
+            },
+        );
+        let mut report = SplitBuyReport { mint, legs: join_all(legs).await };
+
+        if let Some(primary) = split.consolidate_to {
+            for (leg, payer) in report.legs.iter_mut().zip(split.payers.iter()) {
+                if !leg.is_filled() || leg.payer == primary {
+                    continue;
+                }
+                match self.transfer_token(payer, &primary, &mint, None).await {
+                    Ok(amount) => leg.consolidated_amount = Some(amount),
+                    Err(e) => leg.consolidation_error = Some(e.to_string()),
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Swap token A into token B through SOL
+    ///
+    /// The sell of `mint_in` and the buy of `mint_out` go into one transaction when both fit
+    /// (account lock limit, packet size, compute budget), sharing the wSOL ATA between the hops.
+    /// The buy leg then spends the sell leg's SOL output on the curve, less slippage and a fee
+    /// reserve. Otherwise, or with `TwoHopMode::Sequential`, the sell is sent first and the buy
+    /// spends the SOL it actually returned once it confirmed.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the sell leg fails, or with `TwoHopMode::SingleTransaction` when the legs do
+    /// not fit one transaction. A failed buy after a landed sell is reported in `buy_error`.
+    pub async fn swap_two_hop(
+        &self,
+        sell_dex_type: DexType,
+        mint_in: Pubkey,
+        token_amount: u64,
+        sell_extension_params: Box<dyn ProtocolParams>,
+        buy_dex_type: DexType,
+        mint_out: Pubkey,
+        buy_extension_params: Box<dyn ProtocolParams>,
+        slippage_basis_points: Option<u64>,
+        recent_blockhash: Hash,
+        lookup_table_keys: Vec<Pubkey>,
+        mode: TwoHopMode,
+    ) -> Result<TwoHopReport, anyhow::Error> {
+        let sell_applied = self.applied_defaults(&sell_dex_type, slippage_basis_points);
+        let buy_applied = self.applied_defaults(&buy_dex_type, slippage_basis_points);
+        let slippage = sell_applied.slippage_bps;
+        // SOL the buy leg of a single transaction spends, unknown for CLMM sells
+        let intermediate_lamports =
+            market::sell_output_lamports(sell_extension_params.as_ref(), token_amount)
+                .ok()
+                .map(|output| {
+                    let kept_bps = 10_000u64.saturating_sub(slippage + TWO_HOP_FEE_RESERVE_BPS);
+                    (output as u128 * kept_bps as u128 / 10_000) as u64
+                })
+                .filter(|lamports| *lamports > 0);
+
+        let sell_params = SellParams {
+            rpc: Some(self.rpc.clone()),
+            payer: self.trade_payer(None),
+            mint: mint_in,
+            token_amount: Some(token_amount),
+            slippage_basis_points: Some(slippage),
+            min_sol_out: None,
+            priority_fee: self.priority_fee.clone(),
+            lookup_table_keys: lookup_table_keys.clone(),
+            recent_blockhash,
+            data_size_limit: Some(DEFAULT_DATA_SIZE_LIMIT),
+            wait_level: WaitLevel::Confirmed,
+            with_tip: true,
+            protocol_params: sell_extension_params,
+            open_seed_optimize: false,
+            swqos_clients: self.swqos_clients.clone(),
+            middleware_manager: self.middleware_manager.clone(),
+            create_wsol_ata: true,
+            close_wsol_ata: true,
+            spend_tracker: self.spend_tracker.clone(),
+            analysis_callback: self.analysis_callback.clone(),
+            clamp_to_balance: self.clamp_sell_to_balance,
+            expected_min_balance: None,
+            commission: self.commission,
+            labels: sell_applied.label(None),
+            success_policy: SuccessPolicy::FirstAccepted,
+            created_account_check: self.created_account_check,
+            wsol_account: self.wsol_account,
+            cu_sizing: sell_applied.cu_sizing,
+            swap_hooks: SwapHooks::default(),
+            use_durable_nonce: false,
+            blockhash_retry: BlockhashRetry::default(),
+            memo: None,
+            deadline: None,
+        };
+        let buy_params = |sol_amount: u64, priority_fee: Arc<PriorityFee>| BuyParams {
+            rpc: Some(self.rpc.clone()),
+            payer: self.trade_payer(None),
+            mint: mint_out,
+            sol_amount,
+            amount_spec: BuyAmountSpec::ExactSolIn(sol_amount),
+            trade_type: TradeType::Buy,
+            slippage_basis_points: Some(buy_applied.slippage_bps),
+            min_amount_out: None,
+            priority_fee,
+            lookup_table_keys: lookup_table_keys.clone(),
+            recent_blockhash,
+            data_size_limit: Some(DEFAULT_DATA_SIZE_LIMIT),
+            wait_level: WaitLevel::Confirmed,
+            protocol_params: buy_extension_params.clone(),
+            open_seed_optimize: false,
+            create_wsol_ata: true,
+            close_wsol_ata: true,
+            create_mint_ata: true,
+            swqos_clients: self.swqos_clients.clone(),
+            middleware_manager: self.middleware_manager.clone(),
+            spend_tracker: self.spend_tracker.clone(),
+            analysis_callback: self.analysis_callback.clone(),
+            commission: self.commission,
+            labels: buy_applied.label(None),
+            success_policy: SuccessPolicy::FirstAccepted,
+            created_account_check: self.created_account_check,
+            wsol_account: self.wsol_account,
+            cu_sizing: buy_applied.cu_sizing,
+            swap_hooks: SwapHooks::default(),
+            use_durable_nonce: false,
+            blockhash_retry: BlockhashRetry::default(),
+            memo: None,
+            deadline: None,
+        };
+
+        if mode != TwoHopMode::Sequential {
+            let single = match intermediate_lamports {
+                Some(lamports) => {
+                    self.check_buy_amount(&buy_dex_type, lamports)?;
+                    let priority_fee = Arc::new(two_hop_priority_fee(&self.priority_fee));
+                    let mut buy_params = buy_params(lamports, priority_fee);
+                    buy_params.swqos_clients =
+                        apply_spend_budget(&self.spend_tracker, buy_params.swqos_clients)?;
+                    self.build_two_hop(
+                        sell_dex_type.clone(),
+                        &sell_params,
+                        buy_dex_type.clone(),
+                        &buy_params,
+                    )
+                    .await
+                    .map(|instructions| (buy_params, instructions))
+                }
+                None => Err(anyhow::anyhow!("The sell leg output cannot be estimated")),
+            };
+            match single {
+                Ok((buy_params, instructions)) => {
+                    let rpc = self.rpc.clone();
+                    let wallet = self.payer.pubkey();
+                    let swqos_clients = buy_params.swqos_clients.clone();
+                    let priority_fee = buy_params.priority_fee.clone();
+                    let (signature, stages) =
+                        buy_parallel_execute(buy_params, instructions, "TwoHop").await?;
+                    let sell_leg = TradeResult::analyze_sell_transaction(
+                        &rpc,
+                        &signature,
+                        &mint_in,
+                        &wallet,
+                        token_amount as f64,
+                        None,
+                        CommitmentConfig::confirmed(),
+                    )
+                    .await?
+                    .with_stages(stages.clone());
+                    let buy_leg = TradeResult::analyze_transaction(
+                        &rpc,
+                        &signature,
+                        &mint_out,
+                        &wallet,
+                        intermediate_lamports.unwrap_or(0) as f64 / 1_000_000_000.0,
+                        CommitmentConfig::confirmed(),
+                    )
+                    .await
+                    .map(|result| result.with_stages(stages));
+                    record_spend(
+                        &self.spend_tracker,
+                        &swqos_clients,
+                        &priority_fee,
+                        "TwoHop",
+                        true,
+                        intermediate_lamports.unwrap_or(0),
+                        sell_leg.solana_fees,
+                        true,
+                    );
+                    let (buy_leg, buy_error) = match buy_leg {
+                        Ok(buy_leg) => (Some(buy_leg), None),
+                        Err(e) => (None, Some(e.to_string())),
+                    };
+                    return Ok(TwoHopReport {
+                        mint_in,
+                        mint_out,
+                        mode: TwoHopMode::SingleTransaction,
+                        sell_leg,
+                        buy_leg,
+                        buy_error,
+                    });
+                }
+                Err(e) if mode == TwoHopMode::SingleTransaction => return Err(e),
+                Err(e) => {
+                    log::warn!(icon!("⚠️ ", "Two-hop swap falls back to two transactions: {}"), e);
+                }
+            }
+        }
+
+        // Sequential: the buy spends what the confirmed sell returned
+        let sell_leg = TradeFactory::create_executor(sell_dex_type)
+            .sell(sell_params, self.middleware_manager.clone())
+            .await?;
+        let received_lamports = (-sell_leg.sol_spent * 1_000_000_000.0).max(0.0) as u64;
+        let sol_amount = if sell_leg.analysis_pending || received_lamports == 0 {
+            intermediate_lamports
+        } else {
+            Some(received_lamports)
+        };
+        let (buy_leg, buy_error) = match sol_amount {
+            Some(sol_amount) => match self.check_buy_amount(&buy_dex_type, sol_amount) {
+                Ok(()) => match TradeFactory::create_executor(buy_dex_type)
+                    .buy(
+                        buy_params(sol_amount, self.priority_fee.clone()),
+                        self.middleware_manager.clone(),
+                    )
+                    .await
+                {
+                    Ok(buy_leg) => (Some(buy_leg), None),
+                    Err(e) => (None, Some(e.to_string())),
+                },
+                Err(e) => (None, Some(e.to_string())),
+            },
+            None => (None, Some("SOL returned by the sell leg is unknown".to_string())),
+        };
+        Ok(TwoHopReport {
+            mint_in,
+            mint_out,
+            mode: TwoHopMode::Sequential,
+            sell_leg,
+            buy_leg,
+            buy_error,
+        })
+    }
+
+    /// Instructions of both two-hop legs in one transaction, or why they do not fit
+    async fn build_two_hop(
+        &self,
+        sell_dex_type: DexType,
+        sell_params: &SellParams,
+        buy_dex_type: DexType,
+        buy_params: &BuyParams,
+    ) -> Result<Vec<Instruction>, anyhow::Error> {
+        let payer = self.payer.pubkey();
+        let mut sell_instructions = TradeFactory::create_instruction_builder(sell_dex_type)
+            .build_sell_instructions(sell_params)
+            .await?;
+        append_sell_commission(&mut sell_instructions, sell_params);
+        let mut buy_instructions = TradeFactory::create_instruction_builder(buy_dex_type)
+            .build_buy_instructions(buy_params)
+            .await?;
+        append_commission(
+            &mut buy_instructions,
+            buy_params.commission,
+            &payer,
+            true,
+            buy_params.sol_amount,
+        );
+        let instructions = merge_two_hop_instructions(&payer, sell_instructions, buy_instructions);
+        check_created_accounts(&instructions, buy_params.created_account_check)?;
+        let lookup_tables = get_address_lookup_table_accounts(&buy_params.lookup_table_keys)?;
+        check_single_transaction_fit(
+            &payer,
+            &instructions,
+            &lookup_tables,
+            &buy_params.priority_fee,
+            buy_params.data_size_limit,
+        )?;
+        Ok(instructions)
+    }
+
+    /// Send several trades and instruction sets as one bundle, one transaction per leg
+    ///
+    /// Legs run in the given order and the tip is paid once, in the last transaction. Providers
+    /// that take bundles (Jito) get them as one bundle that lands all or nothing, e.g. buys on
+    /// two pools that must both fill. Without such a provider the legs are sent one by one
+    /// through the other providers and may land partially, `BundleReport::is_atomic` tells
+    /// which happened. A `recent_blockhash` of `None` takes the `BlockhashCache` one.
+    ///
+    /// Returns once a provider accepted the submission; watch the signatures of the report, e.g.
+    /// with `ConfirmationService::watch`, to follow them on chain.
+    ///
+    /// # Errors
+    ///
+    /// Fails when a leg cannot be built, there are more than `MAX_BUNDLE_TRANSACTIONS` legs, or
+    /// every provider rejected the submission.
+    pub async fn execute_bundle(
+        &self,
+        legs: Vec<BundleLeg>,
+        recent_blockhash: Option<Hash>,
+        lookup_table_keys: Vec<Pubkey>,
+    ) -> Result<BundleReport, anyhow::Error> {
+        let recent_blockhash = self.resolve_blockhash(recent_blockhash).await?;
+        let mut built_legs = Vec::with_capacity(legs.len());
+        let mut trade_lamports = 0u64;
+        for leg in legs {
+            built_legs.push(match leg {
+                BundleLeg::Buy { dex_type, mint, sol_amount, params, options } => {
+                    trade_lamports += sol_amount;
+                    let protocol_name = format!("{:?}", dex_type);
+                    let instructions = self
+                        .build_buy_instructions(dex_type, mint, sol_amount, params, options)
+                        .await?;
+                    BuiltBundleLeg { protocol_name, is_buy: true, instructions }
+                }
+                BundleLeg::Sell { dex_type, mint, token_amount, params, options } => {
+                    let protocol_name = format!("{:?}", dex_type);
+                    let instructions = self
+                        .build_sell_instructions(dex_type, mint, token_amount, params, options)
+                        .await?;
+                    BuiltBundleLeg { protocol_name, is_buy: false, instructions }
+                }
+                BundleLeg::Instructions(instructions) => BuiltBundleLeg {
+                    protocol_name: RAW_BUNDLE_LEG_PROTOCOL.to_string(),
+                    is_buy: false,
+                    instructions,
+                },
+            });
+        }
+        let is_buy = built_legs.iter().any(|leg| leg.is_buy);
+
+        let swqos_clients = apply_spend_budget(&self.spend_tracker, self.swqos_clients.clone())?;
+        let report = bundle_execute(
+            swqos_clients.clone(),
+            self.trade_payer(None),
+            built_legs,
+            self.priority_fee.clone(),
+            &lookup_table_keys,
+            recent_blockhash,
+            DEFAULT_DATA_SIZE_LIMIT,
+            self.middleware_manager.clone(),
+            trade_lamports,
+        )
+        .await?;
+        record_spend(
+            &self.spend_tracker,
+            &swqos_clients,
+            &self.priority_fee,
+            RAW_BUNDLE_LEG_PROTOCOL,
+            is_buy,
+            trade_lamports,
+            None,
+            true,
+        );
+        Ok(report)
+    }
+
+    /// Check the credentials of every configured swqos provider
+    ///
+    /// Sends each provider a request it rejects after authenticating, so no transaction is
+    /// submitted. Returns one error per provider whose credentials were rejected or that could
+    /// not be reached, an empty list when all are fine.
+    pub async fn validate_swqos_auth(&self) -> Vec<SwqosAuthError> {
+        swqos::auth::probe_clients(&self.swqos_clients).await
+    }
+
+    /// Outcome of the last credential check of every provider
+    pub fn get_swqos_auth_status(&self) -> HashMap<SwqosType, Result<(), SwqosAuthError>> {
+        swqos::auth::auth_status()
+    }
+
+    /// Pick up a trade again from its signature, e.g. after a restart
+    ///
+    /// Checks the signature status, waits for confirmation while the transaction may still land
+    /// and analyzes it once it did. Pass the transaction's `recent_blockhash` when known so an
+    /// expired transaction is reported as `Expired` instead of `Pending`. Safe to call
+    /// repeatedly for the same signature.
+    pub async fn resume_tracking(
+        &self,
+        signature: Signature,
+        mint: Pubkey,
+        side: ResumeSide,
+        recent_blockhash: Option<Hash>,
+    ) -> Result<ResumedTrade, anyhow::Error> {
+        resume_tracking(&self.rpc, signature, mint, self.payer.pubkey(), side, recent_blockhash)
+            .await
+    }
+
+    /// Sell a token the SDK holds no position data for, e.g. an airdrop
+    ///
+    /// Finds the market with `detect_venue`, builds its params from chain state and sells with
+    /// the slippage resolved for its protocol, waiting for confirmation. Refuses to send when a transfer
+    /// restriction makes the sell certain to fail and warns about the others. The analysis of
+    /// such a sell has no entry price, so its PnL fields are `None`.
+    pub async fn sell_unknown_token(
+        &self,
+        mint: Pubkey,
+        amount: SellAmount,
+    ) -> Result<Signature, anyhow::Error> {
+        let owner = self.payer.pubkey();
+        for restriction in transfer_restrictions(&self.rpc, &mint, Some(&owner)).await? {
+            if restriction.is_blocking() {
+                return Err(anyhow::anyhow!("Token {} cannot be sold: {:?}", mint, restriction));
+            }
+            println!(icon!("⚠️ ", "Token {} has a transfer restriction: {:?}"), mint, restriction);
+        }
+        let token_amount = match amount {
+            SellAmount::All => get_sell_token_balance(&self.rpc, &owner, &mint, false).await?,
+            SellAmount::Exact(token_amount) => token_amount,
+        };
+        if token_amount == 0 {
+            return Err(anyhow::anyhow!("No balance of token {} to sell", mint));
+        }
+        let (dex_type, params) = detect_venue(&self.rpc, &mint).await?;
+        let recent_blockhash = self.rpc.get_latest_blockhash().await?;
+        self.sell_with_options(
+            dex_type,
+            mint,
+            token_amount,
+            recent_blockhash,
+            params,
+            SellOptions::default(),
+        )
+        .await
+    }
+
+    /// Sell a PumpFun token into its bonding curve and its PumpSwap pool at once
+    ///
+    /// Around migration a token can trade on both an active bonding curve and a PumpSwap pool.
+    /// When both exist the amount is split with `optimal_split` to maximize the SOL received,
+    /// and each venue gets its own transaction, sent concurrently. With a single venue this is a
+    /// plain sell, which fails up front when the amount exceeds what a curve can fill.
+    ///
+    /// # Errors
+    ///
+    /// Fails when no venue is found or there is nothing to sell; per-leg failures are reported
+    /// in the returned report.
+    pub async fn sell_smart(
+        &self,
+        mint: Pubkey,
+        amount: SellAmount,
+        slippage_basis_points: Option<u64>,
+        recent_blockhash: Hash,
+    ) -> Result<SmartSellReport, anyhow::Error> {
+        let token_amount = match amount {
+            SellAmount::All => {
+                get_sell_token_balance(&self.rpc, &self.payer.pubkey(), &mint, false).await?
+            }
+            SellAmount::Exact(token_amount) => token_amount,
+        };
+        if token_amount == 0 {
+            return Err(anyhow::anyhow!("No balance of token {} to sell", mint));
+        }
+
+        let pool = async {
+            let pool = crate::instruction::utils::pumpswap::find_pool(&self.rpc, &mint).await?;
+            PumpSwapParams::from_pool_address_by_rpc(&self.rpc, &pool).await
+        };
+        let (curve, pool) = tokio::join!(PumpFunParams::from_mint_by_rpc(&self.rpc, &mint), pool);
+        let curve = curve
+            .ok()
+            .and_then(|params| SellVenue::from_bonding_curve(&params).map(|venue| (params, venue)));
+        let pool = pool.ok().and_then(|params| {
+            SellVenue::from_pumpswap_pool(&params, &mint).map(|venue| (params, venue))
+        });
+        let to_curve = match (&curve, &pool) {
+            (Some((_, curve)), Some((_, pool))) => optimal_split(curve, pool, token_amount),
+            (Some((_, curve)), None) if token_amount > curve.max_token_amount() => {
+                return Err(anyhow::anyhow!(
+                    "Selling {} of {} exceeds the bonding curve liquidity of {}",
+                    token_amount,
+                    mint,
+                    curve.max_token_amount()
+                ));
+            }
+            (Some(_), None) => token_amount,
+            (None, Some(_)) => 0,
+            (None, None) => {
+                return Err(anyhow::anyhow!(
+                    "No active PumpFun bonding curve or PumpSwap WSOL pool found for mint {}",
+                    mint
+                ));
+            }
+        };
+
+        let mut legs: Vec<(DexType, Box<dyn ProtocolParams>, u64, u64)> = Vec::with_capacity(2);
+        if let Some((params, venue)) = curve.filter(|_| to_curve > 0) {
+            let expected = venue.output(to_curve).unwrap_or(0);
+            legs.push((DexType::PumpFun, Box::new(params), to_curve, expected));
+        }
+        if let Some((params, venue)) = pool.filter(|_| token_amount > to_curve) {
+            let to_pool = token_amount - to_curve;
+            let expected = venue.output(to_pool).unwrap_or(0);
+            legs.push((DexType::PumpSwap, Box::new(params), to_pool, expected));
+        }
+
+        let legs = legs.into_iter().map(
+            |(dex_type, protocol_params, leg_amount, expected_lamports)| async move {
+                let mut leg = SmartSellLeg {
+                    dex_type: dex_type.clone(),
+                    token_amount: leg_amount,
+                    expected_lamports,
+                    trade_result: None,
+                    error: None,
+                };
+                let applied = self.applied_defaults(&dex_type, slippage_basis_points);
+                let sell_params = SellParams {
+                    rpc: Some(self.rpc.clone()),
+                    payer: self.trade_payer(None),
+                    mint,
+                    token_amount: Some(leg_amount),
+                    slippage_basis_points: Some(applied.slippage_bps),
+                    min_sol_out: None,
+                    priority_fee: self.priority_fee.clone(),
+                    lookup_table_keys: Vec::new(),
+                    recent_blockhash,
+                    data_size_limit: Some(DEFAULT_DATA_SIZE_LIMIT),
+                    wait_level: WaitLevel::Confirmed,
+                    with_tip: false,
+                    protocol_params,
+                    open_seed_optimize: false,
+                    swqos_clients: self.swqos_clients.clone(),
+                    middleware_manager: self.middleware_manager.clone(),
+                    create_wsol_ata: true,
+                    close_wsol_ata: true,
+                    spend_tracker: self.spend_tracker.clone(),
+                    analysis_callback: self.analysis_callback.clone(),
+                    clamp_to_balance: false,
+                    expected_min_balance: None,
+                    commission: self.commission,
+                    labels: applied.label(None),
+                    success_policy: SuccessPolicy::FirstAccepted,
+                    created_account_check: self.created_account_check,
+                    wsol_account: self.wsol_account,
+                    cu_sizing: applied.cu_sizing,
+                    swap_hooks: SwapHooks::default(),
+                    use_durable_nonce: false,
+                    blockhash_retry: BlockhashRetry::default(),
+                    memo: None,
+                    deadline: None,
+                };
+                let executor = TradeFactory::create_executor(dex_type);
+                match executor.sell(sell_params, self.middleware_manager.clone()).await {
+                    Ok(trade_result) => leg.trade_result = Some(trade_result),
+                    Err(e) => leg.error = Some(e.to_string()),
+                }
+                leg
+            },
+        );
+        Ok(SmartSellReport { mint, legs: join_all(legs).await })
+    }
+
+    /// Create a PumpFun token with the payer as creator
+    ///
+    /// A fresh mint keypair signs the `create` instruction. The transaction is sent through the
+    /// swqos clients like a buy, with the `TradeType::Create` fee profile.
+    ///
+    /// # Returns
+    /// - `Ok(CreatedToken)`: The mint, its bonding curve and the signature, see
+    ///   `CreatedToken::pumpfun_params` for follow-up trades
+    /// - `Err(anyhow::Error)`: If the name or symbol is empty or the transaction fails
+    pub async fn create_token(
+        &self,
+        metadata: TokenMetadata,
+        recent_blockhash: Hash,
+    ) -> Result<CreatedToken, anyhow::Error> {
+        self.create_pumpfun_token(metadata, None, None, recent_blockhash).await
+    }
+
+    /// Create a PumpFun token and buy `sol_amount` of it in the same transaction
+    ///
+    /// The dev buy is quoted at the curve's initial reserves with
+    /// `GlobalAccount::get_initial_buy_price`, and the transaction uses the
+    /// `TradeType::CreateAndBuy` fee profile. Otherwise the same as `create_token`.
+    pub async fn create_and_buy(
+        &self,
+        metadata: TokenMetadata,
+        sol_amount: u64,
+        slippage_basis_points: Option<u64>,
+        recent_blockhash: Hash,
+    ) -> Result<CreatedToken, anyhow::Error> {
+        self.check_buy_amount(&DexType::PumpFun, sol_amount)?;
+        self.create_pumpfun_token(
+            metadata,
+            Some(sol_amount),
+            slippage_basis_points,
+            recent_blockhash,
+        )
+        .await
+    }
+
+    async fn create_pumpfun_token(
+        &self,
+        metadata: TokenMetadata,
+        dev_sol_amount: Option<u64>,
+        slippage_basis_points: Option<u64>,
+        recent_blockhash: Hash,
+    ) -> Result<CreatedToken, anyhow::Error> {
+        use crate::instruction::pumpfun::{build_create_instruction, build_dev_buy_instructions};
+        use crate::instruction::utils::pumpfun::get_bonding_curve_pda;
+
+        metadata.validate()?;
+        let mint_keypair = Arc::new(Keypair::new());
+        let mint = mint_keypair.pubkey();
+        let creator = self.payer.pubkey();
+        let applied = self.applied_defaults(&DexType::PumpFun, slippage_basis_points);
+
+        let mut instructions = vec![build_create_instruction(
+            &mint,
+            &creator,
+            &metadata.name,
+            &metadata.symbol,
+            &metadata.uri,
+        )?];
+        let (trade_type, dev_sol_amount, dev_token_amount) = match dev_sol_amount {
+            Some(sol_amount) => {
+                // With the volume accumulators, like the params of `CreatedToken::pumpfun_params`
+                let (buy_instructions, token_amount) = build_dev_buy_instructions(
+                    &mint,
+                    &creator,
+                    sol_amount,
+                    applied.slippage_bps,
+                    true,
+                )?;
+                instructions.extend(buy_instructions);
+                (TradeType::CreateAndBuy, sol_amount, token_amount)
+            }
+            None => (TradeType::Create, 0, 0),
+        };
+
+        let swqos_clients = apply_spend_budget(&self.spend_tracker, self.swqos_clients.clone())?;
+        let priority_fee = Arc::new(self.priority_fee.for_trade_type(trade_type));
+        let created = CreatedToken {
+            mint,
+            bonding_curve: get_bonding_curve_pda(&mint)
+                .ok_or_else(|| anyhow::anyhow!("Bonding curve not found"))?,
+            creator,
+            signature: Signature::default(),
+            dev_token_amount,
+            dev_sol_amount,
+        };
+        let buy_params = BuyParams {
+            rpc: Some(self.rpc.clone()),
+            payer: self.trade_payer(None),
+            mint,
+            sol_amount: dev_sol_amount,
+            amount_spec: BuyAmountSpec::ExactSolIn(dev_sol_amount),
+            trade_type,
+            slippage_basis_points: Some(applied.slippage_bps),
+            min_amount_out: None,
+            priority_fee: priority_fee.clone(),
+            lookup_table_keys: Vec::new(),
+            recent_blockhash,
+            data_size_limit: Some(DEFAULT_DATA_SIZE_LIMIT),
+            wait_level: WaitLevel::Confirmed,
+            protocol_params: Box::new(created.pumpfun_params(None)),
+            open_seed_optimize: false,
+            create_wsol_ata: false,
+            close_wsol_ata: false,
+            create_mint_ata: true,
+            swqos_clients: swqos_clients.clone(),
+            middleware_manager: self.middleware_manager.clone(),
+            spend_tracker: self.spend_tracker.clone(),
+            analysis_callback: None,
+            commission: None,
+            labels: applied.label(None),
+            success_policy: SuccessPolicy::FirstAccepted,
+            created_account_check: self.created_account_check,
+            wsol_account: self.wsol_account,
+            cu_sizing: CuSizing::Static,
+            swap_hooks: SwapHooks::default(),
+            use_durable_nonce: false,
+            blockhash_retry: BlockhashRetry::default(),
+            memo: None,
+            deadline: None,
+        };
+        let (signature, _) =
+            co_signed_parallel_execute(buy_params, instructions, "PumpFun", vec![mint_keypair])
+                .await?;
+        record_spend(
+            &self.spend_tracker,
+            &swqos_clients,
+            &priority_fee,
+            "PumpFun",
+            true,
+            dev_sol_amount,
+            None,
+            true,
+        );
+        Ok(CreatedToken { signature, ..created })
+    }
+
     /// Wraps SOL into wSOL (Wrapped SOL)
     ///
     /// This function creates a wSOL associated token account (if it doesn't exist),
@@ -427,21 +2542,65 @@ impl SolanaTrade {
     ///
     /// # Arguments
     /// - `amount`: The amount of SOL to wrap (in lamports)
+    /// - `owner`: Wallet whose wSOL ATA receives the wSOL, the payer when `None`. The payer
+    ///   funds the SOL and the ATA rent either way.
     ///
     /// # Returns
-    /// - `Ok(String)`: Transaction signature
+    /// - `Ok(WsolBalanceUpdate)`: Transaction signature and the owner's wSOL balance after it
     /// - `Err(anyhow::Error)`: If the transaction fails
-    pub async fn wrap_sol_to_wsol(&self, amount: u64) -> Result<String, anyhow::Error> {
-        use crate::trading::common::wsol_manager::handle_wsol;
+    pub async fn wrap_sol_to_wsol(
+        &self,
+        amount: u64,
+        owner: Option<Pubkey>,
+    ) -> Result<WsolBalanceUpdate, anyhow::Error> {
+        use crate::trading::common::wsol_manager::handle_wsol_for_owner;
         use solana_sdk::transaction::Transaction;
+        let owner = owner.unwrap_or_else(|| self.payer.pubkey());
         let recent_blockhash = self.rpc.get_latest_blockhash().await?;
-        let instructions = handle_wsol(&self.payer.pubkey(), amount);
+        let instructions = handle_wsol_for_owner(&self.payer.pubkey(), &owner, amount);
         let mut transaction =
             Transaction::new_with_payer(&instructions, Some(&self.payer.pubkey()));
         transaction.sign(&[&*self.payer], recent_blockhash);
         let signature = self.rpc.send_and_confirm_transaction(&transaction).await?;
-        Ok(signature.to_string())
+        let wsol_balance = self.get_token_balance(&owner, &WSOL_TOKEN_ACCOUNT).await?;
+        Ok(WsolBalanceUpdate { signature, wsol_balance })
+    }
+
+    /// Unwraps part of the payer's wSOL back to native SOL, leaving the rest wrapped
+    ///
+    /// `amount` moves into a temporary seeded token account that is closed in the same
+    /// transaction, so the wSOL ATA stays open with its remaining balance.
+    ///
+    /// # Arguments
+    /// - `amount`: The amount of wSOL to unwrap (in lamports)
+    ///
+    /// # Returns
+    /// - `Ok(WsolBalanceUpdate)`: Transaction signature and the wSOL left in the ATA
+    /// - `Err(anyhow::Error)`: If the ATA holds less than `amount` or the transaction fails
+    pub async fn unwrap_wsol_partial(
+        &self,
+        amount: u64,
+    ) -> Result<WsolBalanceUpdate, anyhow::Error> {
+        use crate::trading::common::wsol_manager::unwrap_wsol_partial;
+        use solana_sdk::transaction::Transaction;
+        let payer = self.payer.pubkey();
+        let balance = self.get_token_balance(&payer, &WSOL_TOKEN_ACCOUNT).await?;
+        if amount == 0 || amount > balance {
+            return Err(anyhow::anyhow!(
+                "Cannot unwrap {} lamports of wSOL, the wSOL account holds {}",
+                amount,
+                balance
+            ));
+        }
+        let recent_blockhash = self.rpc.get_latest_blockhash().await?;
+        let instructions = unwrap_wsol_partial(&payer, amount)?;
+        let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer));
+        transaction.sign(&[&*self.payer], recent_blockhash);
+        let signature = self.rpc.send_and_confirm_transaction(&transaction).await?;
+        let wsol_balance = self.get_token_balance(&payer, &WSOL_TOKEN_ACCOUNT).await?;
+        Ok(WsolBalanceUpdate { signature, wsol_balance })
     }
+
     /// Closes the wSOL account and unwraps SOL back to native SOL
     ///
     /// This function closes the wSOL associated token account, which automatically
@@ -474,7 +2633,7 @@ impl SolanaTrade {
         recent_blockhash: Hash,
         custom_buy_tip_fee: Option<f64>,
         extension_params: Box<dyn ProtocolParams>,
-        lookup_table_key: Option<Pubkey>,
+        lookup_table_keys: Vec<Pubkey>,
         custom_priority_fee: Option<PriorityFee>,
     ) -> Result<TradeResult, anyhow::Error> {
         let executor = TradeFactory::create_executor(dex_type.clone());
@@ -483,18 +2642,24 @@ impl SolanaTrade {
         // Use custom priority fee if provided, otherwise use default from trade config
         let base_priority_fee = custom_priority_fee.unwrap_or_else(|| (*self.priority_fee).clone());
         
+        self.check_buy_amount(&dex_type, sol_amount)?;
+        let applied = self.applied_defaults(&dex_type, slippage_basis_points);
+
         // Create basic buy params first
         let buy_params = BuyParams {
             rpc: Some(self.rpc.clone()),
-            payer: self.payer.clone(),
+            payer: self.trade_payer(None),
             mint: mint,
             sol_amount: sol_amount,
-            slippage_basis_points: slippage_basis_points,
+            amount_spec: BuyAmountSpec::ExactSolIn(sol_amount),
+            trade_type: TradeType::Buy,
+            slippage_basis_points: Some(applied.slippage_bps),
+            min_amount_out: None,
             priority_fee: Arc::new(base_priority_fee.clone()),
-            lookup_table_key,
+            lookup_table_keys,
             recent_blockhash,
-            data_size_limit: 512 * 1024,
-            wait_transaction_confirmed: true,
+            data_size_limit: Some(DEFAULT_DATA_SIZE_LIMIT),
+            wait_level: WaitLevel::Confirmed,
             protocol_params: protocol_params.clone(),
             open_seed_optimize: false,
             create_wsol_ata: true,
@@ -502,15 +2667,26 @@ impl SolanaTrade {
             create_mint_ata: true,
             swqos_clients: self.swqos_clients.clone(),
             middleware_manager: self.middleware_manager.clone(),
+            spend_tracker: self.spend_tracker.clone(),
+            analysis_callback: self.analysis_callback.clone(),
+            commission: self.commission,
+            labels: applied.label(None),
+            success_policy: SuccessPolicy::FirstAccepted,
+            created_account_check: self.created_account_check,
+            wsol_account: self.wsol_account,
+            cu_sizing: applied.cu_sizing,
+            swap_hooks: SwapHooks::default(),
+            use_durable_nonce: false,
+            blockhash_retry: BlockhashRetry::default(),
+            memo: None,
+            deadline: None,
         };
         
         // Convert to tip params and apply custom tip fee
         let mut buy_with_tip_params = buy_params.with_tip(self.swqos_clients.clone());
         let mut priority_fee = base_priority_fee.clone();
-        if custom_buy_tip_fee.is_some() {
-            priority_fee.buy_tip_fee = custom_buy_tip_fee.unwrap();
-            priority_fee.buy_tip_fees =
-                priority_fee.buy_tip_fees.iter().map(|_| custom_buy_tip_fee.unwrap()).collect();
+        if let Some(custom_buy_tip_fee) = custom_buy_tip_fee {
+            priority_fee.override_buy_tips(TipAmount::from_legacy_sol(custom_buy_tip_fee));
         }
         buy_with_tip_params.priority_fee = priority_fee;
 
@@ -533,10 +2709,14 @@ impl SolanaTrade {
             DexType::RaydiumAmmV4 => {
                 protocol_params.as_any().downcast_ref::<RaydiumAmmV4Params>().is_some()
             }
+            DexType::MeteoraDlmm => {
+                protocol_params.as_any().downcast_ref::<MeteoraDlmmParams>().is_some()
+            }
+            DexType::Jupiter => protocol_params.as_any().downcast_ref::<JupiterParams>().is_some(),
         };
 
         if !is_valid_params {
-            return Err(anyhow::anyhow!("Invalid protocol params for Trade"));
+            return Err(TradeError::params_mismatch(format!("{:?}", dex_type)).into());
         }
 
         // Use Jito execution with REAL transaction analysis
@@ -555,7 +2735,7 @@ impl SolanaTrade {
         custom_buy_tip_fee: Option<f64>,
         with_tip: bool,
         extension_params: Box<dyn ProtocolParams>,
-        lookup_table_key: Option<Pubkey>,
+        lookup_table_keys: Vec<Pubkey>,
         custom_priority_fee: Option<PriorityFee>,
     ) -> Result<TradeResult, anyhow::Error> {
         let executor = TradeFactory::create_executor(dex_type.clone());
@@ -564,17 +2744,21 @@ impl SolanaTrade {
         // Use custom priority fee if provided, otherwise use default from trade config
         let base_priority_fee = custom_priority_fee.unwrap_or_else(|| (*self.priority_fee).clone());
         
+        let applied = self.applied_defaults(&dex_type, slippage_basis_points);
+
         // Create basic sell params first
         let sell_params = SellParams {
             rpc: Some(self.rpc.clone()),
-            payer: self.payer.clone(),
+            payer: self.trade_payer(None),
             mint: mint,
             token_amount: Some(token_amount),
-            slippage_basis_points: slippage_basis_points,
+            slippage_basis_points: Some(applied.slippage_bps),
+            min_sol_out: None,
             priority_fee: Arc::new(base_priority_fee.clone()),
-            lookup_table_key,
+            lookup_table_keys,
             recent_blockhash,
-            wait_transaction_confirmed: true,
+            data_size_limit: Some(DEFAULT_DATA_SIZE_LIMIT),
+            wait_level: WaitLevel::Confirmed,
             with_tip: with_tip,
             protocol_params: protocol_params.clone(),
             open_seed_optimize: false,
@@ -582,6 +2766,21 @@ impl SolanaTrade {
             middleware_manager: self.middleware_manager.clone(),
             create_wsol_ata: true,
             close_wsol_ata: true,
+            spend_tracker: self.spend_tracker.clone(),
+            analysis_callback: self.analysis_callback.clone(),
+            clamp_to_balance: self.clamp_sell_to_balance,
+            expected_min_balance: None,
+            commission: self.commission,
+            labels: applied.label(None),
+            success_policy: SuccessPolicy::FirstAccepted,
+            created_account_check: self.created_account_check,
+            wsol_account: self.wsol_account,
+            cu_sizing: applied.cu_sizing,
+            swap_hooks: SwapHooks::default(),
+            use_durable_nonce: false,
+            blockhash_retry: BlockhashRetry::default(),
+            memo: None,
+            deadline: None,
         };
 
         // Validate protocol params
@@ -603,10 +2802,14 @@ impl SolanaTrade {
             DexType::RaydiumAmmV4 => {
                 protocol_params.as_any().downcast_ref::<RaydiumAmmV4Params>().is_some()
             }
+            DexType::MeteoraDlmm => {
+                protocol_params.as_any().downcast_ref::<MeteoraDlmmParams>().is_some()
+            }
+            DexType::Jupiter => protocol_params.as_any().downcast_ref::<JupiterParams>().is_some(),
         };
 
         if !is_valid_params {
-            return Err(anyhow::anyhow!("Invalid protocol params for Trade"));
+            return Err(TradeError::params_mismatch(format!("{:?}", dex_type)).into());
         }
 
         // Execute sell based on tip preference
@@ -614,10 +2817,8 @@ impl SolanaTrade {
             // Convert to tip params and apply custom tip fee
             let mut sell_with_tip_params = sell_params.with_tip(self.swqos_clients.clone());
             let mut priority_fee = base_priority_fee.clone();
-            if custom_buy_tip_fee.is_some() {
-                priority_fee.buy_tip_fee = custom_buy_tip_fee.unwrap();
-                priority_fee.buy_tip_fees =
-                    priority_fee.buy_tip_fees.iter().map(|_| custom_buy_tip_fee.unwrap()).collect();
+            if let Some(custom_buy_tip_fee) = custom_buy_tip_fee {
+                priority_fee.override_buy_tips(TipAmount::from_legacy_sol(custom_buy_tip_fee));
             }
             sell_with_tip_params.priority_fee = priority_fee;
             executor.sell_with_tip(sell_with_tip_params, self.middleware_manager.clone()).await
@@ -626,3 +2827,58 @@ impl SolanaTrade {
         }
     }
 }
+
+#[cfg(test)]
+impl SolanaTrade {
+    /// Client on `rpc` without swqos clients or background tasks, for engine tests
+    pub(crate) fn offline(rpc: SolanaRpcClient) -> Self {
+        Self {
+            payer: Arc::new(Keypair::new()),
+            rpc: Arc::new(rpc),
+            rpc_client: Vec::new(),
+            swqos_clients: Vec::new(),
+            priority_fee: Arc::new(PriorityFee::default()),
+            middleware_manager: None,
+            spend_tracker: None,
+            analysis_callback: None,
+            clamp_sell_to_balance: false,
+            commission: None,
+            amount_precision: None,
+            created_account_check: CreatedAccountCheck::Warn,
+            wsol_account: WsolAccountChoice::CanonicalAta,
+            cu_sizing: CuSizing::Static,
+            per_dex_defaults: Arc::new(HashMap::new()),
+            default_slippage_bps: None,
+            track_position: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ephemeral_wsol_needs_both_flags_where_the_builder_wraps_sol() {
+        let ephemeral = WsolAccountChoice::EphemeralPerTrade;
+        for (create, close) in [(true, false), (false, true), (false, false)] {
+            assert!(
+                check_account_flags(&DexType::PumpSwap, ephemeral, create, close, None).is_err()
+            );
+        }
+        assert!(check_account_flags(&DexType::PumpSwap, ephemeral, true, true, Some(true)).is_ok());
+        assert!(check_account_flags(
+            &DexType::PumpSwap,
+            WsolAccountChoice::CanonicalAta,
+            false,
+            false,
+            Some(true)
+        )
+        .is_ok());
+
+        // Builders that do not wrap SOL ignore the flags with a warning
+        for dex_type in [DexType::PumpFun, DexType::RaydiumClmm] {
+            assert!(check_account_flags(&dex_type, ephemeral, true, false, Some(false)).is_ok());
+        }
+    }
+}
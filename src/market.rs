@@ -0,0 +1,503 @@
+//! Spot price and liquidity depth of a pool, computed from the state in its protocol params
+//!
+//! Bonding curves (PumpFun, Bonk) and the constant product AMMs (PumpSwap, Raydium CPMM,
+//! Raydium AMM V4) are all `x * y = k` on their (virtual) reserves, so price and depth have a
+//! closed form. Fees are not included: depth is what the curve holds, not what a trade nets.
+//!
+//! Raydium CLMM params carry no pool state, use `clmm_price` / `clmm_depth` with the pool's
+//! `sqrt_price_x64` and `liquidity` instead.
+
+use anyhow::{anyhow, Result};
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+
+use crate::{
+    constants::WSOL_TOKEN_ACCOUNT,
+    params::{BonkParams, PumpFunParams, PumpSwapParams, RaydiumAmmV4Params, RaydiumCpmmParams},
+    trading::core::traits::ProtocolParams,
+};
+
+/// SOL a pool can absorb before its spot price moves by a given amount
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DepthReport {
+    /// SOL buys can spend before the price rises by `within_bps`
+    pub buy_side_sol: f64,
+    /// SOL sells can take out before the price falls by `within_bps`
+    pub sell_side_sol: f64,
+}
+
+/// SOL and token reserves of a constant product curve, in base units
+struct Reserves {
+    sol: f64,
+    token: f64,
+    /// Most SOL a buy can still put in (bonding curves complete)
+    max_buy_lamports: Option<f64>,
+    /// Most SOL a sell can take out (real SOL held by a bonding curve)
+    max_sell_lamports: Option<f64>,
+}
+
+impl Reserves {
+    fn new(sol: f64, token: f64) -> Self {
+        Self { sol, token, max_buy_lamports: None, max_sell_lamports: None }
+    }
+
+    /// Orient a pair of pool reserves so that the WSOL side is `sol`
+    fn from_pair(mint_a: &Pubkey, reserve_a: u64, mint_b: &Pubkey, reserve_b: u64) -> Result<Self> {
+        if *mint_b == WSOL_TOKEN_ACCOUNT {
+            Ok(Self::new(reserve_b as f64, reserve_a as f64))
+        } else if *mint_a == WSOL_TOKEN_ACCOUNT {
+            Ok(Self::new(reserve_a as f64, reserve_b as f64))
+        } else {
+            Err(anyhow!("Pool is not quoted in SOL"))
+        }
+    }
+}
+
+fn reserves(params: &dyn ProtocolParams) -> Result<Reserves> {
+    let any = params.as_any();
+    if let Some(params) = any.downcast_ref::<PumpFunParams>() {
+        let curve = &params.bonding_curve;
+        let mut reserves =
+            Reserves::new(curve.virtual_sol_reserves as f64, curve.virtual_token_reserves as f64);
+        // Buying every real token left completes the curve
+        let remaining = curve.virtual_token_reserves.saturating_sub(curve.real_token_reserves);
+        if remaining > 0 {
+            reserves.max_buy_lamports =
+                Some(reserves.sol * curve.real_token_reserves as f64 / remaining as f64);
+        }
+        reserves.max_sell_lamports = Some(curve.real_sol_reserves as f64);
+        return Ok(reserves);
+    }
+    if let Some(params) = any.downcast_ref::<BonkParams>() {
+        if !params.is_wsol_quote() {
+            return Err(anyhow!("Pool is not quoted in SOL"));
+        }
+        let sol = params.virtual_quote.saturating_add(params.real_quote);
+        let token = params.virtual_base.saturating_sub(params.real_base);
+        let mut reserves = Reserves::new(sol as f64, token as f64);
+        reserves.max_sell_lamports = Some(params.real_quote as f64);
+        return Ok(reserves);
+    }
+    if let Some(params) = any.downcast_ref::<PumpSwapParams>() {
+        return Reserves::from_pair(
+            &params.base_mint,
+            params.pool_base_token_reserves,
+            &params.quote_mint,
+            params.pool_quote_token_reserves,
+        );
+    }
+    if let Some(params) = any.downcast_ref::<RaydiumCpmmParams>() {
+        return Reserves::from_pair(
+            &params.base_mint,
+            params.base_reserve,
+            &params.quote_mint,
+            params.quote_reserve,
+        );
+    }
+    if let Some(params) = any.downcast_ref::<RaydiumAmmV4Params>() {
+        return Reserves::from_pair(
+            &params.coin_mint,
+            params.coin_reserve,
+            &params.pc_mint,
+            params.pc_reserve,
+        );
+    }
+    Err(anyhow!("Protocol params carry no reserves, use clmm_price / clmm_depth for CLMM pools"))
+}
+
+/// Spot price in SOL per whole token
+///
+/// This is the marginal price of the curve: an infinitesimal buy pays exactly this price.
+pub fn price(params: &dyn ProtocolParams, token_decimals: u8) -> Result<f64> {
+    let reserves = reserves(params)?;
+    Ok(spot_price(reserves.sol, reserves.token, token_decimals))
+}
+
+/// SOL depth within `within_bps` of the spot price
+///
+/// On `x * y = k` the price scales with the square of the SOL reserve, so moving it up by
+/// `within_bps` takes `sol * (sqrt(1 + b) - 1)` and moving it down releases
+/// `sol * (1 - sqrt(1 - b))`. Bonding curves are additionally capped by the SOL left to
+/// complete the curve and by the real SOL they hold.
+pub fn depth(params: &dyn ProtocolParams, within_bps: u64) -> Result<DepthReport> {
+    let reserves = reserves(params)?;
+    let mut report = constant_product_depth(reserves.sol, within_bps);
+    if let Some(max_buy) = reserves.max_buy_lamports {
+        report.buy_side_sol = report.buy_side_sol.min(max_buy / LAMPORTS_PER_SOL as f64);
+    }
+    if let Some(max_sell) = reserves.max_sell_lamports {
+        report.sell_side_sol = report.sell_side_sol.min(max_sell / LAMPORTS_PER_SOL as f64);
+    }
+    Ok(report)
+}
+
+/// Token base units a buy of `lamports` returns on the curve, before fees
+pub fn buy_output_tokens(params: &dyn ProtocolParams, lamports: u64) -> Result<u64> {
+    let reserves = reserves(params)?;
+    let mut lamports = lamports as f64;
+    if let Some(max_buy) = reserves.max_buy_lamports {
+        lamports = lamports.min(max_buy);
+    }
+    Ok((reserves.token * lamports / (reserves.sol + lamports)) as u64)
+}
+
+/// Lamports a sell of `token_amount` base units returns on the curve, before fees
+pub fn sell_output_lamports(params: &dyn ProtocolParams, token_amount: u64) -> Result<u64> {
+    let reserves = reserves(params)?;
+    let token_amount = token_amount as f64;
+    let mut output = reserves.sol * token_amount / (reserves.token + token_amount);
+    if let Some(max_sell) = reserves.max_sell_lamports {
+        output = output.min(max_sell);
+    }
+    Ok(output as u64)
+}
+
+/// Spot price of a CLMM pool in SOL per whole token
+///
+/// # Arguments
+/// * `sqrt_price_x64` - Pool sqrt price, price of token0 in token1 as Q64.64
+/// * `sol_is_token0` - Whether WSOL is token0 of the pool
+/// * `token_decimals` - Decimals of the non-SOL token
+pub fn clmm_price(sqrt_price_x64: u128, sol_is_token0: bool, token_decimals: u8) -> f64 {
+    let (sol, token) = clmm_virtual_reserves(sqrt_price_x64, 1, sol_is_token0);
+    spot_price(sol, token, token_decimals)
+}
+
+/// Approximate SOL depth of a CLMM pool within `within_bps` of the spot price
+///
+/// Treats the current tick's `liquidity` as constant across the whole range, which makes the
+/// pool a constant product on virtual reserves `L / sqrt(P)` and `L * sqrt(P)`. Liquidity
+/// changes at initialized ticks inside the range are ignored, so the result is only accurate
+/// while the range stays within the current position boundaries.
+pub fn clmm_depth(
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    sol_is_token0: bool,
+    within_bps: u64,
+) -> DepthReport {
+    let (sol, _) = clmm_virtual_reserves(sqrt_price_x64, liquidity, sol_is_token0);
+    constant_product_depth(sol, within_bps)
+}
+
+/// Virtual (SOL, token) reserves of a CLMM pool in base units
+fn clmm_virtual_reserves(sqrt_price_x64: u128, liquidity: u128, sol_is_token0: bool) -> (f64, f64) {
+    let sqrt_price = sqrt_price_x64 as f64 / (1u128 << 64) as f64;
+    let liquidity = liquidity as f64;
+    let (amount0, amount1) = (liquidity / sqrt_price, liquidity * sqrt_price);
+    if sol_is_token0 {
+        (amount0, amount1)
+    } else {
+        (amount1, amount0)
+    }
+}
+
+fn spot_price(sol_reserve: f64, token_reserve: f64, token_decimals: u8) -> f64 {
+    if token_reserve == 0.0 {
+        return 0.0;
+    }
+    let sol = sol_reserve / LAMPORTS_PER_SOL as f64;
+    let tokens = token_reserve / 10f64.powi(token_decimals as i32);
+    sol / tokens
+}
+
+fn constant_product_depth(sol_reserve_lamports: f64, within_bps: u64) -> DepthReport {
+    let sol = sol_reserve_lamports / LAMPORTS_PER_SOL as f64;
+    let ratio = (within_bps as f64 / 10_000.0).min(1.0);
+    DepthReport {
+        buy_side_sol: sol * ((1.0 + ratio).sqrt() - 1.0),
+        sell_side_sol: sol * (1.0 - (1.0 - ratio).sqrt()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::bonding_curve::BondingCurveAccount;
+    use crate::instruction::utils::raydium_amm_v4::accounts::{
+        SWAP_FEE_DENOMINATOR, SWAP_FEE_NUMERATOR,
+    };
+    use crate::utils::calc;
+    use std::sync::Arc;
+
+    const SOL: u64 = LAMPORTS_PER_SOL;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            ((actual - expected) / expected).abs() < tolerance,
+            "{} is not within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    fn pumpswap(base_mint: Pubkey, quote_mint: Pubkey, base: u64, quote: u64) -> PumpSwapParams {
+        PumpSwapParams {
+            pool: Pubkey::new_unique(),
+            base_mint,
+            quote_mint,
+            pool_base_token_reserves: base,
+            pool_quote_token_reserves: quote,
+            base_token_program: crate::constants::TOKEN_PROGRAM,
+            quote_token_program: crate::constants::TOKEN_PROGRAM,
+            creator: Pubkey::default(),
+            auto_handle_wsol: true,
+            fee_config: Pubkey::new_unique(),
+            fee_program: Pubkey::new_unique(),
+        }
+    }
+
+    fn cpmm(mint: Pubkey, token: u64, sol: u64) -> RaydiumCpmmParams {
+        RaydiumCpmmParams {
+            pool_state: Pubkey::new_unique(),
+            amm_config: Pubkey::new_unique(),
+            base_mint: mint,
+            quote_mint: WSOL_TOKEN_ACCOUNT,
+            base_reserve: token,
+            quote_reserve: sol,
+            base_vault: Pubkey::new_unique(),
+            quote_vault: Pubkey::new_unique(),
+            base_token_program: crate::constants::TOKEN_PROGRAM,
+            quote_token_program: crate::constants::TOKEN_PROGRAM,
+            observation_state: Pubkey::new_unique(),
+            input_mint: Pubkey::default(),
+            auto_handle_wsol: true,
+            authority: None,
+            input_vault: None,
+            output_vault: None,
+        }
+    }
+
+    fn amm_v4(mint: Pubkey, token: u64, sol: u64) -> RaydiumAmmV4Params {
+        RaydiumAmmV4Params {
+            amm: Pubkey::new_unique(),
+            coin_mint: mint,
+            pc_mint: WSOL_TOKEN_ACCOUNT,
+            token_coin: Pubkey::new_unique(),
+            token_pc: Pubkey::new_unique(),
+            coin_reserve: token,
+            pc_reserve: sol,
+            auto_handle_wsol: true,
+            open_orders: Pubkey::new_unique(),
+            market: Pubkey::new_unique(),
+            serum_dex: Pubkey::new_unique(),
+            target_orders: Pubkey::new_unique(),
+            bids: Pubkey::default(),
+            asks: Pubkey::default(),
+            event_queue: Pubkey::default(),
+            coin_vault: Pubkey::default(),
+            pc_vault: Pubkey::default(),
+            vault_signer: Pubkey::default(),
+        }
+    }
+
+    fn pumpfun(curve: BondingCurveAccount) -> PumpFunParams {
+        PumpFunParams {
+            bonding_curve: Arc::new(curve),
+            ..PumpFunParams::immediate_sell(Pubkey::default(), false)
+        }
+    }
+
+    #[test]
+    fn constant_product_pools_match_hand_computed_values() {
+        let mint = Pubkey::new_unique();
+        // 1 token (6 decimals) against 2 SOL
+        let pools: [Box<dyn ProtocolParams>; 4] = [
+            Box::new(pumpswap(mint, WSOL_TOKEN_ACCOUNT, 1_000_000, 2 * SOL)),
+            Box::new(pumpswap(WSOL_TOKEN_ACCOUNT, mint, 2 * SOL, 1_000_000)),
+            Box::new(cpmm(mint, 1_000_000, 2 * SOL)),
+            Box::new(amm_v4(mint, 1_000_000, 2 * SOL)),
+        ];
+        for pool in &pools {
+            assert_close(price(pool.as_ref(), 6).unwrap(), 2.0, 1e-12);
+            // sqrt(1.21) = 1.1 and sqrt(0.81) = 0.9, 10% of the 2 SOL reserve either way
+            assert_close(depth(pool.as_ref(), 2_100).unwrap().buy_side_sol, 0.2, 1e-12);
+            assert_close(depth(pool.as_ref(), 1_900).unwrap().sell_side_sol, 0.2, 1e-12);
+            // Halving the price takes 1 - sqrt(0.5) of the reserve out
+            assert_close(depth(pool.as_ref(), 5_000).unwrap().sell_side_sol, 0.585_786_437_6, 1e-9);
+            // 2 SOL in doubles the SOL reserve and halves the token reserve
+            assert_eq!(buy_output_tokens(pool.as_ref(), 2 * SOL).unwrap(), 500_000);
+            assert_eq!(sell_output_lamports(pool.as_ref(), 1_000_000).unwrap(), SOL);
+        }
+
+        let usdc_pool = pumpswap(mint, crate::constants::USDC_TOKEN_ACCOUNT, 1_000_000, 2_000_000);
+        assert!(price(&usdc_pool, 6).is_err());
+        assert!(depth(&usdc_pool, 100).is_err());
+    }
+
+    #[test]
+    fn bonding_curves_are_capped_by_what_they_hold() {
+        // 30 SOL against 1000 tokens, 200 of them real, and 5 real SOL
+        let curve = pumpfun(BondingCurveAccount {
+            virtual_sol_reserves: 30 * SOL,
+            virtual_token_reserves: 1_000_000_000,
+            real_token_reserves: 200_000_000,
+            real_sol_reserves: 5 * SOL,
+            ..Default::default()
+        });
+        assert_close(price(&curve, 6).unwrap(), 0.03, 1e-12);
+        // 30 * (sqrt(1.01) - 1), below both caps
+        assert_close(depth(&curve, 100).unwrap().buy_side_sol, 0.149_626_863, 1e-8);
+        // Uncapped the curve would take 30 * (sqrt(2) - 1) = 12.4 SOL, but 30 * 200 / 800 SOL
+        // buys every real token
+        let full = depth(&curve, 10_000).unwrap();
+        assert_close(full.buy_side_sol, 7.5, 1e-12);
+        assert_close(full.sell_side_sol, 5.0, 1e-12);
+        assert_eq!(buy_output_tokens(&curve, 100 * SOL).unwrap(), 200_000_000);
+        assert_eq!(sell_output_lamports(&curve, 1_000_000_000).unwrap(), 5 * SOL);
+
+        // The same curve as a Bonk pool: virtual quote plus real quote, virtual base less real
+        let bonk = BonkParams {
+            virtual_quote: 20 * SOL as u128,
+            real_quote: 10 * SOL as u128,
+            virtual_base: 1_200_000_000,
+            real_base: 200_000_000,
+            ..Default::default()
+        };
+        assert_close(price(&bonk, 6).unwrap(), 0.03, 1e-12);
+        assert_close(depth(&bonk, 10_000).unwrap().sell_side_sol, 10.0, 1e-12);
+        let usd1 = BonkParams { quote_mint: crate::constants::USD1_TOKEN_ACCOUNT, ..bonk };
+        assert!(price(&usd1, 6).is_err());
+    }
+
+    #[test]
+    fn clmm_price_and_depth_follow_the_sqrt_price() {
+        // sqrt(P) = 2, token0 is worth 4 token1
+        let sqrt_price_x64 = 2u128 << 64;
+        assert_close(clmm_price(sqrt_price_x64, false, 9), 4.0, 1e-12);
+        assert_close(clmm_price(sqrt_price_x64, true, 9), 0.25, 1e-12);
+        assert_close(clmm_price(sqrt_price_x64, false, 6), 0.004, 1e-12);
+
+        // Virtual SOL reserve is L * sqrt(P) as token1 and L / sqrt(P) as token0
+        let liquidity = SOL as u128;
+        assert_close(clmm_depth(sqrt_price_x64, liquidity, false, 2_100).buy_side_sol, 0.2, 1e-12);
+        assert_close(clmm_depth(sqrt_price_x64, liquidity, true, 2_100).buy_side_sol, 0.05, 1e-12);
+    }
+
+    /// Spot price from a buy and a sell of the same size, whose average prices sit on either
+    /// side of it: the buy pays `sol / (token - a)`, the sell gets `sol / (token + a)`
+    fn marginal_price(bought: (f64, f64), sold: (f64, f64), token_decimals: u8) -> f64 {
+        let buy_price = bought.0 / bought.1;
+        let sell_price = sold.0 / sold.1;
+        (buy_price * sell_price).sqrt() * 10f64.powi(token_decimals as i32) / SOL as f64
+    }
+
+    #[test]
+    fn price_is_the_marginal_quote_of_the_swap_math() {
+        const TOLERANCE: f64 = 1e-4;
+        let mint = Pubkey::new_unique();
+        let (token, sol) = (200_000_000_000_000u64, 80 * SOL);
+        // 0.1% of the token reserve, each way
+        let tokens = token / 1_000;
+
+        let pool = pumpswap(mint, WSOL_TOKEN_ACCOUNT, token, sol);
+        let creator = Pubkey::default();
+        let buy = calc::pumpswap::buy_base_input_internal(tokens, 0, token, sol, &creator).unwrap();
+        let sell =
+            calc::pumpswap::sell_base_input_internal(tokens, 0, token, sol, &creator).unwrap();
+        assert_close(
+            price(&pool, 6).unwrap(),
+            marginal_price(
+                (buy.internal_quote_amount as f64, tokens as f64),
+                (sell.internal_quote_amount_out as f64, tokens as f64),
+                6,
+            ),
+            TOLERANCE,
+        );
+
+        // Constant product quotes are fee-inclusive, undo the fees on the input
+        let lamports = sol / 1_000;
+        let pool = cpmm(mint, token, sol);
+        let buy = calc::raydium_cpmm::compute_swap_amount(token, sol, false, lamports, 0);
+        let sell = calc::raydium_cpmm::compute_swap_amount(token, sol, true, tokens, 0);
+        assert_close(
+            price(&pool, 6).unwrap(),
+            marginal_price(
+                ((lamports - buy.fee) as f64, buy.amount_out as f64),
+                (sell.amount_out as f64, (tokens - sell.fee) as f64),
+                6,
+            ),
+            TOLERANCE,
+        );
+
+        // AMM v4 also takes a swap fee, a share of the input fee, out of the output
+        let pool = amm_v4(mint, token, sol);
+        let swap_fee = |fee: u64| fee * SWAP_FEE_NUMERATOR / SWAP_FEE_DENOMINATOR;
+        let buy = calc::raydium_amm_v4::compute_swap_amount(token, sol, false, lamports, 0);
+        let sell = calc::raydium_amm_v4::compute_swap_amount(token, sol, true, tokens, 0);
+        assert_close(
+            price(&pool, 6).unwrap(),
+            marginal_price(
+                ((lamports - buy.fee) as f64, (buy.amount_out + swap_fee(buy.fee)) as f64),
+                ((sell.amount_out + swap_fee(sell.fee)) as f64, (tokens - sell.fee) as f64),
+                6,
+            ),
+            TOLERANCE,
+        );
+
+        // Bonk takes 1.25% of the SOL side in both directions
+        let bonk = BonkParams {
+            virtual_base: token as u128,
+            virtual_quote: sol as u128,
+            ..Default::default()
+        };
+        let bought = calc::bonk::get_buy_token_amount_from_sol_amount(
+            lamports,
+            token as u128,
+            sol as u128,
+            0,
+            0,
+            0,
+        );
+        let received = calc::bonk::get_sell_sol_amount_from_token_amount(
+            tokens,
+            token as u128,
+            sol as u128,
+            0,
+            0,
+            0,
+        );
+        assert_close(
+            price(&bonk, 6).unwrap(),
+            marginal_price(
+                (lamports as f64 * 0.9875, bought as f64),
+                (received as f64 / 0.9875, tokens as f64),
+                6,
+            ),
+            TOLERANCE,
+        );
+
+        // PumpFun charges its fee on top of the SOL of a buy and out of the SOL of a sell
+        let (virtual_token, virtual_sol) = (1_000_000_000_000_000u64, 32_190_005_730u64);
+        let curve = pumpfun(BondingCurveAccount {
+            virtual_token_reserves: virtual_token,
+            virtual_sol_reserves: virtual_sol,
+            real_token_reserves: 793_100_000_000_000,
+            ..Default::default()
+        });
+        let tokens = virtual_token / 1_000;
+        let fee_rate = calc::pumpfun::bonding_curve_fee(SOL as u128, &creator) as f64 / SOL as f64;
+        let cost = calc::pumpfun::get_buy_sol_amount_from_token_amount(
+            virtual_token as u128,
+            virtual_sol as u128,
+            793_100_000_000_000,
+            creator,
+            tokens,
+        )
+        .unwrap();
+        let proceeds = calc::pumpfun::get_sell_sol_amount_from_token_amount(
+            virtual_token as u128,
+            virtual_sol as u128,
+            creator,
+            tokens,
+        );
+        assert_close(
+            price(&curve, 6).unwrap(),
+            marginal_price(
+                (cost as f64 / (1.0 + fee_rate), tokens as f64),
+                (proceeds as f64 / (1.0 - fee_rate), tokens as f64),
+                6,
+            ),
+            TOLERANCE,
+        );
+    }
+}
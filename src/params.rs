@@ -0,0 +1,1157 @@
+//! Protocol params structs under a flat path
+//!
+//! `sol_trade_sdk::params::PumpFunParams` is the path to use, also brought in by
+//! `sol_trade_sdk::prelude::*`. The previous paths (`trading::core::params`,
+//! `instruction::raydium_clmm`) still resolve but are deprecated.
+
+use crate::common::bonding_curve::BondingCurveAccount;
+use crate::common::SolanaRpcClient;
+use crate::instruction::utils::raydium_amm_v4::SerumMarket;
+use crate::solana_streamer_sdk::streaming::event_parser::common::EventType;
+use crate::solana_streamer_sdk::streaming::event_parser::protocols::bonk::{
+    types::PoolState, BonkTradeEvent,
+};
+use crate::trading::core::traits::ProtocolParams;
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey};
+use solana_streamer_sdk::streaming::event_parser::protocols::pumpfun::PumpFunTradeEvent;
+use solana_streamer_sdk::streaming::event_parser::protocols::pumpswap::{
+    PumpSwapBuyEvent, PumpSwapCreatePoolEvent, PumpSwapSellEvent,
+};
+use solana_streamer_sdk::streaming::event_parser::protocols::raydium_amm_v4::types::AmmInfo;
+use solana_streamer_sdk::streaming::event_parser::protocols::raydium_cpmm::RaydiumCpmmSwapEvent;
+use std::sync::Arc;
+
+pub use crate::instruction::utils::pumpfun::PumpFunLayout;
+
+/// PumpFun protocol specific parameters
+/// Configuration parameters specific to PumpFun trading protocol
+#[derive(Clone)]
+pub struct PumpFunParams {
+    pub bonding_curve: Arc<BondingCurveAccount>,
+    pub associated_bonding_curve: Pubkey,
+    pub creator_vault: Pubkey,
+    /// Whether to close token account when selling, only effective during sell operations
+    pub close_token_account_when_sell: Option<bool>,
+    
+    // CUSTOM FIELDS: Restored from backup for compatibility with our trading system
+    /// Fee config account for PumpFun fee management
+    pub fee_config: Pubkey,
+    /// Fee program account for PumpFun fee calculation
+    pub fee_program: Pubkey,
+    /// Instruction account layout, defaults to the layout of the current program
+    pub layout: PumpFunLayout,
+    /// Whether buys pass the volume accumulator accounts, true by default
+    pub include_volume_accumulators: bool,
+}
+
+impl PumpFunParams {
+    pub fn immediate_sell(creator_vault: Pubkey, close_token_account_when_sell: bool) -> Self {
+        Self {
+            bonding_curve: Arc::new(BondingCurveAccount { ..Default::default() }),
+            associated_bonding_curve: Pubkey::default(),
+            creator_vault: creator_vault,
+            close_token_account_when_sell: Some(close_token_account_when_sell),
+            fee_config: crate::instruction::utils::pumpfun::accounts::FEE_CONFIG,
+            fee_program: crate::instruction::utils::pumpfun::accounts::FEE_PROGRAM,
+            layout: PumpFunLayout::default(),
+            include_volume_accumulators: true,
+        }
+    }
+
+    pub fn from_dev_trade(
+        event: &PumpFunTradeEvent,
+        close_token_account_when_sell: Option<bool>,
+    ) -> Self {
+        let bonding_curve = BondingCurveAccount::from_dev_trade(
+            &event.mint,
+            event.token_amount,
+            event.max_sol_cost,
+            event.creator,
+        );
+        Self {
+            bonding_curve: Arc::new(bonding_curve),
+            associated_bonding_curve: event.associated_bonding_curve,
+            creator_vault: event.creator_vault,
+            close_token_account_when_sell: close_token_account_when_sell,
+            fee_config: crate::instruction::utils::pumpfun::accounts::FEE_CONFIG,
+            fee_program: crate::instruction::utils::pumpfun::accounts::FEE_PROGRAM,
+            layout: PumpFunLayout::default(),
+            include_volume_accumulators: true,
+        }
+    }
+
+    pub fn from_trade(
+        event: &PumpFunTradeEvent,
+        close_token_account_when_sell: Option<bool>,
+    ) -> Self {
+        let bonding_curve = BondingCurveAccount::from_trade(event);
+        Self {
+            bonding_curve: Arc::new(bonding_curve),
+            associated_bonding_curve: event.associated_bonding_curve,
+            creator_vault: event.creator_vault,
+            close_token_account_when_sell: close_token_account_when_sell,
+            fee_config: crate::instruction::utils::pumpfun::accounts::FEE_CONFIG,
+            fee_program: crate::instruction::utils::pumpfun::accounts::FEE_PROGRAM,
+            layout: PumpFunLayout::default(),
+            include_volume_accumulators: true,
+        }
+    }
+
+    /// Use a specific instruction layout, see `instruction::utils::pumpfun::detect_layout`
+    pub fn with_layout(mut self, layout: PumpFunLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Leave the global and user volume accumulators out of buys with `false`
+    ///
+    /// Every pump.fun buy write-locks the global volume accumulator, so bots buying the same
+    /// slots contend on it. Without the accumulators the buy uses the 14-account form the program
+    /// still accepts, and the wallet earns no volume rewards for it.
+    pub fn with_volume_accumulators(mut self, include_volume_accumulators: bool) -> Self {
+        self.include_volume_accumulators = include_volume_accumulators;
+        self
+    }
+
+    /// Build params from the bonding curve account of `mint`
+    pub async fn from_mint_by_rpc(
+        rpc: &SolanaRpcClient,
+        mint: &Pubkey,
+    ) -> Result<Self, anyhow::Error> {
+        use crate::instruction::utils::pumpfun;
+
+        let (bonding_curve, account) = pumpfun::fetch_bonding_curve_account(rpc, mint).await?;
+        let creator_vault = pumpfun::get_creator_vault_pda(&bonding_curve.creator)
+            .ok_or_else(|| anyhow::anyhow!("Failed to derive creator vault"))?;
+        let mut params = Self::immediate_sell(creator_vault, false);
+        params.bonding_curve = Arc::new(BondingCurveAccount {
+            account,
+            virtual_token_reserves: bonding_curve.virtual_token_reserves,
+            virtual_sol_reserves: bonding_curve.virtual_sol_reserves,
+            real_token_reserves: bonding_curve.real_token_reserves,
+            real_sol_reserves: bonding_curve.real_sol_reserves,
+            token_total_supply: pumpfun::global_constants::TOKEN_TOTAL_SUPPLY,
+            complete: bonding_curve.complete,
+            creator: bonding_curve.creator,
+            ..Default::default()
+        });
+        Ok(params)
+    }
+}
+
+impl ProtocolParams for PumpFunParams {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn ProtocolParams> {
+        Box::new(self.clone())
+    }
+}
+
+/// PumpSwap Protocol Specific Parameters
+///
+/// Parameters for configuring PumpSwap trading protocol, including liquidity pool information,
+/// token configuration, and transaction amounts.
+///
+/// **Performance Note**: If these parameters are not provided, the system will attempt to
+/// retrieve the relevant information from RPC, which will increase transaction time.
+/// For optimal performance, it is recommended to provide all necessary parameters in advance.
+#[derive(Clone)]
+pub struct PumpSwapParams {
+    /// Liquidity pool address
+    pub pool: Pubkey,
+    /// Base token mint address
+    /// The mint account address of the base token in the trading pair
+    pub base_mint: Pubkey,
+    /// Quote token mint address
+    /// The mint account address of the quote token in the trading pair, usually SOL or USDC
+    pub quote_mint: Pubkey,
+    /// Base token reserves in the pool
+    pub pool_base_token_reserves: u64,
+    /// Quote token reserves in the pool
+    pub pool_quote_token_reserves: u64,
+    /// Base token program ID (spl_token::ID or spl_token_2022::ID), spl_token::ID when unset
+    pub base_token_program: Pubkey,
+    /// Quote token program ID (spl_token::ID or spl_token_2022::ID), spl_token::ID when unset
+    pub quote_token_program: Pubkey,
+    
+    // CUSTOM FIELDS: Restored from backup for compatibility with our trading system
+    /// Token creator address (coin_creator from PumpSwap events)
+    /// This is required for deriving the correct coin_creator_vault_authority
+    pub creator: Pubkey,
+    /// Deprecated alias of the trade's `create_wsol_ata` and `close_wsol_ata`, which the
+    /// builder follows while this is true. False turns both off.
+    pub auto_handle_wsol: bool,
+    /// Fee config account for PumpSwap fee management
+    pub fee_config: Pubkey,
+    /// Fee program account for PumpSwap fee calculation
+    pub fee_program: Pubkey,
+}
+
+impl PumpSwapParams {
+    pub fn from_buy_trade(event: &PumpSwapBuyEvent) -> Self {
+        let fee_config = crate::instruction::utils::pumpswap::accounts::get_fee_config();
+        let fee_program = crate::instruction::utils::pumpswap::accounts::FEE_PROGRAM;
+        
+        Self {
+            pool: event.pool,
+            base_mint: event.base_mint,
+            quote_mint: event.quote_mint,
+            pool_base_token_reserves: event.pool_base_token_reserves,
+            pool_quote_token_reserves: event.pool_quote_token_reserves,
+            base_token_program: event.base_token_program,
+            quote_token_program: event.quote_token_program,
+            creator: event.coin_creator,
+            auto_handle_wsol: true,
+            // 🔧 CRITICAL FIX: Event fee fields are #[borsh(skip)] and empty - use proper PDA derivation
+            fee_config,
+            fee_program,
+        }
+    }
+
+    pub fn from_sell_trade(event: &PumpSwapSellEvent) -> Self {
+        let fee_config = crate::instruction::utils::pumpswap::accounts::get_fee_config();
+        let fee_program = crate::instruction::utils::pumpswap::accounts::FEE_PROGRAM;
+        
+        Self {
+            pool: event.pool,
+            base_mint: event.base_mint,
+            quote_mint: event.quote_mint,
+            pool_base_token_reserves: event.pool_base_token_reserves,
+            pool_quote_token_reserves: event.pool_quote_token_reserves,
+            base_token_program: event.base_token_program,
+            quote_token_program: event.quote_token_program,
+            creator: event.coin_creator,
+            auto_handle_wsol: true,
+            // 🔧 CRITICAL FIX: Event fee fields are #[borsh(skip)] and empty - use proper PDA derivation
+            fee_config,
+            fee_program,
+        }
+    }
+
+    /// Build params from the event of a pool's creation, e.g. a PumpFun migration, without RPC
+    ///
+    /// Reserves are the pool's initial ones, so the params can buy in the slot the pool was
+    /// created. The event carries no token programs; each is Token-2022 when the creator's token
+    /// account in the event is its Token-2022 ATA, spl_token otherwise.
+    pub fn from_create_pool_event(event: &PumpSwapCreatePoolEvent) -> Self {
+        let fee_config = crate::instruction::utils::pumpswap::accounts::get_fee_config();
+        let fee_program = crate::instruction::utils::pumpswap::accounts::FEE_PROGRAM;
+        let token_program = |mint: &Pubkey, token_account: &Pubkey| {
+            let token_2022_ata =
+                spl_associated_token_account::get_associated_token_address_with_program_id(
+                    &event.creator,
+                    mint,
+                    &crate::constants::TOKEN_PROGRAM_2022,
+                );
+            if token_2022_ata == *token_account {
+                crate::constants::TOKEN_PROGRAM_2022
+            } else {
+                crate::constants::TOKEN_PROGRAM
+            }
+        };
+
+        Self {
+            pool: event.pool,
+            base_mint: event.base_mint,
+            quote_mint: event.quote_mint,
+            pool_base_token_reserves: event.pool_base_amount,
+            pool_quote_token_reserves: event.pool_quote_amount,
+            base_token_program: token_program(&event.base_mint, &event.user_base_token_account),
+            quote_token_program: token_program(&event.quote_mint, &event.user_quote_token_account),
+            creator: event.coin_creator,
+            auto_handle_wsol: true,
+            fee_config,
+            fee_program,
+        }
+    }
+
+    /// Build params from the pool account, then its two vaults and two mints (two round trips)
+    ///
+    /// Fails with an `AccountFetchError` naming the accounts that could not be loaded.
+    pub async fn from_pool_address_by_rpc(
+        rpc: &SolanaRpcClient,
+        pool_address: &Pubkey,
+    ) -> Result<Self, anyhow::Error> {
+        let pool_data = crate::instruction::utils::pumpswap::fetch_pool(rpc, pool_address).await?;
+        let (
+            pool_base_token_reserves,
+            pool_quote_token_reserves,
+            base_token_program,
+            quote_token_program,
+        ) = crate::instruction::utils::pumpswap::get_token_balances_and_programs(&pool_data, rpc)
+            .await?;
+
+        let fee_config = crate::instruction::utils::pumpswap::accounts::get_fee_config();
+        let fee_program = crate::instruction::utils::pumpswap::accounts::FEE_PROGRAM;
+        
+        Ok(Self {
+            pool: pool_address.clone(),
+            base_mint: pool_data.base_mint,
+            quote_mint: pool_data.quote_mint,
+            pool_base_token_reserves: pool_base_token_reserves,
+            pool_quote_token_reserves: pool_quote_token_reserves,
+            base_token_program,
+            quote_token_program,
+            creator: pool_data.coin_creator, // Extract creator from pool data
+            auto_handle_wsol: true,
+            fee_config,
+            fee_program,
+        })
+    }
+
+    /// Resolved base token program, spl_token::ID when unset
+    pub fn base_token_program(&self) -> Pubkey {
+        if self.base_token_program == Pubkey::default() {
+            crate::constants::TOKEN_PROGRAM
+        } else {
+            self.base_token_program
+        }
+    }
+
+    /// Resolved quote token program, spl_token::ID when unset
+    pub fn quote_token_program(&self) -> Pubkey {
+        if self.quote_token_program == Pubkey::default() {
+            crate::constants::TOKEN_PROGRAM
+        } else {
+            self.quote_token_program
+        }
+    }
+}
+
+impl ProtocolParams for PumpSwapParams {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn ProtocolParams> {
+        Box::new(self.clone())
+    }
+}
+
+/// Bonk protocol specific parameters
+/// Configuration parameters specific to Bonk trading protocol
+#[derive(Clone, Default)]
+pub struct BonkParams {
+    pub virtual_base: u128,
+    pub virtual_quote: u128,
+    pub real_base: u128,
+    pub real_quote: u128,
+    pub pool_state: Pubkey,
+    pub base_vault: Pubkey,
+    pub quote_vault: Pubkey,
+    /// Token program ID
+    /// Specifies the program used by the token, usually spl_token::ID or spl_token_2022::ID
+    pub mint_token_program: Pubkey,
+    pub platform_config: Pubkey,
+    pub platform_associated_account: Pubkey,
+    pub creator_associated_account: Pubkey,
+    /// Quote token mint of the pool, `Pubkey::default()` means WSOL
+    pub quote_mint: Pubkey,
+    /// Quote token program, `Pubkey::default()` means spl_token::ID
+    pub quote_token_program: Pubkey,
+    
+    // CUSTOM FIELDS: Restored from backup for compatibility with our trading system
+    /// Deprecated alias of the trade's `create_wsol_ata` and `close_wsol_ata`, which the
+    /// builder follows while this is true. False turns both off.
+    pub auto_handle_wsol: bool,
+    /// Dynamic fee destination accounts from trade event  
+    pub fee_destination_1: Pubkey,
+    pub fee_destination_2: Pubkey,
+}
+
+impl BonkParams {
+    pub fn immediate_sell(
+        mint_token_program: Pubkey,
+        platform_config: Pubkey,
+        platform_associated_account: Pubkey,
+        creator_associated_account: Pubkey,
+    ) -> Self {
+        Self {
+            mint_token_program,
+            platform_config,
+            platform_associated_account,
+            creator_associated_account,
+            ..Default::default()
+        }
+    }
+    pub fn from_trade(trade_info: BonkTradeEvent) -> Self {
+        Self {
+            virtual_base: trade_info.virtual_base as u128,
+            virtual_quote: trade_info.virtual_quote as u128,
+            real_base: trade_info.real_base_after as u128,
+            real_quote: trade_info.real_quote_after as u128,
+            pool_state: trade_info.pool_state,
+            base_vault: trade_info.base_vault,
+            quote_vault: trade_info.quote_vault,
+            mint_token_program: trade_info.base_token_program,
+            platform_config: trade_info.platform_config,
+            platform_associated_account: trade_info.platform_associated_account,
+            creator_associated_account: trade_info.creator_associated_account,
+            quote_mint: trade_info.quote_token_mint,
+            quote_token_program: trade_info.quote_token_program,
+            auto_handle_wsol: true,
+            fee_destination_1: trade_info.fee_destination_1,
+            fee_destination_2: trade_info.fee_destination_2,
+        }
+    }
+
+    pub fn from_dev_trade(trade_info: BonkTradeEvent) -> Self {
+        const DEFAULT_VIRTUAL_BASE: u128 = 1073025605596382;
+        const DEFAULT_VIRTUAL_QUOTE: u128 = 30000852951;
+        let amount_in = if trade_info.metadata.event_type == EventType::BonkBuyExactIn {
+            trade_info.amount_in
+        } else {
+            crate::instruction::utils::bonk::get_amount_in(
+                trade_info.amount_out,
+                crate::instruction::utils::bonk::accounts::PROTOCOL_FEE_RATE,
+                crate::instruction::utils::bonk::accounts::PLATFORM_FEE_RATE,
+                crate::instruction::utils::bonk::accounts::SHARE_FEE_RATE,
+                DEFAULT_VIRTUAL_BASE,
+                DEFAULT_VIRTUAL_QUOTE,
+                0,
+                0,
+                0,
+            )
+        };
+        let real_quote = crate::instruction::utils::bonk::get_amount_in_net(
+            amount_in,
+            crate::instruction::utils::bonk::accounts::PROTOCOL_FEE_RATE,
+            crate::instruction::utils::bonk::accounts::PLATFORM_FEE_RATE,
+            crate::instruction::utils::bonk::accounts::SHARE_FEE_RATE,
+        ) as u128;
+        let amount_out = if trade_info.metadata.event_type == EventType::BonkBuyExactIn {
+            crate::instruction::utils::bonk::get_amount_out(
+                trade_info.amount_in,
+                crate::instruction::utils::bonk::accounts::PROTOCOL_FEE_RATE,
+                crate::instruction::utils::bonk::accounts::PLATFORM_FEE_RATE,
+                crate::instruction::utils::bonk::accounts::SHARE_FEE_RATE,
+                DEFAULT_VIRTUAL_BASE,
+                DEFAULT_VIRTUAL_QUOTE,
+                0,
+                0,
+                0,
+            ) as u128
+        } else {
+            trade_info.amount_out as u128
+        };
+        let real_base = amount_out;
+        Self {
+            virtual_base: DEFAULT_VIRTUAL_BASE,
+            virtual_quote: DEFAULT_VIRTUAL_QUOTE,
+            real_base: real_base,
+            real_quote: real_quote,
+            pool_state: trade_info.pool_state,
+            base_vault: trade_info.base_vault,
+            quote_vault: trade_info.quote_vault,
+            mint_token_program: trade_info.base_token_program,
+            platform_config: trade_info.platform_config,
+            platform_associated_account: trade_info.platform_associated_account,
+            creator_associated_account: trade_info.creator_associated_account,
+            quote_mint: trade_info.quote_token_mint,
+            quote_token_program: trade_info.quote_token_program,
+            auto_handle_wsol: true,
+            fee_destination_1: trade_info.fee_destination_1,
+            fee_destination_2: trade_info.fee_destination_2,
+        }
+    }
+
+    /// Build params for the pool of `mint`, whichever of WSOL, USD1 and USDC it is quoted in
+    ///
+    /// The candidate pools and the mint are loaded in one round trip, see
+    /// `bonk::find_pool_by_base_mint`.
+    pub async fn from_mint_by_rpc(
+        rpc: &SolanaRpcClient,
+        mint: &Pubkey,
+    ) -> Result<Self, anyhow::Error> {
+        let (pool_address, pool_data, mint_token_program, quote_mint) =
+            crate::instruction::utils::bonk::find_pool_by_base_mint(rpc, mint).await?;
+        Ok(Self::from_pool_state(
+            pool_address,
+            pool_data,
+            mint_token_program,
+            quote_mint,
+            crate::constants::TOKEN_PROGRAM,
+        ))
+    }
+
+    /// Build params for the pool of `mint` quoted in `quote_mint` (e.g. WSOL or USD1), the pool
+    /// and the mints loaded in one round trip
+    ///
+    /// Fails with an `AccountFetchError` naming the accounts that could not be loaded.
+    pub async fn from_mint_and_quote_by_rpc(
+        rpc: &SolanaRpcClient,
+        mint: &Pubkey,
+        quote_mint: &Pubkey,
+    ) -> Result<Self, anyhow::Error> {
+        let pool_address = crate::instruction::utils::bonk::get_pool_pda(mint, quote_mint)
+            .ok_or_else(|| anyhow::anyhow!("Failed to derive Bonk pool for {}", mint))?;
+        let (pool_data, mint_token_program, quote_token_program) =
+            crate::instruction::utils::bonk::fetch_pool_state_and_token_programs(
+                rpc,
+                &pool_address,
+                mint,
+                quote_mint,
+            )
+            .await?;
+        Ok(Self::from_pool_state(
+            pool_address,
+            pool_data,
+            mint_token_program,
+            *quote_mint,
+            quote_token_program,
+        ))
+    }
+
+    fn from_pool_state(
+        pool_address: Pubkey,
+        pool_data: PoolState,
+        mint_token_program: Pubkey,
+        quote_mint: Pubkey,
+        quote_token_program: Pubkey,
+    ) -> Self {
+        let platform_associated_account =
+            crate::instruction::utils::bonk::get_platform_associated_account(
+                &pool_data.platform_config,
+            );
+        let creator_associated_account =
+            crate::instruction::utils::bonk::get_creator_associated_account(&pool_data.creator);
+        let platform_associated_account = platform_associated_account.unwrap();
+        let creator_associated_account = creator_associated_account.unwrap();
+        Self {
+            virtual_base: pool_data.virtual_base as u128,
+            virtual_quote: pool_data.virtual_quote as u128,
+            real_base: pool_data.real_base as u128,
+            real_quote: pool_data.real_quote as u128,
+            pool_state: pool_address,
+            base_vault: pool_data.base_vault,
+            quote_vault: pool_data.quote_vault,
+            mint_token_program,
+            platform_config: pool_data.platform_config,
+            platform_associated_account,
+            creator_associated_account,
+            quote_mint,
+            quote_token_program,
+            auto_handle_wsol: true,
+            fee_destination_1: Pubkey::default(),
+            fee_destination_2: Pubkey::default(),
+        }
+    }
+
+    /// Resolved quote mint, WSOL when unset
+    pub fn quote_mint(&self) -> Pubkey {
+        if self.quote_mint == Pubkey::default() {
+            crate::constants::WSOL_TOKEN_ACCOUNT
+        } else {
+            self.quote_mint
+        }
+    }
+
+    /// Resolved quote token program, spl_token::ID when unset
+    pub fn quote_token_program(&self) -> Pubkey {
+        if self.quote_token_program == Pubkey::default() {
+            crate::constants::TOKEN_PROGRAM
+        } else {
+            self.quote_token_program
+        }
+    }
+
+    /// Whether the pool is quoted in WSOL, the only case where wSOL wrapping applies
+    pub fn is_wsol_quote(&self) -> bool {
+        self.quote_mint() == crate::constants::WSOL_TOKEN_ACCOUNT
+    }
+}
+
+impl ProtocolParams for BonkParams {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn ProtocolParams> {
+        Box::new(self.clone())
+    }
+}
+
+/// RaydiumCpmm protocol specific parameters
+/// Configuration parameters specific to Raydium CPMM trading protocol
+#[derive(Clone)]
+pub struct RaydiumCpmmParams {
+    /// Pool address
+    pub pool_state: Pubkey,
+    /// Amm config address
+    pub amm_config: Pubkey,
+    /// Base token mint address
+    pub base_mint: Pubkey,
+    /// Quote token mint address
+    pub quote_mint: Pubkey,
+    /// Base token reserve amount in the pool
+    pub base_reserve: u64,
+    /// Quote token reserve amount in the pool
+    pub quote_reserve: u64,
+    /// Base token vault address
+    pub base_vault: Pubkey,
+    /// Quote token vault address
+    pub quote_vault: Pubkey,
+    /// Base token program ID (usually spl_token::ID or spl_token_2022::ID)
+    pub base_token_program: Pubkey,
+    /// Quote token program ID (usually spl_token::ID or spl_token_2022::ID)
+    pub quote_token_program: Pubkey,
+    /// Observation state account
+    pub observation_state: Pubkey,
+    /// Mint paid on buys and received on sells, one of the pool's two mints. WSOL when unset,
+    /// see `with_input_mint` for other pairs such as USDC/token.
+    pub input_mint: Pubkey,
+
+    // CUSTOM FIELDS: Restored from backup for backward compatibility with our trading system
+    /// Deprecated alias of the trade's `create_wsol_ata` and `close_wsol_ata`, which the
+    /// builder follows while this is true. False turns both off.
+    pub auto_handle_wsol: bool,
+    /// Pool authority address (for backward compatibility)
+    pub authority: Option<Pubkey>,
+    /// Input token vault account (alias for base_vault for backward compatibility)
+    pub input_vault: Option<Pubkey>,
+    /// Output token vault account (alias for quote_vault for backward compatibility)  
+    pub output_vault: Option<Pubkey>,
+}
+
+impl RaydiumCpmmParams {
+    pub fn from_trade(
+        trade_info: RaydiumCpmmSwapEvent,
+        base_reserve: u64,
+        quote_reserve: u64,
+    ) -> Self {
+        Self {
+            pool_state: trade_info.pool_state,
+            amm_config: trade_info.amm_config,
+            base_mint: trade_info.input_token_mint,
+            quote_mint: trade_info.output_token_mint,
+            base_reserve: base_reserve,
+            quote_reserve: quote_reserve,
+            base_vault: trade_info.input_vault,
+            quote_vault: trade_info.output_vault,
+            base_token_program: trade_info.input_token_program,
+            quote_token_program: trade_info.output_token_program,
+            observation_state: trade_info.observation_state,
+            input_mint: Pubkey::default(),
+            auto_handle_wsol: true,
+            authority: None,
+            input_vault: Some(trade_info.input_vault),
+            output_vault: Some(trade_info.output_vault),
+        }
+    }
+
+    /// Build params from the pool state and its two vaults (two round trips)
+    ///
+    /// Fails with an `AccountFetchError` naming the accounts that could not be loaded.
+    pub async fn from_pool_address_by_rpc(
+        rpc: &SolanaRpcClient,
+        pool_address: &Pubkey,
+    ) -> Result<Self, anyhow::Error> {
+        let pool =
+            crate::instruction::utils::raydium_cpmm::fetch_pool_state(rpc, pool_address).await?;
+        let (token0_balance, token1_balance) =
+            crate::instruction::utils::raydium_cpmm::get_pool_token_balances(
+                rpc,
+                pool_address,
+                &pool.token0_mint,
+                &pool.token1_mint,
+            )
+            .await?;
+        Ok(Self {
+            pool_state: pool_address.clone(),
+            amm_config: pool.amm_config,
+            base_mint: pool.token0_mint,
+            quote_mint: pool.token1_mint,
+            base_reserve: token0_balance,
+            quote_reserve: token1_balance,
+            base_vault: pool.token0_vault,
+            quote_vault: pool.token1_vault,
+            base_token_program: pool.token0_program,
+            quote_token_program: pool.token1_program,
+            observation_state: pool.observation_key,
+            input_mint: Pubkey::default(),
+            auto_handle_wsol: true,
+            authority: None,
+            input_vault: Some(pool.token0_vault),
+            output_vault: Some(pool.token1_vault),
+        })
+    }
+
+    /// Trade the token against `input_mint` instead of WSOL
+    ///
+    /// Buys then spend `sol_amount` in base units of `input_mint` and sells pay out in it. No
+    /// wSOL is wrapped or unwrapped unless `input_mint` is WSOL.
+    pub fn with_input_mint(mut self, input_mint: Pubkey) -> Self {
+        self.input_mint = input_mint;
+        self
+    }
+
+    /// Resolved input mint, WSOL when unset
+    pub fn input_mint(&self) -> Pubkey {
+        if self.input_mint == Pubkey::default() {
+            crate::constants::WSOL_TOKEN_ACCOUNT
+        } else {
+            self.input_mint
+        }
+    }
+
+    /// Whether the input mint is WSOL, the only case where wSOL wrapping applies
+    pub fn is_wsol_input(&self) -> bool {
+        self.input_mint() == crate::constants::WSOL_TOKEN_ACCOUNT
+    }
+
+    /// Whether the input mint is the pool's base mint, orienting the reserves of
+    /// `compute_swap_amount` for buys. Fails when it is neither of the pool's mints.
+    pub fn is_input_base(&self) -> Result<bool, anyhow::Error> {
+        let input_mint = self.input_mint();
+        if input_mint == self.base_mint {
+            Ok(true)
+        } else if input_mint == self.quote_mint {
+            Ok(false)
+        } else {
+            Err(anyhow::anyhow!(
+                "Input mint {} is not a mint of the pool ({} / {})",
+                input_mint,
+                self.base_mint,
+                self.quote_mint
+            ))
+        }
+    }
+
+    /// Token program of the input mint, spl_token::ID when unset
+    pub fn input_token_program(&self) -> Result<Pubkey, anyhow::Error> {
+        let program =
+            if self.is_input_base()? { self.base_token_program } else { self.quote_token_program };
+        Ok(if program == Pubkey::default() { crate::constants::TOKEN_PROGRAM } else { program })
+    }
+}
+
+impl ProtocolParams for RaydiumCpmmParams {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn ProtocolParams> {
+        Box::new(self.clone())
+    }
+}
+
+/// RaydiumCpmm protocol specific parameters
+/// Configuration parameters specific to Raydium CPMM trading protocol
+#[derive(Clone)]
+pub struct RaydiumAmmV4Params {
+    /// AMM pool address
+    pub amm: Pubkey,
+    /// Base token (coin) mint address
+    pub coin_mint: Pubkey,
+    /// Quote token (pc) mint address  
+    pub pc_mint: Pubkey,
+    /// Pool's coin token account address
+    pub token_coin: Pubkey,
+    /// Pool's pc token account address
+    pub token_pc: Pubkey,
+    /// Current coin reserve amount in the pool
+    pub coin_reserve: u64,
+    /// Current pc reserve amount in the pool
+    pub pc_reserve: u64,
+    /// Deprecated alias of the trade's `create_wsol_ata` and `close_wsol_ata`, which the
+    /// builder follows while this is true. False turns both off.
+    pub auto_handle_wsol: bool,
+    /// AMM open orders account
+    pub open_orders: Pubkey,
+    /// Serum market account
+    pub market: Pubkey,
+    /// Serum program account
+    pub serum_dex: Pubkey,
+    /// AMM target orders account
+    pub target_orders: Pubkey,
+    /// Serum market bids, asks and event queue accounts
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+    pub event_queue: Pubkey,
+    /// Serum market coin and pc vaults
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+    /// Serum market vault signer
+    pub vault_signer: Pubkey,
+}
+
+impl RaydiumAmmV4Params {
+    /// Params without the serum market accounts, set them with `with_market`
+    ///
+    /// Until then the builder passes the market and pool vaults in their place, which only
+    /// pools whose swaps skip the market accept.
+    pub fn from_amm_info_and_reserves(
+        amm: Pubkey,
+        amm_info: AmmInfo,
+        coin_reserve: u64,
+        pc_reserve: u64,
+    ) -> Self {
+        Self {
+            amm,
+            coin_mint: amm_info.coin_mint,
+            pc_mint: amm_info.pc_mint,
+            token_coin: amm_info.token_coin,
+            token_pc: amm_info.token_pc,
+            coin_reserve,
+            pc_reserve,
+            auto_handle_wsol: true,
+            open_orders: amm_info.open_orders,
+            market: amm_info.market,
+            serum_dex: amm_info.serum_dex,
+            target_orders: amm_info.target_orders,
+            bids: Pubkey::default(),
+            asks: Pubkey::default(),
+            event_queue: Pubkey::default(),
+            coin_vault: Pubkey::default(),
+            pc_vault: Pubkey::default(),
+            vault_signer: Pubkey::default(),
+        }
+    }
+
+    /// Set the serum market accounts, e.g. from `raydium_amm_v4::fetch_market`
+    pub fn with_market(mut self, market: &SerumMarket) -> Self {
+        self.bids = market.bids;
+        self.asks = market.asks;
+        self.event_queue = market.event_queue;
+        self.coin_vault = market.coin_vault;
+        self.pc_vault = market.pc_vault;
+        self.vault_signer = market.vault_signer;
+        self
+    }
+
+    /// Whether the serum market accounts are set
+    pub fn has_market(&self) -> bool {
+        self.vault_signer != Pubkey::default()
+    }
+
+    /// Build params from the amm account, then its two vaults and its serum market (two round
+    /// trips)
+    ///
+    /// Fails with an `AccountFetchError` naming the accounts that could not be loaded.
+    pub async fn from_amm_address_by_rpc(
+        rpc: &SolanaRpcClient,
+        amm: Pubkey,
+    ) -> Result<Self, anyhow::Error> {
+        let amm_info = crate::instruction::utils::raydium_amm_v4::fetch_amm_info(rpc, amm).await?;
+        let (coin_reserve, pc_reserve, market) =
+            crate::instruction::utils::raydium_amm_v4::fetch_reserves_and_market(rpc, &amm_info)
+                .await?;
+        Ok(Self::from_amm_info_and_reserves(amm, amm_info, coin_reserve, pc_reserve)
+            .with_market(&market))
+    }
+}
+
+impl ProtocolParams for RaydiumAmmV4Params {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn ProtocolParams> {
+        Box::new(self.clone())
+    }
+}
+
+/// Raydium CLMM V1 parameters
+#[derive(Clone)]
+pub struct RaydiumClmmParams {
+    /// Core CLMM accounts
+    pub amm_config: Pubkey,
+    pub pool_state: Pubkey,
+    pub input_vault: Pubkey,
+    pub output_vault: Pubkey,
+    pub observation_state: Pubkey,
+    /// Tick arrays for swap execution
+    pub tick_arrays: Vec<Pubkey>,
+    /// Token programs
+    pub token_program: Pubkey,
+    /// User token accounts
+    pub payer_sol_account: Pubkey,
+    pub payer_token_account: Pubkey,
+    /// Instruction parameters
+    pub other_amount_threshold: u64,
+    pub sqrt_price_limit_x64: u128,
+    pub is_base_input: bool,
+}
+
+impl ProtocolParams for RaydiumClmmParams {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn ProtocolParams> {
+        Box::new(self.clone())
+    }
+}
+
+/// Raydium CLMM V2 protocol specific parameters
+/// Configuration parameters specific to Raydium CLMM V2 trading protocol
+#[derive(Clone)]
+pub struct RaydiumClmmV2Params {
+    /// Core CLMM accounts
+    pub amm_config: Pubkey,
+    pub pool_state: Pubkey,
+    pub input_vault: Pubkey,
+    pub output_vault: Pubkey,
+    pub observation_state: Pubkey,
+    /// Vault mint addresses (V2 specific)
+    pub input_vault_mint: Pubkey,
+    pub output_vault_mint: Pubkey,
+    /// Tick arrays for swap execution, in any order; the builder orders them for the swap
+    /// direction, keeping the tick array bitmap extension first if passed
+    pub tick_arrays: Vec<Pubkey>,
+    /// `tick_spacing` and `tick_current` of the pool state
+    pub tick_spacing: u16,
+    pub tick_current: i32,
+    /// Token programs (V2 includes token_program_2022)
+    pub input_token_program: Pubkey,
+    pub output_token_program: Pubkey,
+    pub token_program: Pubkey,
+    pub token_program_2022: Pubkey,
+    pub memo_program: Pubkey,
+    /// User token accounts
+    pub payer_sol_account: Pubkey,
+    pub payer_token_account: Pubkey,
+    /// Instruction parameters
+    pub other_amount_threshold: u64,
+    pub sqrt_price_limit_x64: u128,
+    pub is_base_input: bool,
+    /// Deprecated alias of the trade's `create_wsol_ata` and `close_wsol_ata`, which the
+    /// builder follows while this is true. False turns both off.
+    pub auto_handle_wsol: bool,
+}
+
+impl RaydiumClmmV2Params {
+    /// Build params for swapping `input_mint` on the pool (two round trips)
+    ///
+    /// The params are directional: pass wSOL as `input_mint` for a buy and the token for a
+    /// sell. The tick arrays are the one holding the current tick and its two neighbours in
+    /// swap direction. Fails with an `AccountFetchError` naming the accounts that could not be
+    /// loaded, or when `input_mint` is not one of the pool's mints.
+    pub async fn from_pool_address_by_rpc(
+        rpc: &SolanaRpcClient,
+        pool_state: &Pubkey,
+        input_mint: &Pubkey,
+    ) -> Result<Self, anyhow::Error> {
+        let pool =
+            crate::instruction::utils::raydium_clmm::fetch_pool_state(rpc, pool_state).await?;
+        let zero_for_one = if *input_mint == pool.token_mint_0 {
+            true
+        } else if *input_mint == pool.token_mint_1 {
+            false
+        } else {
+            return Err(anyhow::anyhow!(
+                "Mint {} is not traded by Raydium CLMM pool {}",
+                input_mint,
+                pool_state
+            ));
+        };
+        let (token_program_0, token_program_1) =
+            crate::instruction::utils::raydium_clmm::fetch_mint_token_programs(rpc, &pool)
+                .await?;
+        let tick_arrays = crate::instruction::utils::raydium_clmm::swap_tick_arrays(
+            pool_state,
+            pool.tick_current,
+            pool.tick_spacing,
+            zero_for_one,
+        );
+        let side_0 = (pool.token_vault_0, pool.token_mint_0, token_program_0);
+        let side_1 = (pool.token_vault_1, pool.token_mint_1, token_program_1);
+        let (input, output) = if zero_for_one { (side_0, side_1) } else { (side_1, side_0) };
+        Ok(Self {
+            amm_config: pool.amm_config,
+            pool_state: *pool_state,
+            input_vault: input.0,
+            output_vault: output.0,
+            observation_state: pool.observation_key,
+            input_vault_mint: input.1,
+            output_vault_mint: output.1,
+            tick_arrays,
+            tick_spacing: pool.tick_spacing,
+            tick_current: pool.tick_current,
+            input_token_program: input.2,
+            output_token_program: output.2,
+            token_program: spl_token::ID,
+            token_program_2022: spl_token_2022::ID,
+            memo_program: crate::instruction::utils::raydium_clmm::accounts::MEMO_PROGRAM,
+            // The builder derives the payer's token accounts itself
+            payer_sol_account: Pubkey::default(),
+            payer_token_account: Pubkey::default(),
+            other_amount_threshold: 0,
+            sqrt_price_limit_x64: 0,
+            is_base_input: true,
+            auto_handle_wsol: true,
+        })
+    }
+}
+
+impl ProtocolParams for RaydiumClmmV2Params {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn ProtocolParams> {
+        Box::new(self.clone())
+    }
+}
+
+/// Jupiter v6 route parameters
+/// A quoted route sent through the `shared_accounts_route` instruction; a quote has a direction,
+/// so buys need a wSOL -> mint quote and sells a mint -> wSOL quote
+#[derive(Clone)]
+pub struct JupiterParams {
+    /// Mint the route spends
+    pub input_mint: Pubkey,
+    /// Mint the route receives
+    pub output_mint: Pubkey,
+    /// Amount the route was quoted for, the trade amount must match it
+    pub in_amount: u64,
+    /// Output the route was quoted for, the slippage is applied to it on chain
+    pub quoted_out_amount: u64,
+    /// Borsh-encoded `Vec<RoutePlanStep>` of the quote
+    pub route_plan: Vec<u8>,
+    /// Accounts of the route's swaps, passed after the fixed accounts of the instruction
+    pub route_accounts: Vec<AccountMeta>,
+    /// Id of the program authority whose token accounts the route shares
+    pub program_authority_id: u8,
+    /// Token program of the traded mint (spl_token::ID or spl_token_2022::ID)
+    pub mint_token_program: Pubkey,
+    /// Deprecated alias of the trade's `create_wsol_ata` and `close_wsol_ata`, which the
+    /// builder follows while this is true. False turns both off.
+    pub auto_handle_wsol: bool,
+}
+
+impl JupiterParams {
+    /// Quote `amount` of `input_mint` to `output_mint` via the public quote API
+    ///
+    /// Two HTTP round trips and one RPC call for the token program of the non-wSOL mint. The
+    /// quote goes stale quickly, fetch it right before trading.
+    pub async fn from_quote_api(
+        rpc: &SolanaRpcClient,
+        user: &Pubkey,
+        input_mint: Pubkey,
+        output_mint: Pubkey,
+        amount: u64,
+        slippage_bps: u64,
+    ) -> Result<Self, anyhow::Error> {
+        let route = crate::instruction::utils::jupiter::fetch_quoted_route(
+            user,
+            &input_mint,
+            &output_mint,
+            amount,
+            slippage_bps,
+        )
+        .await?;
+        let mint = if input_mint == crate::constants::WSOL_TOKEN_ACCOUNT {
+            output_mint
+        } else {
+            input_mint
+        };
+        let mint_token_program = rpc.get_account(&mint).await?.owner;
+        Ok(Self {
+            input_mint,
+            output_mint,
+            in_amount: route.args.in_amount,
+            quoted_out_amount: route.args.quoted_out_amount,
+            route_plan: route.args.route_plan,
+            route_accounts: route.route_accounts,
+            program_authority_id: route.args.id,
+            mint_token_program,
+            auto_handle_wsol: true,
+        })
+    }
+}
+
+impl ProtocolParams for JupiterParams {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn ProtocolParams> {
+        Box::new(self.clone())
+    }
+}
+
+/// Meteora DLMM protocol specific parameters
+/// Configuration parameters of one lb pair, one of its tokens must be wSOL
+#[derive(Clone)]
+pub struct MeteoraDlmmParams {
+    /// Lb pair address
+    pub lb_pair: Pubkey,
+    /// Token X mint address
+    pub token_x_mint: Pubkey,
+    /// Token Y mint address
+    pub token_y_mint: Pubkey,
+    /// Token X reserve account of the pair
+    pub reserve_x: Pubkey,
+    /// Token Y reserve account of the pair
+    pub reserve_y: Pubkey,
+    /// Token X amount held by the pair
+    pub reserve_x_amount: u64,
+    /// Token Y amount held by the pair
+    pub reserve_y_amount: u64,
+    /// Token X program ID (spl_token::ID or spl_token_2022::ID)
+    pub token_x_program: Pubkey,
+    /// Token Y program ID (spl_token::ID or spl_token_2022::ID)
+    pub token_y_program: Pubkey,
+    /// Oracle account of the pair
+    pub oracle: Pubkey,
+    /// Bin array bitmap extension, needed when the active bin is far from bin 0
+    pub bin_array_bitmap_extension: Option<Pubkey>,
+    /// Initialized bin arrays around the active bin, in any order; the builder passes the ones
+    /// in the swap direction
+    pub bin_arrays: Vec<Pubkey>,
+    /// `active_id`, `bin_step`, `base_factor` and `base_fee_power_factor` of the pair
+    pub active_id: i32,
+    pub bin_step: u16,
+    pub base_factor: u16,
+    pub base_fee_power_factor: u8,
+    /// Deprecated alias of the trade's `create_wsol_ata` and `close_wsol_ata`, which the
+    /// builder follows while this is true. False turns both off.
+    pub auto_handle_wsol: bool,
+}
+
+impl MeteoraDlmmParams {
+    /// Build params from the lb pair, then its reserves and bin arrays (two round trips)
+    ///
+    /// Fails with an `AccountFetchError` naming the accounts that could not be loaded.
+    pub async fn from_pool_address_by_rpc(
+        rpc: &SolanaRpcClient,
+        lb_pair: &Pubkey,
+    ) -> Result<Self, anyhow::Error> {
+        let pair = crate::instruction::utils::meteora_dlmm::fetch_lb_pair(rpc, lb_pair).await?;
+        let (reserve_x_amount, reserve_y_amount, bin_array_bitmap_extension, bin_arrays) =
+            crate::instruction::utils::meteora_dlmm::fetch_swap_accounts(rpc, lb_pair, &pair)
+                .await?;
+        Ok(Self {
+            lb_pair: *lb_pair,
+            token_x_mint: pair.token_x_mint,
+            token_y_mint: pair.token_y_mint,
+            reserve_x: pair.reserve_x,
+            reserve_y: pair.reserve_y,
+            reserve_x_amount,
+            reserve_y_amount,
+            token_x_program: pair.token_x_program,
+            token_y_program: pair.token_y_program,
+            oracle: pair.oracle,
+            bin_array_bitmap_extension,
+            bin_arrays,
+            active_id: pair.active_id,
+            bin_step: pair.bin_step,
+            base_factor: pair.base_factor,
+            base_fee_power_factor: pair.base_fee_power_factor,
+            auto_handle_wsol: true,
+        })
+    }
+}
+
+impl ProtocolParams for MeteoraDlmmParams {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn ProtocolParams> {
+        Box::new(self.clone())
+    }
+}
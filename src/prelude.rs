@@ -0,0 +1,31 @@
+//! Commonly used types
+//!
+//! `use sol_trade_sdk::prelude::*;` brings in the client, its configuration, every protocol
+//! params struct and the Solana types needed to drive a trade.
+
+pub use crate::common::{
+    commission::{Commission, CommissionOn},
+    dex_defaults::DexDefaults,
+    signer::{KeypairSigner, TradeSigner},
+    AnyResult, PriorityFee, SolanaRpcClient, TipAmount, TipStrategy, TradeConfig, TradeTypeFee,
+};
+pub use crate::params::*;
+pub use crate::swqos::{
+    confirmation::{ConfirmationMode, TradeStages, WaitLevel},
+    SwqosClient, SwqosClientTrait, SwqosConfig, SwqosRegion, SwqosType, TradeType,
+};
+pub use crate::trading::factory::DexType;
+pub use crate::{
+    split_across_payers, AnalysisCallback, BlockhashRetry, BondingCurveCompleted, BuyAmountSpec,
+    BuyOptions, CreatedAccountCheck, CreatedToken, CuSizing, CurveCompleteAction, SellAmount,
+    SellOptions, SimulationResult, SolanaTrade, SplitAcrossPayers, SplitBuyReport, SuccessPolicy,
+    SwapHooks, TokenMetadata, TradeResult, WsolAccountChoice,
+};
+
+pub use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+};
@@ -0,0 +1,107 @@
+//! Startup checks of swqos credentials
+//!
+//! Each provider is sent a request with its credentials in the usual place and a body it can not
+//! accept. A provider that accepted the credentials answers with a validation error, bad
+//! credentials get a 401/403 or an auth error in the response body.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+use reqwest::RequestBuilder;
+use serde::Serialize;
+
+use super::{SwqosClient, SwqosType};
+
+/// Time allowed for one probe request
+pub const AUTH_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Body fragments providers use for rejected credentials, matched lowercase
+const AUTH_ERROR_MARKERS: [&str; 6] =
+    ["unauthorized", "forbidden", "invalid api key", "invalid api-key", "invalid auth", "api key"];
+
+lazy_static::lazy_static! {
+    static ref AUTH_STATUS: Mutex<HashMap<SwqosType, Result<(), SwqosAuthError>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Credentials a provider rejected, or a provider the probe could not reach
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, thiserror::Error)]
+#[error("{provider:?} auth check failed at {endpoint} (status {status:?}): {hint}")]
+pub struct SwqosAuthError {
+    pub provider: SwqosType,
+    /// Endpoint probed, with credentials in the url masked
+    pub endpoint: String,
+    /// HTTP status, `None` when no response arrived
+    pub status: Option<u16>,
+    pub hint: String,
+}
+
+/// Send `request` and classify the answer; `endpoint` is only used in the error
+pub(crate) async fn probe(
+    provider: SwqosType,
+    endpoint: &str,
+    request: RequestBuilder,
+) -> Result<(), SwqosAuthError> {
+    let error = |status: Option<u16>, hint: String| SwqosAuthError {
+        provider: provider.clone(),
+        endpoint: mask_credentials(endpoint),
+        status,
+        hint,
+    };
+    let result = match request.timeout(AUTH_PROBE_TIMEOUT).send().await {
+        Err(e) => Err(error(None, format!("endpoint unreachable: {}", e))),
+        Ok(response) => {
+            let status = response.status().as_u16();
+            let body = response.text().await.unwrap_or_default().to_lowercase();
+            if status == 401 || status == 403 {
+                Err(error(Some(status), format!("the {:?} auth token was rejected", provider)))
+            } else if AUTH_ERROR_MARKERS.iter().any(|marker| body.contains(marker)) {
+                Err(error(
+                    Some(status),
+                    format!("the {:?} auth token was rejected: {}", provider, body),
+                ))
+            } else {
+                Ok(())
+            }
+        }
+    };
+    AUTH_STATUS.lock().insert(provider, result.clone());
+    result
+}
+
+/// Probe every client concurrently, returning the failures
+pub async fn probe_clients(clients: &[Arc<SwqosClient>]) -> Vec<SwqosAuthError> {
+    futures::future::join_all(clients.iter().map(|client| client.probe_auth()))
+        .await
+        .into_iter()
+        .filter_map(Result::err)
+        .collect()
+}
+
+/// Outcome of the last auth probe of every provider
+pub fn auth_status() -> HashMap<SwqosType, Result<(), SwqosAuthError>> {
+    AUTH_STATUS.lock().clone()
+}
+
+/// Whether the last auth probe of `provider` failed
+pub fn auth_failed(provider: &SwqosType) -> bool {
+    AUTH_STATUS.lock().get(provider).is_some_and(|status| status.is_err())
+}
+
+/// JSON-RPC `sendTransaction` with a transaction that does not decode
+pub(crate) fn malformed_send_transaction() -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "sendTransaction",
+        "params": ["auth-probe", { "encoding": "base64" }]
+    })
+    .to_string()
+}
+
+fn mask_credentials(endpoint: &str) -> String {
+    match endpoint.split_once('?') {
+        Some((base, _)) => format!("{}?<credentials>", base),
+        None => endpoint.to_string(),
+    }
+}
@@ -1,4 +1,4 @@
-use crate::swqos::common::{poll_transaction_confirmation, serialize_transaction_and_encode};
+use crate::swqos::common::{poll_transaction_level, serialize_transaction_and_encode};
 use rand::seq::IndexedRandom;
 use reqwest::{Client, header::{HeaderMap, HeaderValue, CONTENT_TYPE}};
 use serde_json::json;
@@ -8,8 +8,9 @@ use std::time::Duration;
 use solana_transaction_status::UiTransactionEncoding;
 
 use anyhow::Result;
-use solana_sdk::transaction::VersionedTransaction;
-use crate::swqos::{SwqosType, TradeType};
+use solana_sdk::{signature::Signature, transaction::VersionedTransaction};
+use crate::swqos::{confirmation::WaitLevel, timing::SubmitTimer, SwqosType, TradeType};
+use crate::swqos::auth::{self, SwqosAuthError};
 use crate::swqos::SwqosClientTrait;
 
 use crate::{common::SolanaRpcClient, constants::swqos::BLOCKRAZOR_TIP_ACCOUNTS};
@@ -29,22 +30,37 @@ pub struct BlockRazorClient {
 
 #[async_trait::async_trait]
 impl SwqosClientTrait for BlockRazorClient {
-    async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<()> {
+    async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<Signature> {
         self.send_transaction(trade_type, transaction).await
     }
 
+    async fn confirm_transaction(&self, trade_type: TradeType, signature: Signature) -> Result<()> {
+        self.confirm_transaction(trade_type, signature, WaitLevel::Confirmed).await
+    }
+
+    async fn confirm_transaction_at_level(&self, trade_type: TradeType, signature: Signature, level: WaitLevel) -> Result<()> {
+        self.confirm_transaction(trade_type, signature, level).await
+    }
+
     async fn send_transactions(&self, trade_type: TradeType, transactions: &Vec<VersionedTransaction>) -> Result<()> {
         self.send_transactions(trade_type, transactions).await
     }
 
-    fn get_tip_account(&self) -> Result<String> {
-        let tip_account = *BLOCKRAZOR_TIP_ACCOUNTS.choose(&mut rand::rng()).or_else(|| BLOCKRAZOR_TIP_ACCOUNTS.first()).unwrap();
-        Ok(tip_account.to_string())
+    fn get_tip_account(&self) -> Option<String> {
+        BLOCKRAZOR_TIP_ACCOUNTS.choose(&mut rand::rng()).map(|account| account.to_string())
     }
 
     fn get_swqos_type(&self) -> SwqosType {
         SwqosType::BlockRazor
     }
+
+    async fn probe_auth(&self) -> Result<(), SwqosAuthError> {
+        self.probe_auth().await
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        self.health_check().await
+    }
 }
 
 impl BlockRazorClient {
@@ -143,18 +159,29 @@ impl BlockRazorClient {
             .send()
             .await?;
         
-        if response.status().is_success() {
-            // ping successful, connection remains active
-            // Can optionally log, but to reduce noise, not printing here
-        } else {
-            eprintln!("BlockRazor ping request failed with status: {}", response.status());
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("ping returned status {}", response.status()));
         }
-        
         Ok(())
     }
 
-    pub async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<()> {
+    /// Request the ping endpoint the keep-alive task uses, see `swqos::health`
+    pub async fn health_check(&self) -> Result<()> {
+        Self::send_ping_request(&self.http_client, &self.endpoint, &self.auth_token).await
+    }
+
+    /// Send a request the provider rejects after checking the credentials, see `swqos::auth`
+    pub async fn probe_auth(&self) -> Result<(), SwqosAuthError> {
+        let request = self.http_client.post(&self.endpoint)
+            .body(json!({ "transaction": "auth-probe", "mode": "fast" }).to_string())
+            .header("Content-Type", "application/json")
+            .header("apikey", &self.auth_token);
+        auth::probe(SwqosType::BlockRazor, &self.endpoint, request).await
+    }
+
+    pub async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<Signature> {
         let start_time = Instant::now();
+        let mut timer = SubmitTimer::start(SwqosType::BlockRazor);
         let (content, signature) = serialize_transaction_and_encode(transaction, UiTransactionEncoding::Base64).await?;
         println!(" Transaction encoded to base64: {:?}", start_time.elapsed());
 
@@ -165,28 +192,32 @@ impl BlockRazorClient {
         }))?;
 
         // BlockRazor使用apikey header
-        let response_text = self.http_client.post(&self.endpoint)
+        let request = self.http_client.post(&self.endpoint)
             .body(request_body)
             .header("Content-Type", "application/json")
-            .header("apikey", &self.auth_token)
-            .send()
-            .await?
-            .text()
-            .await?;
+            .header("apikey", &self.auth_token);
+        let response_text = timer.send(request).await?;
 
         // Parse JSON response
         if let Ok(response_json) = serde_json::from_str::<serde_json::Value>(&response_text) {
             if response_json.get("result").is_some() || response_json.get("signature").is_some() {
                 println!(" blockrazor {} submitted: {:?}", trade_type, start_time.elapsed());
-            } else if let Some(_error) = response_json.get("error") {
-                eprintln!(" blockrazor {} submission failed: {:?}", trade_type, _error);
+            } else if let Some(error) = response_json.get("error") {
+                eprintln!(" blockrazor {} submission failed: {:?}", trade_type, error);
+                return Err(anyhow::anyhow!("BlockRazor submission failed: {} | Signature: {}", error, signature));
             }
         } else {
             eprintln!(" blockrazor {} submission failed: {:?}", trade_type, response_text);
+            return Err(anyhow::anyhow!("BlockRazor submission failed: {} | Signature: {}", response_text, signature));
         }
 
+        timer.succeed();
+        Ok(signature)
+    }
+
+    pub async fn confirm_transaction(&self, trade_type: TradeType, signature: Signature, level: WaitLevel) -> Result<()> {
         let start_time: Instant = Instant::now();
-        match poll_transaction_confirmation(&self.rpc_client, signature).await {
+        match poll_transaction_level(&self.rpc_client, signature, level).await {
             Ok(_) => (),
             Err(e) => {
                 println!(" signature: {:?}", signature);
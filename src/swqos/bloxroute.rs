@@ -1,4 +1,4 @@
-use crate::swqos::common::{poll_transaction_confirmation, serialize_transaction_and_encode, FormatBase64VersionedTransaction};
+use crate::swqos::common::{poll_transaction_level, serialize_transaction_and_encode, FormatBase64VersionedTransaction};
 use rand::seq::IndexedRandom;
 use reqwest::Client;
 use std::{sync::Arc, time::Instant};
@@ -7,8 +7,9 @@ use std::time::Duration;
 use solana_transaction_status::UiTransactionEncoding;
 
 use anyhow::Result;
-use solana_sdk::transaction::VersionedTransaction;
-use crate::swqos::{SwqosType, TradeType};
+use solana_sdk::{signature::Signature, transaction::VersionedTransaction};
+use crate::swqos::{confirmation::WaitLevel, timing::SubmitTimer, SwqosType, TradeType};
+use crate::swqos::auth::{self, SwqosAuthError};
 use crate::swqos::SwqosClientTrait;
 
 use crate::{common::SolanaRpcClient, constants::swqos::BLOX_TIP_ACCOUNTS};
@@ -24,22 +25,33 @@ pub struct BloxrouteClient {
 
 #[async_trait::async_trait]
 impl SwqosClientTrait for BloxrouteClient {
-    async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<()> {
+    async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<Signature> {
         self.send_transaction(trade_type, transaction).await
     }
 
+    async fn confirm_transaction(&self, trade_type: TradeType, signature: Signature) -> Result<()> {
+        self.confirm_transaction(trade_type, signature, WaitLevel::Confirmed).await
+    }
+
+    async fn confirm_transaction_at_level(&self, trade_type: TradeType, signature: Signature, level: WaitLevel) -> Result<()> {
+        self.confirm_transaction(trade_type, signature, level).await
+    }
+
     async fn send_transactions(&self, trade_type: TradeType, transactions: &Vec<VersionedTransaction>) -> Result<()> {
         self.send_transactions(trade_type, transactions).await
     }
 
-    fn get_tip_account(&self) -> Result<String> {
-        let tip_account = *BLOX_TIP_ACCOUNTS.choose(&mut rand::rng()).or_else(|| BLOX_TIP_ACCOUNTS.first()).unwrap();
-        Ok(tip_account.to_string())
+    fn get_tip_account(&self) -> Option<String> {
+        BLOX_TIP_ACCOUNTS.choose(&mut rand::rng()).map(|account| account.to_string())
     }
 
     fn get_swqos_type(&self) -> SwqosType {
         SwqosType::Bloxroute
     }
+
+    async fn probe_auth(&self) -> Result<(), SwqosAuthError> {
+        self.probe_auth().await
+    }
 }
 
 impl BloxrouteClient {
@@ -57,8 +69,20 @@ impl BloxrouteClient {
         Self { rpc_client: Arc::new(rpc_client), endpoint, auth_token, http_client }
     }
 
-    pub async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<()> {
+    /// Send a request the provider rejects after checking the credentials, see `swqos::auth`
+    pub async fn probe_auth(&self) -> Result<(), SwqosAuthError> {
+        let endpoint = format!("{}/api/v2/submit", self.endpoint);
+        let request_body = serde_json::json!({ "transaction": { "content": "auth-probe" } });
+        let request = self.http_client.post(&endpoint)
+            .body(request_body.to_string())
+            .header("Content-Type", "application/json")
+            .header("Authorization", self.auth_token.clone());
+        auth::probe(SwqosType::Bloxroute, &endpoint, request).await
+    }
+
+    pub async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<Signature> {
         let start_time = Instant::now();
+        let mut timer = SubmitTimer::start(SwqosType::Bloxroute);
         let (content, signature) = serialize_transaction_and_encode(transaction, UiTransactionEncoding::Base64).await?;
         println!(" Transaction encoded to base64: {:?}", start_time.elapsed());
 
@@ -71,28 +95,32 @@ impl BloxrouteClient {
         });
 
         let endpoint = format!("{}/api/v2/submit", self.endpoint);
-        let response_text = self.http_client.post(&endpoint)
+        let request = self.http_client.post(&endpoint)
             .body(body.to_string())
             .header("Content-Type", "application/json")
-            .header("Authorization", self.auth_token.clone())
-            .send()
-            .await?
-            .text()
-            .await?;
+            .header("Authorization", self.auth_token.clone());
+        let response_text = timer.send(request).await?;
 
         // 5. Use `serde_json::from_str()` to parse JSON, reducing extra wait from `.json().await?`
         if let Ok(response_json) = serde_json::from_str::<serde_json::Value>(&response_text) {
             if response_json.get("result").is_some() {
                 println!(" bloxroute {} submitted: {:?}", trade_type, start_time.elapsed());
-            } else if let Some(_error) = response_json.get("error") {
-                eprintln!(" bloxroute {} submission failed: {:?}", trade_type, _error);
+            } else if let Some(error) = response_json.get("error") {
+                eprintln!(" bloxroute {} submission failed: {:?}", trade_type, error);
+                return Err(anyhow::anyhow!("Bloxroute submission failed: {} | Signature: {}", error, signature));
             }
         } else {
             eprintln!(" bloxroute {} submission failed: {:?}", trade_type, response_text);
+            return Err(anyhow::anyhow!("Bloxroute submission failed: {} | Signature: {}", response_text, signature));
         }
 
+        timer.succeed();
+        Ok(signature)
+    }
+
+    pub async fn confirm_transaction(&self, trade_type: TradeType, signature: Signature, level: WaitLevel) -> Result<()> {
         let start_time: Instant = Instant::now();
-        match poll_transaction_confirmation(&self.rpc_client, signature).await {
+        match poll_transaction_level(&self.rpc_client, signature, level).await {
             Ok(_) => (),
             Err(e) => {
                 println!(" signature: {:?}", signature);
@@ -3,11 +3,12 @@ use serde_json::json;
 use solana_client::rpc_client::SerializableTransaction;
 use solana_sdk::signature::Signature;
 use solana_sdk::transaction::Transaction;
-use solana_transaction_status::{TransactionConfirmationStatus, UiTransactionEncoding};
+use solana_transaction_status::UiTransactionEncoding;
 use std::str::FromStr;
-use std::time::{Duration, Instant};
-use tokio::time::sleep;
+use std::time::Instant;
 use crate::common::types::SolanaRpcClient;
+use crate::swqos::TradeType;
+use crate::swqos::confirmation::{until_deadline, ConfirmationService, WaitLevel};
 use anyhow::Result;
 use base64::Engine;
 use base64::engine::general_purpose::{self, STANDARD};
@@ -25,35 +26,28 @@ impl FormatBase64VersionedTransaction for VersionedTransaction {
     }
 }
 
+/// Wait for confirmation through the shared `ConfirmationService` of this RPC endpoint
 pub async fn poll_transaction_confirmation(rpc: &SolanaRpcClient, txt_sig: Signature) -> Result<Signature> {
-    let timeout: Duration = Duration::from_secs(5);
-    let interval: Duration = Duration::from_millis(1000);
-    let start: Instant = Instant::now();
-
-    loop {
-        if start.elapsed() >= timeout {
-            return Err(anyhow::anyhow!("Transaction {}'s confirmation timed out", txt_sig));
-        }
-
-        let status = rpc.get_signature_statuses(&[txt_sig]).await?;
-
-        match status.value[0].clone() {
-            Some(status) => {
-                if status.err.is_none()
-                    && (status.confirmation_status == Some(TransactionConfirmationStatus::Confirmed)
-                        || status.confirmation_status == Some(TransactionConfirmationStatus::Finalized))
-                {
-                    return Ok(txt_sig);
-                }
-                if status.err.is_some() {
-                    return Err(anyhow::anyhow!(status.err.unwrap()));
-                }
-            }
-            None => {
-                sleep(interval).await;
-            }
-        }
-    }
+    poll_transaction_level(rpc, txt_sig, WaitLevel::Confirmed).await
+}
+
+/// Wait until the transaction reached `level` through the shared `ConfirmationService` of this
+/// RPC endpoint, with the default timeout of the level
+pub async fn poll_transaction_level(rpc: &SolanaRpcClient, txt_sig: Signature, level: WaitLevel) -> Result<Signature> {
+    ConfirmationService::for_rpc(rpc).wait_for_level(txt_sig, level, level.default_timeout()).await?;
+    Ok(txt_sig)
+}
+
+/// `poll_transaction_confirmation` failing with `TradeError::deadline_exceeded` once
+/// `deadline` passed
+pub async fn poll_transaction_confirmation_until(rpc: &SolanaRpcClient, txt_sig: Signature, deadline: Option<Instant>) -> Result<Signature> {
+    poll_transaction_level_until(rpc, txt_sig, WaitLevel::Confirmed, deadline).await
+}
+
+/// `poll_transaction_level` failing with `TradeError::deadline_exceeded` once `deadline` passed,
+/// even when the default timeout of the level runs longer
+pub async fn poll_transaction_level_until(rpc: &SolanaRpcClient, txt_sig: Signature, level: WaitLevel, deadline: Option<Instant>) -> Result<Signature> {
+    until_deadline(deadline, txt_sig, poll_transaction_level(rpc, txt_sig, level)).await
 }
 
 pub async fn send_nb_transaction(client: Client, endpoint: &str, auth_token: &str, transaction: &Transaction) -> Result<Signature, anyhow::Error> {
@@ -97,6 +91,18 @@ pub async fn send_nb_transaction(client: Client, endpoint: &str, auth_token: &st
     Ok(signature)
 }
 
+/// JSON-RPC request for a swqos endpoint
+///
+/// The trade type goes in the request id, relays that prioritize by trade type read it from there.
+pub fn rpc_request(trade_type: TradeType, method: &str, params: serde_json::Value) -> serde_json::Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": trade_type.to_string(),
+        "method": method,
+        "params": params,
+    })
+}
+
 pub async fn serialize_and_encode(
     transaction: &Vec<u8>,
     encoding: UiTransactionEncoding,
@@ -0,0 +1,781 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_rpc_client_api::{config::RpcSignatureSubscribeConfig, response::RpcSignatureResult};
+use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature};
+use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
+use tokio::sync::watch;
+
+use crate::common::SolanaRpcClient;
+use crate::trading::core::error::TradeError;
+
+/// Maximum number of signatures accepted by one `getSignatureStatuses` call
+pub const MAX_SIGNATURES_PER_STATUS_REQUEST: usize = 256;
+/// Interval between two status polls
+pub const DEFAULT_STATUS_POLL_INTERVAL: Duration = Duration::from_millis(300);
+/// Default time a waiter waits for confirmation
+pub const DEFAULT_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(5);
+/// Default time a waiter waits for finalization, counted from when it starts waiting
+pub const DEFAULT_FINALIZATION_TIMEOUT: Duration = Duration::from_secs(45);
+/// Time allowed to open the shared pubsub connection before falling back to polling
+pub const WEBSOCKET_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+lazy_static::lazy_static! {
+    static ref SERVICES: Mutex<HashMap<String, Arc<ConfirmationService>>> =
+        Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfirmationStatus {
+    Pending,
+    /// Landed in a block not voted on yet, it can still be forked out
+    Processed,
+    Confirmed,
+    Finalized,
+    Failed(String),
+}
+
+/// Status of a watched signature and the slot it was last seen landed in
+#[derive(Debug, Clone, PartialEq)]
+struct Tracked {
+    status: ConfirmationStatus,
+    slot: Option<u64>,
+}
+
+/// How `ConfirmationService` learns that a signature was confirmed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ConfirmationMode {
+    /// Batched `getSignatureStatuses` polls
+    #[default]
+    Polling,
+    /// One `signatureSubscribe` per signature over a pubsub connection shared by the endpoint
+    ///
+    /// A signature whose subscription fails, or whose socket drops, is polled instead.
+    /// Finalization is always polled.
+    WebSocket,
+}
+
+/// How far a trade waits before returning
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum WaitLevel {
+    /// Return once a backend accepted the transaction
+    Submitted,
+    /// Return once the transaction reached processed commitment. The trade is analyzed in the
+    /// background once it is confirmed, as with `Submitted`.
+    Processed,
+    /// Return once the transaction reached confirmed commitment
+    #[default]
+    Confirmed,
+    /// Return once the transaction reached finalized commitment
+    Finalized,
+}
+
+impl WaitLevel {
+    /// Commitment matching the level, `None` for `Submitted`
+    pub fn commitment(&self) -> Option<CommitmentConfig> {
+        match self {
+            WaitLevel::Submitted => None,
+            WaitLevel::Processed => Some(CommitmentConfig::processed()),
+            WaitLevel::Confirmed => Some(CommitmentConfig::confirmed()),
+            WaitLevel::Finalized => Some(CommitmentConfig::finalized()),
+        }
+    }
+
+    /// Time a wait for this level is given unless the caller says otherwise
+    pub fn default_timeout(&self) -> Duration {
+        match self {
+            WaitLevel::Finalized => DEFAULT_FINALIZATION_TIMEOUT,
+            _ => DEFAULT_CONFIRMATION_TIMEOUT,
+        }
+    }
+}
+
+/// `true` maps to `Confirmed` and `false` to `Submitted`, matching `wait_transaction_confirmed`
+impl From<bool> for WaitLevel {
+    fn from(wait_transaction_confirmed: bool) -> Self {
+        if wait_transaction_confirmed {
+            WaitLevel::Confirmed
+        } else {
+            WaitLevel::Submitted
+        }
+    }
+}
+
+/// Stage timestamps of a sent trade, in unix milliseconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TradeStages {
+    /// Highest stage known to be reached
+    pub achieved: Option<WaitLevel>,
+    pub submitted_at_ms: Option<u64>,
+    #[serde(default)]
+    pub processed_at_ms: Option<u64>,
+    pub confirmed_at_ms: Option<u64>,
+    pub finalized_at_ms: Option<u64>,
+}
+
+impl TradeStages {
+    /// Record that `level` was reached now
+    pub fn mark(&mut self, level: WaitLevel) {
+        let now = Some(chrono::Utc::now().timestamp_millis() as u64);
+        match level {
+            WaitLevel::Submitted => self.submitted_at_ms = now,
+            WaitLevel::Processed => self.processed_at_ms = now,
+            WaitLevel::Confirmed => self.confirmed_at_ms = now,
+            WaitLevel::Finalized => self.finalized_at_ms = now,
+        }
+        if self.achieved.map_or(true, |achieved| achieved < level) {
+            self.achieved = Some(level);
+        }
+    }
+}
+
+/// Shared signature status poller
+///
+/// Concurrent waits on the same signature share one entry, and all pending signatures are
+/// polled together in `getSignatureStatuses` batches of up to 256. A single poll loop runs while
+/// anything is pending and stops when the last waiter is done. In `ConfirmationMode::WebSocket`
+/// the poll loop skips signatures with a live subscription.
+pub struct ConfirmationService {
+    rpc: Arc<SolanaRpcClient>,
+    poll_interval: Duration,
+    pending: Mutex<HashMap<Signature, watch::Sender<Tracked>>>,
+    polling: Mutex<bool>,
+    mode: Mutex<ConfirmationMode>,
+    ws_url: String,
+    pubsub: tokio::sync::Mutex<Option<Arc<PubsubClient>>>,
+    subscribed: Mutex<HashSet<Signature>>,
+}
+
+/// Wait handle for one signature
+pub struct ConfirmationHandle {
+    pub signature: Signature,
+    receiver: watch::Receiver<Tracked>,
+}
+
+impl ConfirmationService {
+    pub fn new(rpc: Arc<SolanaRpcClient>) -> Arc<Self> {
+        Self::with_poll_interval(rpc, DEFAULT_STATUS_POLL_INTERVAL)
+    }
+
+    fn with_poll_interval(rpc: Arc<SolanaRpcClient>, poll_interval: Duration) -> Arc<Self> {
+        let ws_url = websocket_url(&rpc.url());
+        Arc::new(Self {
+            rpc,
+            poll_interval,
+            pending: Mutex::new(HashMap::new()),
+            polling: Mutex::new(false),
+            mode: Mutex::new(ConfirmationMode::Polling),
+            ws_url,
+            pubsub: tokio::sync::Mutex::new(None),
+            subscribed: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Switch how signatures registered from now on are confirmed
+    pub fn set_mode(&self, mode: ConfirmationMode) {
+        *self.mode.lock() = mode;
+    }
+
+    pub fn mode(&self) -> ConfirmationMode {
+        *self.mode.lock()
+    }
+
+    /// Service shared by every client using the same RPC endpoint
+    pub fn for_rpc(rpc: &SolanaRpcClient) -> Arc<Self> {
+        let url = rpc.url();
+        SERVICES
+            .lock()
+            .entry(url.clone())
+            .or_insert_with(|| {
+                Self::new(Arc::new(SolanaRpcClient::new_with_commitment(url, rpc.commitment())))
+            })
+            .clone()
+    }
+
+    /// Register interest in `signature`, joining an existing wait if there is one
+    pub fn watch(self: &Arc<Self>, signature: Signature) -> ConfirmationHandle {
+        let (receiver, added) = {
+            let mut pending = self.pending.lock();
+            match pending.get(&signature) {
+                Some(sender) => (sender.subscribe(), false),
+                None => {
+                    let (sender, receiver) =
+                        watch::channel(Tracked { status: ConfirmationStatus::Pending, slot: None });
+                    pending.insert(signature, sender);
+                    (receiver, true)
+                }
+            }
+        };
+        if added && self.mode() == ConfirmationMode::WebSocket {
+            self.subscribed.lock().insert(signature);
+            let service = self.clone();
+            tokio::spawn(async move {
+                service.subscribe(signature).await;
+            });
+        }
+        self.ensure_polling();
+        ConfirmationHandle { signature, receiver }
+    }
+
+    /// Wait for `signature` with the given timeout
+    pub async fn wait(self: &Arc<Self>, signature: Signature, timeout: Duration) -> Result<()> {
+        self.watch(signature).wait(timeout).await
+    }
+
+    /// Wait until `signature` reaches `level`, `Submitted` returns immediately
+    ///
+    /// In `ConfirmationMode::WebSocket` the subscription reports confirmation only, so a
+    /// `Processed` wait returns once the transaction is confirmed.
+    pub async fn wait_for_level(
+        self: &Arc<Self>,
+        signature: Signature,
+        level: WaitLevel,
+        timeout: Duration,
+    ) -> Result<()> {
+        match level {
+            WaitLevel::Submitted => Ok(()),
+            WaitLevel::Processed => self.watch(signature).wait_processed(timeout).await,
+            WaitLevel::Confirmed => self.wait(signature, timeout).await,
+            WaitLevel::Finalized => self.watch(signature).wait_finalized(timeout).await,
+        }
+    }
+
+    /// Wait until `signature` is confirmed and return the slot it landed in
+    ///
+    /// Together with the slot read when sending, this gives the slots a transaction took to
+    /// land.
+    pub async fn wait_landed_slot(
+        self: &Arc<Self>,
+        signature: Signature,
+        timeout: Duration,
+    ) -> Result<u64> {
+        self.watch(signature).wait_landed_slot(timeout).await
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.lock().len()
+    }
+
+    fn ensure_polling(self: &Arc<Self>) {
+        {
+            let mut polling = self.polling.lock();
+            if *polling {
+                return;
+            }
+            *polling = true;
+        }
+        let service = self.clone();
+        tokio::spawn(async move {
+            service.poll_loop().await;
+        });
+    }
+
+    async fn poll_loop(self: Arc<Self>) {
+        loop {
+            let signatures: Vec<Signature> = {
+                let mut pending = self.pending.lock();
+                // Drop entries nobody waits for any more (all waiters done or timed out)
+                pending.retain(|_, sender| sender.receiver_count() > 0);
+                if pending.is_empty() {
+                    // Checked under the pending lock so a concurrent `watch` either sees the
+                    // loop still running or starts a new one
+                    *self.polling.lock() = false;
+                    return;
+                }
+                let subscribed = self.subscribed.lock();
+                pending
+                    .keys()
+                    .filter(|signature| !subscribed.contains(signature))
+                    .copied()
+                    .collect()
+            };
+
+            for chunk in signatures.chunks(MAX_SIGNATURES_PER_STATUS_REQUEST) {
+                self.poll_statuses(chunk).await;
+            }
+
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    async fn poll_statuses(&self, signatures: &[Signature]) {
+        match self.rpc.get_signature_statuses(signatures).await {
+            Ok(statuses) => {
+                for (signature, status) in signatures.iter().zip(statuses.value) {
+                    // A dropped (forked out) transaction reports no status, keep waiting
+                    let Some(status) = status else { continue };
+                    if let Some(resolved) = resolved_status(&status) {
+                        self.resolve(signature, resolved, status.slot);
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!(" signature status poll failed: {}", e);
+            }
+        }
+    }
+
+    /// Publish the status of `signature` to its waiters
+    fn resolve(&self, signature: &Signature, status: ConfirmationStatus, slot: u64) {
+        let mut pending = self.pending.lock();
+        let done = matches!(status, ConfirmationStatus::Finalized | ConfirmationStatus::Failed(_));
+        if let Some(sender) = pending.get(signature) {
+            let resolved = Tracked { status, slot: Some(slot) };
+            sender.send_if_modified(|current| {
+                // A lagging poll must not take a confirmed signature back to processed
+                let downgrade = resolved.status == ConfirmationStatus::Processed
+                    && current.status != ConfirmationStatus::Pending;
+                if *current == resolved || downgrade {
+                    return false;
+                }
+                *current = resolved;
+                true
+            });
+        }
+        // Processed and confirmed entries stay while someone waits for a later stage
+        if done {
+            pending.remove(signature);
+        }
+    }
+
+    fn is_waited_for(&self, signature: &Signature) -> bool {
+        self.pending.lock().get(signature).map_or(false, |sender| sender.receiver_count() > 0)
+    }
+
+    /// Confirm `signature` over the shared pubsub connection, handing it back to the poll loop
+    /// when the subscription fails
+    async fn subscribe(self: Arc<Self>, signature: Signature) {
+        match self.pubsub_client().await {
+            Ok(client) => {
+                if let Err(e) = self.wait_notification(&client, signature).await {
+                    eprintln!(" signature subscription failed, polling instead: {}", e);
+                    self.reset_pubsub_client(&client).await;
+                }
+            }
+            Err(e) => eprintln!(" pubsub connection failed, polling instead: {}", e),
+        }
+        self.subscribed.lock().remove(&signature);
+    }
+
+    async fn wait_notification(&self, client: &PubsubClient, signature: Signature) -> Result<()> {
+        let config = RpcSignatureSubscribeConfig {
+            commitment: Some(CommitmentConfig::confirmed()),
+            enable_received_notification: Some(false),
+        };
+        let (mut notifications, unsubscribe) =
+            client.signature_subscribe(&signature, Some(config)).await?;
+        // A transaction confirmed before the subscription started is not notified
+        self.poll_statuses(&[signature]).await;
+
+        let mut ticker = tokio::time::interval(self.poll_interval);
+        let notification = loop {
+            tokio::select! {
+                notification = notifications.next() => break notification,
+                _ = ticker.tick() => {
+                    if !self.is_waited_for(&signature) {
+                        drop(notifications);
+                        unsubscribe().await;
+                        return Ok(());
+                    }
+                }
+            }
+        };
+        let Some(notification) = notification else {
+            return Err(anyhow::anyhow!("pubsub connection to {} closed", self.ws_url));
+        };
+        if let RpcSignatureResult::ProcessedSignature(result) = notification.value {
+            let status = match result.err {
+                Some(err) => ConfirmationStatus::Failed(err.to_string()),
+                None => ConfirmationStatus::Confirmed,
+            };
+            self.resolve(&signature, status, notification.context.slot);
+        }
+        Ok(())
+    }
+
+    async fn pubsub_client(&self) -> Result<Arc<PubsubClient>> {
+        let mut pubsub = self.pubsub.lock().await;
+        if let Some(client) = pubsub.as_ref() {
+            return Ok(client.clone());
+        }
+        let client =
+            tokio::time::timeout(WEBSOCKET_CONNECT_TIMEOUT, PubsubClient::new(&self.ws_url))
+                .await
+                .map_err(|_| anyhow::anyhow!("connecting to {} timed out", self.ws_url))??;
+        let client = Arc::new(client);
+        *pubsub = Some(client.clone());
+        Ok(client)
+    }
+
+    /// Drop the shared connection if it is still `client`, the next subscription reconnects
+    async fn reset_pubsub_client(&self, client: &PubsubClient) {
+        let mut pubsub = self.pubsub.lock().await;
+        if pubsub.as_deref().map_or(false, |current| std::ptr::eq(current, client)) {
+            *pubsub = None;
+        }
+    }
+}
+
+/// Run `wait` until `deadline`, if any, failing with `TradeError::deadline_exceeded` for
+/// `signature` once it passed
+pub async fn until_deadline<T>(
+    deadline: Option<Instant>,
+    signature: Signature,
+    wait: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline.into(), wait)
+            .await
+            .unwrap_or_else(|_| Err(TradeError::deadline_exceeded(vec![signature]).into())),
+        None => wait.await,
+    }
+}
+
+/// Status of a `getSignatureStatuses` entry, `None` when the node reports no commitment
+fn resolved_status(status: &TransactionStatus) -> Option<ConfirmationStatus> {
+    if let Some(err) = &status.err {
+        return Some(ConfirmationStatus::Failed(err.to_string()));
+    }
+    match status.confirmation_status {
+        Some(TransactionConfirmationStatus::Finalized) => Some(ConfirmationStatus::Finalized),
+        Some(TransactionConfirmationStatus::Confirmed) => Some(ConfirmationStatus::Confirmed),
+        Some(TransactionConfirmationStatus::Processed) => Some(ConfirmationStatus::Processed),
+        None => None,
+    }
+}
+
+/// Pubsub URL of an RPC URL: `ws`/`wss` scheme, and the next port when one is given, as the
+/// Solana validator serves pubsub on the RPC port plus one
+pub fn websocket_url(rpc_url: &str) -> String {
+    let (scheme, rest) = match rpc_url.split_once("://") {
+        Some(("https", rest)) => ("wss", rest),
+        Some((_, rest)) => ("ws", rest),
+        None => ("ws", rpc_url),
+    };
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) => match port.parse::<u16>() {
+            Ok(port) => format!("{}:{}", name, port.saturating_add(1)),
+            Err(_) => host.to_string(),
+        },
+        None => host.to_string(),
+    };
+    format!("{}://{}{}", scheme, host, path)
+}
+
+impl ConfirmationHandle {
+    /// Wait until the signature is confirmed, failed on chain or `timeout` elapsed
+    pub async fn wait(self, timeout: Duration) -> Result<()> {
+        self.wait_landed_slot(timeout).await.map(|_| ())
+    }
+
+    /// Like `wait`, returning the slot the transaction landed in
+    pub async fn wait_landed_slot(self, timeout: Duration) -> Result<u64> {
+        self.wait_until(
+            timeout,
+            |status| !matches!(status, ConfirmationStatus::Pending | ConfirmationStatus::Processed),
+            "confirmation",
+        )
+        .await
+    }
+
+    /// Wait until the signature is processed, failed on chain or `timeout` elapsed
+    pub async fn wait_processed(self, timeout: Duration) -> Result<()> {
+        self.wait_until(timeout, |status| *status != ConfirmationStatus::Pending, "processing")
+            .await
+            .map(|_| ())
+    }
+
+    /// Wait until the signature is finalized, failed on chain or `timeout` elapsed
+    ///
+    /// A transaction that was confirmed on a fork that got abandoned never finalizes and ends
+    /// in a timeout.
+    pub async fn wait_finalized(self, timeout: Duration) -> Result<()> {
+        self.wait_until(
+            timeout,
+            |status| {
+                matches!(status, ConfirmationStatus::Finalized | ConfirmationStatus::Failed(_))
+            },
+            "finalization",
+        )
+        .await
+        .map(|_| ())
+    }
+
+    async fn wait_until(
+        mut self,
+        timeout: Duration,
+        mut reached: impl FnMut(&ConfirmationStatus) -> bool,
+        stage: &str,
+    ) -> Result<u64> {
+        let signature = self.signature;
+        let result = tokio::time::timeout(
+            timeout,
+            self.receiver.wait_for(|tracked| reached(&tracked.status)),
+        )
+        .await;
+        match result {
+            Ok(Ok(tracked)) => match (&tracked.status, tracked.slot) {
+                (ConfirmationStatus::Failed(err), _) => {
+                    Err(anyhow::anyhow!("{} | Signature: {}", err, signature))
+                }
+                // Every resolved status carries the slot it was reported in
+                (_, slot) => Ok(slot.unwrap_or_default()),
+            },
+            Ok(Err(_)) => Err(anyhow::anyhow!("Transaction {}'s {} was dropped", signature, stage)),
+            Err(_) => {
+                let stage = stage.to_string();
+                Err(TradeError::ConfirmationTimeout { signatures: vec![signature], stage }.into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::fake_chain::FakeChain;
+    use serde_json::{json, Value};
+    use solana_rpc_client_api::request::RpcRequest;
+
+    const POLL: Duration = Duration::from_millis(10);
+
+    fn service(
+        chain: FakeChain,
+        poll_interval: Duration,
+    ) -> (Arc<ConfirmationService>, Arc<Mutex<Vec<RpcRequest>>>) {
+        let (rpc, requests) = chain.client();
+        (ConfirmationService::with_poll_interval(Arc::new(rpc), poll_interval), requests)
+    }
+
+    /// `getSignatureStatuses` answer with one entry per polled signature
+    fn statuses(entries: Vec<Value>) -> Result<Value, String> {
+        Ok(json!({ "context": { "slot": 1 }, "value": entries }))
+    }
+
+    fn landed(slot: u64, commitment: &str) -> Value {
+        json!({
+            "slot": slot,
+            "confirmations": null,
+            "status": { "Ok": null },
+            "err": null,
+            "confirmationStatus": commitment,
+        })
+    }
+
+    /// Chain reporting `entries` for one signature, one poll each
+    fn chain(entries: Vec<Value>) -> FakeChain {
+        entries.into_iter().fold(FakeChain::default(), |chain, entry| {
+            chain.script(RpcRequest::GetSignatureStatuses, statuses(vec![entry]))
+        })
+    }
+
+    fn timed_out_stage(error: &anyhow::Error) -> Option<String> {
+        match error.downcast_ref::<TradeError>() {
+            Some(TradeError::ConfirmationTimeout { stage, .. }) => Some(stage.clone()),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn each_level_returns_once_its_commitment_is_reported() {
+        let signature = Signature::new_unique();
+        let (service, _) = service(
+            chain(vec![
+                Value::Null,
+                landed(10, "processed"),
+                landed(10, "confirmed"),
+                landed(10, "finalized"),
+            ]),
+            POLL,
+        );
+        let timeout = Duration::from_secs(2);
+
+        let (submitted, processed, confirmed, finalized) = tokio::join!(
+            service.wait_for_level(signature, WaitLevel::Submitted, timeout),
+            service.wait_for_level(signature, WaitLevel::Processed, timeout),
+            service.wait_landed_slot(signature, timeout),
+            service.wait_for_level(signature, WaitLevel::Finalized, timeout),
+        );
+        submitted.unwrap();
+        processed.unwrap();
+        assert_eq!(confirmed.unwrap(), 10);
+        finalized.unwrap();
+        // A finalized signature is not polled any more
+        assert_eq!(service.pending_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn confirmed_transaction_forked_out_never_finalizes() {
+        let signature = Signature::new_unique();
+        let mut entries = vec![landed(10, "processed"), landed(10, "confirmed")];
+        // The fork holding the transaction is abandoned, nodes report no status for it
+        entries.extend(std::iter::repeat(Value::Null).take(50));
+        let (service, _) = service(chain(entries), POLL);
+
+        let (confirmed, finalized) = tokio::join!(
+            service.wait_for_level(signature, WaitLevel::Confirmed, Duration::from_secs(2)),
+            service.wait_for_level(signature, WaitLevel::Finalized, Duration::from_millis(300)),
+        );
+        confirmed.unwrap();
+        let e = finalized.unwrap_err();
+        assert_eq!(timed_out_stage(&e).as_deref(), Some("finalization"), "{}", e);
+    }
+
+    #[tokio::test]
+    async fn pending_signature_times_out_at_its_stage() {
+        let signature = Signature::new_unique();
+        let (service, _) = service(chain(vec![Value::Null; 50]), POLL);
+
+        let e = service.wait(signature, Duration::from_millis(100)).await.unwrap_err();
+        assert_eq!(timed_out_stage(&e).as_deref(), Some("confirmation"), "{}", e);
+        let e = service
+            .wait_for_level(signature, WaitLevel::Processed, Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert_eq!(timed_out_stage(&e).as_deref(), Some("processing"), "{}", e);
+    }
+
+    #[tokio::test]
+    async fn failed_transaction_ends_every_wait_with_its_error() {
+        let signature = Signature::new_unique();
+        let failed = json!({
+            "slot": 10,
+            "confirmations": 0,
+            "status": { "Err": "AccountInUse" },
+            "err": "AccountInUse",
+            "confirmationStatus": "processed",
+        });
+        let (service, _) = service(chain(vec![failed]), POLL);
+        let timeout = Duration::from_secs(2);
+
+        let (confirmed, finalized) = tokio::join!(
+            service.wait(signature, timeout),
+            service.wait_for_level(signature, WaitLevel::Finalized, timeout),
+        );
+        for result in [confirmed, finalized] {
+            let e = result.unwrap_err();
+            assert!(timed_out_stage(&e).is_none(), "{}", e);
+            assert!(e.to_string().contains(&signature.to_string()), "{}", e);
+        }
+        assert_eq!(service.pending_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn pending_signatures_are_polled_together_in_batches() {
+        let signatures: Vec<Signature> = (0..300).map(|_| Signature::new_unique()).collect();
+        let (service, requests) = service(
+            FakeChain::default()
+                .script(RpcRequest::GetSignatureStatuses, statuses(vec![Value::Null; 256]))
+                .script(RpcRequest::GetSignatureStatuses, statuses(vec![Value::Null; 44])),
+            Duration::from_secs(10),
+        );
+
+        let mut handles: Vec<ConfirmationHandle> =
+            signatures.iter().map(|signature| service.watch(*signature)).collect();
+        // A second wait on a signature joins the first one
+        handles.push(service.watch(signatures[0]));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(service.pending_count(), 300);
+        assert_eq!(*requests.lock(), vec![RpcRequest::GetSignatureStatuses; 2]);
+    }
+
+    #[tokio::test]
+    async fn polling_stops_once_nobody_waits() {
+        let (service, requests) = service(FakeChain::default(), POLL);
+
+        drop(service.watch(Signature::new_unique()));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert_eq!(service.pending_count(), 0);
+        assert!(!*service.polling.lock());
+        let polls = requests.lock().len();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(requests.lock().len(), polls);
+    }
+
+    #[tokio::test]
+    async fn lagging_poll_does_not_take_a_confirmed_signature_back() {
+        let signature = Signature::new_unique();
+        let (service, _) = service(FakeChain::default(), Duration::from_secs(10));
+        let handle = service.watch(signature);
+
+        service.resolve(&signature, ConfirmationStatus::Confirmed, 10);
+        service.resolve(&signature, ConfirmationStatus::Processed, 11);
+
+        let tracked = handle.receiver.borrow().clone();
+        assert_eq!(tracked, Tracked { status: ConfirmationStatus::Confirmed, slot: Some(10) });
+        assert_eq!(service.pending_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn websocket_mode_polls_when_the_subscription_fails() {
+        let signature = Signature::new_unique();
+        // The fake RPC serves no pubsub endpoint, so the subscription cannot connect
+        let (service, _) = service(chain(vec![landed(10, "confirmed")]), POLL);
+        service.set_mode(ConfirmationMode::WebSocket);
+
+        let slot = service
+            .wait_landed_slot(signature, WEBSOCKET_CONNECT_TIMEOUT + Duration::from_secs(2))
+            .await
+            .unwrap();
+        assert_eq!(slot, 10);
+        assert!(service.subscribed.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn deadline_cuts_a_wait_short_with_the_signature() {
+        let signature = Signature::new_unique();
+        let never = std::future::pending::<Result<()>>();
+
+        let e = until_deadline(Some(Instant::now()), signature, never).await.unwrap_err();
+        match e.downcast_ref::<TradeError>() {
+            Some(TradeError::ConfirmationTimeout { signatures, .. }) => {
+                assert_eq!(signatures, &[signature])
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        let ready = || async { Ok::<_, anyhow::Error>(7) };
+        let later = Some(Instant::now() + Duration::from_secs(5));
+        assert_eq!(until_deadline(later, signature, ready()).await.unwrap(), 7);
+        assert_eq!(until_deadline(None, signature, ready()).await.unwrap(), 7);
+    }
+
+    #[test]
+    fn websocket_url_follows_the_rpc_url() {
+        assert_eq!(websocket_url("http://127.0.0.1:8899"), "ws://127.0.0.1:8900");
+        assert_eq!(
+            websocket_url("https://mainnet.example.com/key-123"),
+            "wss://mainnet.example.com/key-123"
+        );
+        assert_eq!(
+            websocket_url("http://node.example.com:port/rpc"),
+            "ws://node.example.com:port/rpc"
+        );
+        assert_eq!(websocket_url("localhost:8899"), "ws://localhost:8900");
+    }
+
+    #[test]
+    fn stages_keep_the_highest_level_reached() {
+        let mut stages = TradeStages::default();
+        stages.mark(WaitLevel::Confirmed);
+        stages.mark(WaitLevel::Submitted);
+
+        assert_eq!(stages.achieved, Some(WaitLevel::Confirmed));
+        assert!(stages.submitted_at_ms.is_some() && stages.confirmed_at_ms.is_some());
+        assert!(stages.finalized_at_ms.is_none());
+        assert_eq!(WaitLevel::from(true), WaitLevel::Confirmed);
+        assert_eq!(WaitLevel::from(false), WaitLevel::Submitted);
+    }
+}
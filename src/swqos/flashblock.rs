@@ -1,4 +1,4 @@
-use crate::swqos::common::{poll_transaction_confirmation, serialize_transaction_and_encode};
+use crate::swqos::common::{poll_transaction_level, serialize_transaction_and_encode};
 use rand::seq::IndexedRandom;
 use reqwest::Client;
 use serde_json::json;
@@ -8,8 +8,9 @@ use std::time::Duration;
 use solana_transaction_status::UiTransactionEncoding;
 
 use anyhow::Result;
-use solana_sdk::transaction::VersionedTransaction;
-use crate::swqos::{SwqosType, TradeType};
+use solana_sdk::{signature::Signature, transaction::VersionedTransaction};
+use crate::swqos::{confirmation::WaitLevel, timing::SubmitTimer, SwqosType, TradeType};
+use crate::swqos::auth::{self, SwqosAuthError};
 use crate::swqos::SwqosClientTrait;
 
 use crate::{common::SolanaRpcClient, constants::swqos::FLASHBLOCK_TIP_ACCOUNTS};
@@ -25,22 +26,33 @@ pub struct FlashBlockClient {
 
 #[async_trait::async_trait]
 impl SwqosClientTrait for FlashBlockClient {
-    async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<()> {
+    async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<Signature> {
         self.send_transaction(trade_type, transaction).await
     }
 
+    async fn confirm_transaction(&self, trade_type: TradeType, signature: Signature) -> Result<()> {
+        self.confirm_transaction(trade_type, signature, WaitLevel::Confirmed).await
+    }
+
+    async fn confirm_transaction_at_level(&self, trade_type: TradeType, signature: Signature, level: WaitLevel) -> Result<()> {
+        self.confirm_transaction(trade_type, signature, level).await
+    }
+
     async fn send_transactions(&self, trade_type: TradeType, transactions: &Vec<VersionedTransaction>) -> Result<()> {
         self.send_transactions(trade_type, transactions).await
     }
 
-    fn get_tip_account(&self) -> Result<String> {
-        let tip_account = *FLASHBLOCK_TIP_ACCOUNTS.choose(&mut rand::rng()).or_else(|| FLASHBLOCK_TIP_ACCOUNTS.first()).unwrap();
-        Ok(tip_account.to_string())
+    fn get_tip_account(&self) -> Option<String> {
+        FLASHBLOCK_TIP_ACCOUNTS.choose(&mut rand::rng()).map(|account| account.to_string())
     }
 
     fn get_swqos_type(&self) -> SwqosType {
         SwqosType::FlashBlock
     }
+
+    async fn probe_auth(&self) -> Result<(), SwqosAuthError> {
+        self.probe_auth().await
+    }
 }
 
 impl FlashBlockClient {
@@ -58,8 +70,19 @@ impl FlashBlockClient {
         Self { rpc_client: Arc::new(rpc_client), endpoint, auth_token, http_client }
     }
 
-    pub async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<()> {
+    /// Send a request the provider rejects after checking the credentials, see `swqos::auth`
+    pub async fn probe_auth(&self) -> Result<(), SwqosAuthError> {
+        let url = format!("{}/api/v2/submit-batch", self.endpoint);
+        let request = self.http_client.post(&url)
+            .body(json!({ "transactions": ["auth-probe"] }).to_string())
+            .header("Authorization", &self.auth_token)
+            .header("Content-Type", "application/json");
+        auth::probe(SwqosType::FlashBlock, &url, request).await
+    }
+
+    pub async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<Signature> {
         let start_time = Instant::now();
+        let mut timer = SubmitTimer::start(SwqosType::FlashBlock);
         let (content, signature) = serialize_transaction_and_encode(transaction, UiTransactionEncoding::Base64).await?;
         println!(" Transaction encoded to base64: {:?}", start_time.elapsed());
 
@@ -71,30 +94,34 @@ impl FlashBlockClient {
         let url = format!("{}/api/v2/submit-batch", self.endpoint);
 
         // Send request to FlashBlock
-        let response_text = self.http_client.post(&url)
+        let request = self.http_client.post(&url)
             .body(request_body)
             .header("Authorization", &self.auth_token)
             .header("Content-Type", "application/json")
             .header("Connection", "keep-alive")
-            .header("Keep-Alive", "timeout=30, max=1000")
-            .send()
-            .await?
-            .text()
-            .await?;
+            .header("Keep-Alive", "timeout=30, max=1000");
+        let response_text = timer.send(request).await?;
 
         // Parse response
         if let Ok(response_json) = serde_json::from_str::<serde_json::Value>(&response_text) {
             if response_json.get("success").is_some() || response_json.get("result").is_some() {
                 println!(" FlashBlock {} submitted: {:?}", trade_type, start_time.elapsed());
-            } else if let Some(_error) = response_json.get("error") {
-                eprintln!(" FlashBlock {} submission failed: {:?}", trade_type, _error);
+            } else if let Some(error) = response_json.get("error") {
+                eprintln!(" FlashBlock {} submission failed: {:?}", trade_type, error);
+                return Err(anyhow::anyhow!("FlashBlock submission failed: {} | Signature: {}", error, signature));
             }
         } else {
             eprintln!(" FlashBlock {} submission failed: {:?}", trade_type, response_text);
+            return Err(anyhow::anyhow!("FlashBlock submission failed: {} | Signature: {}", response_text, signature));
         }
 
+        timer.succeed();
+        Ok(signature)
+    }
+
+    pub async fn confirm_transaction(&self, trade_type: TradeType, signature: Signature, level: WaitLevel) -> Result<()> {
         let start_time: Instant = Instant::now();
-        match poll_transaction_confirmation(&self.rpc_client, signature).await {
+        match poll_transaction_level(&self.rpc_client, signature, level).await {
             Ok(_) => (),
             Err(e) => {
                 println!(" signature: {:?}", signature);
@@ -0,0 +1,178 @@
+//! Background health checks of swqos providers
+//!
+//! `start_health_monitor` runs `SwqosClientTrait::health_check` of every client on an interval.
+//! A provider turns unhealthy after `UNHEALTHY_AFTER_FAILURES` failed checks in a row and
+//! healthy again with its next successful one; providers never checked count as healthy.
+//! Before each trade `order_clients` drops the unhealthy clients, unless none is left, and
+//! sorts the rest by the share of their last `SUCCESS_RATE_WINDOW` submissions that were
+//! accepted, read from `timing`.
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::task::JoinHandle;
+
+use super::{timing, SwqosClient, SwqosType};
+
+/// Interval of the background checks unless configured otherwise
+pub const DEFAULT_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// Time allowed for one check, a slower answer counts as a failure
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Failed checks in a row before a provider is skipped
+pub const UNHEALTHY_AFTER_FAILURES: u32 = 2;
+/// Submissions the success rate is computed over
+pub const SUCCESS_RATE_WINDOW: usize = 50;
+
+static SKIP_UNHEALTHY: AtomicBool = AtomicBool::new(true);
+
+lazy_static::lazy_static! {
+    static ref HEALTH: Mutex<HashMap<SwqosType, HealthState>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Default)]
+struct HealthState {
+    consecutive_failures: u32,
+    last_error: Option<String>,
+    last_checked_at: Option<i64>,
+}
+
+/// Health of one provider
+#[derive(Debug, Clone, Serialize)]
+pub struct SwqosHealth {
+    pub swqos_type: SwqosType,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    /// Error of the last check, cleared by a successful one
+    pub last_error: Option<String>,
+    /// Unix time of the last check, in seconds, `None` before the first
+    pub last_checked_at: Option<i64>,
+    /// Share of the recent submissions the provider accepted, `None` before the first
+    pub success_rate: Option<f64>,
+}
+
+/// Whether trades skip unhealthy providers, on by default
+pub fn set_skip_unhealthy(skip: bool) {
+    SKIP_UNHEALTHY.store(skip, Ordering::Relaxed);
+}
+
+/// Check every client concurrently and record the outcomes
+pub async fn check_clients(clients: &[Arc<SwqosClient>]) {
+    let checks = clients.iter().map(|client| async move {
+        let result = tokio::time::timeout(HEALTH_CHECK_TIMEOUT, client.health_check())
+            .await
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("no answer in {:?}", HEALTH_CHECK_TIMEOUT)));
+        (client.get_swqos_type(), result)
+    });
+    let results = futures::future::join_all(checks).await;
+    let checked_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let mut health = HEALTH.lock();
+    for (swqos_type, result) in results {
+        let state = health.entry(swqos_type.clone()).or_default();
+        state.last_checked_at = Some(checked_at as i64);
+        match result {
+            Ok(()) => {
+                if state.consecutive_failures >= UNHEALTHY_AFTER_FAILURES {
+                    log::info!(icon!(" ✅ ", "{:?} is healthy again"), swqos_type);
+                }
+                state.consecutive_failures = 0;
+                state.last_error = None;
+            }
+            Err(e) => {
+                state.consecutive_failures += 1;
+                if state.consecutive_failures == UNHEALTHY_AFTER_FAILURES {
+                    log::warn!(icon!("⚠️ ", "{:?} is unhealthy, skipping it: {}"), swqos_type, e);
+                }
+                state.last_error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+/// Check `clients` every `interval` in a background task
+pub fn start_health_monitor(clients: Vec<Arc<SwqosClient>>, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            check_clients(&clients).await;
+        }
+    })
+}
+
+/// Whether the checks of `swqos_type` pass, `true` before its first check
+pub fn is_healthy(swqos_type: &SwqosType) -> bool {
+    HEALTH
+        .lock()
+        .get(swqos_type)
+        .is_none_or(|state| state.consecutive_failures < UNHEALTHY_AFTER_FAILURES)
+}
+
+/// Share of the last `SUCCESS_RATE_WINDOW` submissions of `swqos_type` that were accepted
+pub fn success_rate(swqos_type: &SwqosType) -> Option<f64> {
+    let submits = timing::recent_submit_timings(swqos_type, SUCCESS_RATE_WINDOW);
+    if submits.is_empty() {
+        return None;
+    }
+    let accepted = submits.iter().filter(|timing| timing.success).count();
+    Some(accepted as f64 / submits.len() as f64)
+}
+
+/// Health of the providers of `clients`, one entry per provider
+pub fn health_snapshot(clients: &[Arc<SwqosClient>]) -> Vec<SwqosHealth> {
+    let mut snapshot: Vec<SwqosHealth> = Vec::with_capacity(clients.len());
+    for client in clients {
+        let swqos_type = client.get_swqos_type();
+        if snapshot.iter().any(|health| health.swqos_type == swqos_type) {
+            continue;
+        }
+        let (consecutive_failures, last_error, last_checked_at) =
+            match HEALTH.lock().get(&swqos_type) {
+                Some(state) => {
+                    (state.consecutive_failures, state.last_error.clone(), state.last_checked_at)
+                }
+                None => (0, None, None),
+            };
+        snapshot.push(SwqosHealth {
+            healthy: consecutive_failures < UNHEALTHY_AFTER_FAILURES,
+            consecutive_failures,
+            last_error,
+            last_checked_at,
+            success_rate: success_rate(&swqos_type),
+            swqos_type,
+        });
+    }
+    snapshot
+}
+
+/// Clients a trade is submitted to, best first, each with its index in `clients`
+///
+/// Unhealthy clients are dropped when skipping is on, unless all of them are unhealthy. The
+/// rest are sorted by success rate, clients without submissions first, ties keep the
+/// configured order. The index finds what is configured per client in swqos order, like tips.
+pub(crate) fn order_clients(clients: &[Arc<SwqosClient>]) -> Vec<(usize, Arc<SwqosClient>)> {
+    let configured = || clients.iter().cloned().enumerate().collect();
+    if clients.len() < 2 {
+        return configured();
+    }
+    let mut ordered: Vec<(f64, usize, Arc<SwqosClient>)> = Vec::with_capacity(clients.len());
+    for (index, client) in clients.iter().enumerate() {
+        let swqos_type = client.get_swqos_type();
+        if SKIP_UNHEALTHY.load(Ordering::Relaxed) && !is_healthy(&swqos_type) {
+            continue;
+        }
+        ordered.push((success_rate(&swqos_type).unwrap_or(1.0), index, client.clone()));
+    }
+    if ordered.is_empty() {
+        return configured();
+    }
+    ordered.sort_by(|a, b| b.0.total_cmp(&a.0));
+    ordered.into_iter().map(|(_, index, client)| (index, client)).collect()
+}
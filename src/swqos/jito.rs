@@ -1,5 +1,5 @@
 
-use crate::swqos::common::{poll_transaction_confirmation, serialize_transaction_and_encode, FormatBase64VersionedTransaction};
+use crate::swqos::common::{rpc_request, poll_transaction_level_until, serialize_transaction_and_encode, FormatBase64VersionedTransaction};
 use rand::seq::IndexedRandom;
 use reqwest::Client;
 use serde_json::json;
@@ -8,10 +8,12 @@ use solana_transaction_status::UiTransactionEncoding;
 
 use anyhow::Result;
 use solana_sdk::{signature::Signature, transaction::VersionedTransaction};
-use crate::swqos::{SwqosType, TradeType};
+use crate::swqos::{confirmation::WaitLevel, timing::SubmitTimer, SwqosType, TradeType};
+use crate::swqos::auth::{self, SwqosAuthError};
 use crate::swqos::SwqosClientTrait;
 
 use crate::{common::SolanaRpcClient, constants::swqos::JITO_TIP_ACCOUNTS};
+use crate::trading::core::error::TradeError;
 
 
 pub struct JitoClient {
@@ -23,25 +25,45 @@ pub struct JitoClient {
 
 #[async_trait::async_trait]
 impl SwqosClientTrait for JitoClient {
-    async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<()> {
+    async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<Signature> {
         self.send_transaction(trade_type, transaction).await
     }
 
+    async fn confirm_transaction(&self, trade_type: TradeType, signature: Signature) -> Result<()> {
+        self.confirm_transaction_with_retry(trade_type, signature, WaitLevel::Confirmed, Instant::now(), None).await
+    }
+
+    async fn confirm_transaction_at_level(&self, trade_type: TradeType, signature: Signature, level: WaitLevel) -> Result<()> {
+        self.confirm_transaction_with_retry(trade_type, signature, level, Instant::now(), None).await
+    }
+
+    async fn confirm_transaction_until(&self, trade_type: TradeType, signature: Signature, level: WaitLevel, deadline: Option<Instant>) -> Result<()> {
+        self.confirm_transaction_with_retry(trade_type, signature, level, Instant::now(), deadline).await
+    }
+
     async fn send_transactions(&self, trade_type: TradeType, transactions: &Vec<VersionedTransaction>) -> Result<()> {
         self.send_transactions(trade_type, transactions).await
     }
 
-    fn get_tip_account(&self) -> Result<String> {
-        if let Some(acc) = JITO_TIP_ACCOUNTS.choose(&mut rand::rng()) {
-            Ok(acc.to_string())
-        } else {
-            Err(anyhow::anyhow!("no valid tip accounts found"))
-        }
+    fn supports_bundles(&self) -> bool {
+        true
+    }
+
+    async fn send_bundle(&self, trade_type: TradeType, transactions: &[VersionedTransaction]) -> Result<String> {
+        self.send_bundle(trade_type, transactions).await
+    }
+
+    fn get_tip_account(&self) -> Option<String> {
+        JITO_TIP_ACCOUNTS.choose(&mut rand::rng()).map(|account| account.to_string())
     }
 
     fn get_swqos_type(&self) -> SwqosType {
         SwqosType::Jito
     }
+
+    async fn probe_auth(&self) -> Result<(), SwqosAuthError> {
+        self.probe_auth().await
+    }
 }
 
 impl JitoClient {
@@ -59,21 +81,30 @@ impl JitoClient {
         Self { rpc_client: Arc::new(rpc_client), endpoint, auth_token, http_client }
     }
 
-    pub async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<()> {
-        let overall_start = Instant::now();
+    /// Send a request the provider rejects after checking the credentials, see `swqos::auth`
+    pub async fn probe_auth(&self) -> Result<(), SwqosAuthError> {
+        if self.auth_token.is_empty() {
+            return Ok(());
+        }
+        let url = format!("{}/api/v1/transactions?uuid={}", self.endpoint, self.auth_token);
+        let request = self.http_client.post(&url)
+            .body(auth::malformed_send_transaction())
+            .header("x-jito-auth", &self.auth_token)
+            .header("Content-Type", "application/json");
+        auth::probe(SwqosType::Jito, &url, request).await
+    }
+
+    /// sendTransaction request carrying `trade_type` in its id
+    pub(crate) fn send_transaction_body(trade_type: TradeType, content: &str) -> serde_json::Value {
+        rpc_request(trade_type, "sendTransaction", json!([content, { "encoding": "base64" }]))
+    }
+
+    pub async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<Signature> {
+        let start_time = Instant::now();
+        let mut timer = SubmitTimer::start(SwqosType::Jito);
         let (content, signature) = serialize_transaction_and_encode(transaction, UiTransactionEncoding::Base64).await?;
 
-        let request_body = serde_json::to_string(&json!({
-            "id": 1,
-            "jsonrpc": "2.0", 
-            "method": "sendTransaction",
-            "params": [
-                content,
-                {
-                    "encoding": "base64"
-                }
-            ]
-        }))?;
+        let request_body = serde_json::to_string(&Self::send_transaction_body(trade_type, &content))?;
 
         let endpoint = if self.auth_token.is_empty() {
             format!("{}/api/v1/transactions", self.endpoint)
@@ -86,52 +117,62 @@ impl JitoClient {
             self.http_client.post(&endpoint)
                 .header("x-jito-auth", &self.auth_token)
         };
-        let response_text = response
+        let request = response
             .body(request_body)
-            .header("Content-Type", "application/json")
-            .send()
-            .await?
-            .text()
-            .await?;
+            .header("Content-Type", "application/json");
+        let response_text = timer.send(request).await?;
 
         // Check submission result
         if let Ok(response_json) = serde_json::from_str::<serde_json::Value>(&response_text) {
             if response_json.get("result").is_none() {
                 if let Some(error) = response_json.get("error") {
-                    println!("\x1b[31m❌ [Jito] {} submission failed: {} | Sig: {}\x1b[0m", trade_type, error, &signature.to_string()[..8]);
-                    return Err(anyhow::anyhow!("Jito submission failed: {} | Signature: {}", error, signature));
+                    println!(
+                        icon!("❌ ", "[Jito] {} submission failed: {} | Sig: {}"),
+                        trade_type,
+                        error,
+                        &signature.to_string()[..8]
+                    );
+                    return Err(anyhow::anyhow!(
+                        "Jito submission failed: {} | Signature: {}",
+                        error,
+                        signature
+                    ));
                 }
             }
         } else {
-            println!("\x1b[31m❌ [Jito] {} submission failed: {} | Sig: {}\x1b[0m", trade_type, response_text, &signature.to_string()[..8]);
-            return Err(anyhow::anyhow!("Jito submission failed: {} | Signature: {}", response_text, signature));
+            println!(
+                icon!("❌ ", "[Jito] {} submission failed: {} | Sig: {}"),
+                trade_type,
+                response_text,
+                &signature.to_string()[..8]
+            );
+            return Err(anyhow::anyhow!(
+                "Jito submission failed: {} | Signature: {}",
+                response_text,
+                signature
+            ));
         }
 
-        // Confirm transaction with retry logic for timeouts
-        match self.confirm_transaction_with_retry(trade_type, signature, overall_start).await {
-            Ok(_) => {
-                // Success message is printed in confirm_transaction_with_retry
-            },
-            Err(e) => {
-                return Err(e);
-            },
-        }
+        println!(" jito {} submitted: {:?}", trade_type, start_time.elapsed());
 
-        Ok(())
+        timer.succeed();
+        Ok(signature)
     }
 
     pub async fn send_transactions(&self, trade_type: TradeType, transactions: &Vec<VersionedTransaction>) -> Result<()> {
+        self.send_bundle(trade_type, transactions).await.map(|_| ())
+    }
+
+    /// sendBundle request carrying `trade_type` in its id
+    pub(crate) fn send_bundle_body(trade_type: TradeType, txs_base64: &[String]) -> serde_json::Value {
+        rpc_request(trade_type, "sendBundle", json!([txs_base64, { "encoding": "base64" }]))
+    }
+
+    /// Submit `transactions` through `sendBundle`, returning the bundle id
+    pub async fn send_bundle(&self, trade_type: TradeType, transactions: &[VersionedTransaction]) -> Result<String> {
         let start_time = Instant::now();
         let txs_base64 = transactions.iter().map(|tx| tx.to_base64_string()).collect::<Vec<String>>();
-        let body = serde_json::json!({
-            "jsonrpc": "2.0",
-            "method": "sendBundle",
-            "params": [
-                txs_base64,
-                { "encoding": "base64" }
-            ],
-            "id": 1,
-        });
+        let body = Self::send_bundle_body(trade_type, &txs_base64);
 
         let endpoint = if self.auth_token.is_empty() {
             format!("{}/api/v1/bundles", self.endpoint)
@@ -152,40 +193,57 @@ impl JitoClient {
             .text()
             .await?;
 
-        if let Ok(response_json) = serde_json::from_str::<serde_json::Value>(&response_text) {
-            if response_json.get("result").is_some() {
-                println!(" jito {} submitted: {:?}", trade_type, start_time.elapsed());
-            } else if let Some(_error) = response_json.get("error") {
-                eprintln!(" jito {} submission failed: {:?}", trade_type, _error);
-            }
+        let response_json = serde_json::from_str::<serde_json::Value>(&response_text)
+            .map_err(|_| anyhow::anyhow!("Jito bundle submission failed: {}", response_text))?;
+        if let Some(bundle_id) = response_json.get("result").and_then(|result| result.as_str()) {
+            println!(" jito {} bundle submitted: {:?}", trade_type, start_time.elapsed());
+            return Ok(bundle_id.to_string());
         }
-
-        Ok(())
+        let error = response_json.get("error").unwrap_or(&response_json);
+        eprintln!(" jito {} bundle submission failed: {:?}", trade_type, error);
+        Err(anyhow::anyhow!("Jito bundle submission failed: {}", error))
     }
 
     /// Confirm transaction with retry logic for timeout errors
+    ///
+    /// Timeouts are retried twice, or until `deadline` when one is given.
     async fn confirm_transaction_with_retry(
         &self, 
         trade_type: TradeType, 
         signature: Signature,
-        overall_start: Instant
+        level: WaitLevel,
+        overall_start: Instant,
+        deadline: Option<Instant>,
     ) -> Result<()> {
-        let max_retries = 2; // As requested by user
+        let max_retries = if deadline.is_some() { u32::MAX } else { 2 };
         
         for attempt in 0..=max_retries {
-            match poll_transaction_confirmation(&self.rpc_client, signature).await {
+            match poll_transaction_level_until(&self.rpc_client, signature, level, deadline).await {
                 Ok(_) => {
-                    println!("\x1b[32m✅ [Jito] {} confirmed in {:?} | Sig: {}\x1b[0m", 
-                        trade_type, overall_start.elapsed(), &signature.to_string()[..8]);
+                    println!(
+                        icon!("✅ ", "[Jito] {} confirmed in {:?} | Sig: {}"),
+                        trade_type,
+                        overall_start.elapsed(),
+                        &signature.to_string()[..8]
+                    );
                     return Ok(());
                 },
                 Err(e) => {
                     let error_msg = e.to_string();
                     
                     // Check if this is a timeout error
-                    if error_msg.contains("confirmation timed out") {
+                    let timed_out = matches!(
+                        e.downcast_ref::<TradeError>(),
+                        Some(TradeError::ConfirmationTimeout { .. })
+                    );
+                    if timed_out && deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        println!(icon!("⏰ ", "[Jito] {} deadline passed before confirmation in {:?} | Sig: {}"), 
+                            trade_type, overall_start.elapsed(), &signature.to_string()[..8]);
+                        return Err(TradeError::deadline_exceeded(vec![signature]).into());
+                    }
+                    if timed_out {
                         if attempt < max_retries {
-                            println!("\x1b[33m⏰ [Jito] {} confirmation timed out on attempt {}, retrying... | Sig: {}\x1b[0m", 
+                            println!(icon!("⏰ ", "[Jito] {} confirmation timed out on attempt {}, retrying... | Sig: {}"), 
                                 trade_type, attempt + 1, &signature.to_string()[..8]);
                             
                             // Brief pause before retry
@@ -193,14 +251,22 @@ impl JitoClient {
                             continue;
                         } else {
                             // All retries exhausted for timeout
-                            println!("\x1b[31m❌ [Jito] {} confirmation failed after {} retries (all timeouts) in {:?} | Sig: {}\x1b[0m", 
+                            println!(icon!("❌ ", "[Jito] {} confirmation failed after {} retries (all timeouts) in {:?} | Sig: {}"), 
                                 trade_type, max_retries + 1, overall_start.elapsed(), &signature.to_string()[..8]);
-                            return Err(anyhow::anyhow!("Transaction confirmation timed out after {} retries | Signature: {}", max_retries + 1, signature));
+                            return Err(e);
                         }
                     } else {
                         // Non-timeout error - don't retry, fail immediately
-                        println!("\x1b[31m❌ [Jito] {} confirmation failed in {:?} | Sig: {} | Error: {}\x1b[0m", 
-                            trade_type, overall_start.elapsed(), &signature.to_string()[..8], error_msg);
+                        println!(
+                            icon!(
+                                "❌ ",
+                                "[Jito] {} confirmation failed in {:?} | Sig: {} | Error: {}"
+                            ),
+                            trade_type,
+                            overall_start.elapsed(),
+                            &signature.to_string()[..8],
+                            error_msg
+                        );
                         return Err(anyhow::anyhow!("{} | Signature: {}", error_msg, signature));
                     }
                 }
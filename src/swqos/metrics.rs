@@ -0,0 +1,323 @@
+//! Per-provider submission counters
+//!
+//! Unlike `timing`, which keeps a window of samples behind a lock, these are plain atomics
+//! counting every submission since start or the last `reset_stats`. Built-in clients update
+//! them through their `SubmitTimer`, confirmations are counted when a provider's own
+//! confirmation polling finishes.
+//!
+//! Trades sent with labels are also counted per label set, see `labeled_swqos_stats`. Only the
+//! first `MAX_LABELED_SERIES` label sets get their own counters, so labels with unbounded values
+//! such as a trade id cannot grow the registry without limit.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use super::SwqosType;
+use crate::common::labels::{TradeLabels, RESERVED_LABEL_KEYS};
+
+/// Label sets counted separately, submissions of further label sets are counted together in
+/// one series flagged `overflow`
+pub const MAX_LABELED_SERIES: usize = 64;
+
+const SWQOS_TYPES: [SwqosType; 11] = [
+    SwqosType::Jito,
+    SwqosType::NextBlock,
+    SwqosType::ZeroSlot,
+    SwqosType::Temporal,
+    SwqosType::Bloxroute,
+    SwqosType::Node1,
+    SwqosType::FlashBlock,
+    SwqosType::BlockRazor,
+    SwqosType::Astralane,
+    SwqosType::External,
+    SwqosType::Default,
+];
+
+struct ProviderMetrics {
+    submissions: AtomicU64,
+    failures: AtomicU64,
+    confirmations: AtomicU64,
+    confirmation_failures: AtomicU64,
+    latency_min_us: AtomicU64,
+    latency_max_us: AtomicU64,
+    latency_total_us: AtomicU64,
+}
+
+impl ProviderMetrics {
+    const fn new() -> Self {
+        Self {
+            submissions: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            confirmations: AtomicU64::new(0),
+            confirmation_failures: AtomicU64::new(0),
+            latency_min_us: AtomicU64::new(u64::MAX),
+            latency_max_us: AtomicU64::new(0),
+            latency_total_us: AtomicU64::new(0),
+        }
+    }
+
+    fn reset(&self) {
+        self.submissions.store(0, Ordering::Relaxed);
+        self.failures.store(0, Ordering::Relaxed);
+        self.confirmations.store(0, Ordering::Relaxed);
+        self.confirmation_failures.store(0, Ordering::Relaxed);
+        self.latency_min_us.store(u64::MAX, Ordering::Relaxed);
+        self.latency_max_us.store(0, Ordering::Relaxed);
+        self.latency_total_us.store(0, Ordering::Relaxed);
+    }
+
+    fn record_submission(&self, elapsed: Duration, success: bool) {
+        let elapsed_us = elapsed.as_micros() as u64;
+        self.submissions.fetch_add(1, Ordering::Relaxed);
+        if !success {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+        }
+        self.latency_total_us.fetch_add(elapsed_us, Ordering::Relaxed);
+        self.latency_min_us.fetch_min(elapsed_us, Ordering::Relaxed);
+        self.latency_max_us.fetch_max(elapsed_us, Ordering::Relaxed);
+    }
+
+    fn record_confirmation(&self, success: bool) {
+        if success {
+            self.confirmations.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.confirmation_failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// `None` before the first submission
+    fn stats(&self, swqos_type: &SwqosType) -> Option<SwqosStats> {
+        let submissions = self.submissions.load(Ordering::Relaxed);
+        if submissions == 0 {
+            return None;
+        }
+        let failures = self.failures.load(Ordering::Relaxed).min(submissions);
+        let total_us = self.latency_total_us.load(Ordering::Relaxed);
+        // Unset when a reset raced the submission count
+        let min_us = self.latency_min_us.load(Ordering::Relaxed);
+        Some(SwqosStats {
+            swqos_type: swqos_type.clone(),
+            submissions,
+            accepted: submissions - failures,
+            failures,
+            confirmations: self.confirmations.load(Ordering::Relaxed),
+            confirmation_failures: self.confirmation_failures.load(Ordering::Relaxed),
+            submit_latency_min: (min_us != u64::MAX).then(|| Duration::from_micros(min_us)),
+            submit_latency_avg: Some(Duration::from_micros(total_us / submissions)),
+            submit_latency_max: Some(Duration::from_micros(
+                self.latency_max_us.load(Ordering::Relaxed),
+            )),
+        })
+    }
+}
+
+static METRICS: [ProviderMetrics; SWQOS_TYPES.len()] =
+    [const { ProviderMetrics::new() }; SWQOS_TYPES.len()];
+
+fn provider_index(swqos_type: &SwqosType) -> usize {
+    match swqos_type {
+        SwqosType::Jito => 0,
+        SwqosType::NextBlock => 1,
+        SwqosType::ZeroSlot => 2,
+        SwqosType::Temporal => 3,
+        SwqosType::Bloxroute => 4,
+        SwqosType::Node1 => 5,
+        SwqosType::FlashBlock => 6,
+        SwqosType::BlockRazor => 7,
+        SwqosType::Astralane => 8,
+        SwqosType::External => 9,
+        SwqosType::Default => 10,
+    }
+}
+
+fn metrics(swqos_type: &SwqosType) -> &'static ProviderMetrics {
+    &METRICS[provider_index(swqos_type)]
+}
+
+/// Counters of one provider since start or the last `reset_stats`
+#[derive(Debug, Clone, Serialize)]
+pub struct SwqosStats {
+    pub swqos_type: SwqosType,
+    pub submissions: u64,
+    /// Submissions the provider accepted
+    pub accepted: u64,
+    /// Submissions that errored or were rejected
+    pub failures: u64,
+    /// Accepted transactions the provider's confirmation polling saw land
+    pub confirmations: u64,
+    pub confirmation_failures: u64,
+    /// Submission round trip, `None` before the first submission
+    pub submit_latency_min: Option<Duration>,
+    pub submit_latency_avg: Option<Duration>,
+    pub submit_latency_max: Option<Duration>,
+}
+
+/// Counters of one provider and label set since start or the last `reset_stats`
+#[derive(Debug, Clone, Serialize)]
+pub struct LabeledSwqosStats {
+    /// Trade labels without the SDK's reserved keys, empty for the overflow series
+    pub labels: TradeLabels,
+    /// Counts label sets seen after `MAX_LABELED_SERIES` others
+    pub overflow: bool,
+    pub stats: SwqosStats,
+}
+
+/// Per-provider counters of the trades sent with one label set
+pub(crate) struct LabeledSeries {
+    labels: TradeLabels,
+    providers: [ProviderMetrics; SWQOS_TYPES.len()],
+}
+
+impl LabeledSeries {
+    fn new(labels: TradeLabels) -> Self {
+        Self { labels, providers: [const { ProviderMetrics::new() }; SWQOS_TYPES.len()] }
+    }
+
+    pub(crate) fn record_submission(
+        &self,
+        swqos_type: &SwqosType,
+        elapsed: Duration,
+        success: bool,
+    ) {
+        self.providers[provider_index(swqos_type)].record_submission(elapsed, success);
+    }
+
+    pub(crate) fn record_confirmation(&self, swqos_type: &SwqosType, success: bool) {
+        self.providers[provider_index(swqos_type)].record_confirmation(success);
+    }
+
+    fn stats(&self, overflow: bool) -> impl Iterator<Item = LabeledSwqosStats> + '_ {
+        SWQOS_TYPES.iter().zip(&self.providers).filter_map(move |(swqos_type, metrics)| {
+            Some(LabeledSwqosStats {
+                labels: self.labels.clone(),
+                overflow,
+                stats: metrics.stats(swqos_type)?,
+            })
+        })
+    }
+}
+
+static LABELED_SERIES: Lazy<DashMap<String, Arc<LabeledSeries>>> = Lazy::new(DashMap::new);
+static OVERFLOW_SERIES: Lazy<Arc<LabeledSeries>> =
+    Lazy::new(|| Arc::new(LabeledSeries::new(TradeLabels::new())));
+
+/// Counters of the label set of a trade, `None` when it has no labels besides reserved ones
+///
+/// Resolved once per trade, the counters are then updated without touching the registry.
+pub(crate) fn labeled_series(labels: &TradeLabels) -> Option<Arc<LabeledSeries>> {
+    let labels: BTreeMap<&String, &String> =
+        labels.iter().filter(|(key, _)| !RESERVED_LABEL_KEYS.contains(&key.as_str())).collect();
+    if labels.is_empty() {
+        return None;
+    }
+    let key = serde_json::to_string(&labels).ok()?;
+    if let Some(series) = LABELED_SERIES.get(&key) {
+        return Some(series.clone());
+    }
+    // Concurrent trades may each add a new label set past the check, overshooting by a few
+    if LABELED_SERIES.len() >= MAX_LABELED_SERIES {
+        return Some(OVERFLOW_SERIES.clone());
+    }
+    let labels = labels.into_iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+    Some(LABELED_SERIES.entry(key).or_insert_with(|| Arc::new(LabeledSeries::new(labels))).clone())
+}
+
+/// Count a submission and its round trip
+pub fn record_submission(swqos_type: &SwqosType, elapsed: Duration, success: bool) {
+    metrics(swqos_type).record_submission(elapsed, success);
+}
+
+/// Count the outcome of a confirmation wait
+pub fn record_confirmation(swqos_type: &SwqosType, success: bool) {
+    metrics(swqos_type).record_confirmation(success);
+}
+
+/// Counters of every provider that submitted at least once
+///
+/// Counters are read one by one, a snapshot taken during submissions may be off by the
+/// submissions in flight.
+pub fn swqos_stats() -> Vec<SwqosStats> {
+    SWQOS_TYPES.iter().filter_map(|swqos_type| metrics(swqos_type).stats(swqos_type)).collect()
+}
+
+/// Counters of every provider and label set that submitted at least once, the overflow series
+/// last
+pub fn labeled_swqos_stats() -> Vec<LabeledSwqosStats> {
+    let mut stats: Vec<LabeledSwqosStats> =
+        LABELED_SERIES.iter().flat_map(|series| series.stats(false).collect::<Vec<_>>()).collect();
+    stats.extend(OVERFLOW_SERIES.stats(true));
+    stats
+}
+
+/// Zero the counters of every provider and forget all label sets
+pub fn reset_stats() {
+    for metrics in &METRICS {
+        metrics.reset();
+    }
+    LABELED_SERIES.clear();
+    for metrics in &OVERFLOW_SERIES.providers {
+        metrics.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> TradeLabels {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    fn stats_of(series: &TradeLabels) -> Vec<LabeledSwqosStats> {
+        labeled_swqos_stats().into_iter().filter(|stats| stats.labels == *series).collect()
+    }
+
+    // One test, the registry is process-wide
+    #[test]
+    fn labeled_series_group_trades_and_cap_the_label_sets() {
+        let strategy = labels(&[("strategy", "shared-series"), ("wallet", "reserved")]);
+        let first = labeled_series(&strategy).unwrap();
+        let second = labeled_series(&labels(&[("strategy", "shared-series")])).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(labeled_series(&labels(&[("wallet", "reserved")])).is_none());
+
+        first.record_submission(&SwqosType::Jito, Duration::from_millis(4), true);
+        second.record_submission(&SwqosType::Jito, Duration::from_millis(8), false);
+        second.record_submission(&SwqosType::ZeroSlot, Duration::from_millis(2), true);
+        first.record_confirmation(&SwqosType::Jito, true);
+
+        let stats = stats_of(&labels(&[("strategy", "shared-series")]));
+        assert_eq!(stats.len(), 2);
+        let jito = stats.iter().find(|stats| stats.stats.swqos_type == SwqosType::Jito).unwrap();
+        assert!(!jito.overflow);
+        assert_eq!((jito.stats.submissions, jito.stats.failures), (2, 1));
+        assert_eq!(jito.stats.confirmations, 1);
+        assert_eq!(jito.stats.submit_latency_avg, Some(Duration::from_millis(6)));
+
+        // Label sets past the limit share the overflow series, known ones keep theirs
+        for i in 0..MAX_LABELED_SERIES {
+            labeled_series(&labels(&[("trade_id", &format!("fill-{}", i))])).unwrap();
+        }
+        let overflow = labeled_series(&labels(&[("trade_id", "one-too-many")])).unwrap();
+        assert!(Arc::ptr_eq(&overflow, &OVERFLOW_SERIES));
+        assert!(LABELED_SERIES.len() <= MAX_LABELED_SERIES);
+        let known = labeled_series(&labels(&[("strategy", "shared-series")])).unwrap();
+        assert!(Arc::ptr_eq(&known, &first));
+
+        overflow.record_submission(&SwqosType::NextBlock, Duration::from_millis(1), true);
+        let stats = stats_of(&TradeLabels::new());
+        assert!(stats.iter().any(|stats| stats.overflow
+            && stats.stats.swqos_type == SwqosType::NextBlock
+            && stats.stats.submissions >= 1));
+    }
+}
@@ -1,4 +1,11 @@
+pub mod auth;
 pub mod common;
+pub mod confirmation;
+pub mod health;
+pub mod region;
+pub mod tip_account;
+pub mod timing;
+pub mod metrics;
 pub mod solana_rpc;
 pub mod jito;
 pub mod nextblock;
@@ -9,28 +16,24 @@ pub mod node1;
 pub mod flashblock;
 pub mod blockrazor;
 pub mod astralane;
+#[cfg(test)]
+pub(crate) mod testing;
 
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
-use solana_sdk::{commitment_config::CommitmentConfig, transaction::VersionedTransaction};
+use solana_sdk::{
+    commitment_config::CommitmentConfig, signature::Signature, transaction::VersionedTransaction,
+};
 use tokio::sync::RwLock;
 
 use anyhow::Result;
 
 use crate::{
     common::SolanaRpcClient, 
-    constants::swqos::{
-        SWQOS_ENDPOINTS_BLOX, 
-        SWQOS_ENDPOINTS_JITO, 
-        SWQOS_ENDPOINTS_NEXTBLOCK, 
-        SWQOS_ENDPOINTS_TEMPORAL, 
-        SWQOS_ENDPOINTS_ZERO_SLOT, 
-        SWQOS_ENDPOINTS_NODE1, 
-        SWQOS_ENDPOINTS_FLASHBLOCK,
-        SWQOS_ENDPOINTS_BLOCKRAZOR,
-        SWQOS_ENDPOINTS_ASTRALANE
-    }, 
+    constants::swqos::swqos_endpoint,
     swqos::{
+        auth::SwqosAuthError,
+        confirmation::WaitLevel,
         bloxroute::BloxrouteClient, 
         jito::JitoClient, 
         nextblock::NextBlockClient, 
@@ -41,14 +44,14 @@ use crate::{
         flashblock::FlashBlockClient,
         blockrazor::BlockRazorClient,
         astralane::AstralaneClient
-    }
+    },
 };
 
 lazy_static::lazy_static! {
     static ref TIP_ACCOUNT_CACHE: RwLock<Vec<String>> = RwLock::new(Vec::new());
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TradeType {
     Create,
     CreateAndBuy,
@@ -68,7 +71,7 @@ impl std::fmt::Display for TradeType {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize)]
 pub enum SwqosType {
     Jito,
     NextBlock,
@@ -79,20 +82,128 @@ pub enum SwqosType {
     FlashBlock,
     BlockRazor,
     Astralane,
+    /// User supplied backend registered through `SwqosConfig::External`
+    External,
     Default,
 }
 
 pub type SwqosClient = dyn SwqosClientTrait + Send + Sync + 'static;
 
+/// Transaction submission backend
+///
+/// Contract shared by the built-in clients and custom implementations:
+/// - `send_transaction` only submits. It returns the transaction signature once the backend
+///   accepted the transaction and must not wait for confirmation. An `Err` means the transaction
+///   was rejected or the backend could not be reached; include the signature in the message
+///   (`| Signature: <sig>`) when it is known.
+/// - `confirm_transaction` waits until the signature is confirmed on chain. It returns an `Err`
+///   when the transaction failed on chain or was not confirmed in time. It is only called when the
+///   caller asked to wait for confirmation.
+/// - `confirm_transaction_at_level` waits for the `WaitLevel` the trade asked for, `Processed`
+///   or `Confirmed`. The default waits for confirmation through `confirm_transaction` whatever
+///   the level; the built-in clients poll for the requested level.
+/// - `confirm_transaction_until` is what trades call, with the trade's deadline. The default
+///   cuts `confirm_transaction_at_level` off at the deadline; clients that retry internally
+///   override it to retry until the deadline instead.
+/// - `send_transactions` submits a batch (a bundle where the backend supports it) with the same
+///   error semantics as `send_transaction`.
+/// - `send_bundle` submits the transactions as one atomic bundle and returns the bundle id. Only
+///   backends whose `supports_bundles` is true implement it, the default fails.
+/// - `get_tip_account` returns the account tips are paid to, or `None` when the backend does not
+///   take tips. No tip instruction is added for `None`.
+/// - `get_swqos_type` identifies the backend. `SwqosType::Default` marks a plain RPC client that
+///   is used for tip-less sends; custom backends normally return `SwqosType::External`.
+/// - `probe_auth` checks the credentials without sending a real transaction. Backends without
+///   credentials keep the default, which always succeeds.
 #[async_trait::async_trait]
 pub trait SwqosClientTrait {
-    async fn send_transaction(&self, trade_type: TradeType, transaction: &VersionedTransaction) -> Result<()>;
-    async fn send_transactions(&self, trade_type: TradeType, transactions: &Vec<VersionedTransaction>) -> Result<()>;
-    fn get_tip_account(&self) -> Result<String>;
+    /// Submit a transaction and return its signature without waiting for confirmation
+    async fn send_transaction(
+        &self,
+        trade_type: TradeType,
+        transaction: &VersionedTransaction,
+    ) -> Result<Signature>;
+    /// Wait until a submitted transaction is confirmed
+    async fn confirm_transaction(&self, trade_type: TradeType, signature: Signature) -> Result<()>;
+    /// Wait until a submitted transaction reached `level`
+    async fn confirm_transaction_at_level(
+        &self,
+        trade_type: TradeType,
+        signature: Signature,
+        level: WaitLevel,
+    ) -> Result<()> {
+        match level {
+            WaitLevel::Submitted => Ok(()),
+            _ => self.confirm_transaction(trade_type, signature).await,
+        }
+    }
+    /// `confirm_transaction_at_level` giving up at `deadline`, if any, with
+    /// `TradeError::ConfirmationTimeout`
+    async fn confirm_transaction_until(
+        &self,
+        trade_type: TradeType,
+        signature: Signature,
+        level: WaitLevel,
+        deadline: Option<Instant>,
+    ) -> Result<()> {
+        let confirmed = self.confirm_transaction_at_level(trade_type, signature, level);
+        confirmation::until_deadline(deadline, signature, confirmed).await
+    }
+    /// Submit a batch of transactions without waiting for confirmation
+    async fn send_transactions(
+        &self,
+        trade_type: TradeType,
+        transactions: &Vec<VersionedTransaction>,
+    ) -> Result<()>;
+    /// Whether `send_bundle` submits atomic bundles
+    fn supports_bundles(&self) -> bool {
+        false
+    }
+    /// Submit transactions as one bundle that lands all or nothing, returning the bundle id
+    async fn send_bundle(
+        &self,
+        _trade_type: TradeType,
+        _transactions: &[VersionedTransaction],
+    ) -> Result<String> {
+        Err(anyhow::anyhow!("{:?} does not accept bundles", self.get_swqos_type()))
+    }
+    /// Tip account for this backend, `None` if tips are not applicable
+    fn get_tip_account(&self) -> Option<String>;
     fn get_swqos_type(&self) -> SwqosType;
+    /// Check the credentials with a request the backend rejects after authenticating
+    async fn probe_auth(&self) -> Result<(), SwqosAuthError> {
+        Ok(())
+    }
+    /// Check the backend is reachable with a cheap request, see `swqos::health`
+    ///
+    /// Backends without a health endpoint are always healthy.
+    async fn health_check(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+// Identity based comparisons so `SwqosConfig::External` keeps the config derives
+impl std::fmt::Debug for SwqosClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SwqosClient({:?})", self.get_swqos_type())
+    }
+}
+
+impl PartialEq for SwqosClient {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::addr_eq(self as *const Self, other as *const Self)
+    }
+}
+
+impl Eq for SwqosClient {}
+
+impl std::hash::Hash for SwqosClient {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (self as *const Self).cast::<()>().hash(state);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize)]
 pub enum SwqosRegion {
     NewYork,
     Frankfurt,
@@ -102,6 +213,16 @@ pub enum SwqosRegion {
     London,
     LosAngeles,
     Default,
+    /// Region with the lowest measured latency, see `swqos::region`
+    Auto,
+}
+
+/// The provider has no endpoint in the requested region
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{swqos_type:?} has no endpoint in region {region:?}")]
+pub struct RegionUnavailable {
+    pub swqos_type: SwqosType,
+    pub region: SwqosRegion,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -116,32 +237,81 @@ pub enum SwqosConfig {
     FlashBlock(String, SwqosRegion, Option<String>),
     BlockRazor(String, SwqosRegion, Option<String>),
     Astralane(String, SwqosRegion, Option<String>),
+    /// Custom submission backend implementing `SwqosClientTrait`
+    External(Arc<SwqosClient>),
 }
 
 impl SwqosConfig {
-    pub fn get_endpoint(swqos_type: SwqosType, region: SwqosRegion, url: Option<String>) -> String {
+    /// Endpoint for a provider and region, a custom `url` takes precedence
+    ///
+    /// `SwqosRegion::Auto` uses the cached probe result (see `swqos::region`) and falls back to
+    /// `SwqosRegion::Default` until a probe ran.
+    pub fn get_endpoint(
+        swqos_type: SwqosType,
+        region: SwqosRegion,
+        url: Option<String>,
+    ) -> Result<String, RegionUnavailable> {
         if let Some(custom_url) = url {
-            return custom_url;
+            return Ok(custom_url);
+        }
+        if matches!(swqos_type, SwqosType::External | SwqosType::Default) {
+            return Ok("".to_string());
         }
-        
-        match swqos_type {
-            SwqosType::Jito => SWQOS_ENDPOINTS_JITO[region as usize].to_string(),
-            SwqosType::NextBlock => SWQOS_ENDPOINTS_NEXTBLOCK[region as usize].to_string(),
-            SwqosType::ZeroSlot => SWQOS_ENDPOINTS_ZERO_SLOT[region as usize].to_string(),
-            SwqosType::Temporal => SWQOS_ENDPOINTS_TEMPORAL[region as usize].to_string(),
-            SwqosType::Bloxroute => SWQOS_ENDPOINTS_BLOX[region as usize].to_string(),
-            SwqosType::Node1 => SWQOS_ENDPOINTS_NODE1[region as usize].to_string(),
-            SwqosType::FlashBlock => SWQOS_ENDPOINTS_FLASHBLOCK[region as usize].to_string(),
-            SwqosType::BlockRazor => SWQOS_ENDPOINTS_BLOCKRAZOR[region as usize].to_string(),
-            SwqosType::Astralane => SWQOS_ENDPOINTS_ASTRALANE[region as usize].to_string(),
-            SwqosType::Default => "".to_string(),
+
+        let region = match region {
+            SwqosRegion::Auto => region::cached_region(&swqos_type).unwrap_or(SwqosRegion::Default),
+            region => region,
+        };
+        swqos_endpoint(&swqos_type, &region)
+            .map(|endpoint| endpoint.to_string())
+            .ok_or(RegionUnavailable { swqos_type, region })
+    }
+
+    /// Provider, region and custom url of a built-in provider config
+    pub fn provider(&self) -> Option<(SwqosType, &SwqosRegion, &Option<String>)> {
+        match self {
+            SwqosConfig::Jito(_, region, url) => Some((SwqosType::Jito, region, url)),
+            SwqosConfig::NextBlock(_, region, url) => Some((SwqosType::NextBlock, region, url)),
+            SwqosConfig::Bloxroute(_, region, url) => Some((SwqosType::Bloxroute, region, url)),
+            SwqosConfig::Temporal(_, region, url) => Some((SwqosType::Temporal, region, url)),
+            SwqosConfig::ZeroSlot(_, region, url) => Some((SwqosType::ZeroSlot, region, url)),
+            SwqosConfig::Node1(_, region, url) => Some((SwqosType::Node1, region, url)),
+            SwqosConfig::FlashBlock(_, region, url) => Some((SwqosType::FlashBlock, region, url)),
+            SwqosConfig::BlockRazor(_, region, url) => Some((SwqosType::BlockRazor, region, url)),
+            SwqosConfig::Astralane(_, region, url) => Some((SwqosType::Astralane, region, url)),
+            SwqosConfig::Default(_) | SwqosConfig::External(_) => None,
+        }
+    }
+
+    /// Check that the configured region exists for the provider
+    pub fn validate_region(&self) -> Result<(), RegionUnavailable> {
+        match self.provider() {
+            Some((swqos_type, region, url)) => {
+                Self::get_endpoint(swqos_type, region.clone(), url.clone()).map(|_| ())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Like `get_endpoint`, falling back to the provider's default endpoint on a missing region
+    fn endpoint_or_default(
+        swqos_type: SwqosType,
+        region: SwqosRegion,
+        url: Option<String>,
+    ) -> String {
+        match Self::get_endpoint(swqos_type.clone(), region, url) {
+            Ok(endpoint) => endpoint,
+            Err(e) => {
+                eprintln!(icon!(" ❌ ", "{}, using the default endpoint"), e);
+                swqos_endpoint(&swqos_type, &SwqosRegion::Default).unwrap_or_default().to_string()
+            }
         }
     }
 
     pub fn get_swqos_client(rpc_url: String, commitment: CommitmentConfig, swqos_config: SwqosConfig) -> Arc<SwqosClient> {
         match swqos_config {
             SwqosConfig::Jito(auth_token, region, url) => {
-                let endpoint = SwqosConfig::get_endpoint(SwqosType::Jito, region, url);
+                let endpoint = SwqosConfig::endpoint_or_default(SwqosType::Jito, region, url);
                 let jito_client = JitoClient::new(
                     rpc_url.clone(),
                     endpoint,
@@ -150,7 +320,7 @@ impl SwqosConfig {
                 Arc::new(jito_client)
             }
             SwqosConfig::NextBlock(auth_token, region, url) => {
-                let endpoint = SwqosConfig::get_endpoint(SwqosType::NextBlock, region, url);
+                let endpoint = SwqosConfig::endpoint_or_default(SwqosType::NextBlock, region, url);
                 let nextblock_client = NextBlockClient::new(
                     rpc_url.clone(),
                     endpoint.to_string(),
@@ -159,7 +329,7 @@ impl SwqosConfig {
                 Arc::new(nextblock_client)
             },
             SwqosConfig::ZeroSlot(auth_token, region, url) => {
-                let endpoint = SwqosConfig::get_endpoint(SwqosType::ZeroSlot, region, url);
+                let endpoint = SwqosConfig::endpoint_or_default(SwqosType::ZeroSlot, region, url);
                 let zeroslot_client = ZeroSlotClient::new(
                     rpc_url.clone(),
                     endpoint.to_string(),
@@ -168,7 +338,7 @@ impl SwqosConfig {
                 Arc::new(zeroslot_client)
             },
             SwqosConfig::Temporal(auth_token, region, url) => {  
-                let endpoint = SwqosConfig::get_endpoint(SwqosType::Temporal, region, url);
+                let endpoint = SwqosConfig::endpoint_or_default(SwqosType::Temporal, region, url);
                 let temporal_client = TemporalClient::new(
                     rpc_url.clone(),
                     endpoint.to_string(),
@@ -177,7 +347,7 @@ impl SwqosConfig {
                 Arc::new(temporal_client)
             },
             SwqosConfig::Bloxroute(auth_token, region, url) => { 
-                let endpoint = SwqosConfig::get_endpoint(SwqosType::Bloxroute, region, url);
+                let endpoint = SwqosConfig::endpoint_or_default(SwqosType::Bloxroute, region, url);
                 let bloxroute_client = BloxrouteClient::new(
                     rpc_url.clone(),
                     endpoint.to_string(),
@@ -186,7 +356,7 @@ impl SwqosConfig {
                 Arc::new(bloxroute_client)
             },
             SwqosConfig::Node1(auth_token, region, url) => {
-                let endpoint = SwqosConfig::get_endpoint(SwqosType::Node1, region, url);
+                let endpoint = SwqosConfig::endpoint_or_default(SwqosType::Node1, region, url);
                 let node1_client = Node1Client::new(
                     rpc_url.clone(),
                     endpoint.to_string(),
@@ -195,7 +365,7 @@ impl SwqosConfig {
                 Arc::new(node1_client)
             },
             SwqosConfig::FlashBlock(auth_token, region, url) => {
-                let endpoint = SwqosConfig::get_endpoint(SwqosType::FlashBlock, region, url);
+                let endpoint = SwqosConfig::endpoint_or_default(SwqosType::FlashBlock, region, url);
                 let flashblock_client = FlashBlockClient::new(
                     rpc_url.clone(),
                     endpoint.to_string(),
@@ -204,7 +374,7 @@ impl SwqosConfig {
                 Arc::new(flashblock_client)
             },
             SwqosConfig::BlockRazor(auth_token, region, url) => {
-                let endpoint = SwqosConfig::get_endpoint(SwqosType::BlockRazor, region, url);
+                let endpoint = SwqosConfig::endpoint_or_default(SwqosType::BlockRazor, region, url);
                 let blockrazor_client = BlockRazorClient::new(
                     rpc_url.clone(),
                     endpoint.to_string(),
@@ -213,7 +383,7 @@ impl SwqosConfig {
                 Arc::new(blockrazor_client)
             },
             SwqosConfig::Astralane(auth_token, region, url) => {
-                let endpoint = SwqosConfig::get_endpoint(SwqosType::Astralane, region, url);
+                let endpoint = SwqosConfig::endpoint_or_default(SwqosType::Astralane, region, url);
                 let astralane_client = AstralaneClient::new(
                     rpc_url.clone(),
                     endpoint.to_string(),
@@ -221,6 +391,7 @@ impl SwqosConfig {
                 );
                 Arc::new(astralane_client)
             },
+            SwqosConfig::External(client) => client,
             SwqosConfig::Default(endpoint) => {
                 let rpc = SolanaRpcClient::new_with_commitment(
                     endpoint,
@@ -231,4 +402,68 @@ impl SwqosConfig {
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const ALL_TRADE_TYPES: [(TradeType, &str); 4] = [
+        (TradeType::Create, "Create"),
+        (TradeType::CreateAndBuy, "Create and Buy"),
+        (TradeType::Buy, "Buy"),
+        (TradeType::Sell, "Sell"),
+    ];
+
+    #[test]
+    fn trade_type_reaches_every_send_transaction_body() {
+        type BodyFn = fn(TradeType, &str) -> serde_json::Value;
+        let providers: [(&str, BodyFn, serde_json::Value); 5] = [
+            ("jito", JitoClient::send_transaction_body, json!([{ "encoding": "base64" }])),
+            (
+                "0slot",
+                ZeroSlotClient::send_transaction_body,
+                json!([{ "encoding": "base64", "skipPreflight": true }]),
+            ),
+            ("nozomi", TemporalClient::send_transaction_body, json!([{ "encoding": "base64" }])),
+            (
+                "node1",
+                Node1Client::send_transaction_body,
+                json!([{ "encoding": "base64", "skipPreflight": true }]),
+            ),
+            (
+                "astralane",
+                AstralaneClient::send_transaction_body,
+                json!([{ "encoding": "base64", "skipPreflight": true }, { "mevProtect": false }]),
+            ),
+        ];
+        for (provider, body, options) in providers {
+            for (trade_type, expected) in ALL_TRADE_TYPES {
+                let mut params = vec![json!("dHg=")];
+                params.extend(options.as_array().unwrap().iter().cloned());
+                assert_eq!(
+                    body(trade_type, "dHg="),
+                    json!({
+                        "jsonrpc": "2.0",
+                        "id": expected,
+                        "method": "sendTransaction",
+                        "params": params,
+                    }),
+                    "{provider} {trade_type}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn trade_type_reaches_the_jito_bundle_body() {
+        let txs = vec!["dHgx".to_string(), "dHgy".to_string()];
+        for (trade_type, expected) in ALL_TRADE_TYPES {
+            let body = JitoClient::send_bundle_body(trade_type, &txs);
+            assert_eq!(body["id"], expected);
+            assert_eq!(body["method"], "sendBundle");
+            assert_eq!(body["params"], json!([["dHgx", "dHgy"], { "encoding": "base64" }]));
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Latency based region selection for `SwqosRegion::Auto`
+//!
+//! Every region a provider serves is probed with a few HTTP requests; the region with the lowest
+//! median round trip wins. Results are cached per provider for `REGION_PROBE_TTL`.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use reqwest::Client;
+use serde::Serialize;
+
+use super::{SwqosRegion, SwqosType};
+use crate::constants::swqos::swqos_endpoint;
+
+/// How long a probe result is reused
+pub const REGION_PROBE_TTL: Duration = Duration::from_secs(600);
+/// Requests sent to each region per probe
+pub const REGION_PROBE_SAMPLES: usize = 3;
+const REGION_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Regions `Auto` picks from
+pub const CONCRETE_REGIONS: [SwqosRegion; 7] = [
+    SwqosRegion::NewYork,
+    SwqosRegion::Frankfurt,
+    SwqosRegion::Amsterdam,
+    SwqosRegion::SLC,
+    SwqosRegion::Tokyo,
+    SwqosRegion::London,
+    SwqosRegion::LosAngeles,
+];
+
+lazy_static::lazy_static! {
+    static ref PROBES: Mutex<HashMap<SwqosType, RegionProbe>> = Mutex::new(HashMap::new());
+}
+
+/// Median latency of one region of a provider, `None` if no request got a response
+#[derive(Debug, Clone, Serialize)]
+pub struct RegionLatency {
+    pub region: SwqosRegion,
+    pub median: Option<Duration>,
+}
+
+/// Outcome of probing every region of a provider
+#[derive(Debug, Clone, Serialize)]
+pub struct RegionProbe {
+    pub swqos_type: SwqosType,
+    /// Fastest region, `SwqosRegion::Default` when no region answered
+    pub selected: SwqosRegion,
+    pub latencies: Vec<RegionLatency>,
+    #[serde(skip)]
+    pub measured_at: Instant,
+}
+
+/// Region `Auto` resolves to, probing when the cached result is missing or stale
+pub async fn resolve_auto_region(swqos_type: &SwqosType) -> SwqosRegion {
+    if let Some(region) = cached_region(swqos_type) {
+        return region;
+    }
+    probe_regions(swqos_type).await.selected
+}
+
+/// Cached `Auto` region if it is younger than `REGION_PROBE_TTL`
+pub fn cached_region(swqos_type: &SwqosType) -> Option<SwqosRegion> {
+    PROBES
+        .lock()
+        .get(swqos_type)
+        .filter(|probe| probe.measured_at.elapsed() < REGION_PROBE_TTL)
+        .map(|probe| probe.selected.clone())
+}
+
+/// Last probe result of every provider
+pub fn region_probes() -> Vec<RegionProbe> {
+    PROBES.lock().values().cloned().collect()
+}
+
+/// Probe every region the provider serves now and cache the result
+pub async fn probe_regions(swqos_type: &SwqosType) -> RegionProbe {
+    let client = Client::builder().timeout(REGION_PROBE_TIMEOUT).build().unwrap_or_default();
+    let probes = CONCRETE_REGIONS.iter().filter_map(|region| {
+        let endpoint = swqos_endpoint(swqos_type, region)?;
+        let client = client.clone();
+        Some(async move {
+            RegionLatency {
+                region: region.clone(),
+                median: median_latency(&client, endpoint).await,
+            }
+        })
+    });
+    let latencies = futures::future::join_all(probes).await;
+    let selected = latencies
+        .iter()
+        .filter_map(|latency| latency.median.map(|median| (median, &latency.region)))
+        .min_by_key(|(median, _)| *median)
+        .map(|(_, region)| region.clone())
+        .unwrap_or(SwqosRegion::Default);
+
+    let probe = RegionProbe {
+        swqos_type: swqos_type.clone(),
+        selected,
+        latencies,
+        measured_at: Instant::now(),
+    };
+    PROBES.lock().insert(swqos_type.clone(), probe.clone());
+    probe
+}
+
+/// Median time until response headers arrive; any HTTP status counts as an answer
+async fn median_latency(client: &Client, endpoint: &str) -> Option<Duration> {
+    let mut samples = Vec::with_capacity(REGION_PROBE_SAMPLES);
+    for _ in 0..REGION_PROBE_SAMPLES {
+        let start = Instant::now();
+        if client.get(endpoint).send().await.is_ok() {
+            samples.push(start.elapsed());
+        }
+    }
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_unstable();
+    Some(samples[samples.len() / 2])
+}
@@ -1,13 +1,18 @@
 use std::{sync::Arc, time::Instant};
 
 use solana_client::rpc_config::RpcSendTransactionConfig;
-use solana_sdk::{commitment_config::CommitmentLevel, transaction::VersionedTransaction};
+use solana_sdk::{
+    commitment_config::CommitmentLevel, signature::Signature, transaction::VersionedTransaction,
+};
 use solana_transaction_status::UiTransactionEncoding;
 
 use crate::swqos::SwqosClientTrait;
 use crate::{
     common::SolanaRpcClient,
-    swqos::{common::poll_transaction_confirmation, SwqosType, TradeType},
+    swqos::{
+        common::poll_transaction_level, confirmation::WaitLevel, timing::SubmitTimer, SwqosType,
+        TradeType,
+    },
 };
 use anyhow::Result;
 
@@ -22,7 +27,9 @@ impl SwqosClientTrait for SolRpcClient {
         &self,
         trade_type: TradeType,
         transaction: &VersionedTransaction,
-    ) -> Result<()> {
+    ) -> Result<Signature> {
+        let start_time = Instant::now();
+        let mut timer = SubmitTimer::start(SwqosType::Default);
         let signature = self
             .rpc_client
             .send_transaction_with_config(
@@ -36,9 +43,25 @@ impl SwqosClientTrait for SolRpcClient {
                 },
             )
             .await?;
+        timer.responded();
+        timer.succeed();
+        println!(" rpc {} submitted: {:?}", trade_type, start_time.elapsed());
+
+        Ok(signature)
+    }
+
+    async fn confirm_transaction(&self, trade_type: TradeType, signature: Signature) -> Result<()> {
+        self.confirm_transaction_at_level(trade_type, signature, WaitLevel::Confirmed).await
+    }
 
+    async fn confirm_transaction_at_level(
+        &self,
+        trade_type: TradeType,
+        signature: Signature,
+        level: WaitLevel,
+    ) -> Result<()> {
         let start_time = Instant::now();
-        match poll_transaction_confirmation(&self.rpc_client, signature).await {
+        match poll_transaction_level(&self.rpc_client, signature, level).await {
             Ok(_) => (),
             Err(e) => {
                 println!(" signature: {:?}", signature);
@@ -63,13 +86,18 @@ impl SwqosClientTrait for SolRpcClient {
         Ok(())
     }
 
-    fn get_tip_account(&self) -> Result<String> {
-        Ok("".to_string())
+    fn get_tip_account(&self) -> Option<String> {
+        None
     }
 
     fn get_swqos_type(&self) -> SwqosType {
         SwqosType::Default
     }
+
+    async fn health_check(&self) -> Result<()> {
+        self.rpc_client.get_health().await?;
+        Ok(())
+    }
 }
 
 impl SolRpcClient {
@@ -0,0 +1,66 @@
+//! Stand-in swqos client for unit tests
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use solana_sdk::{pubkey::Pubkey, signature::Signature, transaction::VersionedTransaction};
+
+use super::{SwqosClientTrait, SwqosType, TradeType};
+
+/// Client that accepts every transaction without sending it anywhere
+pub(crate) struct StubClient {
+    swqos_type: SwqosType,
+    tip_account: Option<String>,
+    /// Transactions handed to `send_transaction`, in order
+    pub sent: Mutex<Vec<VersionedTransaction>>,
+}
+
+impl StubClient {
+    pub fn new(swqos_type: SwqosType) -> Self {
+        Self { swqos_type, tip_account: None, sent: Mutex::new(Vec::new()) }
+    }
+
+    /// Pay tips to `tip_account`, without one the client takes no tip
+    pub fn with_tip_account(mut self, tip_account: Pubkey) -> Self {
+        self.tip_account = Some(tip_account.to_string());
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl SwqosClientTrait for StubClient {
+    async fn send_transaction(
+        &self,
+        _trade_type: TradeType,
+        transaction: &VersionedTransaction,
+    ) -> Result<Signature> {
+        self.sent.lock().push(transaction.clone());
+        Ok(transaction.signatures[0])
+    }
+
+    async fn confirm_transaction(
+        &self,
+        _trade_type: TradeType,
+        _signature: Signature,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    async fn send_transactions(
+        &self,
+        trade_type: TradeType,
+        transactions: &Vec<VersionedTransaction>,
+    ) -> Result<()> {
+        for transaction in transactions {
+            self.send_transaction(trade_type, transaction).await?;
+        }
+        Ok(())
+    }
+
+    fn get_tip_account(&self) -> Option<String> {
+        self.tip_account.clone()
+    }
+
+    fn get_swqos_type(&self) -> SwqosType {
+        self.swqos_type.clone()
+    }
+}
@@ -0,0 +1,243 @@
+//! Per-provider timing of swqos submissions
+//!
+//! Every built-in client records the HTTP round trip of each submission, failed ones included,
+//! split into time to first byte (request written, response headers received), body read and
+//! parse. Confirmation waits are recorded separately. The last `TIMING_WINDOW` samples of each
+//! provider are kept for p50 / p95 queries through `latency_stats`, running totals are kept in
+//! `metrics`.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::{metrics, SwqosType};
+
+/// Samples kept per provider
+pub const TIMING_WINDOW: usize = 256;
+/// A connection used within this window is assumed to still be pooled (the shortest pool idle
+/// timeout among the built-in clients)
+const WARM_CONNECTION_WINDOW: Duration = Duration::from_secs(30);
+
+lazy_static::lazy_static! {
+    static ref TIMINGS: Mutex<HashMap<SwqosType, ProviderTimings>> = Mutex::new(HashMap::new());
+}
+
+/// Timing of one submission round trip
+#[derive(Debug, Clone, Serialize)]
+pub struct SubmitTiming {
+    /// Whether a pooled connection was likely reused. reqwest does not expose connection reuse,
+    /// this is inferred from the provider's previous request finishing less than 30s earlier.
+    pub connection_reused: bool,
+    /// Request sent until response headers arrived, `None` if no response came back
+    pub time_to_first_byte: Option<Duration>,
+    /// Response body read, `None` if the body was not read
+    pub body_read: Option<Duration>,
+    /// Response parsing until the submission succeeded or failed
+    pub parse: Duration,
+    pub total: Duration,
+    pub success: bool,
+}
+
+#[derive(Default)]
+struct ProviderTimings {
+    submits: VecDeque<SubmitTiming>,
+    confirmations: VecDeque<(Duration, bool)>,
+    last_request_at: Option<Instant>,
+}
+
+impl ProviderTimings {
+    fn push_submit(&mut self, timing: SubmitTiming) {
+        if self.submits.len() == TIMING_WINDOW {
+            self.submits.pop_front();
+        }
+        self.submits.push_back(timing);
+    }
+
+    fn push_confirmation(&mut self, elapsed: Duration, success: bool) {
+        if self.confirmations.len() == TIMING_WINDOW {
+            self.confirmations.pop_front();
+        }
+        self.confirmations.push_back((elapsed, success));
+    }
+}
+
+/// Rolling p50 / p95 of one provider over the last `TIMING_WINDOW` samples
+#[derive(Debug, Clone, Serialize)]
+pub struct SwqosLatencyStats {
+    pub swqos_type: SwqosType,
+    pub submit_samples: usize,
+    pub submit_failures: usize,
+    pub connection_reuse_ratio: f64,
+    pub time_to_first_byte_p50: Option<Duration>,
+    pub time_to_first_byte_p95: Option<Duration>,
+    pub parse_p50: Option<Duration>,
+    pub parse_p95: Option<Duration>,
+    pub submit_total_p50: Option<Duration>,
+    pub submit_total_p95: Option<Duration>,
+    pub confirmation_samples: usize,
+    pub confirmation_failures: usize,
+    pub confirmation_p50: Option<Duration>,
+    pub confirmation_p95: Option<Duration>,
+    /// Most recent submission
+    pub last_submit: Option<SubmitTiming>,
+}
+
+/// Latency stats of every provider that submitted at least once
+pub fn latency_stats() -> Vec<SwqosLatencyStats> {
+    let timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+    timings.iter().map(|(swqos_type, provider)| stats_for(swqos_type, provider)).collect()
+}
+
+/// Latency stats of one provider, `None` before its first sample
+pub fn provider_latency_stats(swqos_type: &SwqosType) -> Option<SwqosLatencyStats> {
+    let timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+    timings.get(swqos_type).map(|provider| stats_for(swqos_type, provider))
+}
+
+/// Last `n` submissions of one provider, oldest first
+///
+/// A submission is recorded before `send_transaction` returns, so a caller with a single
+/// submission in flight per provider finds its own timing last.
+pub fn recent_submit_timings(swqos_type: &SwqosType, n: usize) -> Vec<SubmitTiming> {
+    let timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+    timings
+        .get(swqos_type)
+        .map(|provider| {
+            provider
+                .submits
+                .iter()
+                .skip(provider.submits.len().saturating_sub(n))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Record how long a confirmation wait took
+pub fn record_confirmation(swqos_type: SwqosType, elapsed: Duration, success: bool) {
+    metrics::record_confirmation(&swqos_type, success);
+    let mut timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+    timings.entry(swqos_type).or_default().push_confirmation(elapsed, success);
+}
+
+fn stats_for(swqos_type: &SwqosType, provider: &ProviderTimings) -> SwqosLatencyStats {
+    let submits = &provider.submits;
+    let reused = submits.iter().filter(|t| t.connection_reused).count();
+    let confirmed: Vec<Duration> = provider.confirmations.iter().map(|(d, _)| *d).collect();
+    SwqosLatencyStats {
+        swqos_type: swqos_type.clone(),
+        submit_samples: submits.len(),
+        submit_failures: submits.iter().filter(|t| !t.success).count(),
+        connection_reuse_ratio: if submits.is_empty() {
+            0.0
+        } else {
+            reused as f64 / submits.len() as f64
+        },
+        time_to_first_byte_p50: percentile(submits.iter().filter_map(|t| t.time_to_first_byte), 50),
+        time_to_first_byte_p95: percentile(submits.iter().filter_map(|t| t.time_to_first_byte), 95),
+        parse_p50: percentile(submits.iter().map(|t| t.parse), 50),
+        parse_p95: percentile(submits.iter().map(|t| t.parse), 95),
+        submit_total_p50: percentile(submits.iter().map(|t| t.total), 50),
+        submit_total_p95: percentile(submits.iter().map(|t| t.total), 95),
+        confirmation_samples: confirmed.len(),
+        confirmation_failures: provider.confirmations.iter().filter(|(_, ok)| !ok).count(),
+        confirmation_p50: percentile(confirmed.iter().copied(), 50),
+        confirmation_p95: percentile(confirmed.iter().copied(), 95),
+        last_submit: submits.back().cloned(),
+    }
+}
+
+/// Nearest-rank percentile
+pub fn percentile<T: Ord + Copy>(samples: impl Iterator<Item = T>, pct: usize) -> Option<T> {
+    let mut samples: Vec<T> = samples.collect();
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_unstable();
+    let rank = (samples.len() * pct).div_ceil(100).max(1);
+    Some(samples[rank - 1])
+}
+
+/// Times one submission; records a failure when dropped before `succeed`
+pub struct SubmitTimer {
+    swqos_type: SwqosType,
+    started_at: Instant,
+    connection_reused: bool,
+    time_to_first_byte: Option<Duration>,
+    body_read: Option<Duration>,
+    parse_started_at: Option<Instant>,
+    success: bool,
+}
+
+impl SubmitTimer {
+    pub fn start(swqos_type: SwqosType) -> Self {
+        let now = Instant::now();
+        let connection_reused = {
+            let timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+            timings
+                .get(&swqos_type)
+                .and_then(|provider| provider.last_request_at)
+                .is_some_and(|at| now.duration_since(at) < WARM_CONNECTION_WINDOW)
+        };
+        Self {
+            swqos_type,
+            started_at: now,
+            connection_reused,
+            time_to_first_byte: None,
+            body_read: None,
+            parse_started_at: None,
+            success: false,
+        }
+    }
+
+    /// Send the request and read the response body, timing both
+    pub async fn send(&mut self, request: reqwest::RequestBuilder) -> Result<String> {
+        let sent_at = Instant::now();
+        let response = request.send().await?;
+        let headers_at = Instant::now();
+        self.time_to_first_byte = Some(headers_at - sent_at);
+        let text = response.text().await?;
+        let body_at = Instant::now();
+        self.body_read = Some(body_at - headers_at);
+        self.parse_started_at = Some(body_at);
+        Ok(text)
+    }
+
+    /// Mark a response received by a client that does not go through `send`
+    pub fn responded(&mut self) {
+        let now = Instant::now();
+        self.time_to_first_byte = Some(now - self.started_at);
+        self.parse_started_at = Some(now);
+    }
+
+    /// Mark the submission accepted by the provider
+    pub fn succeed(&mut self) {
+        self.success = true;
+    }
+}
+
+impl Drop for SubmitTimer {
+    fn drop(&mut self) {
+        let now = Instant::now();
+        let timing = SubmitTiming {
+            connection_reused: self.connection_reused,
+            time_to_first_byte: self.time_to_first_byte,
+            body_read: self.body_read,
+            parse: self.parse_started_at.map(|at| now - at).unwrap_or_default(),
+            total: now - self.started_at,
+            success: self.success,
+        };
+        metrics::record_submission(&self.swqos_type, timing.total, timing.success);
+        let mut timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+        let provider = timings.entry(self.swqos_type.clone()).or_default();
+        provider.push_submit(timing);
+        if self.time_to_first_byte.is_some() {
+            provider.last_request_at = Some(now);
+        }
+    }
+}
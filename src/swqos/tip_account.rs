@@ -0,0 +1,102 @@
+//! Tips paid to an account of the caller's choice instead of the provider's published ones
+//!
+//! The built-in clients pick a random account of their provider's tip account list for every
+//! transaction. `TradeConfig::with_tip_account` pins one, e.g. to reconcile tips in an explorer;
+//! `SolanaTrade::new` then wraps the provider's clients in a `TipAccountOverride`.
+
+use std::{sync::Arc, time::Instant};
+
+use anyhow::Result;
+use solana_sdk::{signature::Signature, transaction::VersionedTransaction};
+
+use super::{
+    auth::SwqosAuthError, confirmation::WaitLevel, SwqosClient, SwqosClientTrait, SwqosType,
+    TradeType,
+};
+
+/// Client that pays tips to `tip_account` and leaves everything else to the client it wraps
+pub struct TipAccountOverride {
+    inner: Arc<SwqosClient>,
+    tip_account: String,
+}
+
+impl TipAccountOverride {
+    pub fn new(inner: Arc<SwqosClient>, tip_account: String) -> Self {
+        Self { inner, tip_account }
+    }
+
+    /// `client` paying its tips to `tip_account`
+    pub fn wrap(client: Arc<SwqosClient>, tip_account: String) -> Arc<SwqosClient> {
+        Arc::new(Self::new(client, tip_account))
+    }
+}
+
+#[async_trait::async_trait]
+impl SwqosClientTrait for TipAccountOverride {
+    async fn send_transaction(
+        &self,
+        trade_type: TradeType,
+        transaction: &VersionedTransaction,
+    ) -> Result<Signature> {
+        self.inner.send_transaction(trade_type, transaction).await
+    }
+
+    async fn confirm_transaction(&self, trade_type: TradeType, signature: Signature) -> Result<()> {
+        self.inner.confirm_transaction(trade_type, signature).await
+    }
+
+    async fn confirm_transaction_at_level(
+        &self,
+        trade_type: TradeType,
+        signature: Signature,
+        level: WaitLevel,
+    ) -> Result<()> {
+        self.inner.confirm_transaction_at_level(trade_type, signature, level).await
+    }
+
+    async fn confirm_transaction_until(
+        &self,
+        trade_type: TradeType,
+        signature: Signature,
+        level: WaitLevel,
+        deadline: Option<Instant>,
+    ) -> Result<()> {
+        self.inner.confirm_transaction_until(trade_type, signature, level, deadline).await
+    }
+
+    async fn send_transactions(
+        &self,
+        trade_type: TradeType,
+        transactions: &Vec<VersionedTransaction>,
+    ) -> Result<()> {
+        self.inner.send_transactions(trade_type, transactions).await
+    }
+
+    fn supports_bundles(&self) -> bool {
+        self.inner.supports_bundles()
+    }
+
+    async fn send_bundle(
+        &self,
+        trade_type: TradeType,
+        transactions: &[VersionedTransaction],
+    ) -> Result<String> {
+        self.inner.send_bundle(trade_type, transactions).await
+    }
+
+    fn get_tip_account(&self) -> Option<String> {
+        Some(self.tip_account.clone())
+    }
+
+    fn get_swqos_type(&self) -> SwqosType {
+        self.inner.get_swqos_type()
+    }
+
+    async fn probe_auth(&self) -> Result<(), SwqosAuthError> {
+        self.inner.probe_auth().await
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        self.inner.health_check().await
+    }
+}
@@ -0,0 +1,301 @@
+use std::fmt;
+
+use anyhow::anyhow;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+use crate::constants::{SYSTEM_PROGRAM, TOKEN_PROGRAM, TOKEN_PROGRAM_2022};
+
+// System program instruction tags
+const SYSTEM_CREATE_ACCOUNT: u32 = 0;
+const SYSTEM_CREATE_ACCOUNT_WITH_SEED: u32 = 3;
+// Token program instruction tags
+const TOKEN_INITIALIZE_ACCOUNT: u8 = 1;
+const TOKEN_INITIALIZE_ACCOUNT2: u8 = 16;
+const TOKEN_INITIALIZE_ACCOUNT3: u8 = 18;
+
+/// What to do when the accounts a trade creates do not match the accounts its swap uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CreatedAccountCheck {
+    Off,
+    /// Log the issues and send the trade
+    #[default]
+    Warn,
+    /// Reject the trade before it is sent
+    Error,
+}
+
+/// Token account created by an instruction of a trade
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatedAccount {
+    pub address: Pubkey,
+    /// Owner and mint, when the transaction initializes the account
+    pub owner: Option<Pubkey>,
+    pub mint: Option<Pubkey>,
+    /// Index of the creating instruction
+    pub instruction_index: usize,
+    /// Created through `create_account_with_seed` or `create_account` instead of the ATA program
+    pub seeded: bool,
+}
+
+impl fmt::Display for CreatedAccount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.seeded { "account" } else { "ATA" };
+        write!(f, "{} {} (instruction {}", kind, self.address, self.instruction_index)?;
+        if let (Some(owner), Some(mint)) = (self.owner, self.mint) {
+            write!(f, ", owner {} mint {}", owner, mint)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CreatedAccountIssue {
+    /// No instruction outside the system, token and ATA programs references the account, so
+    /// its rent is spent on an account the swap never uses
+    Unreferenced(CreatedAccount),
+    /// The same account is created twice, or two accounts for the same owner and mint
+    Duplicate { first: CreatedAccount, second: CreatedAccount },
+}
+
+impl fmt::Display for CreatedAccountIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CreatedAccountIssue::Unreferenced(account) => {
+                write!(f, "{} is created but not used by the swap", account)
+            }
+            CreatedAccountIssue::Duplicate { first, second } => {
+                write!(f, "{} duplicates {}", second, first)
+            }
+        }
+    }
+}
+
+/// Accounts created by `instructions` and how they conflict with the accounts the swap uses
+pub fn find_created_account_issues(instructions: &[Instruction]) -> Vec<CreatedAccountIssue> {
+    let ata_program = spl_associated_token_account::id();
+    let is_housekeeping = |program_id: &Pubkey| {
+        [SYSTEM_PROGRAM, TOKEN_PROGRAM, TOKEN_PROGRAM_2022, ata_program].contains(program_id)
+    };
+
+    let mut created: Vec<CreatedAccount> = Vec::new();
+    for (index, instruction) in instructions.iter().enumerate() {
+        if instruction.program_id == ata_program && instruction.accounts.len() >= 4 {
+            created.push(CreatedAccount {
+                address: instruction.accounts[1].pubkey,
+                owner: Some(instruction.accounts[2].pubkey),
+                mint: Some(instruction.accounts[3].pubkey),
+                instruction_index: index,
+                seeded: false,
+            });
+        } else if instruction.program_id == SYSTEM_PROGRAM && instruction.accounts.len() >= 2 {
+            let tag =
+                instruction.data.get(..4).map(|tag| u32::from_le_bytes(tag.try_into().unwrap()));
+            if matches!(tag, Some(SYSTEM_CREATE_ACCOUNT | SYSTEM_CREATE_ACCOUNT_WITH_SEED)) {
+                created.push(CreatedAccount {
+                    address: instruction.accounts[1].pubkey,
+                    owner: None,
+                    mint: None,
+                    instruction_index: index,
+                    seeded: true,
+                });
+            }
+        } else if let Some((address, mint, owner)) = initialized_token_account(instruction) {
+            if let Some(account) =
+                created.iter_mut().rev().find(|account| account.address == address)
+            {
+                account.owner = Some(owner);
+                account.mint = Some(mint);
+            }
+        }
+    }
+
+    let mut issues = Vec::new();
+    for (i, account) in created.iter().enumerate() {
+        let duplicate = created[..i].iter().find(|first| {
+            first.address == account.address
+                || (first.owner.is_some()
+                    && first.mint.is_some()
+                    && first.owner == account.owner
+                    && first.mint == account.mint)
+        });
+        if let Some(first) = duplicate {
+            issues.push(CreatedAccountIssue::Duplicate {
+                first: first.clone(),
+                second: account.clone(),
+            });
+            continue;
+        }
+        let referenced = instructions.iter().any(|instruction| {
+            !is_housekeeping(&instruction.program_id)
+                && instruction.accounts.iter().any(|meta| meta.pubkey == account.address)
+        });
+        if !referenced {
+            issues.push(CreatedAccountIssue::Unreferenced(account.clone()));
+        }
+    }
+    issues
+}
+
+/// Run `find_created_account_issues` and warn or fail according to `check`
+pub fn check_created_accounts(
+    instructions: &[Instruction],
+    check: CreatedAccountCheck,
+) -> Result<(), anyhow::Error> {
+    if check == CreatedAccountCheck::Off {
+        return Ok(());
+    }
+    let issues = find_created_account_issues(instructions);
+    if issues.is_empty() {
+        return Ok(());
+    }
+    if check == CreatedAccountCheck::Error {
+        let issues: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+        return Err(anyhow!("Created accounts do not match the swap: {}", issues.join("; ")));
+    }
+    for issue in issues {
+        log::warn!(icon!("⚠️ ", "{}"), issue);
+    }
+    Ok(())
+}
+
+/// Token account, mint and owner of an `InitializeAccount` instruction
+fn initialized_token_account(instruction: &Instruction) -> Option<(Pubkey, Pubkey, Pubkey)> {
+    if instruction.program_id != TOKEN_PROGRAM && instruction.program_id != TOKEN_PROGRAM_2022 {
+        return None;
+    }
+    let account = instruction.accounts.first()?.pubkey;
+    let mint = instruction.accounts.get(1)?.pubkey;
+    match *instruction.data.first()? {
+        TOKEN_INITIALIZE_ACCOUNT => Some((account, mint, instruction.accounts.get(2)?.pubkey)),
+        TOKEN_INITIALIZE_ACCOUNT2 | TOKEN_INITIALIZE_ACCOUNT3 => {
+            let owner = Pubkey::try_from(instruction.data.get(1..33)?).ok()?;
+            Some((account, mint, owner))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::instruction::AccountMeta;
+
+    use super::*;
+    use crate::common::fast_fn::{
+        create_associated_token_account_idempotent_fast,
+        get_associated_token_address_with_program_id_fast,
+    };
+    use crate::common::seed::{create_associated_token_account_use_seed, set_rent};
+    use crate::constants::WSOL_TOKEN_ACCOUNT;
+    use crate::trading::common::wsol_manager::{create_wsol_ata, handle_wsol};
+
+    const SPL_TOKEN_RENT: u64 = 2_039_280;
+
+    /// Swap instruction of an unknown program using `accounts`
+    fn swap(accounts: &[Pubkey]) -> Instruction {
+        Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            accounts.iter().map(|account| AccountMeta::new(*account, false)).collect(),
+        )
+    }
+
+    #[test]
+    fn seeded_account_next_to_the_ata_the_swap_uses_is_a_duplicate() {
+        set_rent(false, SPL_TOKEN_RENT);
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let ata = get_associated_token_address_with_program_id_fast(&payer, &mint, &TOKEN_PROGRAM);
+        let mut instructions =
+            create_associated_token_account_idempotent_fast(&payer, &payer, &mint, &TOKEN_PROGRAM);
+        instructions.extend(
+            create_associated_token_account_use_seed(&payer, &payer, &mint, &TOKEN_PROGRAM)
+                .unwrap(),
+        );
+        instructions.push(swap(&[payer, ata, mint]));
+
+        let issues = find_created_account_issues(&instructions);
+
+        let [CreatedAccountIssue::Duplicate { first, second }] = issues.as_slice() else {
+            panic!("expected one duplicate, got {:?}", issues);
+        };
+        assert_eq!((first.address, first.seeded), (ata, false));
+        assert!(second.seeded);
+        assert_eq!((second.owner, second.mint), (Some(payer), Some(mint)));
+    }
+
+    #[test]
+    fn seeded_account_the_swap_does_not_use_is_unreferenced() {
+        set_rent(false, SPL_TOKEN_RENT);
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let ata = get_associated_token_address_with_program_id_fast(&payer, &mint, &TOKEN_PROGRAM);
+        let mut instructions =
+            create_associated_token_account_use_seed(&payer, &payer, &mint, &TOKEN_PROGRAM)
+                .unwrap();
+        instructions.push(swap(&[payer, ata, mint]));
+
+        let issues = find_created_account_issues(&instructions);
+
+        let [CreatedAccountIssue::Unreferenced(account)] = issues.as_slice() else {
+            panic!("expected one unreferenced account, got {:?}", issues);
+        };
+        assert!(account.seeded);
+        assert_ne!(account.address, ata);
+    }
+
+    #[test]
+    fn wsol_ata_created_twice_is_a_duplicate() {
+        let payer = Pubkey::new_unique();
+        let wsol_ata = get_associated_token_address_with_program_id_fast(
+            &payer,
+            &WSOL_TOKEN_ACCOUNT,
+            &TOKEN_PROGRAM,
+        );
+        // `create_wsol_ata` together with a protocol that wraps the SOL itself
+        let mut instructions = create_wsol_ata(&payer);
+        instructions.extend(handle_wsol(&payer, 1_000_000));
+        instructions.push(swap(&[payer, wsol_ata]));
+
+        let issues = find_created_account_issues(&instructions);
+
+        let [CreatedAccountIssue::Duplicate { first, second }] = issues.as_slice() else {
+            panic!("expected one duplicate, got {:?}", issues);
+        };
+        assert_eq!((first.address, second.address), (wsol_ata, wsol_ata));
+        assert_eq!(second.instruction_index, 1);
+    }
+
+    #[test]
+    fn created_accounts_the_swap_uses_pass() {
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let ata = get_associated_token_address_with_program_id_fast(&payer, &mint, &TOKEN_PROGRAM);
+        let wsol_ata = get_associated_token_address_with_program_id_fast(
+            &payer,
+            &WSOL_TOKEN_ACCOUNT,
+            &TOKEN_PROGRAM,
+        );
+        let mut instructions: Vec<Instruction> = handle_wsol(&payer, 1_000_000).into_vec();
+        instructions.extend(create_associated_token_account_idempotent_fast(
+            &payer,
+            &payer,
+            &mint,
+            &TOKEN_PROGRAM,
+        ));
+        instructions.push(swap(&[payer, wsol_ata, ata]));
+
+        assert!(find_created_account_issues(&instructions).is_empty());
+    }
+
+    #[test]
+    fn check_warns_or_rejects_as_configured() {
+        let payer = Pubkey::new_unique();
+        let mut instructions = create_wsol_ata(&payer);
+        instructions.extend(create_wsol_ata(&payer));
+
+        assert!(check_created_accounts(&instructions, CreatedAccountCheck::Off).is_ok());
+        assert!(check_created_accounts(&instructions, CreatedAccountCheck::Warn).is_ok());
+        let error = check_created_accounts(&instructions, CreatedAccountCheck::Error).unwrap_err();
+        assert!(error.to_string().contains("duplicates"), "{}", error);
+    }
+}
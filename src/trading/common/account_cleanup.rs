@@ -0,0 +1,242 @@
+//! Closing the payer's empty token accounts to reclaim their rent
+//!
+//! Accounts are found by the owner field of the token account data rather than by address, so
+//! the seeded accounts of `create_associated_token_account_use_seed` are closed together with
+//! the canonical ATAs.
+
+use anyhow::anyhow;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_hash::Hash;
+use solana_rpc_client_api::config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    instruction::Instruction,
+    packet::PACKET_DATA_SIZE,
+    program_option::COption,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    transaction::Transaction,
+};
+use spl_token_2022::{
+    extension::{transfer_fee::TransferFeeAmount, BaseStateWithExtensions, StateWithExtensions},
+    instruction::{burn_checked, close_account},
+    state::{Account as TokenAccount, AccountState},
+};
+
+use crate::{
+    common::{mint_cache::MintDecimalsCache, PriorityFee, SolanaRpcClient},
+    constants::{TOKEN_PROGRAM, TOKEN_PROGRAM_2022, WSOL_TOKEN_ACCOUNT},
+};
+
+/// Most accounts one cleanup transaction closes, fewer when the transaction would not fit
+pub const MAX_CLOSES_PER_TRANSACTION: usize = 20;
+/// Protocol name the compute unit price of cleanup transactions is looked up by in
+/// `PriorityFee::protocol_limits`, `rpc_unit_price` otherwise
+pub const TOKEN_ACCOUNT_CLEANUP_PROTOCOL: &str = "TokenAccountCleanup";
+/// Compute units budgeted per account, enough for a burn and the close
+const UNITS_PER_ACCOUNT: u32 = 10_000;
+/// Offset of the owner in token account data, the same for both token programs
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+
+/// Token account of the payer the cleanup closes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClosableTokenAccount {
+    pub address: Pubkey,
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    /// Balance burned before the close, 0 for an empty account
+    pub dust: u64,
+    /// Decimals of the mint, only read when `dust` is burned
+    pub decimals: u8,
+    /// Lamports the close returns to the payer
+    pub lamports: u64,
+}
+
+/// Outcome of `SolanaTrade::close_empty_token_accounts`
+#[derive(Debug, Clone, Default)]
+pub struct TokenAccountCleanup {
+    /// Accounts closed by the transactions of `signatures`
+    pub closed: Vec<ClosableTokenAccount>,
+    pub signatures: Vec<Signature>,
+    /// Lamports the closed accounts returned to the payer
+    pub rent_reclaimed: u64,
+    /// Error of each transaction that failed, its accounts stay open
+    pub errors: Vec<String>,
+}
+
+/// Token accounts of `owner` under both token programs that can be closed
+///
+/// Empty accounts are always included. With `burn_dust`, accounts holding less than one whole
+/// token are included too, their balance burned before the close. Frozen accounts, accounts
+/// another key may close, Token-2022 accounts holding withheld transfer fees and wSOL accounts
+/// are left out; `SolanaTrade::close_wsol` closes the wSOL ATA.
+pub async fn find_closable_token_accounts(
+    rpc: &SolanaRpcClient,
+    owner: &Pubkey,
+    burn_dust: bool,
+) -> Result<Vec<ClosableTokenAccount>, anyhow::Error> {
+    let mut closable = Vec::new();
+    for token_program in [TOKEN_PROGRAM, TOKEN_PROGRAM_2022] {
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                TOKEN_ACCOUNT_OWNER_OFFSET,
+                &owner.to_bytes(),
+            ))]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                data_slice: None,
+                commitment: None,
+                min_context_slot: None,
+            },
+            with_context: None,
+            sort_results: None,
+        };
+        let accounts = rpc.get_program_accounts_with_config(&token_program, config).await?;
+        for (address, account) in accounts {
+            // Mints matching the filter by chance do not unpack as token accounts
+            let Ok(state) = StateWithExtensions::<TokenAccount>::unpack(&account.data) else {
+                continue;
+            };
+            let base = state.base;
+            let withheld_fees = state
+                .get_extension::<TransferFeeAmount>()
+                .map_or(0, |fees| u64::from(fees.withheld_amount));
+            let foreign_close_authority =
+                base.close_authority.is_some() && base.close_authority != COption::Some(*owner);
+            if base.owner != *owner
+                || base.mint == WSOL_TOKEN_ACCOUNT
+                || base.is_native()
+                || base.state != AccountState::Initialized
+                || foreign_close_authority
+                || withheld_fees > 0
+                || (base.amount > 0 && !burn_dust)
+            {
+                continue;
+            }
+            let decimals = if base.amount > 0 {
+                let decimals = MintDecimalsCache::get_instance().fetch(rpc, &base.mint).await?;
+                if base.amount >= 10u64.saturating_pow(decimals as u32) {
+                    continue;
+                }
+                decimals
+            } else {
+                0
+            };
+            closable.push(ClosableTokenAccount {
+                address,
+                mint: base.mint,
+                token_program,
+                dust: base.amount,
+                decimals,
+                lamports: account.lamports,
+            });
+        }
+    }
+    Ok(closable)
+}
+
+/// Close `accounts`, burning their dust first, in transactions of at most
+/// `MAX_CLOSES_PER_TRANSACTION` accounts
+///
+/// A failed transaction is recorded in `errors` and the remaining ones are still sent.
+pub async fn close_token_accounts(
+    rpc: &SolanaRpcClient,
+    payer: &Keypair,
+    accounts: Vec<ClosableTokenAccount>,
+    priority_fee: &PriorityFee,
+) -> Result<TokenAccountCleanup, anyhow::Error> {
+    let mut batches = Vec::new();
+    let mut batch: Vec<ClosableTokenAccount> = Vec::new();
+    for account in accounts {
+        batch.push(account);
+        let too_large = batch.len() > MAX_CLOSES_PER_TRANSACTION
+            || transaction_size(payer, &batch, priority_fee)? > PACKET_DATA_SIZE;
+        if too_large && batch.len() > 1 {
+            batch.pop();
+            batches.push(std::mem::replace(&mut batch, vec![account]));
+        }
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+
+    let mut cleanup = TokenAccountCleanup::default();
+    for batch in batches {
+        let recent_blockhash = rpc.get_latest_blockhash().await?;
+        let transaction = cleanup_transaction(payer, &batch, priority_fee, recent_blockhash)?;
+        match rpc.send_and_confirm_transaction(&transaction).await {
+            Ok(signature) => {
+                cleanup.rent_reclaimed += batch.iter().map(|account| account.lamports).sum::<u64>();
+                cleanup.signatures.push(signature);
+                cleanup.closed.extend(batch);
+            }
+            Err(e) => {
+                cleanup.errors.push(format!("Closing {} token accounts failed: {}", batch.len(), e))
+            }
+        }
+    }
+    Ok(cleanup)
+}
+
+fn cleanup_instructions(
+    payer: &Pubkey,
+    accounts: &[ClosableTokenAccount],
+    priority_fee: &PriorityFee,
+) -> Result<Vec<Instruction>, anyhow::Error> {
+    let (unit_price, _) = priority_fee.unit_price_and_limit(TOKEN_ACCOUNT_CLEANUP_PROTOCOL, true);
+    let mut instructions = vec![
+        ComputeBudgetInstruction::set_compute_unit_price(unit_price),
+        ComputeBudgetInstruction::set_compute_unit_limit(UNITS_PER_ACCOUNT * accounts.len() as u32),
+    ];
+    for account in accounts {
+        if account.dust > 0 {
+            instructions.push(burn_checked(
+                &account.token_program,
+                &account.address,
+                &account.mint,
+                payer,
+                &[],
+                account.dust,
+                account.decimals,
+            )?);
+        }
+        instructions.push(close_account(
+            &account.token_program,
+            &account.address,
+            payer,
+            payer,
+            &[],
+        )?);
+    }
+    Ok(instructions)
+}
+
+fn cleanup_transaction(
+    payer: &Keypair,
+    accounts: &[ClosableTokenAccount],
+    priority_fee: &PriorityFee,
+    recent_blockhash: Hash,
+) -> Result<Transaction, anyhow::Error> {
+    let instructions = cleanup_instructions(&payer.pubkey(), accounts, priority_fee)?;
+    Ok(Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_blockhash,
+    ))
+}
+
+/// Serialized size of the transaction closing `accounts`
+fn transaction_size(
+    payer: &Keypair,
+    accounts: &[ClosableTokenAccount],
+    priority_fee: &PriorityFee,
+) -> Result<usize, anyhow::Error> {
+    let instructions = cleanup_instructions(&payer.pubkey(), accounts, priority_fee)?;
+    let transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
+    bincode::serialize(&transaction)
+        .map(|bytes| bytes.len())
+        .map_err(|e| anyhow!("Failed to serialize the cleanup transaction: {}", e))
+}
@@ -1,17 +1,137 @@
-use solana_sdk::{message::AddressLookupTableAccount, pubkey::Pubkey};
+use anyhow::{anyhow, Result};
+use solana_sdk::{
+    message::AddressLookupTableAccount, pubkey::Pubkey, signature::Keypair, signer::Signer,
+};
+use std::sync::Arc;
 
-use crate::common::address_lookup_cache::get_address_lookup_table_account;
+use crate::common::address_lookup_cache::{
+    get_address_lookup_table_account, AddressLookupTableCache,
+};
+pub use crate::common::address_lookup_manage::MAX_ADDRESSES_PER_EXTEND;
+use crate::common::address_lookup_manage::{create_lookup_table_with, extend_lookup_table_with};
+use crate::common::SolanaRpcClient;
+use crate::constants;
+use crate::trading::factory::DexType;
 
-/// Get address lookup table account list
-/// If lookup_table_key is provided, get the corresponding account, otherwise return empty list
-pub async fn get_address_lookup_table_accounts(
-    lookup_table_key: Option<Pubkey>,
-) -> Vec<AddressLookupTableAccount> {
-    match lookup_table_key {
-        Some(key) => {
-            let account = get_address_lookup_table_account(&key).await;
-            vec![account]
-        }
-        None => Vec::new(),
+/// Get the cached accounts of `lookup_table_keys`, in the same order
+///
+/// Fails with every key missing from the `AddressLookupTableCache`, load them with
+/// `AddressLookupTableCache::set_address_lookup_table` first.
+pub fn get_address_lookup_table_accounts(
+    lookup_table_keys: &[Pubkey],
+) -> Result<Vec<AddressLookupTableAccount>> {
+    let mut accounts = Vec::with_capacity(lookup_table_keys.len());
+    let mut missing = Vec::new();
+    for key in lookup_table_keys {
+        match get_address_lookup_table_account(key) {
+            Some(account) => accounts.push(account),
+            None => missing.push(key.to_string()),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(anyhow!(
+            "Address lookup tables not loaded in AddressLookupTableCache: {}",
+            missing.join(", ")
+        ));
+    }
+    Ok(accounts)
+}
+
+/// Make sure `accounts` are in an address lookup table owned by `payer`
+///
+/// Reuses a cached table with `payer` as authority, or creates one. Missing accounts are added
+/// through `extend_lookup_table_with`, which waits until they are usable and registers the
+/// refreshed table in the `AddressLookupTableCache`.
+///
+/// # Returns
+/// Returns the table address to add to `lookup_table_keys`
+pub async fn ensure_lookup_table(
+    rpc: Arc<SolanaRpcClient>,
+    payer: &Keypair,
+    accounts: Vec<Pubkey>,
+) -> Result<Pubkey> {
+    let cache = AddressLookupTableCache::get_instance();
+    let table_address = match cache.find_table_by_authority(&payer.pubkey()) {
+        Some(table) => table.key,
+        None => create_lookup_table_with(rpc.clone(), payer).await?.0,
+    };
+    extend_lookup_table_with(rpc, payer, table_address, accounts).await?;
+    Ok(table_address)
+}
+
+/// Static accounts the builders of `dex_type` reference for `mint`
+///
+/// Covers program ids, global and fee accounts, plus the accounts derivable from the mint
+/// (PumpFun bonding curve, Bonk WSOL pool and vaults). Pool based protocols also reference pool
+/// and vault accounts that depend on the chosen pool, add those from the protocol params.
+pub fn suggest_alt_accounts(dex_type: DexType, mint: &Pubkey) -> Vec<Pubkey> {
+    let mut accounts = vec![
+        constants::SYSTEM_PROGRAM,
+        constants::TOKEN_PROGRAM,
+        constants::TOKEN_PROGRAM_2022,
+        constants::WSOL_TOKEN_ACCOUNT,
+        spl_associated_token_account::ID,
+    ];
+    match dex_type {
+        DexType::PumpFun => {
+            use crate::instruction::utils::pumpfun::{accounts, get_bonding_curve_pda};
+            accounts.extend([
+                accounts::PUMPFUN,
+                accounts::GLOBAL_ACCOUNT,
+                accounts::FEE_RECIPIENT,
+                accounts::EVENT_AUTHORITY,
+                accounts::FEE_PROGRAM,
+                accounts::FEE_CONFIG,
+                accounts::GLOBAL_VOLUME_ACCUMULATOR,
+            ]);
+            accounts.extend(get_bonding_curve_pda(mint));
+        }
+        DexType::PumpSwap => {
+            use crate::instruction::utils::pumpswap::accounts;
+            accounts.extend([
+                accounts::AMM_PROGRAM,
+                accounts::GLOBAL_ACCOUNT,
+                accounts::FEE_RECIPIENT,
+                accounts::PROTOCOL_FEE_RECIPIENT,
+                accounts::EVENT_AUTHORITY,
+                accounts::FEE_PROGRAM,
+                accounts::GLOBAL_VOLUME_ACCUMULATOR,
+                accounts::get_fee_config(),
+            ]);
+        }
+        DexType::Bonk => {
+            use crate::instruction::utils::bonk::{accounts, get_pool_pda, get_vault_pda};
+            accounts.extend([
+                accounts::BONK,
+                accounts::AUTHORITY,
+                accounts::GLOBAL_CONFIG,
+                accounts::EVENT_AUTHORITY,
+            ]);
+            if let Some(pool) = get_pool_pda(mint, &constants::WSOL_TOKEN_ACCOUNT) {
+                accounts.push(pool);
+                accounts.extend(get_vault_pda(&pool, mint));
+                accounts.extend(get_vault_pda(&pool, &constants::WSOL_TOKEN_ACCOUNT));
+            }
+        }
+        DexType::RaydiumCpmm => {
+            use crate::instruction::utils::raydium_cpmm::accounts;
+            accounts.extend([accounts::RAYDIUM_CPMM, accounts::AUTHORITY]);
+        }
+        DexType::RaydiumClmm | DexType::RaydiumClmmV2 => {
+            accounts.push(crate::instruction::data::RAYDIUM_CLMM_PROGRAM);
+        }
+        DexType::RaydiumAmmV4 => {
+            use crate::instruction::utils::raydium_amm_v4::accounts;
+            accounts.extend([accounts::RAYDIUM_AMM_V4, accounts::AUTHORITY]);
+        }
+        DexType::MeteoraDlmm => {
+            use crate::instruction::utils::meteora_dlmm::accounts;
+            accounts.extend([accounts::METEORA_DLMM, accounts::EVENT_AUTHORITY]);
+        }
+        DexType::Jupiter => {
+            use crate::instruction::utils::jupiter::accounts;
+            accounts.extend([accounts::JUPITER, accounts::EVENT_AUTHORITY]);
+        }
     }
+    accounts
 }
@@ -10,7 +10,6 @@ struct ComputeBudgetCacheKey {
     data_size_limit: u32,
     unit_price: u64,
     unit_limit: u32,
-    is_buy: bool,
 }
 
 /// Global cache storing compute budget instructions
@@ -18,21 +17,19 @@ struct ComputeBudgetCacheKey {
 static COMPUTE_BUDGET_CACHE: Lazy<DashMap<ComputeBudgetCacheKey, SmallVec<[Instruction; 3]>>> =
     Lazy::new(|| DashMap::new());
 
+/// Compute budget instructions of a trade, `SetLoadedAccountsDataSizeLimit` is left out when
+/// `data_size_limit` is 0
 #[inline(always)]
 pub fn compute_budget_instructions(
     priority_fee: &PriorityFee,
     data_size_limit: u32,
+    protocol_name: &str,
     is_rpc: bool,
-    is_buy: bool,
 ) -> SmallVec<[Instruction; 3]> {
-    let (unit_price, unit_limit) = if is_rpc {
-        (priority_fee.rpc_unit_price, priority_fee.rpc_unit_limit)
-    } else {
-        (priority_fee.tip_unit_price, priority_fee.tip_unit_limit)
-    };
+    let (unit_price, unit_limit) = priority_fee.unit_price_and_limit(protocol_name, is_rpc);
 
     // Create cache key
-    let cache_key = ComputeBudgetCacheKey { data_size_limit, unit_price, unit_limit, is_buy };
+    let cache_key = ComputeBudgetCacheKey { data_size_limit, unit_price, unit_limit };
 
     // Try to get from cache first
     if let Some(cached_insts) = COMPUTE_BUDGET_CACHE.get(&cache_key) {
@@ -42,7 +39,7 @@ pub fn compute_budget_instructions(
     // Cache miss, generate new instructions
     let mut insts = SmallVec::<[Instruction; 3]>::new();
 
-    if is_buy {
+    if data_size_limit > 0 {
         insts.push(ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(data_size_limit));
     }
 
@@ -57,3 +54,91 @@ pub fn compute_budget_instructions(
 
     insts
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::trade::trade::DEFAULT_DATA_SIZE_LIMIT,
+        trading::core::options::{BuyOptions, SellOptions},
+    };
+
+    fn priority_fee() -> PriorityFee {
+        PriorityFee {
+            tip_unit_limit: 80_000,
+            tip_unit_price: 500_000,
+            rpc_unit_limit: 100_000,
+            rpc_unit_price: 1_000_000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn data_size_limit_comes_first_when_set() {
+        let instructions =
+            compute_budget_instructions(&priority_fee(), 256 * 1024, "PumpFun", false);
+        assert_eq!(
+            instructions.into_vec(),
+            vec![
+                ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(256 * 1024),
+                ComputeBudgetInstruction::set_compute_unit_price(500_000),
+                ComputeBudgetInstruction::set_compute_unit_limit(80_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_data_size_limit_when_none() {
+        let data_size_limit = BuyOptions::default().with_data_size_limit(None).data_size_limit;
+        let instructions = compute_budget_instructions(
+            &priority_fee(),
+            data_size_limit.unwrap_or(0),
+            "PumpFun",
+            true,
+        );
+        assert_eq!(
+            instructions.into_vec(),
+            vec![
+                ComputeBudgetInstruction::set_compute_unit_price(1_000_000),
+                ComputeBudgetInstruction::set_compute_unit_limit(100_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn sells_default_to_512kb() {
+        let data_size_limit = SellOptions::default().data_size_limit;
+        assert_eq!(data_size_limit, Some(DEFAULT_DATA_SIZE_LIMIT));
+        assert_eq!(DEFAULT_DATA_SIZE_LIMIT, 512 * 1024);
+        let instructions = compute_budget_instructions(
+            &priority_fee(),
+            data_size_limit.unwrap_or(0),
+            "RaydiumClmm",
+            false,
+        );
+        assert_eq!(
+            instructions.into_vec(),
+            vec![
+                ComputeBudgetInstruction::set_loaded_accounts_data_size_limit(512 * 1024),
+                ComputeBudgetInstruction::set_compute_unit_price(500_000),
+                ComputeBudgetInstruction::set_compute_unit_limit(80_000),
+            ]
+        );
+    }
+
+    #[test]
+    fn protocol_limits_replace_tip_and_rpc_values() {
+        let mut priority_fee = priority_fee();
+        priority_fee.protocol_limits.insert("RaydiumClmm".to_string(), (300_000, 2_000_000));
+        for is_rpc in [false, true] {
+            let instructions = compute_budget_instructions(&priority_fee, 0, "RaydiumClmm", is_rpc);
+            assert_eq!(
+                instructions.into_vec(),
+                vec![
+                    ComputeBudgetInstruction::set_compute_unit_price(2_000_000),
+                    ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+                ]
+            );
+        }
+    }
+}
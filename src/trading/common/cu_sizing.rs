@@ -0,0 +1,296 @@
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use solana_hash::Hash;
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    instruction::Instruction,
+    message::{v0, VersionedMessage},
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+
+use super::address_lookup_manager::get_address_lookup_table_accounts;
+use crate::common::{PriorityFee, SolanaRpcClient};
+use crate::constants::trade::trade::{
+    CU_SIMULATION_TIMEOUT_MS, CU_SIZING_EXTRA_UNITS, MAX_COMPUTE_UNIT_LIMIT,
+};
+use crate::trading::core::error::TradeError;
+
+/// How the compute unit limit of a trade is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CuSizing {
+    /// The unit limits of `PriorityFee`
+    #[default]
+    Static,
+    /// Simulate the transaction and use the units it consumed plus `margin_pct` percent
+    ///
+    /// Results are cached per protocol, mint and side for `cache_ttl`, so only the first trade
+    /// pays the simulation round trip. A simulation that fails or takes longer than
+    /// `CU_SIMULATION_TIMEOUT_MS` falls back to the static limits.
+    Simulated { margin_pct: u32, cache_ttl: Duration },
+}
+
+/// Trades sharing a simulated unit limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CuSizingKey {
+    pub protocol: &'static str,
+    pub mint: Pubkey,
+    pub is_buy: bool,
+}
+
+/// Units consumed by the last simulation of each protocol, mint and side
+static CU_SIZING_CACHE: Lazy<DashMap<CuSizingKey, (u64, Instant)>> = Lazy::new(DashMap::new);
+
+/// `units_consumed` plus `margin_pct` percent, capped at the maximum a transaction may request
+///
+/// `CU_SIZING_EXTRA_UNITS` is added first for the instructions the simulation leaves out.
+pub fn unit_limit_with_margin(units_consumed: u64, margin_pct: u32) -> u32 {
+    let units = (units_consumed + CU_SIZING_EXTRA_UNITS)
+        .saturating_mul(100 + margin_pct as u64)
+        .div_ceil(100);
+    units.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32
+}
+
+/// Units consumed by `instructions` in a simulation with the maximum unit limit
+pub async fn simulate_units_consumed(
+    rpc: &SolanaRpcClient,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_table_keys: &[Pubkey],
+) -> Result<u64, anyhow::Error> {
+    let mut simulated = Vec::with_capacity(instructions.len() + 1);
+    simulated.push(ComputeBudgetInstruction::set_compute_unit_limit(MAX_COMPUTE_UNIT_LIMIT));
+    simulated.extend_from_slice(instructions);
+
+    let lookup_tables = get_address_lookup_table_accounts(lookup_table_keys)?;
+    let message = v0::Message::try_compile(payer, &simulated, &lookup_tables, Hash::default())?;
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); message.header.num_required_signatures as usize],
+        message: VersionedMessage::V0(message),
+    };
+    let config = solana_rpc_client_api::config::RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: true,
+        ..Default::default()
+    };
+    let result = rpc.simulate_transaction_with_config(&transaction, config).await?.value;
+    if let Some(err) = result.err {
+        let logs = result.logs.unwrap_or_default();
+        return Err(TradeError::SimulationFailed { err: err.to_string(), logs }.into());
+    }
+    result.units_consumed.ok_or_else(|| anyhow!("Simulation returned no units consumed"))
+}
+
+/// Fees with unit limits sized by `sizing`, the unchanged fees when sizing is static or fails
+pub async fn size_compute_units(
+    sizing: CuSizing,
+    rpc: &SolanaRpcClient,
+    priority_fee: &PriorityFee,
+    key: CuSizingKey,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_table_keys: &[Pubkey],
+) -> PriorityFee {
+    let CuSizing::Simulated { margin_pct, cache_ttl } = sizing else {
+        return priority_fee.clone();
+    };
+    let cached = CU_SIZING_CACHE
+        .get(&key)
+        .filter(|entry| entry.1.elapsed() < cache_ttl)
+        .map(|entry| entry.0);
+
+    let units_consumed = match cached {
+        Some(units_consumed) => units_consumed,
+        None => {
+            let simulation = tokio::time::timeout(
+                Duration::from_millis(CU_SIMULATION_TIMEOUT_MS),
+                simulate_units_consumed(rpc, payer, instructions, lookup_table_keys),
+            )
+            .await
+            .unwrap_or_else(|_| Err(anyhow!("Simulation timed out")));
+            match simulation {
+                Ok(units_consumed) => {
+                    CU_SIZING_CACHE.insert(key, (units_consumed, Instant::now()));
+                    units_consumed
+                }
+                Err(e) => {
+                    log::warn!(
+                        icon!("⚠️ ", "Compute unit sizing for {} fell back to static limits: {}"),
+                        key.mint,
+                        e
+                    );
+                    return priority_fee.clone();
+                }
+            }
+        }
+    };
+
+    let unit_limit = unit_limit_with_margin(units_consumed, margin_pct);
+    let mut priority_fee = priority_fee.clone();
+    priority_fee.map_unit_limits(|_| unit_limit);
+    priority_fee
+}
+
+/// Drop cached simulation results, e.g. after a program upgrade changed compute usage
+pub fn clear_cu_sizing_cache() {
+    CU_SIZING_CACHE.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trading::common::compute_budget_manager::compute_budget_instructions;
+    use serde_json::{json, Value};
+    use solana_rpc_client_api::request::RpcRequest;
+    use solana_system_interface::instruction::transfer;
+    use std::collections::HashMap;
+
+    const MARGIN: CuSizing =
+        CuSizing::Simulated { margin_pct: 10, cache_ttl: Duration::from_secs(60) };
+
+    fn priority_fee() -> PriorityFee {
+        let mut priority_fee = PriorityFee {
+            tip_unit_limit: 80_000,
+            tip_unit_price: 500_000,
+            rpc_unit_limit: 100_000,
+            rpc_unit_price: 1_000_000,
+            ..Default::default()
+        };
+        priority_fee.protocol_limits.insert("RaydiumClmm".to_string(), (300_000, 2_000_000));
+        priority_fee
+    }
+
+    fn key(protocol: &'static str) -> CuSizingKey {
+        CuSizingKey { protocol, mint: Pubkey::new_unique(), is_buy: true }
+    }
+
+    /// Mocked RPC whose next `simulateTransaction` returns `value`
+    fn rpc_simulating(value: Value) -> SolanaRpcClient {
+        SolanaRpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            HashMap::from([(
+                RpcRequest::SimulateTransaction,
+                json!({ "context": { "slot": 1 }, "value": value }),
+            )]),
+        )
+    }
+
+    fn consumed(units_consumed: u64) -> Value {
+        json!({ "err": null, "logs": [], "accounts": null, "unitsConsumed": units_consumed })
+    }
+
+    async fn sized(sizing: CuSizing, rpc: &SolanaRpcClient, key: CuSizingKey) -> PriorityFee {
+        let payer = Pubkey::new_unique();
+        let instructions = [transfer(&payer, &Pubkey::new_unique(), 1)];
+        size_compute_units(sizing, rpc, &priority_fee(), key, &payer, &instructions, &[]).await
+    }
+
+    fn unit_limit_instruction(
+        priority_fee: &PriorityFee,
+        protocol: &str,
+        is_rpc: bool,
+    ) -> Instruction {
+        compute_budget_instructions(priority_fee, 0, protocol, is_rpc).into_vec().pop().unwrap()
+    }
+
+    #[test]
+    fn margin_is_added_on_top_of_the_extra_units_and_capped() {
+        assert_eq!(unit_limit_with_margin(5_000, 0), 6_000);
+        assert_eq!(unit_limit_with_margin(150_000, 10), 166_100);
+        assert_eq!(unit_limit_with_margin(42_137, 15), 49_608);
+        assert_eq!(unit_limit_with_margin(1_300_000, 20), MAX_COMPUTE_UNIT_LIMIT);
+    }
+
+    #[tokio::test]
+    async fn simulated_units_set_the_final_unit_limit() {
+        for (units_consumed, margin_pct, expected) in [
+            (5_000, 0, 6_000),
+            (150_000, 10, 166_100),
+            (42_137, 15, 49_608),
+            (1_300_000, 20, MAX_COMPUTE_UNIT_LIMIT),
+        ] {
+            let sizing = CuSizing::Simulated { margin_pct, cache_ttl: Duration::from_secs(60) };
+            let rpc = rpc_simulating(consumed(units_consumed));
+            let priority_fee = sized(sizing, &rpc, key("PumpFun")).await;
+            for is_rpc in [false, true] {
+                assert_eq!(
+                    unit_limit_instruction(&priority_fee, "PumpFun", is_rpc),
+                    ComputeBudgetInstruction::set_compute_unit_limit(expected),
+                    "{} units consumed with a {}% margin",
+                    units_consumed,
+                    margin_pct
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn simulated_units_replace_per_protocol_limits() {
+        let rpc = rpc_simulating(consumed(250_000));
+        let priority_fee = sized(MARGIN, &rpc, key("RaydiumClmm")).await;
+        assert_eq!(
+            compute_budget_instructions(&priority_fee, 0, "RaydiumClmm", false).into_vec(),
+            vec![
+                ComputeBudgetInstruction::set_compute_unit_price(2_000_000),
+                ComputeBudgetInstruction::set_compute_unit_limit(276_100),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn cached_units_skip_the_simulation_until_the_ttl_expires() {
+        let key = key("PumpFun");
+        let rpc = rpc_simulating(consumed(150_000));
+        sized(MARGIN, &rpc, key).await;
+
+        // A second simulation would report other units, the cached ones must win
+        let rpc = rpc_simulating(consumed(20_000));
+        let priority_fee = sized(MARGIN, &rpc, key).await;
+        assert_eq!(
+            unit_limit_instruction(&priority_fee, "PumpFun", false),
+            ComputeBudgetInstruction::set_compute_unit_limit(166_100)
+        );
+
+        let expired = CuSizing::Simulated { margin_pct: 10, cache_ttl: Duration::ZERO };
+        let priority_fee = sized(expired, &rpc, key).await;
+        assert_eq!(
+            unit_limit_instruction(&priority_fee, "PumpFun", false),
+            ComputeBudgetInstruction::set_compute_unit_limit(23_100)
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_simulations_fall_back_to_static_limits() {
+        for value in [
+            json!({ "err": "AccountNotFound", "logs": ["Program log: failed"], "accounts": null }),
+            json!({ "err": null, "logs": [], "accounts": null, "unitsConsumed": null }),
+        ] {
+            let key = key("PumpFun");
+            let rpc = rpc_simulating(value);
+            let priority_fee = sized(MARGIN, &rpc, key).await;
+            assert_eq!(
+                unit_limit_instruction(&priority_fee, "PumpFun", false),
+                ComputeBudgetInstruction::set_compute_unit_limit(80_000)
+            );
+            assert_eq!(
+                unit_limit_instruction(&priority_fee, "PumpFun", true),
+                ComputeBudgetInstruction::set_compute_unit_limit(100_000)
+            );
+            assert!(CU_SIZING_CACHE.get(&key).is_none(), "failures must not be cached");
+        }
+    }
+
+    #[tokio::test]
+    async fn static_sizing_never_simulates() {
+        let rpc = rpc_simulating(consumed(150_000));
+        let priority_fee = sized(CuSizing::Static, &rpc, key("PumpFun")).await;
+        assert_eq!(
+            unit_limit_instruction(&priority_fee, "PumpFun", false),
+            ComputeBudgetInstruction::set_compute_unit_limit(80_000)
+        );
+    }
+}
@@ -1,7 +1,12 @@
+pub mod account_check;
+pub mod account_cleanup;
+pub mod cu_sizing;
 pub mod nonce_manager;
 pub mod transaction_builder;
 pub mod compute_budget_manager;
 pub mod address_lookup_manager;
+pub mod swap_hooks;
+pub mod token_safety;
 pub mod utils;
 pub mod wsol_manager;
 
@@ -10,5 +15,6 @@ pub use nonce_manager::*;
 pub use transaction_builder::*;
 pub use compute_budget_manager::*;
 pub use address_lookup_manager::*;
+pub use token_safety::*;
 pub use utils::*;
 pub use wsol_manager::*;
\ No newline at end of file
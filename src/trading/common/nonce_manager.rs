@@ -1,10 +1,37 @@
 use anyhow::anyhow;
 use solana_hash::Hash;
-use solana_sdk::{instruction::Instruction, signature::Keypair, signer::Signer};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
 use solana_system_interface::instruction::advance_nonce_account;
 
 use crate::common::nonce_cache::NonceCache;
 
+/// Nonce account and its current nonce, `None` when no nonce account is configured
+///
+/// Returns error if nonce is already used or not ready
+pub fn current_nonce() -> Result<Option<(Pubkey, Hash)>, anyhow::Error> {
+    let nonce_cache = NonceCache::get_instance();
+    let nonce_info = nonce_cache.get_nonce_info();
+
+    // Only check if nonce_account exists
+    let Some(nonce_pubkey) = nonce_info.nonce_account else {
+        return Ok(None);
+    };
+    if nonce_info.used {
+        return Err(anyhow!("Nonce is used"));
+    }
+    if nonce_info.current_nonce == Hash::default() {
+        return Err(anyhow!("Nonce is not ready"));
+    }
+    Ok(Some((nonce_pubkey, nonce_info.current_nonce)))
+}
+
+/// Nonce account and current nonce of a trade sent with a durable nonce
+///
+/// Unlike `current_nonce`, a missing nonce account is an error: the trade asked for a nonce.
+pub fn durable_nonce() -> Result<(Pubkey, Hash), anyhow::Error> {
+    current_nonce()?.ok_or_else(|| anyhow!("Durable nonce requested but no nonce account is set"))
+}
+
 /// Add nonce advance instruction to the instruction set
 ///
 /// Nonce functionality is only used when nonce_pubkey is provided
@@ -14,18 +41,7 @@ pub fn add_nonce_instruction(
     instructions: &mut Vec<Instruction>,
     payer: &Keypair,
 ) -> Result<(), anyhow::Error> {
-    let nonce_cache = NonceCache::get_instance();
-    let nonce_info = nonce_cache.get_nonce_info();
-
-    // Only check if nonce_account exists
-    if let Some(nonce_pubkey) = nonce_info.nonce_account {
-        if nonce_info.used {
-            return Err(anyhow!("Nonce is used"));
-        }
-        if nonce_info.current_nonce == Hash::default() {
-            return Err(anyhow!("Nonce is not ready"));
-        }
-
+    if let Some((nonce_pubkey, _)) = current_nonce()? {
         // Create Solana system nonce advance instruction - using system program ID
         let nonce_advance_ix = advance_nonce_account(&nonce_pubkey, &payer.pubkey());
 
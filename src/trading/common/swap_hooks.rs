@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+use super::address_lookup_manager::get_address_lookup_table_accounts;
+use crate::common::PriorityFee;
+use crate::constants::trade::trade::{MAX_COMPUTE_UNIT_LIMIT, MEMO_COMPUTE_UNITS};
+use crate::constants::MEMO_PROGRAM;
+use crate::trading::core::two_hop::check_transaction_fit;
+
+/// Instructions spliced around the swap instructions of one trade
+///
+/// The swap instructions are the ones targeting the protocol's program. Hooks are spliced in
+/// after the protocol builder ran and before middleware, so middleware sees the combined list.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SwapHooks {
+    /// Inserted right before the first swap instruction
+    pub pre_swap_instructions: Vec<Instruction>,
+    /// Inserted right after the last swap instruction
+    pub post_swap_instructions: Vec<Instruction>,
+    /// Compute units the hook instructions need on top of the static unit limits
+    ///
+    /// Ignored with `CuSizing::Simulated`, whose simulation runs the hooks.
+    pub extra_compute_units: u32,
+}
+
+impl SwapHooks {
+    pub fn is_empty(&self) -> bool {
+        self.pre_swap_instructions.is_empty() && self.post_swap_instructions.is_empty()
+    }
+
+    /// `instructions` with the hooks around the instructions of `program_id`
+    pub fn splice(
+        &self,
+        mut instructions: Vec<Instruction>,
+        program_id: &Pubkey,
+    ) -> Result<Vec<Instruction>> {
+        if self.is_empty() {
+            return Ok(instructions);
+        }
+        let first = instructions.iter().position(|ix| ix.program_id == *program_id);
+        let last = instructions.iter().rposition(|ix| ix.program_id == *program_id);
+        let (Some(first), Some(last)) = (first, last) else {
+            return Err(anyhow!("No swap instruction of program {} to hook around", program_id));
+        };
+        instructions.splice(last + 1..last + 1, self.post_swap_instructions.iter().cloned());
+        instructions.splice(first..first, self.pre_swap_instructions.iter().cloned());
+        Ok(instructions)
+    }
+
+    /// Record `memo` in the transaction, after the other post-swap instructions and before the
+    /// wSOL account is closed
+    pub fn push_memo(&mut self, memo: &str) {
+        self.post_swap_instructions.push(memo_instruction(memo));
+        self.extra_compute_units = self.extra_compute_units.saturating_add(MEMO_COMPUTE_UNITS);
+    }
+
+    /// `priority_fee` with `extra_compute_units` added to its unit limits
+    pub fn reserve_compute_units(&self, priority_fee: &Arc<PriorityFee>) -> Arc<PriorityFee> {
+        if self.extra_compute_units == 0 {
+            return priority_fee.clone();
+        }
+        let mut priority_fee = (**priority_fee).clone();
+        priority_fee.map_unit_limits(|unit_limit| {
+            unit_limit.saturating_add(self.extra_compute_units).min(MAX_COMPUTE_UNIT_LIMIT)
+        });
+        Arc::new(priority_fee)
+    }
+}
+
+/// spl-memo instruction carrying `memo`, signed by no one
+pub fn memo_instruction(memo: &str) -> Instruction {
+    Instruction::new_with_bytes(MEMO_PROGRAM, memo.as_bytes(), Vec::new())
+}
+
+/// Check that a trade with hooks still fits one transaction, nothing is checked without hooks
+pub(crate) async fn check_hooked_transaction_fit(
+    hooks: &SwapHooks,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_table_keys: &[Pubkey],
+    priority_fee: &PriorityFee,
+    data_size_limit: u32,
+) -> Result<()> {
+    if hooks.is_empty() {
+        return Ok(());
+    }
+    let lookup_tables = get_address_lookup_table_accounts(lookup_table_keys)?;
+    check_transaction_fit(
+        "Swap and hooks",
+        payer,
+        instructions,
+        &lookup_tables,
+        priority_fee,
+        data_size_limit,
+    )
+}
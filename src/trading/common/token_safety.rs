@@ -0,0 +1,253 @@
+use solana_sdk::pubkey::Pubkey;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token_2022::{
+    extension::{
+        default_account_state::DefaultAccountState, non_transferable::NonTransferable,
+        permanent_delegate::PermanentDelegate, transfer_fee::TransferFeeConfig,
+        transfer_hook::TransferHook, BaseStateWithExtensions, StateWithExtensions,
+    },
+    state::{Account as TokenAccount, AccountState, Mint},
+};
+
+use crate::common::SolanaRpcClient;
+use crate::constants::{TOKEN_PROGRAM, TOKEN_PROGRAM_2022};
+use anyhow::anyhow;
+
+/// Mint or account setting that can make a transfer, and so a sell, fail or cost more
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferRestriction {
+    /// Token-2022 transfer fee withheld from every transfer
+    TransferFee { basis_points: u16, maximum_fee: u64 },
+    /// Token-2022 non-transferable (soulbound) mint, a sell always fails
+    NonTransferable,
+    /// Every transfer invokes this program, which may reject it
+    TransferHook(Pubkey),
+    /// The mint authority can move or burn tokens from any account
+    PermanentDelegate(Pubkey),
+    /// The freeze authority can freeze token accounts at any time
+    FreezeAuthority(Pubkey),
+    /// New token accounts start frozen
+    DefaultFrozen,
+    /// The owner's token account is frozen, a sell always fails
+    AccountFrozen,
+}
+
+impl TransferRestriction {
+    /// Whether a sell is certain to fail
+    pub fn is_blocking(&self) -> bool {
+        matches!(self, TransferRestriction::NonTransferable | TransferRestriction::AccountFrozen)
+    }
+}
+
+/// Restrictions on transferring `mint` out of `owner`'s token account
+///
+/// Reads the mint and both possible associated token accounts in one `getMultipleAccounts`.
+/// Without `owner` only mint level restrictions are reported.
+pub async fn transfer_restrictions(
+    rpc: &SolanaRpcClient,
+    mint: &Pubkey,
+    owner: Option<&Pubkey>,
+) -> Result<Vec<TransferRestriction>, anyhow::Error> {
+    let mut keys = vec![*mint];
+    if let Some(owner) = owner {
+        keys.extend([TOKEN_PROGRAM, TOKEN_PROGRAM_2022].map(|token_program| {
+            get_associated_token_address_with_program_id(owner, mint, &token_program)
+        }));
+    }
+    let mut accounts = rpc.get_multiple_accounts(&keys).await?.into_iter();
+    let mint_account =
+        accounts.next().flatten().ok_or_else(|| anyhow!("Mint {} not found", mint))?;
+
+    // Legacy mints unpack the same way and carry no extensions
+    let state = StateWithExtensions::<Mint>::unpack(&mint_account.data)
+        .map_err(|e| anyhow!("Failed to unpack mint: {}", e))?;
+    let mut restrictions = Vec::new();
+    if let Some(authority) = Option::<Pubkey>::from(state.base.freeze_authority) {
+        restrictions.push(TransferRestriction::FreezeAuthority(authority));
+    }
+    if mint_account.owner == TOKEN_PROGRAM_2022 {
+        if let Ok(config) = state.get_extension::<TransferFeeConfig>() {
+            let fee = config.newer_transfer_fee;
+            let basis_points = u16::from(fee.transfer_fee_basis_points);
+            if basis_points > 0 {
+                restrictions.push(TransferRestriction::TransferFee {
+                    basis_points,
+                    maximum_fee: u64::from(fee.maximum_fee),
+                });
+            }
+        }
+        if state.get_extension::<NonTransferable>().is_ok() {
+            restrictions.push(TransferRestriction::NonTransferable);
+        }
+        if let Ok(hook) = state.get_extension::<TransferHook>() {
+            if let Some(program_id) = Option::<Pubkey>::from(hook.program_id) {
+                restrictions.push(TransferRestriction::TransferHook(program_id));
+            }
+        }
+        if let Ok(delegate) = state.get_extension::<PermanentDelegate>() {
+            if let Some(delegate) = Option::<Pubkey>::from(delegate.delegate) {
+                restrictions.push(TransferRestriction::PermanentDelegate(delegate));
+            }
+        }
+        if let Ok(default_state) = state.get_extension::<DefaultAccountState>() {
+            if default_state.state == AccountState::Frozen as u8 {
+                restrictions.push(TransferRestriction::DefaultFrozen);
+            }
+        }
+    }
+
+    for account in accounts.flatten() {
+        let state = StateWithExtensions::<TokenAccount>::unpack(&account.data)
+            .map_err(|e| anyhow!("Failed to unpack token account: {}", e))?;
+        if state.base.mint == *mint && state.base.state == AccountState::Frozen {
+            restrictions.push(TransferRestriction::AccountFrozen);
+        }
+    }
+    Ok(restrictions)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use solana_rpc_client_api::request::RpcRequest;
+    use solana_sdk::{
+        account::Account,
+        program_pack::Pack,
+        signature::{Keypair, Signer},
+    };
+    use spl_token_2022::extension::{
+        BaseStateWithExtensionsMut, ExtensionType, StateWithExtensionsMut,
+    };
+
+    use super::*;
+    use crate::common::fake_chain::{token_account, FakeChain};
+    use crate::trading::core::venue::SellAmount;
+    use crate::SolanaTrade;
+
+    /// Token-2022 mint withholding `basis_points` of every transfer, capped at `maximum_fee`
+    fn transfer_fee_mint(basis_points: u16, maximum_fee: u64) -> Account {
+        let len =
+            ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::TransferFeeConfig])
+                .unwrap();
+        let mut data = vec![0; len];
+        let mut state = StateWithExtensionsMut::<Mint>::unpack_uninitialized(&mut data).unwrap();
+        let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+        config.newer_transfer_fee.transfer_fee_basis_points = basis_points.into();
+        config.newer_transfer_fee.maximum_fee = maximum_fee.into();
+        config.older_transfer_fee = config.newer_transfer_fee;
+        state.base = Mint { decimals: 6, is_initialized: true, ..Default::default() };
+        state.pack_base();
+        state.init_account_type().unwrap();
+        Account { lamports: 1_461_600, data, owner: TOKEN_PROGRAM_2022, ..Default::default() }
+    }
+
+    /// Legacy mint without a freeze authority
+    fn plain_mint() -> Account {
+        let mut data = vec![0; Mint::LEN];
+        Mint { decimals: 6, is_initialized: true, ..Default::default() }.pack_into_slice(&mut data);
+        Account { lamports: 1_461_600, data, owner: TOKEN_PROGRAM, ..Default::default() }
+    }
+
+    fn frozen_account(mint: Pubkey, amount: u64, token_program: Pubkey) -> Account {
+        let mut account = token_account(mint, amount, token_program);
+        let mut state = TokenAccount::unpack(&account.data).unwrap();
+        state.state = AccountState::Frozen;
+        state.pack_into_slice(&mut account.data);
+        account
+    }
+
+    fn trade_for(payer: Keypair, rpc: SolanaRpcClient) -> SolanaTrade {
+        SolanaTrade { payer: Arc::new(payer), ..SolanaTrade::offline(rpc) }
+    }
+
+    #[tokio::test]
+    async fn token_2022_airdrop_with_a_transfer_fee_warns_without_blocking() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ata = get_associated_token_address_with_program_id(&owner, &mint, &TOKEN_PROGRAM_2022);
+        let (rpc, requests) = FakeChain::default()
+            .with(mint, transfer_fee_mint(250, 5_000_000))
+            .with(ata, token_account(mint, 1_000_000_000, TOKEN_PROGRAM_2022))
+            .client();
+
+        let restrictions = transfer_restrictions(&rpc, &mint, Some(&owner)).await.unwrap();
+        assert_eq!(
+            restrictions,
+            vec![TransferRestriction::TransferFee { basis_points: 250, maximum_fee: 5_000_000 }]
+        );
+        assert!(!restrictions[0].is_blocking());
+        assert_eq!(requests.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn frozen_airdrop_account_blocks_the_sell() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ata = get_associated_token_address_with_program_id(&owner, &mint, &TOKEN_PROGRAM);
+        let (rpc, _) = FakeChain::default()
+            .with(mint, plain_mint())
+            .with(ata, frozen_account(mint, 1_000, TOKEN_PROGRAM))
+            .client();
+
+        let restrictions = transfer_restrictions(&rpc, &mint, Some(&owner)).await.unwrap();
+        assert_eq!(restrictions, vec![TransferRestriction::AccountFrozen]);
+        assert!(restrictions[0].is_blocking());
+    }
+
+    #[tokio::test]
+    async fn plain_mint_without_a_position_has_no_restrictions() {
+        let mint = Pubkey::new_unique();
+        let (rpc, _) = FakeChain::default().with(mint, plain_mint()).client();
+
+        assert!(transfer_restrictions(&rpc, &mint, None).await.unwrap().is_empty());
+        let owner = Pubkey::new_unique();
+        assert!(transfer_restrictions(&rpc, &mint, Some(&owner)).await.unwrap().is_empty());
+        assert!(transfer_restrictions(&rpc, &Pubkey::new_unique(), None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn sell_unknown_token_refuses_a_frozen_airdrop_up_front() {
+        let (payer, mint) = (Keypair::new(), Pubkey::new_unique());
+        let ata =
+            get_associated_token_address_with_program_id(&payer.pubkey(), &mint, &TOKEN_PROGRAM);
+        let (rpc, requests) = FakeChain::default()
+            .with(mint, plain_mint())
+            .with(ata, frozen_account(mint, 1_000, TOKEN_PROGRAM))
+            .client();
+
+        let e = trade_for(payer, rpc).sell_unknown_token(mint, SellAmount::All).await.unwrap_err();
+        assert!(e.to_string().contains("cannot be sold"), "{}", e);
+        assert_eq!(*requests.lock(), vec![RpcRequest::GetMultipleAccounts]);
+    }
+
+    #[tokio::test]
+    async fn sell_unknown_token_takes_a_transfer_fee_airdrop_on_to_venue_detection() {
+        let (payer, mint) = (Keypair::new(), Pubkey::new_unique());
+        let ata = get_associated_token_address_with_program_id(
+            &payer.pubkey(),
+            &mint,
+            &TOKEN_PROGRAM_2022,
+        );
+        let (rpc, requests) = FakeChain::default()
+            .with(mint, transfer_fee_mint(250, 5_000_000))
+            .with(ata, token_account(mint, 1_000_000_000, TOKEN_PROGRAM_2022))
+            .client();
+
+        // The fee only warns, the balance is found and the sell stops at the missing market
+        let e = trade_for(payer, rpc).sell_unknown_token(mint, SellAmount::All).await.unwrap_err();
+        assert!(e.to_string().contains("No PumpFun, PumpSwap or Bonk market found"), "{}", e);
+        assert_eq!(requests.lock()[..2], [RpcRequest::GetMultipleAccounts; 2]);
+    }
+
+    #[tokio::test]
+    async fn sell_unknown_token_without_a_balance_sends_nothing() {
+        let mint = Pubkey::new_unique();
+        let (rpc, requests) = FakeChain::default().with(mint, plain_mint()).client();
+
+        let e = trade_for(Keypair::new(), rpc)
+            .sell_unknown_token(mint, SellAmount::All)
+            .await
+            .unwrap_err();
+        assert!(e.to_string().contains("No balance of token"), "{}", e);
+        assert_eq!(*requests.lock(), vec![RpcRequest::GetMultipleAccounts; 2]);
+    }
+}
@@ -1,29 +1,126 @@
 use solana_hash::Hash;
 use solana_sdk::{
     instruction::Instruction,
-    message::{v0, VersionedMessage},
-    native_token::sol_str_to_lamports,
+    message::{v0, AddressLookupTableAccount, VersionedMessage},
+    packet::PACKET_DATA_SIZE,
     pubkey::Pubkey,
-    signature::Keypair,
+    signature::{Keypair, Signature},
     signer::Signer,
     transaction::VersionedTransaction,
 };
-use solana_system_interface::instruction::transfer;
-use std::sync::Arc;
+use solana_system_interface::instruction::{advance_nonce_account, transfer};
+use std::{collections::HashMap, sync::Arc};
 
 use super::{
     address_lookup_manager::get_address_lookup_table_accounts,
-    compute_budget_manager::compute_budget_instructions,
-    nonce_manager::{add_nonce_instruction, get_transaction_blockhash},
+    compute_budget_manager::compute_budget_instructions, nonce_manager::durable_nonce,
 };
-use crate::{common::PriorityFee, trading::MiddlewareManager};
+use crate::{
+    common::{signer::TradeSigner, PriorityFee},
+    trading::MiddlewareManager,
+};
+
+/// Transaction inputs read from the process-wide caches
+///
+/// Resolved once per submission by `BuildEnvironment::current`. Replay passes the captured
+/// state instead so a transaction can be rebuilt without the caches.
+#[derive(Debug, Clone, Default)]
+pub struct BuildEnvironment {
+    /// Nonce account and the nonce used as blockhash, for trades sent with a durable nonce
+    pub nonce: Option<(Pubkey, Hash)>,
+    pub lookup_table_accounts: Vec<AddressLookupTableAccount>,
+}
+
+impl BuildEnvironment {
+    /// Read the address lookup table cache, `nonce` is resolved by the caller
+    ///
+    /// Fails when a table is not in the cache.
+    pub fn current(
+        lookup_table_keys: &[Pubkey],
+        nonce: Option<(Pubkey, Hash)>,
+    ) -> Result<Self, anyhow::Error> {
+        let lookup_table_accounts = get_address_lookup_table_accounts(lookup_table_keys)?;
+        Ok(Self { nonce, lookup_table_accounts })
+    }
+
+    /// Read the caches for a trade, with the durable nonce only when `use_durable_nonce` is set
+    ///
+    /// Fails when the nonce is requested but no nonce account is set.
+    pub fn for_trade(
+        lookup_table_keys: &[Pubkey],
+        use_durable_nonce: bool,
+    ) -> Result<Self, anyhow::Error> {
+        let nonce = if use_durable_nonce { Some(durable_nonce()?) } else { None };
+        Self::current(lookup_table_keys, nonce)
+    }
+}
+
+/// A transaction was rejected before sending because it does not fit in a packet
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error(
+    "Transaction serializes to {size} bytes with {account_count} accounts, the limit is {limit}{}",
+    if *lookup_tables_applied {
+        " (lookup tables applied)"
+    } else {
+        ", an address lookup table may help"
+    }
+)]
+pub struct TransactionTooLarge {
+    /// Size of the signed transaction
+    pub size: usize,
+    pub limit: usize,
+    /// Static account keys plus the accounts loaded from lookup tables
+    pub account_count: usize,
+    pub lookup_tables_applied: bool,
+}
+
+/// Size the signed transaction of `message` serializes to
+pub fn signed_transaction_size(message: &VersionedMessage) -> usize {
+    let signature_count = message.header().num_required_signatures as usize;
+    // One byte of length prefix, transactions never carry 128 signatures
+    1 + signature_count * 64 + message.serialize().len()
+}
+
+/// Size of the transaction of `instructions` paid by `payer`, with `lookup_tables` applied
+///
+/// Lets a strategy check ahead of a trade whether e.g. `open_seed_optimize` or a lookup table
+/// keeps it within `PACKET_DATA_SIZE`. Only `instructions` are counted; `compile_message` adds the
+/// compute budget instructions, a tip transfer and a nonce advance around the business
+/// instructions of a trade.
+pub fn estimate_transaction_size(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+) -> Result<usize, anyhow::Error> {
+    let message = v0::Message::try_compile(payer, instructions, lookup_tables, Hash::default())?;
+    Ok(signed_transaction_size(&VersionedMessage::V0(message)))
+}
+
+/// `TransactionTooLarge` when the signed transaction of `message` exceeds `PACKET_DATA_SIZE`
+pub fn check_transaction_size(message: &VersionedMessage) -> Result<(), TransactionTooLarge> {
+    let size = signed_transaction_size(message);
+    if size <= PACKET_DATA_SIZE {
+        return Ok(());
+    }
+    let lookups = message.address_table_lookups().unwrap_or_default();
+    Err(TransactionTooLarge {
+        size,
+        limit: PACKET_DATA_SIZE,
+        account_count: message.static_account_keys().len()
+            + lookups
+                .iter()
+                .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+                .sum::<usize>(),
+        lookup_tables_applied: !lookups.is_empty(),
+    })
+}
 
 /// Build standard RPC transaction
 pub async fn build_transaction(
-    payer: Arc<Keypair>,
+    payer: Arc<dyn TradeSigner>,
     priority_fee: &PriorityFee,
     business_instructions: Vec<Instruction>,
-    lookup_table_key: Option<Pubkey>,
+    lookup_table_keys: &[Pubkey],
     recent_blockhash: Hash,
     data_size_limit: u32,
     middleware_manager: Option<Arc<MiddlewareManager>>,
@@ -31,67 +128,72 @@ pub async fn build_transaction(
     is_buy: bool,
     with_tip: bool,
     tip_account: &Pubkey,
-    tip_amount: f64,
+    tip_lamports: u64,
+    use_durable_nonce: bool,
 ) -> Result<VersionedTransaction, anyhow::Error> {
+    let environment = BuildEnvironment::for_trade(lookup_table_keys, use_durable_nonce)?;
+    let message = compile_message(
+        &payer.pubkey(),
+        priority_fee,
+        &business_instructions,
+        &environment,
+        recent_blockhash,
+        data_size_limit,
+        middleware_manager.as_deref(),
+        protocol_name,
+        is_buy,
+        with_tip,
+        tip_account,
+        tip_lamports,
+    )?;
+    sign_message(payer.as_ref(), &[], message).await
+}
+
+/// Compile the message of a transaction from explicitly given cache state
+///
+/// Adds the nonce advance, compute budget and tip instructions around `business_instructions`
+/// and runs the middlewares, the same way `build_transaction` does. Fails with
+/// `TransactionTooLarge` when the signed transaction would not fit in a packet.
+pub fn compile_message(
+    payer: &Pubkey,
+    priority_fee: &PriorityFee,
+    business_instructions: &[Instruction],
+    environment: &BuildEnvironment,
+    recent_blockhash: Hash,
+    data_size_limit: u32,
+    middleware_manager: Option<&MiddlewareManager>,
+    protocol_name: &str,
+    is_buy: bool,
+    with_tip: bool,
+    tip_account: &Pubkey,
+    tip_lamports: u64,
+) -> Result<VersionedMessage, anyhow::Error> {
     let mut instructions = Vec::with_capacity(business_instructions.len() + 5);
 
     // Add nonce instruction
-    if is_buy {
-        if let Err(e) = add_nonce_instruction(&mut instructions, payer.as_ref()) {
-            return Err(e);
-        }
+    if let Some((nonce_pubkey, _)) = environment.nonce {
+        instructions.push(advance_nonce_account(&nonce_pubkey, payer));
     }
 
     // Add compute budget instructions
     instructions.extend(compute_budget_instructions(
         priority_fee,
         data_size_limit,
+        protocol_name,
         !with_tip,
-        is_buy,
     ));
 
     // Add business instructions
-    instructions.extend(business_instructions);
+    instructions.extend_from_slice(business_instructions);
 
     // Add tip transfer instruction
     if with_tip {
-        instructions.push(transfer(
-            &payer.pubkey(),
-            tip_account,
-            sol_str_to_lamports(tip_amount.to_string().as_str()).unwrap_or(0),
-        ));
+        instructions.push(transfer(payer, tip_account, tip_lamports));
     }
 
     // Get blockhash for transaction
-    let blockhash =
-        if is_buy { get_transaction_blockhash(recent_blockhash) } else { recent_blockhash };
-
-    // Get address lookup table accounts
-    let address_lookup_table_accounts = get_address_lookup_table_accounts(lookup_table_key).await;
-
-    // Build transaction
-    build_versioned_transaction(
-        payer,
-        instructions,
-        address_lookup_table_accounts,
-        blockhash,
-        middleware_manager,
-        protocol_name,
-        is_buy,
-    )
-    .await
-}
+    let blockhash = environment.nonce.map_or(recent_blockhash, |(_, nonce)| nonce);
 
-/// Low-level function for building versioned transactions
-async fn build_versioned_transaction(
-    payer: Arc<Keypair>,
-    instructions: Vec<Instruction>,
-    address_lookup_table_accounts: Vec<solana_sdk::message::AddressLookupTableAccount>,
-    blockhash: Hash,
-    middleware_manager: Option<Arc<MiddlewareManager>>,
-    protocol_name: &str,
-    is_buy: bool,
-) -> Result<VersionedTransaction, anyhow::Error> {
     let full_instructions = match middleware_manager {
         Some(middleware_manager) => middleware_manager
             .apply_middlewares_process_full_instructions(
@@ -102,13 +204,178 @@ async fn build_versioned_transaction(
         None => instructions,
     };
     let v0_message: v0::Message = v0::Message::try_compile(
-        &payer.pubkey(),
+        payer,
         &full_instructions,
-        &address_lookup_table_accounts,
+        &environment.lookup_table_accounts,
         blockhash,
     )?;
-    let versioned_msg = VersionedMessage::V0(v0_message);
-    let msg_bytes = versioned_msg.serialize();
-    let signature = payer.try_sign_message(&msg_bytes).expect("sign failed");
-    Ok(VersionedTransaction { signatures: vec![signature], message: versioned_msg })
+    let message = VersionedMessage::V0(v0_message);
+    check_transaction_size(&message)?;
+    Ok(message)
+}
+
+/// Sign `message` by the payer and `co_signers`, e.g. a mint keypair
+pub async fn sign_message(
+    payer: &dyn TradeSigner,
+    co_signers: &[Arc<Keypair>],
+    message: VersionedMessage,
+) -> Result<VersionedTransaction, anyhow::Error> {
+    let payer_signature = payer.sign_message(&message.serialize()).await?;
+    with_signatures(payer, payer_signature, co_signers, message)
+}
+
+/// Transaction of `message` with a default placeholder for each required signature, for the
+/// caller to sign
+pub fn unsigned_transaction(message: VersionedMessage) -> VersionedTransaction {
+    let signer_count = message.header().num_required_signatures as usize;
+    VersionedTransaction { signatures: vec![Signature::default(); signer_count], message }
+}
+
+/// Sign each of `messages`, asking the payer once per distinct message
+///
+/// The transactions of one trade only differ in the tip, so providers without a tip account
+/// send the same message; with a remote signer every signature is a round trip. Messages that
+/// failed to compile keep their error.
+pub async fn sign_messages(
+    payer: &dyn TradeSigner,
+    co_signers: &[Arc<Keypair>],
+    messages: Vec<Result<VersionedMessage, anyhow::Error>>,
+) -> Vec<Result<VersionedTransaction, anyhow::Error>> {
+    let mut distinct: Vec<Vec<u8>> = Vec::new();
+    for message in messages.iter().flatten() {
+        let bytes = message.serialize();
+        if !distinct.contains(&bytes) {
+            distinct.push(bytes);
+        }
+    }
+    let signed =
+        futures::future::join_all(distinct.iter().map(|bytes| payer.sign_message(bytes))).await;
+    let signatures: HashMap<Vec<u8>, Result<Signature, String>> = distinct
+        .into_iter()
+        .zip(signed)
+        .map(|(bytes, signature)| (bytes, signature.map_err(|e| format!("{:#}", e))))
+        .collect();
+    messages
+        .into_iter()
+        .map(|message| {
+            let message = message?;
+            // Every compiled message is among the distinct ones
+            let payer_signature = signatures[&message.serialize()]
+                .clone()
+                .map_err(|e| anyhow::anyhow!("Payer failed to sign: {}", e))?;
+            with_signatures(payer, payer_signature, co_signers, message)
+        })
+        .collect()
+}
+
+/// Transaction of `message` with the payer's signature and those of `co_signers`, in the order
+/// its account keys list the signers
+fn with_signatures(
+    payer: &dyn TradeSigner,
+    payer_signature: Signature,
+    co_signers: &[Arc<Keypair>],
+    message: VersionedMessage,
+) -> Result<VersionedTransaction, anyhow::Error> {
+    let payer_pubkey = payer.pubkey();
+    let signer_count = message.header().num_required_signatures as usize;
+    let mut signatures = Vec::with_capacity(signer_count);
+    for key in message.static_account_keys().iter().take(signer_count) {
+        if *key == payer_pubkey {
+            signatures.push(payer_signature);
+            continue;
+        }
+        let co_signer = co_signers
+            .iter()
+            .find(|co_signer| co_signer.pubkey() == *key)
+            .ok_or_else(|| anyhow::anyhow!("No signer for {}", key))?;
+        signatures.push(co_signer.try_sign_message(&message.serialize())?);
+    }
+    Ok(VersionedTransaction { signatures, message })
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::instruction::AccountMeta;
+
+    use super::*;
+
+    /// One instruction reading `count` distinct accounts, 32 bytes each unless looked up
+    fn wide_instruction(count: usize) -> (Instruction, Vec<Pubkey>) {
+        let keys: Vec<Pubkey> = (0..count).map(|_| Pubkey::new_unique()).collect();
+        let accounts = keys.iter().map(|key| AccountMeta::new_readonly(*key, false)).collect();
+        (Instruction::new_with_bytes(Pubkey::new_unique(), &[1, 2, 3], accounts), keys)
+    }
+
+    fn compile(
+        payer: &Pubkey,
+        instructions: &[Instruction],
+        lookup_table_accounts: Vec<AddressLookupTableAccount>,
+    ) -> Result<VersionedMessage, anyhow::Error> {
+        let environment = BuildEnvironment { nonce: None, lookup_table_accounts };
+        compile_message(
+            payer,
+            &PriorityFee::default(),
+            instructions,
+            &environment,
+            Hash::new_unique(),
+            256 * 1024,
+            None,
+            "PumpFun",
+            true,
+            false,
+            &Pubkey::default(),
+            0,
+        )
+    }
+
+    #[test]
+    fn signed_size_is_the_serialized_size() {
+        let payer = Keypair::new();
+        let (instruction, _) = wide_instruction(10);
+        let message = compile(&payer.pubkey(), &[instruction], Vec::new()).unwrap();
+        let size = signed_transaction_size(&message);
+        let transaction = VersionedTransaction::try_new(message, &[&payer]).unwrap();
+        assert_eq!(bincode::serialize(&transaction).unwrap().len(), size);
+    }
+
+    #[test]
+    fn transaction_only_fits_with_a_lookup_table() {
+        let payer = Pubkey::new_unique();
+        let (instruction, keys) = wide_instruction(40);
+        let table = AddressLookupTableAccount { key: Pubkey::new_unique(), addresses: keys };
+        let instructions = [instruction];
+
+        let without = estimate_transaction_size(&payer, &instructions, &[]).unwrap();
+        let with = estimate_transaction_size(&payer, &instructions, &[table.clone()]).unwrap();
+        assert!(without > PACKET_DATA_SIZE, "{} bytes without the table", without);
+        assert!(with <= PACKET_DATA_SIZE, "{} bytes with the table", with);
+        // Each looked up account takes a one byte index instead of its 32 byte key
+        assert_eq!(without - with, 40 * 31 - 32 - 2);
+
+        let error = compile(&payer, &instructions, Vec::new()).unwrap_err();
+        let error = error.downcast_ref::<TransactionTooLarge>().unwrap();
+        assert_eq!(error.limit, PACKET_DATA_SIZE);
+        assert!(error.size > PACKET_DATA_SIZE);
+        // Payer, the compute budget program, the instruction's program and its accounts
+        assert_eq!(error.account_count, 43);
+        assert!(!error.lookup_tables_applied);
+        assert!(error.to_string().contains("an address lookup table may help"));
+
+        let message = compile(&payer, &instructions, vec![table]).unwrap();
+        assert!(check_transaction_size(&message).is_ok());
+    }
+
+    #[test]
+    fn oversized_transaction_reports_the_applied_lookup_table() {
+        let payer = Pubkey::new_unique();
+        let (mut instruction, keys) = wide_instruction(40);
+        instruction.data = vec![0; PACKET_DATA_SIZE];
+        let table = AddressLookupTableAccount { key: Pubkey::new_unique(), addresses: keys };
+
+        let error = compile(&payer, &[instruction], vec![table]).unwrap_err();
+        let error = error.downcast_ref::<TransactionTooLarge>().unwrap();
+        assert_eq!(error.account_count, 43);
+        assert!(error.lookup_tables_applied);
+        assert!(error.to_string().ends_with("(lookup tables applied)"));
+    }
 }
@@ -1,12 +1,18 @@
+use solana_client::client_error::ClientError;
 use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer, transaction::Transaction};
 use solana_system_interface::instruction::transfer;
 use spl_associated_token_account::get_associated_token_address;
-use spl_token::instruction::close_account;
+use spl_token::instruction::{close_account, transfer as token_transfer};
+use spl_token_2022::{extension::StateWithExtensions, state::Account as TokenAccount};
+use std::time::Duration;
 
+use crate::common::account_fetch::fetch_token_amounts;
 use crate::common::SolanaRpcClient;
+use crate::constants::{TOKEN_PROGRAM, TOKEN_PROGRAM_2022};
+use crate::trading::core::error::TradeError;
 use anyhow::anyhow;
 
-/// Get the balances of two tokens in the pool
+/// Get the balances of two tokens in the pool, loaded in one `getMultipleAccounts` call
 ///
 /// # Returns
 /// Returns token0_balance, token1_balance
@@ -15,31 +21,153 @@ pub async fn get_multi_token_balances(
     token0_vault: &Pubkey,
     token1_vault: &Pubkey,
 ) -> Result<(u64, u64), anyhow::Error> {
-    let token0_balance = rpc.get_token_account_balance(&token0_vault).await?;
-    let token1_balance = rpc.get_token_account_balance(&token1_vault).await?;
-    // Parse balance string to u64
-    let token0_amount = token0_balance
-        .amount
-        .parse::<u64>()
-        .map_err(|e| anyhow!("Failed to parse token0 balance: {}", e))?;
-    let token1_amount = token1_balance
-        .amount
-        .parse::<u64>()
-        .map_err(|e| anyhow!("Failed to parse token1 balance: {}", e))?;
-    Ok((token0_amount, token1_amount))
+    Ok(fetch_token_amounts(rpc, ("token0 vault", *token0_vault), ("token1 vault", *token1_vault))
+        .await?)
 }
 
+/// Bounded retry of balance reads on token accounts the RPC does not know yet
+///
+/// A token account created by a transaction that just landed can be missing on the RPC node
+/// for a few slots, e.g. when selling right after the buy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceRetry {
+    /// Reads after the first, `0` disables retrying
+    pub max_retries: u32,
+    /// Wait before each retry
+    pub backoff: Duration,
+}
+
+impl BalanceRetry {
+    pub const NONE: BalanceRetry = BalanceRetry { max_retries: 0, backoff: Duration::ZERO };
+}
+
+impl Default for BalanceRetry {
+    fn default() -> Self {
+        Self { max_retries: 3, backoff: Duration::from_millis(100) }
+    }
+}
+
+/// Balance of the payer's ATA, retried per `BalanceRetry::default` while it is not found
 #[inline]
 pub async fn get_token_balance(
     rpc: &SolanaRpcClient,
     payer: &Pubkey,
     mint: &Pubkey,
+) -> Result<u64, anyhow::Error> {
+    get_token_balance_with_retry(rpc, payer, mint, BalanceRetry::default()).await
+}
+
+/// Balance of the payer's ATA, read again per `retry` while the RPC cannot find the account
+///
+/// Other errors are returned at once. Once the retries are spent the last not-found error is
+/// returned.
+pub async fn get_token_balance_with_retry(
+    rpc: &SolanaRpcClient,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    retry: BalanceRetry,
 ) -> Result<u64, anyhow::Error> {
     let ata = get_associated_token_address(payer, mint);
-    let balance = rpc.get_token_account_balance(&ata).await?;
-    let balance_u64 =
-        balance.amount.parse::<u64>().map_err(|_| anyhow!("Failed to parse token balance"))?;
-    Ok(balance_u64)
+    let mut attempt = 0;
+    loop {
+        match rpc.get_token_account_balance(&ata).await {
+            Ok(balance) => {
+                return balance
+                    .amount
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse token balance"));
+            }
+            Err(e) if attempt < retry.max_retries && is_account_not_found(&e) => {
+                attempt += 1;
+                tokio::time::sleep(retry.backoff).await;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+fn is_account_not_found(error: &ClientError) -> bool {
+    let error = error.to_string().to_lowercase();
+    error.contains("could not find account") || error.contains("accountnotfound")
+}
+
+/// Token account a sell spends from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SellTokenAccount {
+    pub address: Pubkey,
+    /// spl-token or token-2022, whichever program owns the account
+    pub token_program: Pubkey,
+    pub amount: u64,
+}
+
+/// Account a sell spends from, `None` when it does not exist
+///
+/// Looks up the spl-token and token-2022 accounts (seeded when `use_seed`, canonical ATA
+/// otherwise) in a single `getMultipleAccounts` call.
+pub async fn get_sell_token_account(
+    rpc: &SolanaRpcClient,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    use_seed: bool,
+) -> Result<Option<SellTokenAccount>, anyhow::Error> {
+    let token_programs = [TOKEN_PROGRAM, TOKEN_PROGRAM_2022];
+    let addresses = token_programs.map(|token_program| {
+        crate::common::fast_fn::get_associated_token_address_with_program_id_fast_use_seed(
+            owner,
+            mint,
+            &token_program,
+            use_seed,
+        )
+    });
+    let accounts = rpc.get_multiple_accounts(&addresses).await?;
+    for (i, account) in accounts.into_iter().enumerate() {
+        let Some(account) = account else {
+            continue;
+        };
+        let state = StateWithExtensions::<TokenAccount>::unpack(&account.data)
+            .map_err(|e| anyhow!("Failed to unpack token account: {}", e))?;
+        if state.base.mint == *mint {
+            return Ok(Some(SellTokenAccount {
+                address: addresses[i],
+                token_program: token_programs[i],
+                amount: state.base.amount,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// `get_sell_token_account`, looked up again per `retry` while neither account exists
+pub async fn get_sell_token_account_with_retry(
+    rpc: &SolanaRpcClient,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    use_seed: bool,
+    retry: BalanceRetry,
+) -> Result<Option<SellTokenAccount>, anyhow::Error> {
+    let mut attempt = 0;
+    loop {
+        let account = get_sell_token_account(rpc, owner, mint, use_seed).await?;
+        if account.is_some() || attempt >= retry.max_retries {
+            return Ok(account);
+        }
+        attempt += 1;
+        tokio::time::sleep(retry.backoff).await;
+    }
+}
+
+/// Balance of the account a sell spends from, 0 when the account does not exist
+///
+/// See `get_sell_token_account`
+pub async fn get_sell_token_balance(
+    rpc: &SolanaRpcClient,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    use_seed: bool,
+) -> Result<u64, anyhow::Error> {
+    Ok(get_sell_token_account(rpc, owner, mint, use_seed)
+        .await?
+        .map_or(0, |account| account.amount))
 }
 
 #[inline]
@@ -63,7 +191,7 @@ pub async fn transfer_sol(
 
     let balance = get_sol_balance(rpc, &payer.pubkey()).await?;
     if balance < amount {
-        return Err(anyhow!("Insufficient balance"));
+        return Err(TradeError::InsufficientBalance { needed: amount, available: balance }.into());
     }
 
     let transfer_instruction = transfer(&payer.pubkey(), receive_wallet, amount);
@@ -82,6 +210,60 @@ pub async fn transfer_sol(
     Ok(())
 }
 
+/// Transfer SPL tokens to another wallet
+///
+/// Creates the receiver's associated token account if needed. Transfers the whole balance
+/// when `amount` is `None`.
+///
+/// # Returns
+///
+/// Returns the transferred amount
+pub async fn transfer_token(
+    rpc: &SolanaRpcClient,
+    payer: &Keypair,
+    receive_wallet: &Pubkey,
+    mint: &Pubkey,
+    amount: Option<u64>,
+) -> Result<u64, anyhow::Error> {
+    let amount = match amount {
+        Some(amount) => amount,
+        None => get_token_balance(rpc, &payer.pubkey(), mint).await?,
+    };
+    if amount == 0 {
+        return Err(anyhow!("transfer_token: Amount cannot be zero"));
+    }
+
+    let source = get_associated_token_address(&payer.pubkey(), mint);
+    let destination = get_associated_token_address(receive_wallet, mint);
+
+    let mut instructions = crate::common::fast_fn::create_associated_token_account_idempotent_fast(
+        &payer.pubkey(),
+        receive_wallet,
+        mint,
+        &crate::constants::TOKEN_PROGRAM,
+    );
+    instructions.push(token_transfer(
+        &crate::constants::TOKEN_PROGRAM,
+        &source,
+        &destination,
+        &payer.pubkey(),
+        &[&payer.pubkey()],
+        amount,
+    )?);
+
+    let recent_blockhash = rpc.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_blockhash,
+    );
+
+    rpc.send_and_confirm_transaction(&transaction).await?;
+
+    Ok(amount)
+}
+
 /// Close token account
 ///
 /// This function is used to close the associated token account for a specified token,
@@ -133,3 +315,152 @@ pub async fn close_token_account(
 
     Ok(())
 }
+
+/// Close the ephemeral wSOL accounts `payer` left open, see `EphemeralWsolAccount`
+///
+/// Trades close their account in the same transaction, so this only finds accounts of
+/// transactions that landed without the close. Their rent and any wSOL are returned as SOL.
+///
+/// # Returns
+///
+/// Returns the closed accounts
+pub async fn close_ephemeral_wsol_accounts(
+    rpc: &SolanaRpcClient,
+    payer: &Keypair,
+) -> Result<Vec<Pubkey>, anyhow::Error> {
+    let addresses = crate::trading::common::wsol_manager::ephemeral_wsol_addresses(&payer.pubkey());
+    let accounts = rpc.get_multiple_accounts(&addresses).await?;
+    let open: Vec<Pubkey> = addresses
+        .into_iter()
+        .zip(accounts)
+        .filter(|(_, account)| {
+            account.as_ref().is_some_and(|account| account.owner == TOKEN_PROGRAM)
+        })
+        .map(|(address, _)| address)
+        .collect();
+    if open.is_empty() {
+        return Ok(open);
+    }
+
+    let instructions = open
+        .iter()
+        .map(|address| {
+            close_account(&TOKEN_PROGRAM, address, &payer.pubkey(), &payer.pubkey(), &[])
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let recent_blockhash = rpc.get_latest_blockhash().await?;
+    let transaction = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_blockhash,
+    );
+    rpc.send_and_confirm_transaction(&transaction).await?;
+
+    Ok(open)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::fake_chain::{token_account, FakeChain};
+    use serde_json::json;
+    use solana_rpc_client_api::request::RpcRequest;
+
+    const NOT_FOUND: &str = "Invalid param: could not find account";
+
+    fn retry(max_retries: u32) -> BalanceRetry {
+        BalanceRetry { max_retries, backoff: Duration::from_millis(1) }
+    }
+
+    fn not_found() -> Result<serde_json::Value, String> {
+        Err(NOT_FOUND.to_string())
+    }
+
+    fn balance(amount: u64) -> Result<serde_json::Value, String> {
+        Ok(json!({
+            "context": { "slot": 1 },
+            "value": {
+                "amount": amount.to_string(),
+                "decimals": 6,
+                "uiAmount": amount as f64 / 1e6,
+                "uiAmountString": (amount as f64 / 1e6).to_string(),
+            },
+        }))
+    }
+
+    #[tokio::test]
+    async fn exhausted_retries_return_the_original_error() {
+        let (payer, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (rpc, requests) = FakeChain::default()
+            .script(RpcRequest::GetTokenAccountBalance, not_found())
+            .script(RpcRequest::GetTokenAccountBalance, not_found())
+            .script(RpcRequest::GetTokenAccountBalance, not_found())
+            .script(RpcRequest::GetTokenAccountBalance, balance(42))
+            .client();
+
+        let e = get_token_balance_with_retry(&rpc, &payer, &mint, retry(2)).await.unwrap_err();
+        assert!(e.to_string().contains(NOT_FOUND), "{}", e);
+        assert_eq!(*requests.lock(), vec![RpcRequest::GetTokenAccountBalance; 3]);
+    }
+
+    #[tokio::test]
+    async fn balance_is_read_once_the_account_appears() {
+        let (payer, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (rpc, requests) = FakeChain::default()
+            .script(RpcRequest::GetTokenAccountBalance, not_found())
+            .script(RpcRequest::GetTokenAccountBalance, balance(42))
+            .client();
+
+        assert_eq!(get_token_balance_with_retry(&rpc, &payer, &mint, retry(2)).await.unwrap(), 42);
+        assert_eq!(requests.lock().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn other_errors_and_disabled_retries_fail_at_once() {
+        let (payer, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (rpc, requests) = FakeChain::default()
+            .script(RpcRequest::GetTokenAccountBalance, Err("Internal error".to_string()))
+            .script(RpcRequest::GetTokenAccountBalance, not_found())
+            .script(RpcRequest::GetTokenAccountBalance, balance(42))
+            .client();
+
+        assert!(get_token_balance_with_retry(&rpc, &payer, &mint, retry(2)).await.is_err());
+        assert_eq!(requests.lock().len(), 1);
+        let e = get_token_balance_with_retry(&rpc, &payer, &mint, BalanceRetry::NONE)
+            .await
+            .unwrap_err();
+        assert!(e.to_string().contains(NOT_FOUND), "{}", e);
+        assert_eq!(requests.lock().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn sell_account_lookup_gives_up_after_its_retries() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (rpc, requests) = FakeChain::default().client();
+
+        let account =
+            get_sell_token_account_with_retry(&rpc, &owner, &mint, false, retry(2)).await.unwrap();
+        assert_eq!(account, None);
+        assert_eq!(*requests.lock(), vec![RpcRequest::GetMultipleAccounts; 3]);
+    }
+
+    #[tokio::test]
+    async fn sell_account_lookup_finds_an_account_created_meanwhile() {
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let ata = get_associated_token_address(&owner, &mint);
+        let missing = Ok(json!({ "context": { "slot": 1 }, "value": [null, null] }));
+        let (rpc, requests) = FakeChain::default()
+            .with(ata, token_account(mint, 1_000, TOKEN_PROGRAM))
+            .script(RpcRequest::GetMultipleAccounts, missing)
+            .client();
+
+        let account =
+            get_sell_token_account_with_retry(&rpc, &owner, &mint, false, retry(2)).await.unwrap();
+        assert_eq!(
+            account,
+            Some(SellTokenAccount { address: ata, token_program: TOKEN_PROGRAM, amount: 1_000 })
+        );
+        assert_eq!(requests.lock().len(), 2);
+    }
+}
@@ -1,14 +1,28 @@
 use crate::common::fast_fn::create_associated_token_account_idempotent_fast;
+use anyhow::anyhow;
 use smallvec::SmallVec;
-use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
-use solana_system_interface::instruction::transfer;
+use solana_sdk::{
+    instruction::Instruction, program_pack::Pack, pubkey::Pubkey, signature::Signature,
+};
+use solana_system_interface::instruction::{create_account_with_seed, transfer};
 use spl_token::instruction::close_account;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 #[inline]
 pub fn handle_wsol(payer: &Pubkey, amount_in: u64) -> SmallVec<[Instruction; 3]> {
+    handle_wsol_for_owner(payer, payer, amount_in)
+}
+
+/// Wrap `amount_in` of the payer's SOL into the wSOL ATA of `owner`, creating it if needed
+#[inline]
+pub fn handle_wsol_for_owner(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    amount_in: u64,
+) -> SmallVec<[Instruction; 3]> {
     let wsol_token_account =
         crate::common::fast_fn::get_associated_token_address_with_program_id_fast(
-            &payer,
+            &owner,
             &crate::constants::WSOL_TOKEN_ACCOUNT,
             &crate::constants::TOKEN_PROGRAM,
         );
@@ -16,7 +30,7 @@ pub fn handle_wsol(payer: &Pubkey, amount_in: u64) -> SmallVec<[Instruction; 3]>
     let mut insts = SmallVec::<[Instruction; 3]>::new();
     insts.extend(create_associated_token_account_idempotent_fast(
         &payer,
-        &payer,
+        &owner,
         &crate::constants::WSOL_TOKEN_ACCOUNT,
         &crate::constants::TOKEN_PROGRAM,
     ));
@@ -63,3 +77,277 @@ pub fn create_wsol_ata(payer: &Pubkey) -> Vec<Instruction> {
         &crate::constants::TOKEN_PROGRAM,
     )
 }
+
+/// wSOL steps a builder adds around the swap, from the `create_wsol_ata` and `close_wsol_ata`
+/// flags of the trade params
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WsolHandling {
+    /// Create the wSOL account before the swap, funding it with the SOL in on buys. Off, a buy
+    /// spends wSOL the account already holds.
+    pub create: bool,
+    /// Close the wSOL account after the swap, unwrapping all of its balance. Off, the wSOL
+    /// stays in the account.
+    pub close: bool,
+}
+
+impl WsolHandling {
+    /// The trade's flags, both off when the deprecated `auto_handle_wsol` of the protocol
+    /// params is false
+    pub fn new(create_wsol_ata: bool, close_wsol_ata: bool, auto_handle_wsol: bool) -> Self {
+        Self {
+            create: create_wsol_ata && auto_handle_wsol,
+            close: close_wsol_ata && auto_handle_wsol,
+        }
+    }
+
+    /// Whether the trade may use an `EphemeralPerTrade` account, which is created and closed
+    /// inside the transaction
+    pub fn allows_ephemeral(&self) -> bool {
+        self.create && self.close
+    }
+}
+
+/// Ephemeral wSOL accounts a payer rotates through, bounding what `close_ephemeral_wsol_accounts`
+/// has to scan. Concurrent trades of one payer beyond this count reuse a slot and fail to create
+/// it while the earlier trade is in flight.
+pub const EPHEMERAL_WSOL_SLOTS: usize = 16;
+
+static NEXT_EPHEMERAL_WSOL_SLOT: AtomicUsize = AtomicUsize::new(0);
+
+/// Token account the SDK wraps and unwraps a trade's SOL in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WsolAccountChoice {
+    /// The payer's wSOL ATA, closed after the trade together with any wSOL it held before
+    #[default]
+    CanonicalAta,
+    /// A seeded account created and closed inside the trade's transaction, leaving the wSOL ATA
+    /// and its balance untouched. Costs the token account rent up front, refunded on close.
+    EphemeralPerTrade,
+}
+
+/// Seeded wSOL account used by a single trade under `WsolAccountChoice::EphemeralPerTrade`
+///
+/// Create and close run in the same transaction, so a failed trade leaves nothing behind. An
+/// account is only left open when a transaction carrying the create lands without the close, e.g.
+/// instructions edited by a middleware. Its rent stays locked and trades that rotate onto that
+/// slot fail to create it until `close_ephemeral_wsol_accounts` closes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EphemeralWsolAccount {
+    pub address: Pubkey,
+    pub slot: usize,
+}
+
+impl EphemeralWsolAccount {
+    /// Next slot of `payer`, or `None` when the trade uses the canonical ATA or the SDK does not
+    /// handle its wSOL (`handles_wsol` false)
+    pub fn for_trade(
+        payer: &Pubkey,
+        choice: WsolAccountChoice,
+        handles_wsol: bool,
+    ) -> Option<Self> {
+        if choice != WsolAccountChoice::EphemeralPerTrade || !handles_wsol {
+            return None;
+        }
+        Some(Self::next(payer))
+    }
+
+    /// Next slot of `payer` in the rotation
+    pub fn next(payer: &Pubkey) -> Self {
+        let slot = NEXT_EPHEMERAL_WSOL_SLOT.fetch_add(1, Ordering::Relaxed) % EPHEMERAL_WSOL_SLOTS;
+        Self::at_slot(payer, slot)
+    }
+
+    pub fn at_slot(payer: &Pubkey, slot: usize) -> Self {
+        let address = Pubkey::create_with_seed(
+            payer,
+            &ephemeral_wsol_seed(slot),
+            &crate::constants::TOKEN_PROGRAM,
+        )
+        .expect("ephemeral wSOL seed is within MAX_SEED_LEN");
+        Self { address, slot }
+    }
+
+    /// Create the account funded with `amount_in` on top of its rent and initialize it. The
+    /// native mint counts the lamports above rent as balance, so no transfer or sync is needed.
+    pub fn open(&self, payer: &Pubkey, amount_in: u64) -> Result<Vec<Instruction>, anyhow::Error> {
+        let rent = ephemeral_wsol_rent()
+            .ok_or_else(|| anyhow!("Rent is required when using an ephemeral wSOL account"))?;
+        Ok(vec![
+            create_account_with_seed(
+                payer,
+                &self.address,
+                payer,
+                &ephemeral_wsol_seed(self.slot),
+                rent + amount_in,
+                spl_token::state::Account::LEN as u64,
+                &crate::constants::TOKEN_PROGRAM,
+            ),
+            spl_token::instruction::initialize_account3(
+                &crate::constants::TOKEN_PROGRAM,
+                &self.address,
+                &crate::constants::WSOL_TOKEN_ACCOUNT,
+                payer,
+            )?,
+        ])
+    }
+
+    /// Close the account, returning its rent and remaining wSOL to `payer`
+    pub fn close(&self, payer: &Pubkey) -> Instruction {
+        close_account(&crate::constants::TOKEN_PROGRAM, &self.address, payer, payer, &[]).unwrap()
+    }
+}
+
+/// Unwrap `amount` of the payer's wSOL ATA, leaving the rest wrapped
+///
+/// The amount moves into an ephemeral account that is closed in the same transaction, returning
+/// it to the payer as SOL together with the account's rent.
+pub fn unwrap_wsol_partial(payer: &Pubkey, amount: u64) -> Result<Vec<Instruction>, anyhow::Error> {
+    let wsol_token_account =
+        crate::common::fast_fn::get_associated_token_address_with_program_id_fast(
+            &payer,
+            &crate::constants::WSOL_TOKEN_ACCOUNT,
+            &crate::constants::TOKEN_PROGRAM,
+        );
+    let ephemeral = EphemeralWsolAccount::next(payer);
+    let mut instructions = ephemeral.open(payer, 0)?;
+    instructions.push(spl_token::instruction::transfer(
+        &crate::constants::TOKEN_PROGRAM,
+        &wsol_token_account,
+        &ephemeral.address,
+        payer,
+        &[],
+        amount,
+    )?);
+    instructions.push(ephemeral.close(payer));
+    Ok(instructions)
+}
+
+/// Signature of a wrap or unwrap, with the wSOL balance read once it confirmed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WsolBalanceUpdate {
+    pub signature: Signature,
+    /// Balance of the affected wSOL ATA after the transaction
+    pub wsol_balance: u64,
+}
+
+/// Rent of an ephemeral wSOL account, `None` until the rent of token accounts was fetched
+#[inline]
+pub fn ephemeral_wsol_rent() -> Option<u64> {
+    crate::common::seed::get_rent(false)
+}
+
+/// Every ephemeral wSOL address `payer` can use
+pub fn ephemeral_wsol_addresses(payer: &Pubkey) -> Vec<Pubkey> {
+    (0..EPHEMERAL_WSOL_SLOTS)
+        .map(|slot| EphemeralWsolAccount::at_slot(payer, slot).address)
+        .collect()
+}
+
+fn ephemeral_wsol_seed(slot: usize) -> String {
+    format!("wsol-trade-{}", slot)
+}
+
+/// `create_wsol_ata`, `close_wsol_ata` and the deprecated `auto_handle_wsol` of a trade, with
+/// the `wsol_steps` of its buy and of its sell on a wSOL-quoted pool
+#[cfg(test)]
+pub(crate) const WSOL_FLAG_CASES: &[((bool, bool, bool), &[&str], &[&str])] = &[
+    ((true, true, true), &["create", "transfer", "sync", "close"], &["create", "close"]),
+    ((true, false, true), &["create", "transfer", "sync"], &["create"]),
+    ((false, true, true), &["close"], &["close"]),
+    ((false, false, true), &[], &[]),
+    // A false alias turns both flags off
+    ((true, true, false), &[], &[]),
+    ((false, false, false), &[], &[]),
+];
+
+/// The steps `instructions` take on the payer's wSOL ATA, in order
+#[cfg(test)]
+pub(crate) fn wsol_steps(instructions: &[Instruction], payer: &Pubkey) -> Vec<&'static str> {
+    let wsol_ata = crate::common::fast_fn::get_associated_token_address_with_program_id_fast(
+        payer,
+        &crate::constants::WSOL_TOKEN_ACCOUNT,
+        &crate::constants::TOKEN_PROGRAM,
+    );
+    let touches = |instruction: &Instruction, index: usize| {
+        instruction.accounts.get(index).is_some_and(|meta| meta.pubkey == wsol_ata)
+    };
+    instructions
+        .iter()
+        .filter_map(|instruction| {
+            let program = instruction.program_id;
+            let tag = instruction.data.first().copied();
+            if program == spl_associated_token_account::ID && touches(instruction, 1) {
+                Some("create")
+            } else if program == crate::constants::SYSTEM_PROGRAM && touches(instruction, 1) {
+                Some("transfer")
+            } else if program == crate::constants::TOKEN_PROGRAM && touches(instruction, 0) {
+                match tag {
+                    Some(17) => Some("sync"),
+                    Some(9) => Some("close"),
+                    _ => None,
+                }
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPL_TOKEN_RENT: u64 = 2_039_280;
+
+    #[test]
+    fn ephemeral_account_holds_its_rent_on_top_of_the_amount() {
+        crate::common::seed::set_rent(false, SPL_TOKEN_RENT);
+        let payer = Pubkey::new_unique();
+        let account = EphemeralWsolAccount::at_slot(&payer, 3);
+
+        let instructions = account.open(&payer, 5_000_000).unwrap();
+
+        assert_eq!(
+            instructions[0],
+            create_account_with_seed(
+                &payer,
+                &account.address,
+                &payer,
+                "wsol-trade-3",
+                SPL_TOKEN_RENT + 5_000_000,
+                spl_token::state::Account::LEN as u64,
+                &crate::constants::TOKEN_PROGRAM,
+            )
+        );
+        assert_eq!(instructions[1].accounts[0].pubkey, account.address);
+        assert_eq!(account.close(&payer).accounts[0].pubkey, account.address);
+    }
+
+    #[test]
+    fn only_handled_wsol_under_ephemeral_per_trade_gets_an_account() {
+        let payer = Pubkey::new_unique();
+        assert!(EphemeralWsolAccount::for_trade(&payer, WsolAccountChoice::CanonicalAta, true)
+            .is_none());
+        assert!(EphemeralWsolAccount::for_trade(
+            &payer,
+            WsolAccountChoice::EphemeralPerTrade,
+            false
+        )
+        .is_none());
+
+        // Rotating slots stay among the addresses the cleanup helper scans
+        let addresses = ephemeral_wsol_addresses(&payer);
+        for _ in 0..EPHEMERAL_WSOL_SLOTS + 1 {
+            let account =
+                EphemeralWsolAccount::for_trade(&payer, WsolAccountChoice::EphemeralPerTrade, true)
+                    .unwrap();
+            assert_eq!(addresses[account.slot], account.address);
+        }
+        let wsol_ata = crate::common::fast_fn::get_associated_token_address_with_program_id_fast(
+            &payer,
+            &crate::constants::WSOL_TOKEN_ACCOUNT,
+            &crate::constants::TOKEN_PROGRAM,
+        );
+        assert!(!addresses.contains(&wsol_ata));
+    }
+}
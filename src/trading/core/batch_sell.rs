@@ -0,0 +1,271 @@
+//! Sells of several mints packed into as few transactions as fit, see `SolanaTrade::sell_many`
+
+use anyhow::anyhow;
+use solana_sdk::{instruction::Instruction, message::AddressLookupTableAccount, pubkey::Pubkey};
+use spl_associated_token_account::ID as ASSOCIATED_TOKEN_PROGRAM_ID;
+
+use super::{
+    error::TradeError,
+    traits::ProtocolParams,
+    two_hop::{check_transaction_fit, is_close_wsol},
+};
+use crate::{
+    common::PriorityFee,
+    constants::{TOKEN_PROGRAM, WSOL_TOKEN_ACCOUNT},
+    trading::factory::DexType,
+};
+
+/// Protocol name of packed sell transactions, a `PriorityFee::protocol_limits` entry under it
+/// replaces the summed unit limits
+pub const SELL_MANY_PROTOCOL: &str = "SellMany";
+
+/// Sell of one mint in `SolanaTrade::sell_many`
+pub struct SellLeg {
+    pub dex_type: DexType,
+    pub mint: Pubkey,
+    /// Tokens to sell in base units, 0 sells the whole balance
+    pub token_amount: u64,
+    pub params: Box<dyn ProtocolParams>,
+}
+
+impl SellLeg {
+    pub fn new(
+        dex_type: DexType,
+        mint: Pubkey,
+        token_amount: u64,
+        params: Box<dyn ProtocolParams>,
+    ) -> Self {
+        Self { dex_type, mint, token_amount, params }
+    }
+}
+
+/// Instructions of a sell leg with the protocol its compute units are looked up by
+pub(crate) struct BuiltSellLeg {
+    pub mint: Pubkey,
+    pub protocol_name: &'static str,
+    pub instructions: Vec<Instruction>,
+}
+
+/// Legs sharing one transaction
+pub(crate) struct PackedSells {
+    /// Indexes into the built legs, in leg order
+    pub legs: Vec<usize>,
+    pub instructions: Vec<Instruction>,
+    /// Priority fee whose unit limits cover every leg
+    pub priority_fee: PriorityFee,
+}
+
+/// Pack the legs in order into transactions, starting a new one when the next leg would break
+/// the account lock, packet size or compute unit limit
+///
+/// Returns the transactions and the legs that do not fit even alone, with the reason.
+pub(crate) fn pack_sells(
+    payer: &Pubkey,
+    legs: &[BuiltSellLeg],
+    lookup_tables: &[AddressLookupTableAccount],
+    priority_fee: &PriorityFee,
+    data_size_limit: u32,
+) -> (Vec<PackedSells>, Vec<(usize, anyhow::Error)>) {
+    let pack = |indexes: &[usize]| -> anyhow::Result<PackedSells> {
+        let instructions = merge_sell_instructions(
+            payer,
+            indexes.iter().map(|index| legs[*index].instructions.as_slice()),
+        );
+        let priority_fee = packed_priority_fee(
+            priority_fee,
+            indexes.iter().map(|index| legs[*index].protocol_name),
+        );
+        check_transaction_fit(
+            "Packed sells",
+            payer,
+            &instructions,
+            lookup_tables,
+            &priority_fee,
+            data_size_limit,
+        )?;
+        Ok(PackedSells { legs: indexes.to_vec(), instructions, priority_fee })
+    };
+
+    let mut packed = Vec::new();
+    let mut unfit = Vec::new();
+    let mut current: Option<PackedSells> = None;
+    for index in 0..legs.len() {
+        if let Some(open) = &current {
+            let mut indexes = open.legs.clone();
+            indexes.push(index);
+            if let Ok(grown) = pack(&indexes) {
+                current = Some(grown);
+                continue;
+            }
+            packed.extend(current.take());
+        }
+        match pack(&[index]) {
+            Ok(single) => current = Some(single),
+            Err(e) => unfit.push((index, e)),
+        }
+    }
+    packed.extend(current);
+    (packed, unfit)
+}
+
+/// Join the instructions of several sells into one list
+///
+/// Only the last close of the wSOL ATA is kept, so the legs before it leave their SOL output
+/// wrapped and no leg has to create the ATA again; repeated ATA creates are dropped.
+pub(crate) fn merge_sell_instructions<'a>(
+    payer: &Pubkey,
+    legs: impl Iterator<Item = &'a [Instruction]>,
+) -> Vec<Instruction> {
+    let wsol_account = crate::common::fast_fn::get_associated_token_address_with_program_id_fast(
+        payer,
+        &WSOL_TOKEN_ACCOUNT,
+        &TOKEN_PROGRAM,
+    );
+    let instructions: Vec<&Instruction> = legs.flatten().collect();
+    let last_close = instructions.iter().rposition(|ix| is_close_wsol(ix, &wsol_account));
+    let mut merged: Vec<Instruction> = Vec::with_capacity(instructions.len());
+    for (index, ix) in instructions.into_iter().enumerate() {
+        if is_close_wsol(ix, &wsol_account) && Some(index) != last_close {
+            continue;
+        }
+        if ix.program_id == ASSOCIATED_TOKEN_PROGRAM_ID && merged.contains(ix) {
+            continue;
+        }
+        merged.push(ix.clone());
+    }
+    merged
+}
+
+/// `priority_fee` with unit limits summed over the protocols of the packed legs
+fn packed_priority_fee<'a>(
+    priority_fee: &PriorityFee,
+    protocol_names: impl Iterator<Item = &'a str>,
+) -> PriorityFee {
+    let mut packed = priority_fee.clone();
+    let (mut tip_unit_limit, mut rpc_unit_limit) = (0u32, 0u32);
+    for protocol_name in protocol_names {
+        let (_, tip_limit) = priority_fee.unit_price_and_limit(protocol_name, false);
+        let (_, rpc_limit) = priority_fee.unit_price_and_limit(protocol_name, true);
+        tip_unit_limit = tip_unit_limit.saturating_add(tip_limit);
+        rpc_unit_limit = rpc_unit_limit.saturating_add(rpc_limit);
+    }
+    packed.tip_unit_limit = tip_unit_limit;
+    packed.rpc_unit_limit = rpc_unit_limit;
+    packed
+}
+
+/// Copy of an error shared by the legs of one transaction, keeping `TradeError`s downcastable
+pub(crate) fn leg_error(error: &anyhow::Error) -> anyhow::Error {
+    match error.downcast_ref::<TradeError>() {
+        Some(trade_error) => trade_error.clone().into(),
+        None => anyhow!("{:#}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::instruction::AccountMeta;
+
+    use super::*;
+    use crate::{
+        constants::trade::trade::{DEFAULT_RPC_UNIT_LIMIT, DEFAULT_TIP_UNIT_LIMIT},
+        trading::common::wsol_manager::{close_wsol, create_wsol_ata, wsol_steps},
+    };
+
+    /// Stand-in for a protocol's sell instruction, touching `account_count` fresh accounts
+    fn swap(payer: &Pubkey, account_count: usize) -> Instruction {
+        let mut accounts = vec![AccountMeta::new(*payer, true)];
+        accounts.extend((0..account_count).map(|_| AccountMeta::new(Pubkey::new_unique(), false)));
+        Instruction::new_with_bytes(Pubkey::new_unique(), &[1, 2, 3], accounts)
+    }
+
+    /// Sell paid out in wSOL: create the wSOL ATA, swap, close the ATA
+    fn wsol_leg(payer: &Pubkey, protocol_name: &'static str, swap: Instruction) -> BuiltSellLeg {
+        let mut instructions = create_wsol_ata(payer);
+        instructions.push(swap);
+        instructions.extend(close_wsol(payer));
+        BuiltSellLeg { mint: Pubkey::new_unique(), protocol_name, instructions }
+    }
+
+    fn leg_indexes(packed: &[PackedSells]) -> Vec<Vec<usize>> {
+        packed.iter().map(|packed| packed.legs.clone()).collect()
+    }
+
+    #[test]
+    fn two_wsol_legs_keep_one_ata_create_and_only_the_last_close() {
+        let payer = Pubkey::new_unique();
+        let (first, second) = (swap(&payer, 6), swap(&payer, 6));
+        let legs = [
+            wsol_leg(&payer, "PumpSwap", first.clone()),
+            wsol_leg(&payer, "RaydiumCpmm", second.clone()),
+        ];
+
+        let mut expected = create_wsol_ata(&payer);
+        expected.extend([first, second]);
+        expected.extend(close_wsol(&payer));
+        let merged =
+            merge_sell_instructions(&payer, legs.iter().map(|leg| leg.instructions.as_slice()));
+        assert_eq!(wsol_steps(&merged, &payer), vec!["create", "close"]);
+        assert_eq!(merged, expected);
+
+        let (packed, unfit) = pack_sells(&payer, &legs, &[], &PriorityFee::default(), 0);
+        assert!(unfit.is_empty());
+        assert_eq!(leg_indexes(&packed), vec![vec![0, 1]]);
+        assert_eq!(packed[0].instructions, expected);
+    }
+
+    #[test]
+    fn oversized_leg_is_unfit_and_the_legs_after_it_still_pack() {
+        let payer = Pubkey::new_unique();
+        let legs = [
+            wsol_leg(&payer, "PumpSwap", swap(&payer, 6)),
+            wsol_leg(&payer, "PumpSwap", swap(&payer, 70)),
+            wsol_leg(&payer, "PumpSwap", swap(&payer, 6)),
+            wsol_leg(&payer, "PumpSwap", swap(&payer, 6)),
+        ];
+
+        let (packed, unfit) = pack_sells(&payer, &legs, &[], &PriorityFee::default(), 0);
+        assert_eq!(unfit.len(), 1);
+        assert_eq!(unfit[0].0, 1);
+        assert!(unfit[0].1.to_string().contains("a transaction may lock 64"), "{}", unfit[0].1);
+        // The oversized leg closes the transaction before it, the next two share one
+        assert_eq!(leg_indexes(&packed), vec![vec![0], vec![2, 3]]);
+        for transaction in &packed {
+            assert_eq!(wsol_steps(&transaction.instructions, &payer), vec!["create", "close"]);
+        }
+    }
+
+    #[test]
+    fn packed_unit_limits_are_summed_over_the_legs() {
+        let priority_fee = PriorityFee::default().with_protocol_limits("PumpFun", 150_000, 7);
+        let packed =
+            packed_priority_fee(&priority_fee, ["PumpFun", "PumpSwap", "PumpFun"].into_iter());
+
+        assert_eq!(packed.tip_unit_limit, 300_000 + DEFAULT_TIP_UNIT_LIMIT);
+        assert_eq!(packed.rpc_unit_limit, 300_000 + DEFAULT_RPC_UNIT_LIMIT);
+        assert_eq!(packed.tip_unit_price, priority_fee.tip_unit_price);
+        assert_eq!(packed.rpc_unit_price, priority_fee.rpc_unit_price);
+        assert_eq!(packed.protocol_limits, priority_fee.protocol_limits);
+    }
+
+    #[test]
+    fn leg_over_the_compute_limit_starts_a_new_transaction() {
+        let payer = Pubkey::new_unique();
+        let priority_fee = PriorityFee::default().with_protocol_limits("PumpFun", 600_000, 7);
+        let legs = [
+            wsol_leg(&payer, "PumpFun", swap(&payer, 2)),
+            wsol_leg(&payer, "PumpFun", swap(&payer, 2)),
+            wsol_leg(&payer, "PumpFun", swap(&payer, 2)),
+        ];
+
+        // Two legs need 1.2M compute units, a third would need 1.8M
+        let (packed, unfit) = pack_sells(&payer, &legs, &[], &priority_fee, 0);
+        assert!(unfit.is_empty());
+        assert_eq!(leg_indexes(&packed), vec![vec![0, 1], vec![2]]);
+        let limits: Vec<(u32, u32)> = packed
+            .iter()
+            .map(|packed| (packed.priority_fee.tip_unit_limit, packed.priority_fee.rpc_unit_limit))
+            .collect();
+        assert_eq!(limits, vec![(1_200_000, 1_200_000), (600_000, 600_000)]);
+    }
+}
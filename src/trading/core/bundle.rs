@@ -0,0 +1,244 @@
+//! Several trades sent together, landing all or nothing where the provider takes bundles
+
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use futures::{
+    stream::{FuturesUnordered, StreamExt},
+    FutureExt,
+};
+use solana_hash::Hash;
+use solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, signature::Signature,
+    transaction::VersionedTransaction,
+};
+
+use super::{
+    error::{RelayError, TradeError},
+    options::{BuyOptions, SellOptions},
+    parallel::clients_with_tips,
+    traits::ProtocolParams,
+};
+use crate::{
+    common::{signer::TradeSigner, PriorityFee},
+    swqos::{SwqosClient, SwqosType, TradeType},
+    trading::{
+        common::{compile_message, sign_messages, BuildEnvironment},
+        factory::DexType,
+        MiddlewareManager,
+    },
+};
+
+/// Most transactions a bundle holds, the limit of the Jito block engine
+pub const MAX_BUNDLE_TRANSACTIONS: usize = 5;
+/// Protocol name the compute budget of `BundleLeg::Instructions` is looked up by
+pub const RAW_BUNDLE_LEG_PROTOCOL: &str = "Bundle";
+
+/// One transaction of a bundle
+pub enum BundleLeg {
+    /// Buy built like `SolanaTrade::build_buy_instructions`
+    Buy {
+        dex_type: DexType,
+        mint: Pubkey,
+        sol_amount: u64,
+        params: Box<dyn ProtocolParams>,
+        options: BuyOptions,
+    },
+    /// Sell built like `SolanaTrade::build_sell_instructions`, a `token_amount` of 0 sells the
+    /// whole balance
+    Sell {
+        dex_type: DexType,
+        mint: Pubkey,
+        token_amount: u64,
+        params: Box<dyn ProtocolParams>,
+        options: SellOptions,
+    },
+    /// Instructions sent as they are, e.g. a transfer, behind the compute budget instructions
+    Instructions(Vec<Instruction>),
+}
+
+impl BundleLeg {
+    /// Buy leg with default options
+    pub fn buy(
+        dex_type: DexType,
+        mint: Pubkey,
+        sol_amount: u64,
+        params: Box<dyn ProtocolParams>,
+    ) -> Self {
+        BundleLeg::Buy { dex_type, mint, sol_amount, params, options: BuyOptions::default() }
+    }
+
+    /// Sell leg with default options
+    pub fn sell(
+        dex_type: DexType,
+        mint: Pubkey,
+        token_amount: u64,
+        params: Box<dyn ProtocolParams>,
+    ) -> Self {
+        BundleLeg::Sell { dex_type, mint, token_amount, params, options: SellOptions::default() }
+    }
+}
+
+/// Instructions of a leg with what its compute budget depends on
+#[derive(Debug, Clone)]
+pub struct BuiltBundleLeg {
+    pub protocol_name: String,
+    pub is_buy: bool,
+    pub instructions: Vec<Instruction>,
+}
+
+/// Submission of a bundle, taken from the first provider that accepted it
+#[derive(Debug, Clone)]
+pub struct BundleReport {
+    /// Signature of each leg's transaction, in leg order
+    ///
+    /// The last one carries the tip of `swqos_type`, the transactions other providers were sent
+    /// differ from it in the tip and have other signatures.
+    pub signatures: Vec<Signature>,
+    /// Id the block engine gave the bundle, `None` when the legs were sent one by one
+    pub bundle_id: Option<String>,
+    pub swqos_type: SwqosType,
+}
+
+impl BundleReport {
+    /// Whether the legs went out as one bundle that lands all or nothing
+    pub fn is_atomic(&self) -> bool {
+        self.bundle_id.is_some()
+    }
+}
+
+/// Sign one transaction per leg for each provider and submit them, returning the first accepted
+/// submission
+///
+/// Providers that take bundles get the legs as one bundle, with the tip transfer in the last
+/// transaction. Only when none is configured are the legs sent one by one through the other
+/// providers, each after the previous was accepted; they then land independently. The other
+/// providers keep submitting in the background once one accepted.
+pub async fn bundle_execute(
+    swqos_clients: Vec<Arc<SwqosClient>>,
+    payer: Arc<dyn TradeSigner>,
+    legs: Vec<BuiltBundleLeg>,
+    priority_fee: Arc<PriorityFee>,
+    lookup_table_keys: &[Pubkey],
+    recent_blockhash: Hash,
+    data_size_limit: u32,
+    middleware_manager: Option<Arc<MiddlewareManager>>,
+    trade_lamports: u64,
+) -> Result<BundleReport> {
+    if legs.is_empty() {
+        return Err(anyhow!("A bundle needs at least one leg"));
+    }
+    if legs.len() > MAX_BUNDLE_TRANSACTIONS {
+        return Err(anyhow!(
+            "A bundle holds at most {} transactions, got {} legs",
+            MAX_BUNDLE_TRANSACTIONS,
+            legs.len()
+        ));
+    }
+    let is_buy = legs.iter().any(|leg| leg.is_buy);
+    let trade_type = if is_buy { TradeType::Buy } else { TradeType::Sell };
+    let tip_lamports = priority_fee.tip_lamports(is_buy, swqos_clients.len(), trade_lamports)?;
+    let swqos_clients = clients_with_tips(&swqos_clients, &tip_lamports);
+    let bundle_clients: Vec<(Arc<SwqosClient>, u64)> =
+        swqos_clients.iter().filter(|(client, _)| client.supports_bundles()).cloned().collect();
+    let swqos_clients = if bundle_clients.is_empty() { swqos_clients } else { bundle_clients };
+    if swqos_clients.is_empty() {
+        return Err(anyhow!("No swqos client to send the bundle through"));
+    }
+
+    let environment = Arc::new(BuildEnvironment::current(lookup_table_keys, None)?);
+    let legs = Arc::new(legs);
+
+    let mut submissions: FuturesUnordered<_> = swqos_clients
+        .into_iter()
+        .map(|(swqos_client, tip_amount)| {
+            let payer = payer.clone();
+            let legs = legs.clone();
+            let priority_fee = priority_fee.clone();
+            let environment = environment.clone();
+            let middleware_manager = middleware_manager.clone();
+            let relay_type = swqos_client.get_swqos_type();
+            let submission = tokio::spawn(async move {
+                let swqos_type = swqos_client.get_swqos_type();
+                let tip_account = swqos_client
+                    .get_tip_account()
+                    .map(|account| Pubkey::from_str(&account))
+                    .transpose()
+                    .map_err(|e| anyhow!("Invalid tip account from {:?}: {}", swqos_type, e))?;
+
+                let messages = legs
+                    .iter()
+                    .enumerate()
+                    .map(|(index, leg)| {
+                        // Only the last transaction pays the tip
+                        let tip_account = tip_account.filter(|_| index + 1 == legs.len());
+                        compile_message(
+                            &payer.pubkey(),
+                            &priority_fee,
+                            &leg.instructions,
+                            &environment,
+                            recent_blockhash,
+                            data_size_limit,
+                            middleware_manager.as_deref(),
+                            &leg.protocol_name,
+                            leg.is_buy,
+                            tip_account.is_some(),
+                            &tip_account.unwrap_or_default(),
+                            tip_amount,
+                        )
+                    })
+                    .collect();
+                let transactions = sign_messages(payer.as_ref(), &[], messages)
+                    .await
+                    .into_iter()
+                    .collect::<Result<Vec<VersionedTransaction>>>()?;
+                let signatures: Vec<Signature> =
+                    transactions.iter().map(|tx| tx.signatures[0]).collect();
+
+                let sent = async {
+                    let bundle_id = if swqos_client.supports_bundles() {
+                        Some(swqos_client.send_bundle(trade_type, &transactions).await?)
+                    } else {
+                        for transaction in &transactions {
+                            swqos_client.send_transaction(trade_type, transaction).await?;
+                        }
+                        None
+                    };
+                    Ok::<_, anyhow::Error>(bundle_id)
+                };
+                match sent.await {
+                    Ok(bundle_id) => {
+                        if let Some(tip_account) = tip_account {
+                            log::info!(
+                                icon!("✅ ", "[{:?}] bundle accepted | Tip: {} lamports to {}"),
+                                swqos_type,
+                                tip_amount,
+                                tip_account
+                            );
+                        }
+                        Ok(BundleReport { signatures, bundle_id, swqos_type })
+                    }
+                    // The tip transaction decides whether the bundle landed
+                    Err(e) => Err(anyhow::Error::from(RelayError {
+                        swqos_type,
+                        signature: signatures.last().copied(),
+                        message: e.to_string(),
+                    })),
+                }
+            });
+            submission.map(move |result| (relay_type, result))
+        })
+        .collect();
+
+    let mut errors = Vec::new();
+    while let Some((swqos_type, result)) = submissions.next().await {
+        match result {
+            Ok(Ok(report)) => return Ok(report),
+            Ok(Err(e)) => errors.push(RelayError::from_task_error(swqos_type, e)),
+            Err(e) => {
+                errors.push(RelayError::from_task_error(swqos_type, anyhow!("Join error: {}", e)))
+            }
+        }
+    }
+    Err(TradeError::relays_failed(errors).into())
+}
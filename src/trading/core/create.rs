@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::{
+    common::bonding_curve::BondingCurveAccount,
+    instruction::utils::pumpfun::{accounts, get_creator_vault_pda, PumpFunLayout},
+    params::PumpFunParams,
+};
+
+/// Metadata of a PumpFun token to create
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+    /// Metadata JSON, usually uploaded to IPFS beforehand
+    pub uri: String,
+}
+
+impl TokenMetadata {
+    pub fn new(name: impl Into<String>, symbol: impl Into<String>, uri: impl Into<String>) -> Self {
+        Self { name: name.into(), symbol: symbol.into(), uri: uri.into() }
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.name.is_empty() || self.symbol.is_empty() {
+            return Err(anyhow!("Token name and symbol cannot be empty"));
+        }
+        Ok(())
+    }
+}
+
+/// A token created by `SolanaTrade::create_token` or `SolanaTrade::create_and_buy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CreatedToken {
+    pub mint: Pubkey,
+    pub bonding_curve: Pubkey,
+    /// Creator of the token, the payer that created it
+    pub creator: Pubkey,
+    pub signature: Signature,
+    /// Tokens of the dev buy as quoted at the initial reserves, 0 without a dev buy
+    pub dev_token_amount: u64,
+    /// SOL of the dev buy in lamports, 0 without a dev buy
+    pub dev_sol_amount: u64,
+}
+
+impl CreatedToken {
+    /// Params for trading the new token, with the curve as left by the dev buy
+    ///
+    /// The dev buy is taken at its quote, trades that landed since are not included.
+    pub fn pumpfun_params(&self, close_token_account_when_sell: Option<bool>) -> PumpFunParams {
+        let bonding_curve = BondingCurveAccount::from_dev_trade(
+            &self.mint,
+            self.dev_token_amount,
+            self.dev_sol_amount,
+            self.creator,
+        );
+        PumpFunParams {
+            bonding_curve: Arc::new(bonding_curve),
+            associated_bonding_curve: Pubkey::default(),
+            creator_vault: get_creator_vault_pda(&self.creator).unwrap_or_default(),
+            close_token_account_when_sell,
+            fee_config: accounts::FEE_CONFIG,
+            fee_program: accounts::FEE_PROGRAM,
+            layout: PumpFunLayout::default(),
+            include_volume_accumulators: true,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+/// What a PumpFun buy does when it lands after its bonding curve completed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CurveCompleteAction {
+    /// Return a `BondingCurveCompleted` error
+    #[default]
+    Fail,
+    /// Re-quote on the canonical PumpSwap pool and buy there once
+    ///
+    /// The pool buy uses the original slippage plus `max_extra_slippage_bps` and is only sent
+    /// while less than `deadline` has passed since the curve buy started. The curve failure is
+    /// only seen when the buy waits for confirmation, not with `WaitLevel::Submitted`.
+    FallbackToPumpSwap { max_extra_slippage_bps: u64, deadline: Duration },
+}
+
+/// A PumpFun buy was rejected because the bonding curve completed before it landed
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Bonding curve of {mint} completed before the buy landed: {detail}")]
+pub struct BondingCurveCompleted {
+    pub mint: Pubkey,
+    /// Error the transaction failed with
+    pub detail: String,
+}
+
+/// Both attempts of a PumpFun buy that may fall back to PumpSwap
+#[derive(Debug, Clone)]
+pub struct CurveFallbackReport {
+    pub mint: Pubkey,
+    /// Signature of the bonding curve buy, or the completion it ran into
+    pub curve_attempt: Result<Signature, BondingCurveCompleted>,
+    /// PumpSwap buy sent after the curve completed, `None` when it was not attempted
+    pub pool_attempt: Option<Result<Signature, String>>,
+}
+
+impl CurveFallbackReport {
+    /// Signature of the buy that landed, if any
+    pub fn signature(&self) -> Option<Signature> {
+        match (&self.curve_attempt, &self.pool_attempt) {
+            (Ok(signature), _) | (Err(_), Some(Ok(signature))) => Some(*signature),
+            _ => None,
+        }
+    }
+
+    /// Signature of the buy that landed, or the error of the last attempt
+    pub fn into_result(self) -> Result<Signature, anyhow::Error> {
+        match (self.curve_attempt, self.pool_attempt) {
+            (Ok(signature), _) | (Err(_), Some(Ok(signature))) => Ok(signature),
+            (Err(curve), Some(Err(e))) => {
+                Err(anyhow!("{}; PumpSwap fallback failed: {}", curve, e))
+            }
+            (Err(curve), None) => Err(curve.into()),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+//! Failures of the trading core that callers can tell apart
+//!
+//! Trading functions return `anyhow::Error`; the failures below are raised as `TradeError` and
+//! reached with `error.downcast_ref::<TradeError>()`. Everything else, e.g. an RPC error while
+//! loading pool state, stays a plain message.
+
+use solana_sdk::signature::Signature;
+
+use crate::swqos::SwqosType;
+
+/// Failure of one provider to send or confirm its transaction of a trade
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{swqos_type:?}: {message}")]
+pub struct RelayError {
+    pub swqos_type: SwqosType,
+    /// Signature of the transaction the provider was given, `None` when it failed before the
+    /// transaction was signed
+    ///
+    /// A transaction whose submission or confirmation failed may still land, look it up by
+    /// this signature before trading again.
+    pub signature: Option<Signature>,
+    pub message: String,
+}
+
+impl RelayError {
+    /// The `RelayError` a provider's task failed with, or one around its other error, which
+    /// happened before the transaction was signed
+    pub(crate) fn from_task_error(swqos_type: SwqosType, error: anyhow::Error) -> Self {
+        error.downcast::<RelayError>().unwrap_or_else(|error| RelayError {
+            swqos_type,
+            signature: None,
+            message: error.to_string(),
+        })
+    }
+
+    /// Whether the provider rejected the transaction for an unknown blockhash
+    pub fn is_blockhash_not_found(&self) -> bool {
+        let message = self.message.to_lowercase();
+        message.contains("blockhash not found") || message.contains("blockhashnotfound")
+    }
+}
+
+/// Failure of a trade a caller may want to handle apart from the others
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum TradeError {
+    /// The trade's parameters do not fit the protocol, nothing was sent
+    #[error("Invalid {protocol} params: {reason}")]
+    InvalidParams { protocol: String, reason: String },
+    /// The wallet holds less than the trade spends, in lamports or token base units
+    #[error("Insufficient balance: {available} available, {needed} needed")]
+    InsufficientBalance { needed: u64, available: u64 },
+    /// Every provider rejected the transaction because its blockhash is unknown or expired
+    ///
+    /// Resubmitting with a fresh blockhash may succeed, see `BlockhashRetry`.
+    #[error("Blockhash expired: {}", join_relay_errors(.0))]
+    BlockhashExpired(Vec<RelayError>),
+    /// Every provider failed to send or confirm the trade, one error per provider
+    #[error("All transactions failed: {}", join_relay_errors(.0))]
+    AllRelaysFailed(Vec<RelayError>),
+    /// Simulation reports the transaction would fail
+    #[error("Simulation failed: {err}")]
+    SimulationFailed { err: String, logs: Vec<String> },
+    /// The transaction did not reach `stage`, e.g. confirmation, in time; it may still land
+    ///
+    /// Holds every transaction of the trade that was handed to a provider, one per provider
+    /// when a trade deadline cut the trade short.
+    #[error("Transaction {}'s {stage} timed out", join_signatures(.signatures))]
+    ConfirmationTimeout { signatures: Vec<Signature>, stage: String },
+}
+
+impl TradeError {
+    pub fn invalid_params(protocol: impl Into<String>, reason: impl Into<String>) -> Self {
+        TradeError::InvalidParams { protocol: protocol.into(), reason: reason.into() }
+    }
+
+    /// `InvalidParams` for protocol params of another protocol than the trade's
+    pub fn params_mismatch(protocol: impl Into<String>) -> Self {
+        TradeError::invalid_params(protocol, "protocol params belong to another protocol")
+    }
+
+    /// `ConfirmationTimeout` of a trade whose deadline passed, with the transactions handed to
+    /// providers by then
+    pub fn deadline_exceeded(signatures: Vec<Signature>) -> Self {
+        TradeError::ConfirmationTimeout {
+            signatures,
+            stage: "confirmation before the deadline".to_string(),
+        }
+    }
+
+    /// `BlockhashExpired` when every provider failed for an unknown blockhash,
+    /// `AllRelaysFailed` otherwise
+    pub fn relays_failed(errors: Vec<RelayError>) -> Self {
+        if !errors.is_empty() && errors.iter().all(RelayError::is_blockhash_not_found) {
+            TradeError::BlockhashExpired(errors)
+        } else {
+            TradeError::AllRelaysFailed(errors)
+        }
+    }
+
+    /// Errors of the individual providers, empty for failures before anything was sent
+    pub fn relay_errors(&self) -> &[RelayError] {
+        match self {
+            TradeError::BlockhashExpired(errors) | TradeError::AllRelaysFailed(errors) => errors,
+            _ => &[],
+        }
+    }
+
+    /// Signatures of transactions the failure involved, which may still land
+    pub fn signatures(&self) -> Vec<Signature> {
+        match self {
+            TradeError::ConfirmationTimeout { signatures, .. } => signatures.clone(),
+            _ => self.relay_errors().iter().filter_map(|error| error.signature).collect(),
+        }
+    }
+}
+
+fn join_relay_errors(errors: &[RelayError]) -> String {
+    errors.iter().map(RelayError::to_string).collect::<Vec<_>>().join("; ")
+}
+
+fn join_signatures(signatures: &[Signature]) -> String {
+    signatures.iter().map(Signature::to_string).collect::<Vec<_>>().join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::account_fetch::{AccountFetchError, AccountFetchFailure};
+    use anyhow::Context;
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    fn error_chains_are_plain_ascii() {
+        let relay_error = |swqos_type, message: &str| RelayError {
+            swqos_type,
+            signature: Some(Signature::default()),
+            message: message.to_string(),
+        };
+        let errors: Vec<anyhow::Error> = vec![
+            TradeError::params_mismatch("PumpSwap").into(),
+            TradeError::InsufficientBalance { needed: 2, available: 1 }.into(),
+            TradeError::relays_failed(vec![relay_error(SwqosType::Jito, "Blockhash not found")])
+                .into(),
+            TradeError::relays_failed(vec![
+                relay_error(SwqosType::Jito, "bundle dropped"),
+                relay_error(SwqosType::Default, "timeout"),
+            ])
+            .into(),
+            TradeError::SimulationFailed {
+                err: "InstructionError(2, Custom(6003))".to_string(),
+                logs: vec!["Program log: slippage".to_string()],
+            }
+            .into(),
+            TradeError::deadline_exceeded(vec![Signature::default()]).into(),
+            AccountFetchError::new(
+                "pool",
+                Pubkey::default(),
+                AccountFetchFailure::Invalid("failed to decode pool".to_string()),
+            )
+            .into(),
+        ];
+        for error in errors {
+            let error = Err::<(), _>(error).context("Failed to sell").unwrap_err();
+            for text in [format!("{:#}", error), format!("{:?}", error)] {
+                assert!(text.is_ascii() && !text.contains(char::from(0x1b)), "{}", text);
+            }
+        }
+    }
+}
@@ -1,17 +1,55 @@
 use anyhow::{anyhow, Result};
-use solana_sdk::signature::Signer;
-use std::sync::Arc;
+use solana_sdk::{
+    commitment_config::CommitmentConfig, instruction::Instruction, pubkey::Pubkey,
+    signature::Signature, transaction::VersionedTransaction,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use crate::trading::core::parallel::{buy_parallel_execute, sell_parallel_execute};
+use crate::{
+    common::{
+        balance_cache::TokenBalanceCache,
+        commission::Commission,
+        labels::TradeLabels,
+        mint_cache::MintDecimalsCache,
+        priority_fee_estimate::PriorityFeeEstimator,
+        spend_budget::{BudgetDecision, SpendTracker},
+        PriorityFee, SolanaRpcClient,
+    },
+    constants::trade::trade::{DEFERRED_ANALYSIS_ATTEMPTS, DEFERRED_ANALYSIS_RETRY_DELAY_MS},
+    market::sell_output_lamports,
+    swqos::{
+        confirmation::{
+            until_deadline, ConfirmationService, TradeStages, WaitLevel,
+            DEFAULT_CONFIRMATION_TIMEOUT, DEFAULT_FINALIZATION_TIMEOUT,
+        },
+        SwqosClient, SwqosType, TradeType,
+    },
+    trading::{
+        common::{
+            account_check::check_created_accounts,
+            cu_sizing::{size_compute_units, CuSizing, CuSizingKey},
+            swap_hooks::{check_hooked_transaction_fit, SwapHooks},
+            transaction_builder::unsigned_transaction,
+            utils::{get_sell_token_account_with_retry, get_sell_token_balance, BalanceRetry},
+        },
+        core::parallel::{buy_parallel_execute, sell_parallel_execute, sell_tip_trade_lamports},
+        MiddlewareManager,
+    },
+};
 
-// Maximum loaded accounts data size limit for transactions (512 KB)
-// This prevents MaxLoadedAccountsDataSizeExceeded errors in complex operations like Raydium CLMM
-const MAX_LOADED_ACCOUNTS_DATA_SIZE_LIMIT: u32 = 512 * 1024;
+// Base fee charged per signature, everything above it in `meta.fee` is priority fee
+const BASE_SIGNATURE_FEE_LAMPORTS: u64 = 5_000;
 
 use super::{
+    error::TradeError,
     params::{BuyParams, BuyWithTipParams, SellParams, SellWithTipParams},
+    sequence::next_sequence,
+    simulation::{first_provider_message, simulate_trade, SimulationResult},
     timer::TradeTimer,
-    trade_result::TradeResult,
+    trade_result::{AnalysisCallback, TradeResult},
     traits::{InstructionBuilder, TradeExecutor},
 };
 
@@ -19,17 +57,587 @@ use super::{
 pub struct GenericTradeExecutor {
     instruction_builder: Arc<dyn InstructionBuilder>,
     protocol_name: &'static str,
+    /// Program the protocol's swap instructions target, swap hooks are spliced around them
+    program_id: Pubkey,
 }
 
 impl GenericTradeExecutor {
     pub fn new(
         instruction_builder: Arc<dyn InstructionBuilder>,
         protocol_name: &'static str,
+        program_id: Pubkey,
     ) -> Self {
-        Self { instruction_builder, protocol_name }
+        Self { instruction_builder, protocol_name, program_id }
+    }
+
+    /// Splice the swap hooks into the built instructions, then run the middleware on the result
+    fn process_instructions(
+        &self,
+        instructions: Vec<Instruction>,
+        swap_hooks: &SwapHooks,
+        middleware_manager: &Option<Arc<MiddlewareManager>>,
+        is_buy: bool,
+    ) -> Result<Vec<Instruction>> {
+        let instructions = swap_hooks.splice(instructions, &self.program_id)?;
+        match middleware_manager {
+            Some(middleware_manager) => middleware_manager
+                .apply_middlewares_process_protocol_instructions(
+                    instructions,
+                    self.protocol_name.to_string(),
+                    is_buy,
+                ),
+            None => Ok(instructions),
+        }
+    }
+
+    /// Protocol instructions of a buy with hooks, middlewares and commission, without compute
+    /// budget or tip instructions
+    ///
+    /// Returns the instructions and the commission in lamports.
+    async fn buy_instructions_with_commission(
+        &self,
+        params: &BuyParams,
+    ) -> Result<(Vec<Instruction>, Option<u64>)> {
+        let instructions = self.instruction_builder.build_buy_instructions(params).await?;
+        let mut final_instructions = self.process_instructions(
+            instructions,
+            &params.swap_hooks,
+            &params.middleware_manager,
+            true,
+        )?;
+        let commission = append_commission(
+            &mut final_instructions,
+            params.commission,
+            &params.payer.pubkey(),
+            true,
+            params.sol_amount,
+        );
+        check_created_accounts(&final_instructions, params.created_account_check)?;
+        Ok((final_instructions, commission))
+    }
+
+    /// Sell counterpart of `buy_instructions_with_commission`, `token_amount` must be resolved
+    async fn sell_instructions_with_commission(
+        &self,
+        params: &SellParams,
+    ) -> Result<(Vec<Instruction>, Option<u64>)> {
+        let instructions = self.instruction_builder.build_sell_instructions(params).await?;
+        let mut final_instructions = self.process_instructions(
+            instructions,
+            &params.swap_hooks,
+            &params.middleware_manager,
+            false,
+        )?;
+        let commission = append_sell_commission(&mut final_instructions, params);
+        check_created_accounts(&final_instructions, params.created_account_check)?;
+        Ok((final_instructions, commission))
+    }
+
+    /// Everything `buy` does before sending: fee profile, spend budget, instructions with hooks,
+    /// middlewares and commission, and the sized compute budget
+    ///
+    /// Returns the final instructions and the commission in lamports.
+    async fn prepare_buy(
+        &self,
+        params: &mut BuyParams,
+        middleware_manager: Option<Arc<MiddlewareManager>>,
+    ) -> Result<(Vec<Instruction>, Option<u64>)> {
+        if params.trade_type != TradeType::Buy {
+            params.priority_fee = Arc::new(params.priority_fee.for_trade_type(params.trade_type));
+        }
+
+        // Override middleware_manager in params if provided
+        if let Some(manager) = middleware_manager {
+            params.middleware_manager = Some(manager);
+        }
+        params.swqos_clients =
+            apply_spend_budget(&params.spend_tracker, std::mem::take(&mut params.swqos_clients))?;
+        apply_memo(&mut params.swap_hooks, &mut params.memo);
+
+        // Build instructions
+        let (final_instructions, commission) =
+            self.buy_instructions_with_commission(params).await?;
+        params.priority_fee = params.swap_hooks.reserve_compute_units(&params.priority_fee);
+        params.priority_fee = sized_priority_fee(
+            params.cu_sizing,
+            params.rpc.as_ref(),
+            &params.priority_fee,
+            CuSizingKey { protocol: self.protocol_name, mint: params.mint, is_buy: true },
+            &params.payer.pubkey(),
+            &final_instructions,
+            &params.lookup_table_keys,
+        )
+        .await;
+        check_hooked_transaction_fit(
+            &params.swap_hooks,
+            &params.payer.pubkey(),
+            &final_instructions,
+            &params.lookup_table_keys,
+            &params.priority_fee,
+            params.data_size_limit.unwrap_or(0),
+        )
+        .await?;
+        Ok((final_instructions, commission))
+    }
+
+    /// Everything `sell` does before sending, see `prepare_buy`
+    async fn prepare_sell(
+        &self,
+        params: &mut SellParams,
+        middleware_manager: Option<Arc<MiddlewareManager>>,
+    ) -> Result<(Vec<Instruction>, Option<u64>)> {
+        // Override middleware_manager in params if provided
+        if let Some(manager) = middleware_manager {
+            params.middleware_manager = Some(manager);
+        }
+        params.swqos_clients =
+            apply_spend_budget(&params.spend_tracker, std::mem::take(&mut params.swqos_clients))?;
+        resolve_sell_amount(params).await?;
+        clamp_sell_amount(params).await?;
+        apply_memo(&mut params.swap_hooks, &mut params.memo);
+
+        // Build instructions
+        let (final_instructions, commission) =
+            self.sell_instructions_with_commission(params).await?;
+        params.priority_fee = params.swap_hooks.reserve_compute_units(&params.priority_fee);
+        params.priority_fee = sized_priority_fee(
+            params.cu_sizing,
+            params.rpc.as_ref(),
+            &params.priority_fee,
+            CuSizingKey { protocol: self.protocol_name, mint: params.mint, is_buy: false },
+            &params.payer.pubkey(),
+            &final_instructions,
+            &params.lookup_table_keys,
+        )
+        .await;
+        check_hooked_transaction_fit(
+            &params.swap_hooks,
+            &params.payer.pubkey(),
+            &final_instructions,
+            &params.lookup_table_keys,
+            &params.priority_fee,
+            params.data_size_limit.unwrap_or(0),
+        )
+        .await?;
+        Ok((final_instructions, commission))
+    }
+}
+
+/// Consult the daily spend budget before building, returns the swqos clients allowed to send
+pub(crate) fn apply_spend_budget(
+    spend_tracker: &Option<Arc<SpendTracker>>,
+    swqos_clients: Vec<Arc<SwqosClient>>,
+) -> Result<Vec<Arc<SwqosClient>>> {
+    let Some(spend_tracker) = spend_tracker else {
+        return Ok(swqos_clients);
+    };
+    match spend_tracker.decide() {
+        BudgetDecision::Proceed => Ok(swqos_clients),
+        BudgetDecision::Block => Err(anyhow!("Daily spend budget exhausted, trade blocked")),
+        BudgetDecision::DropTips => {
+            // Default RPC clients never attach a tip transfer
+            let swqos_clients: Vec<Arc<SwqosClient>> = swqos_clients
+                .into_iter()
+                .filter(|client| client.get_swqos_type() == SwqosType::Default)
+                .collect();
+            if swqos_clients.is_empty() {
+                return Err(anyhow!("Daily spend budget exhausted and no Default RPC client is configured to send without tips"));
+            }
+            Ok(swqos_clients)
+        }
+    }
+}
+
+/// Token balance a sell can spend, at least `expected_min_balance`
+///
+/// Without an expected balance, a token account the RPC does not know yet is looked up again
+/// per `BalanceRetry::default`, it may come from a buy that just landed. A zero balance fails
+/// before anything is built.
+async fn sell_balance(params: &SellParams) -> Result<u64> {
+    let rpc = params.rpc.as_ref().ok_or_else(|| anyhow!("RPC is not set"))?;
+    let wallet = params.payer.pubkey();
+    let balance = match params.expected_min_balance {
+        Some(expected) => {
+            get_sell_token_balance(rpc, &wallet, &params.mint, params.open_seed_optimize)
+                .await?
+                .max(expected)
+        }
+        None => get_sell_token_account_with_retry(
+            rpc,
+            &wallet,
+            &params.mint,
+            params.open_seed_optimize,
+            BalanceRetry::default(),
+        )
+        .await?
+        .map_or(0, |account| account.amount),
+    };
+    if balance == 0 {
+        return Err(anyhow!("Nothing to sell: wallet {} holds no {}", wallet, params.mint));
+    }
+    Ok(balance)
+}
+
+/// Resolve a whole-balance sell (`token_amount` unset or 0) to the token balance
+///
+/// Runs before the instruction builders, which take the amount as given and do no RPC I/O.
+/// A balance fetched within `TOKEN_BALANCE_TTL` and not changed by a trade since is taken from
+/// the `TokenBalanceCache` instead of the RPC.
+async fn resolve_sell_amount(params: &mut SellParams) -> Result<()> {
+    if params.token_amount.is_some_and(|amount| amount > 0) {
+        return Ok(());
+    }
+    let wallet = params.payer.pubkey();
+    let cache = TokenBalanceCache::get_instance();
+    let balance = match cache.get(&wallet, &params.mint) {
+        Some(cached) if cached > 0 => cached.max(params.expected_min_balance.unwrap_or(0)),
+        _ => {
+            let balance = sell_balance(params).await?;
+            cache.insert(wallet, params.mint, balance);
+            balance
+        }
+    };
+    params.token_amount = Some(balance);
+    Ok(())
+}
+
+/// Reduce the sell amount to the token balance when `clamp_to_balance` is set
+///
+/// Whole-balance sells are resolved by `resolve_sell_amount` and left alone.
+async fn clamp_sell_amount(params: &mut SellParams) -> Result<()> {
+    let requested = match params.token_amount {
+        Some(amount) if amount > 0 && params.clamp_to_balance => amount,
+        _ => return Ok(()),
+    };
+    let balance = sell_balance(params).await?;
+    if balance < requested {
+        let cache = MintDecimalsCache::get_instance();
+        log::warn!(
+            icon!("⚠️ ", "Sell amount of {} clamped from {} to the balance of {}"),
+            params.mint,
+            cache.display(&params.mint, requested),
+            cache.display(&params.mint, balance)
+        );
+        params.token_amount = Some(balance);
+    }
+    Ok(())
+}
+
+/// Move the memo of a trade into its post-swap hooks, so it is spliced in, run through the
+/// middleware and size checked like them
+fn apply_memo(swap_hooks: &mut SwapHooks, memo: &mut Option<String>) {
+    if let Some(memo) = memo.take() {
+        swap_hooks.push_memo(&memo);
+    }
+}
+
+/// Append the commission owed on a trade of `trade_lamports` to `instructions`
+///
+/// Returns the lamports charged, `None` when no commission applies or it is below
+/// `min_lamports`. Being part of the trade transaction, it is only paid when the trade lands.
+pub(crate) fn append_commission(
+    instructions: &mut Vec<Instruction>,
+    commission: Option<Commission>,
+    payer: &Pubkey,
+    is_buy: bool,
+    trade_lamports: u64,
+) -> Option<u64> {
+    let commission = commission?;
+    let lamports = commission.lamports(is_buy, trade_lamports)?;
+    instructions.push(commission.instruction(payer, lamports));
+    Some(lamports)
+}
+
+/// `append_commission` for a sell, charged on the expected SOL output
+pub(crate) fn append_sell_commission(
+    instructions: &mut Vec<Instruction>,
+    params: &SellParams,
+) -> Option<u64> {
+    let commission = params.commission.filter(|commission| commission.applies_to(false))?;
+    let token_amount = params.token_amount.unwrap_or(0);
+    let expected_lamports =
+        match sell_output_lamports(params.protocol_params.as_ref(), token_amount) {
+            Ok(lamports) => lamports,
+            Err(e) => {
+                log::warn!(
+                    icon!("⚠️ ", "No commission charged on the sell of {}: {}"),
+                    params.mint,
+                    e
+                );
+                return None;
+            }
+        };
+    append_commission(
+        instructions,
+        Some(commission),
+        &params.payer.pubkey(),
+        false,
+        expected_lamports,
+    )
+}
+
+/// Record tip and priority fee spend of a sent trade
+///
+/// The priority fee comes from the analyzed transaction fee when available, otherwise from the
+/// configured compute unit price. Only one of the parallel transactions lands, but which one is
+/// unknown here, so the largest tip among the tipping clients is recorded. `trade_lamports`
+/// sizes tips under `TipStrategy::PercentOfTrade`.
+pub(crate) fn record_spend(
+    spend_tracker: &Option<Arc<SpendTracker>>,
+    swqos_clients: &[Arc<SwqosClient>],
+    priority_fee: &PriorityFee,
+    protocol_name: &str,
+    is_buy: bool,
+    trade_lamports: u64,
+    analyzed_fee: Option<u64>,
+    with_tip: bool,
+) {
+    let Some(spend_tracker) = spend_tracker else {
+        return;
+    };
+    let tips = if with_tip {
+        priority_fee.tip_lamports(is_buy, swqos_clients.len(), trade_lamports).unwrap_or_default()
+    } else {
+        Vec::new()
+    };
+    let tip_lamports = swqos_clients
+        .iter()
+        .enumerate()
+        .filter(|(_, client)| client.get_swqos_type() != SwqosType::Default)
+        .filter_map(|(i, _)| tips.get(i).copied())
+        .max()
+        .unwrap_or(0);
+    let priority_fee_lamports = match analyzed_fee {
+        Some(fee) => fee.saturating_sub(BASE_SIGNATURE_FEE_LAMPORTS),
+        None => {
+            let (unit_price, unit_limit) =
+                priority_fee.unit_price_and_limit(protocol_name, tip_lamports == 0);
+            // unit price is in micro-lamports per compute unit
+            (unit_price as u128 * unit_limit as u128 / 1_000_000) as u64
+        }
+    };
+    spend_tracker.record(tip_lamports, priority_fee_lamports);
+}
+
+/// What the analysis of a sent trade looks for
+#[derive(Clone, Copy)]
+pub(crate) enum AnalysisKind {
+    Buy { expected_sol_spent: f64 },
+    Sell { expected_tokens_sold: f64 },
+}
+
+pub(crate) async fn analyze(
+    rpc: &SolanaRpcClient,
+    signature: &Signature,
+    mint: &Pubkey,
+    wallet: &Pubkey,
+    kind: AnalysisKind,
+) -> Result<TradeResult> {
+    match kind {
+        AnalysisKind::Buy { expected_sol_spent } => {
+            TradeResult::analyze_transaction(
+                rpc,
+                signature,
+                mint,
+                wallet,
+                expected_sol_spent,
+                CommitmentConfig::confirmed(),
+            )
+            .await
+        }
+        AnalysisKind::Sell { expected_tokens_sold } => {
+            // No entry price is known here, PnL is left to the caller
+            TradeResult::analyze_sell_transaction(
+                rpc,
+                signature,
+                mint,
+                wallet,
+                expected_tokens_sold,
+                None,
+                CommitmentConfig::confirmed(),
+            )
+            .await
+        }
+    }
+}
+
+/// Retry a failed analysis a few times, the RPC may lag behind the confirmation
+pub(crate) async fn retry_analysis(
+    rpc: &SolanaRpcClient,
+    signature: Signature,
+    mint: Pubkey,
+    wallet: Pubkey,
+    kind: AnalysisKind,
+    mut result: Result<TradeResult>,
+) -> Result<TradeResult> {
+    for _ in 0..DEFERRED_ANALYSIS_ATTEMPTS {
+        if result.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(DEFERRED_ANALYSIS_RETRY_DELAY_MS)).await;
+        result = analyze(rpc, &signature, &mint, &wallet, kind).await;
+    }
+    result
+}
+
+fn deliver_analysis(
+    callback: Option<AnalysisCallback>,
+    signature: Signature,
+    result: Result<TradeResult>,
+) {
+    match callback {
+        Some(callback) => callback(signature, result),
+        None => {
+            if let Err(e) = result {
+                log::error!("Deferred analysis of transaction {} failed: {}", signature, e);
+            }
+        }
     }
 }
 
+/// Attach the commission, labels, sequence and protocol of a trade to results delivered to
+/// `callback`
+fn annotated_callback(
+    callback: Option<AnalysisCallback>,
+    commission: Option<u64>,
+    labels: Option<TradeLabels>,
+    sequence: u64,
+    protocol: &'static str,
+) -> Option<AnalysisCallback> {
+    let callback = callback?;
+    Some(Arc::new(move |signature, result: Result<TradeResult>| {
+        let result = result.map(|trade_result| {
+            trade_result
+                .with_commission(commission)
+                .with_labels(labels.clone())
+                .with_sequence(sequence)
+                .with_protocol(protocol)
+        });
+        callback(signature, result)
+    }))
+}
+
+/// Make analysis failures of a landed transaction non-fatal
+///
+/// If the analysis failed but the signature status shows the transaction succeeded on-chain,
+/// a result flagged `analysis_pending` is returned and the analysis is retried in the
+/// background; its outcome is delivered to `callback`. Transactions that did not land (or
+/// landed with an error) keep failing with the analysis error.
+async fn settle_analysis(
+    analysis: Result<TradeResult>,
+    rpc: Arc<SolanaRpcClient>,
+    signature: Signature,
+    stages: TradeStages,
+    mint: Pubkey,
+    wallet: Pubkey,
+    kind: AnalysisKind,
+    callback: Option<AnalysisCallback>,
+) -> Result<TradeResult> {
+    let error = match analysis {
+        Ok(trade_result) => return Ok(trade_result.with_stages(stages)),
+        Err(e) => e,
+    };
+    let landed = match rpc.get_signature_statuses(&[signature]).await {
+        Ok(response) => {
+            matches!(response.value.first(), Some(Some(status)) if status.err.is_none())
+        }
+        Err(_) => false,
+    };
+    if !landed {
+        return Err(error);
+    }
+
+    log::warn!(
+        icon!("⚠️ ", "Transaction {} landed but analysis failed ({}), retrying in background"),
+        signature,
+        error
+    );
+    let pending = TradeResult::pending(&signature, &mint, &wallet).with_stages(stages.clone());
+    tokio::spawn(async move {
+        let result = retry_analysis(&rpc, signature, mint, wallet, kind, Err(error)).await;
+        deliver_analysis(callback, signature, result.map(|r| r.with_stages(stages)));
+    });
+
+    Ok(pending)
+}
+
+/// Return a submitted trade right away, confirmation and analysis happen in the background
+///
+/// The outcome, including a confirmation failure, is delivered to `callback`.
+fn analyze_in_background(
+    rpc: Arc<SolanaRpcClient>,
+    signature: Signature,
+    mut stages: TradeStages,
+    mint: Pubkey,
+    wallet: Pubkey,
+    kind: AnalysisKind,
+    callback: Option<AnalysisCallback>,
+) -> TradeResult {
+    let pending = TradeResult::pending(&signature, &mint, &wallet).with_stages(stages.clone());
+    tokio::spawn(async move {
+        let confirmation =
+            ConfirmationService::for_rpc(&rpc).wait(signature, DEFAULT_CONFIRMATION_TIMEOUT).await;
+        let result = match confirmation {
+            Ok(()) => {
+                stages.mark(WaitLevel::Confirmed);
+                let analysis = analyze(&rpc, &signature, &mint, &wallet, kind).await;
+                retry_analysis(&rpc, signature, mint, wallet, kind, analysis).await
+            }
+            Err(e) => Err(e),
+        };
+        deliver_analysis(callback, signature, result.map(|r| r.with_stages(stages)));
+    });
+    pending
+}
+
+/// `priority_fee` with the unit limits `cu_sizing` picks for `instructions` and the unit price
+/// of the `PriorityFeeEstimator`
+async fn sized_priority_fee(
+    cu_sizing: CuSizing,
+    rpc: Option<&Arc<SolanaRpcClient>>,
+    priority_fee: &Arc<PriorityFee>,
+    key: CuSizingKey,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_table_keys: &[Pubkey],
+) -> Arc<PriorityFee> {
+    let Some(rpc) = rpc else {
+        return priority_fee.clone();
+    };
+    let priority_fee =
+        PriorityFeeEstimator::get_instance().apply(rpc, priority_fee, payer, instructions).await;
+    match cu_sizing {
+        CuSizing::Static => priority_fee,
+        cu_sizing => Arc::new(
+            size_compute_units(
+                cu_sizing,
+                rpc,
+                &priority_fee,
+                key,
+                payer,
+                instructions,
+                lookup_table_keys,
+            )
+            .await,
+        ),
+    }
+}
+
+/// Wait until a confirmed transaction is finalized, giving up at the trade's `deadline`
+async fn wait_finalized(
+    rpc: &SolanaRpcClient,
+    signature: Signature,
+    deadline: Option<Instant>,
+    stages: &mut TradeStages,
+) -> Result<()> {
+    let service = ConfirmationService::for_rpc(rpc);
+    let finalized =
+        service.wait_for_level(signature, WaitLevel::Finalized, DEFAULT_FINALIZATION_TIMEOUT);
+    until_deadline(deadline, signature, finalized).await?;
+    stages.mark(WaitLevel::Finalized);
+    Ok(())
+}
+
 #[async_trait::async_trait]
 impl TradeExecutor for GenericTradeExecutor {
     async fn buy(
@@ -37,45 +645,93 @@ impl TradeExecutor for GenericTradeExecutor {
         mut params: BuyParams,
         middleware_manager: Option<Arc<crate::trading::MiddlewareManager>>,
     ) -> Result<TradeResult> {
-        if params.data_size_limit == 0 {
-            params.data_size_limit = MAX_LOADED_ACCOUNTS_DATA_SIZE_LIMIT;
-        }
         if params.rpc.is_none() {
-            return Err(anyhow!("RPC is not set"));
+            return Err(TradeError::invalid_params(self.protocol_name, "RPC is not set").into());
         }
         let rpc = params.rpc.as_ref().unwrap().clone();
         let mut timer = TradeTimer::new("Build buy transaction");
-        
-        // Override middleware_manager in params if provided
-        if let Some(manager) = middleware_manager {
-            params.middleware_manager = Some(manager);
-        }
-        
-        // Build instructions
-        let instructions = self.instruction_builder.build_buy_instructions(&params).await?;
-        let final_instructions = match &params.middleware_manager {
-            Some(middleware_manager) => middleware_manager
-                .apply_middlewares_process_protocol_instructions(
-                    instructions,
-                    self.protocol_name.to_string(),
-                    true,
-                )?,
-            None => instructions,
-        };
+        let (final_instructions, commission) =
+            self.prepare_buy(&mut params, middleware_manager).await?;
         timer.stage("Build RPC transaction instructions");
 
         // Execute buy transaction
-        let signature = buy_parallel_execute(params.clone(), final_instructions, self.protocol_name).await?;
+        let (signature, mut stages) =
+            buy_parallel_execute(params.clone(), final_instructions, self.protocol_name).await?;
+        TokenBalanceCache::get_instance().invalidate(&params.payer.pubkey(), &params.mint);
+        let sequence = next_sequence(&params.mint);
         timer.stage("Transaction analysis");
 
         // Analyze transaction to get actual trade results
-        let trade_result = TradeResult::analyze_transaction(
-            &rpc,
-            &signature,
-            &params.mint,
-            &params.payer.pubkey(),
-            params.sol_amount as f64 / 1_000_000_000.0, // Convert lamports to SOL
-        ).await?;
+        let kind = AnalysisKind::Buy {
+            expected_sol_spent: params.sol_amount as f64 / 1_000_000_000.0, // Convert lamports to SOL
+        };
+        if params.wait_level <= WaitLevel::Processed {
+            record_spend(
+                &params.spend_tracker,
+                &params.swqos_clients,
+                &params.priority_fee,
+                self.protocol_name,
+                true,
+                params.sol_amount,
+                None,
+                true,
+            );
+            timer.finish();
+            return Ok(analyze_in_background(
+                rpc,
+                signature,
+                stages,
+                params.mint,
+                params.payer.pubkey(),
+                kind,
+                annotated_callback(
+                    params.analysis_callback.clone(),
+                    commission,
+                    params.labels.clone(),
+                    sequence,
+                    self.protocol_name,
+                ),
+            )
+            .with_commission(commission)
+            .with_labels(params.labels.clone())
+            .with_sequence(sequence)
+            .with_protocol(self.protocol_name));
+        }
+        let analysis = analyze(&rpc, &signature, &params.mint, &params.payer.pubkey(), kind).await;
+        record_spend(
+            &params.spend_tracker,
+            &params.swqos_clients,
+            &params.priority_fee,
+            self.protocol_name,
+            true,
+            params.sol_amount,
+            analysis.as_ref().ok().and_then(|result| result.solana_fees),
+            true,
+        );
+        if params.wait_level == WaitLevel::Finalized {
+            wait_finalized(&rpc, signature, params.deadline, &mut stages).await?;
+        }
+        let trade_result = settle_analysis(
+            analysis,
+            rpc,
+            signature,
+            stages,
+            params.mint,
+            params.payer.pubkey(),
+            kind,
+            annotated_callback(
+                params.analysis_callback.clone(),
+                commission,
+                params.labels.clone(),
+                sequence,
+                self.protocol_name,
+            ),
+        )
+        .await?
+        .with_commission(commission)
+        .with_labels(params.labels.clone())
+        .with_sequence(sequence)
+        .with_protocol(self.protocol_name);
 
         timer.finish();
         Ok(trade_result)
@@ -83,49 +739,100 @@ impl TradeExecutor for GenericTradeExecutor {
 
     async fn buy_with_tip(
         &self,
-        params: BuyWithTipParams,
+        mut params: BuyWithTipParams,
         middleware_manager: Option<Arc<crate::trading::MiddlewareManager>>,
     ) -> Result<TradeResult> {
         let mut timer = TradeTimer::new("Build buy transaction");
+        if params.trade_type != TradeType::Buy {
+            params.priority_fee = params.priority_fee.for_trade_type(params.trade_type);
+        }
 
         // Store RPC for later analysis (CRITICAL: like backup version)
         let rpc_for_analysis = params.rpc.clone();
 
+        let swqos_clients = apply_spend_budget(&params.spend_tracker, params.swqos_clients.clone())?;
+
         // Convert to BuyParams for compatibility
-        let buy_params = BuyParams {
+        let mut buy_params = BuyParams {
             rpc: params.rpc,
             payer: params.payer.clone(),
             mint: params.mint,
             sol_amount: params.sol_amount,
+            amount_spec: params.amount_spec,
+            trade_type: params.trade_type,
             slippage_basis_points: params.slippage_basis_points,
-            priority_fee: Arc::new(params.priority_fee),
-            lookup_table_key: params.lookup_table_key,
+            min_amount_out: params.min_amount_out,
+            priority_fee: Arc::new(params.priority_fee.clone()),
+            lookup_table_keys: params.lookup_table_keys,
             recent_blockhash: params.recent_blockhash,
             data_size_limit: params.data_size_limit,
-            wait_transaction_confirmed: true,
+            wait_level: WaitLevel::Confirmed,
             protocol_params: params.protocol_params,
             open_seed_optimize: false,
-            swqos_clients: params.swqos_clients.clone(),
+            swqos_clients: swqos_clients.clone(),
             middleware_manager: middleware_manager,
-            create_wsol_ata: false,
-            close_wsol_ata: false,
+            create_wsol_ata: params.create_wsol_ata,
+            close_wsol_ata: params.close_wsol_ata,
             create_mint_ata: false,
+            spend_tracker: params.spend_tracker.clone(),
+            analysis_callback: params.analysis_callback.clone(),
+            commission: params.commission,
+            labels: params.labels.clone(),
+            success_policy: params.success_policy,
+            created_account_check: params.created_account_check,
+            wsol_account: params.wsol_account,
+            cu_sizing: params.cu_sizing,
+            swap_hooks: params.swap_hooks,
+            use_durable_nonce: params.use_durable_nonce,
+            blockhash_retry: params.blockhash_retry,
+            memo: params.memo,
+            deadline: params.deadline,
         };
+        apply_memo(&mut buy_params.swap_hooks, &mut buy_params.memo);
 
         // Build instructions
         let instructions = self.instruction_builder.build_buy_instructions(&buy_params).await?;
-        let final_instructions = match &buy_params.middleware_manager {
-            Some(middleware_manager) => middleware_manager
-                .apply_middlewares_process_protocol_instructions(
-                    instructions,
-                    self.protocol_name.to_string(),
-                    true,
-                )?,
-            None => instructions,
-        };
+        let mut final_instructions = self.process_instructions(
+            instructions,
+            &buy_params.swap_hooks,
+            &buy_params.middleware_manager,
+            true,
+        )?;
+        let commission = append_commission(
+            &mut final_instructions,
+            buy_params.commission,
+            &buy_params.payer.pubkey(),
+            true,
+            buy_params.sol_amount,
+        );
+        check_created_accounts(&final_instructions, buy_params.created_account_check)?;
+        buy_params.priority_fee =
+            buy_params.swap_hooks.reserve_compute_units(&buy_params.priority_fee);
+        buy_params.priority_fee = sized_priority_fee(
+            buy_params.cu_sizing,
+            buy_params.rpc.as_ref(),
+            &buy_params.priority_fee,
+            CuSizingKey { protocol: self.protocol_name, mint: buy_params.mint, is_buy: true },
+            &buy_params.payer.pubkey(),
+            &final_instructions,
+            &buy_params.lookup_table_keys,
+        )
+        .await;
+        check_hooked_transaction_fit(
+            &buy_params.swap_hooks,
+            &buy_params.payer.pubkey(),
+            &final_instructions,
+            &buy_params.lookup_table_keys,
+            &buy_params.priority_fee,
+            buy_params.data_size_limit.unwrap_or(0),
+        )
+        .await?;
 
         // Execute transactions in parallel to get signature
-        let actual_signature = buy_parallel_execute(buy_params, final_instructions, self.protocol_name).await?;
+        let (actual_signature, stages) =
+            buy_parallel_execute(buy_params, final_instructions, self.protocol_name).await?;
+        TokenBalanceCache::get_instance().invalidate(&params.payer.pubkey(), &params.mint);
+        let sequence = next_sequence(&params.mint);
         timer.stage("Transaction analysis");
 
         // Get RPC client for transaction analysis (CRITICAL: like backup version)
@@ -135,13 +842,41 @@ impl TradeExecutor for GenericTradeExecutor {
         let signature = actual_signature;
 
         // Do REAL transaction analysis just like the standard buy method (CRITICAL: like backup version)
-        let trade_result = TradeResult::analyze_transaction(
-            &rpc,
-            &signature,
-            &params.mint,
-            &params.payer.pubkey(),
-            params.sol_amount as f64 / 1_000_000_000.0, // Convert lamports to SOL
-        ).await?;
+        let kind = AnalysisKind::Buy {
+            expected_sol_spent: params.sol_amount as f64 / 1_000_000_000.0, // Convert lamports to SOL
+        };
+        let analysis = analyze(&rpc, &signature, &params.mint, &params.payer.pubkey(), kind).await;
+        record_spend(
+            &params.spend_tracker,
+            &swqos_clients,
+            &params.priority_fee,
+            self.protocol_name,
+            true,
+            params.sol_amount,
+            analysis.as_ref().ok().and_then(|result| result.solana_fees),
+            true,
+        );
+        let trade_result = settle_analysis(
+            analysis,
+            rpc,
+            signature,
+            stages,
+            params.mint,
+            params.payer.pubkey(),
+            kind,
+            annotated_callback(
+                params.analysis_callback.clone(),
+                commission,
+                params.labels.clone(),
+                sequence,
+                self.protocol_name,
+            ),
+        )
+        .await?
+        .with_commission(commission)
+        .with_labels(params.labels.clone())
+        .with_sequence(sequence)
+        .with_protocol(self.protocol_name);
 
         timer.finish();
         Ok(trade_result)
@@ -153,42 +888,91 @@ impl TradeExecutor for GenericTradeExecutor {
         middleware_manager: Option<Arc<crate::trading::MiddlewareManager>>,
     ) -> Result<TradeResult> {
         if params.rpc.is_none() {
-            return Err(anyhow!("RPC is not set"));
+            return Err(TradeError::invalid_params(self.protocol_name, "RPC is not set").into());
         }
         let rpc = params.rpc.as_ref().unwrap().clone();
         let mut timer = TradeTimer::new("Build sell transaction");
-        
-        // Override middleware_manager in params if provided
-        if let Some(manager) = middleware_manager {
-            params.middleware_manager = Some(manager);
-        }
-        
-        // Build instructions
-        let instructions = self.instruction_builder.build_sell_instructions(&params).await?;
-        let final_instructions = match &params.middleware_manager {
-            Some(middleware_manager) => middleware_manager
-                .apply_middlewares_process_protocol_instructions(
-                    instructions,
-                    self.protocol_name.to_string(),
-                    false,
-                )?,
-            None => instructions,
-        };
+        let (final_instructions, commission) =
+            self.prepare_sell(&mut params, middleware_manager).await?;
         timer.stage("Build RPC transaction instructions");
 
         // Execute sell transaction
-        let signature = sell_parallel_execute(params.clone(), final_instructions, self.protocol_name).await?;
+        let (signature, mut stages) =
+            sell_parallel_execute(params.clone(), final_instructions, self.protocol_name).await?;
+        TokenBalanceCache::get_instance().invalidate(&params.payer.pubkey(), &params.mint);
+        let sequence = next_sequence(&params.mint);
         timer.stage("Transaction analysis");
 
         // Analyze SELL transaction to get actual trade results with profit calculation
-        let trade_result = TradeResult::analyze_sell_transaction(
-            &rpc,
-            &signature,
-            &params.mint,
-            &params.payer.pubkey(),
-            params.token_amount.unwrap_or(0) as f64, // Expected tokens sold
-            0.0, // We'll calculate entry price from trade history if needed
-        ).await?;
+        let kind =
+            AnalysisKind::Sell { expected_tokens_sold: params.token_amount.unwrap_or(0) as f64 };
+        if params.wait_level <= WaitLevel::Processed {
+            record_spend(
+                &params.spend_tracker,
+                &params.swqos_clients,
+                &params.priority_fee,
+                self.protocol_name,
+                false,
+                sell_tip_trade_lamports(&params).unwrap_or(0),
+                None,
+                params.with_tip,
+            );
+            timer.finish();
+            return Ok(analyze_in_background(
+                rpc,
+                signature,
+                stages,
+                params.mint,
+                params.payer.pubkey(),
+                kind,
+                annotated_callback(
+                    params.analysis_callback.clone(),
+                    commission,
+                    params.labels.clone(),
+                    sequence,
+                    self.protocol_name,
+                ),
+            )
+            .with_commission(commission)
+            .with_labels(params.labels.clone())
+            .with_sequence(sequence)
+            .with_protocol(self.protocol_name));
+        }
+        let analysis = analyze(&rpc, &signature, &params.mint, &params.payer.pubkey(), kind).await;
+        record_spend(
+            &params.spend_tracker,
+            &params.swqos_clients,
+            &params.priority_fee,
+            self.protocol_name,
+            false,
+            sell_tip_trade_lamports(&params).unwrap_or(0),
+            analysis.as_ref().ok().and_then(|result| result.solana_fees),
+            params.with_tip,
+        );
+        if params.wait_level == WaitLevel::Finalized {
+            wait_finalized(&rpc, signature, params.deadline, &mut stages).await?;
+        }
+        let trade_result = settle_analysis(
+            analysis,
+            rpc,
+            signature,
+            stages,
+            params.mint,
+            params.payer.pubkey(),
+            kind,
+            annotated_callback(
+                params.analysis_callback.clone(),
+                commission,
+                params.labels.clone(),
+                sequence,
+                self.protocol_name,
+            ),
+        )
+        .await?
+        .with_commission(commission)
+        .with_labels(params.labels.clone())
+        .with_sequence(sequence)
+        .with_protocol(self.protocol_name);
 
         timer.finish();
         Ok(trade_result)
@@ -199,73 +983,356 @@ impl TradeExecutor for GenericTradeExecutor {
         params: SellWithTipParams,
         middleware_manager: Option<Arc<crate::trading::MiddlewareManager>>,
     ) -> Result<TradeResult> {
-        let _timer = TradeTimer::new("Build sell transaction");
-
-        // Convert to SellParams for compatibility
+        // The tip is added per swqos client by the parallel execution, the rest is a plain sell
         let sell_params = SellParams {
             rpc: params.rpc,
-            payer: params.payer.clone(),
+            payer: params.payer,
             mint: params.mint,
             token_amount: params.token_amount,
             slippage_basis_points: params.slippage_basis_points,
+            min_sol_out: params.min_sol_out,
             priority_fee: Arc::new(params.priority_fee),
-            lookup_table_key: params.lookup_table_key,
+            lookup_table_keys: params.lookup_table_keys,
             recent_blockhash: params.recent_blockhash,
-            wait_transaction_confirmed: true,
+            data_size_limit: params.data_size_limit,
+            wait_level: params.wait_level,
             with_tip: true,
             protocol_params: params.protocol_params,
             open_seed_optimize: false,
-            swqos_clients: params.swqos_clients.clone(),
-            middleware_manager: middleware_manager,
-            create_wsol_ata: false,
-            close_wsol_ata: false,
+            swqos_clients: params.swqos_clients,
+            middleware_manager: None,
+            create_wsol_ata: params.create_wsol_ata,
+            close_wsol_ata: params.close_wsol_ata,
+            spend_tracker: params.spend_tracker,
+            analysis_callback: params.analysis_callback,
+            clamp_to_balance: params.clamp_to_balance,
+            expected_min_balance: params.expected_min_balance,
+            commission: params.commission,
+            labels: params.labels,
+            success_policy: params.success_policy,
+            created_account_check: params.created_account_check,
+            wsol_account: params.wsol_account,
+            cu_sizing: params.cu_sizing,
+            swap_hooks: params.swap_hooks,
+            use_durable_nonce: params.use_durable_nonce,
+            blockhash_retry: params.blockhash_retry,
+            memo: params.memo,
+            deadline: params.deadline,
         };
+        self.sell(sell_params, middleware_manager).await
+    }
 
-        // Build instructions
-        let instructions = self.instruction_builder.build_sell_instructions(&sell_params).await?;
-        let final_instructions = match &sell_params.middleware_manager {
-            Some(middleware_manager) => middleware_manager
-                .apply_middlewares_process_protocol_instructions(
-                    instructions,
-                    self.protocol_name.to_string(),
-                    false,
-                )?,
-            None => instructions,
-        };
+    async fn simulate_buy(
+        &self,
+        mut params: BuyParams,
+        middleware_manager: Option<Arc<crate::trading::MiddlewareManager>>,
+    ) -> Result<SimulationResult> {
+        let rpc = params
+            .rpc
+            .clone()
+            .ok_or_else(|| TradeError::invalid_params(self.protocol_name, "RPC is not set"))?;
+        let (final_instructions, _) = self.prepare_buy(&mut params, middleware_manager).await?;
+        simulate_trade(
+            &rpc,
+            &params.swqos_clients,
+            params.payer.clone(),
+            final_instructions,
+            &params.priority_fee,
+            &params.lookup_table_keys,
+            params.recent_blockhash,
+            params.data_size_limit.unwrap_or(0),
+            params.middleware_manager.clone(),
+            self.protocol_name,
+            true,
+            true,
+            params.use_durable_nonce,
+            params.sol_amount,
+        )
+        .await
+    }
+
+    async fn simulate_sell(
+        &self,
+        mut params: SellParams,
+        middleware_manager: Option<Arc<crate::trading::MiddlewareManager>>,
+    ) -> Result<SimulationResult> {
+        let rpc = params
+            .rpc
+            .clone()
+            .ok_or_else(|| TradeError::invalid_params(self.protocol_name, "RPC is not set"))?;
+        let (final_instructions, _) = self.prepare_sell(&mut params, middleware_manager).await?;
+        simulate_trade(
+            &rpc,
+            &params.swqos_clients,
+            params.payer.clone(),
+            final_instructions,
+            &params.priority_fee,
+            &params.lookup_table_keys,
+            params.recent_blockhash,
+            params.data_size_limit.unwrap_or(0),
+            params.middleware_manager.clone(),
+            self.protocol_name,
+            false,
+            params.with_tip,
+            params.use_durable_nonce,
+            sell_tip_trade_lamports(&params)?,
+        )
+        .await
+    }
 
-        // Execute transactions in parallel
-        let signature = sell_parallel_execute(sell_params, final_instructions, self.protocol_name).await?;
+    async fn buy_instructions(
+        &self,
+        mut params: BuyParams,
+        middleware_manager: Option<Arc<crate::trading::MiddlewareManager>>,
+    ) -> Result<Vec<Instruction>> {
+        if let Some(manager) = middleware_manager {
+            params.middleware_manager = Some(manager);
+        }
+        apply_memo(&mut params.swap_hooks, &mut params.memo);
+        let (instructions, _) = self.buy_instructions_with_commission(&params).await?;
+        Ok(instructions)
+    }
 
-        // For parallel execution, return estimated trade result
-        let estimated_sol = (params.token_amount.unwrap_or(0) as f64 * 0.001) * 0.95; // Rough estimate
-        let estimated_tokens = params.token_amount.unwrap_or(0) as f64;
-        let estimated_price = if estimated_tokens > 0.0 {
-            estimated_sol / estimated_tokens
-        } else {
-            0.0
-        };
+    async fn sell_instructions(
+        &self,
+        mut params: SellParams,
+        middleware_manager: Option<Arc<crate::trading::MiddlewareManager>>,
+    ) -> Result<Vec<Instruction>> {
+        if let Some(manager) = middleware_manager {
+            params.middleware_manager = Some(manager);
+        }
+        resolve_sell_amount(&mut params).await?;
+        clamp_sell_amount(&mut params).await?;
+        apply_memo(&mut params.swap_hooks, &mut params.memo);
+        let (instructions, _) = self.sell_instructions_with_commission(&params).await?;
+        Ok(instructions)
+    }
 
-        let trade_result = TradeResult {
-            signature: signature.to_string(),
-            tokens_received: -estimated_tokens, // Negative for sell (tokens sold)
-            entry_price: estimated_price,
-            sol_spent: -estimated_sol, // Negative for sell (SOL received)
-            token_mint: params.mint.to_string(),
-            wallet_address: params.payer.pubkey().to_string(),
-            analysis_duration_ms: 0,
-            profit_loss_absolute: None,
-            profit_loss_percentage: None,
-            original_entry_price: None,
-            slot: None,
-            solana_fees: None,
-            token_decimals: 6, // Default to 6 decimals
-            post_token_balance: None, // Not analyzed here, will be populated by analyze_sell_transaction
-        };
+    async fn buy_transaction(
+        &self,
+        mut params: BuyParams,
+        middleware_manager: Option<Arc<crate::trading::MiddlewareManager>>,
+    ) -> Result<VersionedTransaction> {
+        let (final_instructions, _) = self.prepare_buy(&mut params, middleware_manager).await?;
+        let (_, message) = first_provider_message(
+            &params.swqos_clients,
+            &params.payer.pubkey(),
+            &final_instructions,
+            &params.priority_fee,
+            &params.lookup_table_keys,
+            params.recent_blockhash,
+            params.data_size_limit.unwrap_or(0),
+            params.middleware_manager.as_deref(),
+            self.protocol_name,
+            true,
+            true,
+            params.use_durable_nonce,
+            params.sol_amount,
+        )?;
+        Ok(unsigned_transaction(message))
+    }
 
-        Ok(trade_result)
+    async fn sell_transaction(
+        &self,
+        mut params: SellParams,
+        middleware_manager: Option<Arc<crate::trading::MiddlewareManager>>,
+    ) -> Result<VersionedTransaction> {
+        let (final_instructions, _) = self.prepare_sell(&mut params, middleware_manager).await?;
+        let (_, message) = first_provider_message(
+            &params.swqos_clients,
+            &params.payer.pubkey(),
+            &final_instructions,
+            &params.priority_fee,
+            &params.lookup_table_keys,
+            params.recent_blockhash,
+            params.data_size_limit.unwrap_or(0),
+            params.middleware_manager.as_deref(),
+            self.protocol_name,
+            false,
+            params.with_tip,
+            params.use_durable_nonce,
+            sell_tip_trade_lamports(&params)?,
+        )?;
+        Ok(unsigned_transaction(message))
     }
 
     fn protocol_name(&self) -> &'static str {
         self.protocol_name
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::TipAmount,
+        constants::{MEMO_PROGRAM, TOKEN_PROGRAM, WSOL_TOKEN_ACCOUNT},
+        instruction::{
+            pumpswap::PumpSwapInstructionBuilder,
+            utils::pumpswap::accounts::{AMM_PROGRAM, FEE_PROGRAM},
+        },
+        params::PumpSwapParams,
+        swqos::testing::StubClient,
+        trading::{
+            common::{compile_message, sign_message, BuildEnvironment},
+            InstructionMiddleware,
+        },
+    };
+    use parking_lot::Mutex;
+    use solana_sdk::hash::Hash;
+
+    /// Middleware rewriting memos to upper case, to see the memo went through it
+    #[derive(Clone)]
+    struct UppercaseMemo;
+
+    impl InstructionMiddleware for UppercaseMemo {
+        fn name(&self) -> &'static str {
+            "UppercaseMemo"
+        }
+
+        fn process_protocol_instructions(
+            &self,
+            mut protocol_instructions: Vec<Instruction>,
+            _protocol_name: String,
+            _is_buy: bool,
+        ) -> Result<Vec<Instruction>> {
+            for instruction in &mut protocol_instructions {
+                if instruction.program_id == MEMO_PROGRAM {
+                    instruction.data.make_ascii_uppercase();
+                }
+            }
+            Ok(protocol_instructions)
+        }
+
+        fn process_full_instructions(
+            &self,
+            full_instructions: Vec<Instruction>,
+            _protocol_name: String,
+            _is_buy: bool,
+        ) -> Result<Vec<Instruction>> {
+            Ok(full_instructions)
+        }
+
+        fn clone_box(&self) -> Box<dyn InstructionMiddleware> {
+            Box::new(self.clone())
+        }
+    }
+
+    /// PumpSwap pool of `mint` quoted in wSOL
+    fn pool_params(mint: Pubkey) -> PumpSwapParams {
+        PumpSwapParams {
+            pool: Pubkey::new_unique(),
+            base_mint: mint,
+            quote_mint: WSOL_TOKEN_ACCOUNT,
+            pool_base_token_reserves: 200_000_000_000_000,
+            pool_quote_token_reserves: 80_000_000_000,
+            base_token_program: TOKEN_PROGRAM,
+            quote_token_program: TOKEN_PROGRAM,
+            creator: Pubkey::new_unique(),
+            auto_handle_wsol: true,
+            fee_config: Pubkey::new_unique(),
+            fee_program: FEE_PROGRAM,
+        }
+    }
+
+    #[test]
+    fn background_results_carry_the_trade_labels() {
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let sink = delivered.clone();
+        let callback: AnalysisCallback = Arc::new(move |_, result: Result<TradeResult>| {
+            sink.lock().push(result.unwrap());
+        });
+        let labels = TradeLabels::from([("strategy".to_string(), "copy".to_string())]);
+        let callback =
+            annotated_callback(Some(callback), Some(1_000), Some(labels.clone()), 7, "PumpFun")
+                .unwrap();
+
+        let signature = Signature::default();
+        let result = TradeResult::pending(&signature, &Pubkey::new_unique(), &Pubkey::new_unique());
+        callback(signature, Ok(result));
+
+        let delivered = delivered.lock();
+        assert_eq!(delivered.len(), 1);
+        assert_eq!(delivered[0].labels, Some(labels));
+        assert_eq!(delivered[0].commission_lamports, Some(1_000));
+        assert_eq!(delivered[0].sequence, Some(7));
+        assert_eq!(delivered[0].protocol.as_deref(), Some("PumpFun"));
+    }
+
+    #[tokio::test]
+    async fn memo_is_in_the_signed_transaction_before_the_wsol_close() {
+        let mint = Pubkey::new_unique();
+        let mut params =
+            SellParams::offline(mint, Some(1_000_000_000), Box::new(pool_params(mint)));
+        params.memo = Some("trade-42".to_string());
+        params.middleware_manager =
+            Some(Arc::new(MiddlewareManager::new().add_middleware(Box::new(UppercaseMemo))));
+        apply_memo(&mut params.swap_hooks, &mut params.memo);
+
+        let executor = GenericTradeExecutor::new(
+            Arc::new(PumpSwapInstructionBuilder),
+            "PumpSwap",
+            AMM_PROGRAM,
+        );
+        let (instructions, _) = executor.sell_instructions_with_commission(&params).await.unwrap();
+        let message = compile_message(
+            &params.payer.pubkey(),
+            &params.priority_fee,
+            &instructions,
+            &BuildEnvironment::default(),
+            Hash::new_unique(),
+            256 * 1024,
+            None,
+            "PumpSwap",
+            false,
+            false,
+            &Pubkey::default(),
+            0,
+        )
+        .unwrap();
+        let transaction = sign_message(params.payer.as_ref(), &[], message).await.unwrap();
+
+        let bytes = bincode::serialize(&transaction).unwrap();
+        let decoded: VersionedTransaction = bincode::deserialize(&bytes).unwrap();
+        let keys = decoded.message.static_account_keys();
+        let compiled = decoded.message.instructions();
+        let program = |index: usize| keys[compiled[index].program_id_index as usize];
+        let memo = (0..compiled.len()).find(|&index| program(index) == MEMO_PROGRAM).unwrap();
+        assert_eq!(compiled[memo].data, b"TRADE-42");
+        assert!(compiled[memo].accounts.is_empty());
+        assert_eq!(program(memo - 1), AMM_PROGRAM);
+        // The wSOL account is closed after the memo
+        assert_eq!(program(memo + 1), TOKEN_PROGRAM);
+        assert_eq!(compiled[memo + 1].data, [9]);
+    }
+
+    #[tokio::test]
+    async fn buy_transaction_is_the_tipped_message_left_unsigned() {
+        let mint = Pubkey::new_unique();
+        let tip_account = Pubkey::new_unique();
+        let mut params = BuyParams::offline(mint, 1_000_000, Box::new(pool_params(mint)));
+        params.swqos_clients =
+            vec![Arc::new(StubClient::new(SwqosType::Node1).with_tip_account(tip_account))];
+        params.priority_fee = Arc::new(PriorityFee {
+            buy_tips: vec![TipAmount::Lamports(100_000)],
+            ..Default::default()
+        });
+        params.recent_blockhash = Hash::new_unique();
+        let payer = params.payer.clone();
+
+        let executor = GenericTradeExecutor::new(
+            Arc::new(PumpSwapInstructionBuilder),
+            "PumpSwap",
+            AMM_PROGRAM,
+        );
+        let transaction = executor.buy_transaction(params.clone(), None).await.unwrap();
+
+        assert_eq!(transaction.signatures, vec![Signature::default()]);
+        assert_eq!(transaction.message.recent_blockhash(), &params.recent_blockhash);
+        let keys = transaction.message.static_account_keys();
+        assert_eq!(keys[0], payer.pubkey());
+        assert!(keys.contains(&AMM_PROGRAM));
+        assert!(keys.contains(&tip_account));
+        let signed = sign_message(payer.as_ref(), &[], transaction.message).await.unwrap();
+        assert!(signed.verify_with_results().iter().all(|verified| *verified));
+    }
+}
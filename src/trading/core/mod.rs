@@ -1,6 +1,21 @@
 pub mod params;
 pub mod traits;
+pub mod batch_sell;
+pub mod bundle;
+pub mod create;
+pub mod curve_fallback;
+pub mod error;
 pub mod executor;
+pub mod options;
 pub mod parallel;
+#[cfg(feature = "capture_transaction")]
+pub mod replay;
+pub mod resume;
+pub mod sequence;
+pub mod simulation;
+pub mod smart_sell;
+pub mod split;
+pub mod two_hop;
+pub mod venue;
 pub mod timer;
 pub mod trade_result;
\ No newline at end of file
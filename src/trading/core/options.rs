@@ -0,0 +1,506 @@
+//! Optional trade settings as structs
+//!
+//! New settings are added as fields with a default, so `buy_with_options` and
+//! `sell_with_options` keep their signatures. Both structs are `#[non_exhaustive]`: build them
+//! with `Default::default()` and the `with_*` methods.
+
+use std::{sync::Arc, time::Instant};
+
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+use crate::{
+    common::{commission::Commission, labels::TradeLabels, signer::TradeSigner, PriorityFee},
+    constants::trade::trade::DEFAULT_DATA_SIZE_LIMIT,
+    swqos::confirmation::WaitLevel,
+    trading::common::swap_hooks::SwapHooks,
+    trading::core::{
+        curve_fallback::CurveCompleteAction,
+        parallel::{BlockhashRetry, SuccessPolicy},
+    },
+};
+
+/// Optional settings of `SolanaTrade::buy_with_options`
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct BuyOptions {
+    /// Slippage tolerance in basis points, the protocol or global default when `None`, see
+    /// `dex_defaults`
+    pub slippage_basis_points: Option<u64>,
+    /// Replaces the configured priority fee for this trade
+    pub custom_priority_fee: Option<PriorityFee>,
+    /// Address lookup tables the transaction is compiled with, each must be in the
+    /// `AddressLookupTableCache`
+    pub lookup_table_keys: Vec<Pubkey>,
+    /// Defaults to `WaitLevel::Confirmed`
+    pub wait_level: WaitLevel,
+    /// Create the wSOL ATA and wrap the SOL in, on by default. Off, the buy spends wSOL the ATA
+    /// already holds.
+    pub create_wsol_ata: bool,
+    /// Close the wSOL ATA after the swap, unwrapping its balance, on by default
+    pub close_wsol_ata: bool,
+    /// Create the token account of the mint, on by default
+    pub create_mint_ata: bool,
+    pub open_seed_optimize: bool,
+    /// Replaces the configured commission for this trade
+    pub commission: Option<Commission>,
+    /// Returned on the `TradeResult` of the trade, see `common::labels` for the limits
+    pub labels: Option<TradeLabels>,
+    /// When a trade sent through several swqos clients counts as successful
+    pub success_policy: SuccessPolicy,
+    /// What a PumpFun buy does when the bonding curve completed before it landed
+    pub on_curve_complete: CurveCompleteAction,
+    /// Instructions spliced around the swap, see `SwapHooks`
+    pub swap_hooks: SwapHooks,
+    /// Send with the `NonceCache` durable nonce instead of `recent_blockhash`
+    pub use_durable_nonce: bool,
+    /// Signs and pays for this trade instead of the configured payer
+    pub payer: Option<Arc<dyn TradeSigner>>,
+    /// Resubmission when every provider rejected the transaction for its blockhash
+    pub blockhash_retry: BlockhashRetry,
+    /// Memo recorded in the transaction, see `with_memo`
+    pub memo: Option<String>,
+    /// Keys of the labels recorded in the memo, see `with_memo_labels`
+    pub memo_labels: Vec<String>,
+    /// When to stop waiting for the trade, see `with_deadline`
+    pub deadline: Option<Instant>,
+    /// Tokens to receive exactly, the buy's `sol_amount` then caps the SOL spent
+    pub exact_tokens_out: Option<u64>,
+    /// Fewest tokens to accept, replacing the slippage bound, see `with_min_amount_out`
+    pub min_amount_out: Option<u64>,
+    /// Loaded accounts data size limit of the transaction, `DEFAULT_DATA_SIZE_LIMIT` by
+    /// default; `None` leaves out the `SetLoadedAccountsDataSizeLimit` instruction
+    pub data_size_limit: Option<u32>,
+}
+
+impl Default for BuyOptions {
+    fn default() -> Self {
+        Self {
+            slippage_basis_points: None,
+            custom_priority_fee: None,
+            lookup_table_keys: Vec::new(),
+            wait_level: WaitLevel::Confirmed,
+            create_wsol_ata: true,
+            close_wsol_ata: true,
+            create_mint_ata: true,
+            open_seed_optimize: false,
+            commission: None,
+            labels: None,
+            success_policy: SuccessPolicy::FirstAccepted,
+            on_curve_complete: CurveCompleteAction::Fail,
+            swap_hooks: SwapHooks::default(),
+            use_durable_nonce: false,
+            payer: None,
+            blockhash_retry: BlockhashRetry::default(),
+            memo: None,
+            memo_labels: Vec::new(),
+            deadline: None,
+            exact_tokens_out: None,
+            min_amount_out: None,
+            data_size_limit: Some(DEFAULT_DATA_SIZE_LIMIT),
+        }
+    }
+}
+
+impl BuyOptions {
+    pub fn with_slippage_basis_points(mut self, slippage_basis_points: u64) -> Self {
+        self.slippage_basis_points = Some(slippage_basis_points);
+        self
+    }
+
+    pub fn with_priority_fee(mut self, priority_fee: PriorityFee) -> Self {
+        self.custom_priority_fee = Some(priority_fee);
+        self
+    }
+
+    /// Add one address lookup table, call again to add more
+    pub fn with_lookup_table(mut self, lookup_table_key: Pubkey) -> Self {
+        self.lookup_table_keys.push(lookup_table_key);
+        self
+    }
+
+    pub fn with_lookup_tables(mut self, lookup_table_keys: Vec<Pubkey>) -> Self {
+        self.lookup_table_keys = lookup_table_keys;
+        self
+    }
+
+    pub fn with_wait_level(mut self, wait_level: impl Into<WaitLevel>) -> Self {
+        self.wait_level = wait_level.into();
+        self
+    }
+
+    /// Create the wSOL account before and close it after the trade, honored by every protocol
+    /// trading through wSOL
+    pub fn with_wsol_ata(mut self, create_wsol_ata: bool, close_wsol_ata: bool) -> Self {
+        self.create_wsol_ata = create_wsol_ata;
+        self.close_wsol_ata = close_wsol_ata;
+        self
+    }
+
+    pub fn with_create_mint_ata(mut self, create_mint_ata: bool) -> Self {
+        self.create_mint_ata = create_mint_ata;
+        self
+    }
+
+    pub fn with_seed_optimize(mut self, open_seed_optimize: bool) -> Self {
+        self.open_seed_optimize = open_seed_optimize;
+        self
+    }
+
+    /// Charge `commission` instead of the configured one, a `bps` of 0 charges nothing
+    pub fn with_commission(mut self, commission: Commission) -> Self {
+        self.commission = Some(commission);
+        self
+    }
+
+    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.get_or_insert_with(TradeLabels::new).insert(key.into(), value.into());
+        self
+    }
+
+    pub fn with_success_policy(mut self, success_policy: SuccessPolicy) -> Self {
+        self.success_policy = success_policy;
+        self
+    }
+
+    pub fn with_on_curve_complete(mut self, on_curve_complete: CurveCompleteAction) -> Self {
+        self.on_curve_complete = on_curve_complete;
+        self
+    }
+
+    /// Run `instruction` right before the swap instructions
+    pub fn with_pre_swap_instruction(mut self, instruction: Instruction) -> Self {
+        self.swap_hooks.pre_swap_instructions.push(instruction);
+        self
+    }
+
+    /// Run `instruction` right after the swap instructions
+    pub fn with_post_swap_instruction(mut self, instruction: Instruction) -> Self {
+        self.swap_hooks.post_swap_instructions.push(instruction);
+        self
+    }
+
+    /// Compute units the hook instructions need on top of the static unit limits
+    pub fn with_hook_compute_units(mut self, extra_compute_units: u32) -> Self {
+        self.swap_hooks.extra_compute_units = extra_compute_units;
+        self
+    }
+
+    /// Advance the nonce account set in `NonceCache` and sign with its nonce, so the
+    /// transaction can be signed ahead of time without expiring
+    ///
+    /// The trade is refused when no nonce account is configured, its nonce was not fetched
+    /// (`NonceCache::fetch_nonce_info_use_rpc`) or it was already used. The nonce is marked used
+    /// once a provider accepted the transaction.
+    pub fn with_durable_nonce(mut self, use_durable_nonce: bool) -> Self {
+        self.use_durable_nonce = use_durable_nonce;
+        self
+    }
+
+    /// Sign and pay with `payer` instead of the configured payer; token accounts are derived
+    /// for it while the RPC, swqos clients and caches stay shared. `KeypairSigner::wrap` turns a
+    /// keypair into a signer
+    pub fn with_payer(mut self, payer: Arc<dyn TradeSigner>) -> Self {
+        self.payer = Some(payer);
+        self
+    }
+
+    /// Resubmit up to `max_retries` times when every provider rejected the transaction with
+    /// `BlockhashNotFound`, with a freshly fetched blockhash when `refresh_blockhash` is set
+    pub fn with_blockhash_retry(mut self, max_retries: u32, refresh_blockhash: bool) -> Self {
+        self.blockhash_retry = BlockhashRetry { max_retries, refresh_blockhash };
+        self
+    }
+
+    /// Buy exactly `token_amount` tokens, spending at most the buy's `sol_amount`
+    ///
+    /// Supported on PumpFun, PumpSwap pools quoted in wSOL and Raydium CPMM; other protocols
+    /// fail the trade. The slippage applies to the quoted cost, capped at `sol_amount`.
+    pub fn with_exact_tokens_out(mut self, token_amount: u64) -> Self {
+        self.exact_tokens_out = Some(token_amount);
+        self
+    }
+
+    /// Accept no fewer than `min_amount_out` tokens, written into the instruction instead of the
+    /// bound computed from the slippage
+    ///
+    /// Supported on PumpFun, PumpSwap, Bonk, Raydium CPMM and Raydium AMM V4, not together with
+    /// `with_exact_tokens_out`. The trade fails before sending when the quote is already below
+    /// it. PumpFun and PumpSwap buys name the tokens out in the instruction, so they receive
+    /// exactly `min_amount_out` for at most the buy's `sol_amount`.
+    pub fn with_min_amount_out(mut self, min_amount_out: u64) -> Self {
+        self.min_amount_out = Some(min_amount_out);
+        self
+    }
+
+    /// Limit the data the transaction may load to `data_size_limit` bytes, `None` sends it
+    /// without the `SetLoadedAccountsDataSizeLimit` instruction and the runtime's 64 MB default
+    pub fn with_data_size_limit(mut self, data_size_limit: Option<u32>) -> Self {
+        self.data_size_limit = data_size_limit;
+        self
+    }
+
+    /// Record `memo` in the transaction with an spl-memo instruction right after the swap and
+    /// its post-swap hooks, e.g. an internal trade id
+    ///
+    /// The memo is run through the middleware and counts against the transaction size check
+    /// like the hooks; it adds `MEMO_COMPUTE_UNITS` to the hook compute units.
+    pub fn with_memo(mut self, memo: impl Into<String>) -> Self {
+        self.memo = Some(memo.into());
+        self
+    }
+
+    /// Also record the labels under `keys` in the memo, as a JSON object after the memo text,
+    /// e.g. `trade-42 {"strategy":"copy"}`
+    ///
+    /// Keys the trade has no label for are left out. Labels are public once on-chain, only
+    /// list keys whose values may be published.
+    pub fn with_memo_labels<I, K>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        self.memo_labels = keys.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Stop waiting for the trade at `deadline`, e.g. `Instant::now() + timeout`
+    ///
+    /// Pending confirmations are cancelled and no blockhash retry starts after it; the trade
+    /// fails with `TradeError::ConfirmationTimeout` holding the signatures of the transactions
+    /// handed to providers, which may still land.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+}
+
+/// Optional settings of `SolanaTrade::sell_with_options`
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct SellOptions {
+    /// Slippage tolerance in basis points, the protocol or global default when `None`, see
+    /// `dex_defaults`
+    pub slippage_basis_points: Option<u64>,
+    /// Replaces the configured priority fee for this trade
+    pub custom_priority_fee: Option<PriorityFee>,
+    /// Send through the swqos clients with a tip instead of the plain RPC
+    pub with_tip: bool,
+    /// Address lookup tables the transaction is compiled with, each must be in the
+    /// `AddressLookupTableCache`
+    pub lookup_table_keys: Vec<Pubkey>,
+    /// Defaults to `WaitLevel::Confirmed`
+    pub wait_level: WaitLevel,
+    /// Create the wSOL ATA receiving the proceeds, on by default
+    pub create_wsol_ata: bool,
+    /// Close the wSOL ATA after the swap, unwrapping the proceeds, on by default
+    pub close_wsol_ata: bool,
+    pub open_seed_optimize: bool,
+    /// Replaces the configured commission for this trade
+    pub commission: Option<Commission>,
+    /// Returned on the `TradeResult` of the trade, see `common::labels` for the limits
+    pub labels: Option<TradeLabels>,
+    /// When a trade sent through several swqos clients counts as successful
+    pub success_policy: SuccessPolicy,
+    /// Instructions spliced around the swap, see `SwapHooks`
+    pub swap_hooks: SwapHooks,
+    /// Send with the `NonceCache` durable nonce instead of `recent_blockhash`
+    pub use_durable_nonce: bool,
+    /// Signs and pays for this trade instead of the configured payer
+    pub payer: Option<Arc<dyn TradeSigner>>,
+    /// Resubmission when every provider rejected the transaction for its blockhash
+    pub blockhash_retry: BlockhashRetry,
+    /// Memo recorded in the transaction, see `with_memo`
+    pub memo: Option<String>,
+    /// Keys of the labels recorded in the memo, see `with_memo_labels`
+    pub memo_labels: Vec<String>,
+    /// When to stop waiting for the trade, see `with_deadline`
+    pub deadline: Option<Instant>,
+    /// Balance the wallet is known to hold, see `with_expected_min_balance`
+    pub expected_min_balance: Option<u64>,
+    /// Fewest lamports to accept, replacing the slippage bound, see `with_min_sol_out`
+    pub min_sol_out: Option<u64>,
+    /// Loaded accounts data size limit of the transaction, `DEFAULT_DATA_SIZE_LIMIT` by
+    /// default; `None` leaves out the `SetLoadedAccountsDataSizeLimit` instruction
+    pub data_size_limit: Option<u32>,
+}
+
+impl Default for SellOptions {
+    fn default() -> Self {
+        Self {
+            slippage_basis_points: None,
+            custom_priority_fee: None,
+            with_tip: false,
+            lookup_table_keys: Vec::new(),
+            wait_level: WaitLevel::Confirmed,
+            create_wsol_ata: true,
+            close_wsol_ata: true,
+            open_seed_optimize: false,
+            commission: None,
+            labels: None,
+            success_policy: SuccessPolicy::FirstAccepted,
+            swap_hooks: SwapHooks::default(),
+            use_durable_nonce: false,
+            payer: None,
+            blockhash_retry: BlockhashRetry::default(),
+            memo: None,
+            memo_labels: Vec::new(),
+            deadline: None,
+            expected_min_balance: None,
+            min_sol_out: None,
+            data_size_limit: Some(DEFAULT_DATA_SIZE_LIMIT),
+        }
+    }
+}
+
+impl SellOptions {
+    pub fn with_slippage_basis_points(mut self, slippage_basis_points: u64) -> Self {
+        self.slippage_basis_points = Some(slippage_basis_points);
+        self
+    }
+
+    pub fn with_priority_fee(mut self, priority_fee: PriorityFee) -> Self {
+        self.custom_priority_fee = Some(priority_fee);
+        self
+    }
+
+    pub fn with_tip(mut self, with_tip: bool) -> Self {
+        self.with_tip = with_tip;
+        self
+    }
+
+    /// Add one address lookup table, call again to add more
+    pub fn with_lookup_table(mut self, lookup_table_key: Pubkey) -> Self {
+        self.lookup_table_keys.push(lookup_table_key);
+        self
+    }
+
+    pub fn with_lookup_tables(mut self, lookup_table_keys: Vec<Pubkey>) -> Self {
+        self.lookup_table_keys = lookup_table_keys;
+        self
+    }
+
+    pub fn with_wait_level(mut self, wait_level: impl Into<WaitLevel>) -> Self {
+        self.wait_level = wait_level.into();
+        self
+    }
+
+    /// Create the wSOL account before and close it after the trade, honored by every protocol
+    /// trading through wSOL
+    pub fn with_wsol_ata(mut self, create_wsol_ata: bool, close_wsol_ata: bool) -> Self {
+        self.create_wsol_ata = create_wsol_ata;
+        self.close_wsol_ata = close_wsol_ata;
+        self
+    }
+
+    pub fn with_seed_optimize(mut self, open_seed_optimize: bool) -> Self {
+        self.open_seed_optimize = open_seed_optimize;
+        self
+    }
+
+    /// Charge `commission` instead of the configured one, a `bps` of 0 charges nothing
+    pub fn with_commission(mut self, commission: Commission) -> Self {
+        self.commission = Some(commission);
+        self
+    }
+
+    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.get_or_insert_with(TradeLabels::new).insert(key.into(), value.into());
+        self
+    }
+
+    pub fn with_success_policy(mut self, success_policy: SuccessPolicy) -> Self {
+        self.success_policy = success_policy;
+        self
+    }
+
+    /// Run `instruction` right before the swap instructions
+    pub fn with_pre_swap_instruction(mut self, instruction: Instruction) -> Self {
+        self.swap_hooks.pre_swap_instructions.push(instruction);
+        self
+    }
+
+    /// Run `instruction` right after the swap instructions
+    pub fn with_post_swap_instruction(mut self, instruction: Instruction) -> Self {
+        self.swap_hooks.post_swap_instructions.push(instruction);
+        self
+    }
+
+    /// Compute units the hook instructions need on top of the static unit limits
+    pub fn with_hook_compute_units(mut self, extra_compute_units: u32) -> Self {
+        self.swap_hooks.extra_compute_units = extra_compute_units;
+        self
+    }
+
+    /// Advance the nonce account set in `NonceCache` and sign with its nonce, so the
+    /// transaction can be signed ahead of time without expiring
+    ///
+    /// The trade is refused when no nonce account is configured, its nonce was not fetched
+    /// (`NonceCache::fetch_nonce_info_use_rpc`) or it was already used. The nonce is marked used
+    /// once a provider accepted the transaction.
+    pub fn with_durable_nonce(mut self, use_durable_nonce: bool) -> Self {
+        self.use_durable_nonce = use_durable_nonce;
+        self
+    }
+
+    /// Sign and pay with `payer` instead of the configured payer; token accounts are derived
+    /// for it while the RPC, swqos clients and caches stay shared. `KeypairSigner::wrap` turns a
+    /// keypair into a signer
+    pub fn with_payer(mut self, payer: Arc<dyn TradeSigner>) -> Self {
+        self.payer = Some(payer);
+        self
+    }
+
+    /// Resubmit up to `max_retries` times when every provider rejected the transaction with
+    /// `BlockhashNotFound`, with a freshly fetched blockhash when `refresh_blockhash` is set
+    pub fn with_blockhash_retry(mut self, max_retries: u32, refresh_blockhash: bool) -> Self {
+        self.blockhash_retry = BlockhashRetry { max_retries, refresh_blockhash };
+        self
+    }
+
+    /// Sell against at least `balance` when the RPC reports less or no token account yet, as
+    /// right after the buy that created it. Whole-balance sells sell this amount and clamped
+    /// sells are not reduced below it.
+    pub fn with_expected_min_balance(mut self, balance: u64) -> Self {
+        self.expected_min_balance = Some(balance);
+        self
+    }
+
+    /// Accept no fewer than `min_sol_out` lamports, written into the instruction instead of the
+    /// bound computed from the slippage. Supported on the protocols of
+    /// `BuyOptions::with_min_amount_out`; the trade fails before sending when the quote is
+    /// already below it.
+    pub fn with_min_sol_out(mut self, min_sol_out: u64) -> Self {
+        self.min_sol_out = Some(min_sol_out);
+        self
+    }
+
+    /// Limit the data the transaction may load to `data_size_limit` bytes, `None` sends it
+    /// without the `SetLoadedAccountsDataSizeLimit` instruction and the runtime's 64 MB default
+    pub fn with_data_size_limit(mut self, data_size_limit: Option<u32>) -> Self {
+        self.data_size_limit = data_size_limit;
+        self
+    }
+
+    /// Record `memo` in the transaction before the wSOL account is closed, see
+    /// `BuyOptions::with_memo`
+    pub fn with_memo(mut self, memo: impl Into<String>) -> Self {
+        self.memo = Some(memo.into());
+        self
+    }
+
+    /// Also record the labels under `keys` in the memo, see `BuyOptions::with_memo_labels`
+    pub fn with_memo_labels<I, K>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<String>,
+    {
+        self.memo_labels = keys.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Stop waiting for the trade at `deadline`, see `BuyOptions::with_deadline`
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+}
@@ -1,36 +1,152 @@
 use anyhow::{anyhow, Result};
+use futures::stream::{FuturesUnordered, StreamExt};
+use parking_lot::Mutex;
 use solana_hash::Hash;
 use solana_sdk::{
-    instruction::Instruction, pubkey::Pubkey, signature::Keypair, signature::Signature,
+    instruction::Instruction, message::VersionedMessage, pubkey::Pubkey, signature::Keypair,
+    signature::Signature,
+};
+use std::{
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use std::{str::FromStr, sync::Arc};
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 
 use crate::{
-    common::PriorityFee,
-    swqos::{SwqosClient, SwqosType, TradeType},
-    trading::{common::build_transaction, BuyParams, MiddlewareManager, SellParams},
+    common::{
+        nonce_cache::NonceCache, signer::TradeSigner, PriorityFee, SolanaRpcClient, TipStrategy,
+    },
+    market::sell_output_lamports,
+    swqos::{
+        confirmation::{ConfirmationService, TradeStages, WaitLevel, DEFAULT_CONFIRMATION_TIMEOUT},
+        health,
+        metrics::{self, LabeledSeries},
+        timing, SwqosClient, SwqosType, TradeType,
+    },
+    trading::{
+        common::{compile_message, sign_messages, BuildEnvironment, TransactionTooLarge},
+        core::{
+            batch_sell::SELL_MANY_PROTOCOL,
+            error::{RelayError, TradeError},
+        },
+        BuyParams, MiddlewareManager, SellParams,
+    },
 };
 
+/// When a trade sent through several swqos providers counts as successful
+///
+/// Timeouts: `FirstAccepted` relies on each provider's own confirmation polling. The other
+/// policies give every accepted signature `DEFAULT_CONFIRMATION_TIMEOUT` from its acceptance to
+/// show up on chain at the RPC commitment, and count acknowledgements until every provider has
+/// answered, which is bounded by the providers' HTTP timeouts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SuccessPolicy {
+    /// The first provider that accepted the transaction, and confirmed it when the wait level
+    /// asks for confirmation
+    #[default]
+    FirstAccepted,
+    /// A submitted transaction is confirmed on chain, whichever provider sent it
+    Confirmed,
+    /// `Confirmed`, and at least this many providers acknowledged their submission
+    ConfirmedAndQuorum(usize),
+}
+
+impl SuccessPolicy {
+    fn quorum(&self) -> usize {
+        match self {
+            SuccessPolicy::ConfirmedAndQuorum(quorum) => *quorum,
+            _ => 0,
+        }
+    }
+}
+
+/// The trade landed but fewer providers than required acknowledged it
+///
+/// The transaction is on chain; callers that only want to flag a dropping relay can take
+/// `signature` and `stages` from the error.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Transaction {signature} confirmed but only {acknowledged} of {required} providers acknowledged it")]
+pub struct QuorumNotMet {
+    pub signature: Signature,
+    pub stages: TradeStages,
+    pub acknowledged: usize,
+    pub required: usize,
+}
+
+/// Resubmission of a trade every provider rejected because its blockhash expired
+///
+/// Only failures where every error names `BlockhashNotFound` are retried, program errors and
+/// other rejections are returned as they are. Trades on a durable nonce, and trades under a
+/// policy other than `FirstAccepted`, are never retried, nor is a trade past its deadline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlockhashRetry {
+    /// Further attempts after the first, `0` disables retrying
+    pub max_retries: u32,
+    /// Fetch a new blockhash from the trade's RPC before each attempt, otherwise the same
+    /// blockhash is resent for RPC nodes lagging behind the cluster
+    pub refresh_blockhash: bool,
+}
+
 pub async fn buy_parallel_execute(
     params: BuyParams,
     instructions: Vec<Instruction>,
     protocol_name: &'static str,
-) -> Result<Signature> {
+) -> Result<(Signature, TradeStages)> {
     parallel_execute(
         params.swqos_clients,
+        params.rpc,
         params.payer,
         instructions,
         params.priority_fee,
-        params.lookup_table_key,
+        params.lookup_table_keys,
         params.recent_blockhash,
-        params.data_size_limit,
+        params.data_size_limit.unwrap_or(0),
         params.middleware_manager,
         protocol_name,
+        params.trade_type,
+        params.wait_level,
+        params.success_policy,
         true,
-        params.wait_transaction_confirmed,
+        params.use_durable_nonce,
+        params.blockhash_retry,
+        params.deadline,
+        params.sol_amount,
+        Vec::new(),
+        params.labels.as_ref().and_then(metrics::labeled_series),
+    )
+    .await
+}
+
+/// Send a buy whose instructions need `co_signers` besides the payer, e.g. a mint keypair
+pub async fn co_signed_parallel_execute(
+    params: BuyParams,
+    instructions: Vec<Instruction>,
+    protocol_name: &'static str,
+    co_signers: Vec<Arc<Keypair>>,
+) -> Result<(Signature, TradeStages)> {
+    parallel_execute(
+        params.swqos_clients,
+        params.rpc,
+        params.payer,
+        instructions,
+        params.priority_fee,
+        params.lookup_table_keys,
+        params.recent_blockhash,
+        params.data_size_limit.unwrap_or(0),
+        params.middleware_manager,
+        protocol_name,
+        params.trade_type,
+        params.wait_level,
+        params.success_policy,
         true,
+        params.use_durable_nonce,
+        params.blockhash_retry,
+        params.deadline,
+        params.sol_amount,
+        co_signers,
+        params.labels.as_ref().and_then(metrics::labeled_series),
     )
     .await
 }
@@ -39,155 +155,684 @@ pub async fn sell_parallel_execute(
     params: SellParams,
     instructions: Vec<Instruction>,
     protocol_name: &'static str,
-) -> Result<Signature> {
+) -> Result<(Signature, TradeStages)> {
+    let trade_lamports = sell_tip_trade_lamports(&params)?;
     parallel_execute(
         params.swqos_clients,
+        params.rpc,
         params.payer,
         instructions,
         params.priority_fee,
-        params.lookup_table_key,
+        params.lookup_table_keys,
         params.recent_blockhash,
-        0,
+        params.data_size_limit.unwrap_or(0),
         params.middleware_manager,
         protocol_name,
-        false,
-        params.wait_transaction_confirmed,
+        TradeType::Sell,
+        params.wait_level,
+        params.success_policy,
+        params.with_tip,
+        params.use_durable_nonce,
+        params.blockhash_retry,
+        params.deadline,
+        trade_lamports,
+        Vec::new(),
+        params.labels.as_ref().and_then(metrics::labeled_series),
+    )
+    .await
+}
+
+/// Send sells of several mints packed into one transaction, see `batch_sell`
+///
+/// `params` are those of the first packed leg with the priority fee of the packed transaction,
+/// `trade_lamports` the expected SOL output of all legs.
+pub(crate) async fn packed_sell_parallel_execute(
+    params: SellParams,
+    instructions: Vec<Instruction>,
+    trade_lamports: u64,
+) -> Result<(Signature, TradeStages)> {
+    parallel_execute(
+        params.swqos_clients,
+        params.rpc,
+        params.payer,
+        instructions,
+        params.priority_fee,
+        params.lookup_table_keys,
+        params.recent_blockhash,
+        params.data_size_limit.unwrap_or(0),
+        params.middleware_manager,
+        SELL_MANY_PROTOCOL,
+        TradeType::Sell,
+        params.wait_level,
+        params.success_policy,
         params.with_tip,
+        params.use_durable_nonce,
+        params.blockhash_retry,
+        params.deadline,
+        trade_lamports,
+        Vec::new(),
+        params.labels.as_ref().and_then(metrics::labeled_series),
     )
     .await
 }
 
-/// Generic function for parallel transaction execution
+/// Expected SOL output of a sell, read only when its tip is a percentage of the trade
+pub(crate) fn sell_tip_trade_lamports(params: &SellParams) -> Result<u64> {
+    match params.priority_fee.tip_strategy {
+        TipStrategy::PercentOfTrade(_) if params.with_tip => {
+            sell_output_lamports(params.protocol_params.as_ref(), params.token_amount.unwrap_or(0))
+                .map_err(|e| anyhow!("Cannot size the tip on the sell output: {}", e))
+        }
+        _ => Ok(0),
+    }
+}
+
+/// Generic function for parallel transaction execution, resubmitting per `blockhash_retry`
 async fn parallel_execute(
     swqos_clients: Vec<Arc<SwqosClient>>,
-    payer: Arc<Keypair>,
+    rpc: Option<Arc<SolanaRpcClient>>,
+    payer: Arc<dyn TradeSigner>,
     instructions: Vec<Instruction>,
     priority_fee: Arc<PriorityFee>,
-    lookup_table_key: Option<Pubkey>,
-    recent_blockhash: Hash,
+    lookup_table_keys: Vec<Pubkey>,
+    mut recent_blockhash: Hash,
     data_size_limit: u32,
     middleware_manager: Option<Arc<MiddlewareManager>>,
     protocol_name: &'static str,
-    is_buy: bool,
-    wait_transaction_confirmed: bool,
+    trade_type: TradeType,
+    wait_level: WaitLevel,
+    success_policy: SuccessPolicy,
     with_tip: bool,
-) -> Result<Signature> {
-    let cores = core_affinity::get_core_ids().unwrap();
-    let mut handles: Vec<JoinHandle<Result<Signature>>> = Vec::with_capacity(swqos_clients.len());
-    if is_buy
-        && (swqos_clients.len() > priority_fee.buy_tip_fees.len()
-            || priority_fee.buy_tip_fees.is_empty())
-    {
-        return Err(anyhow!("Number of tip clients exceeds the configured buy tip fees. Please configure buy_tip_fees to match swqos_clients"));
-    }
-    if !is_buy
-        && !with_tip
-        && (swqos_clients.len() > priority_fee.sell_tip_fees.len()
-            || priority_fee.sell_tip_fees.is_empty())
-    {
-        return Err(anyhow!("Number of tip clients exceeds the configured sell tip fees. Please configure sell_tip_fees to match swqos_clients"));
+    use_durable_nonce: bool,
+    blockhash_retry: BlockhashRetry,
+    deadline: Option<Instant>,
+    trade_lamports: u64,
+    co_signers: Vec<Arc<Keypair>>,
+    labeled_series: Option<Arc<LabeledSeries>>,
+) -> Result<(Signature, TradeStages)> {
+    let is_buy = trade_type != TradeType::Sell;
+    let swqos_clients =
+        submission_order(&swqos_clients, &priority_fee, is_buy, with_tip, trade_lamports)?;
+    let instructions = Arc::new(instructions);
+    let co_signers = Arc::new(co_signers);
+    let retries = if use_durable_nonce || success_policy != SuccessPolicy::FirstAccepted {
+        0
+    } else {
+        blockhash_retry.max_retries
+    };
+    let mut attempt = 0;
+    loop {
+        let result = parallel_execute_once(
+            swqos_clients.clone(),
+            rpc.clone(),
+            payer.clone(),
+            instructions.clone(),
+            priority_fee.clone(),
+            &lookup_table_keys,
+            recent_blockhash,
+            data_size_limit,
+            middleware_manager.clone(),
+            protocol_name,
+            trade_type,
+            wait_level,
+            success_policy,
+            with_tip,
+            use_durable_nonce,
+            deadline,
+            co_signers.clone(),
+            labeled_series.clone(),
+        )
+        .await;
+        let expired = result.as_ref().err().is_some_and(|e| {
+            matches!(e.downcast_ref::<TradeError>(), Some(TradeError::BlockhashExpired(_)))
+        });
+        let past_deadline = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        if !expired || attempt >= retries || past_deadline {
+            return result;
+        }
+        attempt += 1;
+        if blockhash_retry.refresh_blockhash {
+            let Some(rpc) = rpc.as_ref() else {
+                return result;
+            };
+            recent_blockhash = rpc.get_latest_blockhash().await?;
+        }
+        log::warn!(
+            icon!("⚠️ ", "Blockhash not found, resubmitting {} ({}/{}) with blockhash {}"),
+            protocol_name,
+            attempt,
+            retries,
+            recent_blockhash
+        );
     }
+}
 
-    let instructions = Arc::new(instructions);
+/// Providers in submission order with their tips in lamports, see `clients_with_tips`
+///
+/// Tips are converted to lamports once, rejecting out-of-range values before anything is sent.
+/// Without tip only `Default` clients send, which attach no tip transfer.
+pub(crate) fn submission_order(
+    swqos_clients: &[Arc<SwqosClient>],
+    priority_fee: &PriorityFee,
+    is_buy: bool,
+    with_tip: bool,
+    trade_lamports: u64,
+) -> Result<Vec<(Arc<SwqosClient>, u64)>> {
+    let tip_lamports = if is_buy || with_tip {
+        priority_fee.tip_lamports(is_buy, swqos_clients.len(), trade_lamports)?
+    } else {
+        Vec::new()
+    };
+    Ok(clients_with_tips(swqos_clients, &tip_lamports))
+}
+
+/// Providers in submission order with their tips in lamports
+///
+/// Unhealthy providers are left out and the rest tried best first, see `swqos::health`. Tips
+/// are configured in swqos order, so each is looked up by its provider's configured position.
+pub(crate) fn clients_with_tips(
+    swqos_clients: &[Arc<SwqosClient>],
+    tip_lamports: &[u64],
+) -> Vec<(Arc<SwqosClient>, u64)> {
+    health::order_clients(swqos_clients)
+        .into_iter()
+        .map(|(index, client)| (client, tip_lamports.get(index).copied().unwrap_or(0)))
+        .collect()
+}
+
+/// Message one provider is sent for a trade
+pub(crate) struct ProviderMessage {
+    /// Position of the provider in the submission order
+    pub index: usize,
+    pub tip_account: Option<Pubkey>,
+    pub tip_amount: u64,
+    pub message: Result<VersionedMessage>,
+}
+
+/// Compile the message of each provider the trade is sent through, in submission order
+///
+/// Without tip only `Default` clients send, which attach no tip transfer. Messages are compiled
+/// as the iterator advances; simulation takes the first one, the message the first provider
+/// is sent.
+pub(crate) fn provider_messages<'a>(
+    swqos_clients: &'a [(Arc<SwqosClient>, u64)],
+    payer: &'a Pubkey,
+    priority_fee: &'a PriorityFee,
+    instructions: &'a [Instruction],
+    environment: &'a BuildEnvironment,
+    recent_blockhash: Hash,
+    data_size_limit: u32,
+    middleware_manager: Option<&'a MiddlewareManager>,
+    protocol_name: &'a str,
+    is_buy: bool,
+    with_tip: bool,
+) -> impl Iterator<Item = ProviderMessage> + 'a {
+    swqos_clients
+        .iter()
+        .enumerate()
+        .filter(move |(_, (swqos_client, _))| {
+            with_tip || matches!(swqos_client.get_swqos_type(), SwqosType::Default)
+        })
+        .map(move |(index, (swqos_client, tip_amount))| {
+            let swqos_type = swqos_client.get_swqos_type();
+            let compiled = swqos_client
+                .get_tip_account()
+                .map(|account| Pubkey::from_str(&account))
+                .transpose()
+                .map_err(|e| anyhow!("Invalid tip account from {:?}: {}", swqos_type, e))
+                .and_then(|tip_account| {
+                    let message = compile_message(
+                        payer,
+                        priority_fee,
+                        instructions,
+                        environment,
+                        recent_blockhash,
+                        data_size_limit,
+                        middleware_manager,
+                        protocol_name,
+                        is_buy,
+                        tip_account.is_some(),
+                        &tip_account.unwrap_or_default(),
+                        *tip_amount,
+                    )?;
+                    Ok((tip_account, message))
+                });
+            let (tip_account, message) = match compiled {
+                Ok((tip_account, message)) => (tip_account, Ok(message)),
+                Err(e) => (None, Err(e)),
+            };
+            ProviderMessage { index, tip_account, tip_amount: *tip_amount, message }
+        })
+}
+
+/// One parallel submission of the trade through every eligible swqos client
+async fn parallel_execute_once(
+    swqos_clients: Vec<(Arc<SwqosClient>, u64)>,
+    rpc: Option<Arc<SolanaRpcClient>>,
+    payer: Arc<dyn TradeSigner>,
+    instructions: Arc<Vec<Instruction>>,
+    priority_fee: Arc<PriorityFee>,
+    lookup_table_keys: &[Pubkey],
+    recent_blockhash: Hash,
+    data_size_limit: u32,
+    middleware_manager: Option<Arc<MiddlewareManager>>,
+    protocol_name: &'static str,
+    trade_type: TradeType,
+    wait_level: WaitLevel,
+    success_policy: SuccessPolicy,
+    with_tip: bool,
+    use_durable_nonce: bool,
+    deadline: Option<Instant>,
+    co_signers: Arc<Vec<Arc<Keypair>>>,
+    labeled_series: Option<Arc<LabeledSeries>>,
+) -> Result<(Signature, TradeStages)> {
+    let is_buy = trade_type != TradeType::Sell;
+    // Only `FirstAccepted` lets each provider confirm its own transaction
+    let confirm_each = success_policy == SuccessPolicy::FirstAccepted;
+    let cores = core_affinity::get_core_ids().unwrap();
+    let mut handles: Vec<(SwqosType, JoinHandle<Result<(Signature, TradeStages)>>)> =
+        Vec::with_capacity(swqos_clients.len());
 
-    for i in 0..swqos_clients.len() {
-        let swqos_client = swqos_clients[i].clone();
-        if !with_tip && !matches!(swqos_client.get_swqos_type(), SwqosType::Default) {
-            continue;
+    // Check the policy can be met before anything is sent
+    let quorum_rpc = if confirm_each {
+        None
+    } else {
+        let providers = swqos_clients
+            .iter()
+            .filter(|(client, _)| with_tip || matches!(client.get_swqos_type(), SwqosType::Default))
+            .count();
+        if success_policy.quorum() > providers {
+            return Err(anyhow!(
+                "Quorum of {} exceeds the {} providers the trade is sent through",
+                success_policy.quorum(),
+                providers
+            ));
         }
-        let payer = payer.clone();
-        let instructions = instructions.clone();
-        let priority_fee = priority_fee.clone();
-        let core_id = cores[i % cores.len()];
+        Some(rpc.ok_or_else(|| anyhow!("{:?} needs an RPC client", success_policy))?)
+    };
+
+    // Every provider's transaction advances the same nonce, so only one of them can land
+    let environment = BuildEnvironment::for_trade(lookup_table_keys, use_durable_nonce)?;
+
+    // Compile every provider's message before signing, so the payer signs each distinct
+    // message once; providers without a tip account send the same one
+    let payer_pubkey = payer.pubkey();
+    let (sends, messages): (Vec<_>, Vec<_>) = provider_messages(
+        &swqos_clients,
+        &payer_pubkey,
+        &priority_fee,
+        &instructions,
+        &environment,
+        recent_blockhash,
+        data_size_limit,
+        middleware_manager.as_deref(),
+        protocol_name,
+        is_buy,
+        with_tip,
+    )
+    .map(|provider| ((provider.index, provider.tip_account, provider.tip_amount), provider.message))
+    .unzip();
+    let transactions = sign_messages(payer.as_ref(), &co_signers, messages).await;
 
-        let middleware_manager = middleware_manager.clone();
+    // Transactions handed to a provider, reported when the deadline cuts the trade short
+    let handed_over = Arc::new(Mutex::new(Vec::with_capacity(sends.len())));
+    let mut aborts = Vec::with_capacity(sends.len());
+    for ((i, tip_account, tip_amount), transaction) in sends.into_iter().zip(transactions) {
+        let swqos_client = swqos_clients[i].0.clone();
+        let handed_over = handed_over.clone();
+        let labeled_series = labeled_series.clone();
+        let core_id = cores[i % cores.len()];
+        let relay_type = swqos_client.get_swqos_type();
+        let has_tip_account = tip_account.is_some();
+        let tip_account = tip_account.unwrap_or_default();
+        #[cfg(feature = "capture_transaction")]
+        if let Ok(transaction) = &transaction {
+            super::replay::capture(|| {
+                super::replay::TradeInputSnapshot::from_build(
+                    protocol_name,
+                    trade_type,
+                    &relay_type,
+                    &instructions,
+                    &priority_fee,
+                    &environment,
+                    recent_blockhash,
+                    data_size_limit,
+                    middleware_manager.as_deref(),
+                    has_tip_account,
+                    &tip_account,
+                    tip_amount,
+                    transaction,
+                )
+            });
+        }
 
         let handle = tokio::spawn(async move {
             core_affinity::set_for_current(core_id);
 
             let swqos_type = swqos_client.get_swqos_type();
+            let transaction = transaction?;
 
-            let tip_account_str = swqos_client.get_tip_account()?;
-            let tip_account = Arc::new(Pubkey::from_str(&tip_account_str).unwrap_or_default());
-            let tip_amount = priority_fee.buy_tip_fees[i];
-
-            let transaction = build_transaction(
-                payer,
-                &priority_fee,
-                instructions.as_ref().clone(),
-                lookup_table_key,
-                recent_blockhash,
-                data_size_limit,
-                middleware_manager,
-                protocol_name,
-                is_buy,
-                swqos_type != SwqosType::Default,
-                &tip_account,
-                tip_amount,
-            )
-            .await?;
-
-            swqos_client
-                .send_transaction(
-                    if is_buy { TradeType::Buy } else { TradeType::Sell },
-                    &transaction,
-                )
-                .await?;
-
-            transaction
-                .signatures
-                .first()
-                .ok_or_else(|| anyhow!("Transaction has no signatures"))
-                .cloned()
+            // From here on the transaction may land, failures carry its signature
+            let signature = transaction.signatures[0];
+            let submitted = async {
+                let mut stages = TradeStages::default();
+                handed_over.lock().push(signature);
+                let sent_at = Instant::now();
+                let sent = swqos_client.send_transaction(trade_type, &transaction).await;
+                if let Some(series) = &labeled_series {
+                    series.record_submission(&swqos_type, sent_at.elapsed(), sent.is_ok());
+                }
+                sent?;
+                if has_tip_account {
+                    log::info!(
+                        icon!("✅ ", "[{:?}] {} accepted | Sig: {} | Tip: {} lamports to {}"),
+                        swqos_type,
+                        trade_type,
+                        signature,
+                        tip_amount,
+                        tip_account
+                    );
+                }
+                if use_durable_nonce {
+                    NonceCache::get_instance().mark_used();
+                }
+                stages.mark(WaitLevel::Submitted);
+                // Finalization is awaited by the caller once one backend confirmed
+                if confirm_each && wait_level >= WaitLevel::Processed {
+                    let level = wait_level.min(WaitLevel::Confirmed);
+                    let confirm_start = Instant::now();
+                    let confirmed = swqos_client
+                        .confirm_transaction_until(trade_type, signature, level, deadline)
+                        .await;
+                    // Confirmation timings only, processing is much faster
+                    if level == WaitLevel::Confirmed {
+                        timing::record_confirmation(
+                            swqos_type.clone(),
+                            confirm_start.elapsed(),
+                            confirmed.is_ok(),
+                        );
+                        if let Some(series) = &labeled_series {
+                            series.record_confirmation(&swqos_type, confirmed.is_ok());
+                        }
+                    }
+                    confirmed?;
+                    stages.mark(level);
+                }
+                Ok::<_, anyhow::Error>((signature, stages))
+            };
+            submitted.await.map_err(|e| {
+                RelayError { swqos_type, signature: Some(signature), message: e.to_string() }.into()
+            })
         });
 
-        handles.push(handle);
+        aborts.push(handle.abort_handle());
+        handles.push((relay_type, handle));
     }
     // Return as soon as any one succeeds
     let (tx, mut rx) = mpsc::channel(swqos_clients.len());
 
     // Start monitoring tasks
-    for handle in handles {
+    for (swqos_type, handle) in handles {
         let tx = tx.clone();
         tokio::spawn(async move {
             let result = handle.await;
-            let _ = tx.send(result).await;
+            let _ = tx.send((swqos_type, result)).await;
         });
     }
     drop(tx); // Close the sender
 
+    let outcome = async {
+        match quorum_rpc {
+            Some(rpc) => {
+                let service = ConfirmationService::for_rpc(&rpc);
+                let quorum = success_policy.quorum();
+                await_confirmed_quorum(rx, &service, quorum, DEFAULT_CONFIRMATION_TIMEOUT).await
+            }
+            None => await_first_success(rx, wait_level).await,
+        }
+    };
+    let Some(deadline) = deadline else {
+        return outcome.await;
+    };
+    match tokio::time::timeout_at(deadline.into(), outcome).await {
+        Ok(Ok(success)) => Ok(success),
+        // Providers confirming until the deadline themselves may report it first
+        Ok(Err(e)) if Instant::now() < deadline => Err(e),
+        _ => {
+            for abort in aborts {
+                abort.abort();
+            }
+            Err(TradeError::deadline_exceeded(handed_over.lock().clone()).into())
+        }
+    }
+}
+
+/// The first provider's success, or why every provider failed; with `WaitLevel::Submitted` the
+/// first provider to answer decides
+async fn await_first_success(
+    mut reports: mpsc::Receiver<(
+        SwqosType,
+        Result<Result<(Signature, TradeStages)>, tokio::task::JoinError>,
+    )>,
+    wait_level: WaitLevel,
+) -> Result<(Signature, TradeStages)> {
     // Wait for the first successful result
     let mut errors = Vec::new();
+    // Returned over `TradeError::AllRelaysFailed` when no provider succeeded, nothing was sent
+    // for it
+    let mut too_large: Option<TransactionTooLarge> = None;
 
-    if !wait_transaction_confirmed {
-        if let Some(result) = rx.recv().await {
+    if wait_level == WaitLevel::Submitted {
+        if let Some((_, result)) = reports.recv().await {
             match result {
-                Ok(Ok(sig)) => return Ok(sig),
-                Ok(Err(e)) => errors.push(format!("Task error: {}", e)),
-                Err(e) => errors.push(format!("Join error: {}", e)),
+                Ok(Ok(submitted)) => return Ok(submitted),
+                Ok(Err(e)) if e.is::<TransactionTooLarge>() => return Err(e),
+                Ok(Err(_)) | Err(_) => {}
             }
         }
         return Err(anyhow!("No transaction signature available"));
     }
 
-    while let Some(result) = rx.recv().await {
+    while let Some((swqos_type, result)) = reports.recv().await {
         match result {
-            Ok(Ok(sig)) => {
-                return Ok(sig);
+            Ok(Ok(confirmed)) => {
+                return Ok(confirmed);
+            }
+            Ok(Err(e)) if e.is::<TransactionTooLarge>() => {
+                too_large = e.downcast().ok();
+            }
+            Ok(Err(e)) => errors.push(RelayError::from_task_error(swqos_type, e)),
+            Err(e) => {
+                errors.push(RelayError::from_task_error(swqos_type, anyhow!("Join error: {}", e)))
             }
-            Ok(Err(e)) => {
-                // Preserve signature information in error messages
-                let error_msg = e.to_string();
-                if error_msg.contains("Signature: ") || error_msg.contains("Sig: ") || error_msg.contains("Transaction ") {
-                    errors.push(error_msg); // Keep original error with signature info
-                } else {
-                    errors.push(format!("Task error: {}", e));
+        }
+    }
+
+    // If no success, return error
+    if let Some(too_large) = too_large {
+        return Err(too_large.into());
+    }
+    Err(TradeError::relays_failed(errors).into())
+}
+
+/// Count provider acknowledgements and watch the accepted signatures until one is confirmed
+/// and `quorum` providers acknowledged, or no provider is left to answer
+///
+/// Each accepted signature gets `timeout` from its acceptance to be confirmed.
+async fn await_confirmed_quorum(
+    mut reports: mpsc::Receiver<(
+        SwqosType,
+        Result<Result<(Signature, TradeStages)>, tokio::task::JoinError>,
+    )>,
+    service: &Arc<ConfirmationService>,
+    quorum: usize,
+    timeout: Duration,
+) -> Result<(Signature, TradeStages)> {
+    let mut confirmations = FuturesUnordered::new();
+    let mut reports_open = true;
+    let mut acknowledged = 0;
+    let mut confirmed: Option<(Signature, TradeStages)> = None;
+    let mut errors = Vec::new();
+
+    loop {
+        if confirmed.is_some() && (acknowledged >= quorum || !reports_open) {
+            break;
+        }
+        if confirmed.is_none() && !reports_open && confirmations.is_empty() {
+            break;
+        }
+        tokio::select! {
+            report = reports.recv(), if reports_open => match report {
+                Some((swqos_type, Ok(Ok((signature, stages))))) => {
+                    acknowledged += 1;
+                    let handle = service.watch(signature);
+                    confirmations.push(async move {
+                        let result = handle.wait(timeout).await;
+                        (swqos_type, signature, stages, result)
+                    });
                 }
+                Some((swqos_type, Ok(Err(e)))) => errors.push(RelayError::from_task_error(swqos_type, e)),
+                Some((swqos_type, Err(e))) => errors.push(RelayError::from_task_error(
+                    swqos_type,
+                    anyhow!("Join error: {}", e),
+                )),
+                None => reports_open = false,
             },
-            Err(e) => errors.push(format!("Join error: {}", e)),
+            Some((swqos_type, signature, mut stages, result)) = confirmations.next(),
+                if !confirmations.is_empty() =>
+            {
+                match result {
+                    Ok(()) if confirmed.is_none() => {
+                        stages.mark(WaitLevel::Confirmed);
+                        confirmed = Some((signature, stages));
+                    }
+                    Ok(()) => {}
+                    Err(e) => errors.push(RelayError {
+                        swqos_type,
+                        signature: Some(signature),
+                        message: e.to_string(),
+                    }),
+                }
+            }
         }
     }
 
-    // If no success, return error
-    return Err(anyhow!("All transactions failed: {:?}", errors));
+    match confirmed {
+        Some(confirmed) if acknowledged >= quorum => Ok(confirmed),
+        Some((signature, stages)) => {
+            Err(QuorumNotMet { signature, stages, acknowledged, required: quorum }.into())
+        }
+        None => Err(TradeError::relays_failed(errors).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swqos::{testing::StubClient, timing::SubmitTimer};
+
+    fn record_submissions(swqos_type: SwqosType, success: bool, count: usize) {
+        for _ in 0..count {
+            let mut timer = SubmitTimer::start(swqos_type.clone());
+            if success {
+                timer.succeed();
+            }
+        }
+    }
+
+    type Report = (SwqosType, Result<Result<(Signature, TradeStages)>, tokio::task::JoinError>);
+
+    /// Reports of providers that accepted `signature` or rejected it, as `parallel_execute_once`
+    /// forwards them
+    fn provider_reports(
+        signature: Signature,
+        accepted: &[SwqosType],
+        rejected: &[SwqosType],
+    ) -> mpsc::Receiver<Report> {
+        let (tx, rx) = mpsc::channel(accepted.len() + rejected.len());
+        for swqos_type in accepted {
+            tx.try_send((swqos_type.clone(), Ok(Ok((signature, TradeStages::default()))))).unwrap();
+        }
+        for swqos_type in rejected {
+            tx.try_send((swqos_type.clone(), Ok(Err(anyhow!("rejected"))))).unwrap();
+        }
+        rx
+    }
+
+    /// Confirmation service on a mocked RPC, `"succeeds"` reports every signature finalized and
+    /// `"sig_not_found"` reports none
+    fn confirmation_service(rpc_url: &str) -> Arc<ConfirmationService> {
+        ConfirmationService::new(Arc::new(SolanaRpcClient::new_mock(rpc_url.to_string())))
+    }
+
+    #[tokio::test]
+    async fn quorum_met_and_confirmed_succeeds() {
+        let signature = Signature::new_unique();
+        let accepted = [SwqosType::Jito, SwqosType::Temporal, SwqosType::Astralane];
+        let reports = provider_reports(signature, &accepted, &[]);
+        let service = confirmation_service("succeeds");
+
+        let (confirmed, stages) =
+            await_confirmed_quorum(reports, &service, 2, Duration::from_secs(5)).await.unwrap();
+
+        assert_eq!(confirmed, signature);
+        assert_eq!(stages.achieved, Some(WaitLevel::Confirmed));
+    }
+
+    #[tokio::test]
+    async fn confirmed_without_quorum_reports_the_landed_signature() {
+        let signature = Signature::new_unique();
+        let reports = provider_reports(
+            signature,
+            &[SwqosType::Jito],
+            &[SwqosType::Temporal, SwqosType::Astralane],
+        );
+        let service = confirmation_service("succeeds");
+
+        let error =
+            await_confirmed_quorum(reports, &service, 3, Duration::from_secs(5)).await.unwrap_err();
+
+        // The caller decides whether a landed trade below the quorum counts
+        let quorum = error.downcast_ref::<QuorumNotMet>().expect("QuorumNotMet");
+        assert_eq!(quorum.signature, signature);
+        assert_eq!((quorum.acknowledged, quorum.required), (1, 3));
+        assert_eq!(quorum.stages.achieved, Some(WaitLevel::Confirmed));
+    }
+
+    #[tokio::test]
+    async fn neither_confirmed_nor_quorum_fails_after_the_timeout() {
+        let signature = Signature::new_unique();
+        let reports = provider_reports(signature, &[SwqosType::Jito], &[SwqosType::Temporal]);
+        let service = confirmation_service("sig_not_found");
+        let timeout = Duration::from_millis(500);
+
+        let started = Instant::now();
+        let error = await_confirmed_quorum(reports, &service, 2, timeout).await.unwrap_err();
+
+        assert!(started.elapsed() >= timeout);
+        assert!(error.downcast_ref::<QuorumNotMet>().is_none());
+        let Some(TradeError::AllRelaysFailed(errors)) = error.downcast_ref::<TradeError>() else {
+            panic!("expected AllRelaysFailed, got {}", error);
+        };
+        let failed: Vec<SwqosType> = errors.iter().map(|e| e.swqos_type.clone()).collect();
+        assert_eq!(failed, vec![SwqosType::Temporal, SwqosType::Jito]);
+    }
+
+    #[test]
+    fn tips_stay_with_their_client_when_health_reorders() {
+        // Temporal is configured first but rejects every submission, Astralane accepts them
+        record_submissions(SwqosType::Temporal, false, 4);
+        record_submissions(SwqosType::Astralane, true, 4);
+        let clients: Vec<Arc<SwqosClient>> = vec![
+            Arc::new(StubClient::new(SwqosType::Temporal)),
+            Arc::new(StubClient::new(SwqosType::Astralane)),
+        ];
+
+        let ordered: Vec<(SwqosType, u64)> = clients_with_tips(&clients, &[1_000, 2_000])
+            .iter()
+            .map(|(client, tip)| (client.get_swqos_type(), *tip))
+            .collect();
+
+        assert_eq!(ordered, vec![(SwqosType::Astralane, 2_000), (SwqosType::Temporal, 1_000)]);
+    }
 }
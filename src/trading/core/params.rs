@@ -1,54 +1,121 @@
+use super::trade_result::AnalysisCallback;
 use super::traits::ProtocolParams;
-use crate::common::bonding_curve::BondingCurveAccount;
+use crate::common::commission::Commission;
+use crate::common::labels::TradeLabels;
+use crate::common::signer::TradeSigner;
+use crate::common::spend_budget::SpendTracker;
 use crate::common::{PriorityFee, SolanaRpcClient};
-use crate::solana_streamer_sdk::streaming::event_parser::common::EventType;
-use crate::solana_streamer_sdk::streaming::event_parser::protocols::bonk::BonkTradeEvent;
-use crate::swqos::SwqosClient;
-use crate::trading::common::get_multi_token_balances;
+use crate::swqos::{confirmation::WaitLevel, SwqosClient, TradeType};
+use crate::trading::common::account_check::CreatedAccountCheck;
+use crate::trading::common::cu_sizing::CuSizing;
+use crate::trading::common::swap_hooks::SwapHooks;
+use crate::trading::common::wsol_manager::WsolAccountChoice;
+use crate::trading::core::parallel::{BlockhashRetry, SuccessPolicy};
 use crate::trading::MiddlewareManager;
 use solana_hash::Hash;
-use solana_sdk::{pubkey::Pubkey, signature::Keypair};
-use solana_streamer_sdk::streaming::event_parser::protocols::pumpfun::PumpFunTradeEvent;
-use solana_streamer_sdk::streaming::event_parser::protocols::pumpswap::{
-    PumpSwapBuyEvent, PumpSwapSellEvent,
-};
-use solana_streamer_sdk::streaming::event_parser::protocols::raydium_amm_v4::types::AmmInfo;
-use solana_streamer_sdk::streaming::event_parser::protocols::raydium_cpmm::RaydiumCpmmSwapEvent;
-use std::sync::Arc;
+use solana_sdk::pubkey::Pubkey;
+use std::{sync::Arc, time::Instant};
+/// What a buy fixes, the SOL spent or the tokens received
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuyAmountSpec {
+    /// Spend exactly this many lamports, receiving whatever tokens they buy
+    ExactSolIn(u64),
+    /// Receive exactly `token_amount` tokens, spending at most `max_sol` lamports
+    ExactTokensOut { token_amount: u64, max_sol: u64 },
+}
+
+impl BuyAmountSpec {
+    /// Lamports the buy may spend at most, the amount to wrap for it
+    pub fn max_sol_in(&self) -> u64 {
+        match *self {
+            BuyAmountSpec::ExactSolIn(sol_amount) => sol_amount,
+            BuyAmountSpec::ExactTokensOut { max_sol, .. } => max_sol,
+        }
+    }
+}
+
 /// Buy parameters
 #[derive(Clone)]
 pub struct BuyParams {
     pub rpc: Option<Arc<SolanaRpcClient>>,
-    pub payer: Arc<Keypair>,
+    pub payer: Arc<dyn TradeSigner>,
     pub mint: Pubkey,
+    /// Lamports spent, the most spent when `amount_spec` is `ExactTokensOut`
     pub sol_amount: u64,
+    /// Exact SOL in, or exact tokens out for the builders supporting it
+    pub amount_spec: BuyAmountSpec,
+    /// `Buy`, or `CreateAndBuy` when the instructions also create the mint. Selects the
+    /// matching `PriorityFee` profile and is passed on to the swqos clients.
+    pub trade_type: TradeType,
     pub slippage_basis_points: Option<u64>,
+    /// Fewest tokens the buy accepts, written into the instruction in place of the bound
+    /// `slippage_basis_points` gives, see `BuyParams::min_out_floor`
+    pub min_amount_out: Option<u64>,
     pub priority_fee: Arc<PriorityFee>,
-    pub lookup_table_key: Option<Pubkey>,
+    pub lookup_table_keys: Vec<Pubkey>,
     pub recent_blockhash: Hash,
-    pub data_size_limit: u32,
-    pub wait_transaction_confirmed: bool,
+    /// Loaded accounts data size limit of the transaction, `None` leaves out the
+    /// `SetLoadedAccountsDataSizeLimit` instruction
+    pub data_size_limit: Option<u32>,
+    /// Stage to wait for before returning
+    pub wait_level: WaitLevel,
     pub protocol_params: Box<dyn ProtocolParams>,
     pub open_seed_optimize: bool,
     pub swqos_clients: Vec<Arc<SwqosClient>>,
     pub middleware_manager: Option<Arc<MiddlewareManager>>,
+    /// Create the wSOL ATA and wrap the SOL in, off spends wSOL the ATA already holds
     pub create_wsol_ata: bool,
+    /// Close the wSOL ATA after the swap, unwrapping its balance
     pub close_wsol_ata: bool,
     pub create_mint_ata: bool,
+    pub spend_tracker: Option<Arc<SpendTracker>>,
+    /// Receives deferred analysis results of trades returned with `analysis_pending`
+    pub analysis_callback: Option<AnalysisCallback>,
+    /// Commission appended to the trade transaction
+    pub commission: Option<Commission>,
+    /// Returned on the `TradeResult`, including results delivered to `analysis_callback`
+    pub labels: Option<TradeLabels>,
+    /// When the trade sent through the swqos clients counts as successful
+    pub success_policy: SuccessPolicy,
+    /// How created token accounts that the swap does not use are reported
+    pub created_account_check: CreatedAccountCheck,
+    /// Account the SDK wraps and unwraps the trade's SOL in
+    pub wsol_account: WsolAccountChoice,
+    /// How the compute unit limit is chosen
+    pub cu_sizing: CuSizing,
+    /// Instructions spliced around the swap, see `SwapHooks`
+    pub swap_hooks: SwapHooks,
+    /// Advance the `NonceCache` nonce account and use its nonce as blockhash, see
+    /// `nonce_manager::durable_nonce`
+    pub use_durable_nonce: bool,
+    /// Resubmission when every provider rejected the transaction for its blockhash
+    pub blockhash_retry: BlockhashRetry,
+    /// Recorded in the transaction with an spl-memo instruction after the swap, see
+    /// `SwapHooks::push_memo`
+    pub memo: Option<String>,
+    /// Give up waiting for the trade at this instant, see `BuyOptions::with_deadline`
+    pub deadline: Option<Instant>,
 }
 
 /// Sell parameters
 #[derive(Clone)]
 pub struct SellParams {
     pub rpc: Option<Arc<SolanaRpcClient>>,
-    pub payer: Arc<Keypair>,
+    pub payer: Arc<dyn TradeSigner>,
     pub mint: Pubkey,
     pub token_amount: Option<u64>,
     pub slippage_basis_points: Option<u64>,
+    /// Fewest lamports the sell accepts, written into the instruction in place of the bound
+    /// `slippage_basis_points` gives, see `SellParams::min_out_floor`
+    pub min_sol_out: Option<u64>,
     pub priority_fee: Arc<PriorityFee>,
-    pub lookup_table_key: Option<Pubkey>,
+    pub lookup_table_keys: Vec<Pubkey>,
     pub recent_blockhash: Hash,
-    pub wait_transaction_confirmed: bool,
+    /// Loaded accounts data size limit of the transaction, `None` leaves out the
+    /// `SetLoadedAccountsDataSizeLimit` instruction
+    pub data_size_limit: Option<u32>,
+    /// Stage to wait for before returning
+    pub wait_level: WaitLevel,
     pub with_tip: bool,
     pub protocol_params: Box<dyn ProtocolParams>,
     pub open_seed_optimize: bool,
@@ -56,6 +123,38 @@ pub struct SellParams {
     pub middleware_manager: Option<Arc<MiddlewareManager>>,
     pub create_wsol_ata: bool,
     pub close_wsol_ata: bool,
+    pub spend_tracker: Option<Arc<SpendTracker>>,
+    /// Receives deferred analysis results of trades returned with `analysis_pending`
+    pub analysis_callback: Option<AnalysisCallback>,
+    /// Reduce `token_amount` to the token balance before building
+    pub clamp_to_balance: bool,
+    /// Balance the wallet is known to hold, used when the RPC reports less, e.g. right after
+    /// the buy that created the token account
+    pub expected_min_balance: Option<u64>,
+    /// Commission appended to the trade transaction
+    pub commission: Option<Commission>,
+    /// Returned on the `TradeResult`, including results delivered to `analysis_callback`
+    pub labels: Option<TradeLabels>,
+    /// When the trade sent through the swqos clients counts as successful
+    pub success_policy: SuccessPolicy,
+    /// How created token accounts that the swap does not use are reported
+    pub created_account_check: CreatedAccountCheck,
+    /// Account the SDK wraps and unwraps the trade's SOL in
+    pub wsol_account: WsolAccountChoice,
+    /// How the compute unit limit is chosen
+    pub cu_sizing: CuSizing,
+    /// Instructions spliced around the swap, see `SwapHooks`
+    pub swap_hooks: SwapHooks,
+    /// Advance the `NonceCache` nonce account and use its nonce as blockhash, see
+    /// `nonce_manager::durable_nonce`
+    pub use_durable_nonce: bool,
+    /// Resubmission when every provider rejected the transaction for its blockhash
+    pub blockhash_retry: BlockhashRetry,
+    /// Recorded in the transaction with an spl-memo instruction after the swap, see
+    /// `SwapHooks::push_memo`
+    pub memo: Option<String>,
+    /// Give up waiting for the trade at this instant, see `BuyOptions::with_deadline`
+    pub deadline: Option<Instant>,
 }
 
 /// Buy parameters with MEV service support
@@ -64,16 +163,52 @@ pub struct SellParams {
 pub struct BuyWithTipParams {
     pub rpc: Option<Arc<SolanaRpcClient>>,
     pub swqos_clients: Vec<Arc<SwqosClient>>,
-    pub payer: Arc<Keypair>,
+    pub payer: Arc<dyn TradeSigner>,
     pub mint: Pubkey,
     pub creator: Pubkey,
     pub sol_amount: u64,
+    pub amount_spec: BuyAmountSpec,
+    pub trade_type: TradeType,
     pub slippage_basis_points: Option<u64>,
+    /// Fewest tokens the buy accepts, written into the instruction in place of the bound
+    /// `slippage_basis_points` gives, see `BuyParams::min_out_floor`
+    pub min_amount_out: Option<u64>,
     pub priority_fee: PriorityFee,
-    pub lookup_table_key: Option<Pubkey>,
+    pub lookup_table_keys: Vec<Pubkey>,
     pub recent_blockhash: Hash,
-    pub data_size_limit: u32,
+    /// Loaded accounts data size limit of the transaction, `None` leaves out the
+    /// `SetLoadedAccountsDataSizeLimit` instruction
+    pub data_size_limit: Option<u32>,
     pub protocol_params: Box<dyn ProtocolParams>,
+    pub spend_tracker: Option<Arc<SpendTracker>>,
+    /// Receives deferred analysis results of trades returned with `analysis_pending`
+    pub analysis_callback: Option<AnalysisCallback>,
+    /// Commission appended to the trade transaction
+    pub commission: Option<Commission>,
+    /// Returned on the `TradeResult`, including results delivered to `analysis_callback`
+    pub labels: Option<TradeLabels>,
+    /// When the trade sent through the swqos clients counts as successful
+    pub success_policy: SuccessPolicy,
+    /// How created token accounts that the swap does not use are reported
+    pub created_account_check: CreatedAccountCheck,
+    /// Account the SDK wraps and unwraps the trade's SOL in
+    pub wsol_account: WsolAccountChoice,
+    pub create_wsol_ata: bool,
+    pub close_wsol_ata: bool,
+    /// How the compute unit limit is chosen
+    pub cu_sizing: CuSizing,
+    /// Instructions spliced around the swap, see `SwapHooks`
+    pub swap_hooks: SwapHooks,
+    /// Advance the `NonceCache` nonce account and use its nonce as blockhash, see
+    /// `nonce_manager::durable_nonce`
+    pub use_durable_nonce: bool,
+    /// Resubmission when every provider rejected the transaction for its blockhash
+    pub blockhash_retry: BlockhashRetry,
+    /// Recorded in the transaction with an spl-memo instruction after the swap, see
+    /// `SwapHooks::push_memo`
+    pub memo: Option<String>,
+    /// Give up waiting for the trade at this instant, see `BuyOptions::with_deadline`
+    pub deadline: Option<Instant>,
 }
 
 /// Sell parameters with MEV service support
@@ -82,603 +217,107 @@ pub struct BuyWithTipParams {
 pub struct SellWithTipParams {
     pub rpc: Option<Arc<SolanaRpcClient>>,
     pub swqos_clients: Vec<Arc<SwqosClient>>,
-    pub payer: Arc<Keypair>,
+    pub payer: Arc<dyn TradeSigner>,
     pub mint: Pubkey,
     pub creator: Pubkey,
     pub token_amount: Option<u64>,
     pub slippage_basis_points: Option<u64>,
+    /// Fewest lamports the sell accepts, written into the instruction in place of the bound
+    /// `slippage_basis_points` gives, see `SellParams::min_out_floor`
+    pub min_sol_out: Option<u64>,
     pub priority_fee: PriorityFee,
-    pub lookup_table_key: Option<Pubkey>,
+    pub lookup_table_keys: Vec<Pubkey>,
     pub recent_blockhash: Hash,
+    /// Loaded accounts data size limit of the transaction, `None` leaves out the
+    /// `SetLoadedAccountsDataSizeLimit` instruction
+    pub data_size_limit: Option<u32>,
+    /// Stage to wait for before returning, below `Confirmed` the result is `analysis_pending`
+    pub wait_level: WaitLevel,
     pub protocol_params: Box<dyn ProtocolParams>,
+    pub spend_tracker: Option<Arc<SpendTracker>>,
+    /// Receives deferred analysis results of trades returned with `analysis_pending`
+    pub analysis_callback: Option<AnalysisCallback>,
+    /// Reduce `token_amount` to the token balance before building
+    pub clamp_to_balance: bool,
+    /// Balance the wallet is known to hold, used when the RPC reports less, e.g. right after
+    /// the buy that created the token account
+    pub expected_min_balance: Option<u64>,
+    /// Commission appended to the trade transaction
+    pub commission: Option<Commission>,
+    /// Returned on the `TradeResult`, including results delivered to `analysis_callback`
+    pub labels: Option<TradeLabels>,
+    /// When the trade sent through the swqos clients counts as successful
+    pub success_policy: SuccessPolicy,
+    /// How created token accounts that the swap does not use are reported
+    pub created_account_check: CreatedAccountCheck,
+    /// Account the SDK wraps and unwraps the trade's SOL in
+    pub wsol_account: WsolAccountChoice,
+    pub create_wsol_ata: bool,
+    pub close_wsol_ata: bool,
+    /// How the compute unit limit is chosen
+    pub cu_sizing: CuSizing,
+    /// Instructions spliced around the swap, see `SwapHooks`
+    pub swap_hooks: SwapHooks,
+    /// Advance the `NonceCache` nonce account and use its nonce as blockhash, see
+    /// `nonce_manager::durable_nonce`
+    pub use_durable_nonce: bool,
+    /// Resubmission when every provider rejected the transaction for its blockhash
+    pub blockhash_retry: BlockhashRetry,
+    /// Recorded in the transaction with an spl-memo instruction after the swap, see
+    /// `SwapHooks::push_memo`
+    pub memo: Option<String>,
+    /// Give up waiting for the trade at this instant, see `BuyOptions::with_deadline`
+    pub deadline: Option<Instant>,
 }
 
-/// PumpFun protocol specific parameters
-/// Configuration parameters specific to PumpFun trading protocol
-#[derive(Clone)]
-pub struct PumpFunParams {
-    pub bonding_curve: Arc<BondingCurveAccount>,
-    pub associated_bonding_curve: Pubkey,
-    pub creator_vault: Pubkey,
-    /// Whether to close token account when selling, only effective during sell operations
-    pub close_token_account_when_sell: Option<bool>,
-    
-    // CUSTOM FIELDS: Restored from backup for compatibility with our trading system
-    /// Fee config account for PumpFun fee management
-    pub fee_config: Pubkey,
-    /// Fee program account for PumpFun fee calculation
-    pub fee_program: Pubkey,
-}
-
-impl PumpFunParams {
-    pub fn immediate_sell(creator_vault: Pubkey, close_token_account_when_sell: bool) -> Self {
-        Self {
-            bonding_curve: Arc::new(BondingCurveAccount { ..Default::default() }),
-            associated_bonding_curve: Pubkey::default(),
-            creator_vault: creator_vault,
-            close_token_account_when_sell: Some(close_token_account_when_sell),
-            fee_config: crate::instruction::utils::pumpfun::accounts::FEE_CONFIG,
-            fee_program: crate::instruction::utils::pumpfun::accounts::FEE_PROGRAM,
-        }
-    }
-
-    pub fn from_dev_trade(
-        event: &PumpFunTradeEvent,
-        close_token_account_when_sell: Option<bool>,
-    ) -> Self {
-        let bonding_curve = BondingCurveAccount::from_dev_trade(
-            &event.mint,
-            event.token_amount,
-            event.max_sol_cost,
-            event.creator,
-        );
-        Self {
-            bonding_curve: Arc::new(bonding_curve),
-            associated_bonding_curve: event.associated_bonding_curve,
-            creator_vault: event.creator_vault,
-            close_token_account_when_sell: close_token_account_when_sell,
-            fee_config: crate::instruction::utils::pumpfun::accounts::FEE_CONFIG,
-            fee_program: crate::instruction::utils::pumpfun::accounts::FEE_PROGRAM,
-        }
-    }
-
-    pub fn from_trade(
-        event: &PumpFunTradeEvent,
-        close_token_account_when_sell: Option<bool>,
-    ) -> Self {
-        let bonding_curve = BondingCurveAccount::from_trade(event);
-        Self {
-            bonding_curve: Arc::new(bonding_curve),
-            associated_bonding_curve: event.associated_bonding_curve,
-            creator_vault: event.creator_vault,
-            close_token_account_when_sell: close_token_account_when_sell,
-            fee_config: crate::instruction::utils::pumpfun::accounts::FEE_CONFIG,
-            fee_program: crate::instruction::utils::pumpfun::accounts::FEE_PROGRAM,
-        }
-    }
-}
-
-impl ProtocolParams for PumpFunParams {
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
-
-    fn clone_box(&self) -> Box<dyn ProtocolParams> {
-        Box::new(self.clone())
-    }
-}
-
-/// PumpSwap Protocol Specific Parameters
-///
-/// Parameters for configuring PumpSwap trading protocol, including liquidity pool information,
-/// token configuration, and transaction amounts.
-///
-/// **Performance Note**: If these parameters are not provided, the system will attempt to
-/// retrieve the relevant information from RPC, which will increase transaction time.
-/// For optimal performance, it is recommended to provide all necessary parameters in advance.
-#[derive(Clone)]
-pub struct PumpSwapParams {
-    /// Liquidity pool address
-    pub pool: Pubkey,
-    /// Base token mint address
-    /// The mint account address of the base token in the trading pair
-    pub base_mint: Pubkey,
-    /// Quote token mint address
-    /// The mint account address of the quote token in the trading pair, usually SOL or USDC
-    pub quote_mint: Pubkey,
-    /// Base token reserves in the pool
-    pub pool_base_token_reserves: u64,
-    /// Quote token reserves in the pool
-    pub pool_quote_token_reserves: u64,
-    
-    // CUSTOM FIELDS: Restored from backup for compatibility with our trading system
-    /// Token creator address (coin_creator from PumpSwap events)
-    /// This is required for deriving the correct coin_creator_vault_authority
-    pub creator: Pubkey,
-    /// Automatically handle WSOL wrapping
-    /// When true, automatically handles wrapping and unwrapping operations between SOL and WSOL
-    pub auto_handle_wsol: bool,
-    /// Fee config account for PumpSwap fee management
-    pub fee_config: Pubkey,
-    /// Fee program account for PumpSwap fee calculation
-    pub fee_program: Pubkey,
-}
-
-impl PumpSwapParams {
-    pub fn from_buy_trade(event: &PumpSwapBuyEvent) -> Self {
-        let fee_config = crate::instruction::utils::pumpswap::accounts::get_fee_config();
-        let fee_program = crate::instruction::utils::pumpswap::accounts::FEE_PROGRAM;
-        
-        Self {
-            pool: event.pool,
-            base_mint: event.base_mint,
-            quote_mint: event.quote_mint,
-            pool_base_token_reserves: event.pool_base_token_reserves,
-            pool_quote_token_reserves: event.pool_quote_token_reserves,
-            creator: event.coin_creator,
-            auto_handle_wsol: true,
-            // 🔧 CRITICAL FIX: Event fee fields are #[borsh(skip)] and empty - use proper PDA derivation
-            fee_config,
-            fee_program,
-        }
-    }
-
-    pub fn from_sell_trade(event: &PumpSwapSellEvent) -> Self {
-        let fee_config = crate::instruction::utils::pumpswap::accounts::get_fee_config();
-        let fee_program = crate::instruction::utils::pumpswap::accounts::FEE_PROGRAM;
-        
-        Self {
-            pool: event.pool,
-            base_mint: event.base_mint,
-            quote_mint: event.quote_mint,
-            pool_base_token_reserves: event.pool_base_token_reserves,
-            pool_quote_token_reserves: event.pool_quote_token_reserves,
-            creator: event.coin_creator,
-            auto_handle_wsol: true,
-            // 🔧 CRITICAL FIX: Event fee fields are #[borsh(skip)] and empty - use proper PDA derivation
-            fee_config,
-            fee_program,
-        }
-    }
-
-    pub async fn from_pool_address_by_rpc(
-        rpc: &SolanaRpcClient,
-        pool_address: &Pubkey,
-    ) -> Result<Self, anyhow::Error> {
-        let pool_data = crate::instruction::utils::pumpswap::fetch_pool(rpc, pool_address).await?;
-        let (pool_base_token_reserves, pool_quote_token_reserves) =
-            crate::instruction::utils::pumpswap::get_token_balances(&pool_data, rpc).await?;
-
-        let fee_config = crate::instruction::utils::pumpswap::accounts::get_fee_config();
-        let fee_program = crate::instruction::utils::pumpswap::accounts::FEE_PROGRAM;
-        
-        Ok(Self {
-            pool: pool_address.clone(),
-            base_mint: pool_data.base_mint,
-            quote_mint: pool_data.quote_mint,
-            pool_base_token_reserves: pool_base_token_reserves,
-            pool_quote_token_reserves: pool_quote_token_reserves,
-            creator: pool_data.coin_creator, // Extract creator from pool data
-            auto_handle_wsol: true,
-            fee_config,
-            fee_program,
-        })
-    }
-}
-
-impl ProtocolParams for PumpSwapParams {
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
-
-    fn clone_box(&self) -> Box<dyn ProtocolParams> {
-        Box::new(self.clone())
-    }
-}
-
-/// Bonk protocol specific parameters
-/// Configuration parameters specific to Bonk trading protocol
-#[derive(Clone, Default)]
-pub struct BonkParams {
-    pub virtual_base: u128,
-    pub virtual_quote: u128,
-    pub real_base: u128,
-    pub real_quote: u128,
-    pub pool_state: Pubkey,
-    pub base_vault: Pubkey,
-    pub quote_vault: Pubkey,
-    /// Token program ID
-    /// Specifies the program used by the token, usually spl_token::ID or spl_token_2022::ID
-    pub mint_token_program: Pubkey,
-    pub platform_config: Pubkey,
-    pub platform_associated_account: Pubkey,
-    pub creator_associated_account: Pubkey,
-    
-    // CUSTOM FIELDS: Restored from backup for compatibility with our trading system
-    pub auto_handle_wsol: bool,
-    /// Dynamic fee destination accounts from trade event  
-    pub fee_destination_1: Pubkey,
-    pub fee_destination_2: Pubkey,
-}
+// Previous paths of the protocol params, which moved to `crate::params`
+#[deprecated(note = "use sol_trade_sdk::prelude::PumpFunParams")]
+pub type PumpFunParams = crate::params::PumpFunParams;
+#[deprecated(note = "use sol_trade_sdk::prelude::PumpSwapParams")]
+pub type PumpSwapParams = crate::params::PumpSwapParams;
+#[deprecated(note = "use sol_trade_sdk::prelude::BonkParams")]
+pub type BonkParams = crate::params::BonkParams;
+#[deprecated(note = "use sol_trade_sdk::prelude::RaydiumCpmmParams")]
+pub type RaydiumCpmmParams = crate::params::RaydiumCpmmParams;
+#[deprecated(note = "use sol_trade_sdk::prelude::RaydiumAmmV4Params")]
+pub type RaydiumAmmV4Params = crate::params::RaydiumAmmV4Params;
+#[deprecated(note = "use sol_trade_sdk::prelude::RaydiumClmmParams")]
+pub type RaydiumClmmParams = crate::params::RaydiumClmmParams;
+#[deprecated(note = "use sol_trade_sdk::prelude::RaydiumClmmV2Params")]
+pub type RaydiumClmmV2Params = crate::params::RaydiumClmmV2Params;
+#[deprecated(note = "use sol_trade_sdk::prelude::JupiterParams")]
+pub type JupiterParams = crate::params::JupiterParams;
+#[deprecated(note = "use sol_trade_sdk::prelude::MeteoraDlmmParams")]
+pub type MeteoraDlmmParams = crate::params::MeteoraDlmmParams;
 
-impl BonkParams {
-    pub fn immediate_sell(
-        mint_token_program: Pubkey,
-        platform_config: Pubkey,
-        platform_associated_account: Pubkey,
-        creator_associated_account: Pubkey,
-    ) -> Self {
-        Self {
-            mint_token_program,
-            platform_config,
-            platform_associated_account,
-            creator_associated_account,
-            ..Default::default()
-        }
-    }
-    pub fn from_trade(trade_info: BonkTradeEvent) -> Self {
-        Self {
-            virtual_base: trade_info.virtual_base as u128,
-            virtual_quote: trade_info.virtual_quote as u128,
-            real_base: trade_info.real_base_after as u128,
-            real_quote: trade_info.real_quote_after as u128,
-            pool_state: trade_info.pool_state,
-            base_vault: trade_info.base_vault,
-            quote_vault: trade_info.quote_vault,
-            mint_token_program: trade_info.base_token_program,
-            platform_config: trade_info.platform_config,
-            platform_associated_account: trade_info.platform_associated_account,
-            creator_associated_account: trade_info.creator_associated_account,
-            auto_handle_wsol: true,
-            fee_destination_1: trade_info.fee_destination_1,
-            fee_destination_2: trade_info.fee_destination_2,
+// CUSTOM METHODS: Restored from backup for compatibility with our trading system
+impl BuyParams {
+    /// Fail for `ExactTokensOut` buys on protocols that only build exact-in swaps
+    pub fn require_exact_sol_in(&self, protocol: &str) -> Result<(), anyhow::Error> {
+        match self.amount_spec {
+            BuyAmountSpec::ExactSolIn(_) => Ok(()),
+            BuyAmountSpec::ExactTokensOut { .. } => Err(anyhow::anyhow!(
+                "{} does not support exact-out buys, use BuyAmountSpec::ExactSolIn",
+                protocol
+            )),
         }
     }
 
-    pub fn from_dev_trade(trade_info: BonkTradeEvent) -> Self {
-        const DEFAULT_VIRTUAL_BASE: u128 = 1073025605596382;
-        const DEFAULT_VIRTUAL_QUOTE: u128 = 30000852951;
-        let amount_in = if trade_info.metadata.event_type == EventType::BonkBuyExactIn {
-            trade_info.amount_in
-        } else {
-            crate::instruction::utils::bonk::get_amount_in(
-                trade_info.amount_out,
-                crate::instruction::utils::bonk::accounts::PROTOCOL_FEE_RATE,
-                crate::instruction::utils::bonk::accounts::PLATFORM_FEE_RATE,
-                crate::instruction::utils::bonk::accounts::SHARE_FEE_RATE,
-                DEFAULT_VIRTUAL_BASE,
-                DEFAULT_VIRTUAL_QUOTE,
-                0,
-                0,
-                0,
-            )
-        };
-        let real_quote = crate::instruction::utils::bonk::get_amount_in_net(
-            amount_in,
-            crate::instruction::utils::bonk::accounts::PROTOCOL_FEE_RATE,
-            crate::instruction::utils::bonk::accounts::PLATFORM_FEE_RATE,
-            crate::instruction::utils::bonk::accounts::SHARE_FEE_RATE,
-        ) as u128;
-        let amount_out = if trade_info.metadata.event_type == EventType::BonkBuyExactIn {
-            crate::instruction::utils::bonk::get_amount_out(
-                trade_info.amount_in,
-                crate::instruction::utils::bonk::accounts::PROTOCOL_FEE_RATE,
-                crate::instruction::utils::bonk::accounts::PLATFORM_FEE_RATE,
-                crate::instruction::utils::bonk::accounts::SHARE_FEE_RATE,
-                DEFAULT_VIRTUAL_BASE,
-                DEFAULT_VIRTUAL_QUOTE,
-                0,
-                0,
-                0,
-            ) as u128
-        } else {
-            trade_info.amount_out as u128
+    /// `min_amount_out` once checked against `expected_out`, the tokens the buy is quoted
+    /// before slippage
+    ///
+    /// `None` leaves the bound to `slippage_basis_points`. Fails when the quote is already below
+    /// the floor, or when the buy is exact-out, whose token amount is the floor itself.
+    pub fn min_out_floor(&self, expected_out: u64) -> Result<Option<u64>, anyhow::Error> {
+        let Some(min_amount_out) = self.min_amount_out else {
+            return Ok(None);
         };
-        let real_base = amount_out;
-        Self {
-            virtual_base: DEFAULT_VIRTUAL_BASE,
-            virtual_quote: DEFAULT_VIRTUAL_QUOTE,
-            real_base: real_base,
-            real_quote: real_quote,
-            pool_state: trade_info.pool_state,
-            base_vault: trade_info.base_vault,
-            quote_vault: trade_info.quote_vault,
-            mint_token_program: trade_info.base_token_program,
-            platform_config: trade_info.platform_config,
-            platform_associated_account: trade_info.platform_associated_account,
-            creator_associated_account: trade_info.creator_associated_account,
-            auto_handle_wsol: true,
-            fee_destination_1: trade_info.fee_destination_1,
-            fee_destination_2: trade_info.fee_destination_2,
+        if let BuyAmountSpec::ExactTokensOut { .. } = self.amount_spec {
+            return Err(anyhow::anyhow!("min_amount_out cannot be combined with an exact-out buy"));
         }
+        check_min_out(expected_out, min_amount_out, "tokens")
     }
 
-    pub async fn from_mint_by_rpc(
-        rpc: &SolanaRpcClient,
-        mint: &Pubkey,
-    ) -> Result<Self, anyhow::Error> {
-        let pool_address = crate::instruction::utils::bonk::get_pool_pda(
-            mint,
-            &crate::constants::WSOL_TOKEN_ACCOUNT,
-        )
-        .unwrap();
-        let pool_data =
-            crate::instruction::utils::bonk::fetch_pool_state(rpc, &pool_address).await?;
-        let token_account = rpc.get_account(&pool_data.base_mint).await?;
-        let platform_associated_account =
-            crate::instruction::utils::bonk::get_platform_associated_account(
-                &pool_data.platform_config,
-            );
-        let creator_associated_account =
-            crate::instruction::utils::bonk::get_creator_associated_account(&pool_data.creator);
-        let platform_associated_account = platform_associated_account.unwrap();
-        let creator_associated_account = creator_associated_account.unwrap();
-        Ok(Self {
-            virtual_base: pool_data.virtual_base as u128,
-            virtual_quote: pool_data.virtual_quote as u128,
-            real_base: pool_data.real_base as u128,
-            real_quote: pool_data.real_quote as u128,
-            pool_state: pool_address,
-            base_vault: pool_data.base_vault,
-            quote_vault: pool_data.quote_vault,
-            mint_token_program: token_account.owner,
-            platform_config: pool_data.platform_config,
-            platform_associated_account,
-            creator_associated_account,
-            auto_handle_wsol: true,
-            fee_destination_1: Pubkey::default(),
-            fee_destination_2: Pubkey::default(),
-        })
-    }
-}
-
-impl ProtocolParams for BonkParams {
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
-
-    fn clone_box(&self) -> Box<dyn ProtocolParams> {
-        Box::new(self.clone())
-    }
-}
-
-/// RaydiumCpmm protocol specific parameters
-/// Configuration parameters specific to Raydium CPMM trading protocol
-#[derive(Clone)]
-pub struct RaydiumCpmmParams {
-    /// Pool address
-    pub pool_state: Pubkey,
-    /// Amm config address
-    pub amm_config: Pubkey,
-    /// Base token mint address
-    pub base_mint: Pubkey,
-    /// Quote token mint address
-    pub quote_mint: Pubkey,
-    /// Base token reserve amount in the pool
-    pub base_reserve: u64,
-    /// Quote token reserve amount in the pool
-    pub quote_reserve: u64,
-    /// Base token vault address
-    pub base_vault: Pubkey,
-    /// Quote token vault address
-    pub quote_vault: Pubkey,
-    /// Base token program ID (usually spl_token::ID or spl_token_2022::ID)
-    pub base_token_program: Pubkey,
-    /// Quote token program ID (usually spl_token::ID or spl_token_2022::ID)
-    pub quote_token_program: Pubkey,
-    /// Observation state account
-    pub observation_state: Pubkey,
-    
-    // CUSTOM FIELDS: Restored from backup for backward compatibility with our trading system
-    /// Whether to automatically handle wSOL wrapping and unwrapping
-    pub auto_handle_wsol: bool,
-    /// Pool authority address (for backward compatibility)
-    pub authority: Option<Pubkey>,
-    /// Input token vault account (alias for base_vault for backward compatibility)
-    pub input_vault: Option<Pubkey>,
-    /// Output token vault account (alias for quote_vault for backward compatibility)  
-    pub output_vault: Option<Pubkey>,
-}
-
-impl RaydiumCpmmParams {
-    pub fn from_trade(
-        trade_info: RaydiumCpmmSwapEvent,
-        base_reserve: u64,
-        quote_reserve: u64,
-    ) -> Self {
-        Self {
-            pool_state: trade_info.pool_state,
-            amm_config: trade_info.amm_config,
-            base_mint: trade_info.input_token_mint,
-            quote_mint: trade_info.output_token_mint,
-            base_reserve: base_reserve,
-            quote_reserve: quote_reserve,
-            base_vault: trade_info.input_vault,
-            quote_vault: trade_info.output_vault,
-            base_token_program: trade_info.input_token_program,
-            quote_token_program: trade_info.output_token_program,
-            observation_state: trade_info.observation_state,
-            auto_handle_wsol: true,
-            authority: None,
-            input_vault: Some(trade_info.input_vault),
-            output_vault: Some(trade_info.output_vault),
-        }
-    }
-
-    pub async fn from_pool_address_by_rpc(
-        rpc: &SolanaRpcClient,
-        pool_address: &Pubkey,
-    ) -> Result<Self, anyhow::Error> {
-        let pool =
-            crate::instruction::utils::raydium_cpmm::fetch_pool_state(rpc, pool_address).await?;
-        let (token0_balance, token1_balance) =
-            crate::instruction::utils::raydium_cpmm::get_pool_token_balances(
-                rpc,
-                pool_address,
-                &pool.token0_mint,
-                &pool.token1_mint,
-            )
-            .await?;
-        Ok(Self {
-            pool_state: pool_address.clone(),
-            amm_config: pool.amm_config,
-            base_mint: pool.token0_mint,
-            quote_mint: pool.token1_mint,
-            base_reserve: token0_balance,
-            quote_reserve: token1_balance,
-            base_vault: pool.token0_vault,
-            quote_vault: pool.token1_vault,
-            base_token_program: pool.token0_program,
-            quote_token_program: pool.token1_program,
-            observation_state: pool.observation_key,
-            auto_handle_wsol: true,
-            authority: None,
-            input_vault: Some(pool.token0_vault),
-            output_vault: Some(pool.token1_vault),
-        })
-    }
-}
-
-impl ProtocolParams for RaydiumCpmmParams {
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
-
-    fn clone_box(&self) -> Box<dyn ProtocolParams> {
-        Box::new(self.clone())
-    }
-}
-
-/// RaydiumCpmm protocol specific parameters
-/// Configuration parameters specific to Raydium CPMM trading protocol
-#[derive(Clone)]
-pub struct RaydiumAmmV4Params {
-    /// AMM pool address
-    pub amm: Pubkey,
-    /// Base token (coin) mint address
-    pub coin_mint: Pubkey,
-    /// Quote token (pc) mint address  
-    pub pc_mint: Pubkey,
-    /// Pool's coin token account address
-    pub token_coin: Pubkey,
-    /// Pool's pc token account address
-    pub token_pc: Pubkey,
-    /// Current coin reserve amount in the pool
-    pub coin_reserve: u64,
-    /// Current pc reserve amount in the pool
-    pub pc_reserve: u64,
-    /// Whether to automatically handle wSOL wrapping and unwrapping
-    pub auto_handle_wsol: bool,
-    /// AMM open orders account
-    pub open_orders: Pubkey,
-    /// Serum market account
-    pub market: Pubkey,
-    /// Serum program account
-    pub serum_dex: Pubkey,
-    /// AMM target orders account
-    pub target_orders: Pubkey,
-}
-
-impl RaydiumAmmV4Params {
-    pub fn from_amm_info_and_reserves(
-        amm: Pubkey,
-        amm_info: AmmInfo,
-        coin_reserve: u64,
-        pc_reserve: u64,
-    ) -> Self {
-        Self {
-            amm,
-            coin_mint: amm_info.coin_mint,
-            pc_mint: amm_info.pc_mint,
-            token_coin: amm_info.token_coin,
-            token_pc: amm_info.token_pc,
-            coin_reserve,
-            pc_reserve,
-            auto_handle_wsol: true,
-            open_orders: amm_info.open_orders,
-            market: amm_info.market,
-            serum_dex: amm_info.serum_dex,
-            target_orders: amm_info.target_orders,
-        }
-    }
-    pub async fn from_amm_address_by_rpc(
-        rpc: &SolanaRpcClient,
-        amm: Pubkey,
-    ) -> Result<Self, anyhow::Error> {
-        let amm_info = crate::instruction::utils::raydium_amm_v4::fetch_amm_info(rpc, amm).await?;
-        let (coin_reserve, pc_reserve) =
-            get_multi_token_balances(rpc, &amm_info.token_coin, &amm_info.token_pc).await?;
-        Ok(Self {
-            amm,
-            coin_mint: amm_info.coin_mint,
-            pc_mint: amm_info.pc_mint,
-            token_coin: amm_info.token_coin,
-            token_pc: amm_info.token_pc,
-            coin_reserve,
-            pc_reserve,
-            auto_handle_wsol: true,
-            open_orders: amm_info.open_orders,
-            market: amm_info.market,
-            serum_dex: amm_info.serum_dex,
-            target_orders: amm_info.target_orders,
-        })
-    }
-}
-
-impl ProtocolParams for RaydiumAmmV4Params {
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
-
-    fn clone_box(&self) -> Box<dyn ProtocolParams> {
-        Box::new(self.clone())
-    }
-}
-
-/// Raydium CLMM V2 protocol specific parameters
-/// Configuration parameters specific to Raydium CLMM V2 trading protocol
-#[derive(Clone)]
-pub struct RaydiumClmmV2Params {
-    /// Core CLMM accounts
-    pub amm_config: Pubkey,
-    pub pool_state: Pubkey,
-    pub input_vault: Pubkey,
-    pub output_vault: Pubkey,
-    pub observation_state: Pubkey,
-    /// Vault mint addresses (V2 specific)
-    pub input_vault_mint: Pubkey,
-    pub output_vault_mint: Pubkey,
-    /// Tick arrays for swap execution
-    pub tick_arrays: Vec<Pubkey>,
-    /// Token programs (V2 includes token_program_2022)
-    pub input_token_program: Pubkey,
-    pub output_token_program: Pubkey,
-    pub token_program: Pubkey,
-    pub token_program_2022: Pubkey,
-    pub memo_program: Pubkey,
-    /// User token accounts
-    pub payer_sol_account: Pubkey,
-    pub payer_token_account: Pubkey,
-    /// Instruction parameters
-    pub other_amount_threshold: u64,
-    pub sqrt_price_limit_x64: u128,
-    pub is_base_input: bool,
-    /// Whether to automatically handle wSOL wrapping and unwrapping
-    pub auto_handle_wsol: bool,
-}
-
-impl ProtocolParams for RaydiumClmmV2Params {
-    fn as_any(&self) -> &dyn std::any::Any {
-        self
-    }
-
-    fn clone_box(&self) -> Box<dyn ProtocolParams> {
-        Box::new(self.clone())
-    }
-}
-
-// CUSTOM METHODS: Restored from backup for compatibility with our trading system
-impl BuyParams {
     /// Convert to BuyWithTipParams
     /// Transforms basic buy parameters into MEV-enabled parameters
     pub fn with_tip(self, swqos_clients: Vec<Arc<SwqosClient>>) -> BuyWithTipParams {
@@ -689,17 +328,47 @@ impl BuyParams {
             mint: self.mint,
             creator: Pubkey::default(),
             sol_amount: self.sol_amount,
+            amount_spec: self.amount_spec,
+            trade_type: self.trade_type,
             slippage_basis_points: self.slippage_basis_points,
+            min_amount_out: self.min_amount_out,
             priority_fee: (*self.priority_fee).clone(),
-            lookup_table_key: self.lookup_table_key,
+            lookup_table_keys: self.lookup_table_keys,
             recent_blockhash: self.recent_blockhash,
             data_size_limit: self.data_size_limit,
             protocol_params: self.protocol_params,
+            spend_tracker: self.spend_tracker,
+            analysis_callback: self.analysis_callback,
+            commission: self.commission,
+            labels: self.labels,
+            success_policy: self.success_policy,
+            created_account_check: self.created_account_check,
+            wsol_account: self.wsol_account,
+            create_wsol_ata: self.create_wsol_ata,
+            close_wsol_ata: self.close_wsol_ata,
+            cu_sizing: self.cu_sizing,
+            swap_hooks: self.swap_hooks,
+            use_durable_nonce: self.use_durable_nonce,
+            blockhash_retry: self.blockhash_retry,
+            memo: self.memo,
+            deadline: self.deadline,
         }
     }
 }
 
 impl SellParams {
+    /// `min_sol_out` once checked against `expected_out`, the lamports the sell is quoted
+    /// before slippage
+    ///
+    /// `None` leaves the bound to `slippage_basis_points`. Fails when the quote is already below
+    /// the floor.
+    pub fn min_out_floor(&self, expected_out: u64) -> Result<Option<u64>, anyhow::Error> {
+        match self.min_sol_out {
+            Some(min_sol_out) => check_min_out(expected_out, min_sol_out, "lamports"),
+            None => Ok(None),
+        }
+    }
+
     /// Convert to SellWithTipParams
     /// Transforms basic sell parameters into MEV-enabled parameters
     pub fn with_tip(self, swqos_clients: Vec<Arc<SwqosClient>>) -> SellWithTipParams {
@@ -711,10 +380,145 @@ impl SellParams {
             creator: Pubkey::default(),
             token_amount: self.token_amount,
             slippage_basis_points: self.slippage_basis_points,
+            min_sol_out: self.min_sol_out,
             priority_fee: (*self.priority_fee).clone(),
-            lookup_table_key: self.lookup_table_key,
+            lookup_table_keys: self.lookup_table_keys,
             recent_blockhash: self.recent_blockhash,
+            data_size_limit: self.data_size_limit,
+            wait_level: self.wait_level,
             protocol_params: self.protocol_params,
+            spend_tracker: self.spend_tracker,
+            analysis_callback: self.analysis_callback,
+            clamp_to_balance: self.clamp_to_balance,
+            expected_min_balance: self.expected_min_balance,
+            commission: self.commission,
+            labels: self.labels,
+            success_policy: self.success_policy,
+            created_account_check: self.created_account_check,
+            wsol_account: self.wsol_account,
+            create_wsol_ata: self.create_wsol_ata,
+            close_wsol_ata: self.close_wsol_ata,
+            cu_sizing: self.cu_sizing,
+            swap_hooks: self.swap_hooks,
+            use_durable_nonce: self.use_durable_nonce,
+            blockhash_retry: self.blockhash_retry,
+            memo: self.memo,
+            deadline: self.deadline,
         }
     }
 }
+
+#[cfg(test)]
+impl BuyParams {
+    /// Buy params without an RPC client, for instruction builder tests
+    pub(crate) fn offline(
+        mint: Pubkey,
+        sol_amount: u64,
+        protocol_params: Box<dyn ProtocolParams>,
+    ) -> Self {
+        let options = super::options::BuyOptions::default();
+        let payer = Arc::new(solana_sdk::signature::Keypair::new());
+        BuyParams {
+            rpc: None,
+            payer: crate::common::signer::KeypairSigner::wrap(payer),
+            mint,
+            sol_amount,
+            amount_spec: BuyAmountSpec::ExactSolIn(sol_amount),
+            trade_type: TradeType::Buy,
+            slippage_basis_points: Some(100),
+            min_amount_out: None,
+            priority_fee: Arc::new(PriorityFee::default()),
+            lookup_table_keys: Vec::new(),
+            recent_blockhash: Hash::default(),
+            data_size_limit: options.data_size_limit,
+            wait_level: options.wait_level,
+            protocol_params,
+            open_seed_optimize: options.open_seed_optimize,
+            swqos_clients: Vec::new(),
+            middleware_manager: None,
+            create_wsol_ata: options.create_wsol_ata,
+            close_wsol_ata: options.close_wsol_ata,
+            create_mint_ata: options.create_mint_ata,
+            spend_tracker: None,
+            analysis_callback: None,
+            commission: None,
+            labels: None,
+            success_policy: options.success_policy,
+            created_account_check: CreatedAccountCheck::default(),
+            wsol_account: WsolAccountChoice::default(),
+            cu_sizing: CuSizing::default(),
+            swap_hooks: options.swap_hooks,
+            use_durable_nonce: options.use_durable_nonce,
+            blockhash_retry: options.blockhash_retry,
+            memo: None,
+            deadline: None,
+        }
+    }
+}
+
+#[cfg(test)]
+impl SellParams {
+    /// Sell params without an RPC client, for instruction builder tests
+    ///
+    /// Builders must not need the RPC, the amount reaches them already resolved.
+    pub(crate) fn offline(
+        mint: Pubkey,
+        token_amount: Option<u64>,
+        protocol_params: Box<dyn ProtocolParams>,
+    ) -> Self {
+        let options = super::options::SellOptions::default();
+        let payer = Arc::new(solana_sdk::signature::Keypair::new());
+        SellParams {
+            rpc: None,
+            payer: crate::common::signer::KeypairSigner::wrap(payer),
+            mint,
+            token_amount,
+            slippage_basis_points: Some(100),
+            min_sol_out: None,
+            priority_fee: Arc::new(PriorityFee::default()),
+            lookup_table_keys: Vec::new(),
+            recent_blockhash: Hash::default(),
+            data_size_limit: options.data_size_limit,
+            wait_level: options.wait_level,
+            with_tip: options.with_tip,
+            protocol_params,
+            open_seed_optimize: options.open_seed_optimize,
+            swqos_clients: Vec::new(),
+            middleware_manager: None,
+            create_wsol_ata: options.create_wsol_ata,
+            close_wsol_ata: options.close_wsol_ata,
+            spend_tracker: None,
+            analysis_callback: None,
+            clamp_to_balance: false,
+            expected_min_balance: None,
+            commission: None,
+            labels: None,
+            success_policy: options.success_policy,
+            created_account_check: CreatedAccountCheck::default(),
+            wsol_account: WsolAccountChoice::default(),
+            cu_sizing: CuSizing::default(),
+            swap_hooks: options.swap_hooks,
+            use_durable_nonce: options.use_durable_nonce,
+            blockhash_retry: options.blockhash_retry,
+            memo: None,
+            deadline: None,
+        }
+    }
+}
+
+/// The floor, unless the quote already misses it and the swap could only fail on chain
+fn check_min_out(
+    expected_out: u64,
+    min_out: u64,
+    unit: &str,
+) -> Result<Option<u64>, anyhow::Error> {
+    if expected_out < min_out {
+        return Err(anyhow::anyhow!(
+            "Quoted output of {} {} is below the minimum of {}",
+            expected_out,
+            unit,
+            min_out
+        ));
+    }
+    Ok(Some(min_out))
+}
@@ -0,0 +1,657 @@
+//! Capture and offline replay of submitted transactions
+//!
+//! With a capture handler installed, every transaction built for a swqos provider is recorded
+//! as a `TradeInputSnapshot` right before it is sent: the protocol instructions, fee profile,
+//! blockhash, cache state (nonce, lookup tables, token account rents), tip choice, middlewares
+//! and the signed transaction. `replay` rebuilds the message from a snapshot with the same
+//! builder code and reports every difference to the captured bytes.
+//!
+//! Signing is deterministic, so an identical message means an identical transaction and replay
+//! does not need the payer keypair.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use solana_hash::Hash;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    message::{AddressLookupTableAccount, VersionedMessage},
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+
+use crate::{
+    common::{seed, PriorityFee},
+    swqos::{SwqosType, TradeType},
+    trading::{
+        common::{compile_message, BuildEnvironment},
+        MiddlewareManager,
+    },
+};
+
+type CaptureHandler = Arc<dyn Fn(&TradeInputSnapshot) + Send + Sync>;
+
+lazy_static::lazy_static! {
+    static ref CAPTURE_HANDLER: RwLock<Option<CaptureHandler>> = RwLock::new(None);
+}
+
+/// Record every transaction submitted from now on with `handler`
+///
+/// The handler runs on the submission task before the transaction is sent, keep it short.
+pub fn set_capture_handler(handler: impl Fn(&TradeInputSnapshot) + Send + Sync + 'static) {
+    *CAPTURE_HANDLER.write() = Some(Arc::new(handler));
+}
+
+/// Stop recording submitted transactions
+pub fn clear_capture_handler() {
+    *CAPTURE_HANDLER.write() = None;
+}
+
+/// Write a snapshot of every submitted transaction to `dir` as `<signature>.json`
+pub fn capture_to_dir(dir: impl Into<PathBuf>) {
+    let dir = dir.into();
+    set_capture_handler(move |snapshot| {
+        let path = dir.join(format!("{}.json", snapshot.signature));
+        if let Err(e) = snapshot.save(&path) {
+            log::warn!(icon!("⚠️ ", "Failed to write trade snapshot {}: {}"), path.display(), e);
+        }
+    });
+}
+
+/// Pass the snapshot built by `snapshot` to the capture handler, if one is installed
+pub(crate) fn capture(snapshot: impl FnOnce() -> TradeInputSnapshot) {
+    let Some(handler) = CAPTURE_HANDLER.read().clone() else {
+        return;
+    };
+    handler(&snapshot());
+}
+
+/// Everything the SDK used to build one submitted transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeInputSnapshot {
+    /// Unix time of the capture in milliseconds
+    pub captured_at_ms: u64,
+    #[serde(with = "as_string")]
+    pub signature: Signature,
+    pub protocol_name: String,
+    /// `TradeType` and `SwqosType` the transaction was built for
+    pub trade_type: String,
+    pub swqos_type: String,
+    pub is_buy: bool,
+    #[serde(with = "as_string")]
+    pub payer: Pubkey,
+    /// Protocol instructions as returned by the instruction builder
+    pub business_instructions: Vec<InstructionSnapshot>,
+    pub priority_fee: PriorityFee,
+    pub data_size_limit: u32,
+    #[serde(with = "as_string")]
+    pub recent_blockhash: Hash,
+    pub nonce: Option<NonceSnapshot>,
+    pub lookup_tables: Vec<LookupTableSnapshot>,
+    /// Token account rents cached at capture time, used by seeded account creation
+    pub token_account_rent: Option<u64>,
+    pub token_2022_account_rent: Option<u64>,
+    pub with_tip: bool,
+    /// Tip account picked at random from the provider's list
+    #[serde(with = "as_string")]
+    pub tip_account: Pubkey,
+    pub tip_lamports: u64,
+    /// Names of the middlewares that processed the instructions, in order
+    pub middlewares: Vec<String>,
+    /// Base64 of the bincode-serialized signed transaction
+    pub transaction: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NonceSnapshot {
+    #[serde(with = "as_string")]
+    pub account: Pubkey,
+    #[serde(with = "as_string")]
+    pub nonce: Hash,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LookupTableSnapshot {
+    #[serde(with = "as_string")]
+    pub key: Pubkey,
+    #[serde(with = "as_strings")]
+    pub addresses: Vec<Pubkey>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstructionSnapshot {
+    #[serde(with = "as_string")]
+    pub program_id: Pubkey,
+    pub accounts: Vec<AccountMetaSnapshot>,
+    /// Base64 of the instruction data
+    pub data: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountMetaSnapshot {
+    #[serde(with = "as_string")]
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl From<&Instruction> for InstructionSnapshot {
+    fn from(instruction: &Instruction) -> Self {
+        Self {
+            program_id: instruction.program_id,
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|meta| AccountMetaSnapshot {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: STANDARD.encode(&instruction.data),
+        }
+    }
+}
+
+impl InstructionSnapshot {
+    pub fn to_instruction(&self) -> Result<Instruction> {
+        Ok(Instruction {
+            program_id: self.program_id,
+            accounts: self
+                .accounts
+                .iter()
+                .map(|meta| AccountMeta {
+                    pubkey: meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: STANDARD.decode(&self.data)?,
+        })
+    }
+}
+
+impl TradeInputSnapshot {
+    /// Snapshot of a transaction built by the parallel executor
+    pub(crate) fn from_build(
+        protocol_name: &str,
+        trade_type: TradeType,
+        swqos_type: &SwqosType,
+        business_instructions: &[Instruction],
+        priority_fee: &PriorityFee,
+        environment: &BuildEnvironment,
+        recent_blockhash: Hash,
+        data_size_limit: u32,
+        middleware_manager: Option<&MiddlewareManager>,
+        with_tip: bool,
+        tip_account: &Pubkey,
+        tip_lamports: u64,
+        transaction: &VersionedTransaction,
+    ) -> Self {
+        let captured_at_ms =
+            SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |since| since.as_millis());
+        Self {
+            captured_at_ms: captured_at_ms as u64,
+            signature: transaction.signatures.first().copied().unwrap_or_default(),
+            protocol_name: protocol_name.to_string(),
+            trade_type: format!("{:?}", trade_type),
+            swqos_type: format!("{:?}", swqos_type),
+            is_buy: trade_type != TradeType::Sell,
+            payer: transaction.message.static_account_keys()[0],
+            business_instructions: business_instructions.iter().map(Into::into).collect(),
+            priority_fee: priority_fee.clone(),
+            data_size_limit,
+            recent_blockhash,
+            nonce: environment.nonce.map(|(account, nonce)| NonceSnapshot { account, nonce }),
+            lookup_tables: environment
+                .lookup_table_accounts
+                .iter()
+                .map(|table| LookupTableSnapshot {
+                    key: table.key,
+                    addresses: table.addresses.clone(),
+                })
+                .collect(),
+            token_account_rent: seed::get_rent(false),
+            token_2022_account_rent: seed::get_rent(true),
+            with_tip,
+            tip_account: *tip_account,
+            tip_lamports,
+            middlewares: middleware_manager
+                .map(|manager| manager.middleware_names().into_iter().map(String::from).collect())
+                .unwrap_or_default(),
+            transaction: STANDARD.encode(bincode::serialize(transaction).unwrap_or_default()),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+
+    /// The transaction as it was submitted
+    pub fn captured_transaction(&self) -> Result<VersionedTransaction> {
+        Ok(bincode::deserialize(&STANDARD.decode(&self.transaction)?)?)
+    }
+
+    pub fn business_instructions(&self) -> Result<Vec<Instruction>> {
+        self.business_instructions.iter().map(InstructionSnapshot::to_instruction).collect()
+    }
+
+    /// Nonce and lookup table cache state at capture time
+    pub fn environment(&self) -> BuildEnvironment {
+        BuildEnvironment {
+            nonce: self.nonce.as_ref().map(|nonce| (nonce.account, nonce.nonce)),
+            lookup_table_accounts: self
+                .lookup_tables
+                .iter()
+                .map(|table| AddressLookupTableAccount {
+                    key: table.key,
+                    addresses: table.addresses.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Set the cached token account rents to the captured values
+    ///
+    /// Call before rebuilding the protocol instructions so seeded account creation uses the
+    /// rents of the original trade.
+    pub fn install_rents(&self) {
+        if let Some(rent) = self.token_account_rent {
+            seed::set_rent(false, rent);
+        }
+        if let Some(rent) = self.token_2022_account_rent {
+            seed::set_rent(true, rent);
+        }
+    }
+}
+
+/// Inputs that replace the captured ones during a replay
+///
+/// Everything not set is taken from the snapshot. Middlewares cannot be serialized, pass the
+/// manager the trade ran with to replay their changes.
+#[derive(Clone, Default)]
+pub struct ReplayOptions {
+    pub middleware_manager: Option<Arc<MiddlewareManager>>,
+    /// Protocol instructions rebuilt from the trade params instead of the captured ones
+    pub business_instructions: Option<Vec<Instruction>>,
+    /// Tip account instead of the captured random choice
+    pub tip_account: Option<Pubkey>,
+    /// Nonce and lookup table state instead of the captured cache state
+    pub environment: Option<BuildEnvironment>,
+}
+
+impl ReplayOptions {
+    pub fn with_middleware_manager(mut self, middleware_manager: Arc<MiddlewareManager>) -> Self {
+        self.middleware_manager = Some(middleware_manager);
+        self
+    }
+
+    pub fn with_business_instructions(mut self, instructions: Vec<Instruction>) -> Self {
+        self.business_instructions = Some(instructions);
+        self
+    }
+
+    pub fn with_tip_account(mut self, tip_account: Pubkey) -> Self {
+        self.tip_account = Some(tip_account);
+        self
+    }
+
+    pub fn with_environment(mut self, environment: BuildEnvironment) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+}
+
+/// Input of a replay that did not come from the snapshot and can explain differences
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Nondeterminism {
+    /// A different tip account than the captured random choice
+    TipAccount,
+    /// A different nonce than the one cached at capture time
+    NonceCache,
+    /// Lookup tables that differ from the ones cached at capture time
+    LookupTableCache,
+    /// Rebuilt protocol instructions while the cached rents differ from the captured ones
+    TokenAccountRent,
+    /// Middlewares that differ from the captured names
+    Middlewares,
+}
+
+/// One field that differs between the captured and the replayed message
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Difference {
+    /// What differs, e.g. `"blockhash"` or `"instruction 3 data"`
+    pub field: String,
+    pub captured: String,
+    pub replayed: String,
+}
+
+/// Result of a replay
+#[derive(Debug, Clone)]
+pub struct ReplayDiff {
+    /// The rebuilt message serializes to the captured bytes
+    pub identical: bool,
+    pub differences: Vec<Difference>,
+    pub nondeterminism: Vec<Nondeterminism>,
+    pub replayed: VersionedMessage,
+}
+
+impl fmt::Display for ReplayDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.identical {
+            return write!(f, "replayed message is identical");
+        }
+        write!(f, "{} differences", self.differences.len())?;
+        if !self.nondeterminism.is_empty() {
+            write!(f, ", replayed with {:?}", self.nondeterminism)?;
+        }
+        for difference in &self.differences {
+            write!(
+                f,
+                "\n  {}: captured {} | replayed {}",
+                difference.field, difference.captured, difference.replayed
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Rebuild the message of a captured transaction and diff it against the captured one
+pub fn replay(snapshot: &TradeInputSnapshot, options: &ReplayOptions) -> Result<ReplayDiff> {
+    let captured = snapshot.captured_transaction()?.message;
+    let captured_environment = snapshot.environment();
+    let environment = options.environment.clone().unwrap_or_else(|| captured_environment.clone());
+    let business_instructions = match &options.business_instructions {
+        Some(instructions) => instructions.clone(),
+        None => snapshot.business_instructions()?,
+    };
+    let tip_account = options.tip_account.unwrap_or(snapshot.tip_account);
+
+    let replayed = compile_message(
+        &snapshot.payer,
+        &snapshot.priority_fee,
+        &business_instructions,
+        &environment,
+        snapshot.recent_blockhash,
+        snapshot.data_size_limit,
+        options.middleware_manager.as_deref(),
+        &snapshot.protocol_name,
+        snapshot.is_buy,
+        snapshot.with_tip,
+        &tip_account,
+        snapshot.tip_lamports,
+    )?;
+
+    let mut nondeterminism = Vec::new();
+    if tip_account != snapshot.tip_account {
+        nondeterminism.push(Nondeterminism::TipAccount);
+    }
+    if environment.nonce != captured_environment.nonce {
+        nondeterminism.push(Nondeterminism::NonceCache);
+    }
+    if lookup_tables(&environment) != lookup_tables(&captured_environment) {
+        nondeterminism.push(Nondeterminism::LookupTableCache);
+    }
+    if options.business_instructions.is_some()
+        && (seed::get_rent(false) != snapshot.token_account_rent
+            || seed::get_rent(true) != snapshot.token_2022_account_rent)
+    {
+        nondeterminism.push(Nondeterminism::TokenAccountRent);
+    }
+    let middlewares = options
+        .middleware_manager
+        .as_ref()
+        .map(|manager| manager.middleware_names())
+        .unwrap_or_default();
+    if middlewares != snapshot.middlewares {
+        nondeterminism.push(Nondeterminism::Middlewares);
+    }
+
+    let differences = diff_messages(
+        &captured,
+        &captured_environment.lookup_table_accounts,
+        &replayed,
+        &environment.lookup_table_accounts,
+    )?;
+    Ok(ReplayDiff {
+        identical: captured.serialize() == replayed.serialize(),
+        differences,
+        nondeterminism,
+        replayed,
+    })
+}
+
+fn lookup_tables(environment: &BuildEnvironment) -> Vec<(Pubkey, &[Pubkey])> {
+    environment
+        .lookup_table_accounts
+        .iter()
+        .map(|table| (table.key, table.addresses.as_slice()))
+        .collect()
+}
+
+fn diff_messages(
+    captured: &VersionedMessage,
+    captured_tables: &[AddressLookupTableAccount],
+    replayed: &VersionedMessage,
+    replayed_tables: &[AddressLookupTableAccount],
+) -> Result<Vec<Difference>> {
+    let mut differences = Vec::new();
+    let mut differ = |field: String, captured: String, replayed: String| {
+        if captured != replayed {
+            differences.push(Difference { field, captured, replayed });
+        }
+    };
+
+    differ(
+        "blockhash".to_string(),
+        captured.recent_blockhash().to_string(),
+        replayed.recent_blockhash().to_string(),
+    );
+    differ(
+        "lookup tables".to_string(),
+        format!("{:?}", table_keys(captured)),
+        format!("{:?}", table_keys(replayed)),
+    );
+
+    let captured = resolve_instructions(captured, captured_tables)?;
+    let replayed = resolve_instructions(replayed, replayed_tables)?;
+    for index in 0..captured.len().max(replayed.len()) {
+        let (captured, replayed) = match (captured.get(index), replayed.get(index)) {
+            (Some(captured), Some(replayed)) => (captured, replayed),
+            (captured, replayed) => {
+                differ(
+                    format!("instruction {}", index),
+                    captured.map_or("missing".to_string(), describe_instruction),
+                    replayed.map_or("missing".to_string(), describe_instruction),
+                );
+                continue;
+            }
+        };
+        differ(
+            format!("instruction {} program", index),
+            captured.program_id.to_string(),
+            replayed.program_id.to_string(),
+        );
+        if captured.accounts.len() != replayed.accounts.len() {
+            differ(
+                format!("instruction {} accounts", index),
+                describe_accounts(&captured.accounts),
+                describe_accounts(&replayed.accounts),
+            );
+        } else {
+            for (position, (captured, replayed)) in
+                captured.accounts.iter().zip(&replayed.accounts).enumerate()
+            {
+                differ(
+                    format!("instruction {} account {}", index, position),
+                    describe_account(captured),
+                    describe_account(replayed),
+                );
+            }
+        }
+        differ(
+            format!("instruction {} data", index),
+            STANDARD.encode(&captured.data),
+            STANDARD.encode(&replayed.data),
+        );
+    }
+    Ok(differences)
+}
+
+fn table_keys(message: &VersionedMessage) -> Vec<Pubkey> {
+    message
+        .address_table_lookups()
+        .map(|lookups| lookups.iter().map(|lookup| lookup.account_key).collect())
+        .unwrap_or_default()
+}
+
+/// Instructions of a compiled message with account keys loaded from `tables`
+fn resolve_instructions(
+    message: &VersionedMessage,
+    tables: &[AddressLookupTableAccount],
+) -> Result<Vec<Instruction>> {
+    let static_keys = message.static_account_keys();
+    let header = message.header();
+    let signers = header.num_required_signatures as usize;
+    let writable_signers = signers - header.num_readonly_signed_accounts as usize;
+    let writable_unsigned = static_keys.len() - header.num_readonly_unsigned_accounts as usize;
+
+    let mut keys: Vec<(Pubkey, bool)> = static_keys
+        .iter()
+        .enumerate()
+        .map(|(index, key)| {
+            let writable =
+                if index < signers { index < writable_signers } else { index < writable_unsigned };
+            (*key, writable)
+        })
+        .collect();
+    let lookups = message.address_table_lookups().unwrap_or_default();
+    let mut readonly = Vec::new();
+    for lookup in lookups {
+        let table = tables
+            .iter()
+            .find(|table| table.key == lookup.account_key)
+            .ok_or_else(|| anyhow!("Lookup table {} is not in the snapshot", lookup.account_key))?;
+        let load = |index: &u8| {
+            table.addresses.get(*index as usize).copied().ok_or_else(|| {
+                anyhow!("Lookup table {} has no address {}", lookup.account_key, index)
+            })
+        };
+        for index in &lookup.writable_indexes {
+            keys.push((load(index)?, true));
+        }
+        for index in &lookup.readonly_indexes {
+            readonly.push((load(index)?, false));
+        }
+    }
+    keys.extend(readonly);
+
+    let key = |index: u8| {
+        keys.get(index as usize)
+            .copied()
+            .ok_or_else(|| anyhow!("Account index {} is out of range", index))
+    };
+    message
+        .instructions()
+        .iter()
+        .map(|instruction| {
+            let accounts = instruction
+                .accounts
+                .iter()
+                .map(|index| {
+                    let (pubkey, is_writable) = key(*index)?;
+                    Ok(AccountMeta { pubkey, is_signer: (*index as usize) < signers, is_writable })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Instruction {
+                program_id: key(instruction.program_id_index)?.0,
+                accounts,
+                data: instruction.data.clone(),
+            })
+        })
+        .collect()
+}
+
+fn describe_instruction(instruction: &Instruction) -> String {
+    format!(
+        "{} [{}] {}",
+        instruction.program_id,
+        describe_accounts(&instruction.accounts),
+        STANDARD.encode(&instruction.data)
+    )
+}
+
+fn describe_accounts(accounts: &[AccountMeta]) -> String {
+    accounts.iter().map(describe_account).collect::<Vec<_>>().join(", ")
+}
+
+fn describe_account(meta: &AccountMeta) -> String {
+    let signer = if meta.is_signer { "s" } else { "" };
+    let writable = if meta.is_writable { "w" } else { "" };
+    format!("{} {}{}", meta.pubkey, signer, writable)
+}
+
+mod as_string {
+    use std::{fmt::Display, str::FromStr};
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+    }
+}
+
+mod as_strings {
+    use std::{fmt::Display, str::FromStr};
+
+    use serde::{de::Error, ser::SerializeSeq, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Display, S: Serializer>(
+        values: &[T],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(values.len()))?;
+        for value in values {
+            seq.serialize_element(&value.to_string())?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|value| value.parse().map_err(D::Error::custom))
+            .collect()
+    }
+}
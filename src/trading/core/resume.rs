@@ -0,0 +1,122 @@
+use anyhow::Result;
+use solana_hash::Hash;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{TransactionConfirmationStatus, TransactionStatus};
+
+use super::{
+    executor::{analyze, retry_analysis, AnalysisKind},
+    trade_result::TradeResult,
+};
+use crate::{
+    common::SolanaRpcClient,
+    swqos::confirmation::{
+        ConfirmationService, TradeStages, WaitLevel, DEFAULT_CONFIRMATION_TIMEOUT,
+    },
+};
+
+/// Side and expected amounts of a trade whose signature is known
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResumeSide {
+    Buy { expected_sol_spent: f64 },
+    Sell { expected_tokens_sold: f64 },
+}
+
+impl From<ResumeSide> for AnalysisKind {
+    fn from(side: ResumeSide) -> Self {
+        match side {
+            ResumeSide::Buy { expected_sol_spent } => AnalysisKind::Buy { expected_sol_spent },
+            ResumeSide::Sell { expected_tokens_sold } => {
+                AnalysisKind::Sell { expected_tokens_sold }
+            }
+        }
+    }
+}
+
+/// State of a trade picked up again from its signature
+#[derive(Debug, Clone)]
+pub enum ResumedTrade {
+    /// Landed and succeeded, with the analyzed result
+    Landed(TradeResult),
+    /// Not confirmed yet and the blockhash may still be valid; call again later
+    Pending,
+    /// The blockhash expired without the transaction landing
+    Expired,
+    /// Landed but failed on-chain
+    Failed(String),
+}
+
+/// Re-attach confirmation and analysis to a signature submitted earlier
+///
+/// Reads nothing but chain state, so calling it again for the same signature is safe and
+/// returns the same outcome once the transaction settled. Without `recent_blockhash` a
+/// transaction that never landed can not be told apart from a pending one and is reported as
+/// `Pending`.
+pub async fn resume_tracking(
+    rpc: &SolanaRpcClient,
+    signature: Signature,
+    mint: Pubkey,
+    wallet: Pubkey,
+    side: ResumeSide,
+    recent_blockhash: Option<Hash>,
+) -> Result<ResumedTrade> {
+    let mut status = signature_status(rpc, &signature).await?;
+    if !is_confirmed(status.as_ref()) && status.as_ref().map_or(true, |s| s.err.is_none()) {
+        if blockhash_expired(rpc, recent_blockhash).await? {
+            // It may have landed right before the blockhash expired
+            status = signature_status(rpc, &signature).await?;
+        } else {
+            let _ = ConfirmationService::for_rpc(rpc)
+                .wait(signature, DEFAULT_CONFIRMATION_TIMEOUT)
+                .await;
+            status = signature_status(rpc, &signature).await?;
+        }
+    }
+
+    let Some(status) = status else {
+        return Ok(if blockhash_expired(rpc, recent_blockhash).await? {
+            ResumedTrade::Expired
+        } else {
+            ResumedTrade::Pending
+        });
+    };
+    if let Some(err) = status.err {
+        return Ok(ResumedTrade::Failed(err.to_string()));
+    }
+    if !is_confirmed(Some(&status)) {
+        return Ok(ResumedTrade::Pending);
+    }
+
+    let mut stages = TradeStages::default();
+    stages.mark(WaitLevel::Confirmed);
+    if status.confirmation_status == Some(TransactionConfirmationStatus::Finalized) {
+        stages.mark(WaitLevel::Finalized);
+    }
+    let kind = AnalysisKind::from(side);
+    let analysis = analyze(rpc, &signature, &mint, &wallet, kind).await;
+    let trade_result = retry_analysis(rpc, signature, mint, wallet, kind, analysis).await?;
+    Ok(ResumedTrade::Landed(trade_result.with_stages(stages)))
+}
+
+/// Status of a signature, searching the ledger history for older transactions
+async fn signature_status(
+    rpc: &SolanaRpcClient,
+    signature: &Signature,
+) -> Result<Option<TransactionStatus>> {
+    let response = rpc.get_signature_statuses_with_history(&[*signature]).await?;
+    Ok(response.value.into_iter().next().flatten())
+}
+
+fn is_confirmed(status: Option<&TransactionStatus>) -> bool {
+    status.is_some_and(|status| {
+        status.err.is_none() && status.satisfies_commitment(CommitmentConfig::confirmed())
+    })
+}
+
+async fn blockhash_expired(rpc: &SolanaRpcClient, recent_blockhash: Option<Hash>) -> Result<bool> {
+    match recent_blockhash {
+        Some(blockhash) => {
+            Ok(!rpc.is_blockhash_valid(&blockhash, CommitmentConfig::processed()).await?)
+        }
+        None => Ok(false),
+    }
+}
@@ -0,0 +1,141 @@
+//! Per-mint submission order of trades
+//!
+//! Every buy and sell takes the next sequence number of its mint once a backend accepted it,
+//! returned as `TradeResult::sequence` and on results delivered to the analysis callback.
+//! Results can arrive out of order (deferred analysis, background confirmation);
+//! `SequenceBuffer` puts them back in submission order before they are applied to a position.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use solana_sdk::pubkey::Pubkey;
+
+use super::trade_result::TradeResult;
+
+lazy_static::lazy_static! {
+    static ref SEQUENCES: Mutex<HashMap<Pubkey, u64>> = Mutex::new(HashMap::new());
+}
+
+/// Next submission sequence of `mint`, starting at 1
+pub fn next_sequence(mint: &Pubkey) -> u64 {
+    let mut sequences = SEQUENCES.lock();
+    let sequence = sequences.entry(*mint).or_insert(0);
+    *sequence += 1;
+    *sequence
+}
+
+/// Last submission sequence handed out for `mint`, 0 before its first submission
+pub fn last_sequence(mint: &Pubkey) -> u64 {
+    SEQUENCES.lock().get(mint).copied().unwrap_or(0)
+}
+
+/// Results of one mint released by a `SequenceBuffer`, in sequence order
+#[derive(Debug, Clone)]
+pub struct ReleasedResults {
+    pub token_mint: String,
+    pub results: Vec<TradeResult>,
+    /// A missing sequence was skipped after the timeout, or a result arrived after its gap was
+    /// skipped. A position built from these results should be re-derived from the chain.
+    pub needs_reconciliation: bool,
+}
+
+#[derive(Default)]
+struct MintQueue {
+    next: u64,
+    buffered: BTreeMap<u64, TradeResult>,
+    /// When the oldest buffered result started waiting for a gap
+    waiting_since: Option<Instant>,
+}
+
+impl MintQueue {
+    fn release_ready(&mut self) -> Vec<TradeResult> {
+        let mut ready = Vec::new();
+        while let Some(result) = self.buffered.remove(&self.next) {
+            ready.push(result);
+            self.next += 1;
+        }
+        ready
+    }
+}
+
+/// Reorders trade results per mint by `TradeResult::sequence`
+///
+/// Expects every sequence of a mint from 1 on, so create it before the first trade. A trade
+/// that fails before returning a result leaves a gap: results behind it are held until
+/// `timeout` passed and are then released by `flush_expired` with `needs_reconciliation` set.
+pub struct SequenceBuffer {
+    timeout: Duration,
+    mints: Mutex<HashMap<String, MintQueue>>,
+}
+
+impl SequenceBuffer {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout, mints: Mutex::new(HashMap::new()) }
+    }
+
+    /// Add a result and return the results of its mint that are now in order
+    ///
+    /// Results without a sequence pass through. Results flagged `analysis_pending` are dropped,
+    /// the analyzed result follows through the analysis callback with the same sequence.
+    pub fn push(&self, result: TradeResult) -> Option<ReleasedResults> {
+        if result.analysis_pending {
+            return None;
+        }
+        let token_mint = result.token_mint.clone();
+        let Some(sequence) = result.sequence else {
+            return Some(ReleasedResults {
+                token_mint,
+                results: vec![result],
+                needs_reconciliation: false,
+            });
+        };
+
+        let mut mints = self.mints.lock();
+        let queue = mints
+            .entry(token_mint.clone())
+            .or_insert_with(|| MintQueue { next: 1, ..Default::default() });
+        if sequence < queue.next {
+            return Some(ReleasedResults {
+                token_mint,
+                results: vec![result],
+                needs_reconciliation: true,
+            });
+        }
+        queue.buffered.insert(sequence, result);
+        let results = queue.release_ready();
+        if queue.buffered.is_empty() {
+            queue.waiting_since = None;
+        } else if queue.waiting_since.is_none() || !results.is_empty() {
+            queue.waiting_since = Some(Instant::now());
+        }
+        if results.is_empty() {
+            return None;
+        }
+        Some(ReleasedResults { token_mint, results, needs_reconciliation: false })
+    }
+
+    /// Release results that waited for a missing sequence longer than the timeout
+    pub fn flush_expired(&self) -> Vec<ReleasedResults> {
+        let mut released = Vec::new();
+        for (token_mint, queue) in self.mints.lock().iter_mut() {
+            if !queue.waiting_since.is_some_and(|since| since.elapsed() >= self.timeout) {
+                continue;
+            }
+            let buffered = std::mem::take(&mut queue.buffered);
+            queue.next = buffered.keys().next_back().map_or(queue.next, |last| last + 1);
+            queue.waiting_since = None;
+            released.push(ReleasedResults {
+                token_mint: token_mint.clone(),
+                results: buffered.into_values().collect(),
+                needs_reconciliation: true,
+            });
+        }
+        released
+    }
+
+    /// Number of results held back for `token_mint`
+    pub fn buffered(&self, token_mint: &str) -> usize {
+        self.mints.lock().get(token_mint).map_or(0, |queue| queue.buffered.len())
+    }
+}
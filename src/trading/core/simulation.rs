@@ -0,0 +1,255 @@
+//! Dry runs of trades through `simulateTransaction`
+//!
+//! A simulated trade is built by the same executor steps as a sent one: swap hooks,
+//! middlewares, commission, compute budget, lookup tables, the durable nonce when asked for and
+//! the tip of the first provider in submission order. Only the last step differs, the signed
+//! transaction goes to the RPC's `simulateTransaction`.
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use solana_hash::Hash;
+use solana_rpc_client_api::config::RpcSimulateTransactionConfig;
+use solana_sdk::{
+    instruction::Instruction,
+    message::VersionedMessage,
+    pubkey::Pubkey,
+    transaction::{TransactionError, VersionedTransaction},
+};
+
+use crate::{
+    common::{signer::TradeSigner, PriorityFee, SolanaRpcClient},
+    swqos::{SwqosClient, SwqosType},
+    trading::{
+        common::{sign_message, BuildEnvironment},
+        core::{
+            error::TradeError,
+            parallel::{provider_messages, submission_order},
+        },
+        MiddlewareManager,
+    },
+};
+
+/// Outcome of a simulated trade
+#[derive(Debug, Clone)]
+pub struct SimulationResult {
+    /// The signed transaction that would have been sent
+    pub transaction: VersionedTransaction,
+    /// Provider whose tip account and tip the transaction carries
+    pub swqos_type: SwqosType,
+    pub units_consumed: Option<u64>,
+    pub logs: Vec<String>,
+    /// Why the transaction would fail, `None` when it would succeed
+    pub err: Option<TransactionError>,
+}
+
+impl SimulationResult {
+    pub fn is_success(&self) -> bool {
+        self.err.is_none()
+    }
+
+    /// `TradeError::SimulationFailed` with the logs when the transaction would fail
+    pub fn check(&self) -> Result<(), TradeError> {
+        match &self.err {
+            Some(err) => {
+                Err(TradeError::SimulationFailed { err: err.to_string(), logs: self.logs.clone() })
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// Build the transaction the first eligible swqos client would send and simulate it
+pub(crate) async fn simulate_trade(
+    rpc: &SolanaRpcClient,
+    swqos_clients: &[Arc<SwqosClient>],
+    payer: Arc<dyn TradeSigner>,
+    instructions: Vec<Instruction>,
+    priority_fee: &PriorityFee,
+    lookup_table_keys: &[Pubkey],
+    recent_blockhash: Hash,
+    data_size_limit: u32,
+    middleware_manager: Option<Arc<MiddlewareManager>>,
+    protocol_name: &str,
+    is_buy: bool,
+    with_tip: bool,
+    use_durable_nonce: bool,
+    trade_lamports: u64,
+) -> Result<SimulationResult> {
+    let (swqos_type, transaction) = simulated_transaction(
+        swqos_clients,
+        payer.as_ref(),
+        &instructions,
+        priority_fee,
+        lookup_table_keys,
+        recent_blockhash,
+        data_size_limit,
+        middleware_manager.as_deref(),
+        protocol_name,
+        is_buy,
+        with_tip,
+        use_durable_nonce,
+        trade_lamports,
+    )
+    .await?;
+
+    let config = RpcSimulateTransactionConfig { sig_verify: true, ..Default::default() };
+    let result = rpc.simulate_transaction_with_config(&transaction, config).await?.value;
+    Ok(SimulationResult {
+        transaction,
+        swqos_type,
+        units_consumed: result.units_consumed,
+        logs: result.logs.unwrap_or_default(),
+        err: result.err,
+    })
+}
+
+/// The signed transaction the parallel execution would send through its first provider
+///
+/// Signs the message of `first_provider_message`.
+pub(crate) async fn simulated_transaction(
+    swqos_clients: &[Arc<SwqosClient>],
+    payer: &dyn TradeSigner,
+    instructions: &[Instruction],
+    priority_fee: &PriorityFee,
+    lookup_table_keys: &[Pubkey],
+    recent_blockhash: Hash,
+    data_size_limit: u32,
+    middleware_manager: Option<&MiddlewareManager>,
+    protocol_name: &str,
+    is_buy: bool,
+    with_tip: bool,
+    use_durable_nonce: bool,
+    trade_lamports: u64,
+) -> Result<(SwqosType, VersionedTransaction)> {
+    let (swqos_type, message) = first_provider_message(
+        swqos_clients,
+        &payer.pubkey(),
+        instructions,
+        priority_fee,
+        lookup_table_keys,
+        recent_blockhash,
+        data_size_limit,
+        middleware_manager,
+        protocol_name,
+        is_buy,
+        with_tip,
+        use_durable_nonce,
+        trade_lamports,
+    )?;
+    let transaction = sign_message(payer, &[], message).await?;
+    Ok((swqos_type, transaction))
+}
+
+/// The message the parallel execution would send through its first provider
+///
+/// Providers are ordered, tipped and filtered by `parallel::submission_order` and
+/// `parallel::provider_messages`, and the nonce is only used with `use_durable_nonce`, exactly
+/// as for a sent trade.
+pub(crate) fn first_provider_message(
+    swqos_clients: &[Arc<SwqosClient>],
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    priority_fee: &PriorityFee,
+    lookup_table_keys: &[Pubkey],
+    recent_blockhash: Hash,
+    data_size_limit: u32,
+    middleware_manager: Option<&MiddlewareManager>,
+    protocol_name: &str,
+    is_buy: bool,
+    with_tip: bool,
+    use_durable_nonce: bool,
+    trade_lamports: u64,
+) -> Result<(SwqosType, VersionedMessage)> {
+    let swqos_clients =
+        submission_order(swqos_clients, priority_fee, is_buy, with_tip, trade_lamports)?;
+    let environment = BuildEnvironment::for_trade(lookup_table_keys, use_durable_nonce)?;
+    let first = provider_messages(
+        &swqos_clients,
+        payer,
+        priority_fee,
+        instructions,
+        &environment,
+        recent_blockhash,
+        data_size_limit,
+        middleware_manager,
+        protocol_name,
+        is_buy,
+        with_tip,
+    )
+    .next()
+    .ok_or_else(|| anyhow!("No swqos client would send the trade"))?;
+    Ok((swqos_clients[first.index].0.get_swqos_type(), first.message?))
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{signature::Keypair, signer::Signer};
+    use solana_system_interface::instruction::transfer;
+
+    use super::*;
+    use crate::{
+        common::{signer::KeypairSigner, TipAmount},
+        swqos::testing::StubClient,
+    };
+
+    #[tokio::test]
+    async fn simulates_the_message_the_first_provider_is_sent() {
+        let keypair = Arc::new(Keypair::new());
+        let payer = KeypairSigner::wrap(keypair.clone());
+        let swqos_clients: Vec<Arc<SwqosClient>> = vec![
+            Arc::new(StubClient::new(SwqosType::Node1).with_tip_account(Pubkey::new_unique())),
+            Arc::new(StubClient::new(SwqosType::BlockRazor).with_tip_account(Pubkey::new_unique())),
+        ];
+        let priority_fee = PriorityFee {
+            buy_tips: vec![TipAmount::Lamports(100_000), TipAmount::Lamports(200_000)],
+            ..Default::default()
+        };
+        let instructions = vec![transfer(&keypair.pubkey(), &Pubkey::new_unique(), 1_000_000)];
+        let recent_blockhash = Hash::new_unique();
+
+        let (swqos_type, simulated) = simulated_transaction(
+            &swqos_clients,
+            payer.as_ref(),
+            &instructions,
+            &priority_fee,
+            &[],
+            recent_blockhash,
+            256 * 1024,
+            None,
+            "PumpFun",
+            true,
+            true,
+            false,
+            1_000_000,
+        )
+        .await
+        .unwrap();
+
+        // What `parallel_execute` compiles for the same trade
+        let ordered =
+            submission_order(&swqos_clients, &priority_fee, true, true, 1_000_000).unwrap();
+        let environment = BuildEnvironment::for_trade(&[], false).unwrap();
+        let sent: Vec<_> = provider_messages(
+            &ordered,
+            &keypair.pubkey(),
+            &priority_fee,
+            &instructions,
+            &environment,
+            recent_blockhash,
+            256 * 1024,
+            None,
+            "PumpFun",
+            true,
+            true,
+        )
+        .collect();
+        let first = &sent[0];
+
+        assert_eq!(sent.len(), 2);
+        assert_eq!(swqos_type, ordered[first.index].0.get_swqos_type());
+        assert_eq!(simulated.message.serialize(), first.message.as_ref().unwrap().serialize(),);
+        assert_eq!(simulated.message.recent_blockhash(), &recent_blockhash);
+        assert!(simulated.verify_with_results().iter().all(|verified| *verified));
+    }
+}
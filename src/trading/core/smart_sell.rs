@@ -0,0 +1,271 @@
+use solana_sdk::pubkey::Pubkey;
+
+use super::trade_result::TradeResult;
+use crate::{
+    instruction::utils::{
+        pumpfun::global_constants::FEE_BASIS_POINTS,
+        pumpswap::accounts::{LP_FEE_BASIS_POINTS, PROTOCOL_FEE_BASIS_POINTS},
+    },
+    params::{PumpFunParams, PumpSwapParams},
+    trading::factory::DexType,
+    utils::calc::{
+        common::compute_fee, pumpfun::creator_fee_basis_points,
+        pumpswap::coin_creator_fee_basis_points,
+    },
+};
+
+/// Constant product market a sell can be routed to, as seen by `optimal_split`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SellVenue {
+    /// Token reserve the output is computed on, virtual for a bonding curve
+    pub token_reserve: u128,
+    /// SOL reserve the output is computed on, virtual for a bonding curve
+    pub sol_reserve: u128,
+    /// Fees taken from the SOL output
+    pub fee_basis_points: u64,
+    /// SOL the venue can pay out before fees, the real reserves of a bonding curve
+    pub max_sol_out: Option<u128>,
+}
+
+impl SellVenue {
+    /// Active PumpFun bonding curve, `None` once it completed
+    pub fn from_bonding_curve(params: &PumpFunParams) -> Option<Self> {
+        let curve = &params.bonding_curve;
+        if curve.complete || curve.virtual_token_reserves == 0 {
+            return None;
+        }
+        Some(Self {
+            token_reserve: curve.virtual_token_reserves as u128,
+            sol_reserve: curve.virtual_sol_reserves as u128,
+            fee_basis_points: FEE_BASIS_POINTS + creator_fee_basis_points(&curve.creator),
+            max_sol_out: Some(curve.real_sol_reserves as u128),
+        })
+    }
+
+    /// PumpSwap pool of `mint` quoted in WSOL
+    pub fn from_pumpswap_pool(params: &PumpSwapParams, mint: &Pubkey) -> Option<Self> {
+        if params.base_mint != *mint
+            || params.quote_mint != crate::constants::WSOL_TOKEN_ACCOUNT
+            || params.pool_base_token_reserves == 0
+        {
+            return None;
+        }
+        Some(Self {
+            token_reserve: params.pool_base_token_reserves as u128,
+            sol_reserve: params.pool_quote_token_reserves as u128,
+            fee_basis_points: LP_FEE_BASIS_POINTS
+                + PROTOCOL_FEE_BASIS_POINTS
+                + coin_creator_fee_basis_points(&params.creator),
+            max_sol_out: None,
+        })
+    }
+
+    /// Lamports received for `token_amount` after fees, `None` above `max_token_amount`
+    pub fn output(&self, token_amount: u64) -> Option<u64> {
+        if token_amount == 0 {
+            return Some(0);
+        }
+        let token_amount = token_amount as u128;
+        let gross = self.sol_reserve * token_amount / (self.token_reserve + token_amount);
+        if self.max_sol_out.is_some_and(|max_sol_out| gross > max_sol_out) {
+            return None;
+        }
+        Some(gross.saturating_sub(compute_fee(gross, self.fee_basis_points as u128)) as u64)
+    }
+
+    /// Largest sell the venue can fill
+    pub fn max_token_amount(&self) -> u64 {
+        match self.max_sol_out {
+            Some(max_sol_out) if max_sol_out < self.sol_reserve => {
+                // sol * x / (token + x) <= max  <=>  x <= max * token / (sol - max)
+                (max_sol_out * self.token_reserve / (self.sol_reserve - max_sol_out))
+                    .min(u64::MAX as u128) as u64
+            }
+            Some(_) | None => u64::MAX,
+        }
+    }
+}
+
+/// Tokens of `amount` to sell into `curve`, the rest goes to `pool`, maximizing the SOL out
+///
+/// Both outputs are concave, so the optimum is where their marginal outputs are equal:
+/// `a / (Tc + x)^2 = b / (Tp + A - x)^2` with `a = (1 - fc) Sc Tc` and `b = (1 - fp) Sp Tp`,
+/// which gives `x = (sqrt(a) (Tp + A) - sqrt(b) Tc) / (sqrt(a) + sqrt(b))`. The result is
+/// clamped to what the curve can fill and settled on the better neighbouring integer.
+pub fn optimal_split(curve: &SellVenue, pool: &SellVenue, amount: u64) -> u64 {
+    let weight = |venue: &SellVenue| {
+        let fee_factor = 1.0 - venue.fee_basis_points as f64 / 10_000.0;
+        (fee_factor * venue.sol_reserve as f64 * venue.token_reserve as f64).sqrt()
+    };
+    let (a, b) = (weight(curve), weight(pool));
+    let upper = amount.min(curve.max_token_amount());
+    if a + b == 0.0 {
+        return 0;
+    }
+    let x = (a * (pool.token_reserve as f64 + amount as f64) - b * curve.token_reserve as f64)
+        / (a + b);
+    let x = x.clamp(0.0, upper as f64);
+
+    let total = |to_curve: u64| {
+        let curve_out = curve.output(to_curve)?;
+        let pool_out = pool.output(amount - to_curve)?;
+        Some(curve_out as u128 + pool_out as u128)
+    };
+    [x.floor() as u64, x.ceil() as u64, 0, upper]
+        .into_iter()
+        .filter(|to_curve| *to_curve <= upper)
+        .filter_map(|to_curve| total(to_curve).map(|sol| (to_curve, sol)))
+        .max_by_key(|(_, sol)| *sol)
+        .map(|(to_curve, _)| to_curve)
+        .unwrap_or(0)
+}
+
+/// One venue's share of a `sell_smart`
+#[derive(Debug, Clone)]
+pub struct SmartSellLeg {
+    pub dex_type: DexType,
+    pub token_amount: u64,
+    /// Lamports the split was sized to receive after fees, before slippage
+    pub expected_lamports: u64,
+    /// Set when the leg filled
+    pub trade_result: Option<TradeResult>,
+    /// Why the leg failed
+    pub error: Option<String>,
+}
+
+/// Outcome of a `sell_smart`
+#[derive(Debug, Clone)]
+pub struct SmartSellReport {
+    pub mint: Pubkey,
+    /// One leg per venue sold into, the bonding curve first
+    pub legs: Vec<SmartSellLeg>,
+}
+
+impl SmartSellReport {
+    pub fn is_filled(&self) -> bool {
+        self.legs.iter().all(|leg| leg.trade_result.is_some())
+    }
+
+    pub fn failed_legs(&self) -> Vec<&SmartSellLeg> {
+        self.legs.iter().filter(|leg| leg.trade_result.is_none()).collect()
+    }
+
+    /// Tokens sold by the filled legs
+    pub fn tokens_sold(&self) -> u64 {
+        self.legs.iter().filter(|leg| leg.trade_result.is_some()).map(|leg| leg.token_amount).sum()
+    }
+
+    /// SOL received by the filled legs, in SOL
+    pub fn sol_received(&self) -> f64 {
+        self.legs
+            .iter()
+            .filter_map(|leg| leg.trade_result.as_ref())
+            .map(|trade_result| -trade_result.sol_spent)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// SOL out of selling `to_curve` into `curve` and the rest of `amount` into `pool`
+    fn total(curve: &SellVenue, pool: &SellVenue, amount: u64, to_curve: u64) -> Option<u64> {
+        Some(curve.output(to_curve)? + pool.output(amount - to_curve)?)
+    }
+
+    /// Best total over `steps + 1` evenly spaced splits, every split when `steps` covers them all
+    fn grid_best(curve: &SellVenue, pool: &SellVenue, amount: u64, steps: u64) -> u64 {
+        let upper = amount.min(curve.max_token_amount());
+        let steps = steps.min(upper);
+        (0..=steps)
+            .map(|step| (upper as u128 * step as u128 / steps.max(1) as u128) as u64)
+            .filter_map(|to_curve| total(curve, pool, amount, to_curve))
+            .max()
+            .unwrap()
+    }
+
+    /// Bonding curve shaped like PumpFun's, `token_scale` and `sol_scale` set the units
+    fn curve(rng: &mut StdRng, token_scale: u128, sol_scale: u128) -> SellVenue {
+        let sol_reserve = rng.random_range(30..=115) * sol_scale;
+        SellVenue {
+            token_reserve: rng.random_range(300..=1_073) * token_scale,
+            sol_reserve,
+            fee_basis_points: FEE_BASIS_POINTS + rng.random_range(0..=5),
+            max_sol_out: Some(rng.random_range(0..=sol_reserve - 30 * sol_scale)),
+        }
+    }
+
+    fn pool(rng: &mut StdRng, token_scale: u128, sol_scale: u128) -> SellVenue {
+        SellVenue {
+            token_reserve: rng.random_range(10..=1_000) * token_scale,
+            sol_reserve: rng.random_range(1..=1_000) * sol_scale,
+            fee_basis_points: LP_FEE_BASIS_POINTS + PROTOCOL_FEE_BASIS_POINTS,
+            max_sol_out: None,
+        }
+    }
+
+    #[test]
+    fn split_matches_exhaustive_search_on_small_reserves() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..300 {
+            let (curve, pool) =
+                (curve(&mut rng, 1_000_000, 10_000), pool(&mut rng, 1_000_000, 10_000));
+            let amount = rng.random_range(1..=50_000);
+            let to_curve = optimal_split(&curve, &pool, amount);
+            assert!(to_curve <= amount.min(curve.max_token_amount()));
+            assert_eq!(
+                total(&curve, &pool, amount, to_curve).unwrap(),
+                grid_best(&curve, &pool, amount, u64::MAX),
+                "{:?} {:?} selling {}",
+                curve,
+                pool,
+                amount
+            );
+        }
+    }
+
+    #[test]
+    fn split_beats_grid_search_on_mainnet_sized_reserves() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..200 {
+            let curve = curve(&mut rng, 1_000_000_000_000, 1_000_000_000);
+            let pool = pool(&mut rng, 1_000_000_000_000, 1_000_000_000);
+            let amount = rng.random_range(1_000_000_000..=100_000_000_000_000);
+            let to_curve = optimal_split(&curve, &pool, amount);
+            assert!(to_curve <= amount.min(curve.max_token_amount()));
+            assert!(
+                total(&curve, &pool, amount, to_curve).unwrap()
+                    >= grid_best(&curve, &pool, amount, 5_000),
+                "{:?} {:?} selling {}",
+                curve,
+                pool,
+                amount
+            );
+        }
+    }
+
+    #[test]
+    fn drained_curve_sends_everything_to_the_pool() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let curve =
+            SellVenue { max_sol_out: Some(0), ..curve(&mut rng, 1_000_000_000_000, 1_000_000_000) };
+        let pool = pool(&mut rng, 1_000_000_000_000, 1_000_000_000);
+        assert_eq!(curve.max_token_amount(), 0);
+        assert_eq!(optimal_split(&curve, &pool, 1_000_000_000_000), 0);
+    }
+
+    #[test]
+    fn curve_output_stops_at_its_real_reserves() {
+        let curve = SellVenue {
+            token_reserve: 1_000_000,
+            sol_reserve: 1_000,
+            fee_basis_points: 0,
+            max_sol_out: Some(500),
+        };
+        assert_eq!(curve.max_token_amount(), 1_000_000);
+        assert_eq!(curve.output(1_000_000), Some(500));
+        assert_eq!(curve.output(1_000_100), None);
+    }
+}
@@ -0,0 +1,172 @@
+use super::trade_result::TradeResult;
+use anyhow::{anyhow, Result};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+use std::sync::Arc;
+
+/// Splits one buy across several wallets
+///
+/// Every payer buys its weighted share of the SOL amount in its own transaction, with its own
+/// ATAs and its own tip. Legs are submitted concurrently.
+#[derive(Clone)]
+pub struct SplitAcrossPayers {
+    pub payers: Vec<Arc<Keypair>>,
+    pub weights: Vec<u8>,
+    /// Transfer the bought tokens of every filled leg to this wallet afterwards
+    pub consolidate_to: Option<Pubkey>,
+}
+
+impl SplitAcrossPayers {
+    pub fn new(payers: Vec<Arc<Keypair>>, weights: Vec<u8>) -> Result<Self> {
+        if payers.is_empty() {
+            return Err(anyhow!("At least one payer is required"));
+        }
+        if payers.len() != weights.len() {
+            return Err(anyhow!(
+                "Payers and weights length mismatch: {} payers, {} weights",
+                payers.len(),
+                weights.len()
+            ));
+        }
+        if weights.iter().any(|weight| *weight == 0) {
+            return Err(anyhow!("Weights must be greater than zero"));
+        }
+        Ok(Self { payers, weights, consolidate_to: None })
+    }
+
+    /// Consolidate the purchased tokens into `primary` once all legs settled
+    pub fn with_consolidation(mut self, primary: Pubkey) -> Self {
+        self.consolidate_to = Some(primary);
+        self
+    }
+
+    /// Lamports each payer spends, in payer order; the last leg absorbs rounding
+    pub fn leg_amounts(&self, sol_amount: u64) -> Vec<u64> {
+        let total_weight: u128 = self.weights.iter().map(|weight| *weight as u128).sum();
+        let mut amounts: Vec<u64> = self
+            .weights
+            .iter()
+            .map(|weight| (sol_amount as u128 * *weight as u128 / total_weight) as u64)
+            .collect();
+        let assigned: u64 = amounts.iter().sum();
+        if let Some(last) = amounts.last_mut() {
+            *last += sol_amount - assigned;
+        }
+        amounts
+    }
+}
+
+/// Shorthand for `SplitAcrossPayers::new`
+pub fn split_across_payers(
+    payers: Vec<Arc<Keypair>>,
+    weights: Vec<u8>,
+) -> Result<SplitAcrossPayers> {
+    SplitAcrossPayers::new(payers, weights)
+}
+
+/// Outcome of one wallet's share of a split buy
+#[derive(Debug, Clone)]
+pub struct SplitBuyLeg {
+    pub payer: Pubkey,
+    pub weight: u8,
+    /// Lamports this leg was sized to spend
+    pub sol_amount: u64,
+    /// Set when the leg filled
+    pub trade_result: Option<TradeResult>,
+    /// Why the leg was skipped or failed
+    pub error: Option<String>,
+    /// Token amount moved to the primary wallet
+    pub consolidated_amount: Option<u64>,
+    /// Why consolidating this leg failed
+    pub consolidation_error: Option<String>,
+}
+
+impl SplitBuyLeg {
+    pub(crate) fn new(payer: &Keypair, weight: u8, sol_amount: u64) -> Self {
+        Self {
+            payer: payer.pubkey(),
+            weight,
+            sol_amount,
+            trade_result: None,
+            error: None,
+            consolidated_amount: None,
+            consolidation_error: None,
+        }
+    }
+
+    pub fn is_filled(&self) -> bool {
+        self.trade_result.is_some()
+    }
+}
+
+/// Aggregated result of a split buy
+#[derive(Debug, Clone)]
+pub struct SplitBuyReport {
+    pub mint: Pubkey,
+    pub legs: Vec<SplitBuyLeg>,
+}
+
+impl SplitBuyReport {
+    pub fn filled_legs(&self) -> impl Iterator<Item = &SplitBuyLeg> {
+        self.legs.iter().filter(|leg| leg.is_filled())
+    }
+
+    pub fn failed_legs(&self) -> impl Iterator<Item = &SplitBuyLeg> {
+        self.legs.iter().filter(|leg| !leg.is_filled())
+    }
+
+    /// True when every leg filled
+    pub fn is_complete(&self) -> bool {
+        self.legs.iter().all(|leg| leg.is_filled())
+    }
+
+    /// Total SOL spent by filled legs, including fees (in SOL)
+    pub fn total_sol_spent(&self) -> f64 {
+        self.filled_legs().filter_map(|leg| leg.trade_result.as_ref()).map(|r| r.sol_spent).sum()
+    }
+
+    /// Total tokens received by filled legs (in UI units)
+    pub fn total_tokens_received(&self) -> f64 {
+        self.filled_legs()
+            .filter_map(|leg| leg.trade_result.as_ref())
+            .map(|r| r.tokens_received)
+            .sum()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(weights: Vec<u8>) -> SplitAcrossPayers {
+        let payers = weights.iter().map(|_| Arc::new(Keypair::new())).collect();
+        SplitAcrossPayers::new(payers, weights).unwrap()
+    }
+
+    #[test]
+    fn uneven_weights_leave_the_remainder_to_the_last_leg() {
+        // 1_000_000 / 7 = 142_857.14..., the three truncated lamports go to the last leg
+        assert_eq!(split(vec![1, 2, 4]).leg_amounts(1_000_000), [142_857, 285_714, 571_429]);
+        assert_eq!(split(vec![4, 2, 1]).leg_amounts(1_000_000), [571_428, 285_714, 142_858]);
+    }
+
+    #[test]
+    fn amount_below_the_total_weight_goes_to_the_last_leg() {
+        assert_eq!(split(vec![3, 3, 1]).leg_amounts(2), [0, 0, 2]);
+        assert_eq!(split(vec![3, 3, 1]).leg_amounts(5), [2, 2, 1]);
+        assert_eq!(split(vec![3, 3, 1]).leg_amounts(0), [0, 0, 0]);
+    }
+
+    #[test]
+    fn single_payer_takes_the_whole_amount() {
+        assert_eq!(split(vec![7]).leg_amounts(12_345), [12_345]);
+        assert_eq!(split(vec![1]).leg_amounts(u64::MAX), [u64::MAX]);
+    }
+
+    #[test]
+    fn payers_and_weights_must_line_up() {
+        let payers = || vec![Arc::new(Keypair::new()), Arc::new(Keypair::new())];
+        assert!(SplitAcrossPayers::new(Vec::new(), Vec::new()).is_err());
+        assert!(SplitAcrossPayers::new(payers(), vec![1]).is_err());
+        assert!(SplitAcrossPayers::new(payers(), vec![1, 0]).is_err());
+        assert!(SplitAcrossPayers::new(payers(), vec![1, 1]).is_ok());
+    }
+}
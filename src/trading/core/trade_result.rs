@@ -1,14 +1,27 @@
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
-use solana_client::rpc_config::RpcTransactionConfig;
+use solana_client::{client_error::ClientError, rpc_config::RpcTransactionConfig};
 use solana_sdk::{
     commitment_config::CommitmentConfig,
     pubkey::Pubkey,
     signature::Signature,
 };
-use solana_transaction_status::UiTransactionEncoding;
-use std::time::Instant;
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta,
+    EncodedTransaction, UiInstruction, UiMessage, UiParsedInstruction, UiTransactionEncoding,
+};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::common::labels::TradeLabels;
+use crate::common::mint_cache::MintDecimalsCache;
 use crate::common::SolanaRpcClient;
+use crate::swqos::confirmation::TradeStages;
+use crate::constants::decimals::DEFAULT_TOKEN_DECIMALS;
+use crate::constants::swqos::is_tip_account;
+use crate::constants::WSOL_TOKEN_ACCOUNT;
+use crate::constants::trade::trade::{ANALYSIS_NOT_FOUND_RETRIES, ANALYSIS_NOT_FOUND_RETRY_DELAY_MS};
 use spl_token::state::Mint;
 use solana_program::program_pack::Pack;
 
@@ -46,16 +59,292 @@ pub struct TradeResult {
     /// Post-trade token balance (remaining tokens after the transaction) - CRITICAL for account cleanup
     /// This is the actual balance left in the account after the sell, used to determine if cleanup is needed
     pub post_token_balance: Option<f64>,
+    /// The transaction landed but could not be analyzed yet, trade amounts are not filled in.
+    /// The analysis is retried in the background and delivered through the analysis callback
+    #[serde(default)]
+    pub analysis_pending: bool,
+    /// Stages the transaction reached with their timestamps
+    #[serde(default)]
+    pub stages: TradeStages,
+    /// Commission charged in the same transaction, in lamports, already part of `sol_spent`
+    #[serde(default)]
+    pub commission_lamports: Option<u64>,
+    /// Labels the trade was sent with
+    #[serde(default)]
+    pub labels: Option<TradeLabels>,
+    /// Per-mint submission order, assigned when a backend accepted the transaction
+    #[serde(default)]
+    pub sequence: Option<u64>,
+    /// Unix time of the block the transaction landed in, in seconds
+    #[serde(default)]
+    pub block_time: Option<i64>,
+    /// Exact amounts from the transaction balances, `None` when the trade was not analyzed
+    #[serde(default)]
+    pub raw: Option<RawTradeAmounts>,
+    /// Protocol the trade was built for, e.g. `PumpFun`
+    #[serde(default)]
+    pub protocol: Option<String>,
+}
+
+/// Integer amounts of a landed transaction, read from the balances in its metadata
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawTradeAmounts {
+    /// Change of the wallet's SOL: its lamports plus the wSOL held in its token accounts
+    pub sol_delta_lamports: i64,
+    /// Change of the wallet's balance of the traded mint, in base units
+    pub token_delta: i64,
+    /// Network fee, base and priority fee
+    pub fee_lamports: u64,
+    /// Lamports the wallet transferred to swqos tip accounts
+    pub tip_lamports: u64,
+    /// Rent locked in token accounts the transaction created for the wallet, minus the rent
+    /// refunded by the ones it closed
+    pub rent_lamports: i64,
+}
+
+impl RawTradeAmounts {
+    /// Read the amounts of `wallet` and `mint` from a transaction fetched as `JsonParsed`
+    ///
+    /// `None` when the wallet is not among the transaction's accounts.
+    pub fn from_transaction(
+        transaction: &EncodedConfirmedTransactionWithStatusMeta,
+        wallet: &Pubkey,
+        mint: &Pubkey,
+    ) -> Option<Self> {
+        let meta = transaction.transaction.meta.as_ref()?;
+        let EncodedTransaction::Json(ui_transaction) = &transaction.transaction.transaction else {
+            return None;
+        };
+        let UiMessage::Parsed(message) = &ui_transaction.message else {
+            return None;
+        };
+        let wallet_str = wallet.to_string();
+        let wallet_index = message.account_keys.iter().position(|key| key.pubkey == wallet_str)?;
+        let lamports_delta = *meta.post_balances.get(wallet_index)? as i64
+            - *meta.pre_balances.get(wallet_index)? as i64;
+
+        let token_deltas = token_deltas(transaction, wallet);
+        let wsol_delta = token_deltas.get(&WSOL_TOKEN_ACCOUNT.to_string()).copied().unwrap_or(0);
+        let sol_delta_lamports =
+            if *mint == WSOL_TOKEN_ACCOUNT { lamports_delta } else { lamports_delta + wsol_delta };
+
+        let mut tip_lamports = 0;
+        for instruction in &message.instructions {
+            let UiInstruction::Parsed(UiParsedInstruction::Parsed(instruction)) = instruction
+            else {
+                continue;
+            };
+            if instruction.program != "system" || instruction.parsed["type"] != "transfer" {
+                continue;
+            }
+            let info = &instruction.parsed["info"];
+            let destination =
+                info["destination"].as_str().and_then(|key| Pubkey::from_str(key).ok());
+            if info["source"] == wallet_str.as_str()
+                && destination.is_some_and(|key| is_tip_account(&key))
+            {
+                tip_lamports += info["lamports"].as_u64().unwrap_or(0);
+            }
+        }
+
+        // Token accounts of the wallet that only exist before or after the transaction
+        let pre = wallet_token_accounts(&meta.pre_token_balances, &wallet_str);
+        let post = wallet_token_accounts(&meta.post_token_balances, &wallet_str);
+        let mut rent_lamports = 0i64;
+        for (index, (account_mint, amount)) in &post {
+            if !pre.contains_key(index) {
+                let lamports = *meta.post_balances.get(*index as usize)? as i64;
+                rent_lamports += lamports - native_amount(account_mint, *amount);
+            }
+        }
+        for (index, (account_mint, amount)) in &pre {
+            if !post.contains_key(index) {
+                let lamports = *meta.pre_balances.get(*index as usize)? as i64;
+                rent_lamports -= lamports - native_amount(account_mint, *amount);
+            }
+        }
+
+        Some(Self {
+            sol_delta_lamports,
+            token_delta: token_deltas.get(&mint.to_string()).copied().unwrap_or(0),
+            fee_lamports: meta.fee,
+            tip_lamports,
+            rent_lamports,
+        })
+    }
+
+    /// SOL that went into (negative) or came out of the swap itself
+    ///
+    /// The wallet change without the network fee, tip, rent and `commission_lamports`.
+    pub fn swap_lamports(&self, commission_lamports: u64) -> i64 {
+        self.sol_delta_lamports
+            + self.fee_lamports as i64
+            + self.tip_lamports as i64
+            + self.rent_lamports
+            + commission_lamports as i64
+    }
+}
+
+/// Change of every mint the wallet holds in the transaction, in base units
+pub fn token_deltas(
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    wallet: &Pubkey,
+) -> HashMap<String, i64> {
+    let mut deltas = HashMap::new();
+    let Some(meta) = transaction.transaction.meta.as_ref() else {
+        return deltas;
+    };
+    let wallet_str = wallet.to_string();
+    let post = wallet_token_accounts(&meta.post_token_balances, &wallet_str);
+    let pre = wallet_token_accounts(&meta.pre_token_balances, &wallet_str);
+    for (mint, amount) in post.into_values() {
+        *deltas.entry(mint).or_insert(0) += amount as i64;
+    }
+    for (mint, amount) in pre.into_values() {
+        *deltas.entry(mint).or_insert(0) -= amount as i64;
+    }
+    deltas
 }
 
+/// Mint and raw amount of the wallet's token accounts by account index
+fn wallet_token_accounts(
+    balances: &OptionSerializer<Vec<solana_transaction_status::UiTransactionTokenBalance>>,
+    wallet: &str,
+) -> HashMap<u8, (String, u64)> {
+    let OptionSerializer::Some(balances) = balances else {
+        return HashMap::new();
+    };
+    balances
+        .iter()
+        .filter(|balance| match &balance.owner {
+            OptionSerializer::Some(owner) => owner == wallet,
+            _ => false,
+        })
+        .map(|balance| {
+            let amount = balance.ui_token_amount.amount.parse::<u64>().unwrap_or(0);
+            (balance.account_index, (balance.mint.clone(), amount))
+        })
+        .collect()
+}
+
+/// Lamports of a token account that are token balance rather than rent
+fn native_amount(mint: &str, amount: u64) -> i64 {
+    if mint == WSOL_TOKEN_ACCOUNT.to_string() {
+        amount as i64
+    } else {
+        0
+    }
+}
+
+/// Receives the result of an analysis that was deferred because the RPC lagged behind
+pub type AnalysisCallback = Arc<dyn Fn(Signature, Result<TradeResult>) + Send + Sync>;
+
 impl TradeResult {
+    /// Result of a landed transaction whose analysis is still pending
+    pub fn pending(signature: &Signature, token_mint: &Pubkey, wallet_address: &Pubkey) -> Self {
+        TradeResult {
+            signature: signature.to_string(),
+            tokens_received: 0.0,
+            entry_price: 0.0,
+            sol_spent: 0.0,
+            token_mint: token_mint.to_string(),
+            wallet_address: wallet_address.to_string(),
+            analysis_duration_ms: 0,
+            profit_loss_absolute: None,
+            profit_loss_percentage: None,
+            original_entry_price: None,
+            slot: None,
+            solana_fees: None,
+            token_decimals: 6,
+            post_token_balance: None,
+            analysis_pending: true,
+            stages: TradeStages::default(),
+            commission_lamports: None,
+            labels: None,
+            sequence: None,
+            block_time: None,
+            raw: None,
+            protocol: None,
+        }
+    }
+
+    /// Attach the stages reached while sending
+    pub fn with_stages(mut self, stages: TradeStages) -> Self {
+        self.stages = stages;
+        self
+    }
+
+    /// Attach the commission charged in the transaction
+    pub fn with_commission(mut self, commission_lamports: Option<u64>) -> Self {
+        self.commission_lamports = commission_lamports;
+        self
+    }
+
+    /// Attach the labels the trade was sent with
+    pub fn with_labels(mut self, labels: Option<TradeLabels>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Attach the submission sequence of the trade
+    pub fn with_sequence(mut self, sequence: u64) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    /// Attach the protocol the trade was built for
+    pub fn with_protocol(mut self, protocol: &str) -> Self {
+        self.protocol = Some(protocol.to_string());
+        self
+    }
+
+    /// Fetch a transaction at `commitment`, retrying while the RPC does not know it yet
+    ///
+    /// Right after confirmation `getTransaction` often returns `null` for a few hundred
+    /// milliseconds, only that case is retried. `getTransaction` does not serve processed
+    /// transactions, processed commitment is read as confirmed.
+    pub(crate) async fn fetch_transaction(
+        rpc_client: &SolanaRpcClient,
+        signature: &Signature,
+        commitment: CommitmentConfig,
+    ) -> Result<EncodedConfirmedTransactionWithStatusMeta> {
+        let commitment = if commitment.is_finalized() {
+            commitment
+        } else {
+            CommitmentConfig::confirmed()
+        };
+        let config = RpcTransactionConfig {
+            commitment: Some(commitment),
+            encoding: Some(UiTransactionEncoding::JsonParsed),
+            max_supported_transaction_version: Some(0),
+        };
+        let mut attempt = 0;
+        loop {
+            match rpc_client.get_transaction_with_config(signature, config).await {
+                Ok(transaction) => return Ok(transaction),
+                Err(e) if attempt < ANALYSIS_NOT_FOUND_RETRIES && Self::is_not_found(&e) => {
+                    attempt += 1;
+                    tokio::time::sleep(Duration::from_millis(ANALYSIS_NOT_FOUND_RETRY_DELAY_MS))
+                        .await;
+                }
+                Err(e) => return Err(anyhow!("Failed to fetch transaction: {}", e)),
+            }
+        }
+    }
+
+    /// `getTransaction` returns `null` for unknown signatures, which surfaces as a decode error
+    fn is_not_found(error: &ClientError) -> bool {
+        let message = error.to_string();
+        message.contains("null") || message.to_lowercase().contains("not found")
+    }
+
     /// Get token decimals from mint account
     #[allow(dead_code)]
     async fn get_token_decimals(
         rpc_client: &SolanaRpcClient,
         token_mint: &Pubkey,
     ) -> Result<u8> {
-        println!("🔍 [MINT_DEBUG] Fetching decimals for token mint: {}", token_mint);
+        println!(icon!("🔍 ", "[MINT_DEBUG] Fetching decimals for token mint: {}"), token_mint);
         
         let mint_account = rpc_client
             .get_account(token_mint)
@@ -65,7 +354,10 @@ impl TradeResult {
         let mint_data = Mint::unpack(&mint_account.data)
             .map_err(|e| anyhow!("Failed to deserialize mint account: {}", e))?;
 
-        println!("🔍 [MINT_DEBUG] Token mint {} has {} decimals on-chain", token_mint, mint_data.decimals);
+        println!(
+            icon!("🔍 ", "[MINT_DEBUG] Token mint {} has {} decimals on-chain"),
+            token_mint, mint_data.decimals
+        );
         Ok(mint_data.decimals)
     }
 
@@ -114,6 +406,7 @@ impl TradeResult {
     /// * `token_mint` - Expected token mint address
     /// * `wallet_address` - Wallet address that executed the trade
     /// * `expected_sol_spent` - Expected SOL amount spent (for validation)
+    /// * `commitment` - Commitment the transaction is read at, confirmed or finalized
     /// 
     /// # Returns
     /// 
@@ -124,26 +417,19 @@ impl TradeResult {
         token_mint: &Pubkey,
         wallet_address: &Pubkey,
         expected_sol_spent: f64,
+        commitment: CommitmentConfig,
     ) -> Result<Self> {
         let analysis_start = Instant::now();
         
         // Transaction analysis started
         
-        // Configure RPC request for transaction details
-        let config = RpcTransactionConfig {
-            commitment: Some(CommitmentConfig::confirmed()),
-            encoding: Some(UiTransactionEncoding::JsonParsed),
-            max_supported_transaction_version: Some(0),
-        };
-
         // Fetch transaction details
-        let transaction = rpc_client
-            .get_transaction_with_config(signature, config)
-            .await
-            .map_err(|e| anyhow!("Failed to fetch transaction: {}", e))?;
+        let transaction = Self::fetch_transaction(rpc_client, signature, commitment).await?;
 
         // Extract slot information
         let slot = transaction.slot;
+        let block_time = transaction.block_time;
+        let raw = RawTradeAmounts::from_transaction(&transaction, wallet_address, token_mint);
 
         // Extract meta data
         let meta = transaction
@@ -161,10 +447,14 @@ impl TradeResult {
 
         // Get token decimals for accurate calculations
         // Get token decimals directly from transaction metadata (more reliable than RPC)
-        let token_decimals = Self::extract_decimals_from_transaction_meta(&meta, token_mint, wallet_address)
-            .unwrap_or_else(|| {
-                6 // Default fallback
-            });
+        let decimals_cache = MintDecimalsCache::get_instance();
+        let token_decimals = match Self::extract_decimals_from_transaction_meta(&meta, token_mint, wallet_address) {
+            Some(decimals) => {
+                decimals_cache.insert(*token_mint, decimals);
+                decimals
+            }
+            None => decimals_cache.get(token_mint).unwrap_or(DEFAULT_TOKEN_DECIMALS),
+        };
         
 
         // Analyze token balance changes
@@ -246,13 +536,13 @@ impl TradeResult {
                 
                 if balance_delta_lamports > 0 {
                     sol_spent = balance_delta_lamports as f64 / 1_000_000_000.0;
-                    log::debug!("🔍 [TRADE_ANALYSIS] Found user's wallet at account index {} with SOL spent: {:.9}", 
+                    log::debug!(icon!("🔍 ", "[TRADE_ANALYSIS] Found user's wallet at account index {} with SOL spent: {:.9}"), 
                         index, sol_spent);
                 }
             }
         } else {
             // Fallback: If we can't find the wallet in account keys, use the largest decrease
-            log::warn!("⚠️ [TRADE_ANALYSIS] Could not find wallet {} in account keys, using fallback logic", wallet_address);
+            log::warn!(icon!("⚠️ ", "[TRADE_ANALYSIS] Could not find wallet {} in account keys, using fallback logic"), wallet_address);
             let mut largest_decrease = 0i64;
             let mut best_index = 0usize;
             
@@ -266,7 +556,7 @@ impl TradeResult {
             
             if largest_decrease > 0 {
                 sol_spent = largest_decrease as f64 / 1_000_000_000.0;
-                log::debug!("🔍 [TRADE_ANALYSIS] Fallback: Found largest SOL decrease at account index {} with SOL spent: {:.6}", 
+                log::debug!(icon!("🔍 ", "[TRADE_ANALYSIS] Fallback: Found largest SOL decrease at account index {} with SOL spent: {:.6}"), 
                     best_index, sol_spent);
             }
         }
@@ -286,7 +576,7 @@ impl TradeResult {
                 let estimated_token_cost = tokens_received * 0.0001; // Conservative price estimate
                 sol_spent = (base_network_fees + estimated_token_cost).max(0.001); // Minimum 0.001 SOL
                 
-                log::warn!("🚨 [TRADE_ANALYSIS] Could not determine actual SOL spent for transaction {}. Using estimated SOL spent: {:.6} SOL for {:.6} tokens (network fees: {:.6})", 
+                log::warn!(icon!("🚨 ", "[TRADE_ANALYSIS] Could not determine actual SOL spent for transaction {}. Using estimated SOL spent: {:.6} SOL for {:.6} tokens (network fees: {:.6})"), 
                     signature, sol_spent, tokens_received, base_network_fees);
             }
         }
@@ -298,9 +588,9 @@ impl TradeResult {
         let analysis_duration_ms = analysis_start.elapsed().as_millis() as u64;
         
         // Debug logging for entry price calculation (using println to ensure visibility)
-        println!("🔍 [TRADE_ANALYSIS] Signature: {} | SOL spent: {:.9} | Tokens received: {:.6} | Entry price: {:.10} | Token decimals: {}", 
+        println!(icon!("🔍 ", "[TRADE_ANALYSIS] Signature: {} | SOL spent: {:.9} | Tokens received: {:.6} | Entry price: {:.10} | Token decimals: {}"), 
             signature, sol_spent, tokens_received, entry_price, token_decimals);
-        log::info!("🔍 [TRADE_ANALYSIS] Signature: {} | SOL spent: {:.9} | Tokens received: {:.6} | Entry price: {:.10} | Token decimals: {}", 
+        log::info!(icon!("🔍 ", "[TRADE_ANALYSIS] Signature: {} | SOL spent: {:.9} | Tokens received: {:.6} | Entry price: {:.10} | Token decimals: {}"), 
             signature, sol_spent, tokens_received, entry_price, token_decimals);
 
                 // Analysis complete: {:.6} tokens at {:.10} SOL per token
@@ -320,6 +610,14 @@ impl TradeResult {
             solana_fees,
             token_decimals,  // 🔥 CRITICAL: Include actual token decimals in result
             post_token_balance: None, // Not relevant for buy transactions
+            analysis_pending: false,
+            stages: TradeStages::default(),
+            commission_lamports: None,
+            labels: None,
+            sequence: None,
+            block_time,
+            raw,
+            protocol: None,
         })
     }
 
@@ -332,7 +630,9 @@ impl TradeResult {
     /// * `token_mint` - Expected token mint address
     /// * `wallet_address` - Wallet address that executed the trade
     /// * `expected_tokens_sold` - Expected token amount sold
-    /// * `original_entry_price` - Original entry price for profit calculation
+    /// * `original_entry_price` - Original entry price for profit calculation, `None` when the
+    ///   position has no known entry (e.g. airdropped tokens) and PnL is left out
+    /// * `commitment` - Commitment the transaction is read at, confirmed or finalized
     /// 
     /// # Returns
     /// 
@@ -343,25 +643,18 @@ impl TradeResult {
         token_mint: &Pubkey,
         wallet_address: &Pubkey,
         expected_tokens_sold: f64,
-        original_entry_price: f64,
+        original_entry_price: Option<f64>,
+        commitment: CommitmentConfig,
     ) -> Result<TradeResult> {
         let analysis_start = Instant::now();
         
-        // Configure RPC request for transaction details
-        let config = RpcTransactionConfig {
-            commitment: Some(CommitmentConfig::confirmed()),
-            encoding: Some(UiTransactionEncoding::JsonParsed),
-            max_supported_transaction_version: Some(0),
-        };
-
         // Fetch transaction details
-        let transaction = rpc_client
-            .get_transaction_with_config(signature, config)
-            .await
-            .map_err(|e| anyhow!("Failed to fetch transaction: {}", e))?;
+        let transaction = Self::fetch_transaction(rpc_client, signature, commitment).await?;
 
         // Extract slot information
         let slot = transaction.slot;
+        let block_time = transaction.block_time;
+        let raw = RawTradeAmounts::from_transaction(&transaction, wallet_address, token_mint);
 
         // Extract meta data
         let meta = transaction
@@ -379,10 +672,14 @@ impl TradeResult {
 
         // Get token decimals for accurate calculations
         // Get token decimals directly from transaction metadata (more reliable than RPC)
-        let token_decimals = Self::extract_decimals_from_transaction_meta(&meta, token_mint, wallet_address)
-            .unwrap_or_else(|| {
-                6 // Default fallback
-            });
+        let decimals_cache = MintDecimalsCache::get_instance();
+        let token_decimals = match Self::extract_decimals_from_transaction_meta(&meta, token_mint, wallet_address) {
+            Some(decimals) => {
+                decimals_cache.insert(*token_mint, decimals);
+                decimals
+            }
+            None => decimals_cache.get(token_mint).unwrap_or(DEFAULT_TOKEN_DECIMALS),
+        };
         
 
         // Analyze token balance changes
@@ -497,13 +794,12 @@ impl TradeResult {
         // Calculate current price per token from this sell
         let current_price = sol_received / tokens_sold;
 
-        // Calculate profit/loss
-        let profit_loss_absolute = (current_price - original_entry_price) * tokens_sold;
-        let profit_loss_percentage = if original_entry_price > 0.0 {
-            ((current_price - original_entry_price) / original_entry_price) * 100.0
-        } else {
-            0.0
-        };
+        // Calculate profit/loss, only possible with a known entry price
+        let profit_loss_absolute =
+            original_entry_price.map(|entry_price| (current_price - entry_price) * tokens_sold);
+        let profit_loss_percentage = original_entry_price
+            .filter(|entry_price| *entry_price > 0.0)
+            .map(|entry_price| ((current_price - entry_price) / entry_price) * 100.0);
 
         let analysis_duration_ms = analysis_start.elapsed().as_millis() as u64;
 
@@ -515,14 +811,111 @@ impl TradeResult {
             token_mint: token_mint_str,
             wallet_address: wallet_str,
             analysis_duration_ms,
-            profit_loss_absolute: Some(profit_loss_absolute),
-            profit_loss_percentage: Some(profit_loss_percentage),
-            original_entry_price: Some(original_entry_price),
+            profit_loss_absolute,
+            profit_loss_percentage,
+            original_entry_price,
             slot: Some(slot),
             solana_fees,
             token_decimals,  // 🔥 CRITICAL: Include actual token decimals in result
             post_token_balance, // 🧹 CRITICAL: Actual remaining balance after sell for account cleanup
+            analysis_pending: false,
+            stages: TradeStages::default(),
+            commission_lamports: None,
+            labels: None,
+            sequence: None,
+            block_time,
+            raw,
+            protocol: None,
         })
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use solana_rpc_client_api::request::RpcRequest;
+    use solana_sdk::hash::Hash;
+
+    use super::*;
+    use crate::common::fake_chain::FakeChain;
+
+    /// `getTransaction` result of `wallet` selling 1000 tokens of `mint` for 0.5 SOL
+    fn sell_transaction(wallet: &Pubkey, mint: &Pubkey) -> serde_json::Value {
+        let token_balance = |ui_amount: f64, amount: &str| {
+            json!({
+                "accountIndex": 1,
+                "mint": mint.to_string(),
+                "owner": wallet.to_string(),
+                "programId": spl_token::ID.to_string(),
+                "uiTokenAmount": {
+                    "uiAmount": ui_amount,
+                    "decimals": 6,
+                    "amount": amount,
+                    "uiAmountString": ui_amount.to_string(),
+                },
+            })
+        };
+        let account = |pubkey: String, signer: bool| json!({ "pubkey": pubkey, "writable": true, "signer": signer, "source": "transaction" });
+        json!({
+            "slot": 42,
+            "blockTime": 1_700_000_000,
+            "transaction": {
+                "signatures": [Signature::default().to_string()],
+                "message": {
+                    "accountKeys": [
+                        account(wallet.to_string(), true),
+                        account(Pubkey::new_unique().to_string(), false),
+                    ],
+                    "recentBlockhash": Hash::default().to_string(),
+                    "instructions": [],
+                },
+            },
+            "meta": {
+                "err": null,
+                "status": { "Ok": null },
+                "fee": 5_000,
+                "preBalances": [1_000_000_000u64, 2_039_280],
+                "postBalances": [1_500_000_000u64, 2_039_280],
+                "preTokenBalances": [token_balance(1_000.0, "1000000000")],
+                "postTokenBalances": [token_balance(0.0, "0")],
+            },
+        })
+    }
+
+    async fn analyze_sell(original_entry_price: Option<f64>) -> TradeResult {
+        let (wallet, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (rpc, _) = FakeChain::default()
+            .script(RpcRequest::GetTransaction, Ok(sell_transaction(&wallet, &mint)))
+            .client();
+        TradeResult::analyze_sell_transaction(
+            &rpc,
+            &Signature::default(),
+            &mint,
+            &wallet,
+            1_000.0,
+            original_entry_price,
+            CommitmentConfig::confirmed(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn sell_without_a_position_record_leaves_pnl_out() {
+        let result = analyze_sell(None).await;
+        assert_eq!(result.tokens_received, -1_000.0);
+        assert_eq!(result.sol_spent, -0.5);
+        assert!((result.entry_price - 0.000_5).abs() < 1e-12);
+        assert_eq!(result.original_entry_price, None);
+        assert_eq!(result.profit_loss_absolute, None);
+        assert_eq!(result.profit_loss_percentage, None);
+    }
+
+    #[tokio::test]
+    async fn sell_with_a_known_entry_price_reports_pnl() {
+        let result = analyze_sell(Some(0.000_25)).await;
+        assert_eq!(result.original_entry_price, Some(0.000_25));
+        assert!((result.profit_loss_absolute.unwrap() - 0.25).abs() < 1e-9);
+        assert!((result.profit_loss_percentage.unwrap() - 100.0).abs() < 1e-9);
+    }
+}
@@ -1,11 +1,12 @@
 use std::sync::Arc;
 
 use anyhow::Result;
-use solana_sdk::instruction::Instruction;
+use solana_sdk::{instruction::Instruction, transaction::VersionedTransaction};
 use crate::trading::MiddlewareManager;
 
 use super::{
     params::{BuyParams, BuyWithTipParams, SellParams, SellWithTipParams},
+    simulation::SimulationResult,
     trade_result::TradeResult,
 };
 
@@ -24,11 +25,31 @@ pub trait TradeExecutor: Send + Sync {
     /// 使用MEV服务执行卖出交易
     async fn sell_with_tip(&self, params: SellWithTipParams, middleware_manager: Option<Arc<MiddlewareManager>>) -> Result<TradeResult>;
 
+    /// 构建与 `buy` 完全相同的交易，但通过 simulateTransaction 模拟而不发送
+    async fn simulate_buy(&self, params: BuyParams, middleware_manager: Option<Arc<MiddlewareManager>>) -> Result<SimulationResult>;
+
+    /// 构建与 `sell` 完全相同的交易，但通过 simulateTransaction 模拟而不发送
+    async fn simulate_sell(&self, params: SellParams, middleware_manager: Option<Arc<MiddlewareManager>>) -> Result<SimulationResult>;
+
+    /// 构建买入的完整业务指令（含中间件、hooks 与佣金），不含计算预算和小费指令，也不发送
+    async fn buy_instructions(&self, params: BuyParams, middleware_manager: Option<Arc<MiddlewareManager>>) -> Result<Vec<Instruction>>;
+
+    /// 构建卖出的完整业务指令（含中间件、hooks 与佣金），不含计算预算和小费指令，也不发送
+    async fn sell_instructions(&self, params: SellParams, middleware_manager: Option<Arc<MiddlewareManager>>) -> Result<Vec<Instruction>>;
+
+    /// 构建与 `buy` 完全相同的交易（含计算预算与首个 swqos 客户端的小费），但不签名也不发送
+    async fn buy_transaction(&self, params: BuyParams, middleware_manager: Option<Arc<MiddlewareManager>>) -> Result<VersionedTransaction>;
+
+    /// 构建与 `sell` 完全相同的交易（含计算预算与首个 swqos 客户端的小费），但不签名也不发送
+    async fn sell_transaction(&self, params: SellParams, middleware_manager: Option<Arc<MiddlewareManager>>) -> Result<VersionedTransaction>;
+
     /// 获取协议名称
     fn protocol_name(&self) -> &'static str;
 }
 
 /// 指令构建器trait - 负责构建协议特定的交易指令
+///
+/// 构建器只依赖参数本身，不做任何 RPC 调用；卖出时 `token_amount` 在调用前已由执行器解析为具体数量
 #[async_trait::async_trait]
 pub trait InstructionBuilder: Send + Sync {
     /// 构建买入指令
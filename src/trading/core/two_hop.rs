@@ -0,0 +1,213 @@
+use super::trade_result::TradeResult;
+use crate::{
+    common::PriorityFee,
+    constants::{
+        trade::trade::{MAX_COMPUTE_UNIT_LIMIT, MAX_TX_ACCOUNT_LOCKS},
+        SYSTEM_PROGRAM, TOKEN_PROGRAM, WSOL_TOKEN_ACCOUNT,
+    },
+    trading::common::compute_budget_instructions,
+};
+use anyhow::{anyhow, Result};
+use solana_hash::Hash;
+use solana_sdk::{
+    instruction::Instruction,
+    message::{v0, AddressLookupTableAccount, VersionedMessage},
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+use solana_system_interface::instruction::transfer;
+
+// spl-token instruction tags
+const CLOSE_ACCOUNT_TAG: u8 = 9;
+const SYNC_NATIVE_TAG: u8 = 17;
+// System program transfer instruction tag
+const SYSTEM_TRANSFER_TAG: u32 = 2;
+
+/// How a two-hop swap is executed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TwoHopMode {
+    /// One transaction when it fits, two sequential ones otherwise
+    #[default]
+    Auto,
+    /// One transaction or an error
+    SingleTransaction,
+    /// Sell first, buy once the sell confirmed
+    Sequential,
+}
+
+/// Outcome of a two-hop A -> SOL -> B swap
+#[derive(Debug, Clone)]
+pub struct TwoHopReport {
+    pub mint_in: Pubkey,
+    pub mint_out: Pubkey,
+    /// `SingleTransaction` or `Sequential`, whichever was used
+    pub mode: TwoHopMode,
+    /// Sell of `mint_in`; in a single transaction its SOL fields are the net of both legs
+    pub sell_leg: TradeResult,
+    /// Buy of `mint_out`; in a single transaction its SOL fields are the net of both legs
+    pub buy_leg: Option<TradeResult>,
+    /// Why the buy leg of a sequential swap failed after the sell landed
+    pub buy_error: Option<String>,
+}
+
+impl TwoHopReport {
+    /// Tokens of `mint_out` received per token of `mint_in` sold, in UI amounts
+    pub fn effective_rate(&self) -> Option<f64> {
+        let sold = self.sell_leg.tokens_received.abs();
+        let bought = self.buy_leg.as_ref()?.tokens_received;
+        if sold == 0.0 {
+            return None;
+        }
+        Some(bought / sold)
+    }
+
+    pub fn signatures(&self) -> Vec<Signature> {
+        let mut signatures: Vec<Signature> = Vec::with_capacity(2);
+        for leg in std::iter::once(&self.sell_leg).chain(self.buy_leg.as_ref()) {
+            if let Ok(signature) = leg.signature.parse() {
+                if !signatures.contains(&signature) {
+                    signatures.push(signature);
+                }
+            }
+        }
+        signatures
+    }
+}
+
+/// Join the instructions of both legs into one list
+///
+/// When the sell leg unwraps into the wSOL ATA and the buy leg wraps into it again, the
+/// unwrap and the re-wrap are dropped so the buy spends the wSOL the sell left behind. The buy
+/// leg must then be sized at or below the sell leg's minimum output.
+pub fn merge_two_hop_instructions(
+    payer: &Pubkey,
+    sell_instructions: Vec<Instruction>,
+    buy_instructions: Vec<Instruction>,
+) -> Vec<Instruction> {
+    let wsol_account = crate::common::fast_fn::get_associated_token_address_with_program_id_fast(
+        payer,
+        &WSOL_TOKEN_ACCOUNT,
+        &TOKEN_PROGRAM,
+    );
+    let sell_unwraps = sell_instructions.iter().any(|ix| is_close_wsol(ix, &wsol_account));
+    let buy_wraps = buy_instructions.iter().any(|ix| is_sync_native(ix, &wsol_account));
+    if !(sell_unwraps && buy_wraps) {
+        return sell_instructions.into_iter().chain(buy_instructions).collect();
+    }
+    sell_instructions
+        .into_iter()
+        .filter(|ix| !is_close_wsol(ix, &wsol_account))
+        .chain(
+            buy_instructions.into_iter().filter(|ix| {
+                !is_sync_native(ix, &wsol_account) && !is_transfer_to(ix, &wsol_account)
+            }),
+        )
+        .collect()
+}
+
+/// Check that both legs fit one transaction with compute budget and tip instructions added
+///
+/// Fails when the unique accounts exceed the account lock limit (lookup tables do not help
+/// there), the serialized transaction exceeds the packet size, or both swaps need more compute
+/// than a transaction may request.
+pub fn check_single_transaction_fit(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    priority_fee: &PriorityFee,
+    data_size_limit: u32,
+) -> Result<()> {
+    check_transaction_fit(
+        "Both legs",
+        payer,
+        instructions,
+        lookup_tables,
+        priority_fee,
+        data_size_limit,
+    )
+}
+
+/// `check_single_transaction_fit` for any instructions, `what` names them in errors
+pub(crate) fn check_transaction_fit(
+    what: &str,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    priority_fee: &PriorityFee,
+    data_size_limit: u32,
+) -> Result<()> {
+    let unit_limit = priority_fee.tip_unit_limit.max(priority_fee.rpc_unit_limit);
+    if unit_limit > MAX_COMPUTE_UNIT_LIMIT {
+        return Err(anyhow!(
+            "{} need {} compute units, a transaction may request {}",
+            what,
+            unit_limit,
+            MAX_COMPUTE_UNIT_LIMIT
+        ));
+    }
+
+    let mut full_instructions: Vec<Instruction> =
+        compute_budget_instructions(priority_fee, data_size_limit, "", false).into_vec();
+    full_instructions.extend_from_slice(instructions);
+    full_instructions.push(transfer(payer, &Pubkey::new_unique(), 1));
+
+    let message =
+        v0::Message::try_compile(payer, &full_instructions, lookup_tables, Hash::default())?;
+    let account_count = message.account_keys.len()
+        + message
+            .address_table_lookups
+            .iter()
+            .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+            .sum::<usize>();
+    if account_count > MAX_TX_ACCOUNT_LOCKS {
+        return Err(anyhow!(
+            "{} use {} accounts, a transaction may lock {}",
+            what,
+            account_count,
+            MAX_TX_ACCOUNT_LOCKS
+        ));
+    }
+
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default()],
+        message: VersionedMessage::V0(message),
+    };
+    let size = bincode::serialize(&transaction)?.len();
+    if size > PACKET_DATA_SIZE {
+        return Err(anyhow!(
+            "{} serialize to {} bytes, the limit is {}{}",
+            what,
+            size,
+            PACKET_DATA_SIZE,
+            if lookup_tables.is_empty() { ", an address lookup table may help" } else { "" }
+        ));
+    }
+    Ok(())
+}
+
+/// Priority fee for one transaction running both swaps, compute unit limits doubled
+pub fn two_hop_priority_fee(priority_fee: &PriorityFee) -> PriorityFee {
+    let mut priority_fee = priority_fee.clone();
+    priority_fee.map_unit_limits(|unit_limit| unit_limit.saturating_mul(2));
+    priority_fee
+}
+
+pub(crate) fn is_close_wsol(ix: &Instruction, wsol_account: &Pubkey) -> bool {
+    ix.program_id == TOKEN_PROGRAM
+        && ix.data.first() == Some(&CLOSE_ACCOUNT_TAG)
+        && ix.accounts.first().map(|meta| meta.pubkey) == Some(*wsol_account)
+}
+
+fn is_sync_native(ix: &Instruction, wsol_account: &Pubkey) -> bool {
+    ix.program_id == TOKEN_PROGRAM
+        && ix.data.first() == Some(&SYNC_NATIVE_TAG)
+        && ix.accounts.first().map(|meta| meta.pubkey) == Some(*wsol_account)
+}
+
+fn is_transfer_to(ix: &Instruction, wsol_account: &Pubkey) -> bool {
+    ix.program_id == SYSTEM_PROGRAM
+        && ix.data.get(..4) == Some(&SYSTEM_TRANSFER_TAG.to_le_bytes()[..])
+        && ix.accounts.get(1).map(|meta| meta.pubkey) == Some(*wsol_account)
+}
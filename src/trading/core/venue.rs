@@ -0,0 +1,45 @@
+use anyhow::{anyhow, Result};
+use solana_sdk::pubkey::Pubkey;
+
+use super::traits::ProtocolParams;
+use crate::{
+    common::SolanaRpcClient,
+    instruction::utils::pumpswap,
+    params::{BonkParams, PumpFunParams, PumpSwapParams},
+    trading::factory::DexType,
+};
+
+/// How much of a token to sell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SellAmount {
+    /// The whole balance of the payer's token account
+    All,
+    /// Token base units
+    Exact(u64),
+}
+
+/// Find where `mint` trades and build params for it from chain state
+///
+/// Checks, in order, an active PumpFun bonding curve, a PumpSwap pool and a Bonk pool quoted in
+/// WSOL. A completed bonding curve falls through to PumpSwap, where the token migrated to.
+pub async fn detect_venue(
+    rpc: &SolanaRpcClient,
+    mint: &Pubkey,
+) -> Result<(DexType, Box<dyn ProtocolParams>)> {
+    if let Ok(params) = PumpFunParams::from_mint_by_rpc(rpc, mint).await {
+        if !params.bonding_curve.complete {
+            return Ok((DexType::PumpFun, Box::new(params)));
+        }
+    }
+    if let Ok(pool) = pumpswap::find_pool(rpc, mint).await {
+        let params = PumpSwapParams::from_pool_address_by_rpc(rpc, &pool).await?;
+        return Ok((DexType::PumpSwap, Box::new(params)));
+    }
+    if let Ok(params) =
+        BonkParams::from_mint_and_quote_by_rpc(rpc, mint, &crate::constants::WSOL_TOKEN_ACCOUNT)
+            .await
+    {
+        return Ok((DexType::Bonk, Box::new(params)));
+    }
+    Err(anyhow!("No PumpFun, PumpSwap or Bonk market found for mint {}", mint))
+}
@@ -1,16 +1,25 @@
 // Removed unused imports
+use solana_sdk::pubkey::Pubkey;
 use std::sync::Arc;
 
+use crate::instruction::utils::{
+    bonk, jupiter, meteora_dlmm, pumpfun, pumpswap, raydium_amm_v4, raydium_cpmm,
+};
 use crate::instruction::{
-    bonk::BonkInstructionBuilder, pumpfun::PumpFunInstructionBuilder,
+    bonk::BonkInstructionBuilder, jupiter::JupiterInstructionBuilder,
+    meteora_dlmm::MeteoraDlmmInstructionBuilder,
+    pumpfun::PumpFunInstructionBuilder,
     pumpswap::PumpSwapInstructionBuilder, raydium_amm_v4::RaydiumAmmV4InstructionBuilder,
     raydium_cpmm::RaydiumCpmmInstructionBuilder, raydium_clmm::{RaydiumClmmInstructionBuilder, RaydiumClmmV2InstructionBuilder},
 };
 
-use super::core::{executor::GenericTradeExecutor, traits::TradeExecutor};
+use super::core::{
+    executor::GenericTradeExecutor,
+    traits::{InstructionBuilder, TradeExecutor},
+};
 
 /// 支持的交易协议
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum DexType {
     PumpFun,
     PumpSwap,
@@ -19,6 +28,50 @@ pub enum DexType {
     RaydiumClmm,
     RaydiumClmmV2,
     RaydiumAmmV4,
+    MeteoraDlmm,
+    /// Jupiter v6 聚合路由
+    Jupiter,
+}
+
+impl DexType {
+    /// 协议的程序地址，即兑换指令调用的程序
+    pub fn program_id(&self) -> Pubkey {
+        match self {
+            DexType::PumpFun => pumpfun::accounts::PUMPFUN,
+            DexType::PumpSwap => pumpswap::accounts::AMM_PROGRAM,
+            DexType::Bonk => bonk::accounts::BONK,
+            DexType::RaydiumCpmm => raydium_cpmm::accounts::RAYDIUM_CPMM,
+            DexType::RaydiumClmm | DexType::RaydiumClmmV2 => {
+                crate::instruction::data::RAYDIUM_CLMM_PROGRAM
+            }
+            DexType::RaydiumAmmV4 => raydium_amm_v4::accounts::RAYDIUM_AMM_V4,
+            DexType::MeteoraDlmm => meteora_dlmm::accounts::METEORA_DLMM,
+            DexType::Jupiter => jupiter::accounts::JUPITER,
+        }
+    }
+
+    /// 交易是否经过 wSOL，PumpFun 直接收付 SOL
+    pub fn uses_wsol(&self) -> bool {
+        !matches!(self, DexType::PumpFun)
+    }
+
+    /// 指令构建器是否按 `create_wsol_ata`/`close_wsol_ata` 创建和关闭 wSOL 账户，
+    /// RaydiumClmm 使用参数中给出的 wSOL 账户
+    pub fn handles_wsol(&self) -> bool {
+        !matches!(self, DexType::PumpFun | DexType::RaydiumClmm)
+    }
+
+    /// 指令构建器是否支持 `min_amount_out` / `min_sol_out` 指定的最少输出
+    pub fn supports_min_out(&self) -> bool {
+        matches!(
+            self,
+            DexType::PumpFun
+                | DexType::PumpSwap
+                | DexType::Bonk
+                | DexType::RaydiumCpmm
+                | DexType::RaydiumAmmV4
+        )
+    }
 }
 
 /// 交易工厂 - 用于创建不同协议的交易执行器
@@ -35,6 +88,23 @@ impl TradeFactory {
             DexType::RaydiumClmm => Self::raydium_clmm_executor(),
             DexType::RaydiumClmmV2 => Self::raydium_clmm_v2_executor(),
             DexType::RaydiumAmmV4 => Self::raydium_amm_v4_executor(),
+            DexType::MeteoraDlmm => Self::meteora_dlmm_executor(),
+            DexType::Jupiter => Self::jupiter_executor(),
+        }
+    }
+
+    /// 创建指定协议的指令构建器，用于自行组合多个协议的指令
+    pub fn create_instruction_builder(dex_type: DexType) -> Arc<dyn InstructionBuilder> {
+        match dex_type {
+            DexType::PumpFun => Arc::new(PumpFunInstructionBuilder),
+            DexType::PumpSwap => Arc::new(PumpSwapInstructionBuilder),
+            DexType::Bonk => Arc::new(BonkInstructionBuilder),
+            DexType::RaydiumCpmm => Arc::new(RaydiumCpmmInstructionBuilder),
+            DexType::RaydiumClmm => Arc::new(RaydiumClmmInstructionBuilder),
+            DexType::RaydiumClmmV2 => Arc::new(RaydiumClmmV2InstructionBuilder),
+            DexType::RaydiumAmmV4 => Arc::new(RaydiumAmmV4InstructionBuilder),
+            DexType::MeteoraDlmm => Arc::new(MeteoraDlmmInstructionBuilder),
+            DexType::Jupiter => Arc::new(JupiterInstructionBuilder),
         }
     }
 
@@ -44,7 +114,11 @@ impl TradeFactory {
         static INSTANCE: std::sync::LazyLock<Arc<dyn TradeExecutor>> =
             std::sync::LazyLock::new(|| {
                 let instruction_builder = Arc::new(PumpFunInstructionBuilder);
-                Arc::new(GenericTradeExecutor::new(instruction_builder, "PumpFun"))
+                Arc::new(GenericTradeExecutor::new(
+                    instruction_builder,
+                    "PumpFun",
+                    DexType::PumpFun.program_id(),
+                ))
             });
         INSTANCE.clone()
     }
@@ -54,7 +128,11 @@ impl TradeFactory {
         static INSTANCE: std::sync::LazyLock<Arc<dyn TradeExecutor>> =
             std::sync::LazyLock::new(|| {
                 let instruction_builder = Arc::new(PumpSwapInstructionBuilder);
-                Arc::new(GenericTradeExecutor::new(instruction_builder, "PumpSwap"))
+                Arc::new(GenericTradeExecutor::new(
+                    instruction_builder,
+                    "PumpSwap",
+                    DexType::PumpSwap.program_id(),
+                ))
             });
         INSTANCE.clone()
     }
@@ -64,7 +142,11 @@ impl TradeFactory {
         static INSTANCE: std::sync::LazyLock<Arc<dyn TradeExecutor>> =
             std::sync::LazyLock::new(|| {
                 let instruction_builder = Arc::new(BonkInstructionBuilder);
-                Arc::new(GenericTradeExecutor::new(instruction_builder, "Bonk"))
+                Arc::new(GenericTradeExecutor::new(
+                    instruction_builder,
+                    "Bonk",
+                    DexType::Bonk.program_id(),
+                ))
             });
         INSTANCE.clone()
     }
@@ -74,7 +156,11 @@ impl TradeFactory {
         static INSTANCE: std::sync::LazyLock<Arc<dyn TradeExecutor>> =
             std::sync::LazyLock::new(|| {
                 let instruction_builder = Arc::new(RaydiumCpmmInstructionBuilder);
-                Arc::new(GenericTradeExecutor::new(instruction_builder, "RaydiumCpmm"))
+                Arc::new(GenericTradeExecutor::new(
+                    instruction_builder,
+                    "RaydiumCpmm",
+                    DexType::RaydiumCpmm.program_id(),
+                ))
             });
         INSTANCE.clone()
     }
@@ -84,7 +170,11 @@ impl TradeFactory {
         static INSTANCE: std::sync::LazyLock<Arc<dyn TradeExecutor>> =
             std::sync::LazyLock::new(|| {
                 let instruction_builder = Arc::new(RaydiumClmmInstructionBuilder);
-                Arc::new(GenericTradeExecutor::new(instruction_builder, "RaydiumClmm"))
+                Arc::new(GenericTradeExecutor::new(
+                    instruction_builder,
+                    "RaydiumClmm",
+                    DexType::RaydiumClmm.program_id(),
+                ))
             });
         INSTANCE.clone()
     }
@@ -94,7 +184,11 @@ impl TradeFactory {
         static INSTANCE: std::sync::LazyLock<Arc<dyn TradeExecutor>> =
             std::sync::LazyLock::new(|| {
                 let instruction_builder = Arc::new(RaydiumClmmV2InstructionBuilder);
-                Arc::new(GenericTradeExecutor::new(instruction_builder, "RaydiumClmmV2"))
+                Arc::new(GenericTradeExecutor::new(
+                    instruction_builder,
+                    "RaydiumClmmV2",
+                    DexType::RaydiumClmmV2.program_id(),
+                ))
             });
         INSTANCE.clone()
     }
@@ -104,7 +198,39 @@ impl TradeFactory {
         static INSTANCE: std::sync::LazyLock<Arc<dyn TradeExecutor>> =
             std::sync::LazyLock::new(|| {
                 let instruction_builder = Arc::new(RaydiumAmmV4InstructionBuilder);
-                Arc::new(GenericTradeExecutor::new(instruction_builder, "RaydiumAmmV4"))
+                Arc::new(GenericTradeExecutor::new(
+                    instruction_builder,
+                    "RaydiumAmmV4",
+                    DexType::RaydiumAmmV4.program_id(),
+                ))
+            });
+        INSTANCE.clone()
+    }
+
+    #[inline]
+    fn meteora_dlmm_executor() -> Arc<dyn TradeExecutor> {
+        static INSTANCE: std::sync::LazyLock<Arc<dyn TradeExecutor>> =
+            std::sync::LazyLock::new(|| {
+                let instruction_builder = Arc::new(MeteoraDlmmInstructionBuilder);
+                Arc::new(GenericTradeExecutor::new(
+                    instruction_builder,
+                    "MeteoraDlmm",
+                    DexType::MeteoraDlmm.program_id(),
+                ))
+            });
+        INSTANCE.clone()
+    }
+
+    #[inline]
+    fn jupiter_executor() -> Arc<dyn TradeExecutor> {
+        static INSTANCE: std::sync::LazyLock<Arc<dyn TradeExecutor>> =
+            std::sync::LazyLock::new(|| {
+                let instruction_builder = Arc::new(JupiterInstructionBuilder);
+                Arc::new(GenericTradeExecutor::new(
+                    instruction_builder,
+                    "Jupiter",
+                    DexType::Jupiter.program_id(),
+                ))
             });
         INSTANCE.clone()
     }
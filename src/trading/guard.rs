@@ -0,0 +1,340 @@
+//! Price floor for sells
+//!
+//! A `SellGuard` is checked against the executable price of the sell, the SOL the pool pays
+//! for the whole amount divided by the tokens sold. When the price is below the floor the sell
+//! is not sent but registered as a `PriceAbove(floor)` trigger order that expires after
+//! `recovery_window`: a wick that recovers in time is sold at the floor or better, a collapse
+//! that does not recover leaves the tokens unsold. `force` skips the guard entirely.
+
+use std::time::Duration;
+
+use anyhow::anyhow;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+
+use crate::{
+    market::sell_output_lamports,
+    trading::{
+        core::{options::SellOptions, traits::ProtocolParams},
+        factory::DexType,
+        trigger::{ParamsSource, TriggerCondition, TriggerEngine, TriggerOrder},
+    },
+};
+
+/// Default time a deferred sell waits for the price to recover
+pub const DEFAULT_RECOVERY_WINDOW: Duration = Duration::from_secs(300);
+
+/// Lowest price, in SOL per token, a sell may execute at
+#[derive(Debug, Clone, PartialEq)]
+pub struct SellGuard {
+    pub min_acceptable_price: Option<f64>,
+    /// Largest fall below `entry_price`, in basis points
+    pub max_drawdown_from_entry_bps: Option<u64>,
+    /// Entry price `max_drawdown_from_entry_bps` is measured from
+    pub entry_price: Option<f64>,
+    /// How long a deferred sell waits for the price to recover
+    pub recovery_window: Duration,
+    /// Sell now whatever the price, e.g. in a rug; always wins over the floor
+    pub force: bool,
+}
+
+impl Default for SellGuard {
+    fn default() -> Self {
+        Self {
+            min_acceptable_price: None,
+            max_drawdown_from_entry_bps: None,
+            entry_price: None,
+            recovery_window: DEFAULT_RECOVERY_WINDOW,
+            force: false,
+        }
+    }
+}
+
+impl SellGuard {
+    pub fn with_min_price(mut self, min_acceptable_price: f64) -> Self {
+        self.min_acceptable_price = Some(min_acceptable_price);
+        self
+    }
+
+    pub fn with_max_drawdown(mut self, entry_price: f64, max_drawdown_bps: u64) -> Self {
+        self.entry_price = Some(entry_price);
+        self.max_drawdown_from_entry_bps = Some(max_drawdown_bps);
+        self
+    }
+
+    pub fn with_recovery_window(mut self, recovery_window: Duration) -> Self {
+        self.recovery_window = recovery_window;
+        self
+    }
+
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Highest of the configured floors, `None` when no floor applies
+    pub fn floor(&self) -> Option<f64> {
+        let drawdown_floor = match (self.entry_price, self.max_drawdown_from_entry_bps) {
+            (Some(entry_price), Some(bps)) => {
+                Some(entry_price * 10_000u64.saturating_sub(bps) as f64 / 10_000.0)
+            }
+            _ => None,
+        };
+        match (self.min_acceptable_price, drawdown_floor) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (floor, None) | (None, floor) => floor,
+        }
+    }
+
+    /// Floor the price violates, `None` when the sell may execute
+    pub fn violated_floor(&self, executable_price: f64) -> Option<f64> {
+        if self.force {
+            return None;
+        }
+        self.floor().filter(|floor| executable_price < *floor)
+    }
+}
+
+/// Outcome of `TriggerEngine::guarded_sell`
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuardedSell {
+    /// The price satisfied the guard and the sell was sent
+    Sold(Signature),
+    /// The price was below the floor, a trigger order waits for it to recover
+    Deferred { order_id: u64, executable_price: f64, floor: f64 },
+}
+
+/// Executable price of selling `token_amount` on the pool in `params`, in SOL per token
+pub fn executable_sell_price(
+    params: &dyn ProtocolParams,
+    token_amount: u64,
+    token_decimals: u8,
+) -> Result<f64, anyhow::Error> {
+    if token_amount == 0 {
+        return Err(anyhow!("Amount cannot be zero"));
+    }
+    let lamports = sell_output_lamports(params, token_amount)?;
+    let tokens = token_amount as f64 / 10f64.powi(token_decimals as i32);
+    Ok(lamports as f64 / 1_000_000_000.0 / tokens)
+}
+
+impl TriggerEngine {
+    /// Sell now if the executable price satisfies `guard`, otherwise defer the sell
+    ///
+    /// A deferred sell is registered as a trigger order on `params_source` that fires once the
+    /// spot price is back above the floor and expires after `guard.recovery_window`. It only
+    /// fires while the engine is fed prices (`observe`, `start_polling`).
+    pub async fn guarded_sell(
+        &self,
+        dex_type: DexType,
+        mint: Pubkey,
+        token_amount: u64,
+        token_decimals: u8,
+        params: Box<dyn ProtocolParams>,
+        params_source: ParamsSource,
+        guard: &SellGuard,
+        options: SellOptions,
+    ) -> Result<GuardedSell, anyhow::Error> {
+        if !guard.force {
+            let executable_price =
+                executable_sell_price(params.as_ref(), token_amount, token_decimals)?;
+            if let Some(floor) = guard.violated_floor(executable_price) {
+                let mut order = TriggerOrder::sell(
+                    mint,
+                    dex_type,
+                    TriggerCondition::PriceAbove(floor),
+                    token_amount,
+                    params_source,
+                )
+                .expires_in(guard.recovery_window);
+                order.slippage_basis_points = options.slippage_basis_points;
+                order.labels = options.labels.clone();
+                let order_id = self.register(order)?;
+                return Ok(GuardedSell::Deferred { order_id, executable_price, floor });
+            }
+        }
+        let recent_blockhash = self.trade.rpc.get_latest_blockhash().await?;
+        let signature = self
+            .trade
+            .sell_with_options(dex_type, mint, token_amount, recent_blockhash, params, options)
+            .await?;
+        Ok(GuardedSell::Sold(signature))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::{
+        common::fake_chain::FakeChain,
+        constants::WSOL_TOKEN_ACCOUNT,
+        params::PumpSwapParams,
+        trading::trigger::{MarketObservation, TriggerEvent, TriggerSide},
+        SolanaTrade,
+    };
+
+    /// 10 tokens (6 decimals) sold into 1000 tokens and 1 SOL, 0.000990099 SOL per token
+    const SELL_AMOUNT: u64 = 10_000_000;
+
+    fn pool(mint: Pubkey) -> PumpSwapParams {
+        PumpSwapParams {
+            pool: Pubkey::new_unique(),
+            base_mint: mint,
+            quote_mint: WSOL_TOKEN_ACCOUNT,
+            pool_base_token_reserves: 1_000_000_000,
+            pool_quote_token_reserves: 1_000_000_000,
+            base_token_program: crate::constants::TOKEN_PROGRAM,
+            quote_token_program: crate::constants::TOKEN_PROGRAM,
+            creator: Pubkey::default(),
+            auto_handle_wsol: true,
+            fee_config: Pubkey::new_unique(),
+            fee_program: Pubkey::new_unique(),
+        }
+    }
+
+    /// Bought at 0.002, refusing to sell more than 40% lower until the window ends
+    fn guard() -> SellGuard {
+        SellGuard::default().with_min_price(0.001).with_max_drawdown(0.002, 4_000)
+    }
+
+    /// Engine on an RPC serving nothing, so a sell that is sent fails at its first request
+    fn engine() -> (TriggerEngine, Arc<Mutex<Vec<TriggerEvent>>>) {
+        let (rpc, _) = FakeChain::default().client();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let engine = TriggerEngine::new(Arc::new(SolanaTrade::offline(rpc)))
+            .with_event_callback(Arc::new(move |event| sink.lock().push(event)));
+        (engine, events)
+    }
+
+    fn kinds(events: &Mutex<Vec<TriggerEvent>>) -> Vec<&'static str> {
+        events
+            .lock()
+            .iter()
+            .map(|event| match event {
+                TriggerEvent::Registered { .. } => "registered",
+                TriggerEvent::Triggered { .. } => "triggered",
+                TriggerEvent::Executed { .. } => "executed",
+                TriggerEvent::Failed { .. } => "failed",
+                TriggerEvent::Expired { .. } => "expired",
+                TriggerEvent::Cancelled { .. } => "cancelled",
+            })
+            .collect()
+    }
+
+    fn at(price: f64) -> MarketObservation {
+        MarketObservation { price, curve_progress: None }
+    }
+
+    async fn sell(
+        engine: &TriggerEngine,
+        mint: Pubkey,
+        guard: &SellGuard,
+    ) -> Result<GuardedSell, anyhow::Error> {
+        let pool = pool(mint);
+        let source = ParamsSource::PumpSwapPool(pool.pool);
+        let options = SellOptions::default()
+            .with_slippage_basis_points(9_000)
+            .with_label("strategy", "stop-loss");
+        engine
+            .guarded_sell(
+                DexType::PumpSwap,
+                mint,
+                SELL_AMOUNT,
+                6,
+                Box::new(pool),
+                source,
+                guard,
+                options,
+            )
+            .await
+    }
+
+    #[test]
+    fn floor_is_the_highest_configured_limit() {
+        assert_eq!(SellGuard::default().floor(), None);
+        assert_eq!(SellGuard::default().with_min_price(0.001).floor(), Some(0.001));
+        let floor = guard().floor().unwrap();
+        assert!((floor - 0.001_2).abs() < 1e-12, "{}", floor);
+        assert_eq!(guard().violated_floor(0.001_3), None);
+        assert_eq!(guard().violated_floor(0.001_1), Some(floor));
+        assert_eq!(guard().with_force(true).violated_floor(0.000_1), None);
+    }
+
+    #[test]
+    fn executable_price_includes_the_price_impact() {
+        let price = executable_sell_price(&pool(Pubkey::new_unique()), SELL_AMOUNT, 6).unwrap();
+        assert!((price - 0.000_990_099).abs() < 1e-12, "{}", price);
+        assert!(executable_sell_price(&pool(Pubkey::new_unique()), 0, 6).is_err());
+    }
+
+    #[tokio::test]
+    async fn wick_defers_the_sell_until_the_price_recovers() {
+        let (engine, events) = engine();
+        let mint = Pubkey::new_unique();
+
+        let GuardedSell::Deferred { order_id, executable_price, floor } =
+            sell(&engine, mint, &guard()).await.unwrap()
+        else {
+            panic!("sell below the floor was not deferred");
+        };
+        assert!(executable_price < floor);
+        let open = engine.open_orders();
+        assert_eq!(open.len(), 1);
+        let order = &open[0].order;
+        assert_eq!(open[0].id, order_id);
+        assert_eq!((order.side, order.amount), (TriggerSide::Sell, SELL_AMOUNT));
+        assert_eq!(order.trigger, TriggerCondition::PriceAbove(floor));
+        assert_eq!(order.slippage_basis_points, Some(9_000));
+        assert_eq!(order.labels.as_ref().unwrap()["strategy"], "stop-loss");
+        assert!(order.expiry.is_some());
+
+        // Still below the floor: nothing fires
+        engine.observe(&mint, at(0.000_9)).await;
+        engine.observe(&mint, at(0.001_1)).await;
+        assert_eq!(kinds(&events), vec!["registered"]);
+
+        // The wick recovers inside the window and the sell goes out once
+        engine.observe(&mint, at(0.001_5)).await;
+        engine.observe(&mint, at(0.001_6)).await;
+        assert_eq!(kinds(&events), vec!["registered", "triggered", "failed"]);
+        assert!(engine.open_orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn collapse_that_never_recovers_expires_unsold() {
+        let (engine, events) = engine();
+        let mint = Pubkey::new_unique();
+        let guard = guard().with_recovery_window(Duration::from_secs(1));
+
+        assert!(matches!(sell(&engine, mint, &guard).await.unwrap(), GuardedSell::Deferred { .. }));
+        engine.observe(&mint, at(0.000_5)).await;
+        engine.observe(&mint, at(0.000_1)).await;
+        assert_eq!(kinds(&events), vec!["registered"]);
+
+        // Expiry has a one second resolution, this is past the end of the window
+        tokio::time::sleep(Duration::from_millis(1_100)).await;
+        engine.observe(&mint, at(0.000_05)).await;
+        assert_eq!(kinds(&events), vec!["registered", "expired"]);
+        assert!(engine.open_orders().is_empty());
+
+        // A late recovery no longer sells
+        engine.observe(&mint, at(0.002)).await;
+        assert_eq!(kinds(&events), vec!["registered", "expired"]);
+    }
+
+    #[tokio::test]
+    async fn force_sells_through_a_collapse() {
+        let (engine, events) = engine();
+        let mint = Pubkey::new_unique();
+
+        // Sent right away: the offline RPC rejects its blockhash request
+        let e = sell(&engine, mint, &guard().with_force(true)).await.unwrap_err();
+        assert!(e.to_string().contains("is not served"), "{}", e);
+        assert!(kinds(&events).is_empty());
+        assert!(engine.open_orders().is_empty());
+    }
+}
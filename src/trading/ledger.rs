@@ -0,0 +1,576 @@
+//! Wallet level PnL from analyzed trades
+//!
+//! Every analyzed `TradeResult`, and any other wallet transaction such as a wSOL wrap or a
+//! transfer, is appended to a `LedgerStore` as a `LedgerEntry` with the exact lamport amounts of
+//! the transaction. Reports replay the whole ledger in slot order on every query, so a result
+//! recorded late (deferred analysis, a confirmation that was in limbo) lands in the day its
+//! block was produced and corrects the cost basis of later sells.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    ops::Range,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+
+use crate::{
+    common::{labels::TradeLabels, SolanaRpcClient},
+    constants::WSOL_TOKEN_ACCOUNT,
+    trading::core::trade_result::{token_deltas, RawTradeAmounts, TradeResult},
+};
+
+/// Protocol key for entries recorded without one, e.g. found by `Ledger::scan`
+pub const UNKNOWN_PROTOCOL: &str = "unknown";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LedgerEntryKind {
+    Buy,
+    Sell,
+    /// A transaction that traded no token, e.g. a wSOL wrap or a SOL transfer. Only its fee,
+    /// tip and rent count towards PnL.
+    Transfer,
+    /// A transaction that landed with an error, only its fee counts
+    Failed,
+}
+
+/// One wallet transaction with the exact amounts read from its balances
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub signature: String,
+    pub wallet: String,
+    pub kind: LedgerEntryKind,
+    pub slot: u64,
+    /// Unix time of the block, in seconds
+    pub block_time: i64,
+    #[serde(default)]
+    pub protocol: Option<String>,
+    /// Traded mint, `None` for transfers and failed transactions
+    #[serde(default)]
+    pub token_mint: Option<String>,
+    pub amounts: RawTradeAmounts,
+    #[serde(default)]
+    pub commission_lamports: u64,
+    /// Labels the trade was sent with, `None` for transactions found on-chain
+    #[serde(default)]
+    pub labels: Option<TradeLabels>,
+}
+
+impl LedgerEntry {
+    /// Entry of an analyzed trade
+    ///
+    /// Fails for results without exact amounts: pending analyses and results that were
+    /// estimated instead of analyzed.
+    pub fn from_trade_result(result: &TradeResult) -> Result<Self> {
+        if result.analysis_pending {
+            return Err(anyhow!("Trade {} is not analyzed yet", result.signature));
+        }
+        let (Some(amounts), Some(slot), Some(block_time)) =
+            (result.raw, result.slot, result.block_time)
+        else {
+            return Err(anyhow!("Trade {} has no exact amounts", result.signature));
+        };
+        let kind =
+            if result.tokens_received < 0.0 { LedgerEntryKind::Sell } else { LedgerEntryKind::Buy };
+        Ok(Self {
+            signature: result.signature.clone(),
+            wallet: result.wallet_address.clone(),
+            kind,
+            slot,
+            block_time,
+            protocol: result.protocol.clone(),
+            token_mint: Some(result.token_mint.clone()),
+            amounts,
+            commission_lamports: result.commission_lamports.unwrap_or(0),
+            labels: result.labels.clone(),
+        })
+    }
+}
+
+/// Where ledger entries are kept
+pub trait LedgerStore: Send + Sync {
+    /// Append `entry`, it replaces earlier entries with the same signature
+    fn append(&self, entry: &LedgerEntry) -> Result<()>;
+
+    /// All entries in the order they were appended
+    fn entries(&self) -> Result<Vec<LedgerEntry>>;
+}
+
+/// In-memory store, entries are lost when the process exits
+#[derive(Default)]
+pub struct MemoryLedgerStore {
+    entries: Mutex<Vec<LedgerEntry>>,
+}
+
+impl MemoryLedgerStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl LedgerStore for MemoryLedgerStore {
+    fn append(&self, entry: &LedgerEntry) -> Result<()> {
+        self.entries.lock().push(entry.clone());
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<LedgerEntry>> {
+        Ok(self.entries.lock().clone())
+    }
+}
+
+/// Append-only file with one JSON entry per line
+pub struct JsonlLedgerStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl JsonlLedgerStore {
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), lock: Mutex::new(()) }
+    }
+}
+
+impl LedgerStore for JsonlLedgerStore {
+    fn append(&self, entry: &LedgerEntry) -> Result<()> {
+        let _guard = self.lock.lock();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| anyhow!("Failed to open ledger file {:?}: {}", self.path, e))?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)
+            .map_err(|e| anyhow!("Failed to write ledger file {:?}: {}", self.path, e))?;
+        Ok(())
+    }
+
+    fn entries(&self) -> Result<Vec<LedgerEntry>> {
+        let _guard = self.lock.lock();
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let file = std::fs::File::open(&self.path)
+            .map_err(|e| anyhow!("Failed to read ledger file {:?}: {}", self.path, e))?;
+        let mut entries = Vec::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push(serde_json::from_str(&line).map_err(|e| {
+                anyhow!("Failed to parse line {} of ledger file {:?}: {}", number + 1, self.path, e)
+            })?);
+        }
+        Ok(entries)
+    }
+}
+
+/// PnL of one protocol within a `PnlReport`, in lamports
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ProtocolPnl {
+    pub realized_lamports: i64,
+    pub fees_lamports: u64,
+    pub tips_lamports: u64,
+    pub trade_count: usize,
+}
+
+/// PnL of a wallet over a time range, in lamports
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PnlReport {
+    /// Swap proceeds minus the cost basis of the tokens sold, for sells with recorded buys
+    pub realized_lamports: i64,
+    /// Swap proceeds of sold tokens without recorded buys, e.g. airdrops, not part of
+    /// `realized_lamports`
+    pub unmatched_proceeds_lamports: i64,
+    /// Network fees, including those of failed transactions, and commissions
+    pub fees_lamports: u64,
+    pub tips_lamports: u64,
+    /// Rent refunded by closed token accounts minus rent locked in new ones
+    pub rent_net_lamports: i64,
+    /// Buys and sells
+    pub trade_count: usize,
+    /// Sells with recorded buys that realized a profit or a loss
+    pub wins: usize,
+    pub losses: usize,
+    pub by_protocol: BTreeMap<String, ProtocolPnl>,
+}
+
+impl PnlReport {
+    /// Realized PnL net of fees, tips and rent churn
+    pub fn net_lamports(&self) -> i64 {
+        self.realized_lamports - self.fees_lamports as i64 - self.tips_lamports as i64
+            + self.rent_net_lamports
+    }
+
+    /// Share of sells with recorded buys that made a profit, `None` without such sells
+    pub fn win_rate(&self) -> Option<f64> {
+        let sells = self.wins + self.losses;
+        (sells > 0).then(|| self.wins as f64 / sells as f64)
+    }
+
+    pub fn realized_sol(&self) -> f64 {
+        lamports_to_sol(self.realized_lamports)
+    }
+
+    pub fn fees_sol(&self) -> f64 {
+        lamports_to_sol(self.fees_lamports as i64)
+    }
+
+    pub fn tips_sol(&self) -> f64 {
+        lamports_to_sol(self.tips_lamports as i64)
+    }
+
+    pub fn rent_net_sol(&self) -> f64 {
+        lamports_to_sol(self.rent_net_lamports)
+    }
+
+    pub fn net_sol(&self) -> f64 {
+        lamports_to_sol(self.net_lamports())
+    }
+
+    fn add(&mut self, entry: &LedgerEntry, outcome: &SellOutcome) {
+        let amounts = &entry.amounts;
+        let fees = amounts.fee_lamports + entry.commission_lamports;
+        self.fees_lamports += fees;
+        self.tips_lamports += amounts.tip_lamports;
+        self.rent_net_lamports -= amounts.rent_lamports;
+        self.realized_lamports += outcome.realized.unwrap_or(0);
+        self.unmatched_proceeds_lamports += outcome.unmatched_proceeds;
+        match outcome.realized {
+            Some(realized) if realized > 0 => self.wins += 1,
+            Some(realized) if realized < 0 => self.losses += 1,
+            _ => {}
+        }
+        if entry.kind == LedgerEntryKind::Transfer {
+            return;
+        }
+        if entry.kind != LedgerEntryKind::Failed {
+            self.trade_count += 1;
+        }
+        let protocol = entry.protocol.as_deref().unwrap_or(UNKNOWN_PROTOCOL);
+        let by_protocol = self.by_protocol.entry(protocol.to_string()).or_default();
+        by_protocol.realized_lamports += outcome.realized.unwrap_or(0);
+        by_protocol.fees_lamports += fees;
+        by_protocol.tips_lamports += amounts.tip_lamports;
+        if entry.kind != LedgerEntryKind::Failed {
+            by_protocol.trade_count += 1;
+        }
+    }
+}
+
+fn lamports_to_sol(lamports: i64) -> f64 {
+    lamports as f64 / 1_000_000_000.0
+}
+
+/// Unix time range of a UTC day, in seconds
+pub fn utc_day(date: NaiveDate) -> Range<i64> {
+    let start = date.and_hms_opt(0, 0, 0).map_or(0, |time| time.and_utc().timestamp());
+    start..start + 24 * 60 * 60
+}
+
+/// Realized result of one entry, zero for anything but sells
+#[derive(Default)]
+struct SellOutcome {
+    realized: Option<i64>,
+    unmatched_proceeds: i64,
+}
+
+/// Tokens held and what they cost, per mint
+#[derive(Default)]
+struct CostBasis {
+    held: u128,
+    cost_lamports: u128,
+}
+
+/// Appends trades to a `LedgerStore` and reports PnL from it
+pub struct Ledger {
+    store: Arc<dyn LedgerStore>,
+}
+
+impl Ledger {
+    pub fn new(store: Arc<dyn LedgerStore>) -> Self {
+        Self { store }
+    }
+
+    pub fn in_memory() -> Self {
+        Self::new(Arc::new(MemoryLedgerStore::new()))
+    }
+
+    /// Record an analyzed trade, e.g. from the analysis callback
+    pub fn record(&self, result: &TradeResult) -> Result<LedgerEntry> {
+        let entry = LedgerEntry::from_trade_result(result)?;
+        self.store.append(&entry)?;
+        Ok(entry)
+    }
+
+    pub fn record_entry(&self, entry: &LedgerEntry) -> Result<()> {
+        self.store.append(entry)
+    }
+
+    /// Fetch a landed transaction of `wallet` and record it
+    ///
+    /// A transaction that changed the balance of exactly one mint other than wSOL is a buy or
+    /// a sell of it, anything else a transfer. The protocol is not detected.
+    pub async fn record_transaction(
+        &self,
+        rpc: &SolanaRpcClient,
+        signature: &Signature,
+        wallet: &Pubkey,
+    ) -> Result<LedgerEntry> {
+        let transaction =
+            TradeResult::fetch_transaction(rpc, signature, CommitmentConfig::confirmed()).await?;
+        let meta = transaction
+            .transaction
+            .meta
+            .as_ref()
+            .ok_or_else(|| anyhow!("Transaction meta not found"))?;
+        let block_time = transaction
+            .block_time
+            .ok_or_else(|| anyhow!("Transaction {} has no block time", signature))?;
+
+        let (kind, token_mint, amounts) = if meta.err.is_some() {
+            // Only the fee was charged, tip transfers did not execute
+            let amounts = RawTradeAmounts {
+                sol_delta_lamports: -(meta.fee as i64),
+                fee_lamports: meta.fee,
+                ..Default::default()
+            };
+            (LedgerEntryKind::Failed, None, amounts)
+        } else {
+            let wsol = WSOL_TOKEN_ACCOUNT.to_string();
+            let traded: Vec<String> = token_deltas(&transaction, wallet)
+                .into_iter()
+                .filter(|(mint, delta)| *mint != wsol && *delta != 0)
+                .map(|(mint, _)| mint)
+                .collect();
+            let mint = match traded.as_slice() {
+                [mint] => Some(mint.parse::<Pubkey>()?),
+                _ => None,
+            };
+            let amounts = RawTradeAmounts::from_transaction(
+                &transaction,
+                wallet,
+                mint.as_ref().unwrap_or(&WSOL_TOKEN_ACCOUNT),
+            )
+            .ok_or_else(|| anyhow!("Wallet {} is not in transaction {}", wallet, signature))?;
+            let kind = match mint {
+                Some(_) if amounts.token_delta > 0 => LedgerEntryKind::Buy,
+                Some(_) => LedgerEntryKind::Sell,
+                None => LedgerEntryKind::Transfer,
+            };
+            (kind, mint.map(|mint| mint.to_string()), amounts)
+        };
+
+        let entry = LedgerEntry {
+            signature: signature.to_string(),
+            wallet: wallet.to_string(),
+            kind,
+            slot: transaction.slot,
+            block_time,
+            protocol: None,
+            token_mint,
+            amounts,
+            commission_lamports: 0,
+            labels: None,
+        };
+        self.store.append(&entry)?;
+        Ok(entry)
+    }
+
+    /// Record the latest `limit` transactions of `wallet` that are not in the ledger yet
+    pub async fn scan(
+        &self,
+        rpc: &SolanaRpcClient,
+        wallet: &Pubkey,
+        limit: usize,
+    ) -> Result<Vec<LedgerEntry>> {
+        let config = GetConfirmedSignaturesForAddress2Config {
+            limit: Some(limit),
+            commitment: Some(CommitmentConfig::confirmed()),
+            ..Default::default()
+        };
+        let signatures = rpc.get_signatures_for_address_with_config(wallet, config).await?;
+        let known: HashSet<String> =
+            self.entries(wallet)?.into_iter().map(|entry| entry.signature).collect();
+
+        let mut recorded = Vec::new();
+        for status in signatures {
+            if known.contains(&status.signature) {
+                continue;
+            }
+            let signature: Signature = status.signature.parse()?;
+            recorded.push(self.record_transaction(rpc, &signature, wallet).await?);
+        }
+        Ok(recorded)
+    }
+
+    /// Entries of `wallet` in slot order, the last recorded entry of each signature
+    pub fn entries(&self, wallet: &Pubkey) -> Result<Vec<LedgerEntry>> {
+        let wallet = wallet.to_string();
+        let mut latest: HashMap<String, LedgerEntry> = HashMap::new();
+        for entry in self.store.entries()? {
+            if entry.wallet == wallet {
+                latest.insert(entry.signature.clone(), entry);
+            }
+        }
+        let mut entries: Vec<LedgerEntry> = latest.into_values().collect();
+        entries.sort_by(|a, b| {
+            (a.slot, a.block_time, &a.signature).cmp(&(b.slot, b.block_time, &b.signature))
+        });
+        Ok(entries)
+    }
+
+    /// PnL of `wallet` for blocks with a time in `range`, unix seconds
+    pub fn pnl_report(&self, wallet: &Pubkey, range: Range<i64>) -> Result<PnlReport> {
+        let mut report = PnlReport::default();
+        for (entry, outcome) in self.evaluate(wallet)? {
+            if range.contains(&entry.block_time) {
+                report.add(&entry, &outcome);
+            }
+        }
+        Ok(report)
+    }
+
+    /// PnL of `wallet` per UTC day from `first` to `last`, days without entries included
+    pub fn daily_reports(
+        &self,
+        wallet: &Pubkey,
+        first: NaiveDate,
+        last: NaiveDate,
+    ) -> Result<Vec<(NaiveDate, PnlReport)>> {
+        let mut days: BTreeMap<NaiveDate, PnlReport> = first
+            .iter_days()
+            .take_while(|day| *day <= last)
+            .map(|day| (day, Default::default()))
+            .collect();
+        for (entry, outcome) in self.evaluate(wallet)? {
+            let Some(day) = DateTime::from_timestamp(entry.block_time, 0) else {
+                continue;
+            };
+            if let Some(report) = days.get_mut(&day.date_naive()) {
+                report.add(&entry, &outcome);
+            }
+        }
+        Ok(days.into_iter().collect())
+    }
+
+    /// Write the entries of `wallet` in `range` as CSV, with the realized PnL of each sell
+    pub fn export_csv(
+        &self,
+        wallet: &Pubkey,
+        range: Range<i64>,
+        writer: &mut impl Write,
+    ) -> Result<()> {
+        writeln!(
+            writer,
+            "signature,block_time,slot,kind,protocol,token_mint,sol_delta_lamports,token_delta,\
+             fee_lamports,tip_lamports,rent_lamports,commission_lamports,realized_lamports,\
+             unmatched_proceeds_lamports"
+        )?;
+        for (entry, outcome) in self.evaluate(wallet)? {
+            if !range.contains(&entry.block_time) {
+                continue;
+            }
+            let amounts = &entry.amounts;
+            writeln!(
+                writer,
+                "{},{},{},{:?},{},{},{},{},{},{},{},{},{},{}",
+                entry.signature,
+                entry.block_time,
+                entry.slot,
+                entry.kind,
+                entry.protocol.as_deref().unwrap_or(""),
+                entry.token_mint.as_deref().unwrap_or(""),
+                amounts.sol_delta_lamports,
+                amounts.token_delta,
+                amounts.fee_lamports,
+                amounts.tip_lamports,
+                amounts.rent_lamports,
+                entry.commission_lamports,
+                outcome.realized.map_or(String::new(), |realized| realized.to_string()),
+                outcome.unmatched_proceeds,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Replay every entry of `wallet` in slot order, tracking the cost basis per mint
+    fn evaluate(&self, wallet: &Pubkey) -> Result<Vec<(LedgerEntry, SellOutcome)>> {
+        let mut positions: HashMap<String, CostBasis> = HashMap::new();
+        let mut evaluated = Vec::new();
+        for entry in self.entries(wallet)? {
+            let mut outcome = SellOutcome::default();
+            let swap = entry.amounts.swap_lamports(entry.commission_lamports);
+            let tokens = entry.amounts.token_delta.unsigned_abs() as u128;
+            if let Some(mint) = &entry.token_mint {
+                let position = positions.entry(mint.clone()).or_default();
+                match entry.kind {
+                    LedgerEntryKind::Buy => {
+                        position.held += tokens;
+                        position.cost_lamports += (-swap).max(0) as u128;
+                    }
+                    LedgerEntryKind::Sell if tokens > 0 => {
+                        let proceeds = swap.max(0) as i128;
+                        let matched = tokens.min(position.held);
+                        let matched_proceeds = proceeds * matched as i128 / tokens as i128;
+                        if matched > 0 {
+                            let cost = position.cost_lamports * matched / position.held;
+                            position.held -= matched;
+                            position.cost_lamports -= cost;
+                            outcome.realized = Some((matched_proceeds - cost as i128) as i64);
+                        }
+                        outcome.unmatched_proceeds = (proceeds - matched_proceeds) as i64;
+                    }
+                    _ => {}
+                }
+            }
+            evaluated.push((entry, outcome));
+        }
+        Ok(evaluated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_keep_the_labels_of_their_trade() {
+        let mut result = TradeResult::pending(
+            &Signature::default(),
+            &Pubkey::new_unique(),
+            &Pubkey::new_unique(),
+        );
+        result.analysis_pending = false;
+        result.tokens_received = 100.0;
+        result.slot = Some(300_000_000);
+        result.block_time = Some(1_700_000_000);
+        result.raw = Some(RawTradeAmounts {
+            sol_delta_lamports: -1_000_000_000,
+            token_delta: 100_000_000,
+            ..Default::default()
+        });
+        result.labels = Some(TradeLabels::from([("strategy".to_string(), "copy".to_string())]));
+
+        let entry = LedgerEntry::from_trade_result(&result).unwrap();
+        assert_eq!(entry.labels, result.labels);
+        let line = serde_json::to_string(&entry).unwrap();
+        assert_eq!(serde_json::from_str::<LedgerEntry>(&line).unwrap(), entry);
+    }
+
+    #[test]
+    fn entries_written_before_labels_still_load() {
+        let line = r#"{"signature":"sig","wallet":"wallet","kind":"Transfer","slot":1,"block_time":2,"amounts":{"sol_delta_lamports":-5000,"token_delta":0,"fee_lamports":5000,"tip_lamports":0,"rent_lamports":0}}"#;
+        let entry: LedgerEntry = serde_json::from_str(line).unwrap();
+        assert_eq!(entry.labels, None);
+        assert_eq!(entry.amounts.fee_lamports, 5_000);
+    }
+}
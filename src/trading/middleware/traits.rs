@@ -88,6 +88,11 @@ impl MiddlewareManager {
         Ok(full_instructions)
     }
 
+    /// Names of the middlewares in the order they are applied
+    pub fn middleware_names(&self) -> Vec<&'static str> {
+        self.middlewares.iter().map(|middleware| middleware.name()).collect()
+    }
+
     /// Apply all middlewares to process protocol_instructions
     pub fn apply_middlewares_process_protocol_instructions(
         &self,
@@ -1,7 +1,14 @@
 pub mod common;
 pub mod core;
 pub mod factory;
+pub mod guard;
+pub mod ledger;
 pub mod middleware;
+pub mod paper;
+pub mod position;
+pub mod price;
+pub mod trigger;
+pub mod watcher;
 
 pub use core::params::{BuyParams, SellParams};
 pub use core::traits::{InstructionBuilder, TradeExecutor};
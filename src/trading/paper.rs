@@ -0,0 +1,302 @@
+//! Paper trading against a virtual portfolio
+//!
+//! `PaperEngine` quotes orders on the pool state in the protocol params (see `market`), lets a
+//! `FillModel` decide the fill, and books it on a virtual SOL balance and token positions. Fills
+//! are returned as `TradeResult` with the same sign conventions as analyzed live trades, so
+//! strategy code can run unchanged against either.
+
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+
+use crate::{
+    market::{buy_output_tokens, sell_output_lamports},
+    swqos::confirmation::{TradeStages, WaitLevel},
+    trading::core::{trade_result::TradeResult, traits::ProtocolParams},
+};
+
+/// Order handed to a `FillModel`
+#[derive(Debug, Clone)]
+pub struct PaperOrder {
+    pub mint: Pubkey,
+    pub is_buy: bool,
+    /// Lamports for a buy, token base units for a sell
+    pub input_amount: u64,
+    /// Curve output at order time: token base units for a buy, lamports for a sell
+    pub quoted_output: u64,
+    /// Least output the order accepts
+    pub min_output: u64,
+}
+
+/// How a `FillModel` filled an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaperFill {
+    /// Token base units for a buy, lamports for a sell
+    pub output: u64,
+    /// Network fees and tips charged in lamports
+    pub fee_lamports: u64,
+}
+
+/// Decides how a paper order fills; implement it for custom or adversarial assumptions
+///
+/// Return an error to reject the order, e.g. when the simulated output is below `min_output`.
+#[async_trait::async_trait]
+pub trait FillModel: Send + Sync {
+    async fn fill(&self, order: &PaperOrder) -> Result<PaperFill>;
+}
+
+/// Fills after a fixed latency with a fixed price impact on top of the quote
+#[derive(Debug, Clone)]
+pub struct LatencySlippageFillModel {
+    pub latency: Duration,
+    /// Output lost to other trades landing first, in basis points of the quote
+    pub slippage_basis_points: u64,
+    pub fee_lamports: u64,
+}
+
+impl Default for LatencySlippageFillModel {
+    fn default() -> Self {
+        Self {
+            latency: Duration::from_millis(400),
+            slippage_basis_points: 50,
+            fee_lamports: 10_000,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl FillModel for LatencySlippageFillModel {
+    async fn fill(&self, order: &PaperOrder) -> Result<PaperFill> {
+        tokio::time::sleep(self.latency).await;
+        let kept = 10_000u64.saturating_sub(self.slippage_basis_points);
+        let output = (order.quoted_output as u128 * kept as u128 / 10_000) as u64;
+        if output < order.min_output {
+            return Err(anyhow!(
+                "Paper fill {} is below the minimum output {}",
+                output,
+                order.min_output
+            ));
+        }
+        Ok(PaperFill { output, fee_lamports: self.fee_lamports })
+    }
+}
+
+/// Virtual holding of one token
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PaperPosition {
+    /// Token base units held
+    pub tokens: u64,
+    /// Lamports paid for the tokens still held, fees included
+    pub cost_lamports: u64,
+    pub token_decimals: u8,
+}
+
+impl PaperPosition {
+    /// Average entry price in SOL per whole token
+    pub fn entry_price(&self) -> f64 {
+        if self.tokens == 0 {
+            return 0.0;
+        }
+        sol(self.cost_lamports) / ui_amount(self.tokens, self.token_decimals)
+    }
+}
+
+/// Paper trading engine with a virtual SOL balance and positions
+pub struct PaperEngine {
+    wallet: Pubkey,
+    fill_model: Box<dyn FillModel>,
+    sol_balance: Mutex<u64>,
+    positions: Mutex<HashMap<Pubkey, PaperPosition>>,
+    fill_count: AtomicU64,
+}
+
+impl PaperEngine {
+    /// Engine starting with `sol_balance` lamports and the default fill model
+    pub fn new(wallet: Pubkey, sol_balance: u64) -> Self {
+        Self {
+            wallet,
+            fill_model: Box::new(LatencySlippageFillModel::default()),
+            sol_balance: Mutex::new(sol_balance),
+            positions: Mutex::new(HashMap::new()),
+            fill_count: AtomicU64::new(0),
+        }
+    }
+
+    pub fn with_fill_model(mut self, fill_model: impl FillModel + 'static) -> Self {
+        self.fill_model = Box::new(fill_model);
+        self
+    }
+
+    /// Virtual SOL balance in lamports
+    pub fn sol_balance(&self) -> u64 {
+        *self.sol_balance.lock()
+    }
+
+    pub fn position(&self, mint: &Pubkey) -> Option<PaperPosition> {
+        self.positions.lock().get(mint).copied()
+    }
+
+    pub fn positions(&self) -> HashMap<Pubkey, PaperPosition> {
+        self.positions.lock().clone()
+    }
+
+    /// Buy `sol_amount` lamports of `mint` on the pool described by `params`
+    pub async fn buy(
+        &self,
+        mint: Pubkey,
+        sol_amount: u64,
+        slippage_basis_points: u64,
+        params: &dyn ProtocolParams,
+        token_decimals: u8,
+    ) -> Result<TradeResult> {
+        let start = Instant::now();
+        let quoted_output = buy_output_tokens(params, sol_amount)?;
+        let order = PaperOrder {
+            mint,
+            is_buy: true,
+            input_amount: sol_amount,
+            quoted_output,
+            min_output: min_output(quoted_output, slippage_basis_points),
+        };
+        let fill = self.fill_model.fill(&order).await?;
+        let cost = sol_amount.saturating_add(fill.fee_lamports);
+        {
+            let mut balance = self.sol_balance.lock();
+            if *balance < cost {
+                return Err(anyhow!(
+                    "Paper balance {} lamports is below the {} lamports needed",
+                    *balance,
+                    cost
+                ));
+            }
+            *balance -= cost;
+        }
+        let mut positions = self.positions.lock();
+        let position =
+            positions.entry(mint).or_insert(PaperPosition { token_decimals, ..Default::default() });
+        position.tokens = position.tokens.saturating_add(fill.output);
+        position.cost_lamports = position.cost_lamports.saturating_add(cost);
+
+        let tokens = ui_amount(fill.output, token_decimals);
+        let mut result = self.result(&mint, start, token_decimals, fill.fee_lamports);
+        result.tokens_received = tokens;
+        result.entry_price = if tokens > 0.0 { sol(sol_amount) / tokens } else { 0.0 };
+        result.sol_spent = sol(cost);
+        result.post_token_balance = Some(ui_amount(position.tokens, token_decimals));
+        Ok(result)
+    }
+
+    /// Sell `token_amount` base units of a held position
+    pub async fn sell(
+        &self,
+        mint: Pubkey,
+        token_amount: u64,
+        slippage_basis_points: u64,
+        params: &dyn ProtocolParams,
+    ) -> Result<TradeResult> {
+        let start = Instant::now();
+        let held = self.position(&mint).ok_or_else(|| anyhow!("No paper position in {}", mint))?;
+        if held.tokens < token_amount {
+            return Err(anyhow!(
+                "Paper position holds {} tokens, cannot sell {}",
+                held.tokens,
+                token_amount
+            ));
+        }
+        let quoted_output = sell_output_lamports(params, token_amount)?;
+        let order = PaperOrder {
+            mint,
+            is_buy: false,
+            input_amount: token_amount,
+            quoted_output,
+            min_output: min_output(quoted_output, slippage_basis_points),
+        };
+        let fill = self.fill_model.fill(&order).await?;
+        let received = fill.output.saturating_sub(fill.fee_lamports);
+
+        let mut positions = self.positions.lock();
+        let position = positions.get_mut(&mint).ok_or_else(|| anyhow!("Paper position closed"))?;
+        if position.tokens < token_amount {
+            return Err(anyhow!("Paper position changed while the sell was filling"));
+        }
+        let cost_sold = (position.cost_lamports as u128 * token_amount as u128
+            / position.tokens as u128) as u64;
+        let entry_price = position.entry_price();
+        position.tokens -= token_amount;
+        position.cost_lamports -= cost_sold;
+        let decimals = position.token_decimals;
+        let remaining = position.tokens;
+        if remaining == 0 {
+            positions.remove(&mint);
+        }
+        drop(positions);
+        *self.sol_balance.lock() += received;
+
+        let tokens = ui_amount(token_amount, decimals);
+        let mut result = self.result(&mint, start, decimals, fill.fee_lamports);
+        result.tokens_received = -tokens;
+        result.entry_price = if tokens > 0.0 { sol(fill.output) / tokens } else { 0.0 };
+        result.sol_spent = -sol(received);
+        result.original_entry_price = Some(entry_price);
+        result.profit_loss_absolute = Some(sol(received) - sol(cost_sold));
+        result.profit_loss_percentage = (cost_sold > 0)
+            .then(|| (received as f64 - cost_sold as f64) / cost_sold as f64 * 100.0);
+        result.post_token_balance = Some(ui_amount(remaining, decimals));
+        Ok(result)
+    }
+
+    fn result(
+        &self,
+        mint: &Pubkey,
+        start: Instant,
+        token_decimals: u8,
+        fee_lamports: u64,
+    ) -> TradeResult {
+        let fill_id = self.fill_count.fetch_add(1, Ordering::Relaxed) + 1;
+        let mut stages = TradeStages::default();
+        stages.mark(WaitLevel::Confirmed);
+        TradeResult {
+            signature: format!("paper-{}", fill_id),
+            tokens_received: 0.0,
+            entry_price: 0.0,
+            sol_spent: 0.0,
+            token_mint: mint.to_string(),
+            wallet_address: self.wallet.to_string(),
+            analysis_duration_ms: start.elapsed().as_millis() as u64,
+            profit_loss_absolute: None,
+            profit_loss_percentage: None,
+            original_entry_price: None,
+            slot: None,
+            solana_fees: Some(fee_lamports),
+            token_decimals,
+            post_token_balance: None,
+            analysis_pending: false,
+            stages,
+            commission_lamports: None,
+            labels: None,
+            sequence: None,
+            block_time: None,
+            raw: None,
+            protocol: None,
+        }
+    }
+}
+
+fn min_output(quoted_output: u64, slippage_basis_points: u64) -> u64 {
+    let kept = 10_000u64.saturating_sub(slippage_basis_points);
+    (quoted_output as u128 * kept as u128 / 10_000) as u64
+}
+
+fn sol(lamports: u64) -> f64 {
+    lamports as f64 / LAMPORTS_PER_SOL as f64
+}
+
+fn ui_amount(amount: u64, decimals: u8) -> f64 {
+    amount as f64 / 10f64.powi(decimals as i32)
+}
@@ -0,0 +1,494 @@
+//! Open positions kept from trade results
+//!
+//! The `PositionTracker` folds every recorded `TradeResult` into a `Position` per mint: the
+//! tokens held, their average entry price and the PnL realized by sells. Amounts are the ones
+//! of the results, in SOL and UI token units, so fees and tips are part of the entry price and
+//! of the sell proceeds. Unlike the `Ledger`, which replays exact amounts on every query, the
+//! tracker updates in place and answers without any I/O.
+//!
+//! `record` applies the results of a mint in `TradeResult::sequence` order, so a sell analyzed
+//! before the buy it follows does not corrupt the entry price.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    common::{mint_cache::MintDecimalsCache, SolanaRpcClient},
+    trading::{
+        core::{
+            sequence::{ReleasedResults, SequenceBuffer},
+            trade_result::TradeResult,
+        },
+        ledger::{Ledger, LedgerEntry, LedgerEntryKind},
+    },
+};
+
+/// Balances below this many tokens are treated as a closed position
+const DUST_TOKENS: f64 = 1e-9;
+
+/// How long `record` holds results back waiting for an earlier sequence of their mint
+pub const POSITION_SEQUENCE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Holdings of one mint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub mint: String,
+    /// Tokens held, in UI units
+    pub tokens: f64,
+    /// SOL paid for the tokens held, including fees
+    pub cost_basis_sol: f64,
+    /// SOL per token paid for the tokens held, 0 once the position is closed
+    pub average_entry_price: f64,
+    /// Sell proceeds minus the cost basis of the tokens sold, in SOL
+    pub realized_pnl_sol: f64,
+    pub buy_count: usize,
+    pub sell_count: usize,
+    /// Highest slot among the recorded trades, 0 when none reported a slot
+    pub last_update_slot: u64,
+    /// Results were applied out of submission order after a missing sequence timed out, the
+    /// position should be re-derived with `PositionTracker::reconcile`
+    #[serde(default)]
+    pub needs_reconciliation: bool,
+}
+
+impl Position {
+    fn new(mint: &str) -> Self {
+        Self {
+            mint: mint.to_string(),
+            tokens: 0.0,
+            cost_basis_sol: 0.0,
+            average_entry_price: 0.0,
+            realized_pnl_sol: 0.0,
+            buy_count: 0,
+            sell_count: 0,
+            last_update_slot: 0,
+            needs_reconciliation: false,
+        }
+    }
+
+    /// Whether no tokens are held
+    pub fn is_closed(&self) -> bool {
+        self.tokens <= DUST_TOKENS
+    }
+
+    /// PnL of the tokens held if they were sold at `price` SOL per token, fees not included
+    pub fn unrealized_pnl_sol(&self, price: f64) -> f64 {
+        self.tokens * price - self.cost_basis_sol
+    }
+
+    fn apply_buy(&mut self, tokens: f64, sol_spent: f64) {
+        self.tokens += tokens;
+        self.cost_basis_sol += sol_spent;
+        self.average_entry_price = self.cost_basis_sol / self.tokens;
+        self.buy_count += 1;
+    }
+
+    /// Only the part of the sell covered by the tracked tokens is realized, tokens bought
+    /// before tracking started have no known cost.
+    fn apply_sell(&mut self, tokens: f64, sol_received: f64) {
+        let covered = tokens.min(self.tokens);
+        if covered > 0.0 {
+            let cost = self.average_entry_price * covered;
+            self.realized_pnl_sol += sol_received * covered / tokens - cost;
+            self.tokens -= covered;
+            self.cost_basis_sol -= cost;
+        }
+        if self.is_closed() {
+            self.tokens = 0.0;
+            self.cost_basis_sol = 0.0;
+            self.average_entry_price = 0.0;
+        }
+        self.sell_count += 1;
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct PositionState {
+    positions: HashMap<String, Position>,
+    /// Signatures already recorded, so a result returned by a trade and delivered again to the
+    /// analysis callback counts once
+    #[serde(default)]
+    recorded: HashSet<String>,
+}
+
+/// PositionTracker singleton keeping the positions of all recorded trades
+pub struct PositionTracker {
+    state: Mutex<PositionState>,
+    sequences: SequenceBuffer,
+}
+
+static POSITION_TRACKER: OnceLock<Arc<PositionTracker>> = OnceLock::new();
+
+impl PositionTracker {
+    /// Get PositionTracker singleton instance
+    pub fn get_instance() -> Arc<PositionTracker> {
+        POSITION_TRACKER
+            .get_or_init(|| Arc::new(PositionTracker::new(POSITION_SEQUENCE_TIMEOUT)))
+            .clone()
+    }
+
+    fn new(sequence_timeout: Duration) -> Self {
+        Self {
+            state: Mutex::new(PositionState::default()),
+            sequences: SequenceBuffer::new(sequence_timeout),
+        }
+    }
+
+    /// Add the tokens of a buy to its mint's position right away, whatever its sequence
+    ///
+    /// Returns `Ok(false)` when the signature was already recorded. Fails for results flagged
+    /// `analysis_pending` and for results that are not buys.
+    pub fn record_buy(&self, result: &TradeResult) -> Result<bool> {
+        Self::buy(&mut self.state.lock(), result)
+    }
+
+    /// Remove the tokens of a sell from its mint's position right away, whatever its sequence,
+    /// and realize their PnL
+    ///
+    /// Returns `Ok(false)` when the signature was already recorded. Fails for results flagged
+    /// `analysis_pending` and for results that are not sells.
+    pub fn record_sell(&self, result: &TradeResult) -> Result<bool> {
+        Self::sell(&mut self.state.lock(), result)
+    }
+
+    /// `record_buy` or `record_sell`, by the sign of the traded tokens, in submission order
+    ///
+    /// A result with a `sequence` is held back until the results of its mint's earlier
+    /// sequences are recorded, and is then applied with them. Returns `Ok(false)` while it is
+    /// held back or when the signature was already recorded. Results waiting longer than
+    /// `POSITION_SEQUENCE_TIMEOUT` for a trade that never returned one are applied on the next
+    /// call, or by `flush_expired`, and flag their position `needs_reconciliation`.
+    pub fn record(&self, result: &TradeResult) -> Result<bool> {
+        Self::check_analyzed(result)?;
+        let mut state = self.state.lock();
+        if state.recorded.contains(&result.signature) {
+            return Ok(false);
+        }
+        let released = self.sequences.push(result.clone());
+        let mut outcome = Ok(false);
+        for released in released.into_iter().chain(self.sequences.flush_expired()) {
+            if let Some(applied) = Self::apply_released(&mut state, released, &result.signature) {
+                outcome = applied;
+            }
+        }
+        outcome
+    }
+
+    /// Apply the results `record` held back longer than `POSITION_SEQUENCE_TIMEOUT`, flagging
+    /// their positions `needs_reconciliation`
+    ///
+    /// Returns the number of results applied.
+    pub fn flush_expired(&self) -> usize {
+        let mut state = self.state.lock();
+        let mut applied = 0;
+        for released in self.sequences.flush_expired() {
+            applied += released.results.len();
+            Self::apply_released(&mut state, released, "");
+        }
+        applied
+    }
+
+    /// Re-derive the position of `mint` from the chain
+    ///
+    /// Scans the latest `limit` transactions of `wallet` into `ledger`, then rebuilds the
+    /// position from the wallet's fills of `mint` with `reconcile_fills`.
+    pub async fn reconcile(
+        &self,
+        rpc: &SolanaRpcClient,
+        ledger: &Ledger,
+        wallet: &Pubkey,
+        mint: &Pubkey,
+        limit: usize,
+    ) -> Result<Position> {
+        ledger.scan(rpc, wallet, limit).await?;
+        let decimals = MintDecimalsCache::get_instance().fetch(rpc, mint).await?;
+        Ok(self.reconcile_fills(mint, &ledger.entries(wallet)?, decimals))
+    }
+
+    /// Replace the position of `mint` with the one `fills` add up to, clearing
+    /// `needs_reconciliation`
+    ///
+    /// `fills` are ledger entries read from the chain, e.g. `Ledger::entries`, applied in slot
+    /// order with their exact SOL change, fees and tips included. Entries of other mints,
+    /// transfers and failed transactions are skipped. Their signatures count as recorded, so
+    /// results of the same trades that are still held back are not applied again.
+    pub fn reconcile_fills(&self, mint: &Pubkey, fills: &[LedgerEntry], decimals: u8) -> Position {
+        let mint = mint.to_string();
+        let mut fills: Vec<&LedgerEntry> =
+            fills.iter().filter(|fill| fill.token_mint.as_ref() == Some(&mint)).collect();
+        fills.sort_by_key(|fill| (fill.slot, fill.block_time));
+
+        let unit = 10f64.powi(decimals as i32);
+        let mut position = Position::new(&mint);
+        let mut state = self.state.lock();
+        for fill in fills {
+            let tokens = fill.amounts.token_delta.unsigned_abs() as f64 / unit;
+            let sol = fill.amounts.sol_delta_lamports as f64 / 1_000_000_000.0;
+            match fill.kind {
+                LedgerEntryKind::Buy => position.apply_buy(tokens, -sol),
+                LedgerEntryKind::Sell => position.apply_sell(tokens, sol),
+                LedgerEntryKind::Transfer | LedgerEntryKind::Failed => continue,
+            }
+            position.last_update_slot = position.last_update_slot.max(fill.slot);
+            state.recorded.insert(fill.signature.clone());
+        }
+        state.positions.insert(mint, position.clone());
+        position
+    }
+
+    /// Position of `mint`, `None` when no trade of it was recorded
+    ///
+    /// Closed positions are kept so their realized PnL stays available.
+    pub fn get_position(&self, mint: &Pubkey) -> Option<Position> {
+        self.state.lock().positions.get(&mint.to_string()).cloned()
+    }
+
+    /// All positions, closed ones included
+    pub fn positions(&self) -> Vec<Position> {
+        self.state.lock().positions.values().cloned().collect()
+    }
+
+    /// Forget the position of `mint`, returning it
+    pub fn remove_position(&self, mint: &Pubkey) -> Option<Position> {
+        self.state.lock().positions.remove(&mint.to_string())
+    }
+
+    /// Forget all positions and recorded signatures
+    pub fn clear(&self) {
+        *self.state.lock() = PositionState::default();
+    }
+
+    /// Serialize the positions and recorded signatures, e.g. to persist them across restarts
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&*self.state.lock())?)
+    }
+
+    /// Replace the tracked positions with the ones serialized by `to_json`
+    pub fn restore_json(&self, json: &str) -> Result<()> {
+        let state: PositionState = serde_json::from_str(json)
+            .map_err(|e| anyhow!("Invalid position tracker state: {}", e))?;
+        *self.state.lock() = state;
+        Ok(())
+    }
+
+    fn check_analyzed(result: &TradeResult) -> Result<()> {
+        if result.analysis_pending {
+            return Err(anyhow!("Trade {} is not analyzed yet", result.signature));
+        }
+        Ok(())
+    }
+
+    fn buy(state: &mut PositionState, result: &TradeResult) -> Result<bool> {
+        Self::check_analyzed(result)?;
+        if result.tokens_received <= 0.0 {
+            return Err(anyhow!("Trade {} is not a buy", result.signature));
+        }
+        Ok(Self::update(state, result, |position| {
+            position.apply_buy(result.tokens_received, result.sol_spent)
+        }))
+    }
+
+    fn sell(state: &mut PositionState, result: &TradeResult) -> Result<bool> {
+        Self::check_analyzed(result)?;
+        if result.tokens_received >= 0.0 {
+            return Err(anyhow!("Trade {} is not a sell", result.signature));
+        }
+        Ok(Self::update(state, result, |position| {
+            position.apply_sell(-result.tokens_received, -result.sol_spent)
+        }))
+    }
+
+    /// Apply results released by the sequence buffer in order, returning the outcome of the
+    /// result with `signature` when it is among them
+    fn apply_released(
+        state: &mut PositionState,
+        released: ReleasedResults,
+        signature: &str,
+    ) -> Option<Result<bool>> {
+        let mut outcome = None;
+        for result in &released.results {
+            let applied = if result.tokens_received < 0.0 {
+                Self::sell(state, result)
+            } else {
+                Self::buy(state, result)
+            };
+            if result.signature == signature {
+                outcome = Some(applied);
+            } else if let Err(e) = applied {
+                log::warn!(icon!("⚠️ ", "Position of {} not updated: {}"), result.token_mint, e);
+            }
+        }
+        if released.needs_reconciliation {
+            if let Some(position) = state.positions.get_mut(&released.token_mint) {
+                position.needs_reconciliation = true;
+            }
+        }
+        outcome
+    }
+
+    fn update(
+        state: &mut PositionState,
+        result: &TradeResult,
+        apply: impl FnOnce(&mut Position),
+    ) -> bool {
+        if !state.recorded.insert(result.signature.clone()) {
+            return false;
+        }
+        let position = state
+            .positions
+            .entry(result.token_mint.clone())
+            .or_insert_with(|| Position::new(&result.token_mint));
+        apply(position);
+        if let Some(slot) = result.slot {
+            position.last_update_slot = position.last_update_slot.max(slot);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::signature::Signature;
+
+    use super::*;
+    use crate::trading::core::trade_result::RawTradeAmounts;
+
+    fn trade(mint: &Pubkey, sequence: u64, tokens_received: f64, sol_spent: f64) -> TradeResult {
+        let mut result =
+            TradeResult::pending(&Signature::new_unique(), mint, &Pubkey::new_unique());
+        result.analysis_pending = false;
+        result.tokens_received = tokens_received;
+        result.sol_spent = sol_spent;
+        result.sequence = Some(sequence);
+        result.slot = Some(100 + sequence);
+        result
+    }
+
+    /// Every order of `0..n`
+    fn permutations(n: usize) -> Vec<Vec<usize>> {
+        if n == 0 {
+            return vec![vec![]];
+        }
+        let mut orders = Vec::new();
+        for order in permutations(n - 1) {
+            for index in 0..n {
+                let mut order = order.clone();
+                order.insert(index, n - 1);
+                orders.push(order);
+            }
+        }
+        orders
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn interleaved_results_apply_in_submission_order() {
+        let mint = Pubkey::new_unique();
+        let trades = [
+            trade(&mint, 1, 100.0, 1.0),
+            trade(&mint, 2, 100.0, 3.0),
+            trade(&mint, 3, -100.0, -4.0),
+            trade(&mint, 4, -50.0, -1.5),
+        ];
+        let orders = permutations(trades.len());
+        assert_eq!(orders.len(), 24);
+        for order in orders {
+            let tracker = PositionTracker::new(Duration::from_secs(60));
+            for index in &order {
+                tracker.record(&trades[*index]).unwrap();
+            }
+            let position = tracker.get_position(&mint).unwrap();
+            assert_close(position.tokens, 50.0);
+            assert_close(position.cost_basis_sol, 1.0);
+            assert_close(position.average_entry_price, 0.02);
+            assert_close(position.realized_pnl_sol, 2.5);
+            assert_eq!((position.buy_count, position.sell_count), (2, 2));
+            assert_eq!(position.last_update_slot, 104);
+            assert!(!position.needs_reconciliation, "order {:?}", order);
+        }
+    }
+
+    #[test]
+    fn results_wait_for_earlier_sequences() {
+        let mint = Pubkey::new_unique();
+        let tracker = PositionTracker::new(Duration::from_secs(60));
+        let sell = trade(&mint, 2, -100.0, -2.0);
+        assert!(!tracker.record(&sell).unwrap());
+        assert!(tracker.get_position(&mint).is_none());
+
+        assert!(tracker.record(&trade(&mint, 1, 100.0, 1.0)).unwrap());
+        let position = tracker.get_position(&mint).unwrap();
+        assert!(position.is_closed());
+        assert_close(position.realized_pnl_sol, 1.0);
+        // The result returned by the trade and delivered again by the analysis callback
+        assert!(!tracker.record(&sell).unwrap());
+        assert_eq!(tracker.get_position(&mint).unwrap().sell_count, 1);
+    }
+
+    #[test]
+    fn missing_sequences_time_out_and_reconcile_from_fills() {
+        let mint = Pubkey::new_unique();
+        let tracker = PositionTracker::new(Duration::ZERO);
+        let buy = trade(&mint, 1, 100.0, 1.0);
+        let sell = trade(&mint, 2, -40.0, -0.8);
+
+        // The buy's result never arrives, the sell is applied once the gap times out
+        assert!(tracker.record(&sell).unwrap());
+        let position = tracker.get_position(&mint).unwrap();
+        assert!(position.needs_reconciliation);
+        assert_close(position.realized_pnl_sol, 0.0);
+
+        let fill = |result: &TradeResult, kind, token_delta, sol_delta_lamports| LedgerEntry {
+            signature: result.signature.clone(),
+            wallet: result.wallet_address.clone(),
+            kind,
+            slot: result.slot.unwrap(),
+            block_time: 1_700_000_000,
+            protocol: None,
+            token_mint: Some(mint.to_string()),
+            amounts: RawTradeAmounts { sol_delta_lamports, token_delta, ..Default::default() },
+            commission_lamports: 0,
+            labels: None,
+        };
+        let fills = [
+            fill(&sell, LedgerEntryKind::Sell, -40_000_000, 800_000_000),
+            fill(&buy, LedgerEntryKind::Buy, 100_000_000, -1_000_000_000),
+            fill(&trade(&mint, 3, 0.0, 0.0), LedgerEntryKind::Failed, 0, -5_000),
+        ];
+        let position = tracker.reconcile_fills(&mint, &fills, 6);
+        assert!(!position.needs_reconciliation);
+        assert_close(position.tokens, 60.0);
+        assert_close(position.average_entry_price, 0.01);
+        assert_close(position.realized_pnl_sol, 0.4);
+        assert_eq!(position.last_update_slot, 102);
+        assert_eq!(tracker.get_position(&mint), Some(position));
+
+        // The late result of a reconciled trade is not applied again
+        assert!(!tracker.record(&buy).unwrap());
+        assert_close(tracker.get_position(&mint).unwrap().tokens, 60.0);
+    }
+
+    #[test]
+    fn flush_expired_applies_held_back_results() {
+        let mint = Pubkey::new_unique();
+        let tracker = PositionTracker::new(Duration::from_millis(10));
+        assert!(!tracker.record(&trade(&mint, 2, 100.0, 1.0)).unwrap());
+        assert_eq!(tracker.flush_expired(), 0);
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(tracker.flush_expired(), 1);
+        let position = tracker.get_position(&mint).unwrap();
+        assert_close(position.tokens, 100.0);
+        assert!(position.needs_reconciliation);
+    }
+}
@@ -0,0 +1,300 @@
+//! Current prices read from the bonding curve and pool accounts
+//!
+//! `market::price` prices the state held in protocol params; the functions here load that
+//! state themselves, for dashboards and monitors that never build a trade. Prices are in SOL,
+//! so pools must be quoted in WSOL. `get_prices` answers many tokens with one
+//! `getMultipleAccounts` call for the bonding curves and pool states and, when pools are among
+//! them, a second one for their vaults.
+
+use anyhow::{anyhow, Result};
+use futures::future::try_join_all;
+use solana_sdk::{account::Account, pubkey::Pubkey};
+use spl_token_2022::{extension::StateWithExtensions, state::Mint};
+
+use crate::{
+    common::{
+        account_fetch::{
+            check_owner, token_account_amount, AccountFetchError, AccountFetchFailure,
+        },
+        mint_cache::MintDecimalsCache,
+        rpc_race, SolanaRpcClient,
+    },
+    constants::{
+        decimals::{DEFAULT_TOKEN_DECIMALS, SOL_DECIMALS},
+        WSOL_TOKEN_ACCOUNT,
+    },
+    instruction::utils::{bonk, pumpfun, pumpswap, raydium_amm_v4, raydium_cpmm},
+    trading::factory::DexType,
+    utils::price,
+};
+
+/// Most accounts one `getMultipleAccounts` call loads, larger batches are split
+const MAX_ACCOUNTS_PER_REQUEST: usize = 100;
+
+/// Spot price of a token and the reserves it was computed from
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceInfo {
+    pub dex_type: DexType,
+    pub mint: Pubkey,
+    /// Bonding curve or pool the reserves were read from
+    pub pool: Pubkey,
+    /// SOL per whole token
+    pub price_sol: f64,
+    /// SOL side of the reserves in lamports, the virtual reserves on bonding curves
+    pub sol_reserve: u64,
+    /// Token side of the reserves in base units, the virtual reserves on bonding curves
+    pub token_reserve: u64,
+    pub token_decimals: u8,
+    /// Slot of the RPC response the reserves were read from
+    pub slot: u64,
+}
+
+/// Price of a token on `dex_type`
+///
+/// `address` is the mint for PumpFun and Bonk, whose bonding curve and WSOL pool are derived
+/// from it, and the pool for PumpSwap, Raydium CPMM and Raydium AMM V4. Other protocols fail.
+pub async fn get_price(
+    rpc: &SolanaRpcClient,
+    dex_type: DexType,
+    address: Pubkey,
+) -> Result<PriceInfo> {
+    get_prices(rpc, vec![(dex_type, address)]).await?.remove(0)
+}
+
+/// Prices of many tokens in the order of `queries`, each addressed as for `get_price`
+///
+/// Takes one round trip when only bonding curves and Bonk pools are queried and two when AMM
+/// pools are among them. A query that cannot be priced, e.g. an unknown pool, gets its own
+/// error; the call only fails when the RPC does.
+pub async fn get_prices(
+    rpc: &SolanaRpcClient,
+    queries: Vec<(DexType, Pubkey)>,
+) -> Result<Vec<Result<PriceInfo>>> {
+    let states: Vec<Result<Pubkey>> =
+        queries.iter().map(|(dex_type, address)| state_account(dex_type, address)).collect();
+    let to_load: Vec<Pubkey> =
+        states.iter().filter_map(|state| state.as_ref().ok()).copied().collect();
+    let mut loaded = load_accounts(rpc, &to_load).await?.into_iter();
+    let stages: Vec<Result<Stage>> = queries
+        .into_iter()
+        .zip(states)
+        .map(|((dex_type, address), state)| {
+            let state = state?;
+            let (account, slot) =
+                loaded.next().ok_or_else(|| anyhow!("RPC returned fewer accounts than asked"))?;
+            read_state(dex_type, address, state, account, slot)
+        })
+        .collect();
+
+    let vault_loads: Vec<Vec<Pubkey>> = stages
+        .iter()
+        .map(|stage| match stage {
+            Ok(Stage::Vaults(pool)) => pool.accounts_to_load(),
+            _ => Vec::new(),
+        })
+        .collect();
+    let to_load: Vec<Pubkey> = vault_loads.iter().flatten().copied().collect();
+    let mut loaded = load_accounts(rpc, &to_load).await?.into_iter();
+    Ok(stages
+        .into_iter()
+        .zip(vault_loads)
+        .map(|(stage, addresses)| match stage? {
+            Stage::Priced(info) => Ok(info),
+            Stage::Vaults(pool) => pool.price(loaded.by_ref().take(addresses.len()).collect()),
+        })
+        .collect())
+}
+
+/// A query once its bonding curve or pool state was read
+enum Stage {
+    Priced(PriceInfo),
+    /// AMM pool whose reserves are the balances of its vaults
+    Vaults(PoolVaults),
+}
+
+struct PoolVaults {
+    dex_type: DexType,
+    pool: Pubkey,
+    mint: Pubkey,
+    token_vault: Pubkey,
+    sol_vault: Pubkey,
+    /// From the `MintDecimalsCache`, the mint is loaded with the vaults when not cached
+    token_decimals: Option<u8>,
+}
+
+impl PoolVaults {
+    /// Pool trading `(mint, vault)` side `a` against side `b`, one of them WSOL
+    fn new(
+        dex_type: DexType,
+        pool: Pubkey,
+        side_a: (Pubkey, Pubkey),
+        side_b: (Pubkey, Pubkey),
+    ) -> Result<Self> {
+        let ((mint, token_vault), (_, sol_vault)) = if side_b.0 == WSOL_TOKEN_ACCOUNT {
+            (side_a, side_b)
+        } else if side_a.0 == WSOL_TOKEN_ACCOUNT {
+            (side_b, side_a)
+        } else {
+            return Err(anyhow!("Pool {} is not quoted in SOL", pool));
+        };
+        let token_decimals = MintDecimalsCache::get_instance().get(&mint);
+        Ok(Self { dex_type, pool, mint, token_vault, sol_vault, token_decimals })
+    }
+
+    fn accounts_to_load(&self) -> Vec<Pubkey> {
+        let mut accounts = vec![self.token_vault, self.sol_vault];
+        if self.token_decimals.is_none() {
+            accounts.push(self.mint);
+        }
+        accounts
+    }
+
+    /// Price from the accounts of `accounts_to_load`, in the same order
+    fn price(self, loaded: Vec<(Option<Account>, u64)>) -> Result<PriceInfo> {
+        let slot = loaded.iter().map(|(_, slot)| *slot).min().unwrap_or_default();
+        let mut accounts = loaded.into_iter().map(|(account, _)| account);
+        let token_vault = found("token vault", self.token_vault, accounts.next().flatten())?;
+        let sol_vault = found("sol vault", self.sol_vault, accounts.next().flatten())?;
+        let token_reserve = token_account_amount("token vault", &self.token_vault, &token_vault)?;
+        let sol_reserve = token_account_amount("sol vault", &self.sol_vault, &sol_vault)?;
+        let token_decimals = match self.token_decimals {
+            Some(decimals) => decimals,
+            None => {
+                let mint = found("mint", self.mint, accounts.next().flatten())?;
+                let decimals = mint_decimals(&self.mint, &mint)?;
+                MintDecimalsCache::get_instance().insert(self.mint, decimals);
+                decimals
+            }
+        };
+        Ok(PriceInfo {
+            dex_type: self.dex_type,
+            mint: self.mint,
+            pool: self.pool,
+            price_sol: price::common::price_base_in_quote(
+                token_reserve,
+                sol_reserve,
+                token_decimals,
+                SOL_DECIMALS,
+            ),
+            sol_reserve,
+            token_reserve,
+            token_decimals,
+            slot,
+        })
+    }
+}
+
+/// Bonding curve or pool account holding the reserves the price of `address` comes from
+fn state_account(dex_type: &DexType, address: &Pubkey) -> Result<Pubkey> {
+    match dex_type {
+        DexType::PumpFun => pumpfun::get_bonding_curve_pda(address)
+            .ok_or_else(|| anyhow!("Failed to derive the bonding curve of {}", address)),
+        DexType::Bonk => bonk::get_pool_pda(address, &WSOL_TOKEN_ACCOUNT)
+            .ok_or_else(|| anyhow!("Failed to derive the Bonk pool of {}", address)),
+        DexType::PumpSwap | DexType::RaydiumCpmm | DexType::RaydiumAmmV4 => Ok(*address),
+        _ => Err(anyhow!("Prices of {:?} pools are not supported", dex_type)),
+    }
+}
+
+fn read_state(
+    dex_type: DexType,
+    address: Pubkey,
+    state: Pubkey,
+    account: Option<Account>,
+    slot: u64,
+) -> Result<Stage> {
+    let role = if dex_type == DexType::PumpFun { "bonding curve" } else { "pool" };
+    let account = found(role, state, account)?;
+    check_owner(role, &state, &account, &dex_type.program_id())?;
+    match dex_type {
+        DexType::PumpFun => {
+            let curve = pumpfun::decode_bonding_curve(&account.data)?;
+            Ok(Stage::Priced(PriceInfo {
+                dex_type,
+                mint: address,
+                pool: state,
+                price_sol: price::pumpfun::price_token_in_sol_with_bonding_curve(&curve),
+                sol_reserve: curve.virtual_sol_reserves,
+                token_reserve: curve.virtual_token_reserves,
+                token_decimals: DEFAULT_TOKEN_DECIMALS,
+                slot,
+            }))
+        }
+        DexType::Bonk => {
+            let pool = bonk::decode_pool_account(&state, &account)?;
+            Ok(Stage::Priced(PriceInfo {
+                dex_type,
+                mint: address,
+                pool: state,
+                price_sol: price::bonk::price_token_in_wsol_with_pool_state(&pool),
+                sol_reserve: pool.virtual_quote.saturating_add(pool.real_quote),
+                token_reserve: pool.virtual_base.saturating_sub(pool.real_base),
+                token_decimals: pool.base_decimals,
+                slot,
+            }))
+        }
+        DexType::PumpSwap => {
+            let pool = pumpswap::decode_pool_account(&state, &account)?;
+            PoolVaults::new(
+                dex_type,
+                state,
+                (pool.base_mint, pool.pool_base_token_account),
+                (pool.quote_mint, pool.pool_quote_token_account),
+            )
+            .map(Stage::Vaults)
+        }
+        DexType::RaydiumCpmm => {
+            let pool = raydium_cpmm::decode_pool_account(&state, &account)?;
+            PoolVaults::new(
+                dex_type,
+                state,
+                (pool.token0_mint, pool.token0_vault),
+                (pool.token1_mint, pool.token1_vault),
+            )
+            .map(Stage::Vaults)
+        }
+        DexType::RaydiumAmmV4 => {
+            let amm_info = raydium_amm_v4::decode_amm_account(&state, &account)?;
+            PoolVaults::new(
+                dex_type,
+                state,
+                (amm_info.coin_mint, amm_info.token_coin),
+                (amm_info.pc_mint, amm_info.token_pc),
+            )
+            .map(Stage::Vaults)
+        }
+        _ => Err(anyhow!("Prices of {:?} pools are not supported", dex_type)),
+    }
+}
+
+/// Accounts of `addresses` with the slot each was read at, split into calls of at most
+/// `MAX_ACCOUNTS_PER_REQUEST` accounts sent at once
+async fn load_accounts(
+    rpc: &SolanaRpcClient,
+    addresses: &[Pubkey],
+) -> Result<Vec<(Option<Account>, u64)>> {
+    let chunks = try_join_all(
+        addresses
+            .chunks(MAX_ACCOUNTS_PER_REQUEST)
+            .map(|chunk| rpc_race::get_multiple_accounts_and_slot(rpc, chunk)),
+    )
+    .await?;
+    Ok(chunks
+        .into_iter()
+        .flat_map(|(accounts, slot)| accounts.into_iter().map(move |account| (account, slot)))
+        .collect())
+}
+
+fn found(
+    role: &'static str,
+    address: Pubkey,
+    account: Option<Account>,
+) -> Result<Account, AccountFetchError> {
+    account.ok_or_else(|| AccountFetchError::new(role, address, AccountFetchFailure::NotFound))
+}
+
+fn mint_decimals(mint: &Pubkey, account: &Account) -> Result<u8, AccountFetchError> {
+    StateWithExtensions::<Mint>::unpack(&account.data).map(|state| state.base.decimals).map_err(
+        |e| AccountFetchError::new("mint", *mint, AccountFetchFailure::Invalid(e.to_string())),
+    )
+}
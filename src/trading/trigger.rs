@@ -0,0 +1,774 @@
+use crate::common::labels::{validate_labels, TradeLabels};
+use crate::common::store::KeyValueStore;
+use crate::instruction::utils::{bonk, pumpfun, pumpswap};
+use crate::solana_streamer_sdk::streaming::event_parser::protocols::pumpfun::PumpFunTradeEvent;
+use crate::trading::core::options::{BuyOptions, SellOptions};
+use crate::params::{
+    BonkParams, PumpFunParams, PumpSwapParams, RaydiumAmmV4Params, RaydiumCpmmParams,
+};
+use crate::trading::core::traits::ProtocolParams;
+use crate::trading::factory::DexType;
+use crate::utils::price;
+use crate::SolanaTrade;
+use anyhow::anyhow;
+use futures::future::join_all;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+const STORE_KEY: &str = "trigger_orders";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TriggerSide {
+    Buy,
+    Sell,
+}
+
+/// Condition that fires a trigger order, prices are in SOL per token
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TriggerCondition {
+    PriceBelow(f64),
+    PriceAbove(f64),
+    /// PumpFun bonding curve progress in percent
+    CurveProgressAbove(f64),
+}
+
+impl TriggerCondition {
+    pub fn is_met(&self, observation: &MarketObservation) -> bool {
+        match *self {
+            TriggerCondition::PriceBelow(price) => {
+                observation.price > 0.0 && observation.price < price
+            }
+            TriggerCondition::PriceAbove(price) => observation.price > price,
+            TriggerCondition::CurveProgressAbove(pct) => {
+                observation.curve_progress.map_or(false, |progress| progress > pct)
+            }
+        }
+    }
+}
+
+/// Where protocol params are fetched from when an order fires
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ParamsSource {
+    /// Bonding curve of the order's mint
+    PumpFunBondingCurve,
+    PumpSwapPool(Pubkey),
+    /// Bonk pool of the order's mint
+    BonkPool,
+    RaydiumCpmmPool(Pubkey),
+    RaydiumAmmV4Pool(Pubkey),
+}
+
+impl ParamsSource {
+    async fn fetch(
+        &self,
+        trade: &SolanaTrade,
+        mint: &Pubkey,
+    ) -> Result<Box<dyn ProtocolParams>, anyhow::Error> {
+        let rpc = &trade.rpc;
+        Ok(match self {
+            ParamsSource::PumpFunBondingCurve => {
+                Box::new(PumpFunParams::from_mint_by_rpc(rpc, mint).await?)
+            }
+            ParamsSource::PumpSwapPool(pool) => {
+                Box::new(PumpSwapParams::from_pool_address_by_rpc(rpc, pool).await?)
+            }
+            ParamsSource::BonkPool => Box::new(
+                BonkParams::from_mint_and_quote_by_rpc(
+                    rpc,
+                    mint,
+                    &crate::constants::WSOL_TOKEN_ACCOUNT,
+                )
+                .await?,
+            ),
+            ParamsSource::RaydiumCpmmPool(pool) => {
+                Box::new(RaydiumCpmmParams::from_pool_address_by_rpc(rpc, pool).await?)
+            }
+            ParamsSource::RaydiumAmmV4Pool(amm) => {
+                Box::new(RaydiumAmmV4Params::from_amm_address_by_rpc(rpc, *amm).await?)
+            }
+        })
+    }
+
+    /// Poll the current market state, `None` when polling is not supported for this source
+    async fn observe(
+        &self,
+        trade: &SolanaTrade,
+        mint: &Pubkey,
+    ) -> Result<Option<MarketObservation>, anyhow::Error> {
+        let rpc = &trade.rpc;
+        match self {
+            ParamsSource::PumpFunBondingCurve => {
+                let (bonding_curve, _) = pumpfun::fetch_bonding_curve_account(rpc, mint).await?;
+                Ok(Some(MarketObservation {
+                    price: price::pumpfun::price_token_in_sol_with_bonding_curve(&bonding_curve),
+                    curve_progress: Some(price::pumpfun::curve_progress(
+                        bonding_curve.real_token_reserves,
+                    )),
+                }))
+            }
+            ParamsSource::PumpSwapPool(pool_address) => {
+                let pool = pumpswap::fetch_pool(rpc, pool_address).await?;
+                let (base_amount, quote_amount) = pumpswap::get_token_balances(&pool, rpc).await?;
+                let base_decimals = rpc.get_token_supply(&pool.base_mint).await?.decimals;
+                Ok(Some(MarketObservation {
+                    price: price::pumpswap::price_base_in_quote(
+                        base_amount,
+                        quote_amount,
+                        base_decimals,
+                        9,
+                    ),
+                    curve_progress: None,
+                }))
+            }
+            ParamsSource::BonkPool => {
+                let pool_address = bonk::get_pool_pda(mint, &crate::constants::WSOL_TOKEN_ACCOUNT)
+                    .ok_or_else(|| anyhow!("Failed to derive Bonk pool"))?;
+                let pool_state = bonk::fetch_pool_state(rpc, &pool_address).await?;
+                Ok(Some(MarketObservation {
+                    price: price::bonk::price_token_in_wsol_with_pool_state(&pool_state),
+                    curve_progress: None,
+                }))
+            }
+            ParamsSource::RaydiumCpmmPool(_) | ParamsSource::RaydiumAmmV4Pool(_) => Ok(None),
+        }
+    }
+}
+
+/// A conditional order executed by the `TriggerEngine`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerOrder {
+    pub mint: Pubkey,
+    pub dex_type: DexType,
+    pub side: TriggerSide,
+    pub trigger: TriggerCondition,
+    /// Lamports to spend for buys, token amount to sell for sells
+    pub amount: u64,
+    pub slippage_basis_points: Option<u64>,
+    /// Unix timestamp in seconds after which the order expires
+    pub expiry: Option<u64>,
+    pub params_source: ParamsSource,
+    /// Labels of the trade the order sends, also reported on its `Executed` and `Failed` events
+    #[serde(default)]
+    pub labels: Option<TradeLabels>,
+}
+
+impl TriggerOrder {
+    pub fn buy(
+        mint: Pubkey,
+        dex_type: DexType,
+        trigger: TriggerCondition,
+        sol_amount: u64,
+        params_source: ParamsSource,
+    ) -> Self {
+        Self {
+            mint,
+            dex_type,
+            side: TriggerSide::Buy,
+            trigger,
+            amount: sol_amount,
+            slippage_basis_points: None,
+            expiry: None,
+            params_source,
+            labels: None,
+        }
+    }
+
+    pub fn sell(
+        mint: Pubkey,
+        dex_type: DexType,
+        trigger: TriggerCondition,
+        token_amount: u64,
+        params_source: ParamsSource,
+    ) -> Self {
+        Self {
+            mint,
+            dex_type,
+            side: TriggerSide::Sell,
+            trigger,
+            amount: token_amount,
+            slippage_basis_points: None,
+            expiry: None,
+            params_source,
+            labels: None,
+        }
+    }
+
+    pub fn with_slippage(mut self, slippage_basis_points: u64) -> Self {
+        self.slippage_basis_points = Some(slippage_basis_points);
+        self
+    }
+
+    /// Label the trade the order sends, see `BuyOptions::with_label`
+    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.get_or_insert_with(TradeLabels::new).insert(key.into(), value.into());
+        self
+    }
+
+    /// Expire the order `duration` from now
+    pub fn expires_in(mut self, duration: Duration) -> Self {
+        self.expiry = Some(unix_now() + duration.as_secs());
+        self
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expiry.map_or(false, |expiry| now >= expiry)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TriggerOrderStatus {
+    /// Waiting for the condition
+    Open,
+    /// Condition met and the trade was handed to the executor; never fired again
+    Triggered,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredTriggerOrder {
+    pub id: u64,
+    pub order: TriggerOrder,
+    pub status: TriggerOrderStatus,
+}
+
+/// Current market state of a mint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarketObservation {
+    /// SOL per token
+    pub price: f64,
+    /// PumpFun bonding curve progress in percent
+    pub curve_progress: Option<f64>,
+}
+
+/// Lifecycle events of trigger orders
+#[derive(Debug, Clone)]
+pub enum TriggerEvent {
+    Registered { id: u64, order: TriggerOrder },
+    Triggered { id: u64, observation: MarketObservation },
+    Executed { id: u64, signature: Signature, labels: Option<TradeLabels> },
+    Failed { id: u64, error: String, labels: Option<TradeLabels> },
+    Expired { id: u64 },
+    Cancelled { id: u64 },
+}
+
+pub type TriggerEventCallback = Arc<dyn Fn(TriggerEvent) + Send + Sync>;
+
+/// Executes trigger (limit) orders when their condition holds
+///
+/// Feed prices with `observe` / `observe_pumpfun_trade` from a trade event subscription, or
+/// call `start_polling` to poll pools over RPC. Each order fires at most once: it is marked
+/// `Triggered` and persisted before the trade is sent, and triggered orders are never fired
+/// again, even after a restart.
+pub struct TriggerEngine {
+    pub(crate) trade: Arc<SolanaTrade>,
+    orders: Mutex<HashMap<u64, StoredTriggerOrder>>,
+    next_id: AtomicU64,
+    store: Option<Arc<dyn KeyValueStore>>,
+    event_callback: Option<TriggerEventCallback>,
+    /// Orders a previous run triggered but did not finish, reported once a callback is set
+    interrupted: Vec<StoredTriggerOrder>,
+}
+
+impl TriggerEngine {
+    pub fn new(trade: Arc<SolanaTrade>) -> Self {
+        Self {
+            trade,
+            orders: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            store: None,
+            event_callback: None,
+            interrupted: Vec::new(),
+        }
+    }
+
+    /// Persist orders through `store`, loading the open orders stored by a previous run
+    ///
+    /// Orders stored as triggered were interrupted between firing and reporting the outcome,
+    /// their trade may or may not have been sent. They are dropped and reported as `Failed`,
+    /// to the event callback when one is set, now or later.
+    pub fn with_store(mut self, store: Arc<dyn KeyValueStore>) -> Result<Self, anyhow::Error> {
+        if let Some(value) = store.get(STORE_KEY) {
+            let mut orders: HashMap<u64, StoredTriggerOrder> = serde_json::from_str(&value)
+                .map_err(|e| anyhow!("Failed to parse stored trigger orders: {}", e))?;
+            let next_id = orders.keys().max().map_or(1, |id| id + 1);
+            let interrupted: Vec<StoredTriggerOrder> = orders
+                .values()
+                .filter(|stored| stored.status == TriggerOrderStatus::Triggered)
+                .cloned()
+                .collect();
+            if !interrupted.is_empty() {
+                orders.retain(|_, stored| stored.status == TriggerOrderStatus::Open);
+                store.put(STORE_KEY, serde_json::to_string(&orders)?)?;
+            }
+            self.next_id = AtomicU64::new(next_id);
+            self.orders = Mutex::new(orders);
+            self.interrupted.extend(interrupted);
+        }
+        self.store = Some(store);
+        self.report_interrupted();
+        Ok(self)
+    }
+
+    pub fn with_event_callback(mut self, callback: TriggerEventCallback) -> Self {
+        self.event_callback = Some(callback);
+        self.report_interrupted();
+        self
+    }
+
+    fn report_interrupted(&mut self) {
+        if self.event_callback.is_none() {
+            return;
+        }
+        let mut interrupted = std::mem::take(&mut self.interrupted);
+        interrupted.sort_by_key(|stored| stored.id);
+        for stored in interrupted {
+            self.emit(TriggerEvent::Failed {
+                id: stored.id,
+                error: "Interrupted after the order triggered, its trade may have been sent"
+                    .to_string(),
+                labels: stored.order.labels,
+            });
+        }
+    }
+
+    /// Register an order, returns its id
+    pub fn register(&self, order: TriggerOrder) -> Result<u64, anyhow::Error> {
+        if order.amount == 0 {
+            return Err(anyhow!("Amount cannot be zero"));
+        }
+        if order.is_expired(unix_now()) {
+            return Err(anyhow!("Order is already expired"));
+        }
+        if let Some(labels) = &order.labels {
+            validate_labels(labels)?;
+        }
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        {
+            let mut orders = self.orders.lock();
+            orders.insert(
+                id,
+                StoredTriggerOrder { id, order: order.clone(), status: TriggerOrderStatus::Open },
+            );
+            self.persist(&orders)?;
+        }
+        self.emit(TriggerEvent::Registered { id, order });
+        Ok(id)
+    }
+
+    /// Cancel an order, returns false if it does not exist or already triggered
+    pub fn cancel(&self, id: u64) -> Result<bool, anyhow::Error> {
+        {
+            let mut orders = self.orders.lock();
+            match orders.get(&id) {
+                Some(stored) if stored.status == TriggerOrderStatus::Open => {}
+                _ => return Ok(false),
+            }
+            orders.remove(&id);
+            self.persist(&orders)?;
+        }
+        self.emit(TriggerEvent::Cancelled { id });
+        Ok(true)
+    }
+
+    /// Orders still waiting for their condition
+    pub fn open_orders(&self) -> Vec<StoredTriggerOrder> {
+        let mut open: Vec<StoredTriggerOrder> = self
+            .orders
+            .lock()
+            .values()
+            .filter(|stored| stored.status == TriggerOrderStatus::Open)
+            .cloned()
+            .collect();
+        open.sort_by_key(|stored| stored.id);
+        open
+    }
+
+    /// Feed a market observation, executing every open order on `mint` whose condition holds
+    pub async fn observe(&self, mint: &Pubkey, observation: MarketObservation) {
+        self.expire_orders();
+        let fired = self.claim(|stored| {
+            stored.order.mint == *mint && stored.order.trigger.is_met(&observation)
+        });
+        let executions = fired.into_iter().map(|stored| {
+            self.emit(TriggerEvent::Triggered { id: stored.id, observation });
+            self.execute(stored)
+        });
+        join_all(executions).await;
+    }
+
+    /// Feed a PumpFun trade event from a stream subscription
+    pub async fn observe_pumpfun_trade(&self, event: &PumpFunTradeEvent) {
+        let observation = MarketObservation {
+            price: price::pumpfun::price_token_in_sol(
+                event.virtual_sol_reserves,
+                event.virtual_token_reserves,
+            ),
+            curve_progress: Some(price::pumpfun::curve_progress(event.real_token_reserves)),
+        };
+        self.observe(&event.mint, observation).await;
+    }
+
+    /// Poll every mint with open orders once
+    ///
+    /// Raydium pools are not polled, feed them with `observe`.
+    pub async fn poll_once(&self) {
+        self.expire_orders();
+        let mut seen = HashSet::new();
+        let sources: Vec<(Pubkey, ParamsSource)> = self
+            .open_orders()
+            .into_iter()
+            .map(|stored| (stored.order.mint, stored.order.params_source))
+            .filter(|source| seen.insert(*source))
+            .collect();
+        for (mint, source) in sources {
+            match source.observe(&self.trade, &mint).await {
+                Ok(Some(observation)) => self.observe(&mint, observation).await,
+                Ok(None) => {}
+                Err(e) => log::warn!("Failed to poll price of {}: {}", mint, e),
+            }
+        }
+    }
+
+    /// Poll pools every `interval` in a background task
+    pub fn start_polling(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let engine = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                engine.poll_once().await;
+            }
+        })
+    }
+
+    /// Mark matching open orders as triggered and persist them before anything is sent
+    fn claim(&self, matches: impl Fn(&StoredTriggerOrder) -> bool) -> Vec<StoredTriggerOrder> {
+        let mut orders = self.orders.lock();
+        let mut fired = Vec::new();
+        for stored in orders.values_mut() {
+            if stored.status == TriggerOrderStatus::Open && matches(stored) {
+                stored.status = TriggerOrderStatus::Triggered;
+                fired.push(stored.clone());
+            }
+        }
+        if !fired.is_empty() {
+            if let Err(e) = self.persist(&orders) {
+                // Without the triggered state on disk a restart could fire these orders again,
+                // they stay open for a later observation instead
+                log::error!("Failed to persist triggered orders, not executing them: {}", e);
+                for stored in &fired {
+                    if let Some(order) = orders.get_mut(&stored.id) {
+                        order.status = TriggerOrderStatus::Open;
+                    }
+                }
+                return Vec::new();
+            }
+        }
+        fired
+    }
+
+    async fn execute(&self, stored: StoredTriggerOrder) {
+        let id = stored.id;
+        let labels = stored.order.labels.clone();
+        let event = match self.send(&stored.order).await {
+            Ok(signature) => TriggerEvent::Executed { id, signature, labels },
+            Err(e) => TriggerEvent::Failed { id, error: e.to_string(), labels },
+        };
+        {
+            let mut orders = self.orders.lock();
+            orders.remove(&id);
+            if let Err(e) = self.persist(&orders) {
+                log::error!("Failed to persist trigger orders: {}", e);
+            }
+        }
+        self.emit(event);
+    }
+
+    async fn send(&self, order: &TriggerOrder) -> Result<Signature, anyhow::Error> {
+        let params = order.params_source.fetch(&self.trade, &order.mint).await?;
+        let recent_blockhash = self.trade.rpc.get_latest_blockhash().await?;
+        let handle_wsol = order.dex_type != DexType::PumpFun;
+        match order.side {
+            TriggerSide::Buy => {
+                let options = BuyOptions {
+                    slippage_basis_points: order.slippage_basis_points,
+                    labels: order.labels.clone(),
+                    ..BuyOptions::default()
+                }
+                .with_wsol_ata(handle_wsol, handle_wsol);
+                self.trade
+                    .buy_with_options(
+                        order.dex_type.clone(),
+                        order.mint,
+                        order.amount,
+                        recent_blockhash,
+                        params,
+                        options,
+                    )
+                    .await
+            }
+            TriggerSide::Sell => {
+                let options = SellOptions {
+                    slippage_basis_points: order.slippage_basis_points,
+                    labels: order.labels.clone(),
+                    ..SellOptions::default()
+                }
+                .with_wsol_ata(handle_wsol, handle_wsol);
+                self.trade
+                    .sell_with_options(
+                        order.dex_type.clone(),
+                        order.mint,
+                        order.amount,
+                        recent_blockhash,
+                        params,
+                        options,
+                    )
+                    .await
+            }
+        }
+    }
+
+    fn expire_orders(&self) {
+        let now = unix_now();
+        let expired: Vec<u64> = {
+            let mut orders = self.orders.lock();
+            let expired: Vec<u64> = orders
+                .values()
+                .filter(|stored| {
+                    stored.status == TriggerOrderStatus::Open && stored.order.is_expired(now)
+                })
+                .map(|stored| stored.id)
+                .collect();
+            if expired.is_empty() {
+                return;
+            }
+            for id in &expired {
+                orders.remove(id);
+            }
+            if let Err(e) = self.persist(&orders) {
+                log::error!("Failed to persist trigger orders: {}", e);
+            }
+            expired
+        };
+        for id in expired {
+            self.emit(TriggerEvent::Expired { id });
+        }
+    }
+
+    fn persist(&self, orders: &HashMap<u64, StoredTriggerOrder>) -> Result<(), anyhow::Error> {
+        match &self.store {
+            Some(store) => store.put(STORE_KEY, serde_json::to_string(orders)?),
+            None => Ok(()),
+        }
+    }
+
+    fn emit(&self, event: TriggerEvent) {
+        if let Some(callback) = &self.event_callback {
+            callback(event);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{fake_chain::FakeChain, store::MemoryStore};
+    use std::sync::atomic::AtomicBool;
+
+    /// Memory store whose writes fail while `failing` is set
+    #[derive(Default)]
+    struct FlakyStore {
+        inner: MemoryStore,
+        failing: AtomicBool,
+    }
+
+    impl KeyValueStore for FlakyStore {
+        fn get(&self, key: &str) -> Option<String> {
+            self.inner.get(key)
+        }
+
+        fn put(&self, key: &str, value: String) -> Result<(), anyhow::Error> {
+            if self.failing.load(Ordering::SeqCst) {
+                return Err(anyhow!("disk full"));
+            }
+            self.inner.put(key, value)
+        }
+    }
+
+    /// Engine on an RPC serving no accounts, so every fired order fails to fetch its params
+    fn engine() -> (TriggerEngine, Arc<Mutex<Vec<TriggerEvent>>>) {
+        let (rpc, _) = FakeChain::default().client();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let engine = TriggerEngine::new(Arc::new(SolanaTrade::offline(rpc)))
+            .with_event_callback(Arc::new(move |event| sink.lock().push(event)));
+        (engine, events)
+    }
+
+    /// Variant name and order id of each event
+    fn kinds(events: &Mutex<Vec<TriggerEvent>>) -> Vec<(&'static str, u64)> {
+        events
+            .lock()
+            .iter()
+            .map(|event| match event {
+                TriggerEvent::Registered { id, .. } => ("registered", *id),
+                TriggerEvent::Triggered { id, .. } => ("triggered", *id),
+                TriggerEvent::Executed { id, .. } => ("executed", *id),
+                TriggerEvent::Failed { id, .. } => ("failed", *id),
+                TriggerEvent::Expired { id } => ("expired", *id),
+                TriggerEvent::Cancelled { id } => ("cancelled", *id),
+            })
+            .collect()
+    }
+
+    fn buy_below(mint: Pubkey, price: f64) -> TriggerOrder {
+        TriggerOrder::buy(
+            mint,
+            DexType::PumpFun,
+            TriggerCondition::PriceBelow(price),
+            1_000_000,
+            ParamsSource::PumpFunBondingCurve,
+        )
+    }
+
+    fn at(price: f64) -> MarketObservation {
+        MarketObservation { price, curve_progress: None }
+    }
+
+    #[test]
+    fn conditions_compare_against_the_observation() {
+        let below = TriggerCondition::PriceBelow(0.5);
+        assert!(below.is_met(&at(0.4)));
+        assert!(!below.is_met(&at(0.5)));
+        // A zero price means no liquidity was observed, not a price below every limit
+        assert!(!below.is_met(&at(0.0)));
+
+        let above = TriggerCondition::PriceAbove(0.5);
+        assert!(above.is_met(&at(0.6)));
+        assert!(!above.is_met(&at(0.5)));
+        assert!(!above.is_met(&at(0.0)));
+
+        let progress = TriggerCondition::CurveProgressAbove(80.0);
+        assert!(!progress.is_met(&at(1.0)));
+        assert!(!progress.is_met(&MarketObservation { price: 1.0, curve_progress: Some(80.0) }));
+        assert!(progress.is_met(&MarketObservation { price: 1.0, curve_progress: Some(80.5) }));
+    }
+
+    #[tokio::test]
+    async fn order_fires_once_and_reports_its_outcome() {
+        let (engine, events) = engine();
+        let mint = Pubkey::new_unique();
+        let id = engine.register(buy_below(mint, 0.5).with_label("strategy", "dip")).unwrap();
+
+        engine.observe(&mint, at(0.6)).await;
+        engine.observe(&Pubkey::new_unique(), at(0.1)).await;
+        assert_eq!(engine.open_orders().len(), 1);
+        engine.observe(&mint, at(0.4)).await;
+        engine.observe(&mint, at(0.3)).await;
+
+        assert_eq!(kinds(&events), vec![("registered", id), ("triggered", id), ("failed", id)]);
+        match &events.lock()[2] {
+            TriggerEvent::Failed { labels, .. } => {
+                assert_eq!(labels.as_ref().unwrap()["strategy"], "dip")
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        assert!(engine.open_orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn expired_orders_are_dropped_before_they_can_fire() {
+        let (engine, events) = engine();
+        let mint = Pubkey::new_unique();
+        let expired = buy_below(mint, 0.5).expires_in(Duration::ZERO);
+        assert!(engine.register(expired).is_err());
+
+        let id =
+            engine.register(buy_below(mint, 0.5).expires_in(Duration::from_secs(3600))).unwrap();
+        engine.orders.lock().get_mut(&id).unwrap().order.expiry = Some(unix_now() - 1);
+        engine.observe(&mint, at(0.1)).await;
+
+        assert_eq!(kinds(&events), vec![("registered", id), ("expired", id)]);
+        assert!(engine.open_orders().is_empty());
+        assert!(!engine.cancel(id).unwrap());
+    }
+
+    #[test]
+    fn triggered_order_cannot_be_cancelled() {
+        let (engine, events) = engine();
+        let mint = Pubkey::new_unique();
+        let id = engine.register(buy_below(mint, 0.5)).unwrap();
+        let other = engine.register(buy_below(Pubkey::new_unique(), 0.5)).unwrap();
+
+        assert_eq!(engine.claim(|stored| stored.id == id).len(), 1);
+        assert!(!engine.cancel(id).unwrap());
+        assert!(engine.cancel(other).unwrap());
+
+        assert_eq!(
+            kinds(&events),
+            vec![("registered", id), ("registered", other), ("cancelled", other)]
+        );
+        assert!(engine.open_orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn order_stays_open_when_its_trigger_cannot_be_persisted() {
+        let store = Arc::new(FlakyStore::default());
+        let (engine, events) = engine();
+        let engine = engine.with_store(store.clone()).unwrap();
+        let mint = Pubkey::new_unique();
+        let id = engine.register(buy_below(mint, 0.5)).unwrap();
+
+        store.failing.store(true, Ordering::SeqCst);
+        engine.observe(&mint, at(0.4)).await;
+        assert_eq!(kinds(&events), vec![("registered", id)]);
+        assert_eq!(engine.open_orders()[0].status, TriggerOrderStatus::Open);
+
+        store.failing.store(false, Ordering::SeqCst);
+        engine.observe(&mint, at(0.4)).await;
+        assert_eq!(kinds(&events), vec![("registered", id), ("triggered", id), ("failed", id)]);
+    }
+
+    #[tokio::test]
+    async fn order_triggered_before_a_restart_never_fires_again() {
+        let store: Arc<dyn KeyValueStore> = Arc::new(MemoryStore::new());
+        let mint = Pubkey::new_unique();
+        let (first, _) = engine();
+        let first = first.with_store(store.clone()).unwrap();
+        let triggered = first.register(buy_below(mint, 0.5).with_label("strategy", "dip")).unwrap();
+        let open = first.register(buy_below(mint, 0.1)).unwrap();
+        // The process stops after the trigger was persisted, before the outcome was
+        assert_eq!(first.claim(|stored| stored.id == triggered).len(), 1);
+        drop(first);
+
+        // The callback set after the store still learns about the interrupted order
+        let (rpc, _) = FakeChain::default().client();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let second = TriggerEngine::new(Arc::new(SolanaTrade::offline(rpc)))
+            .with_store(store.clone())
+            .unwrap()
+            .with_event_callback(Arc::new(move |event| sink.lock().push(event)));
+
+        assert_eq!(kinds(&events), vec![("failed", triggered)]);
+        let open_ids: Vec<u64> = second.open_orders().iter().map(|stored| stored.id).collect();
+        assert_eq!(open_ids, vec![open]);
+        second.observe(&mint, at(0.3)).await;
+        assert_eq!(kinds(&events), vec![("failed", triggered)]);
+
+        // The interrupted order is gone from the store too
+        let stored: HashMap<u64, StoredTriggerOrder> =
+            serde_json::from_str(&store.get(STORE_KEY).unwrap()).unwrap();
+        assert_eq!(stored.keys().copied().collect::<Vec<_>>(), vec![open]);
+        assert!(second.register(buy_below(mint, 0.5)).unwrap() > open);
+    }
+}
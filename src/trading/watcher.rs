@@ -0,0 +1,609 @@
+use crate::common::labels::TradeLabels;
+use crate::constants::WSOL_TOKEN_ACCOUNT;
+use crate::solana_streamer_sdk::match_event;
+use crate::solana_streamer_sdk::streaming::event_parser::protocols::pumpfun::PumpFunTradeEvent;
+use crate::solana_streamer_sdk::streaming::event_parser::protocols::pumpswap::{
+    PumpSwapBuyEvent, PumpSwapCreatePoolEvent, PumpSwapSellEvent,
+};
+use crate::solana_streamer_sdk::streaming::event_parser::UnifiedEvent;
+use crate::trading::core::options::SellOptions;
+use crate::params::{PumpFunParams, PumpSwapParams};
+use crate::trading::core::traits::ProtocolParams;
+use crate::trading::factory::DexType;
+use crate::utils::price;
+use crate::SolanaTrade;
+use anyhow::anyhow;
+use futures::future::join_all;
+use parking_lot::Mutex;
+use solana_sdk::{pubkey::Pubkey, signature::Signature};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// Decimals of PumpFun mints, also used for PumpSwap pools unless configured otherwise
+pub const DEFAULT_TOKEN_DECIMALS: u8 = 6;
+
+/// Rule that sells a watched position, percentages are relative to the entry price
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitRule {
+    /// Price at least this many percent above the entry price
+    TakeProfit(f64),
+    /// Price at least this many percent below the entry price
+    StopLoss(f64),
+    /// Price at least this many percent below the highest price seen since registration
+    TrailingStop(f64),
+    /// Time since registration, fires without a trade event
+    Timeout(Duration),
+}
+
+impl ExitRule {
+    pub fn is_met(&self, entry_price: f64, peak_price: f64, price: f64, elapsed: Duration) -> bool {
+        match *self {
+            ExitRule::TakeProfit(pct) => price >= entry_price * (1.0 + pct / 100.0),
+            ExitRule::StopLoss(pct) => price <= entry_price * (1.0 - pct / 100.0),
+            ExitRule::TrailingStop(pct) => price <= peak_price * (1.0 - pct / 100.0),
+            ExitRule::Timeout(timeout) => elapsed >= timeout,
+        }
+    }
+
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        match *self {
+            ExitRule::TakeProfit(pct) if !(pct > 0.0) => {
+                Err(anyhow!("Take profit must be above 0%, got {}", pct))
+            }
+            ExitRule::StopLoss(pct) | ExitRule::TrailingStop(pct)
+                if !(pct > 0.0 && pct < 100.0) =>
+            {
+                Err(anyhow!("Stop loss must be between 0% and 100%, got {}", pct))
+            }
+            ExitRule::Timeout(timeout) if timeout.is_zero() => {
+                Err(anyhow!("Timeout cannot be zero"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A held position the `PositionWatcher` sells once one of its rules fires
+#[derive(Clone)]
+pub struct WatchedPosition {
+    pub mint: Pubkey,
+    /// Tokens sold when a rule fires
+    pub token_amount: u64,
+    /// `PumpFun` or `PumpSwap`
+    pub dex_type: DexType,
+    /// Params the sell is built from, replaced by those of every trade event of the mint
+    pub params: Box<dyn ProtocolParams>,
+    pub rules: Vec<ExitRule>,
+    /// SOL per token the rules are relative to, the first observed price when `None`
+    pub entry_price: Option<f64>,
+    /// Decimals of the mint, used to price PumpSwap pools
+    pub token_decimals: u8,
+    pub sell_options: SellOptions,
+}
+
+impl WatchedPosition {
+    pub fn new(
+        mint: Pubkey,
+        token_amount: u64,
+        dex_type: DexType,
+        params: Box<dyn ProtocolParams>,
+    ) -> Self {
+        Self {
+            mint,
+            token_amount,
+            dex_type,
+            params,
+            rules: Vec::new(),
+            entry_price: None,
+            token_decimals: DEFAULT_TOKEN_DECIMALS,
+            sell_options: SellOptions::default(),
+        }
+    }
+
+    pub fn with_rule(mut self, rule: ExitRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn with_entry_price(mut self, entry_price: f64) -> Self {
+        self.entry_price = Some(entry_price);
+        self
+    }
+
+    pub fn with_token_decimals(mut self, token_decimals: u8) -> Self {
+        self.token_decimals = token_decimals;
+        self
+    }
+
+    pub fn with_sell_options(mut self, sell_options: SellOptions) -> Self {
+        self.sell_options = sell_options;
+        self
+    }
+
+    /// PumpSwap pool the position trades on, `None` while it is on the bonding curve
+    fn pool(&self) -> Option<Pubkey> {
+        self.params.as_any().downcast_ref::<PumpSwapParams>().map(|params| params.pool)
+    }
+}
+
+/// State of a watched position
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchStatus {
+    pub id: u64,
+    pub mint: Pubkey,
+    pub dex_type: DexType,
+    /// `None` until the first price was observed
+    pub entry_price: Option<f64>,
+    pub peak_price: Option<f64>,
+    pub last_price: Option<f64>,
+    pub watched_for: Duration,
+}
+
+/// Lifecycle events of watched positions
+#[derive(Debug, Clone)]
+pub enum WatchEvent {
+    Registered {
+        id: u64,
+        mint: Pubkey,
+    },
+    /// The bonding curve of the mint migrated, the position is sold on the PumpSwap `pool`
+    Migrated {
+        id: u64,
+        pool: Pubkey,
+    },
+    /// `price` is `None` for timeouts
+    Fired {
+        id: u64,
+        rule: ExitRule,
+        price: Option<f64>,
+    },
+    /// `labels` are those of the position's `sell_options`
+    Sold {
+        id: u64,
+        signature: Signature,
+        labels: Option<TradeLabels>,
+    },
+    Failed {
+        id: u64,
+        error: String,
+        labels: Option<TradeLabels>,
+    },
+    Unregistered {
+        id: u64,
+    },
+}
+
+pub type WatchEventCallback = Arc<dyn Fn(WatchEvent) + Send + Sync>;
+
+struct Watch {
+    position: WatchedPosition,
+    entry_price: Option<f64>,
+    peak_price: Option<f64>,
+    last_price: Option<f64>,
+    registered_at: Instant,
+    /// Set once a rule fired, the position is never sold twice
+    firing: bool,
+    timeout_task: Option<JoinHandle<()>>,
+}
+
+impl Watch {
+    /// Record `price`, returning the first rule it fires
+    fn observe(&mut self, price: f64) -> Option<ExitRule> {
+        if self.firing || !(price > 0.0) {
+            return None;
+        }
+        let entry_price = *self.entry_price.get_or_insert(price);
+        let peak_price = self.peak_price.map_or(price, |peak| peak.max(price));
+        self.peak_price = Some(peak_price);
+        self.last_price = Some(price);
+        let elapsed = self.registered_at.elapsed();
+        self.position
+            .rules
+            .iter()
+            .copied()
+            .find(|rule| rule.is_met(entry_price, peak_price, price, elapsed))
+    }
+
+    fn status(&self, id: u64) -> WatchStatus {
+        WatchStatus {
+            id,
+            mint: self.position.mint,
+            dex_type: self.position.dex_type.clone(),
+            entry_price: self.entry_price,
+            peak_price: self.peak_price,
+            last_price: self.last_price,
+            watched_for: self.registered_at.elapsed(),
+        }
+    }
+}
+
+struct PendingSell {
+    id: u64,
+    rule: ExitRule,
+    price: Option<f64>,
+    position: WatchedPosition,
+}
+
+/// Sells watched positions when a take-profit, stop-loss, trailing-stop or timeout rule fires
+///
+/// Prices come from the reserves of PumpFun and PumpSwap trade events: pass `event_callback` to
+/// a stream subscription, or call the `observe_*` methods from your own callback. Each event
+/// also refreshes the params of the position, so a firing rule sells right away with the
+/// cached blockhash. A position is sold at most once and is removed after the sell, whether it
+/// succeeded or not. When its bonding curve migrates the position moves to the new PumpSwap
+/// pool, seen in the pool creation or the first trade on the pool.
+pub struct PositionWatcher {
+    pub(crate) trade: Arc<SolanaTrade>,
+    watches: Mutex<HashMap<u64, Watch>>,
+    next_id: AtomicU64,
+    event_callback: Option<WatchEventCallback>,
+}
+
+impl PositionWatcher {
+    pub fn new(trade: Arc<SolanaTrade>) -> Self {
+        Self {
+            trade,
+            watches: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            event_callback: None,
+        }
+    }
+
+    pub fn with_event_callback(mut self, callback: WatchEventCallback) -> Self {
+        self.event_callback = Some(callback);
+        self
+    }
+
+    /// Watch a position, returns its id
+    pub fn register(self: &Arc<Self>, position: WatchedPosition) -> Result<u64, anyhow::Error> {
+        if position.token_amount == 0 {
+            return Err(anyhow!("Token amount cannot be zero"));
+        }
+        if !matches!(position.dex_type, DexType::PumpFun | DexType::PumpSwap) {
+            return Err(anyhow!("Only PumpFun and PumpSwap positions can be watched"));
+        }
+        if position.rules.is_empty() {
+            return Err(anyhow!("At least one exit rule is required"));
+        }
+        for rule in &position.rules {
+            rule.validate()?;
+        }
+        if position.entry_price.is_some_and(|price| !(price > 0.0)) {
+            return Err(anyhow!("Entry price must be positive"));
+        }
+        let timeout = position
+            .rules
+            .iter()
+            .filter_map(|rule| match rule {
+                ExitRule::Timeout(timeout) => Some(*timeout),
+                _ => None,
+            })
+            .min();
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let mint = position.mint;
+        {
+            let mut watches = self.watches.lock();
+            let timeout_task = timeout.map(|timeout| {
+                let watcher = self.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(timeout).await;
+                    watcher.fire(id, ExitRule::Timeout(timeout)).await;
+                })
+            });
+            watches.insert(
+                id,
+                Watch {
+                    entry_price: position.entry_price,
+                    peak_price: position.entry_price,
+                    last_price: None,
+                    registered_at: Instant::now(),
+                    firing: false,
+                    timeout_task,
+                    position,
+                },
+            );
+        }
+        self.emit(WatchEvent::Registered { id, mint });
+        Ok(id)
+    }
+
+    /// Stop watching a position, returns false if it does not exist or a rule already fired
+    pub fn unregister(&self, id: u64) -> bool {
+        {
+            let mut watches = self.watches.lock();
+            match watches.get(&id) {
+                Some(watch) if !watch.firing => {}
+                _ => return false,
+            }
+            if let Some(task) = watches.remove(&id).and_then(|watch| watch.timeout_task) {
+                task.abort();
+            }
+        }
+        self.emit(WatchEvent::Unregistered { id });
+        true
+    }
+
+    /// Positions still waiting for a rule
+    pub fn watched(&self) -> Vec<WatchStatus> {
+        let mut watched: Vec<WatchStatus> = self
+            .watches
+            .lock()
+            .iter()
+            .filter(|(_, watch)| !watch.firing)
+            .map(|(id, watch)| watch.status(*id))
+            .collect();
+        watched.sort_by_key(|status| status.id);
+        watched
+    }
+
+    /// Stream callback feeding PumpFun and PumpSwap events to the watcher, for
+    /// `subscribe_events_immediate`
+    pub fn event_callback(self: &Arc<Self>) -> impl Fn(Box<dyn UnifiedEvent>) + Send + Sync {
+        let watcher = self.clone();
+        move |event: Box<dyn UnifiedEvent>| {
+            match_event!(event, {
+                PumpFunTradeEvent => |e: PumpFunTradeEvent| {
+                    let watcher = watcher.clone();
+                    tokio::spawn(async move { watcher.observe_pumpfun_trade(&e).await });
+                },
+                PumpSwapBuyEvent => |e: PumpSwapBuyEvent| {
+                    let watcher = watcher.clone();
+                    tokio::spawn(async move { watcher.observe_pumpswap_buy(&e).await });
+                },
+                PumpSwapSellEvent => |e: PumpSwapSellEvent| {
+                    let watcher = watcher.clone();
+                    tokio::spawn(async move { watcher.observe_pumpswap_sell(&e).await });
+                },
+                PumpSwapCreatePoolEvent => |e: PumpSwapCreatePoolEvent| {
+                    let watcher = watcher.clone();
+                    tokio::spawn(async move { watcher.observe_pumpswap_create_pool(&e).await });
+                },
+            });
+        }
+    }
+
+    /// Feed a PumpFun trade event, pricing positions on the bonding curve of its mint
+    pub async fn observe_pumpfun_trade(&self, event: &PumpFunTradeEvent) {
+        let price = price::pumpfun::price_token_in_sol(
+            event.virtual_sol_reserves,
+            event.virtual_token_reserves,
+        );
+        let fired = self.claim(|watch| {
+            if watch.position.dex_type != DexType::PumpFun || watch.position.mint != event.mint {
+                return None;
+            }
+            let close_token_account_when_sell = watch
+                .position
+                .params
+                .as_any()
+                .downcast_ref::<PumpFunParams>()
+                .and_then(|params| params.close_token_account_when_sell);
+            watch.position.params =
+                Box::new(PumpFunParams::from_trade(event, close_token_account_when_sell));
+            watch.observe(price).map(|rule| (rule, Some(price)))
+        });
+        self.sell_all(fired).await;
+    }
+
+    /// Feed a PumpSwap buy event
+    pub async fn observe_pumpswap_buy(&self, event: &PumpSwapBuyEvent) {
+        self.observe_pumpswap_trade(
+            PoolReserves {
+                pool: event.pool,
+                base_mint: event.base_mint,
+                quote_mint: event.quote_mint,
+                base_reserves: event.pool_base_token_reserves,
+                quote_reserves: event.pool_quote_token_reserves,
+            },
+            || PumpSwapParams::from_buy_trade(event),
+        )
+        .await;
+    }
+
+    /// Feed a PumpSwap sell event
+    pub async fn observe_pumpswap_sell(&self, event: &PumpSwapSellEvent) {
+        self.observe_pumpswap_trade(
+            PoolReserves {
+                pool: event.pool,
+                base_mint: event.base_mint,
+                quote_mint: event.quote_mint,
+                base_reserves: event.pool_base_token_reserves,
+                quote_reserves: event.pool_quote_token_reserves,
+            },
+            || PumpSwapParams::from_sell_trade(event),
+        )
+        .await;
+    }
+
+    /// Feed a PumpSwap pool creation, moving bonding curve positions of its mint to the pool
+    ///
+    /// The pool's params are taken from the event, see `PumpSwapParams::from_create_pool_event`.
+    pub async fn observe_pumpswap_create_pool(&self, event: &PumpSwapCreatePoolEvent) {
+        let Some(mint) = sol_paired_mint(&event.base_mint, &event.quote_mint) else {
+            return;
+        };
+        let migrating: Vec<u64> = self
+            .watches
+            .lock()
+            .iter()
+            .filter(|(_, watch)| {
+                watch.position.dex_type == DexType::PumpFun && watch.position.mint == mint
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        if migrating.is_empty() {
+            return;
+        }
+        let params = PumpSwapParams::from_create_pool_event(event);
+        let mut migrated = Vec::new();
+        {
+            let mut watches = self.watches.lock();
+            for id in migrating {
+                let Some(watch) = watches.get_mut(&id) else {
+                    continue;
+                };
+                if watch.firing || watch.position.dex_type != DexType::PumpFun {
+                    continue;
+                }
+                watch.position.dex_type = DexType::PumpSwap;
+                watch.position.params = Box::new(params.clone());
+                migrated.push(id);
+            }
+        }
+        for id in migrated {
+            self.emit(WatchEvent::Migrated { id, pool: event.pool });
+        }
+    }
+
+    async fn observe_pumpswap_trade(
+        &self,
+        reserves: PoolReserves,
+        params: impl Fn() -> PumpSwapParams,
+    ) {
+        let Some(mint) = sol_paired_mint(&reserves.base_mint, &reserves.quote_mint) else {
+            return;
+        };
+        let fired = self.claim(|watch| {
+            if watch.position.mint != mint {
+                return None;
+            }
+            match watch.position.dex_type {
+                DexType::PumpSwap if watch.position.pool() == Some(reserves.pool) => {}
+                // The pool creation was missed, the first trade on the pool migrates the position
+                DexType::PumpFun => watch.position.dex_type = DexType::PumpSwap,
+                _ => return None,
+            }
+            watch.position.params = Box::new(params());
+            let price = reserves.price_of(&mint, watch.position.token_decimals);
+            watch.observe(price).map(|rule| (rule, Some(price)))
+        });
+        self.sell_all(fired).await;
+    }
+
+    /// Sell a position whose timeout elapsed
+    async fn fire(&self, id: u64, rule: ExitRule) {
+        let pending = {
+            let mut watches = self.watches.lock();
+            match watches.get_mut(&id) {
+                Some(watch) if !watch.firing => {
+                    watch.firing = true;
+                    PendingSell { id, rule, price: None, position: watch.position.clone() }
+                }
+                _ => return,
+            }
+        };
+        self.sell(pending).await;
+    }
+
+    /// Apply `update` to every watch that has not fired, marking those it fires as firing
+    /// before anything is sent
+    fn claim(
+        &self,
+        mut update: impl FnMut(&mut Watch) -> Option<(ExitRule, Option<f64>)>,
+    ) -> Vec<PendingSell> {
+        let mut fired = Vec::new();
+        let mut migrated = Vec::new();
+        {
+            let mut watches = self.watches.lock();
+            for (id, watch) in watches.iter_mut() {
+                if watch.firing {
+                    continue;
+                }
+                let dex_type = watch.position.dex_type.clone();
+                let fired_rule = update(watch);
+                if dex_type != watch.position.dex_type {
+                    migrated.extend(watch.position.pool().map(|pool| (*id, pool)));
+                }
+                if let Some((rule, price)) = fired_rule {
+                    watch.firing = true;
+                    let position = watch.position.clone();
+                    fired.push(PendingSell { id: *id, rule, price, position });
+                }
+            }
+        }
+        for (id, pool) in migrated {
+            self.emit(WatchEvent::Migrated { id, pool });
+        }
+        fired
+    }
+
+    async fn sell_all(&self, fired: Vec<PendingSell>) {
+        join_all(fired.into_iter().map(|pending| self.sell(pending))).await;
+    }
+
+    async fn sell(&self, pending: PendingSell) {
+        let PendingSell { id, rule, price, position } = pending;
+        self.emit(WatchEvent::Fired { id, rule, price });
+        let labels = position.sell_options.labels.clone();
+        let result = self
+            .trade
+            .sell_with_options(
+                position.dex_type,
+                position.mint,
+                position.token_amount,
+                None,
+                position.params,
+                position.sell_options,
+            )
+            .await;
+        if let Some(task) = self.watches.lock().remove(&id).and_then(|watch| watch.timeout_task) {
+            // A timeout sell runs inside the timeout task
+            if !matches!(rule, ExitRule::Timeout(_)) {
+                task.abort();
+            }
+        }
+        self.emit(match result {
+            Ok(signature) => WatchEvent::Sold { id, signature, labels },
+            Err(e) => WatchEvent::Failed { id, error: e.to_string(), labels },
+        });
+    }
+
+    fn emit(&self, event: WatchEvent) {
+        if let Some(callback) = &self.event_callback {
+            callback(event);
+        }
+    }
+}
+
+struct PoolReserves {
+    pool: Pubkey,
+    base_mint: Pubkey,
+    quote_mint: Pubkey,
+    base_reserves: u64,
+    quote_reserves: u64,
+}
+
+impl PoolReserves {
+    /// SOL per token of `mint`, one side of the pool
+    fn price_of(&self, mint: &Pubkey, token_decimals: u8) -> f64 {
+        if self.base_mint == *mint {
+            price::pumpswap::price_base_in_quote(
+                self.base_reserves,
+                self.quote_reserves,
+                token_decimals,
+                9,
+            )
+        } else {
+            price::pumpswap::price_quote_in_base(
+                self.base_reserves,
+                self.quote_reserves,
+                9,
+                token_decimals,
+            )
+        }
+    }
+}
+
+/// The non-SOL mint of a pool paired with wSOL
+fn sol_paired_mint(base_mint: &Pubkey, quote_mint: &Pubkey) -> Option<Pubkey> {
+    if *quote_mint == WSOL_TOKEN_ACCOUNT {
+        Some(*base_mint)
+    } else if *base_mint == WSOL_TOKEN_ACCOUNT {
+        Some(*quote_mint)
+    } else {
+        None
+    }
+}
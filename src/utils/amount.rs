@@ -0,0 +1,79 @@
+//! Conversion between raw token amounts and decimal strings
+
+use anyhow::anyhow;
+
+/// Format a raw amount with `decimals` as a decimal string with thousands separators
+///
+/// With a `precision` the fraction is rounded half up to exactly that many digits, without one
+/// all decimals are kept and trailing zeros are dropped: `format_amount(1_234_500_000, 6, None)`
+/// is `1,234.5`, with `Some(2)` it is `1,234.50`.
+pub fn format_amount(raw: u64, decimals: u8, precision: Option<u8>) -> String {
+    let (integer, fraction) = match precision {
+        Some(precision) if precision < decimals => {
+            let shift = (decimals - precision) as u32;
+            // Anything shifted by more than 20 digits rounds to zero
+            let rounded = match 10u128.checked_pow(shift).filter(|_| shift <= 20) {
+                Some(divisor) => (raw as u128 + divisor / 2) / divisor,
+                None => 0,
+            };
+            split_digits(rounded, precision)
+        }
+        Some(precision) => {
+            let (integer, mut fraction) = split_digits(raw as u128, decimals);
+            fraction.extend(std::iter::repeat('0').take((precision - decimals) as usize));
+            (integer, fraction)
+        }
+        None => {
+            let (integer, fraction) = split_digits(raw as u128, decimals);
+            (integer, fraction.trim_end_matches('0').to_string())
+        }
+    };
+    let integer = group_thousands(&integer);
+    if fraction.is_empty() {
+        integer
+    } else {
+        format!("{}.{}", integer, fraction)
+    }
+}
+
+/// Parse a decimal string such as `12.5` or `1,000` into a raw amount with `decimals`
+///
+/// `,` and `_` separators are ignored. More fraction digits than `decimals` and amounts above
+/// `u64::MAX` are errors.
+pub fn parse_amount(text: &str, decimals: u8) -> Result<u64, anyhow::Error> {
+    let cleaned: String = text.trim().chars().filter(|c| *c != ',' && *c != '_').collect();
+    let (integer, fraction) = cleaned.split_once('.').unwrap_or((&cleaned, ""));
+    if integer.is_empty() && fraction.is_empty() {
+        return Err(anyhow!("Invalid amount {:?}", text));
+    }
+    if !integer.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit()) {
+        return Err(anyhow!("Invalid amount {:?}", text));
+    }
+    if fraction.len() > decimals as usize {
+        return Err(anyhow!("Amount {:?} has more than {} decimals", text, decimals));
+    }
+    let mut digits = format!("{}{:0<width$}", integer, fraction, width = decimals as usize);
+    digits = digits.trim_start_matches('0').to_string();
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    digits.parse::<u64>().map_err(|_| anyhow!("Amount {:?} is out of range", text))
+}
+
+/// Split `raw` into its integer and zero padded fraction digits
+fn split_digits(raw: u128, decimals: u8) -> (String, String) {
+    let digits = format!("{:0>width$}", raw, width = decimals as usize + 1);
+    let (integer, fraction) = digits.split_at(digits.len() - decimals as usize);
+    (integer.to_string(), fraction.to_string())
+}
+
+fn group_thousands(integer: &str) -> String {
+    let mut grouped = String::with_capacity(integer.len() + integer.len() / 3);
+    for (i, c) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+    grouped
+}
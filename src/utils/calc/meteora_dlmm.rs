@@ -0,0 +1,60 @@
+/// Precision of DLMM fee rates, a rate of `FEE_PRECISION` is 100%
+pub const FEE_PRECISION: u128 = 1_000_000_000;
+
+/// Result of a DLMM swap estimate
+#[derive(Debug, Clone)]
+pub struct ComputeSwapParams {
+    /// The input amount for the swap
+    pub amount_in: u64,
+    /// The expected output amount from the swap
+    pub amount_out: u64,
+    /// The minimum acceptable output amount (considering slippage_basis_points)
+    pub min_amount_out: u64,
+    /// The base fee charged on the input
+    pub fee: u64,
+}
+
+/// Base fee rate of a pair, in `FEE_PRECISION` units
+pub fn base_fee_rate(base_factor: u16, bin_step: u16, base_fee_power_factor: u8) -> u128 {
+    (base_factor as u128) * (bin_step as u128) * 10 * 10u128.pow(base_fee_power_factor as u32)
+}
+
+/// Price of one X atom in Y atoms at bin `active_id`
+pub fn bin_price(active_id: i32, bin_step: u16) -> f64 {
+    (1.0 + bin_step as f64 / 10_000.0).powi(active_id)
+}
+
+/// Estimates a swap at the price of the active bin
+///
+/// Liquidity is spread over bins of rising price, so a swap crossing bins receives less than
+/// this estimate; the variable fee is not included either. Both are left to the slippage.
+///
+/// # Arguments
+/// * `active_id` - The active bin of the pair
+/// * `bin_step` - The bin step of the pair in basis points
+/// * `base_fee_rate` - The base fee rate, see `base_fee_rate`
+/// * `output_reserve` - The reserve of the output token, caps the output
+/// * `swap_for_y` - Whether X is swapped for Y (true) or Y for X (false)
+/// * `amount_in` - The amount of input tokens to swap
+/// * `slippage_basis_points` - The acceptable slippage in basis points (e.g., 100 for 1%)
+pub fn compute_swap_amount(
+    active_id: i32,
+    bin_step: u16,
+    base_fee_rate: u128,
+    output_reserve: u64,
+    swap_for_y: bool,
+    amount_in: u64,
+    slippage_basis_points: u64,
+) -> ComputeSwapParams {
+    let fee = ((amount_in as u128) * base_fee_rate).div_ceil(FEE_PRECISION) as u64;
+    let amount_in_less_fee = amount_in.saturating_sub(fee) as f64;
+    let price = bin_price(active_id, bin_step);
+    let amount_out =
+        if swap_for_y { amount_in_less_fee * price } else { amount_in_less_fee / price };
+    let amount_out = (amount_out as u64).min(output_reserve);
+
+    let min_amount_out =
+        ((amount_out as f64) * (1.0 - (slippage_basis_points as f64) / 10000.0)) as u64;
+
+    ComputeSwapParams { amount_in, amount_out, min_amount_out, fee }
+}
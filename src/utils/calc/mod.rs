@@ -3,4 +3,5 @@ pub mod common;
 pub mod pumpswap;
 pub mod bonk;
 pub mod raydium_amm_v4;
-pub mod raydium_cpmm;
\ No newline at end of file
+pub mod raydium_cpmm;
+pub mod meteora_dlmm;
\ No newline at end of file
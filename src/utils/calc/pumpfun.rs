@@ -5,6 +5,33 @@ use crate::{
     utils::calc::common::compute_fee,
 };
 
+/// Creator fee in basis points for the bonding curve
+///
+/// Curves without a creator (`Pubkey::default()`) are not charged a creator fee.
+#[inline]
+pub fn creator_fee_basis_points(creator: &Pubkey) -> u64 {
+    if *creator != Pubkey::default() {
+        CREATOR_FEE
+    } else {
+        0
+    }
+}
+
+/// Fee the bonding curve charges on `sol_amount` lamports
+///
+/// The program rounds the protocol and creator fees up one by one, so the sum can be a lamport
+/// above a fee computed from the combined basis points.
+pub fn bonding_curve_fee(sol_amount: u128, creator: &Pubkey) -> u128 {
+    compute_fee(sol_amount, FEE_BASIS_POINTS as u128)
+        + compute_fee(sol_amount, creator_fee_basis_points(creator) as u128)
+}
+
+/// SOL the bonding curve charges before fees for `amount` tokens, one lamport above the
+/// constant product price as the program computes it
+fn buy_sol_cost(virtual_token_reserves: u128, virtual_sol_reserves: u128, amount: u128) -> u128 {
+    amount * virtual_sol_reserves / (virtual_token_reserves - amount) + 1
+}
+
 /// Calculates the amount of tokens that can be purchased with a given SOL amount
 /// using the bonding curve formula.
 ///
@@ -32,18 +59,23 @@ pub fn get_buy_token_amount_from_sol_amount(
         return 0;
     }
 
-    let total_fee_basis_points =
-        FEE_BASIS_POINTS + if creator != Pubkey::default() { CREATOR_FEE } else { 0 };
+    let total_fee_basis_points = FEE_BASIS_POINTS + creator_fee_basis_points(&creator);
 
     // Convert to u128 to prevent overflow
     let amount_128 = amount as u128;
     let total_fee_basis_points_128 = total_fee_basis_points as u128;
 
-    let input_amount = amount_128
+    let mut sol_cost = amount_128
         .checked_mul(10_000)
         .unwrap()
         .checked_div(total_fee_basis_points_128 + 10_000)
         .unwrap();
+    // The fees rounded up one by one may push the estimate above `amount`
+    while sol_cost > 0 && sol_cost + bonding_curve_fee(sol_cost, &creator) > amount_128 {
+        sol_cost -= 1;
+    }
+    // The program charges one lamport above the constant product price
+    let input_amount = sol_cost.saturating_sub(1);
 
     let denominator = virtual_sol_reserves + input_amount;
 
@@ -63,6 +95,36 @@ pub fn get_buy_token_amount_from_sol_amount(
     tokens_received as u64
 }
 
+/// Calculates the SOL needed to buy a given token amount using the bonding curve formula,
+/// the inverse of `get_buy_token_amount_from_sol_amount`.
+///
+/// # Arguments
+/// * `virtual_token_reserves` - Virtual token reserves in the bonding curve
+/// * `virtual_sol_reserves` - Virtual SOL reserves in the bonding curve
+/// * `real_token_reserves` - Actual token reserves available for purchase
+/// * `creator` - Creator's public key (affects fee calculation)
+/// * `amount` - Token amount to receive (in token's smallest unit)
+///
+/// # Returns
+/// The SOL cost including fees (in lamports), `None` when the curve cannot sell `amount`
+pub fn get_buy_sol_amount_from_token_amount(
+    virtual_token_reserves: u128,
+    virtual_sol_reserves: u128,
+    real_token_reserves: u128,
+    creator: Pubkey,
+    amount: u64,
+) -> Option<u64> {
+    let amount_128 = amount as u128;
+    if amount == 0 || amount_128 > real_token_reserves || amount_128 >= virtual_token_reserves {
+        return None;
+    }
+
+    let input_amount = buy_sol_cost(virtual_token_reserves, virtual_sol_reserves, amount_128);
+    let fee = bonding_curve_fee(input_amount, &creator);
+
+    u64::try_from(input_amount + fee).ok()
+}
+
 /// Calculates the amount of SOL that will be received when selling a given token amount
 /// using the bonding curve formula with transaction fees deducted.
 ///
@@ -97,12 +159,148 @@ pub fn get_sell_sol_amount_from_token_amount(
 
     let sol_cost = numerator.checked_div(denominator).unwrap_or(0);
 
-    let total_fee_basis_points =
-        FEE_BASIS_POINTS + if creator != Pubkey::default() { CREATOR_FEE } else { 0 };
-    let total_fee_basis_points_128 = total_fee_basis_points as u128;
-
     // Calculate transaction fee
-    let fee = compute_fee(sol_cost, total_fee_basis_points_128);
+    let fee = bonding_curve_fee(sol_cost, &creator);
 
     sol_cost.saturating_sub(fee) as u64
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::str::FromStr;
+
+    /// Buys and sells observed on mainnet curves, replayed against the quote functions
+    const CAPTURES: &str = include_str!("../../../tests/fixtures/pump_trade_captures.json");
+
+    // (virtual_token_reserves, virtual_sol_reserves, real_token_reserves) of a curve without a
+    // creator and of one with a creator, with outputs worked out by hand from the program's fee
+    // rounding
+    const LEGACY_CURVE: (u128, u128, u128) =
+        (1_000_000_000_000_000, 32_190_005_730, 720_900_000_000_000);
+    const CREATOR_CURVE: (u128, u128, u128) =
+        (638_429_218_370_187, 50_420_177_905, 358_529_218_370_187);
+
+    fn creator() -> Pubkey {
+        Pubkey::new_from_array([7; 32])
+    }
+
+    /// SOL the program takes for `amount` tokens, the fees rounded up one by one
+    fn charged_for_buy(
+        amount: u64,
+        (virtual_token, virtual_sol, _): (u128, u128, u128),
+        creator: &Pubkey,
+    ) -> u64 {
+        let amount = amount as u128;
+        let cost = amount * virtual_sol / (virtual_token - amount) + 1;
+        let creator_fee_bps = if *creator == Pubkey::default() { 0 } else { 30 };
+        (cost + compute_fee(cost, 95) + compute_fee(cost, creator_fee_bps)) as u64
+    }
+
+    #[test]
+    fn bonding_curve_fee_rounds_each_fee_up() {
+        assert_eq!(bonding_curve_fee(1_000, &Pubkey::default()), 10);
+        // 9.5 and 3 lamports, rounded up on their own
+        assert_eq!(bonding_curve_fee(1_000, &creator()), 13);
+        assert_eq!(bonding_curve_fee(1_001, &creator()), 14);
+    }
+
+    #[test]
+    fn buys_stay_within_the_sol_amount() {
+        for (curve, creator, tokens) in [
+            (LEGACY_CURVE, Pubkey::default(), 3_067_878_862_874),
+            (CREATOR_CURVE, creator(), 1_248_140_442_264),
+        ] {
+            let sol_amount = 100_000_000;
+            let (virtual_token, virtual_sol, real_token) = curve;
+            let amount = get_buy_token_amount_from_sol_amount(
+                virtual_token,
+                virtual_sol,
+                real_token,
+                creator,
+                sol_amount,
+            );
+            assert_eq!(amount, tokens);
+            assert!(charged_for_buy(amount, curve, &creator) <= sol_amount);
+        }
+    }
+
+    #[test]
+    fn buy_costs_match_the_program() {
+        for (curve, creator, large, small) in [
+            (LEGACY_CURVE, Pubkey::default(), 1_178_604_538, 4_013),
+            (CREATOR_CURVE, creator(), 2_961_018_461, 9_874),
+        ] {
+            let (virtual_token, virtual_sol, real_token) = curve;
+            for (amount, expected) in [(35_000_000_000_000, large), (123_456_789, small)] {
+                let cost = get_buy_sol_amount_from_token_amount(
+                    virtual_token,
+                    virtual_sol,
+                    real_token,
+                    creator,
+                    amount,
+                );
+                assert_eq!(cost, Some(expected));
+                assert_eq!(charged_for_buy(amount, curve, &creator), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn sells_match_the_program() {
+        for (curve, creator, large, small) in [
+            (LEGACY_CURVE, Pubkey::default(), 1_078_209_684, 3_936),
+            (CREATOR_CURVE, creator(), 2_587_721_695, 9_627),
+        ] {
+            let (virtual_token, virtual_sol, _) = curve;
+            for (amount, expected) in [(35_000_000_000_000, large), (123_456_789, small)] {
+                assert_eq!(
+                    get_sell_sol_amount_from_token_amount(
+                        virtual_token,
+                        virtual_sol,
+                        creator,
+                        amount
+                    ),
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn quotes_match_the_captured_trades() {
+        let captures: Value = serde_json::from_str(CAPTURES).unwrap();
+        for capture in captures["pumpfun"].as_array().unwrap() {
+            let amount = |key: &str| capture[key].as_u64().unwrap();
+            let signature = capture["signature"].as_str().unwrap();
+            let creator = Pubkey::from_str(capture["creator"].as_str().unwrap()).unwrap();
+            let (tokens, sol) = (amount("token_amount"), amount("sol_amount"));
+            let fees = amount("fee") + amount("creator_fee");
+            // The event reports the reserves after the trade, undo it
+            let (virtual_token, virtual_sol, real_token) = (
+                amount("virtual_token_reserves") as u128,
+                amount("virtual_sol_reserves") as u128,
+                amount("real_token_reserves") as u128,
+            );
+            if capture["is_buy"].as_bool().unwrap() {
+                let cost = get_buy_sol_amount_from_token_amount(
+                    virtual_token + tokens as u128,
+                    virtual_sol - sol as u128,
+                    real_token + tokens as u128,
+                    creator,
+                    tokens,
+                );
+                assert_eq!(cost, Some(sol + fees), "{}", signature);
+            } else {
+                let proceeds = get_sell_sol_amount_from_token_amount(
+                    virtual_token - tokens as u128,
+                    virtual_sol + sol as u128,
+                    creator,
+                    tokens,
+                );
+                assert_eq!(proceeds, sol - fees, "{}", signature);
+            }
+        }
+    }
+}
@@ -6,6 +6,36 @@ use crate::instruction::utils::pumpswap::accounts::{
 };
 use solana_sdk::pubkey::Pubkey;
 
+/// Whether the pool charges a coin creator fee
+///
+/// Pools created before the creator fee was introduced have `coin_creator == Pubkey::default()`;
+/// the program charges no creator fee on those pools.
+#[inline]
+pub fn has_coin_creator(coin_creator: &Pubkey) -> bool {
+    *coin_creator != Pubkey::default()
+}
+
+/// Coin creator fee in basis points for the pool, `0` for pools without a coin creator
+#[inline]
+pub fn coin_creator_fee_basis_points(coin_creator: &Pubkey) -> u64 {
+    if has_coin_creator(coin_creator) {
+        COIN_CREATOR_FEE_BASIS_POINTS
+    } else {
+        0
+    }
+}
+
+/// Fees the pool charges on `quote_amount`
+///
+/// The program rounds the lp, protocol and coin creator fees up one by one, so the sum can be a
+/// few units above a fee computed from the combined basis points.
+pub fn pool_fees(quote_amount: u64, coin_creator: &Pubkey) -> u64 {
+    let quote_amount = quote_amount as u128;
+    (compute_fee(quote_amount, LP_FEE_BASIS_POINTS as u128)
+        + compute_fee(quote_amount, PROTOCOL_FEE_BASIS_POINTS as u128)
+        + compute_fee(quote_amount, coin_creator_fee_basis_points(coin_creator) as u128)) as u64
+}
+
 /// Result for buying base tokens with base amount input
 #[derive(Clone, Debug)]
 pub struct BuyBaseInputResult {
@@ -57,7 +87,7 @@ pub struct SellQuoteInputResult {
 /// * `slippage_basis_points` - Slippage tolerance in basis points (100 = 1%)
 /// * `base_reserve` - Base token reserves in the pool
 /// * `quote_reserve` - Quote token reserves in the pool
-/// * `coin_creator` - Token creator address
+/// * `coin_creator` - Token creator address, `Pubkey::default()` for pools without creator fee
 ///
 /// # Returns
 /// * `BuyBaseInputResult` containing quote amounts and slippage calculations
@@ -85,16 +115,7 @@ pub fn buy_base_input_internal(
 
     let quote_amount_in = ceil_div(numerator, denominator as u128) as u64;
 
-    // Calculate fees
-    let lp_fee = compute_fee(quote_amount_in as u128, LP_FEE_BASIS_POINTS as u128) as u64;
-    let protocol_fee =
-        compute_fee(quote_amount_in as u128, PROTOCOL_FEE_BASIS_POINTS as u128) as u64;
-    let coin_creator_fee = if *coin_creator == Pubkey::default() {
-        0
-    } else {
-        compute_fee(quote_amount_in as u128, COIN_CREATOR_FEE_BASIS_POINTS as u128) as u64
-    };
-    let total_quote = quote_amount_in + lp_fee + protocol_fee + coin_creator_fee;
+    let total_quote = quote_amount_in + pool_fees(quote_amount_in, coin_creator);
 
     // Calculate max quote with slippage
     let max_quote = calculate_with_slippage_buy(total_quote, slippage_basis_points);
@@ -113,7 +134,7 @@ pub fn buy_base_input_internal(
 /// * `slippage_basis_points` - Slippage tolerance in basis points (100 = 1%)
 /// * `base_reserve` - Base token reserves in the pool
 /// * `quote_reserve` - Quote token reserves in the pool
-/// * `coin_creator` - Token creator address
+/// * `coin_creator` - Token creator address, `Pubkey::default()` for pools without creator fee
 ///
 /// # Returns
 /// * `BuyQuoteInputResult` containing base amount and slippage calculations
@@ -131,11 +152,17 @@ pub fn buy_quote_input_internal(
     // Calculate total fee basis points
     let total_fee_bps = LP_FEE_BASIS_POINTS
         + PROTOCOL_FEE_BASIS_POINTS
-        + if *coin_creator == Pubkey::default() { 0 } else { COIN_CREATOR_FEE_BASIS_POINTS };
+        + coin_creator_fee_basis_points(coin_creator);
     let denominator = 10_000 + total_fee_bps;
 
-    // Calculate effective quote amount after fees
-    let effective_quote = (quote as u128 * 10_000) / denominator as u128;
+    // Calculate effective quote amount after fees, the fees rounded up one by one may push the
+    // estimate above `quote`
+    let mut effective_quote = ((quote as u128 * 10_000) / denominator as u128) as u64;
+    while effective_quote > 0 && effective_quote + pool_fees(effective_quote, coin_creator) > quote
+    {
+        effective_quote -= 1;
+    }
+    let effective_quote = effective_quote as u128;
 
     // Calculate base amount out using constant product formula
     let numerator = (base_reserve as u128) * effective_quote;
@@ -164,7 +191,7 @@ pub fn buy_quote_input_internal(
 /// * `slippage_basis_points` - Slippage tolerance in basis points (100 = 1%)
 /// * `base_reserve` - Base token reserves in the pool
 /// * `quote_reserve` - Quote token reserves in the pool
-/// * `coin_creator` - Token creator address
+/// * `coin_creator` - Token creator address, `Pubkey::default()` for pools without creator fee
 ///
 /// # Returns
 /// * `SellBaseInputResult` containing quote amounts and slippage calculations
@@ -183,18 +210,8 @@ pub fn sell_base_input_internal(
     let quote_amount_out = ((quote_reserve as u128) * (base as u128)
         / ((base_reserve as u128) + (base as u128))) as u64;
 
-    // Calculate fees
-    let lp_fee = compute_fee(quote_amount_out as u128, LP_FEE_BASIS_POINTS as u128) as u64;
-    let protocol_fee =
-        compute_fee(quote_amount_out as u128, PROTOCOL_FEE_BASIS_POINTS as u128) as u64;
-    let coin_creator_fee = if *coin_creator == Pubkey::default() {
-        0
-    } else {
-        compute_fee(quote_amount_out as u128, COIN_CREATOR_FEE_BASIS_POINTS as u128) as u64
-    };
-
     // Calculate final quote after fees
-    let total_fees = lp_fee + protocol_fee + coin_creator_fee;
+    let total_fees = pool_fees(quote_amount_out, coin_creator);
     if total_fees > quote_amount_out {
         return Err("Fees exceed total output; final quote is negative.".to_string());
     }
@@ -233,7 +250,7 @@ fn calculate_quote_amount_out(
 /// * `slippage_basis_points` - Slippage tolerance in basis points (100 = 1%)
 /// * `base_reserve` - Base token reserves in the pool
 /// * `quote_reserve` - Quote token reserves in the pool
-/// * `coin_creator` - Token creator address
+/// * `coin_creator` - Token creator address, `Pubkey::default()` for pools without creator fee
 ///
 /// # Returns
 /// * `SellQuoteInputResult` containing base amount and slippage calculations
@@ -251,13 +268,17 @@ pub fn sell_quote_input_internal(
         return Err("Cannot receive more quote tokens than the pool quote reserves.".to_string());
     }
 
-    // Calculate raw quote amount including fees
-    let raw_quote = calculate_quote_amount_out(
+    // Calculate raw quote amount including fees, the fees rounded up one by one may leave less
+    // than `quote` after them
+    let mut raw_quote = calculate_quote_amount_out(
         quote,
         LP_FEE_BASIS_POINTS,
         PROTOCOL_FEE_BASIS_POINTS,
-        if *coin_creator == Pubkey::default() { 0 } else { COIN_CREATOR_FEE_BASIS_POINTS },
+        coin_creator_fee_basis_points(coin_creator),
     );
+    while raw_quote.saturating_sub(pool_fees(raw_quote, coin_creator)) < quote {
+        raw_quote += 1;
+    }
 
     // Calculate base amount needed using inverse constant product formula
     if raw_quote >= quote_reserve {
@@ -273,3 +294,161 @@ pub fn sell_quote_input_internal(
 
     Ok(SellQuoteInputResult { internal_raw_quote: raw_quote, base: base_amount_in, min_quote })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::str::FromStr;
+
+    /// Buys and sells observed on mainnet pools, replayed against the quote functions
+    const CAPTURES: &str = include_str!("../../../tests/fixtures/pump_trade_captures.json");
+
+    // Reserves of a pool created before the coin creator fee, and of one charging it, with
+    // outputs worked out by hand from the program's fee rounding
+    const LEGACY_POOL: (u64, u64) = (206_900_000_000_000, 84_990_359_679);
+    const CREATOR_POOL: (u64, u64) = (541_231_459_301_077, 132_476_018_925);
+
+    fn creator() -> Pubkey {
+        Pubkey::new_from_array([7; 32])
+    }
+
+    /// Quote the program takes for `base`, each fee rounded up on its own
+    fn charged_for_buy(
+        base: u64,
+        (base_reserve, quote_reserve): (u64, u64),
+        creator: &Pubkey,
+    ) -> u64 {
+        let quote_in =
+            ceil_div(quote_reserve as u128 * base as u128, (base_reserve - base) as u128) as u64;
+        let creator_fee_bps = if *creator == Pubkey::default() { 0 } else { 5 };
+        quote_in
+            + [20, 5, creator_fee_bps]
+                .iter()
+                .map(|bps| ceil_div(quote_in as u128 * bps, 10_000) as u64)
+                .sum::<u64>()
+    }
+
+    /// Quote the program pays for `base`, each fee rounded up on its own
+    fn paid_for_sell(
+        base: u64,
+        (base_reserve, quote_reserve): (u64, u64),
+        creator: &Pubkey,
+    ) -> u64 {
+        let quote_out =
+            (quote_reserve as u128 * base as u128 / (base_reserve + base) as u128) as u64;
+        let creator_fee_bps = if *creator == Pubkey::default() { 0 } else { 5 };
+        quote_out
+            - [20, 5, creator_fee_bps]
+                .iter()
+                .map(|bps| ceil_div(quote_out as u128 * bps, 10_000) as u64)
+                .sum::<u64>()
+    }
+
+    #[test]
+    fn legacy_pool_charges_no_creator_fee() {
+        assert_eq!(pool_fees(412_774_938, &Pubkey::default()), 1_031_938);
+        let (base_reserve, quote_reserve) = LEGACY_POOL;
+        let buy = buy_base_input_internal(
+            1_000_000_000_000,
+            0,
+            base_reserve,
+            quote_reserve,
+            &Pubkey::default(),
+        )
+        .unwrap();
+        assert_eq!(buy.internal_quote_amount, 412_774_938);
+        assert_eq!(buy.ui_quote, 413_806_876);
+        let sell = sell_base_input_internal(
+            1_000_000_000_000,
+            0,
+            base_reserve,
+            quote_reserve,
+            &Pubkey::default(),
+        )
+        .unwrap();
+        assert_eq!(sell.internal_quote_amount_out, 408_804_038);
+        assert_eq!(sell.ui_quote, 407_782_026);
+    }
+
+    #[test]
+    fn creator_pool_charges_the_creator_fee() {
+        let (base_reserve, quote_reserve) = CREATOR_POOL;
+        let buy =
+            buy_base_input_internal(1_000_000_000_000, 0, base_reserve, quote_reserve, &creator())
+                .unwrap();
+        assert_eq!(buy.internal_quote_amount, 245_220_853);
+        assert_eq!(buy.ui_quote, 245_956_517);
+        let sell =
+            sell_base_input_internal(1_000_000_000_000, 0, base_reserve, quote_reserve, &creator())
+                .unwrap();
+        assert_eq!(sell.internal_quote_amount_out, 244_316_364);
+        assert_eq!(sell.ui_quote, 243_583_413);
+    }
+
+    #[test]
+    fn quote_input_buys_stay_within_the_quote() {
+        for (pool, creator, base) in [
+            (LEGACY_POOL, Pubkey::default(), 1_207_077_913_576),
+            (CREATOR_POOL, creator(), 2_029_007_538_474),
+        ] {
+            let quote = 500_000_001;
+            let buy = buy_quote_input_internal(quote, 0, pool.0, pool.1, &creator).unwrap();
+            assert_eq!(buy.base, base);
+            assert!(charged_for_buy(buy.base, pool, &creator) <= quote);
+            // Not far below the quote either
+            assert!(charged_for_buy(buy.base + 1_000_000, pool, &creator) > quote);
+        }
+    }
+
+    #[test]
+    fn quote_input_sells_receive_at_least_the_quote() {
+        for (pool, creator, base) in [
+            (LEGACY_POOL, Pubkey::default(), 1_227_486_925_519),
+            (CREATOR_POOL, creator(), 2_056_685_131_971),
+        ] {
+            let quote = 500_000_001;
+            let sell = sell_quote_input_internal(quote, 0, pool.0, pool.1, &creator).unwrap();
+            assert_eq!(sell.base, base);
+            assert!(paid_for_sell(sell.base, pool, &creator) >= quote);
+            assert_eq!(sell.min_quote, quote);
+        }
+    }
+
+    #[test]
+    fn quotes_match_the_captured_trades() {
+        let captures: Value = serde_json::from_str(CAPTURES).unwrap();
+        for capture in captures["pumpswap"].as_array().unwrap() {
+            let amount = |key: &str| capture[key].as_u64().unwrap();
+            let signature = capture["signature"].as_str().unwrap();
+            let coin_creator = Pubkey::from_str(capture["coin_creator"].as_str().unwrap()).unwrap();
+            let (base_reserve, quote_reserve) =
+                (amount("pool_base_token_reserves"), amount("pool_quote_token_reserves"));
+            match capture["side"].as_str().unwrap() {
+                "buy" => {
+                    let buy = buy_base_input_internal(
+                        amount("base_amount_out"),
+                        0,
+                        base_reserve,
+                        quote_reserve,
+                        &coin_creator,
+                    )
+                    .unwrap();
+                    assert_eq!(buy.ui_quote, amount("user_quote_amount_in"), "{}", signature);
+                }
+                "sell" => {
+                    let sell = sell_base_input_internal(
+                        amount("base_amount_in"),
+                        0,
+                        base_reserve,
+                        quote_reserve,
+                        &coin_creator,
+                    )
+                    .unwrap();
+                    assert_eq!(sell.ui_quote, amount("user_quote_amount_out"), "{}", signature);
+                }
+                side => panic!("{}: unknown side {}", signature, side),
+            }
+        }
+    }
+}
@@ -189,3 +189,105 @@ pub fn compute_swap_amount(
         fee: swap_result.trade_fee,
     }
 }
+
+/// Computes the input needed to receive exactly `amount_out` from a CPMM pool.
+///
+/// The inverse of `compute_swap_amount` without slippage, as charged by the program's
+/// `swap_base_output` with the creator fee taken on the input.
+///
+/// # Arguments
+/// * `base_reserve` - The current reserve amount of the base token in the pool
+/// * `quote_reserve` - The current reserve amount of the quote token in the pool
+/// * `is_base_in` - Whether the input token is the base token (true) or quote token (false)
+/// * `amount_out` - The amount of output tokens to receive
+///
+/// # Returns
+/// The input amount including fees, `None` when the pool cannot pay out `amount_out`
+pub fn compute_swap_amount_base_out(
+    base_reserve: u64,
+    quote_reserve: u64,
+    is_base_in: bool,
+    amount_out: u64,
+) -> Option<u64> {
+    let (input_reserve, output_reserve) =
+        if is_base_in { (base_reserve, quote_reserve) } else { (quote_reserve, base_reserve) };
+    if amount_out == 0 || amount_out >= output_reserve {
+        return None;
+    }
+
+    let numerator = (input_reserve as u128) * (amount_out as u128);
+    let denominator = (output_reserve - amount_out) as u128;
+    let input_amount_less_fees = (numerator + denominator - 1) / denominator;
+
+    // Grow the swapped input back by the trade and creator fees charged on it
+    let total_fee_rate = (TRADE_FEE_RATE + CREATOR_FEE_RATE) as u128;
+    let input_amount = (input_amount_less_fees * FEE_RATE_DENOMINATOR_VALUE
+        + (FEE_RATE_DENOMINATOR_VALUE - total_fee_rate)
+        - 1)
+        / (FEE_RATE_DENOMINATOR_VALUE - total_fee_rate);
+
+    u64::try_from(input_amount).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A USDC / token pool: 1M USDC against 50M tokens, both with 6 decimals
+    const USDC_RESERVE: u64 = 1_000_000_000_000;
+    const TOKEN_RESERVE: u64 = 50_000_000_000_000;
+
+    #[test]
+    fn input_on_either_side_swaps_against_its_own_reserve() {
+        // 1000 USDC in, 0.25% trade fee rounded up, then the constant product
+        for (base_reserve, quote_reserve, is_base_in) in
+            [(USDC_RESERVE, TOKEN_RESERVE, true), (TOKEN_RESERVE, USDC_RESERVE, false)]
+        {
+            let result =
+                compute_swap_amount(base_reserve, quote_reserve, is_base_in, 1_000_000_000, 100);
+            assert_eq!(
+                (result.amount_out, result.min_amount_out, result.fee),
+                (49_825_299_263, 49_327_046_270, 2_500_000),
+                "usdc is base: {}",
+                is_base_in
+            );
+            assert!(result.all_trade);
+        }
+
+        // 2M tokens in
+        for (base_reserve, quote_reserve, is_base_in) in
+            [(TOKEN_RESERVE, USDC_RESERVE, true), (USDC_RESERVE, TOKEN_RESERVE, false)]
+        {
+            let result = compute_swap_amount(
+                base_reserve,
+                quote_reserve,
+                is_base_in,
+                2_000_000_000_000,
+                100,
+            );
+            assert_eq!(
+                (result.amount_out, result.min_amount_out, result.fee),
+                (38_369_073_949, 37_985_383_209, 5_000_000_000),
+                "token is base: {}",
+                is_base_in
+            );
+        }
+    }
+
+    #[test]
+    fn base_out_inverts_base_in_on_either_side() {
+        for (base_reserve, quote_reserve, is_base_in) in
+            [(USDC_RESERVE, TOKEN_RESERVE, true), (TOKEN_RESERVE, USDC_RESERVE, false)]
+        {
+            let amount_out = 49_825_299_263;
+            let amount_in =
+                compute_swap_amount_base_out(base_reserve, quote_reserve, is_base_in, amount_out)
+                    .unwrap();
+            let result = compute_swap_amount(base_reserve, quote_reserve, is_base_in, amount_in, 0);
+            assert!(result.amount_out >= amount_out);
+            let short =
+                compute_swap_amount(base_reserve, quote_reserve, is_base_in, amount_in - 1, 0);
+            assert!(short.amount_out < amount_out, "usdc is base: {}", is_base_in);
+        }
+    }
+}
@@ -1,8 +1,13 @@
+pub mod amount;
 pub mod calc;
+pub mod pda;
 pub mod price;
 
+use crate::common::mint_cache::MintDecimalsCache;
 use crate::solana_streamer_sdk::streaming::event_parser::protocols::pumpfun::PumpFunTradeEvent;
 use crate::trading;
+use crate::trading::common::account_cleanup::TokenAccountCleanup;
+use crate::trading::factory::DexType;
 use crate::SolanaTrade;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Keypair;
@@ -53,11 +58,106 @@ impl SolanaTrade {
         trading::common::utils::transfer_sol(&self.rpc, payer, receive_wallet, amount).await
     }
 
+    #[inline]
+    pub async fn transfer_token(
+        &self,
+        payer: &Keypair,
+        receive_wallet: &Pubkey,
+        mint: &Pubkey,
+        amount: Option<u64>,
+    ) -> Result<u64, anyhow::Error> {
+        trading::common::utils::transfer_token(&self.rpc, payer, receive_wallet, mint, amount)
+            .await
+    }
+
     #[inline]
     pub async fn close_token_account(&self, mint: &Pubkey) -> Result<(), anyhow::Error> {
         trading::common::utils::close_token_account(&self.rpc, self.payer.as_ref(), mint).await
     }
 
+    /// Close per-trade wSOL accounts of the payer that a landed trade left open
+    #[inline]
+    pub async fn close_ephemeral_wsol_accounts(&self) -> Result<Vec<Pubkey>, anyhow::Error> {
+        trading::common::utils::close_ephemeral_wsol_accounts(&self.rpc, self.payer.as_ref()).await
+    }
+
+    /// Close the payer's empty token accounts under both token programs, returning their rent
+    ///
+    /// Canonical ATAs and seeded token accounts are closed alike, about 20 per transaction,
+    /// priced with the `rpc_unit_price` of the priority fee. With `burn_dust`, balances below
+    /// one whole token are burned and their accounts closed too. See
+    /// `trading::common::account_cleanup` for the accounts left open.
+    pub async fn close_empty_token_accounts(
+        &self,
+        burn_dust: bool,
+    ) -> Result<TokenAccountCleanup, anyhow::Error> {
+        let accounts = trading::common::account_cleanup::find_closable_token_accounts(
+            &self.rpc,
+            &self.payer.pubkey(),
+            burn_dust,
+        )
+        .await?;
+        trading::common::account_cleanup::close_token_accounts(
+            &self.rpc,
+            self.payer.as_ref(),
+            accounts,
+            &self.priority_fee,
+        )
+        .await
+    }
+
+    /// Create or extend the payer's address lookup table so it holds `accounts`
+    ///
+    /// Returns the table address to add to `lookup_table_keys`
+    #[inline]
+    pub async fn ensure_lookup_table(
+        &self,
+        accounts: Vec<Pubkey>,
+    ) -> Result<Pubkey, anyhow::Error> {
+        trading::common::ensure_lookup_table(self.rpc.clone(), self.payer.as_ref(), accounts).await
+    }
+
+    /// Static accounts the builders of `dex_type` reference for `mint`, see `ensure_lookup_table`
+    #[inline]
+    pub fn suggest_alt_accounts(&self, dex_type: DexType, mint: &Pubkey) -> Vec<Pubkey> {
+        trading::common::suggest_alt_accounts(dex_type, mint)
+    }
+
+    /// Decimals of `mint`, fetched once and cached for the lifetime of the process
+    #[inline]
+    pub async fn get_mint_decimals(&self, mint: &Pubkey) -> Result<u8, anyhow::Error> {
+        MintDecimalsCache::get_instance().fetch(&self.rpc, mint).await
+    }
+
+    /// Format a raw amount of `mint`, e.g. `1,234.5`, fetching its decimals on a cache miss
+    #[inline]
+    pub async fn format_amount(&self, mint: &Pubkey, raw: u64) -> Result<String, anyhow::Error> {
+        let decimals = self.get_mint_decimals(mint).await?;
+        Ok(amount::format_amount(raw, decimals, self.amount_precision))
+    }
+
+    /// `format_amount` without RPC calls, errors when the decimals of `mint` are not cached
+    #[inline]
+    pub fn format_amount_cached(&self, mint: &Pubkey, raw: u64) -> Result<String, anyhow::Error> {
+        let decimals = MintDecimalsCache::get_instance().require(mint)?;
+        Ok(amount::format_amount(raw, decimals, self.amount_precision))
+    }
+
+    /// Parse an amount of `mint` such as `12.5` into its raw amount, fetching its decimals on a
+    /// cache miss
+    #[inline]
+    pub async fn parse_amount(&self, mint: &Pubkey, text: &str) -> Result<u64, anyhow::Error> {
+        let decimals = self.get_mint_decimals(mint).await?;
+        amount::parse_amount(text, decimals)
+    }
+
+    /// `parse_amount` without RPC calls, errors when the decimals of `mint` are not cached
+    #[inline]
+    pub fn parse_amount_cached(&self, mint: &Pubkey, text: &str) -> Result<u64, anyhow::Error> {
+        let decimals = MintDecimalsCache::get_instance().require(mint)?;
+        amount::parse_amount(text, decimals)
+    }
+
     // -------------------------------- PumpFun --------------------------------
 
     #[inline]
@@ -0,0 +1,168 @@
+//! All PDA derivations of the SDK in one place
+//!
+//! Used to check that other tooling (indexers, TypeScript clients) derives the same addresses.
+//! The `pda_vectors` example prints these derivations as JSON for a given mint and wallet, in
+//! the format of the expected vectors in `tests/fixtures/pda_vectors.json`.
+
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::constants::{TOKEN_PROGRAM, WSOL_TOKEN_ACCOUNT};
+use crate::instruction::utils::{bonk, pumpfun, pumpswap, raydium_cpmm};
+
+/// A named PDA derivation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PdaDerivation {
+    /// `<protocol>.<account>`, e.g. `pumpfun.bonding_curve`
+    pub name: &'static str,
+    /// Inputs of the derivation, in seed order
+    pub inputs: Vec<String>,
+    /// Derived address, `None` if no valid bump was found
+    pub address: Option<String>,
+}
+
+fn derivation(name: &'static str, inputs: &[&Pubkey], address: Option<Pubkey>) -> PdaDerivation {
+    PdaDerivation {
+        name,
+        inputs: inputs.iter().map(|p| p.to_string()).collect(),
+        address: address.map(|p| p.to_string()),
+    }
+}
+
+/// Derive every PDA used by the SDK for `mint` traded by `wallet`
+///
+/// `wallet` is also used as the creator for creator vault derivations, quote mints are wSOL.
+/// `cpmm_amm_config` is the Raydium CPMM amm config the pool was created with.
+pub fn derive_pdas(mint: &Pubkey, wallet: &Pubkey, cpmm_amm_config: &Pubkey) -> Vec<PdaDerivation> {
+    let bonk_pool = bonk::get_pool_pda(mint, &WSOL_TOKEN_ACCOUNT);
+    // CPMM pools order their mints by address
+    let (cpmm_mint0, cpmm_mint1) = if WSOL_TOKEN_ACCOUNT < *mint {
+        (WSOL_TOKEN_ACCOUNT, *mint)
+    } else {
+        (*mint, WSOL_TOKEN_ACCOUNT)
+    };
+    let cpmm_pool = raydium_cpmm::get_pool_pda(cpmm_amm_config, &cpmm_mint0, &cpmm_mint1);
+
+    let mut derivations = vec![
+        // PumpFun
+        derivation("pumpfun.bonding_curve", &[mint], pumpfun::get_bonding_curve_pda(mint)),
+        derivation("pumpfun.creator_vault", &[wallet], pumpfun::get_creator_vault_pda(wallet)),
+        derivation(
+            "pumpfun.user_volume_accumulator",
+            &[wallet],
+            pumpfun::get_user_volume_accumulator_pda(wallet),
+        ),
+        // PumpSwap
+        derivation(
+            "pumpswap.coin_creator_vault_authority",
+            &[wallet],
+            Some(pumpswap::coin_creator_vault_authority(*wallet)),
+        ),
+        derivation(
+            "pumpswap.coin_creator_vault_ata",
+            &[wallet, &WSOL_TOKEN_ACCOUNT],
+            Some(pumpswap::coin_creator_vault_ata(*wallet, WSOL_TOKEN_ACCOUNT, &TOKEN_PROGRAM)),
+        ),
+        derivation(
+            "pumpswap.user_volume_accumulator",
+            &[wallet],
+            pumpswap::get_user_volume_accumulator_pda(wallet),
+        ),
+        derivation(
+            "pumpswap.global_volume_accumulator",
+            &[],
+            pumpswap::get_global_volume_accumulator_pda(),
+        ),
+        derivation("pumpswap.fee_config", &[], pumpswap::get_fee_config_pda()),
+        // Bonk
+        derivation("bonk.pool", &[mint, &WSOL_TOKEN_ACCOUNT], bonk_pool),
+    ];
+
+    if let Some(pool) = bonk_pool {
+        derivations.push(derivation(
+            "bonk.base_vault",
+            &[&pool, mint],
+            bonk::get_vault_pda(&pool, mint),
+        ));
+        derivations.push(derivation(
+            "bonk.quote_vault",
+            &[&pool, &WSOL_TOKEN_ACCOUNT],
+            bonk::get_vault_pda(&pool, &WSOL_TOKEN_ACCOUNT),
+        ));
+    }
+
+    // Raydium CPMM
+    derivations.push(derivation(
+        "raydium_cpmm.pool",
+        &[cpmm_amm_config, &cpmm_mint0, &cpmm_mint1],
+        cpmm_pool,
+    ));
+    if let Some(pool) = cpmm_pool {
+        derivations.push(derivation(
+            "raydium_cpmm.token_vault",
+            &[&pool, mint],
+            raydium_cpmm::get_vault_pda(&pool, mint),
+        ));
+        derivations.push(derivation(
+            "raydium_cpmm.wsol_vault",
+            &[&pool, &WSOL_TOKEN_ACCOUNT],
+            raydium_cpmm::get_vault_pda(&pool, &WSOL_TOKEN_ACCOUNT),
+        ));
+        derivations.push(derivation(
+            "raydium_cpmm.observation_state",
+            &[&pool],
+            raydium_cpmm::get_observation_state_pda(&pool),
+        ));
+    }
+
+    derivations
+}
+
+/// Print every PDA derivation for `mint` and `wallet`, one per line
+pub fn verify_pdas(mint: &Pubkey, wallet: &Pubkey, cpmm_amm_config: &Pubkey) {
+    for d in derive_pdas(mint, wallet, cpmm_amm_config) {
+        println!(
+            "{:<40} {:<46} [{}]",
+            d.name,
+            d.address.as_deref().unwrap_or("<no valid bump>"),
+            d.inputs.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod pda_vectors {
+    use std::str::FromStr;
+
+    use serde_json::Value;
+
+    use super::*;
+
+    /// Derivations of fixed inputs, computed outside this crate from the programs' seeds
+    const VECTORS: &str = include_str!("../../tests/fixtures/pda_vectors.json");
+
+    #[test]
+    fn derivations_match_the_committed_vectors() {
+        let cases: Vec<Value> = serde_json::from_str(VECTORS).unwrap();
+        assert!(!cases.is_empty());
+        for case in &cases {
+            let pubkey = |field: &str| Pubkey::from_str(case[field].as_str().unwrap()).unwrap();
+            let derivations =
+                derive_pdas(&pubkey("mint"), &pubkey("wallet"), &pubkey("cpmm_amm_config"));
+            let expected = case["derivations"].as_array().unwrap();
+            let names: Vec<&str> = derivations.iter().map(|d| d.name).collect();
+            let expected_names: Vec<&str> =
+                expected.iter().map(|d| d["name"].as_str().unwrap()).collect();
+            assert_eq!(names, expected_names, "derivations of mint {}", case["mint"]);
+            for (derivation, expected) in derivations.iter().zip(expected) {
+                assert_eq!(
+                    &serde_json::to_value(derivation).unwrap(),
+                    expected,
+                    "{} of mint {}",
+                    derivation.name,
+                    case["mint"]
+                );
+            }
+        }
+    }
+}
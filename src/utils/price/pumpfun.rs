@@ -1,6 +1,8 @@
 use solana_streamer_sdk::streaming::event_parser::protocols::pumpfun::types::BondingCurve;
 
-use crate::instruction::utils::pumpfun::global_constants::{LAMPORTS_PER_SOL, SCALE};
+use crate::instruction::utils::pumpfun::global_constants::{
+    INITIAL_REAL_TOKEN_RESERVES, LAMPORTS_PER_SOL, SCALE,
+};
 
 /// Calculate the token price in SOL based on virtual reserves
 ///
@@ -29,3 +31,16 @@ pub fn price_token_in_sol(virtual_sol_reserves: u64, virtual_token_reserves: u64
     }
     v_sol / v_tokens
 }
+
+/// Calculate how far the bonding curve has progressed towards completion
+///
+/// # Arguments
+/// * `real_token_reserves` - Real token reserves left in the bonding curve
+///
+/// # Returns
+/// Progress in percent, from 0.0 to 100.0
+pub fn curve_progress(real_token_reserves: u64) -> f64 {
+    let initial = INITIAL_REAL_TOKEN_RESERVES as f64;
+    let sold = initial - (real_token_reserves as f64).min(initial);
+    sold / initial * 100.0
+}
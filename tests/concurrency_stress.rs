@@ -0,0 +1,279 @@
+use sol_trade_sdk::common::address_lookup_cache::AddressLookupTableCache;
+use sol_trade_sdk::common::balance_cache::TokenBalanceCache;
+use sol_trade_sdk::common::bonding_curve::BondingCurveAccount;
+use sol_trade_sdk::common::mint_cache::MintDecimalsCache;
+use sol_trade_sdk::common::nonce_cache::NonceCache;
+use sol_trade_sdk::common::{fast_fn, seed};
+use sol_trade_sdk::instruction::data::{decode_instruction, DecodedInstruction};
+use sol_trade_sdk::instruction::pumpfun::PumpFunInstructionBuilder;
+use sol_trade_sdk::instruction::utils::pumpfun::{
+    accounts, get_bonding_curve_pda, get_creator_vault_pda, global_constants,
+};
+use sol_trade_sdk::prelude::*;
+use sol_trade_sdk::trading::{BuyParams, InstructionBuilder, SellParams};
+use sol_trade_sdk::utils::calc::common::calculate_with_slippage_buy;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const TASKS: usize = 200;
+const OPERATIONS_PER_TASK: usize = 50;
+const MINTS: usize = 64;
+const PAYERS: usize = 8;
+const MINT_DECIMALS: u8 = 6;
+const SPL_TOKEN_RENT: u64 = 2_039_280;
+const SLIPPAGE_BASIS_POINTS: u64 = 500;
+const DEADLOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Hammer the SDK's global caches from many tokio tasks at once
+///
+/// Runs offline: ATA and PDA lookups, PumpFun buy/sell instruction builds, rent, nonce, token
+/// balance, mint decimals and lookup table cache accesses are mixed across tasks, every result is
+/// checked against an uncached computation or the value just written, and the test fails if it
+/// does not finish within `DEADLOCK_TIMEOUT`.
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_mixed_operations_stay_consistent() {
+    let mints: Arc<Vec<Pubkey>> = Arc::new((0..MINTS).map(|_| Pubkey::new_unique()).collect());
+    let payers: Arc<Vec<Arc<Keypair>>> =
+        Arc::new((0..PAYERS).map(|_| Arc::new(Keypair::new())).collect());
+    let operations = Arc::new(AtomicU64::new(0));
+    let started = Instant::now();
+
+    let mut handles = Vec::with_capacity(TASKS);
+    for task in 0..TASKS {
+        let mints = mints.clone();
+        let payers = payers.clone();
+        let operations = operations.clone();
+        handles.push(tokio::spawn(async move {
+            // Each task owns a wallet so its balance cache reads only race with its own writes
+            let wallet = Pubkey::new_unique();
+            for i in 0..OPERATIONS_PER_TASK {
+                let mint = mints[(task * 7 + i) % mints.len()];
+                let payer = payers[(task + i) % payers.len()].clone();
+                match (task + i) % 7 {
+                    0 => check_ata(&payer.pubkey(), &mint)?,
+                    1 => check_pda(&mint)?,
+                    2 => check_trade_instructions(payer, mint).await?,
+                    3 => check_rent()?,
+                    4 => check_nonce((task * OPERATIONS_PER_TASK + i) as u8)?,
+                    5 => check_balance_and_decimals(&wallet, &mint, (task * i) as u64)?,
+                    _ => check_lookup_tables(&payer.pubkey(), &mint)?,
+                }
+                operations.fetch_add(1, Ordering::Relaxed);
+                if i % 10 == 0 {
+                    tokio::task::yield_now().await;
+                }
+            }
+            Ok::<(), anyhow::Error>(())
+        }));
+    }
+
+    let results = tokio::time::timeout(DEADLOCK_TIMEOUT, join_tasks(handles))
+        .await
+        .unwrap_or_else(|_| panic!("Not finished after {:?}, possible deadlock", DEADLOCK_TIMEOUT));
+    for result in results {
+        result.expect("Stress task panicked").expect("Stress task failed");
+    }
+
+    let total = operations.load(Ordering::Relaxed);
+    assert_eq!(total as usize, TASKS * OPERATIONS_PER_TASK);
+    let stats = fast_fn::cache_stats();
+    assert!(stats.ata.hits + stats.ata.misses > 0);
+    assert!(stats.pda.hits + stats.pda.misses > 0);
+    println!("{} operations in {:?}, cache stats: {:?}", total, started.elapsed(), stats);
+}
+
+async fn join_tasks(
+    handles: Vec<tokio::task::JoinHandle<Result<(), anyhow::Error>>>,
+) -> Vec<Result<Result<(), anyhow::Error>, tokio::task::JoinError>> {
+    let mut results = Vec::with_capacity(handles.len());
+    for handle in handles {
+        results.push(handle.await);
+    }
+    results
+}
+
+fn check_ata(owner: &Pubkey, mint: &Pubkey) -> anyhow::Result<()> {
+    let token_program = sol_trade_sdk::constants::TOKEN_PROGRAM;
+    let cached =
+        fast_fn::get_associated_token_address_with_program_id_fast(owner, mint, &token_program);
+    let expected = spl_associated_token_account::get_associated_token_address_with_program_id(
+        owner,
+        mint,
+        &token_program,
+    );
+    anyhow::ensure!(cached == expected, "ATA cache mismatch for {} / {}", owner, mint);
+    Ok(())
+}
+
+fn check_pda(mint: &Pubkey) -> anyhow::Result<()> {
+    let cached = get_bonding_curve_pda(mint).ok_or_else(|| anyhow::anyhow!("No PDA"))?;
+    let (expected, _) =
+        Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &accounts::PUMPFUN);
+    anyhow::ensure!(cached == expected, "PDA cache mismatch for {}", mint);
+    Ok(())
+}
+
+fn check_rent() -> anyhow::Result<()> {
+    seed::set_rent(false, SPL_TOKEN_RENT);
+    let rent = seed::get_rent(false);
+    anyhow::ensure!(rent == Some(SPL_TOKEN_RENT), "Unexpected rent {:?}", rent);
+    Ok(())
+}
+
+/// Nonce and blockhash are written under one lock, so a reader never sees them from two writes
+fn check_nonce(seed: u8) -> anyhow::Result<()> {
+    let cache = NonceCache::get_instance();
+    cache.update_nonce_info_partial(
+        None,
+        Some(Hash::new_from_array([seed; 32])),
+        Some(seed as i64),
+        Some(false),
+    );
+    let info = cache.get_nonce_info();
+    let expected = Hash::new_from_array([info.next_buy_time as u8; 32]);
+    anyhow::ensure!(
+        info.current_nonce == expected,
+        "Torn nonce info: {} at {}",
+        info.current_nonce,
+        info.next_buy_time
+    );
+    Ok(())
+}
+
+fn check_balance_and_decimals(wallet: &Pubkey, mint: &Pubkey, amount: u64) -> anyhow::Result<()> {
+    let balances = TokenBalanceCache::get_instance();
+    balances.insert(*wallet, *mint, amount);
+    let cached = balances.get(wallet, mint);
+    anyhow::ensure!(cached == Some(amount), "Unexpected balance {:?}", cached);
+    balances.invalidate(wallet, mint);
+    anyhow::ensure!(balances.get(wallet, mint).is_none(), "Balance not invalidated");
+
+    let decimals = MintDecimalsCache::get_instance();
+    decimals.insert(*mint, MINT_DECIMALS);
+    let cached = decimals.get(mint);
+    anyhow::ensure!(cached == Some(MINT_DECIMALS), "Unexpected decimals {:?}", cached);
+    Ok(())
+}
+
+/// Scan the lookup table cache while other tasks drop entries from it
+fn check_lookup_tables(authority: &Pubkey, table: &Pubkey) -> anyhow::Result<()> {
+    let cache = AddressLookupTableCache::get_instance();
+    cache.remove_address_lookup_table(table);
+    anyhow::ensure!(
+        cache.find_table_by_authority(authority).is_none(),
+        "Found a lookup table that was never cached"
+    );
+    Ok(())
+}
+
+/// Build PumpFun buy and sell instructions without RPC and check the encoded amounts
+async fn check_trade_instructions(payer: Arc<Keypair>, mint: Pubkey) -> anyhow::Result<()> {
+    let creator = Pubkey::new_from_array([7; 32]);
+    let creator_vault = get_creator_vault_pda(&creator).unwrap();
+    let mut protocol_params = PumpFunParams::immediate_sell(creator_vault, false);
+    protocol_params.bonding_curve = Arc::new(BondingCurveAccount {
+        account: get_bonding_curve_pda(&mint).unwrap(),
+        virtual_token_reserves: global_constants::INITIAL_VIRTUAL_TOKEN_RESERVES,
+        virtual_sol_reserves: global_constants::INITIAL_VIRTUAL_SOL_RESERVES,
+        real_token_reserves: global_constants::INITIAL_REAL_TOKEN_RESERVES,
+        token_total_supply: global_constants::TOKEN_TOTAL_SUPPLY,
+        creator,
+        ..Default::default()
+    });
+    let builder = PumpFunInstructionBuilder;
+
+    let sol_amount = 10_000_000;
+    let buy_params = BuyParams {
+        rpc: None,
+        payer: KeypairSigner::wrap(payer.clone()),
+        mint,
+        sol_amount,
+        amount_spec: BuyAmountSpec::ExactSolIn(sol_amount),
+        trade_type: TradeType::Buy,
+        slippage_basis_points: Some(SLIPPAGE_BASIS_POINTS),
+        min_amount_out: None,
+        priority_fee: Arc::new(PriorityFee::default()),
+        lookup_table_keys: Vec::new(),
+        recent_blockhash: Hash::default(),
+        data_size_limit: None,
+        wait_level: WaitLevel::Submitted,
+        protocol_params: Box::new(protocol_params.clone()),
+        open_seed_optimize: false,
+        swqos_clients: vec![],
+        middleware_manager: None,
+        create_wsol_ata: false,
+        close_wsol_ata: false,
+        create_mint_ata: true,
+        spend_tracker: None,
+        analysis_callback: None,
+        commission: None,
+        labels: None,
+        success_policy: SuccessPolicy::FirstAccepted,
+        created_account_check: CreatedAccountCheck::Warn,
+        wsol_account: WsolAccountChoice::CanonicalAta,
+        cu_sizing: CuSizing::Static,
+        swap_hooks: SwapHooks::default(),
+        use_durable_nonce: false,
+        blockhash_retry: BlockhashRetry::default(),
+        memo: None,
+        deadline: None,
+    };
+    let instructions = builder.build_buy_instructions(&buy_params).await?;
+    let swap = instructions.last().ok_or_else(|| anyhow::anyhow!("No buy instruction"))?;
+    match decode_instruction(&swap.program_id, &swap.data) {
+        Some(DecodedInstruction::PumpFunBuy(args)) => anyhow::ensure!(
+            args.max_sol_cost == calculate_with_slippage_buy(sol_amount, SLIPPAGE_BASIS_POINTS),
+            "Unexpected max_sol_cost {}",
+            args.max_sol_cost
+        ),
+        other => anyhow::bail!("Unexpected buy instruction {:?}", other),
+    }
+
+    let token_amount = 1_000_000_000;
+    let sell_params = SellParams {
+        rpc: None,
+        payer: KeypairSigner::wrap(payer),
+        mint,
+        token_amount: Some(token_amount),
+        slippage_basis_points: Some(SLIPPAGE_BASIS_POINTS),
+        min_sol_out: None,
+        priority_fee: Arc::new(PriorityFee::default()),
+        lookup_table_keys: Vec::new(),
+        recent_blockhash: Hash::default(),
+        data_size_limit: None,
+        wait_level: WaitLevel::Submitted,
+        with_tip: false,
+        protocol_params: Box::new(protocol_params),
+        open_seed_optimize: false,
+        swqos_clients: vec![],
+        middleware_manager: None,
+        create_wsol_ata: false,
+        close_wsol_ata: false,
+        spend_tracker: None,
+        analysis_callback: None,
+        clamp_to_balance: false,
+        expected_min_balance: None,
+        commission: None,
+        labels: None,
+        success_policy: SuccessPolicy::FirstAccepted,
+        created_account_check: CreatedAccountCheck::Warn,
+        wsol_account: WsolAccountChoice::CanonicalAta,
+        cu_sizing: CuSizing::Static,
+        swap_hooks: SwapHooks::default(),
+        use_durable_nonce: false,
+        blockhash_retry: BlockhashRetry::default(),
+        memo: None,
+        deadline: None,
+    };
+    let instructions = builder.build_sell_instructions(&sell_params).await?;
+    let swap = instructions.last().ok_or_else(|| anyhow::anyhow!("No sell instruction"))?;
+    match decode_instruction(&swap.program_id, &swap.data) {
+        Some(DecodedInstruction::PumpFunSell(args)) => {
+            anyhow::ensure!(args.amount == token_amount, "Unexpected sell amount {}", args.amount);
+            anyhow::ensure!(args.min_sol_output > 0, "Sell without minimum output");
+        }
+        other => anyhow::bail!("Unexpected sell instruction {:?}", other),
+    }
+    Ok(())
+}
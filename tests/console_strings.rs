@@ -0,0 +1,72 @@
+//! Plain text check of the crate's messages
+//!
+//! Error messages and log lines end up in alerting pipelines that mangle color codes and
+//! non-English text. Every string and char literal under `src/` must be ASCII without an ANSI
+//! escape sequence, except the marker argument of `icon!`, which the `plain-console` feature
+//! drops.
+
+mod support;
+
+use std::{fs, path::Path};
+use support::scan;
+
+/// Escapes that start an ANSI color or cursor sequence, as written in Rust source
+const ESCAPES: &[&str] = &["\\x1b", "\\x1B", "\\u{1b}", "\\u{1B}", "\\033", "\\e["];
+
+#[test]
+fn messages_are_ascii_without_color_codes() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut offending = Vec::new();
+    for file in rust_files(&root.join("src")) {
+        let source = fs::read_to_string(&file).expect("read a source file");
+        let (code, literals) = scan(&source);
+        for literal in literals {
+            if is_icon_marker(&code[..literal.offset]) {
+                continue;
+            }
+            let non_ascii = !literal.text.is_ascii();
+            let escape = ESCAPES.iter().any(|escape| literal.text.contains(escape));
+            if non_ascii || escape || literal.text.contains('\u{1b}') {
+                offending.push(format!(
+                    "{}:{}: {:?}",
+                    file.strip_prefix(root).unwrap().display(),
+                    literal.line,
+                    literal.text
+                ));
+            }
+        }
+    }
+    assert!(
+        offending.is_empty(),
+        "Literals with non-ASCII text or ANSI escapes, wrap markers in `icon!`:\n{}",
+        offending.join("\n")
+    );
+}
+
+#[test]
+fn icon_markers_are_recognized_across_lines() {
+    let source = "println!(icon!(\n    \"✅ \",\n    \"Done {}\"\n), x); let s = \"✅\";";
+    let (code, literals) = scan(source);
+    let markers: Vec<bool> =
+        literals.iter().map(|literal| is_icon_marker(&code[..literal.offset])).collect();
+    assert_eq!(markers, vec![true, false, false]);
+}
+
+/// Whether the literal following `preceding_code` is the first argument of `icon!`
+fn is_icon_marker(preceding_code: &str) -> bool {
+    preceding_code.trim_end().ends_with("icon!(")
+}
+
+/// `.rs` files under `dir`, recursively
+fn rust_files(dir: &Path) -> Vec<std::path::PathBuf> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(rust_files(&path));
+        } else if path.extension().is_some_and(|extension| extension == "rs") {
+            files.push(path);
+        }
+    }
+    files
+}
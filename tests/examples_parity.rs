@@ -0,0 +1,156 @@
+//! Examples parity check
+//!
+//! Every example crate must be a workspace member, so `cargo check --workspace` builds it
+//! against the current API, and every `pub async fn` of `SolanaTrade` must be called by some
+//! example with as many arguments as it takes. A new entry point without an example, or a
+//! signature change the examples were not updated for, fails the test.
+
+mod support;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use support::{matching_close, strip_comments_and_literals};
+
+#[test]
+fn every_example_crate_is_a_workspace_member() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let members: BTreeSet<String> = workspace_members(root).into_iter().collect();
+    let crates: BTreeSet<String> = fs::read_dir(root.join("examples"))
+        .expect("read the examples directory")
+        .map(|entry| entry.expect("read an examples entry").path())
+        .filter(|path| path.join("Cargo.toml").is_file())
+        .map(|path| format!("examples/{}", path.file_name().unwrap().to_string_lossy()))
+        .collect();
+
+    let missing: Vec<_> = crates.difference(&members).collect();
+    assert!(missing.is_empty(), "Example crates missing from the workspace members: {:?}", missing);
+    let stale: Vec<_> =
+        members.iter().filter(|member| !root.join(member).join("Cargo.toml").is_file()).collect();
+    assert!(stale.is_empty(), "Workspace members without a crate: {:?}", stale);
+}
+
+#[test]
+fn every_entry_point_is_called_by_an_example_with_its_arity() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let lib = fs::read_to_string(root.join("src/lib.rs")).expect("read src/lib.rs");
+    let entry_points = entry_points(&strip_comments_and_literals(&lib));
+    assert!(!entry_points.is_empty(), "No `pub async fn` found in `impl SolanaTrade`");
+
+    let mut sources = Vec::new();
+    for member in workspace_members(root).iter().filter(|member| member.starts_with("examples/")) {
+        for file in rust_files(&root.join(member).join("src")) {
+            let source = fs::read_to_string(&file).expect("read an example source file");
+            sources.push(strip_comments_and_literals(&source));
+        }
+    }
+
+    let mut missing = Vec::new();
+    for (name, arity) in &entry_points {
+        let arities: BTreeSet<usize> =
+            sources.iter().flat_map(|source| call_arities(source, name)).collect();
+        if !arities.contains(arity) {
+            missing.push(format!(
+                "{} takes {} arguments, examples call it with {:?}",
+                name, arity, arities
+            ));
+        }
+    }
+    assert!(
+        missing.is_empty(),
+        "Entry points of SolanaTrade without a matching example call:\n{}",
+        missing.join("\n")
+    );
+}
+
+/// Paths of the `[workspace] members` of the root manifest
+fn workspace_members(root: &Path) -> Vec<String> {
+    let manifest = fs::read_to_string(root.join("Cargo.toml")).expect("read Cargo.toml");
+    let start = manifest.find("members = [").expect("workspace members in Cargo.toml");
+    let list = &manifest[start..];
+    let list = &list[..list.find(']').expect("closing `]` of the workspace members")];
+    list.split('"').skip(1).step_by(2).map(str::to_string).collect()
+}
+
+/// `.rs` files under `dir`, recursively
+fn rust_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(rust_files(&path));
+        } else if path.extension().is_some_and(|extension| extension == "rs") {
+            files.push(path);
+        }
+    }
+    files
+}
+
+/// Name and argument count, without `self`, of every `pub async fn` of `impl SolanaTrade`
+fn entry_points(code: &str) -> BTreeMap<String, usize> {
+    let start = code.find("impl SolanaTrade {").expect("`impl SolanaTrade` in src/lib.rs");
+    let open = start + code[start..].find('{').unwrap();
+    let body = &code[open..=matching_close(code, open)];
+
+    let mut entry_points = BTreeMap::new();
+    for (index, _) in body.match_indices("pub async fn ") {
+        let rest = &body[index + "pub async fn ".len()..];
+        let name: String = rest.chars().take_while(|c| c.is_alphanumeric() || *c == '_').collect();
+        let open = index + "pub async fn ".len() + rest.find('(').unwrap();
+        let params = &body[open + 1..matching_close(body, open)];
+        let arity = split_arguments(params, true)
+            .iter()
+            .filter(|param| !matches!(param.as_str(), "self" | "&self" | "&mut self" | "mut self"))
+            .count();
+        entry_points.insert(name, arity);
+    }
+    entry_points
+}
+
+/// Argument counts of the `.name(..)` and `::name(..)` calls in `code`
+fn call_arities(code: &str, name: &str) -> Vec<usize> {
+    let mut arities = Vec::new();
+    for prefix in [".", "::"] {
+        let call = format!("{}{}(", prefix, name);
+        for (index, _) in code.match_indices(&call) {
+            let open = index + call.len() - 1;
+            let arguments = &code[open + 1..matching_close(code, open)];
+            arities.push(split_arguments(arguments, false).len());
+        }
+    }
+    arities
+}
+
+/// Top-level comma-separated parts of a parameter or argument list, trimmed, empty ones dropped
+///
+/// Parameter lists also nest in `<..>`, argument lists do not, since there `<` is more often
+/// a comparison than a generic.
+fn split_arguments(list: &str, generics: bool) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut current = String::new();
+    let mut previous = ' ';
+    for c in list.chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '<' if generics => depth += 1,
+            // `->` of a fn pointer type closes nothing
+            '>' if generics && previous != '-' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(current.trim().to_string());
+                current.clear();
+                previous = c;
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+        previous = c;
+    }
+    parts.push(current.trim().to_string());
+    parts.retain(|part| !part.is_empty());
+    parts
+}
@@ -0,0 +1,261 @@
+//! Public API surface check
+//!
+//! Lists the public items of the core modules (`SolanaTrade`, the trade params and options,
+//! `TradeConfig`, `SwqosConfig` and the swqos client trait, `DexType`) and compares them with
+//! `tests/public_api.txt`. A signature change, a new positional parameter or a removed field
+//! fails the test until the listing is updated on purpose with
+//!
+//! ```text
+//! UPDATE_PUBLIC_API=1 cargo test --test public_api
+//! ```
+
+mod support;
+
+use std::{env, fs, path::Path};
+use support::{matching_close, strip_comments_and_literals};
+
+/// Files whose public items are listed, relative to the crate root
+const SURFACE_FILES: &[&str] = &[
+    "src/lib.rs",
+    "src/params.rs",
+    "src/common/types.rs",
+    "src/swqos/mod.rs",
+    "src/trading/core/options.rs",
+    "src/trading/core/params.rs",
+    "src/trading/factory.rs",
+];
+
+const EXPECTED: &str = "tests/public_api.txt";
+
+#[test]
+fn public_api_matches_the_checked_in_listing() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut listing = String::new();
+    for file in SURFACE_FILES {
+        let source = fs::read_to_string(root.join(file)).expect("read source file");
+        for item in public_items(&source) {
+            listing.push_str(&format!("{}: {}\n", file, item));
+        }
+    }
+
+    let expected_path = root.join(EXPECTED);
+    if env::var_os("UPDATE_PUBLIC_API").is_some() {
+        fs::write(&expected_path, &listing).expect("write the API listing");
+        return;
+    }
+    let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+    if expected == listing {
+        return;
+    }
+    let expected_lines: Vec<&str> = expected.lines().collect();
+    let actual_lines: Vec<&str> = listing.lines().collect();
+    let mut diff = String::new();
+    for line in expected_lines.iter().filter(|line| !actual_lines.contains(line)) {
+        diff.push_str(&format!("- {}\n", line));
+    }
+    for line in actual_lines.iter().filter(|line| !expected_lines.contains(line)) {
+        diff.push_str(&format!("+ {}\n", line));
+    }
+    panic!(
+        "The public API changed. If this is intended, rerun with UPDATE_PUBLIC_API=1 and commit \
+         {}:\n{}",
+        EXPECTED, diff
+    );
+}
+
+/// Public items of a source file, one normalized signature per entry, in source order
+fn public_items(source: &str) -> Vec<String> {
+    let code = strip_comments_and_literals(source);
+    let mut items = Vec::new();
+    collect_items(&code, &Scope::Module, &mut items);
+    items
+}
+
+#[derive(PartialEq)]
+enum Scope {
+    Module,
+    /// Inherent impl of the named type, its `pub fn`s are listed
+    Impl(String),
+    /// Trait definition, every `fn` is listed
+    Trait(String),
+}
+
+/// List the items of a module, impl or trait body
+fn collect_items(code: &str, scope: &Scope, items: &mut Vec<String>) {
+    let mut rest = code;
+    loop {
+        let Some(end) = rest.find(|c| c == '{' || c == ';') else {
+            return;
+        };
+        let header = strip_attributes(&rest[..end]);
+        if rest.as_bytes()[end] == b';' {
+            if let Some(item) = declaration(&header, scope) {
+                items.push(item);
+            }
+            rest = &rest[end + 1..];
+            continue;
+        }
+        let close = matching_close(rest, end);
+        let body = &rest[end + 1..close];
+        rest = &rest[close + 1..];
+        block_items(&header, body, scope, items);
+    }
+}
+
+/// Items of an item with a body: the item itself and, for types and impls, its members
+fn block_items(header: &str, body: &str, scope: &Scope, items: &mut Vec<String>) {
+    let listed = |header: &str| match scope {
+        Scope::Module => header.to_string(),
+        Scope::Impl(name) => format!("impl {}: {}", name, header),
+        Scope::Trait(name) => format!("trait {}: {}", name, header),
+    };
+    if header.starts_with("pub use ") && *scope == Scope::Module {
+        // A grouped re-export, `pub use path::{A, B}`
+        for name in split_top_level(body) {
+            items.push(format!("{}{}", header, name));
+        }
+    } else if header.starts_with("pub const ") || header.starts_with("pub static ") {
+        // An initializer holding a block, e.g. a lazy static's closure
+        items.push(listed(&without_initializer(header)));
+    } else if let Some(name) = item_name(header, "struct") {
+        if header.starts_with("pub ") {
+            items.push(listed(header));
+            for field in split_top_level(body).iter().filter(|field| field.starts_with("pub ")) {
+                items.push(format!("{}.{}", name, field.trim_start_matches("pub ")));
+            }
+        }
+    } else if let Some(name) = item_name(header, "enum") {
+        if header.starts_with("pub ") {
+            items.push(listed(header));
+            for variant in split_top_level(body) {
+                items.push(format!("{}::{}", name, variant));
+            }
+        }
+    } else if let Some(name) = item_name(header, "trait") {
+        if header.starts_with("pub ") {
+            items.push(listed(header));
+            collect_items(body, &Scope::Trait(name), items);
+        }
+    } else if header.starts_with("impl") && *scope == Scope::Module {
+        if header.contains(" for ") {
+            items.push(header.to_string());
+        } else {
+            let name = header.rsplit(' ').next().unwrap_or_default().to_string();
+            collect_items(body, &Scope::Impl(name), items);
+        }
+    } else if is_fn(header) {
+        let listed_fn = match scope {
+            Scope::Trait(_) => true,
+            _ => header.starts_with("pub "),
+        };
+        if listed_fn {
+            items.push(listed(header));
+        }
+    }
+}
+
+/// A declaration ending in `;`: a constant, type alias, re-export, tuple struct or trait fn
+fn declaration(header: &str, scope: &Scope) -> Option<String> {
+    if header.is_empty() {
+        return None;
+    }
+    match scope {
+        Scope::Trait(name) if is_fn(header) => Some(format!("trait {}: {}", name, header)),
+        Scope::Trait(_) => None,
+        Scope::Impl(name) if header.starts_with("pub ") => {
+            Some(format!("impl {}: {}", name, without_initializer(header)))
+        }
+        Scope::Impl(_) => None,
+        Scope::Module if header.starts_with("pub ") => Some(without_initializer(header)),
+        Scope::Module => None,
+    }
+}
+
+fn is_fn(header: &str) -> bool {
+    header
+        .trim_start_matches("pub(crate) ")
+        .trim_start_matches("pub ")
+        .trim_start_matches("const ")
+        .trim_start_matches("async ")
+        .trim_start_matches("unsafe ")
+        .starts_with("fn ")
+}
+
+/// Name of the `keyword` item declared by `header`
+fn item_name(header: &str, keyword: &str) -> Option<String> {
+    let mut words = header.split(|c: char| c.is_whitespace() || c == '<' || c == '(');
+    words.find(|word| *word == keyword)?;
+    words.find(|word| !word.is_empty()).map(str::to_string)
+}
+
+/// `pub const X: T` of `pub const X: T = value`
+fn without_initializer(header: &str) -> String {
+    match header.find(" = ") {
+        Some(index) => header[..index].to_string(),
+        None => header.to_string(),
+    }
+}
+
+/// Fields or variants of a body, split at the commas outside brackets, normalized
+fn split_top_level(body: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut current = String::new();
+    let mut previous = ' ';
+    for c in body.chars() {
+        match c {
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            // `->` of a fn pointer type closes nothing
+            '>' if previous != '-' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(strip_attributes(&current));
+                current.clear();
+                previous = c;
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+        previous = c;
+    }
+    parts.push(strip_attributes(&current));
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+/// `code` without leading `#[..]` attributes, whitespace collapsed
+fn strip_attributes(code: &str) -> String {
+    let mut rest = code.trim_start();
+    while let Some(attribute) = rest.strip_prefix("#!").or_else(|| rest.strip_prefix('#')) {
+        let mut depth = 0;
+        let mut end = attribute.len();
+        for (index, c) in attribute.char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = index + 1;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        rest = attribute[end..].trim_start();
+    }
+    normalize(rest)
+}
+
+/// Whitespace collapsed to single spaces, none inside brackets or before a trailing comma
+fn normalize(code: &str) -> String {
+    let collapsed = code.split_whitespace().collect::<Vec<_>>().join(" ");
+    collapsed
+        .replace("( ", "(")
+        .replace(" )", ")")
+        .replace(",)", ")")
+        .replace("< ", "<")
+        .replace(" >", ">")
+        .replace(",>", ">")
+}
@@ -0,0 +1,139 @@
+//! Source scanning shared by the tests that read the crate's own code
+
+// Each test crate uses a part of the helpers
+#![allow(dead_code)]
+
+/// Index of the bracket closing the `{`, `(` or `[` at `open`
+pub fn matching_close(code: &str, open: usize) -> usize {
+    let (opening, closing) = match code[open..].chars().next() {
+        Some('(') => ('(', ')'),
+        Some('[') => ('[', ']'),
+        _ => ('{', '}'),
+    };
+    let mut depth = 0;
+    for (index, c) in code[open..].char_indices() {
+        if c == opening {
+            depth += 1;
+        } else if c == closing {
+            depth -= 1;
+            if depth == 0 {
+                return open + index;
+            }
+        }
+    }
+    code.len() - 1
+}
+
+/// `source` without comments and with string and char literals emptied, so braces and
+/// semicolons in them do not count
+pub fn strip_comments_and_literals(source: &str) -> String {
+    scan(source).0
+}
+
+/// A string or char literal of a source file
+pub struct Literal {
+    /// 1-based line the literal starts on
+    pub line: usize,
+    /// Text between the quotes, escapes left as written
+    pub text: String,
+    /// Byte offset of the emptied literal in the stripped code
+    pub offset: usize,
+}
+
+/// `source` as `strip_comments_and_literals` returns it and the literals taken out of it
+pub fn scan(source: &str) -> (String, Vec<Literal>) {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::with_capacity(source.len());
+    let mut literals = Vec::new();
+    let literal = |start: usize, end: usize, out: &str| Literal {
+        line: chars[..start].iter().filter(|c| **c == '\n').count() + 1,
+        text: chars[start..end].iter().collect(),
+        offset: out.len(),
+    };
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        if c == '/' && next == Some('/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            let mut depth = 0;
+            while i < chars.len() {
+                if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                    depth += 1;
+                    i += 2;
+                } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+        } else if c == 'r' && (next == Some('"') || next == Some('#')) && is_raw_string(&chars, i) {
+            let mut hashes = 0;
+            i += 1;
+            while chars[i] == '#' {
+                hashes += 1;
+                i += 1;
+            }
+            i += 1;
+            let start = i;
+            loop {
+                if i >= chars.len() {
+                    break;
+                }
+                if chars[i] == '"' && (0..hashes).all(|h| chars.get(i + 1 + h) == Some(&'#')) {
+                    literals.push(literal(start, i, &out));
+                    i += 1 + hashes;
+                    break;
+                }
+                i += 1;
+            }
+            out.push_str("\"\"");
+        } else if c == '"' {
+            i += 1;
+            let start = i;
+            while i < chars.len() && chars[i] != '"' {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            literals.push(literal(start, i.min(chars.len()), &out));
+            i += 1;
+            out.push_str("\"\"");
+        } else if c == '\'' && (next == Some('\\') || chars.get(i + 2) == Some(&'\'')) {
+            // A char literal, a lifetime has no closing quote
+            i += 1;
+            let start = i;
+            while i < chars.len() && chars[i] != '\'' {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            literals.push(literal(start, i.min(chars.len()), &out));
+            i += 1;
+            out.push_str("' '");
+        } else {
+            out.push(c);
+            i += 1;
+        }
+    }
+    (out, literals)
+}
+
+/// Whether the `r` at `i` starts a raw string rather than ending an identifier
+fn is_raw_string(chars: &[char], i: usize) -> bool {
+    let starts_token = i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '_');
+    let mut j = i + 1;
+    while chars.get(j) == Some(&'#') {
+        j += 1;
+    }
+    starts_token && chars.get(j) == Some(&'"')
+}